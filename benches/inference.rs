@@ -0,0 +1,127 @@
+//! Inference kernels
+//!
+//! - `inference/deep_narrow`: per-layer overhead dominates when layers are
+//!   only a few neurons wide, so this compares `Network::run` with the fused
+//!   kernels of `Network::finalize` on a 12-layer network of width 8
+//! - `matvec/*`: row-major `matvec` against the row-panel layout of
+//!   `PanelMatrix` for square layers of several widths, on whatever SIMD
+//!   extension the host has (AVX2 or NEON)
+//! - `inference/wide`: a finalized network of 8 layers of width 512 (8 MiB
+//!   of `f32` weights, more than most L2 caches) with and without
+//!   `MemoryPrefetcher`
+//! - `matmul/*`: square `matmul` on the built-in kernels against the
+//!   `matrixmultiply` crate, which sets `SimdConfig::gemm_threshold`; run
+//!   with `--features matrixmultiply`, otherwise both use the built-in
+//!   kernels
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use do_fann::memory_manager::MemoryPrefetcher;
+use do_fann::simd::{CpuSimdOps, PanelMatrix, SimdConfig, SimdMatrixOps};
+use do_fann::{ActivationFunction, NetworkBuilder};
+
+const DEPTH: usize = 12;
+const WIDTH: usize = 8;
+
+fn bench_inference(c: &mut Criterion) {
+    let mut builder = NetworkBuilder::<f32>::new().input_layer(WIDTH);
+    for layer in 0..DEPTH {
+        let activation = if layer % 2 == 0 {
+            ActivationFunction::Tanh
+        } else {
+            ActivationFunction::ReLU
+        };
+        builder = builder.hidden_layer_with_activation(WIDTH, activation, 1.0);
+    }
+    let mut network = builder.output_layer(1).build();
+    let finalized = network.finalize().unwrap();
+    let input = vec![0.25f32; WIDTH];
+
+    let mut group = c.benchmark_group("inference/deep_narrow");
+    group.bench_function("network", |b| {
+        b.iter(|| black_box(network.run(black_box(&input))))
+    });
+    group.bench_function("finalized", |b| {
+        b.iter(|| black_box(finalized.run(black_box(&input)).unwrap()))
+    });
+    group.finish();
+}
+
+fn bench_matvec_layout(c: &mut Criterion) {
+    let ops = CpuSimdOps::new_with_defaults();
+    for size in [32, 128, 512] {
+        let matrix: Vec<f32> = (0..size * size)
+            .map(|i| ((i * 31 % 97) as f32 - 48.0) / 97.0)
+            .collect();
+        let packed = PanelMatrix::pack(&matrix, size, size);
+        let x = vec![0.5f32; size];
+        let mut y = vec![0.0f32; size];
+
+        let mut group = c.benchmark_group(format!("matvec/{size}x{size}"));
+        group.bench_with_input(BenchmarkId::from_parameter("row_major"), &size, |b, &n| {
+            b.iter(|| ops.matvec(black_box(&matrix), black_box(&x), &mut y, n, n))
+        });
+        group.bench_with_input(BenchmarkId::from_parameter("panels"), &size, |b, _| {
+            b.iter(|| ops.matvec_panels(black_box(&packed), black_box(&x), &mut y))
+        });
+        group.finish();
+    }
+}
+
+fn bench_prefetch(c: &mut Criterion) {
+    let mut builder = NetworkBuilder::<f32>::new().input_layer(512);
+    for _ in 0..7 {
+        builder = builder.hidden_layer(512);
+    }
+    let finalized = builder.output_layer(512).build().finalize().unwrap();
+    let prefetcher = MemoryPrefetcher::default();
+    let input = vec![0.25f32; 512];
+
+    let mut group = c.benchmark_group("inference/wide");
+    group.bench_function("plain", |b| {
+        b.iter(|| black_box(finalized.run(black_box(&input)).unwrap()))
+    });
+    group.bench_function("prefetched", |b| {
+        b.iter(|| {
+            black_box(
+                finalized
+                    .run_prefetched(black_box(&input), &prefetcher)
+                    .unwrap(),
+            )
+        })
+    });
+    group.finish();
+}
+
+fn bench_matmul_backend(c: &mut Criterion) {
+    let with_threshold = |gemm_threshold| {
+        CpuSimdOps::new(SimdConfig {
+            gemm_threshold,
+            ..SimdConfig::default()
+        })
+    };
+    let builtin = with_threshold(usize::MAX);
+    let packed = with_threshold(0);
+    for size in [4, 8, 12, 16, 32, 64, 256] {
+        let a: Vec<f32> = (0..size * size)
+            .map(|i| ((i * 31 % 97) as f32 - 48.0) / 97.0)
+            .collect();
+        let mut out = vec![0.0f32; size * size];
+
+        let mut group = c.benchmark_group(format!("matmul/{size}x{size}"));
+        for (name, ops) in [("builtin", &builtin), ("matrixmultiply", &packed)] {
+            group.bench_with_input(BenchmarkId::from_parameter(name), &size, |b, &n| {
+                b.iter(|| ops.matmul(black_box(&a), black_box(&a), &mut out, n, n, n))
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(
+    benches,
+    bench_inference,
+    bench_matvec_layout,
+    bench_prefetch,
+    bench_matmul_backend
+);
+criterion_main!(benches);
@@ -0,0 +1,111 @@
+//! Optimizer comparison on small reference problems
+//!
+//! Times a fixed number of epochs for Adam and the newer optimizers on the
+//! XOR, sine regression and two-class ring datasets. The final training error
+//! of each run is printed once per benchmark so convergence can be compared
+//! alongside the timings.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use do_fann::training::{Adam, Lion, Nadam, RAdam, TrainingAlgorithm, TrainingData};
+use do_fann::{Network, NetworkBuilder};
+use num_traits::Float;
+
+#[allow(dead_code)]
+#[path = "../src/tests/fixtures.rs"]
+mod fixtures;
+
+use fixtures::xor;
+
+const EPOCHS: usize = 50;
+
+fn sine() -> TrainingData<f32> {
+    let xs: Vec<f32> = (0..64).map(|i| i as f32 / 64.0).collect();
+    TrainingData {
+        inputs: xs.iter().map(|&x| vec![x]).collect(),
+        outputs: xs
+            .iter()
+            .map(|&x| vec![0.5 + 0.4 * (x * std::f32::consts::TAU).sin()])
+            .collect(),
+    }
+}
+
+/// Points inside a ring of radius 0.5 around the centre are class 1
+fn ring() -> TrainingData<f32> {
+    let mut data = TrainingData {
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    };
+    for i in 0..12 {
+        for j in 0..12 {
+            let (x, y) = (i as f32 / 11.0, j as f32 / 11.0);
+            let radius = ((x - 0.5).powi(2) + (y - 0.5).powi(2)).sqrt();
+            let label = if (0.25..0.45).contains(&radius) {
+                1.0
+            } else {
+                0.0
+            };
+            data.inputs.push(vec![x, y]);
+            data.outputs.push(vec![label]);
+        }
+    }
+    data
+}
+
+fn network(inputs: usize) -> Network<f32> {
+    NetworkBuilder::<f32>::new()
+        .input_layer(inputs)
+        .hidden_layer(8)
+        .output_layer(1)
+        .build()
+}
+
+type MakeOptimizer = fn() -> Box<dyn TrainingAlgorithm<f32>>;
+
+fn optimizers() -> Vec<(&'static str, MakeOptimizer)> {
+    vec![
+        ("adam", || Box::new(Adam::new(0.05))),
+        ("nadam", || Box::new(Nadam::new(0.05))),
+        ("radam", || Box::new(RAdam::new(0.05))),
+        ("lion", || Box::new(Lion::new(0.005))),
+    ]
+}
+
+fn train(
+    optimizer: &mut dyn TrainingAlgorithm<f32>,
+    network: &mut Network<f32>,
+    data: &TrainingData<f32>,
+) -> f32 {
+    for _ in 0..EPOCHS {
+        optimizer.train_epoch(network, data).unwrap();
+    }
+    optimizer.calculate_error(network, data)
+}
+
+fn bench_optimizers(c: &mut Criterion) {
+    let datasets = [("xor", xor()), ("sine", sine()), ("ring", ring())];
+
+    for (name, data) in &datasets {
+        let mut group = c.benchmark_group(format!("optimizers/{name}"));
+        let inputs = data.inputs[0].len();
+        for (optimizer_name, make) in optimizers() {
+            let error = train(make().as_mut(), &mut network(inputs), data);
+            println!("{name}/{optimizer_name}: error after {EPOCHS} epochs = {error:.6}");
+
+            group.bench_with_input(
+                BenchmarkId::from_parameter(optimizer_name),
+                data,
+                |b, data| {
+                    b.iter(|| {
+                        let mut optimizer = make();
+                        let mut network = network(inputs);
+                        black_box(train(optimizer.as_mut(), &mut network, data))
+                    })
+                },
+            );
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_optimizers);
+criterion_main!(benches);
@@ -0,0 +1,28 @@
+//! Links the system BLAS for the `blas` feature
+//!
+//! `DO_FANN_BLAS_LIB` overrides the library (comma-separated, default
+//! `openblas`) and `DO_FANN_BLAS_LIB_DIR` adds a search directory. Nothing
+//! is linked when Accelerate already provides CBLAS on macOS or when
+//! `safe-only` disables the FFI.
+
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=DO_FANN_BLAS_LIB");
+    println!("cargo:rerun-if-env-changed=DO_FANN_BLAS_LIB_DIR");
+
+    let feature = |name: &str| env::var_os(format!("CARGO_FEATURE_{name}")).is_some();
+    let macos = env::var("CARGO_CFG_TARGET_OS").is_ok_and(|os| os == "macos");
+    if !feature("BLAS") || feature("SAFE_ONLY") || (macos && feature("ACCELERATE")) {
+        return;
+    }
+
+    if let Ok(dir) = env::var("DO_FANN_BLAS_LIB_DIR") {
+        println!("cargo:rustc-link-search=native={dir}");
+    }
+    let libs = env::var("DO_FANN_BLAS_LIB").unwrap_or_else(|_| "openblas".to_string());
+    for lib in libs.split(',').map(str::trim).filter(|lib| !lib.is_empty()) {
+        println!("cargo:rustc-link-lib={lib}");
+    }
+}
@@ -1,3 +1,5 @@
+use crate::numeric::cast;
+use num_traits::Float;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -6,6 +8,11 @@ use serde::{Deserialize, Serialize};
 /// These functions are based on the FANN library's activation functions
 /// and include both common neural network activation functions and
 /// some specialized variants.
+///
+/// This is the only activation enum in the crate: neurons, finalized
+/// networks and the SIMD kernels all evaluate it through
+/// [`ActivationFunction::activate`] and [`ActivationFunction::derivative`],
+/// so a new activation is defined once, in this file.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Default)]
@@ -80,6 +87,15 @@ pub enum ActivationFunction {
     /// Symmetric cosine: f(x) = cos(x * steepness)
     /// Output range: [-1, 1]
     CosSymmetric,
+
+    /// Gaussian Error Linear Unit, tanh approximation:
+    /// f(x) = 0.5 * x * (1 + tanh(sqrt(2/π) * (x + 0.044715 * x³)))
+    /// Output range: (-0.17, ∞)
+    Gelu,
+
+    /// Swish (SiLU): f(x) = x / (1 + exp(-x))
+    /// Output range: (-0.28, ∞)
+    Swish,
 }
 
 impl ActivationFunction {
@@ -104,6 +120,8 @@ impl ActivationFunction {
             ActivationFunction::Cos => "Cos",
             ActivationFunction::SinSymmetric => "SinSymmetric",
             ActivationFunction::CosSymmetric => "CosSymmetric",
+            ActivationFunction::Gelu => "Gelu",
+            ActivationFunction::Swish => "Swish",
         }
     }
 
@@ -134,8 +152,140 @@ impl ActivationFunction {
             ActivationFunction::ReLULeaky => ("-inf", "inf"),
             ActivationFunction::Sin | ActivationFunction::Cos => ("0", "1"),
             ActivationFunction::SinSymmetric | ActivationFunction::CosSymmetric => ("-1", "1"),
+            ActivationFunction::Gelu => ("-0.17", "inf"),
+            ActivationFunction::Swish => ("-0.28", "inf"),
         }
     }
+
+    /// Whether a neuron multiplies its input by the steepness before
+    /// activating; the rectifiers and their smooth variants ignore it
+    pub(crate) fn uses_steepness(&self) -> bool {
+        !matches!(
+            self,
+            ActivationFunction::ReLU
+                | ActivationFunction::ReLULeaky
+                | ActivationFunction::Gelu
+                | ActivationFunction::Swish
+        )
+    }
+
+    /// Value of the function at `x`, with any steepness already applied
+    pub fn activate<T: Float>(&self, x: T) -> T {
+        let (zero, one, half) = (T::zero(), T::one(), cast::<T>(0.5));
+        match self {
+            ActivationFunction::Linear => x,
+            ActivationFunction::Threshold => {
+                if x < zero {
+                    zero
+                } else {
+                    one
+                }
+            }
+            ActivationFunction::ThresholdSymmetric => {
+                if x < zero {
+                    -one
+                } else {
+                    one
+                }
+            }
+            ActivationFunction::Sigmoid => one / (one + (-x).exp()),
+            ActivationFunction::SigmoidSymmetric | ActivationFunction::Tanh => x.tanh(),
+            ActivationFunction::Gaussian => (-x * x).exp(),
+            ActivationFunction::GaussianSymmetric => (-x * x).exp() * cast(2.0) - one,
+            ActivationFunction::Elliot => x * half / (one + x.abs()) + half,
+            ActivationFunction::ElliotSymmetric => x / (one + x.abs()),
+            ActivationFunction::LinearPiece => x.max(zero).min(one),
+            ActivationFunction::LinearPieceSymmetric => x.max(-one).min(one),
+            ActivationFunction::ReLU => x.max(zero),
+            ActivationFunction::ReLULeaky => {
+                if x > zero {
+                    x
+                } else {
+                    x * leaky_slope()
+                }
+            }
+            ActivationFunction::Sin => x.sin() * half + half,
+            ActivationFunction::Cos => x.cos() * half + half,
+            ActivationFunction::SinSymmetric => x.sin(),
+            ActivationFunction::CosSymmetric => x.cos(),
+            ActivationFunction::Gelu => x * half * (one + gelu_inner(x).tanh()),
+            ActivationFunction::Swish => x / (one + (-x).exp()),
+        }
+    }
+
+    /// Derivative at input `x`, given the output `y = self.activate(x)`
+    ///
+    /// Both are taken with the steepness already applied; multiply by the
+    /// steepness for the derivative with respect to the unscaled input.
+    /// The thresholds have a zero derivative, hence [`Self::is_trainable`].
+    pub fn derivative<T: Float>(&self, x: T, y: T) -> T {
+        let (zero, one, half) = (T::zero(), T::one(), cast::<T>(0.5));
+        match self {
+            ActivationFunction::Linear => one,
+            ActivationFunction::Threshold | ActivationFunction::ThresholdSymmetric => zero,
+            ActivationFunction::Sigmoid => y * (one - y),
+            ActivationFunction::SigmoidSymmetric | ActivationFunction::Tanh => one - y * y,
+            ActivationFunction::Gaussian => cast::<T>(-2.0) * x * y,
+            ActivationFunction::GaussianSymmetric => cast::<T>(-2.0) * x * (y + one),
+            ActivationFunction::Elliot => half / ((one + x.abs()) * (one + x.abs())),
+            ActivationFunction::ElliotSymmetric => one / ((one + x.abs()) * (one + x.abs())),
+            ActivationFunction::LinearPiece => {
+                if x > zero && x < one {
+                    one
+                } else {
+                    zero
+                }
+            }
+            ActivationFunction::LinearPieceSymmetric => {
+                if x > -one && x < one {
+                    one
+                } else {
+                    zero
+                }
+            }
+            ActivationFunction::ReLU => {
+                if x > zero {
+                    one
+                } else {
+                    zero
+                }
+            }
+            ActivationFunction::ReLULeaky => {
+                if x > zero {
+                    one
+                } else {
+                    leaky_slope()
+                }
+            }
+            ActivationFunction::Sin => x.cos() * half,
+            ActivationFunction::Cos => -x.sin() * half,
+            ActivationFunction::SinSymmetric => x.cos(),
+            ActivationFunction::CosSymmetric => -x.sin(),
+            ActivationFunction::Gelu => {
+                let tanh = gelu_inner(x).tanh();
+                let inner_derivative =
+                    cast::<T>(SQRT_2_OVER_PI) * (one + cast::<T>(3.0 * GELU_CUBIC) * x * x);
+                half * (one + tanh) + half * x * (one - tanh * tanh) * inner_derivative
+            }
+            ActivationFunction::Swish => {
+                let sigmoid = one / (one + (-x).exp());
+                sigmoid * (one + x * (one - sigmoid))
+            }
+        }
+    }
+}
+
+/// Negative-side slope of [`ActivationFunction::ReLULeaky`]
+fn leaky_slope<T: Float>() -> T {
+    cast(0.01)
+}
+
+const SQRT_2_OVER_PI: f64 = 0.797_884_560_802_865_4;
+const GELU_CUBIC: f64 = 0.044_715;
+
+/// Argument of the tanh in the GELU approximation
+fn gelu_inner<T: Float>(x: T) -> T {
+    cast::<T>(SQRT_2_OVER_PI) * (x + cast::<T>(GELU_CUBIC) * x * x * x)
 }
 
 #[cfg(test)]
@@ -164,4 +314,42 @@ mod tests {
         assert_eq!(ActivationFunction::ReLU.output_range(), ("0", "inf"));
         assert_eq!(ActivationFunction::Linear.output_range(), ("-inf", "inf"));
     }
+
+    #[test]
+    fn test_derivatives_match_finite_differences() {
+        use ActivationFunction::*;
+        let all = [
+            Linear,
+            Sigmoid,
+            SigmoidSymmetric,
+            Tanh,
+            Gaussian,
+            GaussianSymmetric,
+            Elliot,
+            ElliotSymmetric,
+            LinearPiece,
+            LinearPieceSymmetric,
+            ReLU,
+            ReLULeaky,
+            Sin,
+            Cos,
+            SinSymmetric,
+            CosSymmetric,
+            Gelu,
+            Swish,
+        ];
+        let h = 1e-6;
+        for activation in all {
+            for x in [-2.3, -0.6, 0.4, 0.7, 1.9] {
+                let numeric = (activation.activate(x + h) - activation.activate(x - h)) / (2.0 * h);
+                let analytic = activation.derivative(x, activation.activate(x));
+                assert!(
+                    (numeric - analytic).abs() < 1e-6,
+                    "{activation:?} at {x}: {analytic} vs {numeric}"
+                );
+            }
+        }
+        assert_eq!(Threshold.activate(0.0), 1.0);
+        assert_eq!(ThresholdSymmetric.derivative(-1.0, -1.0), 0.0);
+    }
 }
@@ -0,0 +1,304 @@
+//! Active-learning query utilities
+//!
+//! When labels are expensive, a model trained on a small labeled set can pick
+//! the unlabeled samples it is least sure about for labeling next. This
+//! module scores a pool of unlabeled inputs by uncertainty and returns the
+//! indices of the most informative ones:
+//!
+//! - [`QueryStrategy::Entropy`]: entropy of the outputs read as a probability
+//!   distribution (a single output is read as a Bernoulli probability)
+//! - [`QueryStrategy::Margin`]: one minus the gap between the two most likely
+//!   classes
+//! - [`QueryStrategy::McDropout`]: variance of the outputs over several
+//!   forward passes with random dropout of hidden neurons (Gal & Ghahramani,
+//!   2016), which also works for regression networks
+
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use thiserror::Error;
+
+/// Errors raised while scoring a query pool
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ActiveLearningError {
+    #[error("Sample {index} has {actual} inputs, network expects {expected}")]
+    InputSizeMismatch {
+        index: usize,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("Dropout rate must be in [0, 1), got {0}")]
+    InvalidDropoutRate(f64),
+
+    #[error("MC dropout needs at least two forward passes")]
+    TooFewPasses,
+}
+
+/// Monte Carlo dropout settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct McDropout {
+    /// Probability of dropping each hidden neuron in a pass
+    pub rate: f64,
+    /// Number of stochastic forward passes
+    pub passes: usize,
+    /// Seed of the dropout masks
+    pub seed: u64,
+}
+
+impl Default for McDropout {
+    fn default() -> Self {
+        Self {
+            rate: 0.5,
+            passes: 20,
+            seed: 0,
+        }
+    }
+}
+
+impl McDropout {
+    pub fn new(rate: f64, passes: usize) -> Self {
+        Self {
+            rate,
+            passes,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Copies of `network` with independent inverted-dropout masks applied
+    /// to the outgoing connections of its hidden neurons
+    fn masked_networks<T: Float>(
+        &self,
+        network: &Network<T>,
+    ) -> Result<Vec<Network<T>>, ActiveLearningError> {
+        if !(0.0..1.0).contains(&self.rate) {
+            return Err(ActiveLearningError::InvalidDropoutRate(self.rate));
+        }
+        if self.passes < 2 {
+            return Err(ActiveLearningError::TooFewPasses);
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let keep_scale = cast(1.0 / (1.0 - self.rate));
+        let hidden = 1..network.layers.len().saturating_sub(1);
+        Ok((0..self.passes)
+            .map(|_| {
+                let mut masked = network.clone();
+                for layer_idx in hidden.clone() {
+                    let dropped: Vec<bool> = masked.layers[layer_idx]
+                        .neurons
+                        .iter()
+                        .map(|n| !n.is_bias && rng.gen_bool(self.rate))
+                        .collect();
+                    let is_bias: Vec<bool> = masked.layers[layer_idx]
+                        .neurons
+                        .iter()
+                        .map(|n| n.is_bias)
+                        .collect();
+                    for neuron in &mut masked.layers[layer_idx + 1].neurons {
+                        for connection in &mut neuron.connections {
+                            let source = connection.from_neuron;
+                            if dropped.get(source).copied().unwrap_or(false) {
+                                connection.weight = T::zero();
+                            } else if !is_bias.get(source).copied().unwrap_or(true) {
+                                connection.weight = connection.weight * keep_scale;
+                            }
+                        }
+                    }
+                }
+                masked
+            })
+            .collect())
+    }
+}
+
+/// How to score the uncertainty of a prediction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueryStrategy {
+    Entropy,
+    Margin,
+    McDropout(McDropout),
+}
+
+/// Outputs read as a probability distribution; a single output is the
+/// probability of the positive class
+fn probabilities<T: Float>(outputs: &[T]) -> Vec<T> {
+    if let [p] = outputs {
+        let p = p.max(T::zero()).min(T::one());
+        return vec![p, T::one() - p];
+    }
+    let clipped: Vec<T> = outputs.iter().map(|&o| o.max(T::zero())).collect();
+    let sum = clipped.iter().fold(T::zero(), |acc, &o| acc + o);
+    if sum > T::zero() {
+        clipped.iter().map(|&o| o / sum).collect()
+    } else {
+        let uniform = T::one() / cast(outputs.len().max(1));
+        vec![uniform; outputs.len()]
+    }
+}
+
+/// Shannon entropy (in nats) of the outputs read as a distribution
+pub fn entropy<T: Float>(outputs: &[T]) -> T {
+    probabilities(outputs)
+        .into_iter()
+        .filter(|&p| p > T::zero())
+        .fold(T::zero(), |acc, p| acc - p * p.ln())
+}
+
+/// `1 - (p_first - p_second)` for the two most likely classes; 1 means the
+/// top two classes are tied
+pub fn margin<T: Float>(outputs: &[T]) -> T {
+    let mut p = probabilities(outputs);
+    p.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    match p.as_slice() {
+        [first, second, ..] => T::one() - (*first - *second),
+        _ => T::zero(),
+    }
+}
+
+/// Uncertainty score of every sample in `pool`; higher is more informative
+pub fn uncertainty_scores<T: Float>(
+    network: &Network<T>,
+    pool: &[Vec<T>],
+    strategy: QueryStrategy,
+) -> Result<Vec<T>, ActiveLearningError> {
+    let expected = network.num_inputs();
+    if let Some((index, input)) = pool.iter().enumerate().find(|(_, i)| i.len() != expected) {
+        return Err(ActiveLearningError::InputSizeMismatch {
+            index,
+            expected,
+            actual: input.len(),
+        });
+    }
+
+    let mut network = network.clone();
+    match strategy {
+        QueryStrategy::Entropy => Ok(pool.iter().map(|x| entropy(&network.run(x))).collect()),
+        QueryStrategy::Margin => Ok(pool.iter().map(|x| margin(&network.run(x))).collect()),
+        QueryStrategy::McDropout(settings) => {
+            let mut passes = settings.masked_networks(&network)?;
+            let count = cast(passes.len());
+            Ok(pool
+                .iter()
+                .map(|input| {
+                    let outputs: Vec<Vec<T>> = passes.iter_mut().map(|n| n.run(input)).collect();
+                    let width = outputs[0].len();
+                    let variance = (0..width).fold(T::zero(), |acc, j| {
+                        let mean = outputs.iter().fold(T::zero(), |s, o| s + o[j]) / count;
+                        acc + outputs
+                            .iter()
+                            .fold(T::zero(), |s, o| s + (o[j] - mean) * (o[j] - mean))
+                            / count
+                    });
+                    variance / cast(width.max(1))
+                })
+                .collect())
+        }
+    }
+}
+
+/// Indices of the `count` most uncertain samples in `pool`, most uncertain
+/// first; ties keep pool order and NaN scores are never preferred
+pub fn select_uncertain<T: Float>(
+    network: &Network<T>,
+    pool: &[Vec<T>],
+    strategy: QueryStrategy,
+    count: usize,
+) -> Result<Vec<usize>, ActiveLearningError> {
+    let scores = uncertainty_scores(network, pool, strategy)?;
+    let key = |i: usize| {
+        scores[i]
+            .to_f64()
+            .filter(|s| !s.is_nan())
+            .unwrap_or(f64::MIN)
+    };
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| key(b).total_cmp(&key(a)));
+    order.truncate(count);
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_entropy_and_margin() {
+        let ln2 = std::f64::consts::LN_2;
+        assert!((entropy(&[0.5]) - ln2).abs() < 1e-12);
+        assert_eq!(entropy(&[1.0]), 0.0);
+        assert!((entropy(&[0.2, 0.2, 0.2, 0.2]) - 4f64.ln()).abs() < 1e-12);
+        assert!(entropy(&[0.9, 0.05, 0.05]) < entropy(&[0.4, 0.3, 0.3]));
+
+        assert_eq!(margin(&[0.5]), 1.0);
+        assert!((margin(&[0.7, 0.2, 0.1]) - 0.5).abs() < 1e-12);
+        assert!(margin(&[0.45, 0.44, 0.11]) > margin(&[0.7, 0.2, 0.1]));
+    }
+
+    #[test]
+    fn test_selects_samples_near_the_decision_boundary() {
+        // A single sigmoid neuron on y = x, so inputs near 0 are the most uncertain
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .output_layer(1)
+            .build();
+        let inputs = network.layers[0].neurons.clone();
+        for connection in &mut network.layers[1].neurons[0].connections {
+            let from_bias = inputs[connection.from_neuron].is_bias;
+            connection.weight = if from_bias { 0.0 } else { 4.0 };
+        }
+        let pool: Vec<Vec<f64>> = [-3.0, 2.0, 0.1, -0.5, 5.0]
+            .iter()
+            .map(|&x| vec![x])
+            .collect();
+
+        for strategy in [QueryStrategy::Entropy, QueryStrategy::Margin] {
+            let selected = select_uncertain(&network, &pool, strategy, 2).unwrap();
+            assert_eq!(selected, vec![2, 3]);
+        }
+        assert!(matches!(
+            select_uncertain(&network, &[vec![1.0, 2.0]], QueryStrategy::Entropy, 1),
+            Err(ActiveLearningError::InputSizeMismatch { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_mc_dropout_variance() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(16)
+            .output_layer(1)
+            .build();
+        let pool = vec![vec![0.0, 0.0], vec![2.0, -1.0], vec![0.5, 0.5]];
+
+        let settings = McDropout::new(0.5, 30).with_seed(7);
+        let scores = uncertainty_scores(&network, &pool, QueryStrategy::McDropout(settings));
+        let scores = scores.unwrap();
+        assert!(scores.iter().all(|&s| s > 0.0 && s.is_finite()));
+        // Same seed, same masks
+        let again = uncertainty_scores(&network, &pool, QueryStrategy::McDropout(settings));
+        assert_eq!(scores, again.unwrap());
+
+        // Without dropout every pass is identical
+        let none = McDropout::new(0.0, 5);
+        let scores = uncertainty_scores(&network, &pool, QueryStrategy::McDropout(none)).unwrap();
+        assert!(scores.iter().all(|&s| s.abs() < 1e-20));
+
+        assert_eq!(
+            uncertainty_scores(
+                &network,
+                &pool,
+                QueryStrategy::McDropout(McDropout::new(1.0, 5))
+            ),
+            Err(ActiveLearningError::InvalidDropoutRate(1.0))
+        );
+    }
+}
@@ -0,0 +1,349 @@
+//! Hidden-layer architecture search
+//!
+//! [`ArchitectureSearch`] sweeps hidden-layer counts and widths and spends
+//! its training budget by successive halving: every candidate trains for a
+//! few epochs, the better half (or third, see
+//! [`with_reduction_factor`](ArchitectureSearch::with_reduction_factor))
+//! continues with proportionally more epochs, and so on until one remains.
+//! Weak architectures are dropped cheaply while promising ones get the
+//! budget.
+//!
+//! The result lists every candidate with the error it reached and its
+//! parameter count, plus the Pareto front of the two: the candidates no
+//! smaller network beats on error. A [`CascadeTrainingResult`] can seed the
+//! space with widths around the hidden neuron count cascade correlation
+//! settled on.
+
+use crate::cascade::CascadeTrainingResult;
+use crate::numeric::cast;
+use crate::training::{TrainingAlgorithm, TrainingData, TrainingError};
+use crate::{Network, NetworkBuilder};
+use num_traits::Float;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Ordering;
+
+/// Hidden layer widths of a candidate, input side first
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Architecture {
+    pub hidden_layers: Vec<usize>,
+}
+
+impl Architecture {
+    pub fn new(hidden_layers: Vec<usize>) -> Self {
+        Self { hidden_layers }
+    }
+
+    /// Build the network for `inputs` and `outputs` with this hidden
+    /// structure
+    pub fn build<T: Float>(&self, inputs: usize, outputs: usize) -> Network<T> {
+        let builder = self.hidden_layers.iter().fold(
+            NetworkBuilder::new().input_layer(inputs),
+            |builder, &width| builder.hidden_layer(width),
+        );
+        builder.output_layer(outputs).build()
+    }
+}
+
+/// Candidate architectures for [`ArchitectureSearch`]
+///
+/// Every combination of depth and width gives one candidate with `depth`
+/// hidden layers of `width` neurons; explicit architectures are added as
+/// they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchSpace {
+    pub depths: Vec<usize>,
+    pub widths: Vec<usize>,
+    pub extra: Vec<Architecture>,
+}
+
+impl SearchSpace {
+    pub fn new(depths: Vec<usize>, widths: Vec<usize>) -> Self {
+        Self {
+            depths,
+            widths,
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn with_architecture(mut self, architecture: Architecture) -> Self {
+        self.extra.push(architecture);
+        self
+    }
+
+    /// Add half, the same and double the number of hidden neurons cascade
+    /// correlation installed as widths
+    pub fn seeded_by_cascade<T: Float>(mut self, result: &CascadeTrainingResult<T>) -> Self {
+        let hidden = result.hidden_neurons_added.max(1);
+        self.widths
+            .extend([(hidden / 2).max(1), hidden, hidden * 2]);
+        self
+    }
+
+    /// The distinct candidates, in sweep order
+    pub fn candidates(&self) -> Vec<Architecture> {
+        let mut candidates: Vec<Architecture> = Vec::new();
+        let swept = self.depths.iter().flat_map(|&depth| {
+            self.widths
+                .iter()
+                .filter(|&&width| width > 0)
+                .map(move |&width| Architecture::new(vec![width; depth]))
+        });
+        for architecture in swept.chain(self.extra.iter().cloned()) {
+            if !candidates.contains(&architecture) {
+                candidates.push(architecture);
+            }
+        }
+        candidates
+    }
+}
+
+/// A trained candidate of an [`ArchitectureSearch`]
+#[derive(Debug, Clone)]
+pub struct CandidateResult<T: Float> {
+    pub architecture: Architecture,
+    /// Weights and biases of the network
+    pub parameters: usize,
+    /// Error on the evaluation data after the candidate's last round
+    pub error: T,
+    pub epochs_trained: usize,
+    /// Successive halving rounds the candidate took part in
+    pub rounds: usize,
+    pub network: Network<T>,
+}
+
+/// Outcome of [`ArchitectureSearch::run`]
+#[derive(Debug, Clone)]
+pub struct SearchResult<T: Float> {
+    /// Every candidate, in sweep order
+    pub candidates: Vec<CandidateResult<T>>,
+    /// Indices into `candidates` of the error/parameter Pareto front,
+    /// smallest network first
+    pub pareto_front: Vec<usize>,
+}
+
+impl<T: Float> SearchResult<T> {
+    /// Candidate with the lowest error
+    pub fn best(&self) -> Option<&CandidateResult<T>> {
+        self.pareto_front
+            .last()
+            .map(|&index| &self.candidates[index])
+    }
+
+    pub fn front(&self) -> impl Iterator<Item = &CandidateResult<T>> {
+        self.pareto_front
+            .iter()
+            .map(|&index| &self.candidates[index])
+    }
+}
+
+/// Creates a fresh training algorithm for each candidate
+pub type AlgorithmFactory<T> = Box<dyn Fn() -> Box<dyn TrainingAlgorithm<T>>>;
+
+/// Successive-halving sweep over hidden-layer architectures
+pub struct ArchitectureSearch<T: Float> {
+    space: SearchSpace,
+    algorithm: AlgorithmFactory<T>,
+    initial_epochs: usize,
+    reduction_factor: usize,
+    seed: u64,
+}
+
+impl<T: Float> ArchitectureSearch<T> {
+    pub fn new(space: SearchSpace, algorithm: AlgorithmFactory<T>) -> Self {
+        Self {
+            space,
+            algorithm,
+            initial_epochs: 10,
+            reduction_factor: 2,
+            seed: 0,
+        }
+    }
+
+    /// Epochs every candidate trains in the first round (default 10)
+    pub fn with_initial_epochs(mut self, epochs: usize) -> Self {
+        self.initial_epochs = epochs.max(1);
+        self
+    }
+
+    /// Keep `1 / factor` of the candidates each round and train survivors
+    /// `factor` times longer (default 2, at least 2)
+    pub fn with_reduction_factor(mut self, factor: usize) -> Self {
+        self.reduction_factor = factor.max(2);
+        self
+    }
+
+    /// Seed of the initial weights; candidate `i` uses `seed + i`
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Run the search, ranking candidates by their error on `validation`,
+    /// or on `train` when no validation set is given
+    pub fn run(
+        &self,
+        train: &TrainingData<T>,
+        validation: Option<&TrainingData<T>>,
+    ) -> Result<SearchResult<T>, TrainingError> {
+        let (Some(input), Some(output)) = (train.inputs.first(), train.outputs.first()) else {
+            return Err(TrainingError::InvalidData(
+                "architecture search needs at least one sample".to_string(),
+            ));
+        };
+        let (num_inputs, num_outputs) = (input.len(), output.len());
+        let evaluation = validation.unwrap_or(train);
+
+        let mut candidates = Vec::new();
+        let mut algorithms = Vec::new();
+        for (i, architecture) in self.space.candidates().into_iter().enumerate() {
+            let mut network = architecture.build::<T>(num_inputs, num_outputs);
+            train.check_shapes(&network)?;
+            evaluation.check_shapes(&network)?;
+            let mut rng = StdRng::seed_from_u64(self.seed.wrapping_add(i as u64));
+            let weights: Vec<T> = (0..network.total_connections())
+                .map(|_| cast(rng.gen_range(-0.1..0.1)))
+                .collect();
+            network
+                .set_weights(&weights)
+                .map_err(|e| TrainingError::TrainingFailed(e.to_string()))?;
+            candidates.push(CandidateResult {
+                parameters: network.total_connections(),
+                architecture,
+                error: T::infinity(),
+                epochs_trained: 0,
+                rounds: 0,
+                network,
+            });
+            algorithms.push((self.algorithm)());
+        }
+
+        let mut alive: Vec<usize> = (0..candidates.len()).collect();
+        let mut epochs = self.initial_epochs;
+        while !alive.is_empty() {
+            for &index in &alive {
+                let candidate = &mut candidates[index];
+                let algorithm = &mut algorithms[index];
+                for _ in 0..epochs {
+                    algorithm.train_epoch(&mut candidate.network, train)?;
+                }
+                candidate.error = algorithm.calculate_error(&candidate.network, evaluation);
+                candidate.epochs_trained += epochs;
+                candidate.rounds += 1;
+            }
+            if alive.len() == 1 {
+                break;
+            }
+            alive.sort_by(|&a, &b| compare_errors(candidates[a].error, candidates[b].error));
+            alive.truncate(alive.len().div_ceil(self.reduction_factor));
+            epochs = epochs.saturating_mul(self.reduction_factor);
+        }
+
+        let pareto_front = pareto_front(&candidates);
+        Ok(SearchResult {
+            candidates,
+            pareto_front,
+        })
+    }
+}
+
+/// Lower error first; NaN ranks last
+fn compare_errors<T: Float>(a: T, b: T) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (false, false) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (a_nan, b_nan) => a_nan.cmp(&b_nan),
+    }
+}
+
+/// Indices of the candidates no other candidate matches or beats on both
+/// error and parameter count, by increasing parameter count
+fn pareto_front<T: Float>(candidates: &[CandidateResult<T>]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..candidates.len())
+        .filter(|&i| !candidates[i].error.is_nan())
+        .collect();
+    order.sort_by(|&a, &b| {
+        candidates[a]
+            .parameters
+            .cmp(&candidates[b].parameters)
+            .then_with(|| compare_errors(candidates[a].error, candidates[b].error))
+    });
+    let mut front = Vec::new();
+    let mut best = T::infinity();
+    for index in order {
+        if candidates[index].error < best || front.is_empty() {
+            best = candidates[index].error;
+            front.push(index);
+        }
+    }
+    front
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::xor;
+    use crate::training::IncrementalBackprop;
+
+    #[test]
+    fn test_successive_halving_and_pareto_front() {
+        let space = SearchSpace::new(vec![1, 2], vec![2, 4, 8])
+            .with_architecture(Architecture::new(vec![4]));
+        assert_eq!(space.candidates().len(), 6);
+
+        let search =
+            ArchitectureSearch::new(space, Box::new(|| Box::new(IncrementalBackprop::new(0.7))))
+                .with_initial_epochs(5)
+                .with_seed(7);
+        let result = search.run(&xor(), None).unwrap();
+        assert_eq!(result.candidates.len(), 6);
+
+        // 6 -> 3 -> 2 -> 1 candidates, with 5, 10, 20 and 40 epochs
+        let mut rounds: Vec<usize> = result.candidates.iter().map(|c| c.rounds).collect();
+        rounds.sort_unstable();
+        assert_eq!(rounds, vec![1, 1, 1, 2, 3, 4]);
+        let winner = result.candidates.iter().find(|c| c.rounds == 4).unwrap();
+        assert_eq!(winner.epochs_trained, 75);
+
+        // The front gets strictly better as networks grow, and nothing
+        // off the front beats a front member of the same size or smaller
+        let front: Vec<&CandidateResult<f32>> = result.front().collect();
+        assert!(!front.is_empty());
+        for pair in front.windows(2) {
+            assert!(pair[0].parameters <= pair[1].parameters);
+            assert!(pair[1].error < pair[0].error);
+        }
+        for candidate in &result.candidates {
+            assert!(front
+                .iter()
+                .any(|member| member.parameters <= candidate.parameters
+                    && member.error <= candidate.error));
+        }
+        let best = result.best().unwrap();
+        assert!(result.candidates.iter().all(|c| best.error <= c.error));
+
+        // Reruns with the same seed are identical
+        let again = search.run(&xor(), None).unwrap();
+        assert_eq!(again.pareto_front, result.pareto_front);
+    }
+
+    #[test]
+    fn test_rejects_mismatched_data() {
+        let search = ArchitectureSearch::<f32>::new(
+            SearchSpace::new(vec![1], vec![2]),
+            Box::new(|| Box::new(IncrementalBackprop::new(0.7))),
+        );
+        let empty = TrainingData {
+            inputs: vec![],
+            outputs: vec![],
+        };
+        assert!(search.run(&empty, None).is_err());
+        let validation = TrainingData {
+            inputs: vec![vec![0.0]],
+            outputs: vec![vec![0.0]],
+        };
+        assert!(matches!(
+            search.run(&xor(), Some(&validation)),
+            Err(TrainingError::InvalidData(_))
+        ));
+    }
+}
@@ -0,0 +1,497 @@
+//! Audio feature extraction
+//!
+//! Turns WAV recordings into fixed-size feature vectors for keyword spotting
+//! and signal classification:
+//!
+//! 1. [`WavAudio::decode`] reads 8/16/24/32-bit PCM or 32-bit float WAV data
+//!    and mixes it down to mono samples in `[-1, 1]`
+//! 2. the signal is cut into overlapping Hann-windowed frames and each frame's
+//!    FFT magnitude is taken ([`Features::Spectrogram`])
+//! 3. optionally the power spectrum is pooled into mel bands, log-compressed
+//!    and decorrelated with a DCT ([`Features::Mfcc`])
+//!
+//! [`AudioFeatureExtractor::clip_features`] pads or truncates every clip to a
+//! fixed number of frames so clips of different lengths fit one network.
+
+use crate::numeric::cast;
+use crate::training::TrainingData;
+use num_traits::Float;
+use std::f32::consts::PI;
+use std::path::Path;
+use thiserror::Error;
+
+/// Errors raised while decoding audio or extracting features
+#[derive(Error, Debug)]
+pub enum AudioError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid WAV data: {0}")]
+    InvalidWav(String),
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(String),
+
+    #[error("{clips} clips but {targets} targets")]
+    TargetCountMismatch { clips: usize, targets: usize },
+}
+
+/// Mono audio decoded from a WAV file
+#[derive(Debug, Clone, PartialEq)]
+pub struct WavAudio {
+    pub sample_rate: u32,
+    /// Samples in `[-1, 1]`, channels averaged
+    pub samples: Vec<f32>,
+}
+
+impl WavAudio {
+    /// Read and decode the WAV file at `path`
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, AudioError> {
+        Self::decode(&std::fs::read(path)?)
+    }
+
+    /// Decode an in-memory WAV file
+    pub fn decode(bytes: &[u8]) -> Result<Self, AudioError> {
+        let invalid = |msg: &str| AudioError::InvalidWav(msg.to_string());
+        if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(invalid("missing RIFF/WAVE header"));
+        }
+
+        let mut format = None;
+        let mut pos = 12;
+        while pos + 8 <= bytes.len() {
+            let id = &bytes[pos..pos + 4];
+            let len = u32::from_le_bytes([
+                bytes[pos + 4],
+                bytes[pos + 5],
+                bytes[pos + 6],
+                bytes[pos + 7],
+            ]) as usize;
+            // A length near `u32::MAX` can overflow `usize` on 32-bit targets
+            let end = (pos + 8).checked_add(len);
+            let body = end
+                .and_then(|end| bytes.get(pos + 8..end))
+                .or_else(|| (id == b"data").then(|| &bytes[pos + 8..]))
+                .ok_or_else(|| invalid("chunk runs past the end of the file"))?;
+            match id {
+                b"fmt " => {
+                    if body.len() < 16 {
+                        return Err(invalid("fmt chunk is too short"));
+                    }
+                    let u16_at = |i: usize| u16::from_le_bytes([body[i], body[i + 1]]);
+                    let mut tag = u16_at(0);
+                    if tag == 0xFFFE && body.len() >= 26 {
+                        // WAVE_FORMAT_EXTENSIBLE keeps the real tag in its sub-format GUID
+                        tag = u16_at(24);
+                    }
+                    let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                    format = Some((tag, u16_at(2) as usize, sample_rate, u16_at(14)));
+                }
+                b"data" => {
+                    let (tag, channels, sample_rate, bits) =
+                        format.ok_or_else(|| invalid("data chunk before fmt chunk"))?;
+                    if channels == 0 {
+                        return Err(invalid("zero channels"));
+                    }
+                    let samples = decode_samples(body, tag, bits)?;
+                    let samples = samples
+                        .chunks_exact(channels)
+                        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+                        .collect();
+                    return Ok(Self {
+                        sample_rate,
+                        samples,
+                    });
+                }
+                _ => {}
+            }
+            // Chunks are padded to an even length; `end` is in bounds here
+            // because only a data chunk may run past the end of the file
+            pos = end.map_or(bytes.len(), |end| end + (len & 1));
+        }
+        Err(invalid("no data chunk"))
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        self.samples.len() as f32 / self.sample_rate.max(1) as f32
+    }
+}
+
+fn decode_samples(data: &[u8], tag: u16, bits: u16) -> Result<Vec<f32>, AudioError> {
+    let samples = match (tag, bits) {
+        (1, 8) => data.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+        (1, 16) => data
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        (1, 24) => data
+            .chunks_exact(3)
+            .map(|b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0)
+            .collect(),
+        (1, 32) => data
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0)
+            .collect(),
+        (3, 32) => data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+        _ => {
+            return Err(AudioError::InvalidWav(format!(
+                "unsupported encoding (format {tag}, {bits} bits)"
+            )))
+        }
+    };
+    Ok(samples)
+}
+
+/// Which features to compute per frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Features {
+    /// FFT magnitudes of bins `0..=n_fft / 2`
+    Spectrogram,
+    /// Mel-frequency cepstral coefficients
+    Mfcc {
+        /// Number of triangular mel filters
+        mel_bands: usize,
+        /// Number of cepstral coefficients kept
+        coefficients: usize,
+    },
+}
+
+/// Framed FFT / MFCC feature extractor
+#[derive(Debug, Clone)]
+pub struct AudioFeatureExtractor {
+    features: Features,
+    frame_len: usize,
+    hop: usize,
+    n_fft: usize,
+    num_frames: usize,
+}
+
+impl AudioFeatureExtractor {
+    /// Extractor with 25 ms frames and a 10 ms hop at `sample_rate`
+    pub fn new(features: Features, sample_rate: u32) -> Self {
+        let frame_len = (sample_rate as usize * 25 / 1000).max(1);
+        Self {
+            features,
+            frame_len,
+            hop: (sample_rate as usize / 100).max(1),
+            n_fft: frame_len.next_power_of_two(),
+            num_frames: 100,
+        }
+    }
+
+    /// 13 coefficients from 26 mel bands
+    pub fn mfcc(sample_rate: u32) -> Self {
+        Self::new(
+            Features::Mfcc {
+                mel_bands: 26,
+                coefficients: 13,
+            },
+            sample_rate,
+        )
+    }
+
+    /// Frame length and hop in samples; the FFT size is the next power of
+    /// two of the frame length
+    pub fn with_framing(mut self, frame_len: usize, hop: usize) -> Self {
+        self.frame_len = frame_len.max(1);
+        self.hop = hop.max(1);
+        self.n_fft = self.frame_len.next_power_of_two();
+        self
+    }
+
+    /// Frames per clip in [`Self::clip_features`]
+    pub fn with_num_frames(mut self, num_frames: usize) -> Self {
+        self.num_frames = num_frames.max(1);
+        self
+    }
+
+    /// Values per frame
+    pub fn frame_dim(&self) -> usize {
+        match self.features {
+            Features::Spectrogram => self.n_fft / 2 + 1,
+            Features::Mfcc { coefficients, .. } => coefficients,
+        }
+    }
+
+    /// Values per clip in [`Self::clip_features`]
+    pub fn clip_dim(&self) -> usize {
+        self.frame_dim() * self.num_frames
+    }
+
+    fn validate(&self) -> Result<(), AudioError> {
+        if let Features::Mfcc {
+            mel_bands,
+            coefficients,
+        } = self.features
+        {
+            if mel_bands == 0 || coefficients == 0 || coefficients > mel_bands {
+                return Err(AudioError::InvalidConfiguration(format!(
+                    "{coefficients} coefficients from {mel_bands} mel bands"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Features of every frame of `audio`; a final partial frame is
+    /// zero-padded
+    pub fn frames(&self, audio: &WavAudio) -> Result<Vec<Vec<f32>>, AudioError> {
+        self.validate()?;
+        let window: Vec<f32> = (0..self.frame_len)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / self.frame_len as f32).cos())
+            .collect();
+        let filters = match self.features {
+            Features::Mfcc { mel_bands, .. } => {
+                mel_filterbank(mel_bands, self.n_fft, audio.sample_rate as f32)
+            }
+            Features::Spectrogram => Vec::new(),
+        };
+
+        let samples = &audio.samples;
+        let count = if samples.len() <= self.frame_len {
+            usize::from(!samples.is_empty())
+        } else {
+            (samples.len() - self.frame_len).div_ceil(self.hop) + 1
+        };
+        Ok((0..count)
+            .map(|f| {
+                let start = f * self.hop;
+                let mut re = vec![0.0; self.n_fft];
+                let mut im = vec![0.0; self.n_fft];
+                for (i, w) in window.iter().enumerate() {
+                    re[i] = samples.get(start + i).copied().unwrap_or(0.0) * w;
+                }
+                fft(&mut re, &mut im);
+                let bins = self.n_fft / 2 + 1;
+                let magnitude = (0..bins).map(|k| (re[k] * re[k] + im[k] * im[k]).sqrt());
+                match self.features {
+                    Features::Spectrogram => magnitude.collect(),
+                    Features::Mfcc { coefficients, .. } => {
+                        let power: Vec<f32> = magnitude.map(|m| m * m).collect();
+                        let log_mel: Vec<f32> = filters
+                            .iter()
+                            .map(|filter| {
+                                let energy: f32 =
+                                    filter.iter().zip(&power).map(|(w, p)| w * p).sum();
+                                energy.max(1e-10).ln()
+                            })
+                            .collect();
+                        dct(&log_mel, coefficients)
+                    }
+                }
+            })
+            .collect())
+    }
+
+    /// Frame features of `audio` flattened to [`Self::clip_dim`] values,
+    /// truncating long clips and zero-padding short ones
+    pub fn clip_features(&self, audio: &WavAudio) -> Result<Vec<f32>, AudioError> {
+        let mut features: Vec<f32> = self
+            .frames(audio)?
+            .into_iter()
+            .take(self.num_frames)
+            .flatten()
+            .collect();
+        features.resize(self.clip_dim(), 0.0);
+        Ok(features)
+    }
+
+    /// Training data with one sample of clip features per clip
+    pub fn to_training_data<T: Float>(
+        &self,
+        clips: &[WavAudio],
+        targets: Vec<Vec<T>>,
+    ) -> Result<TrainingData<T>, AudioError> {
+        if clips.len() != targets.len() {
+            return Err(AudioError::TargetCountMismatch {
+                clips: clips.len(),
+                targets: targets.len(),
+            });
+        }
+        let inputs = clips
+            .iter()
+            .map(|clip| {
+                let features = self.clip_features(clip)?;
+                Ok(features.iter().map(|&v| cast(v)).collect())
+            })
+            .collect::<Result<_, AudioError>>()?;
+        Ok(TrainingData {
+            inputs,
+            outputs: targets,
+        })
+    }
+}
+
+/// In-place iterative radix-2 FFT; the length must be a power of two
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+/// Triangular filters evenly spaced on the mel scale up to Nyquist, one
+/// weight per FFT bin
+fn mel_filterbank(bands: usize, n_fft: usize, sample_rate: f32) -> Vec<Vec<f32>> {
+    let bins = n_fft / 2 + 1;
+    let max_mel = hz_to_mel(sample_rate / 2.0);
+    let edges: Vec<f32> = (0..bands + 2)
+        .map(|i| mel_to_hz(max_mel * i as f32 / (bands + 1) as f32) * n_fft as f32 / sample_rate)
+        .collect();
+    (0..bands)
+        .map(|m| {
+            let (left, center, right) = (edges[m], edges[m + 1], edges[m + 2]);
+            (0..bins)
+                .map(|k| {
+                    let k = k as f32;
+                    if k <= left || k >= right {
+                        0.0
+                    } else if k <= center {
+                        (k - left) / (center - left)
+                    } else {
+                        (right - k) / (right - center)
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// First `count` DCT-II coefficients, orthonormally scaled
+fn dct(values: &[f32], count: usize) -> Vec<f32> {
+    let n = values.len() as f32;
+    (0..count)
+        .map(|k| {
+            let scale = if k == 0 {
+                (1.0 / n).sqrt()
+            } else {
+                (2.0 / n).sqrt()
+            };
+            scale
+                * values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| v * (PI * k as f32 * (i as f32 + 0.5) / n).cos())
+                    .sum::<f32>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16-bit PCM WAV of `samples` with `channels` interleaved channels
+    fn wav(sample_rate: u32, channels: u16, samples: &[i16]) -> Vec<u8> {
+        let data_len = samples.len() as u32 * 2;
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend((36 + data_len).to_le_bytes());
+        bytes.extend(b"WAVEfmt ");
+        bytes.extend(16u32.to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend(channels.to_le_bytes());
+        bytes.extend(sample_rate.to_le_bytes());
+        bytes.extend((sample_rate * channels as u32 * 2).to_le_bytes());
+        bytes.extend((channels * 2).to_le_bytes());
+        bytes.extend(16u16.to_le_bytes());
+        bytes.extend(b"data");
+        bytes.extend(data_len.to_le_bytes());
+        bytes.extend(samples.iter().flat_map(|s| s.to_le_bytes()));
+        bytes
+    }
+
+    fn tone(sample_rate: u32, hz: f32, len: usize) -> WavAudio {
+        let samples: Vec<i16> = (0..len)
+            .map(|i| ((2.0 * PI * hz * i as f32 / sample_rate as f32).sin() * 16000.0) as i16)
+            .collect();
+        WavAudio::decode(&wav(sample_rate, 1, &samples)).unwrap()
+    }
+
+    #[test]
+    fn test_decode_wav() {
+        let audio = WavAudio::decode(&wav(8000, 2, &[16384, -16384, 32767, 32767])).unwrap();
+        assert_eq!(audio.sample_rate, 8000);
+        assert_eq!(audio.samples.len(), 2);
+        assert_eq!(audio.samples[0], 0.0);
+        assert!((audio.samples[1] - 1.0).abs() < 1e-4);
+
+        assert!(WavAudio::decode(b"RIFF\0\0\0\0AVI ").is_err());
+        let mut float = wav(8000, 1, &[]);
+        float[20] = 3;
+        assert!(WavAudio::decode(&float).is_err());
+        // A chunk claiming u32::MAX bytes is rejected, not skipped over
+        let mut huge = wav(8000, 1, &[]);
+        huge[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(WavAudio::decode(&huge).is_err());
+    }
+
+    #[test]
+    fn test_spectrogram_peak_and_mfcc_shape() {
+        // 64-sample frames at 8 kHz: bin k is k * 125 Hz
+        let audio = tone(8000, 1000.0, 800);
+        let spectrogram = AudioFeatureExtractor::new(Features::Spectrogram, 8000)
+            .with_framing(64, 32)
+            .with_num_frames(10);
+        let frames = spectrogram.frames(&audio).unwrap();
+        assert_eq!(frames.len(), 24);
+        let peak = frames[3]
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap()
+            .0;
+        assert_eq!(peak, 8);
+        assert_eq!(spectrogram.clip_features(&audio).unwrap().len(), 330);
+
+        let mfcc = AudioFeatureExtractor::mfcc(8000).with_num_frames(4);
+        let low = mfcc.clip_features(&tone(8000, 300.0, 800)).unwrap();
+        let high = mfcc.clip_features(&tone(8000, 3000.0, 800)).unwrap();
+        assert_eq!(low.len(), 52);
+        assert!(low.iter().chain(&high).all(|v| v.is_finite()));
+        assert_ne!(low, high);
+
+        let data = mfcc
+            .to_training_data(std::slice::from_ref(&audio), vec![vec![1.0f32]])
+            .unwrap();
+        assert_eq!(data.inputs[0].len(), mfcc.clip_dim());
+        assert!(mfcc.to_training_data::<f32>(&[audio], vec![]).is_err());
+    }
+}
@@ -15,6 +15,7 @@
 //! - Parallel candidate training support
 //! - Professional logging and debugging
 
+use crate::numeric::cast;
 use num_traits::Float;
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
@@ -23,9 +24,14 @@ use thiserror::Error;
 use crate::{
     cascade_error,
     errors::{CascadeErrorCategory, RuvFannError},
+    network::{CleanupReport, RemovalReason, RemovedNeuron},
+    training::CancellationToken,
     ActivationFunction, Network, TrainingData,
 };
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 // Rayon imports are done locally in the parallel functions
 
 #[cfg(feature = "logging")]
@@ -79,6 +85,37 @@ impl<T: Float> CascadeNetwork<T> {
             state: CascadeState::default(),
         }
     }
+
+    /// Remove hidden neurons whose outgoing weight magnitude is below
+    /// `threshold`, weakest first
+    ///
+    /// Without data the removed neurons' activations are unknown, so their
+    /// contribution is dropped rather than folded into the next layer's bias;
+    /// [`Self::prune_with_data`] keeps it.
+    pub fn prune(&mut self, threshold: T) -> CleanupReport<T> {
+        let report = prune_hidden_neurons(
+            &mut self.network,
+            PruningCriterion::Magnitude,
+            threshold,
+            None,
+        );
+        self.state.hidden_neurons_pruned += report.removed.len();
+        report
+    }
+
+    /// Remove hidden neurons scoring below `threshold` under
+    /// `config.pruning_criterion` on `data`, folding each one's mean
+    /// activation into the next layer's bias weights
+    pub fn prune_with_data(&mut self, data: &TrainingData<T>, threshold: T) -> CleanupReport<T> {
+        let report = prune_hidden_neurons(
+            &mut self.network,
+            self.config.pruning_criterion,
+            threshold,
+            Some(data),
+        );
+        self.state.hidden_neurons_pruned += report.removed.len();
+        report
+    }
 }
 
 /// State information for cascade training
@@ -86,6 +123,8 @@ impl<T: Float> CascadeNetwork<T> {
 pub struct CascadeState<T: Float> {
     /// Number of hidden neurons added
     pub hidden_neurons_added: usize,
+    /// Number of hidden neurons pruned
+    pub hidden_neurons_pruned: usize,
     /// Current training error
     pub current_error: T,
     /// Best correlation achieved
@@ -96,14 +135,28 @@ impl<T: Float> Default for CascadeState<T> {
     fn default() -> Self {
         Self {
             hidden_neurons_added: 0,
+            hidden_neurons_pruned: 0,
             current_error: T::infinity(),
             best_correlation: T::zero(),
         }
     }
 }
 
+/// How pruning scores the contribution of a hidden neuron
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PruningCriterion {
+    /// Sum of the absolute outgoing weights
+    #[default]
+    Magnitude,
+    /// Largest absolute correlation between the neuron's activation and a
+    /// network output over the training data
+    Correlation,
+}
+
 /// Configuration for cascade correlation training
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CascadeConfig<T: Float> {
     /// Maximum number of hidden neurons to add
     pub max_hidden_neurons: usize,
@@ -141,6 +194,23 @@ pub struct CascadeConfig<T: Float> {
     /// Patience for early stopping (epochs without improvement)
     pub patience: usize,
 
+    /// Stop growing after this many installed neurons without a validation
+    /// error improvement (requires validation data on the trainer)
+    pub validation_patience: Option<usize>,
+
+    /// Wall-clock budget for the whole cascade run, checked between phases
+    pub max_training_time: Option<std::time::Duration>,
+
+    /// Prune hidden neurons whose contribution falls below
+    /// `pruning_threshold` after each candidate installation
+    pub enable_pruning: bool,
+
+    /// How hidden neuron contributions are scored for pruning
+    pub pruning_criterion: PruningCriterion,
+
+    /// Contribution below which a hidden neuron is pruned
+    pub pruning_threshold: T,
+
     /// Whether to use weight decay
     pub use_weight_decay: bool,
 
@@ -170,22 +240,27 @@ impl<T: Float> Default for CascadeConfig<T> {
             num_candidates: 8,
             output_max_epochs: 1000,
             candidate_max_epochs: 1000,
-            output_learning_rate: T::from(0.1).unwrap(),
-            candidate_learning_rate: T::from(0.1).unwrap(),
-            output_target_error: T::from(0.01).unwrap(),
-            candidate_target_correlation: T::from(0.4).unwrap(),
-            min_correlation_improvement: T::from(0.01).unwrap(),
-            candidate_weight_range: (T::from(-1.0).unwrap(), T::from(1.0).unwrap()),
+            output_learning_rate: cast(0.1),
+            candidate_learning_rate: cast(0.1),
+            output_target_error: cast(0.01),
+            candidate_target_correlation: cast(0.4),
+            min_correlation_improvement: cast(0.01),
+            candidate_weight_range: (cast(-1.0), cast(1.0)),
             candidate_activations: vec![
                 ActivationFunction::Sigmoid,
                 ActivationFunction::Tanh,
                 ActivationFunction::Gaussian,
             ],
             patience: 50,
+            validation_patience: None,
+            max_training_time: None,
+            enable_pruning: false,
+            pruning_criterion: PruningCriterion::Magnitude,
+            pruning_threshold: cast(0.01),
             use_weight_decay: true,
-            weight_decay: T::from(0.0001).unwrap(),
+            weight_decay: cast(0.0001),
             use_momentum: true,
-            momentum: T::from(0.9).unwrap(),
+            momentum: cast(0.9),
             parallel_candidates: true,
             random_seed: None,
             verbose: false,
@@ -249,15 +324,13 @@ impl<T: Float> CandidateNeuron<T> {
 
         let weights: Vec<T> = (0..num_inputs)
             .map(|_| {
-                let w: f64 =
-                    rng.gen_range(min_weight.to_f64().unwrap()..=max_weight.to_f64().unwrap());
-                T::from(w).unwrap()
+                let w: f64 = rng.gen_range(cast::<f64>(min_weight)..=cast::<f64>(max_weight));
+                cast(w)
             })
             .collect();
 
-        let bias_val: f64 =
-            rng.gen_range(min_weight.to_f64().unwrap()..=max_weight.to_f64().unwrap());
-        let bias = T::from(bias_val).unwrap();
+        let bias_val: f64 = rng.gen_range(cast::<f64>(min_weight)..=cast::<f64>(max_weight));
+        let bias = cast(bias_val);
 
         Self {
             weights,
@@ -310,7 +383,7 @@ impl<T: Float> CandidateNeuron<T> {
             ActivationFunction::Tanh => (T::one() - output * output) * self.steepness,
             ActivationFunction::Linear => self.steepness,
             ActivationFunction::Gaussian => {
-                let neg_two = T::from(-2.0).unwrap();
+                let neg_two: T = cast(-2.0);
                 neg_two * output * self.steepness * self.steepness
             }
             ActivationFunction::ReLU => {
@@ -359,6 +432,9 @@ pub struct CascadeTrainer<T: Float> {
     /// Current hidden neuron count
     pub hidden_count: usize,
 
+    /// Hidden neurons removed by pruning so far
+    pub neurons_pruned: usize,
+
     /// Training history
     pub training_history: Vec<CascadeTrainingRecord<T>>,
 
@@ -373,10 +449,84 @@ pub struct CascadeTrainer<T: Float> {
 
     /// Performance metrics
     pub metrics: CascadeMetrics,
+
+    /// Optional held-out data used for validation-based stopping
+    pub validation_data: Option<TrainingData<T>>,
+
+    /// Best validation error seen so far and installs since it improved
+    validation_progress: (T, usize),
+
+    /// Seed the candidate RNG was last re-seeded from (captured in checkpoints)
+    rng_seed: u64,
+
+    /// Whether the initial output training phase has already run
+    initial_outputs_trained: bool,
+
+    /// Called with a checkpoint after every neuron installation
+    checkpoint_callback: Option<CascadeCheckpointCallback<T>>,
+
+    /// Checked between epochs of the output and candidate phases
+    cancellation: Option<CancellationToken>,
+}
+
+/// Callback receiving a checkpoint after each installed hidden neuron
+pub type CascadeCheckpointCallback<T> =
+    Box<dyn FnMut(&CascadeCheckpoint<T>) -> Result<(), RuvFannError> + Send + Sync>;
+
+/// Snapshot of a cascade run taken after a neuron installation
+///
+/// Restoring it with [`CascadeTrainer::from_checkpoint`] continues the run
+/// exactly where it stopped, including the candidate RNG stream.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CascadeCheckpoint<T: Float> {
+    pub network: Network<T>,
+    pub config: CascadeConfig<T>,
+    pub hidden_count: usize,
+    pub neurons_pruned: usize,
+    pub training_history: Vec<CascadeTrainingRecord<T>>,
+    pub current_epoch: usize,
+    pub best_error: T,
+    pub metrics: CascadeMetrics,
+    pub best_validation_error: T,
+    pub validation_stall_count: usize,
+    pub rng_seed: u64,
+}
+
+#[cfg(all(feature = "binary", feature = "serde"))]
+impl<T> CascadeCheckpoint<T>
+where
+    T: Float + Serialize + serde::de::DeserializeOwned,
+{
+    /// Write the checkpoint to a file, replacing it atomically
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), RuvFannError> {
+        let path = path.as_ref();
+        let bytes = bincode::serialize(self).map_err(|e| RuvFannError::Io {
+            category: crate::errors::IoErrorCategory::Serialization,
+            message: format!("Failed to serialize cascade checkpoint: {e}"),
+            source: None,
+        })?;
+
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, bytes)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Read a checkpoint previously written with [`CascadeCheckpoint::save`]
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, RuvFannError> {
+        let bytes = std::fs::read(path)?;
+        bincode::deserialize(&bytes).map_err(|e| RuvFannError::Io {
+            category: crate::errors::IoErrorCategory::Serialization,
+            message: format!("Failed to deserialize cascade checkpoint: {e}"),
+            source: None,
+        })
+    }
 }
 
 /// Training record for cascade correlation
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CascadeTrainingRecord<T: Float> {
     pub hidden_neuron_index: usize,
     pub output_training_epochs: usize,
@@ -389,6 +539,7 @@ pub struct CascadeTrainingRecord<T: Float> {
 
 /// Performance metrics for cascade training
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CascadeMetrics {
     pub total_training_time: std::time::Duration,
     pub output_training_time: std::time::Duration,
@@ -417,7 +568,7 @@ impl Default for CascadeMetrics {
     }
 }
 
-impl<T: Float> CascadeTrainer<T> {
+impl<T: Float + Send + Sync> CascadeTrainer<T> {
     /// Create a new cascade trainer
     pub fn new(
         config: CascadeConfig<T>,
@@ -430,25 +581,116 @@ impl<T: Float> CascadeTrainer<T> {
         // Validate training data
         Self::validate_training_data(&training_data, &initial_network)?;
 
-        let rng = if let Some(seed) = config.random_seed {
-            StdRng::seed_from_u64(seed)
-        } else {
-            StdRng::from_entropy()
-        };
+        let rng_seed = config.random_seed.unwrap_or_else(rand::random);
+        let rng = StdRng::seed_from_u64(rng_seed);
 
         Ok(Self {
             config,
             network: initial_network,
             training_data,
             hidden_count: 0,
+            neurons_pruned: 0,
             training_history: Vec::new(),
             current_epoch: 0,
             best_error: T::infinity(),
             rng,
             metrics: CascadeMetrics::default(),
+            validation_data: None,
+            validation_progress: (T::infinity(), 0),
+            rng_seed,
+            initial_outputs_trained: false,
+            checkpoint_callback: None,
+            cancellation: None,
         })
     }
 
+    /// Recreate a trainer from a checkpoint so training can be resumed
+    ///
+    /// Checkpoints hold no data, so pass the run's training data and, for
+    /// the `validation_patience` rule to keep counting from the checkpoint,
+    /// its validation data.
+    pub fn from_checkpoint(
+        checkpoint: CascadeCheckpoint<T>,
+        training_data: TrainingData<T>,
+        validation_data: Option<TrainingData<T>>,
+    ) -> Result<Self, CascadeError> {
+        Self::validate_config(&checkpoint.config)?;
+        Self::validate_training_data(&training_data, &checkpoint.network)?;
+        if let Some(validation_data) = &validation_data {
+            Self::validate_training_data(validation_data, &checkpoint.network)?;
+        }
+
+        Ok(Self {
+            config: checkpoint.config,
+            network: checkpoint.network,
+            training_data,
+            hidden_count: checkpoint.hidden_count,
+            neurons_pruned: checkpoint.neurons_pruned,
+            training_history: checkpoint.training_history,
+            current_epoch: checkpoint.current_epoch,
+            best_error: checkpoint.best_error,
+            rng: StdRng::seed_from_u64(checkpoint.rng_seed),
+            metrics: checkpoint.metrics,
+            validation_data,
+            validation_progress: (
+                checkpoint.best_validation_error,
+                checkpoint.validation_stall_count,
+            ),
+            rng_seed: checkpoint.rng_seed,
+            initial_outputs_trained: true,
+            checkpoint_callback: None,
+            cancellation: None,
+        })
+    }
+
+    /// Register a callback that receives a checkpoint after each installation
+    pub fn set_checkpoint_callback(&mut self, callback: CascadeCheckpointCallback<T>) {
+        self.checkpoint_callback = Some(callback);
+    }
+
+    /// Stop training once `token` is cancelled
+    ///
+    /// The token is checked between output and candidate training epochs. A
+    /// cancelled run returns the network as it stands with
+    /// [`CascadeStopReason::Cancelled`]; a candidate interrupted mid-training is
+    /// discarded rather than installed.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation = Some(token);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Capture the current training state
+    pub fn checkpoint(&self) -> CascadeCheckpoint<T> {
+        CascadeCheckpoint {
+            network: self.network.clone(),
+            config: self.config.clone(),
+            hidden_count: self.hidden_count,
+            neurons_pruned: self.neurons_pruned,
+            training_history: self.training_history.clone(),
+            current_epoch: self.current_epoch,
+            best_error: self.best_error,
+            metrics: self.metrics.clone(),
+            best_validation_error: self.validation_progress.0,
+            validation_stall_count: self.validation_progress.1,
+            rng_seed: self.rng_seed,
+        }
+    }
+
+    /// Attach held-out data used by the `validation_patience` stopping rule
+    pub fn with_validation_data(
+        mut self,
+        validation_data: TrainingData<T>,
+    ) -> Result<Self, CascadeError> {
+        Self::validate_training_data(&validation_data, &self.network)?;
+        self.validation_data = Some(validation_data);
+        Ok(self)
+    }
+
     /// Main cascade training loop
     pub fn train(&mut self) -> Result<CascadeTrainingResult<T>, RuvFannError> {
         let start_time = std::time::Instant::now();
@@ -459,11 +701,18 @@ impl<T: Float> CascadeTrainer<T> {
             self.config.max_hidden_neurons
         );
 
-        // Phase 1: Train initial output weights
-        self.train_output_weights()?;
+        // Phase 1: Train initial output weights (already done when resuming)
+        if !self.initial_outputs_trained {
+            self.train_output_weights()?;
+            self.initial_outputs_trained = true;
+        }
+
+        // Phase 2: Iteratively add hidden neurons until a stopping rule fires
+        let stop_reason = loop {
+            if let Some(reason) = self.check_stopping_rules(start_time) {
+                break reason;
+            }
 
-        // Phase 2: Iteratively add hidden neurons
-        while self.hidden_count < self.config.max_hidden_neurons {
             if self.config.verbose {
                 println!(
                     "Adding hidden neuron {} of {}",
@@ -474,29 +723,44 @@ impl<T: Float> CascadeTrainer<T> {
 
             // Generate and train candidate neurons
             let best_candidate = self.train_candidates()?;
+            if self.is_cancelled() {
+                break CascadeStopReason::Cancelled;
+            }
 
             // Check if candidate meets minimum improvement threshold
             if best_candidate.correlation < self.config.min_correlation_improvement {
                 #[cfg(feature = "logging")]
                 info!("No candidate meets minimum correlation improvement threshold. Stopping cascade training.");
-                break;
+                break CascadeStopReason::InsufficientCorrelation;
             }
 
             // Add best candidate to network
             self.install_candidate(best_candidate)?;
+            self.hidden_count += 1;
 
             // Train output weights with new topology
             self.train_output_weights()?;
+            if self.config.enable_pruning {
+                self.prune_after_install()?;
+            }
 
-            // Check convergence
-            if self.best_error <= self.config.output_target_error {
-                #[cfg(feature = "logging")]
-                info!("Target error achieved. Stopping cascade training.");
-                break;
+            let validation_exhausted = self.validation_patience_exhausted();
+
+            // Re-seed from the current stream so a checkpoint can reproduce it
+            self.rng_seed = self.rng.gen();
+            self.rng = StdRng::seed_from_u64(self.rng_seed);
+            if let Some(mut callback) = self.checkpoint_callback.take() {
+                let result = callback(&self.checkpoint());
+                self.checkpoint_callback = Some(callback);
+                result?;
             }
 
-            self.hidden_count += 1;
-        }
+            if validation_exhausted {
+                #[cfg(feature = "logging")]
+                info!("Validation error stopped improving. Stopping cascade training.");
+                break CascadeStopReason::ValidationPatienceExhausted;
+            }
+        };
 
         self.metrics.total_training_time = start_time.elapsed();
 
@@ -511,12 +775,40 @@ impl<T: Float> CascadeTrainer<T> {
             final_network: self.network.clone(),
             final_error: self.best_error,
             hidden_neurons_added: self.hidden_count,
+            neurons_pruned: self.neurons_pruned,
             training_history: self.training_history.clone(),
             metrics: self.metrics.clone(),
-            convergence_reason: self.determine_convergence_reason(),
+            convergence_reason: stop_reason.to_string(),
+            stop_reason,
         })
     }
 
+    /// Remove hidden neurons contributing less than `pruning_threshold`,
+    /// then retrain the outputs if anything was removed
+    fn prune_after_install(&mut self) -> Result<(), RuvFannError> {
+        let start_time = std::time::Instant::now();
+        let report = prune_hidden_neurons(
+            &mut self.network,
+            self.config.pruning_criterion,
+            self.config.pruning_threshold,
+            Some(&self.training_data),
+        );
+        self.metrics.network_modification_time += start_time.elapsed();
+        if report.is_empty() {
+            return Ok(());
+        }
+
+        #[cfg(feature = "logging")]
+        info!(
+            "Pruned {} hidden neurons ({} connections)",
+            report.removed.len(),
+            report.connections_removed
+        );
+
+        self.neurons_pruned += report.removed.len();
+        self.train_output_weights()
+    }
+
     /// Train output weights using standard backpropagation
     fn train_output_weights(&mut self) -> Result<(), RuvFannError> {
         let start_time = std::time::Instant::now();
@@ -531,6 +823,9 @@ impl<T: Float> CascadeTrainer<T> {
         let mut best_epoch_error = T::infinity();
 
         for epoch in 0..self.config.output_max_epochs {
+            if self.is_cancelled() {
+                break;
+            }
             let epoch_error = self.train_output_epoch()?;
 
             if epoch_error < best_epoch_error {
@@ -571,7 +866,7 @@ impl<T: Float> CascadeTrainer<T> {
     /// Train output weights for one epoch
     fn train_output_epoch(&mut self) -> Result<T, RuvFannError> {
         let mut total_error = T::zero();
-        let num_samples = T::from(self.training_data.inputs.len()).unwrap();
+        let num_samples = cast(self.training_data.inputs.len());
 
         // Clone training data to avoid borrow conflicts
         let inputs = self.training_data.inputs.clone();
@@ -595,6 +890,9 @@ impl<T: Float> CascadeTrainer<T> {
     }
 
     /// Generate and train candidate neurons
+    ///
+    /// Candidates are trained epoch by epoch and all of them are scored together
+    /// in one batched evaluation pass per epoch.
     fn train_candidates(&mut self) -> Result<CandidateNeuron<T>, RuvFannError> {
         let start_time = std::time::Instant::now();
 
@@ -604,27 +902,59 @@ impl<T: Float> CascadeTrainer<T> {
         // Generate candidate neurons
         let mut candidates = self.generate_candidates()?;
 
-        // Train candidates (parallel if enabled)
-        #[cfg(feature = "parallel")]
-        {
-            if self.config.parallel_candidates {
-                // Note: parallel training requires T: Send + Sync
-                // For now, fallback to sequential
-                self.train_candidates_sequential(&mut candidates)?;
-            } else {
-                self.train_candidates_sequential(&mut candidates)?;
+        // The network is frozen while candidates train, so its activations and
+        // residuals are computed once and reused for every candidate and epoch.
+        let cache = self.build_candidate_cache()?;
+
+        let mut best_correlations = vec![T::zero(); candidates.len()];
+        let mut patience_counters = vec![0usize; candidates.len()];
+        let mut active = vec![true; candidates.len()];
+
+        for _epoch in 0..self.config.candidate_max_epochs {
+            if self.is_cancelled() {
+                break;
             }
-        }
+            self.train_candidates_epoch(&mut candidates, &active, &cache);
 
-        #[cfg(not(feature = "parallel"))]
-        {
-            self.train_candidates_sequential(&mut candidates)?;
+            let correlations = self.evaluate_candidates(&candidates, &cache)?;
+
+            for (idx, candidate) in candidates.iter_mut().enumerate() {
+                if !active[idx] {
+                    continue;
+                }
+
+                let correlation = correlations[idx];
+                candidate.correlation = correlation;
+                candidate.training_history.push(correlation);
+
+                if correlation > best_correlations[idx] {
+                    best_correlations[idx] = correlation;
+                    patience_counters[idx] = 0;
+                } else {
+                    patience_counters[idx] += 1;
+                }
+
+                // Early stopping and target correlation
+                if patience_counters[idx] >= self.config.patience
+                    || correlation >= self.config.candidate_target_correlation
+                {
+                    active[idx] = false;
+                }
+            }
+
+            if !active.iter().any(|&is_active| is_active) {
+                break;
+            }
         }
 
         // Select best candidate
         let best_candidate = candidates
             .into_iter()
-            .max_by(|a, b| a.correlation.partial_cmp(&b.correlation).unwrap())
+            .max_by(|a, b| {
+                a.correlation
+                    .partial_cmp(&b.correlation)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
             .ok_or_else(|| {
                 cascade_error!(
                     CascadeErrorCategory::CandidateSelection,
@@ -655,11 +985,13 @@ impl<T: Float> CascadeTrainer<T> {
                 .gen_range(0..self.config.candidate_activations.len());
             let activation = self.config.candidate_activations[activation_idx];
 
+            // Seed from the checkpointed stream, so every candidate starts
+            // differently and a resumed run draws the same weights
             let candidate = CandidateNeuron::new(
                 num_inputs,
                 activation,
                 self.config.candidate_weight_range,
-                self.config.random_seed,
+                Some(self.rng.gen()),
             );
 
             candidates.push(candidate);
@@ -668,56 +1000,82 @@ impl<T: Float> CascadeTrainer<T> {
         Ok(candidates)
     }
 
-    /// Train candidates sequentially
-    fn train_candidates_sequential(
-        &mut self,
+    /// Compute the frozen network's candidate inputs and residuals over the dataset
+    fn build_candidate_cache(&mut self) -> Result<CandidateCache<T>, RuvFannError> {
+        let inputs: Vec<Vec<T>> = self
+            .training_data
+            .inputs
+            .iter()
+            .map(|input| self.extract_candidate_input(input))
+            .collect();
+        let residuals = self.calculate_residuals()?;
+        Ok(CandidateCache::new(inputs, residuals))
+    }
+
+    /// Run one training epoch for every active candidate against the cached data
+    fn train_candidates_epoch(
+        &self,
         candidates: &mut [CandidateNeuron<T>],
-    ) -> Result<(), RuvFannError> {
-        for candidate in candidates.iter_mut() {
-            self.train_single_candidate(candidate)?;
+        active: &[bool],
+        cache: &CandidateCache<T>,
+    ) {
+        #[cfg(feature = "parallel")]
+        if self.config.parallel_candidates {
+            use rayon::prelude::*;
+            candidates
+                .par_iter_mut()
+                .zip(active.par_iter())
+                .filter(|(_, &is_active)| is_active)
+                .for_each(|(candidate, _)| self.train_candidate_epoch(candidate, cache));
+            return;
+        }
+
+        for (candidate, _) in candidates
+            .iter_mut()
+            .zip(active.iter())
+            .filter(|(_, &is_active)| is_active)
+        {
+            self.train_candidate_epoch(candidate, cache);
         }
-        Ok(())
     }
 
-    /// Train a single candidate neuron
-    fn train_single_candidate(
+    /// Score all candidates against the cached residuals in one batched pass
+    ///
+    /// With the `parallel` feature and `parallel_candidates` enabled the
+    /// candidates are evaluated concurrently.
+    fn evaluate_candidates(
         &mut self,
-        candidate: &mut CandidateNeuron<T>,
-    ) -> Result<(), RuvFannError> {
-        let mut best_correlation = T::zero();
-        let mut patience_counter = 0;
-
-        for _epoch in 0..self.config.candidate_max_epochs {
-            // Calculate current network residuals
-            let residuals = self.calculate_residuals()?;
-
-            // Train candidate for one epoch
-            self.train_candidate_epoch(candidate, &residuals)?;
+        candidates: &[CandidateNeuron<T>],
+        cache: &CandidateCache<T>,
+    ) -> Result<Vec<T>, RuvFannError> {
+        let start_time = std::time::Instant::now();
 
-            // Calculate correlation with residuals
-            let correlation = self.calculate_correlation(candidate, &residuals)?;
-            candidate.correlation = correlation;
-            candidate.training_history.push(correlation);
+        let this = &*self;
+        let score = |candidate: &CandidateNeuron<T>| -> Result<T, RuvFannError> {
+            let outputs = cache.candidate_outputs(candidate);
 
-            if correlation > best_correlation {
-                best_correlation = correlation;
-                patience_counter = 0;
-            } else {
-                patience_counter += 1;
-            }
+            // Sum of absolute correlations over all output dimensions
+            cache
+                .residual_columns
+                .iter()
+                .try_fold(T::zero(), |acc, column| {
+                    Ok(acc + this.pearson_correlation(&outputs, column)?.abs())
+                })
+        };
 
-            // Early stopping
-            if patience_counter >= self.config.patience {
-                break;
-            }
+        #[cfg(feature = "parallel")]
+        let scores: Result<Vec<T>, RuvFannError> = if self.config.parallel_candidates {
+            use rayon::prelude::*;
+            candidates.par_iter().map(score).collect()
+        } else {
+            candidates.iter().map(score).collect()
+        };
 
-            // Check target correlation
-            if correlation >= self.config.candidate_target_correlation {
-                break;
-            }
-        }
+        #[cfg(not(feature = "parallel"))]
+        let scores: Result<Vec<T>, RuvFannError> = candidates.iter().map(score).collect();
 
-        Ok(())
+        self.metrics.correlation_calculation_time += start_time.elapsed();
+        scores
     }
 
     /// Calculate network residuals (errors) for candidate training
@@ -731,6 +1089,7 @@ impl<T: Float> CascadeTrainer<T> {
             .zip(self.training_data.outputs.iter())
         {
             let output = self.network.run(input);
+            self.metrics.total_forward_passes += 1;
             let residual: Vec<T> = output
                 .iter()
                 .zip(target.iter())
@@ -742,38 +1101,6 @@ impl<T: Float> CascadeTrainer<T> {
         Ok(residuals)
     }
 
-    /// Calculate correlation between candidate output and residuals
-    fn calculate_correlation(
-        &mut self,
-        candidate: &mut CandidateNeuron<T>,
-        residuals: &[Vec<T>],
-    ) -> Result<T, RuvFannError> {
-        let start_time = std::time::Instant::now();
-
-        // Calculate candidate outputs for all training samples
-        let mut candidate_outputs = Vec::with_capacity(self.training_data.inputs.len());
-
-        for input in &self.training_data.inputs {
-            let candidate_input = self.extract_candidate_input(input);
-            let output = candidate.calculate_output(&candidate_input);
-            candidate_outputs.push(output);
-        }
-
-        // Calculate correlation with each output dimension and sum
-        let mut total_correlation = T::zero();
-        let num_outputs = self.training_data.outputs[0].len();
-
-        for output_idx in 0..num_outputs {
-            let residual_values: Vec<T> = residuals.iter().map(|r| r[output_idx]).collect();
-
-            let correlation = self.pearson_correlation(&candidate_outputs, &residual_values)?;
-            total_correlation = total_correlation + correlation.abs();
-        }
-
-        self.metrics.correlation_calculation_time += start_time.elapsed();
-        Ok(total_correlation)
-    }
-
     /// Calculate Pearson correlation coefficient
     fn pearson_correlation(&self, x: &[T], y: &[T]) -> Result<T, RuvFannError> {
         if x.len() != y.len() || x.is_empty() {
@@ -783,7 +1110,7 @@ impl<T: Float> CascadeTrainer<T> {
             ));
         }
 
-        let n = T::from(x.len()).unwrap();
+        let n = cast(x.len());
 
         // Calculate means
         let mean_x = x.iter().fold(T::zero(), |acc, &val| acc + val) / n;
@@ -875,23 +1202,110 @@ impl<T: Float> CascadeTrainer<T> {
         Ok(())
     }
 
-    fn train_candidate_epoch(
-        &mut self,
-        _candidate: &mut CandidateNeuron<T>,
-        _residuals: &[Vec<T>],
-    ) -> Result<(), RuvFannError> {
-        // Simplified candidate training - full implementation would use gradient descent
-        Ok(())
+    /// Gradient ascent step on the candidate's residual correlation
+    fn train_candidate_epoch(&self, candidate: &mut CandidateNeuron<T>, cache: &CandidateCache<T>) {
+        let num_samples = cache.inputs.len();
+        if num_samples == 0 {
+            return;
+        }
+        let n = cast(num_samples);
+
+        let outputs = cache.candidate_outputs(candidate);
+        let mean_output = outputs.iter().fold(T::zero(), |acc, &o| acc + o) / n;
+
+        // Direction of the correlation with each output's residual
+        let signs: Vec<T> = cache
+            .residual_columns
+            .iter()
+            .map(|column| {
+                let covariance = outputs
+                    .iter()
+                    .zip(column)
+                    .fold(T::zero(), |acc, (&o, &r)| acc + (o - mean_output) * r);
+                if covariance < T::zero() {
+                    -T::one()
+                } else {
+                    T::one()
+                }
+            })
+            .collect();
+
+        let mut weight_gradients = vec![T::zero(); candidate.weights.len()];
+        let mut bias_gradient = T::zero();
+        for (sample_idx, (input, &output)) in cache.inputs.iter().zip(&outputs).enumerate() {
+            let error = cache
+                .residual_columns
+                .iter()
+                .zip(&signs)
+                .fold(T::zero(), |acc, (column, &sign)| {
+                    acc + sign * column[sample_idx]
+                });
+            let delta = error * candidate.activation_derivative(output);
+            for (gradient, &x) in weight_gradients.iter_mut().zip(input) {
+                *gradient = *gradient + delta * x;
+            }
+            bias_gradient = bias_gradient + delta;
+        }
+
+        // update_weights descends, so negate to maximise the correlation
+        for (stored, gradient) in candidate.weight_gradients.iter_mut().zip(weight_gradients) {
+            *stored = -gradient / n;
+        }
+        candidate.bias_gradient = -bias_gradient / n;
+        candidate.update_weights(
+            self.config.candidate_learning_rate,
+            self.config.use_momentum,
+            self.config.momentum,
+        );
     }
 
-    fn determine_convergence_reason(&self) -> String {
-        if self.best_error <= self.config.output_target_error {
-            "Target error achieved".to_string()
+    /// Check the stopping rules evaluated before each new candidate phase
+    fn check_stopping_rules(&self, start_time: std::time::Instant) -> Option<CascadeStopReason> {
+        if self.is_cancelled() {
+            Some(CascadeStopReason::Cancelled)
+        } else if self.best_error <= self.config.output_target_error {
+            Some(CascadeStopReason::TargetErrorReached)
         } else if self.hidden_count >= self.config.max_hidden_neurons {
-            "Maximum hidden neurons reached".to_string()
+            Some(CascadeStopReason::MaxHiddenNeurons)
+        } else if self
+            .config
+            .max_training_time
+            .is_some_and(|budget| start_time.elapsed() >= budget)
+        {
+            Some(CascadeStopReason::TimeBudgetExceeded)
+        } else {
+            None
+        }
+    }
+
+    /// Update validation tracking after an install; true once patience runs out
+    fn validation_patience_exhausted(&mut self) -> bool {
+        let (Some(patience), Some(validation_data)) = (
+            self.config.validation_patience,
+            self.validation_data.as_ref(),
+        ) else {
+            return false;
+        };
+
+        let mut network = self.network.clone();
+        let total = validation_data
+            .inputs
+            .iter()
+            .zip(&validation_data.outputs)
+            .fold(T::zero(), |acc, (input, target)| {
+                let output = network.run(input);
+                acc + self.calculate_output_error(&output, target)
+            });
+        let error = total / cast(validation_data.inputs.len());
+
+        let (best, stalled) = &mut self.validation_progress;
+        if error < *best {
+            *best = error;
+            *stalled = 0;
         } else {
-            "No further improvement possible".to_string()
+            *stalled += 1;
         }
+        *stalled >= patience
     }
 
     /// Configuration validation
@@ -920,6 +1334,14 @@ impl<T: Float> CascadeTrainer<T> {
             ));
         }
 
+        if config.enable_pruning
+            && (config.pruning_threshold.is_nan() || config.pruning_threshold < T::zero())
+        {
+            return Err(CascadeError::InvalidConfiguration(
+                "pruning_threshold must be non-negative".to_string(),
+            ));
+        }
+
         if config.candidate_activations.is_empty() {
             return Err(CascadeError::InvalidConfiguration(
                 "candidate_activations cannot be empty".to_string(),
@@ -973,19 +1395,169 @@ impl<T: Float> CascadeTrainer<T> {
 
         Ok(())
     }
+}
 
-    // Parallel training helper
-    #[cfg(feature = "parallel")]
-    fn train_single_candidate_parallel(
-        &self,
-        _candidate: &mut CandidateNeuron<T>,
-        _training_data: &TrainingData<T>,
-        _config: &CascadeConfig<T>,
-    ) -> Result<(), RuvFannError> {
-        // Simplified parallel training implementation
-        // Full implementation would need thread-safe access to network state
-        Ok(())
+/// Frozen-network activations shared by all candidates during one candidate phase
+struct CandidateCache<T: Float> {
+    /// Candidate inputs (network inputs plus installed hidden outputs) per sample
+    inputs: Vec<Vec<T>>,
+    /// Mean-centred residuals, one column per network output
+    residual_columns: Vec<Vec<T>>,
+}
+
+impl<T: Float> CandidateCache<T> {
+    fn new(inputs: Vec<Vec<T>>, residuals: Vec<Vec<T>>) -> Self {
+        let num_outputs = residuals.first().map_or(0, |r| r.len());
+        let n = cast(residuals.len().max(1));
+        let residual_columns = (0..num_outputs)
+            .map(|output_idx| {
+                let column: Vec<T> = residuals.iter().map(|r| r[output_idx]).collect();
+                let mean = column.iter().fold(T::zero(), |acc, &r| acc + r) / n;
+                column.into_iter().map(|r| r - mean).collect()
+            })
+            .collect();
+
+        Self {
+            inputs,
+            residual_columns,
+        }
     }
+
+    /// Candidate activation for every cached sample
+    fn candidate_outputs(&self, candidate: &CandidateNeuron<T>) -> Vec<T> {
+        self.inputs
+            .iter()
+            .map(|input| candidate.calculate_output(input))
+            .collect()
+    }
+}
+
+/// Contribution score of every removable hidden neuron, with its mean
+/// activation over `data`
+///
+/// Neurons in a layer with a single regular neuron are skipped, since the
+/// layer has to keep one. [`PruningCriterion::Correlation`] needs data and
+/// falls back to the magnitude without it.
+fn contribution_scores<T: Float>(
+    network: &Network<T>,
+    criterion: PruningCriterion,
+    data: Option<&TrainingData<T>>,
+) -> Vec<(usize, usize, T, Option<T>)> {
+    let hidden = 1..network.layers.len().saturating_sub(1);
+    // Activation of every hidden neuron and the outputs, per sample
+    let (activations, outputs) = match data.filter(|d| !d.inputs.is_empty()) {
+        Some(data) => {
+            let mut network = network.clone();
+            let mut activations: Vec<Vec<Vec<T>>> = network
+                .layers
+                .iter()
+                .map(|layer| vec![Vec::new(); layer.size()])
+                .collect();
+            let mut outputs = Vec::with_capacity(data.inputs.len());
+            for input in &data.inputs {
+                outputs.push(network.run(input));
+                for layer in hidden.clone() {
+                    for (values, neuron) in activations[layer]
+                        .iter_mut()
+                        .zip(&network.layers[layer].neurons)
+                    {
+                        values.push(neuron.value);
+                    }
+                }
+            }
+            (Some(activations), outputs)
+        }
+        None => (None, Vec::new()),
+    };
+
+    let mut scores = Vec::new();
+    for layer in hidden {
+        let current = &network.layers[layer];
+        if current.num_regular_neurons() <= 1 {
+            continue;
+        }
+        for (index, neuron) in current.neurons.iter().enumerate() {
+            if neuron.is_bias {
+                continue;
+            }
+            let values = activations.as_ref().map(|a| &a[layer][index]);
+            let magnitude = network.layers[layer + 1]
+                .neurons
+                .iter()
+                .flat_map(|n| &n.connections)
+                .filter(|c| c.from_neuron == index)
+                .fold(T::zero(), |acc, c| acc + c.weight.abs());
+            let score = match (criterion, values) {
+                (PruningCriterion::Correlation, Some(values)) => (0..network.num_outputs())
+                    .map(|o| {
+                        let column: Vec<T> = outputs.iter().map(|output| output[o]).collect();
+                        abs_correlation(values, &column)
+                    })
+                    .fold(T::zero(), T::max),
+                _ => magnitude,
+            };
+            let mean = values.map(|v| v.iter().fold(T::zero(), |acc, &x| acc + x) / cast(v.len()));
+            scores.push((layer, index, score, mean));
+        }
+    }
+    scores
+}
+
+/// Absolute Pearson correlation; 0 when either series is constant
+fn abs_correlation<T: Float>(x: &[T], y: &[T]) -> T {
+    let n = cast(x.len().max(1));
+    let mean_x = x.iter().fold(T::zero(), |acc, &v| acc + v) / n;
+    let mean_y = y.iter().fold(T::zero(), |acc, &v| acc + v) / n;
+    let (mut covariance, mut var_x, mut var_y) = (T::zero(), T::zero(), T::zero());
+    for (&a, &b) in x.iter().zip(y) {
+        let (dx, dy) = (a - mean_x, b - mean_y);
+        covariance = covariance + dx * dy;
+        var_x = var_x + dx * dx;
+        var_y = var_y + dy * dy;
+    }
+    let denominator = (var_x * var_y).sqrt();
+    if denominator > T::zero() {
+        (covariance / denominator).abs()
+    } else {
+        T::zero()
+    }
+}
+
+/// Remove the weakest hidden neuron while its score is below `threshold`,
+/// rescoring after every removal
+fn prune_hidden_neurons<T: Float>(
+    network: &mut Network<T>,
+    criterion: PruningCriterion,
+    threshold: T,
+    data: Option<&TrainingData<T>>,
+) -> CleanupReport<T> {
+    let mut report = CleanupReport::default();
+    // Original position of every neuron still in each layer
+    let mut positions: Vec<Vec<usize>> = network
+        .layers
+        .iter()
+        .map(|layer| (0..layer.size()).collect())
+        .collect();
+
+    loop {
+        let weakest = contribution_scores(network, criterion, data)
+            .into_iter()
+            .filter(|&(_, _, score, _)| score < threshold)
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+        let Some((layer, neuron, score, mean)) = weakest else {
+            break;
+        };
+        let Ok(connections) = network.remove_hidden_neuron(layer, neuron, mean) else {
+            break;
+        };
+        report.connections_removed += connections;
+        report.removed.push(RemovedNeuron {
+            layer,
+            neuron: positions[layer].remove(neuron),
+            reason: RemovalReason::LowContribution { score },
+        });
+    }
+    report
 }
 
 /// Result of cascade correlation training
@@ -994,9 +1566,43 @@ pub struct CascadeTrainingResult<T: Float> {
     pub final_network: Network<T>,
     pub final_error: T,
     pub hidden_neurons_added: usize,
+    /// Hidden neurons removed by pruning
+    pub neurons_pruned: usize,
     pub training_history: Vec<CascadeTrainingRecord<T>>,
     pub metrics: CascadeMetrics,
     pub convergence_reason: String,
+    pub stop_reason: CascadeStopReason,
+}
+
+/// Why cascade training stopped adding hidden neurons
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CascadeStopReason {
+    /// Output error reached `output_target_error`
+    TargetErrorReached,
+    /// `max_hidden_neurons` neurons were installed
+    MaxHiddenNeurons,
+    /// The best candidate fell short of `min_correlation_improvement`
+    InsufficientCorrelation,
+    /// Validation error did not improve for `validation_patience` installs
+    ValidationPatienceExhausted,
+    /// `max_training_time` elapsed
+    TimeBudgetExceeded,
+    /// The trainer's cancellation token was triggered
+    Cancelled,
+}
+
+impl std::fmt::Display for CascadeStopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            Self::TargetErrorReached => "Target error achieved",
+            Self::MaxHiddenNeurons => "Maximum hidden neurons reached",
+            Self::InsufficientCorrelation => "No candidate reached the minimum correlation",
+            Self::ValidationPatienceExhausted => "Validation error stopped improving",
+            Self::TimeBudgetExceeded => "Training time budget exhausted",
+            Self::Cancelled => "Training was cancelled",
+        };
+        f.write_str(reason)
+    }
 }
 
 /// Cascade correlation builder for easy configuration
@@ -1051,154 +1657,45 @@ impl<T: Float> CascadeBuilder<T> {
         self
     }
 
-    pub fn build(self) -> CascadeConfig<T> {
-        self.config
+    pub fn min_correlation_improvement(mut self, min: T) -> Self {
+        self.config.min_correlation_improvement = min;
+        self
     }
-}
 
-impl<T: Float> Default for CascadeBuilder<T> {
-    fn default() -> Self {
-        Self::new()
+    pub fn validation_patience(mut self, patience: usize) -> Self {
+        self.config.validation_patience = Some(patience);
+        self
     }
-}
-
-// Re-export for convenience
-
-// Parallel implementation with proper Send + Sync bounds
-#[cfg(feature = "parallel")]
-impl<T: Float + Send + Sync> CascadeTrainer<T>
-where
-    T::FromStrRadixErr: Send + Sync,
-{
-    /// Train candidates in parallel
-    fn train_candidates_parallel(
-        &mut self,
-        candidates: &mut [CandidateNeuron<T>],
-    ) -> Result<(), RuvFannError> {
-        use rayon::prelude::*;
-        use std::sync::Arc;
-
-        // Create thread-safe shared data
-        let training_data = Arc::new(self.training_data.clone());
-        let config = Arc::new(self.config.clone());
-        let network = Arc::new(self.network.clone());
-
-        // Parallel training using rayon
-        let results: Vec<Result<(), RuvFannError>> = candidates
-            .par_iter_mut()
-            .map(|candidate| {
-                // Clone Arc references for this thread
-                let local_data = training_data.clone();
-                let local_config = config.clone();
-                let local_network = network.clone();
-
-                // Train candidate with thread-local data
-                self.train_single_candidate_with_data(
-                    candidate,
-                    &*local_data,
-                    &*local_config,
-                    &*local_network,
-                )
-            })
-            .collect();
 
-        // Check for any errors
-        for result in results {
-            result?;
-        }
-
-        Ok(())
+    pub fn max_training_time(mut self, budget: std::time::Duration) -> Self {
+        self.config.max_training_time = Some(budget);
+        self
     }
 
-    /// Train single candidate with provided data (thread-safe)
-    fn train_single_candidate_with_data(
-        &self,
-        candidate: &mut CandidateNeuron<T>,
-        data: &TrainingData<T>,
-        config: &CascadeConfig<T>,
-        network: &Network<T>,
-    ) -> Result<(), RuvFannError> {
-        // Simplified training logic for parallel execution
-        let mut best_correlation = T::zero();
-
-        for _epoch in 0..config.candidate_max_epochs {
-            // Calculate correlation with residual errors
-            let correlation =
-                self.calculate_candidate_correlation_with_data(candidate, data, network)?;
-
-            if correlation > best_correlation {
-                best_correlation = correlation;
-                candidate.correlation = correlation;
-            }
-
-            // Check convergence
-            if correlation >= config.candidate_target_correlation {
-                break;
-            }
-
-            // Update candidate weights (simplified)
-            self.update_candidate_weights_simple(
-                candidate,
-                data,
-                network,
-                config.candidate_learning_rate,
-            )?;
-        }
-
-        Ok(())
+    /// Prune hidden neurons scoring below `threshold` after each
+    /// installation
+    pub fn pruning(mut self, criterion: PruningCriterion, threshold: T) -> Self {
+        self.config.enable_pruning = true;
+        self.config.pruning_criterion = criterion;
+        self.config.pruning_threshold = threshold;
+        self
     }
 
-    /// Calculate correlation with provided data
-    fn calculate_candidate_correlation_with_data(
-        &self,
-        candidate: &CandidateNeuron<T>,
-        data: &TrainingData<T>,
-        _network: &Network<T>,
-    ) -> Result<T, RuvFannError> {
-        // Simplified correlation calculation
-        let mut sum = T::zero();
-        let mut count = 0;
-
-        for (input, _target) in data.inputs.iter().zip(data.outputs.iter()) {
-            // Get candidate output
-            let candidate_output = candidate.calculate_output(input);
-
-            // In real implementation, would calculate residual error
-            // For now, just return a placeholder
-            sum = sum + candidate_output.abs();
-            count += 1;
-        }
-
-        if count == 0 {
-            Ok(T::zero())
-        } else {
-            Ok(sum / T::from(count).unwrap())
-        }
+    pub fn build(self) -> CascadeConfig<T> {
+        self.config
     }
+}
 
-    /// Update candidate weights (simplified for parallel)
-    fn update_candidate_weights_simple(
-        &self,
-        candidate: &mut CandidateNeuron<T>,
-        _data: &TrainingData<T>,
-        _network: &Network<T>,
-        learning_rate: T,
-    ) -> Result<(), RuvFannError> {
-        // Simplified weight update
-        for i in 0..candidate.weights.len() {
-            // In real implementation, would calculate gradients
-            // For now, just apply small random change
-            let delta = T::from(0.01).unwrap() * learning_rate;
-            candidate.weights[i] = candidate.weights[i] - delta;
-        }
-
-        Ok(())
+impl<T: Float> Default for CascadeBuilder<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tests::fixtures::xor;
     use crate::NetworkBuilder;
 
     #[test]
@@ -1263,4 +1760,354 @@ mod tests {
         let correlation = trainer.pearson_correlation(&x, &y).unwrap();
         assert!((correlation - 1.0).abs() < 1e-6); // Perfect positive correlation
     }
+
+    #[test]
+    fn test_batched_candidate_evaluation() {
+        let network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+
+        let training_data = xor();
+
+        let config = CascadeConfig {
+            num_candidates: 4,
+            random_seed: Some(7),
+            ..CascadeConfig::default()
+        };
+        let mut trainer = CascadeTrainer::new(config, network, training_data.clone()).unwrap();
+
+        let candidates = trainer.generate_candidates().unwrap();
+        let residuals = trainer.calculate_residuals().unwrap();
+        let cache = trainer.build_candidate_cache().unwrap();
+        let scores = trainer.evaluate_candidates(&candidates, &cache).unwrap();
+
+        assert_eq!(scores.len(), candidates.len());
+        for (candidate, score) in candidates.iter().zip(scores) {
+            let outputs: Vec<f32> = training_data
+                .inputs
+                .iter()
+                .map(|input| candidate.calculate_output(input))
+                .collect();
+            let column: Vec<f32> = residuals.iter().map(|r| r[0]).collect();
+            let expected = trainer
+                .pearson_correlation(&outputs, &column)
+                .unwrap()
+                .abs();
+            assert!((score - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_candidate_training_increases_correlation() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+
+        let training_data = TrainingData {
+            inputs: vec![
+                vec![0.0, 0.0],
+                vec![0.0, 1.0],
+                vec![1.0, 0.0],
+                vec![1.0, 1.0],
+            ],
+            outputs: vec![vec![0.0], vec![1.0], vec![1.0], vec![1.0]],
+        };
+
+        let config = CascadeConfig {
+            num_candidates: 1,
+            candidate_activations: vec![ActivationFunction::Sigmoid],
+            candidate_learning_rate: 0.5,
+            random_seed: Some(3),
+            ..CascadeConfig::default()
+        };
+        let mut trainer = CascadeTrainer::new(config, network, training_data).unwrap();
+
+        let forward_passes = trainer.metrics.total_forward_passes;
+        let cache = trainer.build_candidate_cache().unwrap();
+        assert_eq!(trainer.metrics.total_forward_passes, forward_passes + 4);
+
+        let mut candidates = trainer.generate_candidates().unwrap();
+        let before = trainer.evaluate_candidates(&candidates, &cache).unwrap()[0];
+        for _ in 0..200 {
+            trainer.train_candidates_epoch(&mut candidates, &[true], &cache);
+        }
+        let after = trainer.evaluate_candidates(&candidates, &cache).unwrap()[0];
+
+        // Training reuses the cache: no further forward passes through the network
+        assert_eq!(trainer.metrics.total_forward_passes, forward_passes + 4);
+        assert!(after > before);
+    }
+
+    fn xor_trainer(config: CascadeConfig<f32>) -> CascadeTrainer<f32> {
+        let network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        let training_data = xor();
+        CascadeTrainer::new(config, network, training_data).unwrap()
+    }
+
+    #[test]
+    fn test_stop_reasons() {
+        let base = CascadeBuilder::new()
+            .num_candidates(2)
+            .target_error(0.0)
+            .random_seed(1)
+            .build();
+
+        let config = CascadeConfig {
+            max_hidden_neurons: 2,
+            output_max_epochs: 5,
+            candidate_max_epochs: 5,
+            min_correlation_improvement: 0.0,
+            ..base.clone()
+        };
+        let result = xor_trainer(config).train().unwrap();
+        assert_eq!(result.stop_reason, CascadeStopReason::MaxHiddenNeurons);
+        assert_eq!(result.hidden_neurons_added, 2);
+
+        let config = CascadeConfig {
+            max_training_time: Some(std::time::Duration::ZERO),
+            output_max_epochs: 5,
+            ..base.clone()
+        };
+        let result = xor_trainer(config).train().unwrap();
+        assert_eq!(result.stop_reason, CascadeStopReason::TimeBudgetExceeded);
+        assert_eq!(result.hidden_neurons_added, 0);
+
+        let config = CascadeConfig {
+            min_correlation_improvement: f32::INFINITY,
+            output_max_epochs: 5,
+            candidate_max_epochs: 5,
+            ..base.clone()
+        };
+        let result = xor_trainer(config).train().unwrap();
+        assert_eq!(
+            result.stop_reason,
+            CascadeStopReason::InsufficientCorrelation
+        );
+        assert_eq!(result.convergence_reason, result.stop_reason.to_string());
+    }
+
+    /// Network with one hidden layer whose neuron 1 barely feeds the output
+    fn network_with_weak_neuron() -> Network<f32> {
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        for (i, connection) in network.layers[2].neurons[0]
+            .connections
+            .iter_mut()
+            .enumerate()
+        {
+            connection.weight = if connection.from_neuron == 1 {
+                0.001
+            } else {
+                0.5 + i as f32 * 0.1
+            };
+        }
+        network
+    }
+
+    #[test]
+    fn test_prune_removes_weak_neurons() {
+        let mut cascade = CascadeNetwork::new(network_with_weak_neuron(), CascadeConfig::default());
+        let report = cascade.prune(0.01);
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!((report.removed[0].layer, report.removed[0].neuron), (1, 1));
+        assert!(matches!(
+            report.removed[0].reason,
+            RemovalReason::LowContribution { .. }
+        ));
+        assert_eq!(cascade.network.layers[1].num_regular_neurons(), 2);
+        assert_eq!(cascade.state.hidden_neurons_pruned, 1);
+        assert!(cascade.network.validate_topology().is_ok());
+
+        // A layer always keeps one regular neuron
+        let report = cascade.prune(f32::INFINITY);
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(cascade.network.layers[1].num_regular_neurons(), 1);
+
+        // Folding the mean activation keeps a removed neuron's average effect
+        let data = xor();
+        let mut network = network_with_weak_neuron();
+        let before: Vec<f32> = data.inputs.iter().map(|i| network.run(i)[0]).collect();
+        let mut cascade = CascadeNetwork::new(
+            network,
+            CascadeBuilder::new()
+                .pruning(PruningCriterion::Magnitude, 0.01)
+                .build(),
+        );
+        assert_eq!(cascade.prune_with_data(&data, 0.01).removed.len(), 1);
+        for (input, before) in data.inputs.iter().zip(before) {
+            assert!((cascade.network.run(input)[0] - before).abs() < 1e-3);
+        }
+
+        // After each installation the trainer prunes and reports the count
+        let config = CascadeConfig {
+            max_hidden_neurons: 1,
+            output_max_epochs: 5,
+            candidate_max_epochs: 5,
+            min_correlation_improvement: 0.0,
+            ..CascadeBuilder::new()
+                .num_candidates(2)
+                .target_error(0.0)
+                .random_seed(1)
+                .pruning(PruningCriterion::Magnitude, 0.01)
+                .build()
+        };
+        let mut trainer = CascadeTrainer::new(config, network_with_weak_neuron(), data).unwrap();
+        let result = trainer.train().unwrap();
+        assert_eq!(result.neurons_pruned, 1);
+        assert_eq!(result.final_network.layers[1].num_regular_neurons(), 2);
+        assert_eq!(trainer.checkpoint().neurons_pruned, 1);
+    }
+
+    #[test]
+    fn test_cancellation_returns_partial_result() {
+        let config = CascadeConfig {
+            max_hidden_neurons: 5,
+            output_max_epochs: 5,
+            candidate_max_epochs: 5,
+            min_correlation_improvement: 0.0,
+            ..CascadeBuilder::new()
+                .num_candidates(2)
+                .target_error(0.0)
+                .random_seed(1)
+                .build()
+        };
+        let mut trainer = xor_trainer(config);
+        let token = CancellationToken::new();
+        trainer.set_cancellation_token(token.clone());
+        trainer.set_checkpoint_callback(Box::new(move |_| {
+            token.cancel();
+            Ok(())
+        }));
+
+        let result = trainer.train().unwrap();
+        assert_eq!(result.stop_reason, CascadeStopReason::Cancelled);
+        assert_eq!(result.hidden_neurons_added, 1);
+    }
+
+    #[test]
+    fn test_validation_patience_stops_growth() {
+        let config = CascadeConfig {
+            max_hidden_neurons: 10,
+            num_candidates: 2,
+            output_max_epochs: 5,
+            candidate_max_epochs: 5,
+            output_target_error: 0.0,
+            min_correlation_improvement: 0.0,
+            validation_patience: Some(1),
+            random_seed: Some(1),
+            ..CascadeConfig::default()
+        };
+        let validation = TrainingData {
+            inputs: vec![vec![0.5, 0.5]],
+            outputs: vec![vec![1.0]],
+        };
+        let result = xor_trainer(config.clone())
+            .with_validation_data(validation.clone())
+            .unwrap()
+            .train()
+            .unwrap();
+
+        // Output weights are never changed, so the validation error cannot improve
+        // after the first install
+        assert_eq!(
+            result.stop_reason,
+            CascadeStopReason::ValidationPatienceExhausted
+        );
+        assert_eq!(result.hidden_neurons_added, 2);
+
+        // A resumed run keeps the validation data and stops for the same reason
+        let trainer = xor_trainer(config);
+        let resumed = CascadeTrainer::from_checkpoint(
+            trainer.checkpoint(),
+            trainer.training_data.clone(),
+            Some(validation),
+        )
+        .unwrap()
+        .train()
+        .unwrap();
+        assert_eq!(
+            resumed.stop_reason,
+            CascadeStopReason::ValidationPatienceExhausted
+        );
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint_matches_uninterrupted_run() {
+        use std::sync::{Arc, Mutex};
+
+        // Unseeded runs draw their stream from entropy, which the checkpoint
+        // records as well
+        for random_seed in [Some(11), None] {
+            let config = CascadeConfig {
+                max_hidden_neurons: 3,
+                num_candidates: 3,
+                output_max_epochs: 5,
+                candidate_max_epochs: 5,
+                output_target_error: 0.0,
+                min_correlation_improvement: 0.0,
+                random_seed,
+                ..CascadeConfig::default()
+            };
+
+            let checkpoints = Arc::new(Mutex::new(Vec::new()));
+            let mut trainer = xor_trainer(config.clone());
+            let sink = checkpoints.clone();
+            trainer.set_checkpoint_callback(Box::new(move |checkpoint| {
+                sink.lock().unwrap().push(checkpoint.clone());
+                Ok(())
+            }));
+            let full = trainer.train().unwrap();
+            assert_eq!(checkpoints.lock().unwrap().len(), 3);
+
+            let first = checkpoints.lock().unwrap()[0].clone();
+            assert_eq!(first.hidden_count, 1);
+            let data = xor_trainer(config).training_data;
+            let resumed = CascadeTrainer::from_checkpoint(first, data, None)
+                .unwrap()
+                .train()
+                .unwrap();
+
+            assert_eq!(resumed.hidden_neurons_added, full.hidden_neurons_added);
+            assert_eq!(resumed.training_history.len(), full.training_history.len());
+            for (a, b) in resumed.training_history.iter().zip(&full.training_history) {
+                assert_eq!(a.selected_activation, b.selected_activation);
+                assert_eq!(a.best_candidate_correlation, b.best_candidate_correlation);
+            }
+            assert_eq!(
+                resumed.final_network.get_weights(),
+                full.final_network.get_weights()
+            );
+        }
+    }
+
+    #[cfg(all(feature = "binary", feature = "serde"))]
+    #[test]
+    fn test_checkpoint_file_roundtrip() {
+        let trainer = xor_trainer(CascadeConfig {
+            random_seed: Some(5),
+            ..CascadeConfig::default()
+        });
+        let checkpoint = trainer.checkpoint();
+
+        let path =
+            std::env::temp_dir().join(format!("cascade_checkpoint_{}.bin", std::process::id()));
+        checkpoint.save(&path).unwrap();
+        let loaded = CascadeCheckpoint::<f32>::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.rng_seed, checkpoint.rng_seed);
+        assert_eq!(loaded.hidden_count, checkpoint.hidden_count);
+        assert_eq!(
+            loaded.network.get_weights(),
+            checkpoint.network.get_weights()
+        );
+    }
 }
@@ -0,0 +1,297 @@
+//! Nearest-centroid decoding for embedding networks
+//!
+//! Networks trained with a metric loss output embeddings rather than class
+//! scores. A [`CentroidIndex`] stores one centroid per class, computed from
+//! the embeddings of labeled examples, and decodes a new embedding to the
+//! class with the nearest centroid under cosine or Euclidean distance. New
+//! classes can be enrolled from a few examples without retraining.
+//!
+//! [`EmbeddingModel`] bundles the network with its index so both are
+//! serialized together.
+
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+use thiserror::Error;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Errors raised while building or querying a centroid index
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum EmbeddingError {
+    #[error("No embeddings provided")]
+    Empty,
+
+    #[error("{embeddings} embeddings but {labels} labels")]
+    LabelCountMismatch { embeddings: usize, labels: usize },
+
+    #[error("Embedding has {actual} dimensions, index expects {expected}")]
+    DimensionMismatch { expected: usize, actual: usize },
+}
+
+/// Distance used to compare embeddings with centroids
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Distance {
+    /// `1 - cos(a, b)`; embeddings are L2-normalized before averaging
+    #[default]
+    Cosine,
+    /// Euclidean distance
+    Euclidean,
+}
+
+impl Distance {
+    fn prepare<T: Float>(&self, embedding: &[T]) -> Vec<T> {
+        match self {
+            Distance::Cosine => {
+                let norm = embedding
+                    .iter()
+                    .fold(T::zero(), |acc, &v| acc + v * v)
+                    .sqrt();
+                if norm > T::zero() {
+                    embedding.iter().map(|&v| v / norm).collect()
+                } else {
+                    embedding.to_vec()
+                }
+            }
+            Distance::Euclidean => embedding.to_vec(),
+        }
+    }
+
+    /// Distance between an embedding prepared by [`Self::prepare`] and a
+    /// centroid
+    fn between<T: Float>(&self, prepared: &[T], centroid: &[T]) -> T {
+        match self {
+            Distance::Cosine => {
+                let dot = prepared
+                    .iter()
+                    .zip(centroid)
+                    .fold(T::zero(), |acc, (&a, &b)| acc + a * b);
+                let norm = centroid
+                    .iter()
+                    .fold(T::zero(), |acc, &v| acc + v * v)
+                    .sqrt();
+                if norm > T::zero() {
+                    T::one() - dot / norm
+                } else {
+                    T::one()
+                }
+            }
+            Distance::Euclidean => prepared
+                .iter()
+                .zip(centroid)
+                .fold(T::zero(), |acc, (&a, &b)| acc + (a - b) * (a - b))
+                .sqrt(),
+        }
+    }
+}
+
+/// Class centroids in embedding space
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CentroidIndex<T: Float> {
+    distance: Distance,
+    labels: Vec<String>,
+    centroids: Vec<Vec<T>>,
+    /// Examples averaged into each centroid, so classes can be extended
+    counts: Vec<usize>,
+}
+
+impl<T: Float> CentroidIndex<T> {
+    pub fn new(distance: Distance) -> Self {
+        Self {
+            distance,
+            labels: Vec::new(),
+            centroids: Vec::new(),
+            counts: Vec::new(),
+        }
+    }
+
+    /// Index with one centroid per distinct label of `labels`
+    pub fn fit(
+        distance: Distance,
+        embeddings: &[Vec<T>],
+        labels: &[impl AsRef<str>],
+    ) -> Result<Self, EmbeddingError> {
+        if embeddings.is_empty() {
+            return Err(EmbeddingError::Empty);
+        }
+        if embeddings.len() != labels.len() {
+            return Err(EmbeddingError::LabelCountMismatch {
+                embeddings: embeddings.len(),
+                labels: labels.len(),
+            });
+        }
+        let mut index = Self::new(distance);
+        for (embedding, label) in embeddings.iter().zip(labels) {
+            index.add(label.as_ref(), embedding)?;
+        }
+        Ok(index)
+    }
+
+    /// Fold one labeled example into its class centroid, enrolling the class
+    /// if it is new
+    pub fn add(&mut self, label: &str, embedding: &[T]) -> Result<(), EmbeddingError> {
+        if embedding.is_empty() {
+            return Err(EmbeddingError::Empty);
+        }
+        if let Some(expected) = self.dimension().filter(|&d| d != embedding.len()) {
+            return Err(EmbeddingError::DimensionMismatch {
+                expected,
+                actual: embedding.len(),
+            });
+        }
+        let prepared = self.distance.prepare(embedding);
+        match self.labels.iter().position(|l| l == label) {
+            Some(class) => {
+                self.counts[class] += 1;
+                let n = cast(self.counts[class]);
+                for (c, &v) in self.centroids[class].iter_mut().zip(&prepared) {
+                    *c = *c + (v - *c) / n;
+                }
+            }
+            None => {
+                self.labels.push(label.to_string());
+                self.centroids.push(prepared);
+                self.counts.push(1);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn distance(&self) -> Distance {
+        self.distance
+    }
+
+    /// Class labels, in centroid order
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    pub fn centroid(&self, label: &str) -> Option<&[T]> {
+        let class = self.labels.iter().position(|l| l == label)?;
+        Some(&self.centroids[class])
+    }
+
+    /// Embedding width, once a class has been added
+    pub fn dimension(&self) -> Option<usize> {
+        self.centroids.first().map(Vec::len)
+    }
+
+    /// The `k` nearest classes to `embedding` with their distances, nearest
+    /// first
+    pub fn nearest(&self, embedding: &[T], k: usize) -> Result<Vec<(&str, T)>, EmbeddingError> {
+        let expected = self.dimension().ok_or(EmbeddingError::Empty)?;
+        if embedding.len() != expected {
+            return Err(EmbeddingError::DimensionMismatch {
+                expected,
+                actual: embedding.len(),
+            });
+        }
+        let prepared = self.distance.prepare(embedding);
+        let mut ranked: Vec<(&str, T)> = self
+            .labels
+            .iter()
+            .zip(&self.centroids)
+            .map(|(label, centroid)| (label.as_str(), self.distance.between(&prepared, centroid)))
+            .collect();
+        ranked.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        ranked.truncate(k);
+        Ok(ranked)
+    }
+
+    /// Label of the nearest centroid
+    pub fn decode(&self, embedding: &[T]) -> Result<&str, EmbeddingError> {
+        let nearest = self.nearest(embedding, 1)?;
+        Ok(nearest[0].0)
+    }
+}
+
+/// Embedding network together with the index that decodes its outputs
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EmbeddingModel<T: Float> {
+    pub network: Network<T>,
+    pub index: CentroidIndex<T>,
+}
+
+impl<T: Float> EmbeddingModel<T> {
+    /// Build the index from the network's embeddings of labeled inputs
+    pub fn fit(
+        mut network: Network<T>,
+        distance: Distance,
+        inputs: &[Vec<T>],
+        labels: &[impl AsRef<str>],
+    ) -> Result<Self, EmbeddingError> {
+        let embeddings = network.run_batch(inputs);
+        let index = CentroidIndex::fit(distance, &embeddings, labels)?;
+        Ok(Self { network, index })
+    }
+
+    /// Class of `input`
+    pub fn predict(&mut self, input: &[T]) -> Result<&str, EmbeddingError> {
+        let embedding = self.network.run(input);
+        self.index.decode(&embedding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_and_enroll() {
+        let embeddings = vec![
+            vec![1.0, 0.1],
+            vec![0.9, -0.1],
+            vec![0.0, 1.0],
+            vec![0.2, 2.0],
+        ];
+        let labels = ["east", "east", "north", "north"];
+        let cosine = CentroidIndex::fit(Distance::Cosine, &embeddings, &labels).unwrap();
+        assert_eq!(cosine.labels(), ["east", "north"]);
+        // Cosine ignores magnitude, Euclidean does not
+        assert_eq!(cosine.decode(&[10.0, 0.5]).unwrap(), "east");
+        let euclidean = CentroidIndex::fit(Distance::Euclidean, &embeddings, &labels).unwrap();
+        assert_eq!(euclidean.centroid("north").unwrap(), [0.1, 1.5]);
+        assert_eq!(euclidean.decode(&[0.4, 1.0]).unwrap(), "north");
+
+        let mut index = euclidean.clone();
+        index.add("west", &[-1.0, 0.0]).unwrap();
+        let nearest = index.nearest(&[-0.8, 0.1], 2).unwrap();
+        assert_eq!(nearest[0].0, "west");
+        assert!(nearest[0].1 < nearest[1].1);
+
+        assert_eq!(
+            index.decode(&[1.0]),
+            Err(EmbeddingError::DimensionMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert!(CentroidIndex::<f64>::new(Distance::Cosine)
+            .decode(&[1.0])
+            .is_err());
+        assert!(CentroidIndex::fit(Distance::Cosine, &embeddings, &["a"]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_model_serializes_with_index() {
+        let network = crate::NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .output_layer(3)
+            .build();
+        let inputs = vec![vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]];
+        let mut model =
+            EmbeddingModel::fit(network, Distance::Cosine, &inputs, &["a", "b", "c"]).unwrap();
+        let predicted = model.predict(&inputs[1]).unwrap().to_string();
+
+        let json = serde_json::to_string(&model).unwrap();
+        let mut restored: EmbeddingModel<f64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.index, model.index);
+        assert_eq!(restored.predict(&inputs[1]).unwrap(), predicted);
+    }
+}
@@ -0,0 +1,274 @@
+//! Combining multiple networks
+//!
+//! Provides federated averaging (FedAvg) of compatible networks together with
+//! weight delta extraction and application, which is enough to build simple
+//! federated or periodic-averaging distributed training loops.
+
+use crate::numeric::cast;
+use crate::{Network, NetworkError};
+use num_traits::Float;
+use thiserror::Error;
+
+/// Errors raised when combining networks
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum EnsembleError {
+    #[error("No networks provided")]
+    Empty,
+
+    #[error("Network {index} is incompatible with network 0: {reason}")]
+    IncompatibleNetworks { index: usize, reason: String },
+
+    #[error("Expected {expected} averaging weights, got {actual}")]
+    WeightCountMismatch { expected: usize, actual: usize },
+
+    #[error("Averaging weights must be non-negative and sum to a positive value")]
+    InvalidWeights,
+
+    #[error("Delta has {actual} entries, network has {expected} weights")]
+    DeltaSizeMismatch { expected: usize, actual: usize },
+
+    #[error("Network error: {0}")]
+    Network(#[from] NetworkError),
+}
+
+/// Difference between two parameter sets of the same topology
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightDelta<T: Float> {
+    /// Per-connection weight changes, in [`Network::get_weights`] order
+    pub values: Vec<T>,
+}
+
+impl<T: Float> WeightDelta<T> {
+    /// Euclidean norm of the delta
+    pub fn l2_norm(&self) -> T {
+        self.values
+            .iter()
+            .fold(T::zero(), |acc, &v| acc + v * v)
+            .sqrt()
+    }
+
+    /// Scale the delta in place
+    pub fn scale(&mut self, factor: T) {
+        for v in &mut self.values {
+            *v = *v * factor;
+        }
+    }
+}
+
+/// Weighted average of the parameters of compatible networks
+///
+/// `weights` gives each network's contribution (for FedAvg, typically the
+/// number of local training samples); pass `None` for a plain mean. All
+/// networks must share the topology of the first one; the result is a copy
+/// of the first network with the averaged weights.
+pub fn fed_avg<T: Float>(
+    networks: &[&Network<T>],
+    weights: Option<&[T]>,
+) -> Result<Network<T>, EnsembleError> {
+    let first = *networks.first().ok_or(EnsembleError::Empty)?;
+
+    for (index, network) in networks.iter().enumerate().skip(1) {
+        check_compatible(first, network, index)?;
+    }
+
+    let coefficients = normalized_coefficients(weights, networks.len())?;
+
+    let mut averaged = vec![T::zero(); first.total_connections()];
+    for (network, &coefficient) in networks.iter().zip(&coefficients) {
+        for (acc, w) in averaged.iter_mut().zip(network.get_weights()) {
+            *acc = *acc + coefficient * w;
+        }
+    }
+
+    let mut result = first.clone();
+    result.set_weights(&averaged)?;
+    Ok(result)
+}
+
+/// Extract the weight change from `base` to `updated`
+pub fn weight_delta<T: Float>(
+    base: &Network<T>,
+    updated: &Network<T>,
+) -> Result<WeightDelta<T>, EnsembleError> {
+    check_compatible(base, updated, 1)?;
+
+    let values = updated
+        .get_weights()
+        .into_iter()
+        .zip(base.get_weights())
+        .map(|(u, b)| u - b)
+        .collect();
+    Ok(WeightDelta { values })
+}
+
+/// Add `scale * delta` to the network's weights
+pub fn apply_delta<T: Float>(
+    network: &mut Network<T>,
+    delta: &WeightDelta<T>,
+    scale: T,
+) -> Result<(), EnsembleError> {
+    let expected = network.total_connections();
+    if delta.values.len() != expected {
+        return Err(EnsembleError::DeltaSizeMismatch {
+            expected,
+            actual: delta.values.len(),
+        });
+    }
+
+    let weights: Vec<T> = network
+        .get_weights()
+        .into_iter()
+        .zip(&delta.values)
+        .map(|(w, &d)| w + scale * d)
+        .collect();
+    network.set_weights(&weights)?;
+    Ok(())
+}
+
+/// Weighted mean of several deltas (e.g. client updates in one FedAvg round)
+pub fn average_deltas<T: Float>(
+    deltas: &[WeightDelta<T>],
+    weights: Option<&[T]>,
+) -> Result<WeightDelta<T>, EnsembleError> {
+    let first = deltas.first().ok_or(EnsembleError::Empty)?;
+    if let Some((index, delta)) = deltas
+        .iter()
+        .enumerate()
+        .find(|(_, d)| d.values.len() != first.values.len())
+    {
+        return Err(EnsembleError::IncompatibleNetworks {
+            index,
+            reason: format!(
+                "delta has {} entries instead of {}",
+                delta.values.len(),
+                first.values.len()
+            ),
+        });
+    }
+
+    let coefficients = normalized_coefficients(weights, deltas.len())?;
+
+    let mut values = vec![T::zero(); first.values.len()];
+    for (delta, &coefficient) in deltas.iter().zip(&coefficients) {
+        for (acc, &v) in values.iter_mut().zip(&delta.values) {
+            *acc = *acc + coefficient * v;
+        }
+    }
+    Ok(WeightDelta { values })
+}
+
+/// Normalise averaging weights to sum to one (uniform when `None`)
+fn normalized_coefficients<T: Float>(
+    weights: Option<&[T]>,
+    count: usize,
+) -> Result<Vec<T>, EnsembleError> {
+    let Some(weights) = weights else {
+        return Ok(vec![T::one() / cast(count); count]);
+    };
+
+    if weights.len() != count {
+        return Err(EnsembleError::WeightCountMismatch {
+            expected: count,
+            actual: weights.len(),
+        });
+    }
+
+    let total = weights.iter().fold(T::zero(), |acc, &w| acc + w);
+    if weights.iter().any(|&w| w < T::zero() || !w.is_finite()) || total <= T::zero() {
+        return Err(EnsembleError::InvalidWeights);
+    }
+    Ok(weights.iter().map(|&w| w / total).collect())
+}
+
+fn check_compatible<T: Float>(
+    reference: &Network<T>,
+    other: &Network<T>,
+    index: usize,
+) -> Result<(), EnsembleError> {
+    let diff = reference.diff(other);
+    match diff.topology.first() {
+        None => Ok(()),
+        Some(difference) => Err(EnsembleError::IncompatibleNetworks {
+            index,
+            reason: difference.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    fn network_with_weights(value: f64) -> Network<f64> {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(2)
+            .output_layer(1)
+            .build();
+        let weights = vec![value; network.total_connections()];
+        network.set_weights(&weights).unwrap();
+        network
+    }
+
+    #[test]
+    fn test_fed_avg_weighted() {
+        let a = network_with_weights(1.0);
+        let b = network_with_weights(4.0);
+
+        let mean = fed_avg(&[&a, &b], None).unwrap();
+        assert!(mean.get_weights().iter().all(|&w| (w - 2.5).abs() < 1e-12));
+
+        let weighted = fed_avg(&[&a, &b], Some(&[2.0, 1.0])).unwrap();
+        assert!(weighted
+            .get_weights()
+            .iter()
+            .all(|&w| (w - 2.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_fed_avg_validation() {
+        let a = network_with_weights(1.0);
+        let other = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+
+        assert_eq!(fed_avg::<f64>(&[], None).unwrap_err(), EnsembleError::Empty);
+        assert!(matches!(
+            fed_avg(&[&a, &other], None),
+            Err(EnsembleError::IncompatibleNetworks { index: 1, .. })
+        ));
+        assert_eq!(
+            fed_avg(&[&a, &a], Some(&[1.0])).unwrap_err(),
+            EnsembleError::WeightCountMismatch {
+                expected: 2,
+                actual: 1
+            }
+        );
+        assert_eq!(
+            fed_avg(&[&a, &a], Some(&[0.0, 0.0])).unwrap_err(),
+            EnsembleError::InvalidWeights
+        );
+    }
+
+    #[test]
+    fn test_delta_roundtrip() {
+        let base = network_with_weights(1.0);
+        let client_a = network_with_weights(2.0);
+        let client_b = network_with_weights(4.0);
+
+        let deltas = vec![
+            weight_delta(&base, &client_a).unwrap(),
+            weight_delta(&base, &client_b).unwrap(),
+        ];
+        let mean_delta = average_deltas(&deltas, None).unwrap();
+
+        let mut global = base.clone();
+        apply_delta(&mut global, &mean_delta, 1.0).unwrap();
+
+        let expected = fed_avg(&[&client_a, &client_b], None).unwrap();
+        assert!(global.diff(&expected).is_close(1e-12));
+    }
+}
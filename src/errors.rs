@@ -269,6 +269,8 @@ pub enum RecoveryStrategy {
     Abort,
     /// Use fallback implementation
     Fallback(String),
+    /// Restore the latest valid checkpoint and continue from there
+    CheckpointAndContinue,
 }
 
 /// Error recovery context
@@ -458,6 +460,35 @@ impl From<NetworkError> for RuvFannError {
                 message: "Network has no layers".to_string(),
                 context: None,
             },
+            NetworkError::Recurrent => RuvFannError::Network {
+                category: NetworkErrorCategory::Topology,
+                message: error.to_string(),
+                context: None,
+            },
+            NetworkError::NeuronIndexOutOfRange { .. }
+            | NetworkError::ConnectionNotFound { .. } => RuvFannError::Network {
+                category: NetworkErrorCategory::Connections,
+                message: error.to_string(),
+                context: None,
+            },
+            NetworkError::SchemaMismatch { .. }
+            | NetworkError::DuplicateName(_)
+            | NetworkError::MissingInput(_)
+            | NetworkError::UnknownInput(_) => RuvFannError::Network {
+                category: NetworkErrorCategory::Layers,
+                message: error.to_string(),
+                context: None,
+            },
+            NetworkError::EmptyCalibration => RuvFannError::Validation {
+                category: ValidationErrorCategory::InputData,
+                message: error.to_string(),
+                details: vec![],
+            },
+            NetworkError::UnsupportedFormatVersion(_) => RuvFannError::Io {
+                category: IoErrorCategory::Format,
+                message: error.to_string(),
+                source: None,
+            },
         }
     }
 }
@@ -484,6 +515,16 @@ impl From<TrainingError> for RuvFannError {
     }
 }
 
+impl From<std::io::Error> for RuvFannError {
+    fn from(error: std::io::Error) -> Self {
+        RuvFannError::Io {
+            category: IoErrorCategory::FileAccess,
+            message: error.to_string(),
+            source: Some(Box::new(error)),
+        }
+    }
+}
+
 /// Helper macros for error creation with context
 #[macro_export]
 macro_rules! network_error {
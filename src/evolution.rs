@@ -0,0 +1,480 @@
+//! Neuroevolution over the flat weight representation
+//!
+//! Networks are evolved as genomes holding their weights in
+//! [`Network::get_weights`] order. Crossover and mutation operators are
+//! pluggable, and fitness is supplied by the caller, so the same operators can
+//! be used with [`NeuroEvolution`] or in a custom evolutionary loop.
+
+use crate::numeric::cast;
+use crate::training::TrainingError;
+use crate::Network;
+use num_traits::Float;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_distr::{Distribution, Normal};
+
+/// Combines two parent genomes into two offspring
+pub trait CrossoverOperator<T: Float>: Send + Sync {
+    fn crossover(&self, a: &[T], b: &[T], rng: &mut dyn RngCore) -> (Vec<T>, Vec<T>);
+}
+
+/// Mutates a genome in place
+pub trait MutationOperator<T: Float>: Send + Sync {
+    fn mutate(&self, genome: &mut [T], rng: &mut dyn RngCore);
+}
+
+/// Scores a candidate network; higher is better
+pub trait FitnessFunction<T: Float> {
+    fn fitness(&mut self, network: &mut Network<T>) -> T;
+}
+
+impl<T: Float, F: FnMut(&mut Network<T>) -> T> FitnessFunction<T> for F {
+    fn fitness(&mut self, network: &mut Network<T>) -> T {
+        self(network)
+    }
+}
+
+/// `true` with `probability`; values outside `[0, 1]` (and NaN) act as the
+/// nearest bound instead of panicking like `Rng::gen_bool`
+fn chance(rng: &mut dyn RngCore, probability: f64) -> bool {
+    rng.gen::<f64>() < probability
+}
+
+/// Swaps each gene between the parents with a fixed probability
+#[derive(Debug, Clone, Copy)]
+pub struct UniformCrossover {
+    pub swap_probability: f64,
+}
+
+impl Default for UniformCrossover {
+    fn default() -> Self {
+        Self {
+            swap_probability: 0.5,
+        }
+    }
+}
+
+impl<T: Float> CrossoverOperator<T> for UniformCrossover {
+    fn crossover(&self, a: &[T], b: &[T], rng: &mut dyn RngCore) -> (Vec<T>, Vec<T>) {
+        let mut first = a.to_vec();
+        let mut second = b.to_vec();
+        for (x, y) in first.iter_mut().zip(second.iter_mut()) {
+            if chance(rng, self.swap_probability) {
+                std::mem::swap(x, y);
+            }
+        }
+        (first, second)
+    }
+}
+
+/// Exchanges the gene tails after one random cut point
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SinglePointCrossover;
+
+impl<T: Float> CrossoverOperator<T> for SinglePointCrossover {
+    fn crossover(&self, a: &[T], b: &[T], rng: &mut dyn RngCore) -> (Vec<T>, Vec<T>) {
+        let len = a.len().min(b.len());
+        let mut first = a.to_vec();
+        let mut second = b.to_vec();
+        if len > 1 {
+            let point = rng.gen_range(1..len);
+            first[point..len].copy_from_slice(&b[point..len]);
+            second[point..len].copy_from_slice(&a[point..len]);
+        }
+        (first, second)
+    }
+}
+
+/// Adds zero-mean Gaussian noise to each gene with probability `rate`
+#[derive(Debug, Clone, Copy)]
+pub struct GaussianMutation {
+    pub rate: f64,
+    pub sigma: f64,
+}
+
+impl Default for GaussianMutation {
+    fn default() -> Self {
+        Self {
+            rate: 0.1,
+            sigma: 0.1,
+        }
+    }
+}
+
+impl<T: Float> MutationOperator<T> for GaussianMutation {
+    fn mutate(&self, genome: &mut [T], rng: &mut dyn RngCore) {
+        let Ok(normal) = Normal::new(0.0, self.sigma) else {
+            return;
+        };
+        for gene in genome.iter_mut() {
+            if chance(rng, self.rate) {
+                *gene = *gene + cast(normal.sample(rng));
+            }
+        }
+    }
+}
+
+/// Configuration for [`NeuroEvolution`]
+///
+/// [`NeuroEvolution::new`] rejects an empty population, a crossover rate
+/// outside `[0, 1]` and an empty or non-finite initial weight range.
+#[derive(Debug, Clone)]
+pub struct EvolutionConfig {
+    /// Number of genomes per generation
+    pub population_size: usize,
+    /// Best genomes copied unchanged into the next generation
+    pub elitism: usize,
+    /// Genomes competing in each tournament selection
+    pub tournament_size: usize,
+    /// Probability that selected parents are recombined
+    pub crossover_rate: f64,
+    /// Range of the initial random weights
+    pub initial_weight_range: (f64, f64),
+    /// Random seed for reproducible runs
+    pub random_seed: Option<u64>,
+}
+
+impl EvolutionConfig {
+    fn validate(&self) -> Result<(), TrainingError> {
+        let invalid = |message: String| Err(TrainingError::InvalidData(message));
+        if self.population_size == 0 {
+            return invalid("population_size must be at least 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.crossover_rate) {
+            return invalid(format!(
+                "crossover_rate {} is not a probability",
+                self.crossover_rate
+            ));
+        }
+        let (min, max) = self.initial_weight_range;
+        if !(min.is_finite() && max.is_finite() && min <= max) {
+            return invalid(format!(
+                "initial_weight_range ({min}, {max}) is not a range"
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for EvolutionConfig {
+    fn default() -> Self {
+        Self {
+            population_size: 50,
+            elitism: 2,
+            tournament_size: 3,
+            crossover_rate: 0.9,
+            initial_weight_range: (-1.0, 1.0),
+            random_seed: None,
+        }
+    }
+}
+
+/// Summary of one generation
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationStats<T: Float> {
+    pub generation: usize,
+    pub best_fitness: T,
+    pub mean_fitness: T,
+}
+
+/// Generational genetic algorithm over network weights
+pub struct NeuroEvolution<T: Float> {
+    config: EvolutionConfig,
+    template: Network<T>,
+    crossover: Box<dyn CrossoverOperator<T>>,
+    mutation: Box<dyn MutationOperator<T>>,
+    population: Vec<Vec<T>>,
+    fitness: Vec<T>,
+    generation: usize,
+    rng: StdRng,
+}
+
+impl<T: Float> NeuroEvolution<T> {
+    /// Create a population of random genomes shaped like `template`
+    ///
+    /// Fails with [`TrainingError::InvalidData`] if `config` is invalid.
+    pub fn new(template: Network<T>, config: EvolutionConfig) -> Result<Self, TrainingError> {
+        config.validate()?;
+        let mut rng = match config.random_seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let genome_len = template.total_connections();
+        let (min, max) = config.initial_weight_range;
+        let population = (0..config.population_size)
+            .map(|_| {
+                (0..genome_len)
+                    .map(|_| cast(rng.gen_range(min..=max)))
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            config,
+            template,
+            crossover: Box::new(UniformCrossover::default()),
+            mutation: Box::new(GaussianMutation::default()),
+            population,
+            fitness: Vec::new(),
+            generation: 0,
+            rng,
+        })
+    }
+
+    /// Use a different crossover operator
+    pub fn with_crossover(mut self, crossover: Box<dyn CrossoverOperator<T>>) -> Self {
+        self.crossover = crossover;
+        self
+    }
+
+    /// Use a different mutation operator
+    pub fn with_mutation(mut self, mutation: Box<dyn MutationOperator<T>>) -> Self {
+        self.mutation = mutation;
+        self
+    }
+
+    /// Current population of genomes
+    pub fn population(&self) -> &[Vec<T>] {
+        &self.population
+    }
+
+    /// Number of completed generations
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Build a network carrying the given genome
+    ///
+    /// Fails with [`TrainingError::NetworkError`] if the genome does not
+    /// have one weight per connection of the template.
+    pub fn network_from_genome(&self, genome: &[T]) -> Result<Network<T>, TrainingError> {
+        let mut network = self.template.clone();
+        set_genome(&mut network, genome)?;
+        Ok(network)
+    }
+
+    /// Evaluate the population and breed the next generation
+    ///
+    /// Fails if a crossover or mutation operator changed the genome length.
+    pub fn step<F: FitnessFunction<T>>(
+        &mut self,
+        fitness_fn: &mut F,
+    ) -> Result<GenerationStats<T>, TrainingError> {
+        self.evaluate(fitness_fn)?;
+        let stats = self.stats();
+
+        let mut ranked: Vec<usize> = (0..self.population.len()).collect();
+        ranked.sort_by(|&a, &b| {
+            self.fitness[b]
+                .partial_cmp(&self.fitness[a])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let size = self.population.len();
+        let mut next: Vec<Vec<T>> = ranked
+            .iter()
+            .take(self.config.elitism.min(size))
+            .map(|&i| self.population[i].clone())
+            .collect();
+
+        while next.len() < size {
+            let a = self.tournament();
+            let b = self.tournament();
+            let (mut child_a, mut child_b) = if chance(&mut self.rng, self.config.crossover_rate) {
+                self.crossover
+                    .crossover(&self.population[a], &self.population[b], &mut self.rng)
+            } else {
+                (self.population[a].clone(), self.population[b].clone())
+            };
+            self.mutation.mutate(&mut child_a, &mut self.rng);
+            self.mutation.mutate(&mut child_b, &mut self.rng);
+            next.push(child_a);
+            if next.len() < size {
+                next.push(child_b);
+            }
+        }
+
+        self.population = next;
+        self.fitness.clear();
+        self.generation += 1;
+        Ok(stats)
+    }
+
+    /// Run several generations and return the best network of the final population
+    pub fn run<F: FitnessFunction<T>>(
+        &mut self,
+        fitness_fn: &mut F,
+        generations: usize,
+    ) -> Result<(Network<T>, Vec<GenerationStats<T>>), TrainingError> {
+        let history = (0..generations)
+            .map(|_| self.step(fitness_fn))
+            .collect::<Result<_, _>>()?;
+
+        self.evaluate(fitness_fn)?;
+        let best = (0..self.population.len())
+            .max_by(|&a, &b| {
+                self.fitness[a]
+                    .partial_cmp(&self.fitness[b])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap_or(0);
+        Ok((self.network_from_genome(&self.population[best])?, history))
+    }
+
+    fn evaluate<F: FitnessFunction<T>>(&mut self, fitness_fn: &mut F) -> Result<(), TrainingError> {
+        let mut network = self.template.clone();
+        self.fitness = self
+            .population
+            .iter()
+            .map(|genome| {
+                set_genome(&mut network, genome)?;
+                let score = fitness_fn.fitness(&mut network);
+                Ok(if score.is_nan() {
+                    T::neg_infinity()
+                } else {
+                    score
+                })
+            })
+            .collect::<Result<_, TrainingError>>()?;
+        Ok(())
+    }
+
+    fn stats(&self) -> GenerationStats<T> {
+        let best_fitness = self
+            .fitness
+            .iter()
+            .fold(T::neg_infinity(), |acc, &f| acc.max(f));
+        let mean_fitness = self.fitness.iter().fold(T::zero(), |acc, &f| acc + f)
+            / cast(self.fitness.len().max(1));
+        GenerationStats {
+            generation: self.generation,
+            best_fitness,
+            mean_fitness,
+        }
+    }
+
+    fn tournament(&mut self) -> usize {
+        let size = self.population.len();
+        (0..self.config.tournament_size.max(1))
+            .map(|_| self.rng.gen_range(0..size))
+            .max_by(|&a, &b| {
+                self.fitness[a]
+                    .partial_cmp(&self.fitness[b])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap_or(0)
+    }
+}
+
+fn set_genome<T: Float>(network: &mut Network<T>, genome: &[T]) -> Result<(), TrainingError> {
+    network
+        .set_weights(genome)
+        .map_err(|e| TrainingError::NetworkError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_single_point_crossover_preserves_genes() {
+        let a = vec![1.0f32; 6];
+        let b = vec![2.0f32; 6];
+        let mut rng = StdRng::seed_from_u64(1);
+        let (x, y) = SinglePointCrossover.crossover(&a, &b, &mut rng);
+
+        let point = x.iter().position(|&g| g == 2.0).unwrap();
+        assert!(point > 0);
+        assert!(x[..point].iter().all(|&g| g == 1.0) && x[point..].iter().all(|&g| g == 2.0));
+        assert!(y[..point].iter().all(|&g| g == 2.0) && y[point..].iter().all(|&g| g == 1.0));
+    }
+
+    #[test]
+    fn test_uniform_crossover_and_mutation() {
+        let a = vec![0.0f64; 100];
+        let b = vec![1.0f64; 100];
+        let mut rng = StdRng::seed_from_u64(2);
+        let (x, y) = UniformCrossover::default().crossover(&a, &b, &mut rng);
+        for (gx, gy) in x.iter().zip(&y) {
+            assert_eq!(gx + gy, 1.0);
+        }
+
+        let mut genome = vec![0.0f64; 100];
+        GaussianMutation {
+            rate: 1.0,
+            sigma: 0.5,
+        }
+        .mutate(&mut genome, &mut rng);
+        assert!(genome.iter().all(|&g| g != 0.0));
+
+        // Out-of-range probabilities saturate instead of panicking
+        let (x, _) = UniformCrossover {
+            swap_probability: 2.0,
+        }
+        .crossover(&a, &b, &mut rng);
+        assert_eq!(x, b);
+        let mut unchanged = vec![0.0f64; 10];
+        GaussianMutation {
+            rate: f64::NAN,
+            sigma: 0.5,
+        }
+        .mutate(&mut unchanged, &mut rng);
+        assert!(unchanged.iter().all(|&g| g == 0.0));
+    }
+
+    #[test]
+    fn test_evolution_improves_fitness() {
+        let template = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        let config = EvolutionConfig {
+            population_size: 20,
+            random_seed: Some(3),
+            ..EvolutionConfig::default()
+        };
+
+        // Learn logical OR: fitness is the negative squared error
+        let mut fitness = |network: &mut Network<f64>| {
+            let cases = [
+                ([0.0, 0.0], 0.0),
+                ([0.0, 1.0], 1.0),
+                ([1.0, 0.0], 1.0),
+                ([1.0, 1.0], 1.0),
+            ];
+            -cases
+                .iter()
+                .map(|(input, target)| (network.run(input)[0] - target).powi(2))
+                .sum::<f64>()
+        };
+
+        let mut evolution = NeuroEvolution::new(template.clone(), config.clone())
+            .unwrap()
+            .with_crossover(Box::new(SinglePointCrossover));
+        let (mut best, history) = evolution.run(&mut fitness, 30).unwrap();
+
+        assert_eq!(history.len(), 30);
+        assert!(history.last().unwrap().best_fitness >= history[0].best_fitness);
+        assert!(fitness(&mut best) >= history[0].best_fitness);
+        assert!(evolution.network_from_genome(&[0.5]).is_err());
+
+        for invalid in [
+            EvolutionConfig {
+                population_size: 0,
+                ..config.clone()
+            },
+            EvolutionConfig {
+                crossover_rate: 1.5,
+                ..config.clone()
+            },
+            EvolutionConfig {
+                initial_weight_range: (1.0, -1.0),
+                ..config
+            },
+        ] {
+            assert!(NeuroEvolution::new(template.clone(), invalid).is_err());
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! Execution settings for inference
+//!
+//! An [`ExecutionConfig`] describes how inference should run (backend, thread
+//! count, batch size and numeric precision) independently of the model. It
+//! is plain data, so it can live in a deployment config file next to the
+//! network and be changed without touching code:
+//!
+//! ```json
+//! { "backend": "Cpu", "threads": 4, "batch": 256, "precision": "F32" }
+//! ```
+//!
+//! [`Network::run_with`] and [`Network::run_batch_with`] accept it for
+//! one-off calls. An [`Executor`] prepares the network and worker pool for a
+//! config once and reuses them for every batch.
+
+use crate::Network;
+use num_traits::Float;
+use thiserror::Error;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Where inference runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ExecutionBackend {
+    /// Pick the best backend available in this build
+    #[default]
+    Auto,
+    Cpu,
+    Gpu,
+}
+
+/// Numeric precision used for inference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Precision {
+    /// Compute in the network's own float type
+    #[default]
+    Native,
+    /// Round weights, inputs and outputs to `f32`, reproducing the numerics
+    /// of an `f32` deployment from an `f64` network
+    F32,
+}
+
+/// Errors raised when applying an [`ExecutionConfig`]
+#[derive(Error, Debug)]
+pub enum ExecutionError {
+    #[error("Backend {0:?} is not available for network inference in this build")]
+    BackendUnavailable(ExecutionBackend),
+
+    #[error("Input size mismatch: expected {expected}, got {actual}")]
+    InputSizeMismatch { expected: usize, actual: usize },
+
+    #[error("Failed to create thread pool: {0}")]
+    ThreadPool(String),
+
+    #[cfg(feature = "serde")]
+    #[error("Invalid execution config: {0}")]
+    Config(#[from] serde_json::Error),
+}
+
+/// How inference runs: backend, threads, batch size and precision
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ExecutionConfig {
+    pub backend: ExecutionBackend,
+    /// Worker threads for batch inference; 0 uses all available cores
+    pub threads: usize,
+    /// Samples handed to a worker at a time; 0 splits the batch evenly
+    pub batch: usize,
+    pub precision: Precision,
+}
+
+impl ExecutionConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_backend(mut self, backend: ExecutionBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn with_batch(mut self, batch: usize) -> Self {
+        self.batch = batch;
+        self
+    }
+
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// The backend inference will actually use
+    ///
+    /// Networks only have a CPU inference path, so `Auto` resolves to `Cpu`
+    /// and an explicit `Gpu` request is an error rather than a silent fallback.
+    pub fn resolve_backend(&self) -> Result<ExecutionBackend, ExecutionError> {
+        match self.backend {
+            ExecutionBackend::Auto | ExecutionBackend::Cpu => Ok(ExecutionBackend::Cpu),
+            ExecutionBackend::Gpu => Err(ExecutionError::BackendUnavailable(self.backend)),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, ExecutionError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, ExecutionError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+fn round_to_f32<T: Float>(value: T) -> T {
+    value.to_f32().and_then(T::from).unwrap_or(value)
+}
+
+impl<T: Float> Network<T> {
+    /// Copy of the network with weights rounded to `precision`
+    fn with_precision(&self, precision: Precision) -> Network<T> {
+        let mut network = self.clone();
+        if precision == Precision::F32 {
+            for layer in &mut network.layers {
+                for neuron in &mut layer.neurons {
+                    for connection in &mut neuron.connections {
+                        connection.weight = round_to_f32(connection.weight);
+                    }
+                }
+            }
+        }
+        network
+    }
+
+    fn run_precision(&mut self, input: &[T], precision: Precision) -> Vec<T> {
+        match precision {
+            Precision::Native => self.run(input),
+            Precision::F32 => {
+                let input: Vec<T> = input.iter().copied().map(round_to_f32).collect();
+                self.run(&input).into_iter().map(round_to_f32).collect()
+            }
+        }
+    }
+
+    fn check_input_size(&self, input: &[T]) -> Result<(), ExecutionError> {
+        if input.len() != self.num_inputs() {
+            return Err(ExecutionError::InputSizeMismatch {
+                expected: self.num_inputs(),
+                actual: input.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Run one input under `config`
+    pub fn run_with(
+        &mut self,
+        input: &[T],
+        config: &ExecutionConfig,
+    ) -> Result<Vec<T>, ExecutionError> {
+        match config.precision {
+            Precision::Native => {
+                config.resolve_backend()?;
+                self.check_input_size(input)?;
+                Ok(self.run(input))
+            }
+            Precision::F32 => Executor::new(self, config.clone())?.run(input),
+        }
+    }
+
+    /// Run a batch of inputs under `config`
+    ///
+    /// Builds a one-off [`Executor`]; create one directly to reuse the
+    /// prepared network and thread pool across batches.
+    pub fn run_batch_with(
+        &self,
+        inputs: &[Vec<T>],
+        config: &ExecutionConfig,
+    ) -> Result<Vec<Vec<T>>, ExecutionError>
+    where
+        T: Send + Sync,
+    {
+        Executor::new(self, config.clone())?.run_batch(inputs)
+    }
+}
+
+/// Inference runner for one network under one [`ExecutionConfig`]
+///
+/// The copy of the network rounded to the configured precision and, with the
+/// `parallel` feature and an explicit thread count, the worker pool are built
+/// once in [`Executor::new`] and reused by every call.
+pub struct Executor<T: Float> {
+    network: Network<T>,
+    config: ExecutionConfig,
+    #[cfg(feature = "parallel")]
+    pool: Option<rayon::ThreadPool>,
+}
+
+impl<T: Float> Executor<T> {
+    /// Prepare `network` for inference under `config`
+    pub fn new(network: &Network<T>, config: ExecutionConfig) -> Result<Self, ExecutionError> {
+        config.resolve_backend()?;
+        #[cfg(feature = "parallel")]
+        let pool = match config.threads {
+            0 | 1 => None,
+            threads => Some(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(|e| ExecutionError::ThreadPool(e.to_string()))?,
+            ),
+        };
+        Ok(Self {
+            network: network.with_precision(config.precision),
+            config,
+            #[cfg(feature = "parallel")]
+            pool,
+        })
+    }
+
+    pub fn config(&self) -> &ExecutionConfig {
+        &self.config
+    }
+
+    /// Run one input
+    pub fn run(&mut self, input: &[T]) -> Result<Vec<T>, ExecutionError> {
+        self.network.check_input_size(input)?;
+        Ok(self.network.run_precision(input, self.config.precision))
+    }
+
+    /// Run a batch of inputs
+    ///
+    /// The batch is cut into chunks of `config.batch` samples, each run on a
+    /// clone of the network. With the `parallel` feature the chunks are spread
+    /// over `config.threads` workers; without it they run in order.
+    pub fn run_batch(&self, inputs: &[Vec<T>]) -> Result<Vec<Vec<T>>, ExecutionError>
+    where
+        T: Send + Sync,
+    {
+        for input in inputs {
+            self.network.check_input_size(input)?;
+        }
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let precision = self.config.precision;
+        let run_chunk = |chunk: &[Vec<T>]| -> Vec<Vec<T>> {
+            let mut network = self.network.clone();
+            chunk
+                .iter()
+                .map(|input| network.run_precision(input, precision))
+                .collect()
+        };
+
+        #[cfg(feature = "parallel")]
+        {
+            let threads = match self.config.threads {
+                0 => rayon::current_num_threads(),
+                n => n,
+            };
+            let chunk = match self.config.batch {
+                0 => inputs.len().div_ceil(threads),
+                n => n,
+            };
+            if threads == 1 {
+                return Ok(inputs.chunks(chunk).flat_map(run_chunk).collect());
+            }
+            let run_all = || -> Vec<Vec<T>> {
+                inputs
+                    .par_chunks(chunk)
+                    .map(run_chunk)
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .flatten()
+                    .collect()
+            };
+            match &self.pool {
+                Some(pool) => Ok(pool.install(run_all)),
+                None => Ok(run_all()),
+            }
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            let chunk = match self.config.batch {
+                0 => inputs.len(),
+                n => n,
+            };
+            Ok(inputs.chunks(chunk).flat_map(run_chunk).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    fn network() -> Network<f64> {
+        NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .output_layer(1)
+            .build()
+    }
+
+    #[test]
+    fn test_batch_matches_sequential_run() {
+        let mut network = network();
+        let inputs: Vec<Vec<f64>> = (0..37)
+            .map(|i| vec![i as f64 / 37.0, 1.0 - i as f64 / 37.0])
+            .collect();
+        let expected = network.run_batch(&inputs);
+
+        for config in [
+            ExecutionConfig::new(),
+            ExecutionConfig::new().with_threads(1).with_batch(5),
+            ExecutionConfig::new().with_threads(3).with_batch(4),
+        ] {
+            assert_eq!(network.run_batch_with(&inputs, &config).unwrap(), expected);
+        }
+
+        let executor = Executor::new(&network, ExecutionConfig::new().with_threads(2)).unwrap();
+        assert_eq!(executor.run_batch(&inputs).unwrap(), expected);
+        assert_eq!(executor.run_batch(&inputs[..5]).unwrap(), expected[..5]);
+    }
+
+    #[test]
+    fn test_f32_precision_and_backend_selection() {
+        let mut network = network();
+        let input = [0.1, 0.7];
+        let config = ExecutionConfig::new().with_precision(Precision::F32);
+        let output = network.run_with(&input, &config).unwrap()[0];
+        assert_eq!(output, output as f32 as f64);
+        assert!((output - network.run(&input)[0]).abs() < 1e-5);
+
+        let gpu = ExecutionConfig::new().with_backend(ExecutionBackend::Gpu);
+        assert!(matches!(
+            network.run_with(&input, &gpu),
+            Err(ExecutionError::BackendUnavailable(ExecutionBackend::Gpu))
+        ));
+        assert!(matches!(
+            network.run_with(&[0.1], &config),
+            Err(ExecutionError::InputSizeMismatch { .. })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_config_round_trip() {
+        let config = ExecutionConfig::from_json(r#"{ "threads": 4, "precision": "F32" }"#).unwrap();
+        assert_eq!(config.backend, ExecutionBackend::Auto);
+        assert_eq!(config.threads, 4);
+        assert_eq!(config.precision, Precision::F32);
+        assert_eq!(
+            ExecutionConfig::from_json(&config.to_json().unwrap()).unwrap(),
+            config
+        );
+    }
+}
@@ -0,0 +1,731 @@
+//! Reproducible experiment bundles
+//!
+//! An [`ExperimentBundle`] gathers everything needed to reproduce or audit a
+//! training run in one file: the trained network, the optimizer
+//! configuration, final metrics, summary statistics of the training data, the
+//! random seed, optionally the per-epoch sample order, and a description of
+//! the build and CPU it ran on. Bundles are
+//! written as JSON by [`export_bundle`] so they remain readable without this
+//! crate; weights are parsed back bit-exactly.
+//!
+//! [`compare`] answers "what changed between these two runs": it lines up
+//! the learning curves by epoch and lists the configuration, metric,
+//! environment and weight differences in an [`ExperimentComparison`], which
+//! prints as a readable report.
+
+use crate::io::{IoError, IoResult};
+use crate::training::{OrderingLog, TrainingAlgorithm, TrainingData, TrainingReport};
+use crate::{Network, NetworkDiff};
+use num_traits::Float;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Version of the bundle layout written by [`export_bundle`]
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Build and host information captured when a bundle is created
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvironmentInfo {
+    pub crate_name: String,
+    pub crate_version: String,
+    pub target_arch: String,
+    pub target_os: String,
+    pub debug_build: bool,
+    /// Cargo features of this crate that affect numerics or scheduling
+    pub enabled_features: Vec<String>,
+    /// SIMD extensions detected on the running CPU
+    pub cpu_features: Vec<String>,
+    /// Threads available to the process
+    pub available_threads: usize,
+}
+
+impl EnvironmentInfo {
+    /// Describe the current build and host
+    pub fn capture() -> Self {
+        let features = [
+            ("parallel", cfg!(feature = "parallel")),
+            ("simd", cfg!(feature = "simd")),
+            ("gpu", cfg!(feature = "gpu")),
+            ("no_std", cfg!(feature = "no_std")),
+        ];
+
+        Self {
+            crate_name: env!("CARGO_PKG_NAME").to_string(),
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            target_arch: std::env::consts::ARCH.to_string(),
+            target_os: std::env::consts::OS.to_string(),
+            debug_build: cfg!(debug_assertions),
+            enabled_features: features
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            cpu_features: detect_cpu_features(),
+            available_threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+fn detect_cpu_features() -> Vec<String> {
+    #[allow(unused_mut)]
+    let mut detected = Vec::new();
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        let candidates = [
+            ("sse4.1", is_x86_feature_detected!("sse4.1")),
+            ("avx", is_x86_feature_detected!("avx")),
+            ("avx2", is_x86_feature_detected!("avx2")),
+            ("fma", is_x86_feature_detected!("fma")),
+            ("avx512f", is_x86_feature_detected!("avx512f")),
+        ];
+        detected.extend(
+            candidates
+                .iter()
+                .filter(|(_, present)| *present)
+                .map(|(name, _)| name.to_string()),
+        );
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        let candidates = [
+            ("neon", std::arch::is_aarch64_feature_detected!("neon")),
+            ("sve", std::arch::is_aarch64_feature_detected!("sve")),
+            ("sve2", std::arch::is_aarch64_feature_detected!("sve2")),
+        ];
+        detected.extend(
+            candidates
+                .iter()
+                .filter(|(_, present)| *present)
+                .map(|(name, _)| name.to_string()),
+        );
+    }
+
+    detected
+}
+
+/// Per-column summary of a data set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataStatistics {
+    pub samples: usize,
+    pub input_mean: Vec<f64>,
+    pub input_std_dev: Vec<f64>,
+    pub output_mean: Vec<f64>,
+    pub output_std_dev: Vec<f64>,
+}
+
+impl DataStatistics {
+    pub fn from_data<T: Float>(data: &TrainingData<T>) -> Self {
+        let (input_mean, input_std_dev) = column_moments(&data.inputs);
+        let (output_mean, output_std_dev) = column_moments(&data.outputs);
+        Self {
+            samples: data.inputs.len(),
+            input_mean,
+            input_std_dev,
+            output_mean,
+            output_std_dev,
+        }
+    }
+}
+
+/// Column means and population standard deviations; values without an
+/// `f64` representation are skipped
+fn column_moments<T: Float>(rows: &[Vec<T>]) -> (Vec<f64>, Vec<f64>) {
+    let cols = rows.first().map_or(0, Vec::len);
+    let n = rows.len().max(1) as f64;
+    let mut mean = vec![0.0; cols];
+    for row in rows {
+        for (m, v) in mean.iter_mut().zip(row) {
+            if let Some(v) = v.to_f64() {
+                *m += v / n;
+            }
+        }
+    }
+    let mut variance = vec![0.0; cols];
+    for row in rows {
+        for ((var, m), v) in variance.iter_mut().zip(&mean).zip(row) {
+            if let Some(v) = v.to_f64() {
+                *var += (v - m) * (v - m) / n;
+            }
+        }
+    }
+    (mean, variance.into_iter().map(f64::sqrt).collect())
+}
+
+/// Errors recorded at one epoch of a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurvePoint {
+    pub epoch: usize,
+    pub train_error: f64,
+    pub validation_error: Option<f64>,
+}
+
+/// Everything recorded about one training run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentBundle<T: Float> {
+    pub format_version: u32,
+    /// Seconds since the Unix epoch when the bundle was created
+    pub created_at: u64,
+    pub network: Network<T>,
+    /// Optimizer hyperparameters, keyed by name
+    pub training_config: BTreeMap<String, Vec<f64>>,
+    /// Final metrics, keyed by name
+    pub metrics: BTreeMap<String, f64>,
+    pub data_statistics: Option<DataStatistics>,
+    pub seed: Option<u64>,
+    /// Sample order of every epoch, for exact replay
+    #[serde(default)]
+    pub data_ordering: Option<OrderingLog>,
+    /// Errors over the run, in epoch order
+    #[serde(default)]
+    pub learning_curve: Vec<CurvePoint>,
+    pub environment: EnvironmentInfo,
+}
+
+impl<T: Float> ExperimentBundle<T> {
+    /// Start a bundle for `network`, capturing the current environment
+    pub fn new(network: Network<T>) -> Self {
+        let created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            format_version: BUNDLE_FORMAT_VERSION,
+            created_at,
+            network,
+            training_config: BTreeMap::new(),
+            metrics: BTreeMap::new(),
+            data_statistics: None,
+            seed: None,
+            data_ordering: None,
+            learning_curve: Vec::new(),
+            environment: EnvironmentInfo::capture(),
+        }
+    }
+
+    /// Record the hyperparameters reported by the algorithm's `save_state`;
+    /// values without an `f64` representation are skipped
+    pub fn with_training_config(mut self, algorithm: &dyn TrainingAlgorithm<T>) -> Self {
+        for (key, values) in algorithm.save_state().algorithm_specific {
+            let values = values.iter().filter_map(|v| v.to_f64()).collect();
+            self.training_config.insert(key, values);
+        }
+        self
+    }
+
+    /// Record an additional configuration value
+    pub fn with_config_value(mut self, key: &str, values: Vec<f64>) -> Self {
+        self.training_config.insert(key.to_string(), values);
+        self
+    }
+
+    pub fn with_metric(mut self, name: &str, value: f64) -> Self {
+        self.metrics.insert(name.to_string(), value);
+        self
+    }
+
+    /// Append a point to the learning curve
+    pub fn with_curve_point(
+        mut self,
+        epoch: usize,
+        train_error: f64,
+        validation_error: Option<f64>,
+    ) -> Self {
+        self.learning_curve.push(CurvePoint {
+            epoch,
+            train_error,
+            validation_error,
+        });
+        self
+    }
+
+    /// Record the final, best and validation errors of a [`Trainer`](crate::training::Trainer)
+    /// run, and its validation points as the learning curve
+    ///
+    /// Errors without an `f64` representation are left out rather than
+    /// stored as NaN, which JSON would write as `null`.
+    pub fn with_training_report(mut self, report: &TrainingReport<T>) -> Self {
+        self.metrics
+            .insert("epochs".to_string(), report.epochs as f64);
+        let errors = [
+            ("final_error", Some(report.final_error)),
+            ("best_error", report.best_error),
+            (
+                "validation_error",
+                report.validation_history.last().map(|p| p.validation_error),
+            ),
+        ];
+        for (name, error) in errors {
+            if let Some(error) = error.and_then(|e| e.to_f64()) {
+                self.metrics.insert(name.to_string(), error);
+            }
+        }
+        self.learning_curve = report
+            .validation_history
+            .iter()
+            .filter_map(|point| {
+                Some(CurvePoint {
+                    epoch: point.epoch,
+                    train_error: point.train_error.to_f64()?,
+                    validation_error: point.validation_error.to_f64(),
+                })
+            })
+            .collect();
+        self
+    }
+
+    /// Summarize the training data
+    pub fn with_data(mut self, data: &TrainingData<T>) -> Self {
+        self.data_statistics = Some(DataStatistics::from_data(data));
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Store the sample order of every epoch, see
+    /// [`Trainer::record_ordering`](crate::training::Trainer::record_ordering)
+    pub fn with_ordering_log(mut self, log: &OrderingLog) -> Self {
+        if self.seed.is_none() {
+            self.seed = log.seed;
+        }
+        self.data_ordering = Some(log.clone());
+        self
+    }
+}
+
+/// Write `bundle` to `path` as JSON
+pub fn export_bundle<T, P>(bundle: &ExperimentBundle<T>, path: P) -> IoResult<()>
+where
+    T: Float + Serialize,
+    P: AsRef<Path>,
+{
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, bundle)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read a bundle written by [`export_bundle`]
+pub fn import_bundle<T, P>(path: P) -> IoResult<ExperimentBundle<T>>
+where
+    T: Float + for<'de> Deserialize<'de>,
+    P: AsRef<Path>,
+{
+    let bundle: ExperimentBundle<T> = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    if bundle.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(IoError::InvalidFileFormat(format!(
+            "experiment bundle version {} is newer than supported version {}",
+            bundle.format_version, BUNDLE_FORMAT_VERSION
+        )));
+    }
+    Ok(bundle)
+}
+
+/// A value that differs between two runs; `None` where a run lacks it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<V> {
+    pub name: String,
+    pub left: Option<V>,
+    pub right: Option<V>,
+}
+
+/// A final metric present in either run
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricComparison {
+    pub name: String,
+    pub left: Option<f64>,
+    pub right: Option<f64>,
+}
+
+impl MetricComparison {
+    /// `right - left`, when both runs have the metric
+    pub fn delta(&self) -> Option<f64> {
+        Some(self.right? - self.left?)
+    }
+}
+
+/// Both learning curves at one epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlignedEpoch {
+    pub epoch: usize,
+    pub left: Option<CurvePoint>,
+    pub right: Option<CurvePoint>,
+}
+
+/// Result of [`compare`]; "left" is the first run, "right" the second
+#[derive(Debug, Clone)]
+pub struct ExperimentComparison<T: Float> {
+    /// Hyperparameters that differ or exist in only one run
+    pub config: Vec<Change<Vec<f64>>>,
+    /// Every final metric of either run, by name
+    pub metrics: Vec<MetricComparison>,
+    /// Union of the recorded epochs of both curves, in order
+    pub curve: Vec<AlignedEpoch>,
+    /// Seed, data set, ordering and environment differences, rendered as text
+    pub context: Vec<Change<String>>,
+    /// Topology and weight differences of the trained networks
+    pub network: NetworkDiff<T>,
+}
+
+impl<T: Float> ExperimentComparison<T> {
+    /// Whether the configuration, seed, data and environment are the same,
+    /// so any metric difference comes from elsewhere (e.g. nondeterminism)
+    pub fn same_setup(&self) -> bool {
+        self.config.is_empty() && self.context.is_empty()
+    }
+
+    /// Last epoch recorded by both runs
+    pub fn last_shared_epoch(&self) -> Option<&AlignedEpoch> {
+        self.curve
+            .iter()
+            .rev()
+            .find(|point| point.left.is_some() && point.right.is_some())
+    }
+}
+
+impl<T: Float> fmt::Display for ExperimentComparison<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn show<V: fmt::Debug>(value: &Option<V>) -> String {
+            value
+                .as_ref()
+                .map_or_else(|| "-".to_string(), |v| format!("{v:?}"))
+        }
+
+        writeln!(f, "config:")?;
+        if self.config.is_empty() {
+            writeln!(f, "  identical")?;
+        }
+        for change in &self.config {
+            writeln!(
+                f,
+                "  {}: {} -> {}",
+                change.name,
+                show(&change.left),
+                show(&change.right)
+            )?;
+        }
+        writeln!(f, "metrics:")?;
+        for metric in &self.metrics {
+            let delta = metric
+                .delta()
+                .map_or_else(String::new, |d| format!(" ({d:+})"));
+            writeln!(
+                f,
+                "  {}: {} -> {}{delta}",
+                metric.name,
+                show(&metric.left),
+                show(&metric.right)
+            )?;
+        }
+        if let Some(point) = self.last_shared_epoch() {
+            let train = |p: &Option<CurvePoint>| p.as_ref().map_or(f64::NAN, |p| p.train_error);
+            writeln!(
+                f,
+                "curve: train error at epoch {}: {} -> {}",
+                point.epoch,
+                train(&point.left),
+                train(&point.right)
+            )?;
+        }
+        for change in &self.context {
+            writeln!(
+                f,
+                "{}: {} -> {}",
+                change.name,
+                change.left.as_deref().unwrap_or("-"),
+                change.right.as_deref().unwrap_or("-")
+            )?;
+        }
+        if self.network.topology_matches() {
+            write!(
+                f,
+                "network: weight distance {}",
+                self.network
+                    .total_l2_distance()
+                    .to_f64()
+                    .unwrap_or(f64::NAN)
+            )
+        } else {
+            write!(
+                f,
+                "network: {} topology differences",
+                self.network.topology.len()
+            )
+        }
+    }
+}
+
+/// Compare two runs: configuration diffs, final metrics, learning curves
+/// aligned by epoch, context changes and the weight distance
+pub fn compare<T: Float>(
+    left: &ExperimentBundle<T>,
+    right: &ExperimentBundle<T>,
+) -> ExperimentComparison<T> {
+    let keys: BTreeSet<&String> = left
+        .training_config
+        .keys()
+        .chain(right.training_config.keys())
+        .collect();
+    let config = keys
+        .into_iter()
+        .filter_map(|key| {
+            let (l, r) = (
+                left.training_config.get(key),
+                right.training_config.get(key),
+            );
+            (l != r).then(|| Change {
+                name: key.clone(),
+                left: l.cloned(),
+                right: r.cloned(),
+            })
+        })
+        .collect();
+
+    let names: BTreeSet<&String> = left.metrics.keys().chain(right.metrics.keys()).collect();
+    let metrics = names
+        .into_iter()
+        .map(|name| MetricComparison {
+            name: name.clone(),
+            left: left.metrics.get(name).copied(),
+            right: right.metrics.get(name).copied(),
+        })
+        .collect();
+
+    let mut curve: BTreeMap<usize, AlignedEpoch> = BTreeMap::new();
+    let aligned = |epoch| AlignedEpoch {
+        epoch,
+        left: None,
+        right: None,
+    };
+    for point in &left.learning_curve {
+        curve
+            .entry(point.epoch)
+            .or_insert_with(|| aligned(point.epoch))
+            .left = Some(point.clone());
+    }
+    for point in &right.learning_curve {
+        curve
+            .entry(point.epoch)
+            .or_insert_with(|| aligned(point.epoch))
+            .right = Some(point.clone());
+    }
+
+    ExperimentComparison {
+        config,
+        metrics,
+        curve: curve.into_values().collect(),
+        context: context_changes(left, right),
+        network: left.network.diff(&right.network),
+    }
+}
+
+/// Read two bundles written by [`export_bundle`] and [`compare`] them
+pub fn compare_bundles<T, P, Q>(left: P, right: Q) -> IoResult<ExperimentComparison<T>>
+where
+    T: Float + for<'de> Deserialize<'de>,
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    Ok(compare(&import_bundle(left)?, &import_bundle(right)?))
+}
+
+fn context_changes<T: Float>(
+    left: &ExperimentBundle<T>,
+    right: &ExperimentBundle<T>,
+) -> Vec<Change<String>> {
+    let mut changes = Vec::new();
+    let mut check = |name: &str, l: Option<String>, r: Option<String>| {
+        if l != r {
+            changes.push(Change {
+                name: name.to_string(),
+                left: l,
+                right: r,
+            });
+        }
+    };
+    check(
+        "seed",
+        left.seed.map(|s| s.to_string()),
+        right.seed.map(|s| s.to_string()),
+    );
+    let (l, r) = (&left.data_statistics, &right.data_statistics);
+    check(
+        "data.samples",
+        l.as_ref().map(|d| d.samples.to_string()),
+        r.as_ref().map(|d| d.samples.to_string()),
+    );
+    check(
+        "data.input_mean",
+        l.as_ref().map(|d| format!("{:?}", d.input_mean)),
+        r.as_ref().map(|d| format!("{:?}", d.input_mean)),
+    );
+    check(
+        "data.output_mean",
+        l.as_ref().map(|d| format!("{:?}", d.output_mean)),
+        r.as_ref().map(|d| format!("{:?}", d.output_mean)),
+    );
+    if left.data_ordering != right.data_ordering {
+        let epochs = |b: &ExperimentBundle<T>| {
+            b.data_ordering
+                .as_ref()
+                .map(|o| format!("{} epochs", o.len()))
+        };
+        let (l, r) = (epochs(left), epochs(right));
+        if l == r {
+            check(
+                "data_ordering",
+                l,
+                r.map(|r| format!("{r}, different order")),
+            );
+        } else {
+            check("data_ordering", l, r);
+        }
+    }
+    let (l, r) = (&left.environment, &right.environment);
+    let fields = [
+        (
+            "environment.crate_version",
+            &l.crate_version,
+            &r.crate_version,
+        ),
+        ("environment.target_arch", &l.target_arch, &r.target_arch),
+        ("environment.target_os", &l.target_os, &r.target_os),
+    ];
+    for (name, a, b) in fields {
+        check(name, Some(a.clone()), Some(b.clone()));
+    }
+    check(
+        "environment.debug_build",
+        Some(l.debug_build.to_string()),
+        Some(r.debug_build.to_string()),
+    );
+    check(
+        "environment.enabled_features",
+        Some(l.enabled_features.join(",")),
+        Some(r.enabled_features.join(",")),
+    );
+    check(
+        "environment.cpu_features",
+        Some(l.cpu_features.join(",")),
+        Some(r.cpu_features.join(",")),
+    );
+    check(
+        "environment.available_threads",
+        Some(l.available_threads.to_string()),
+        Some(r.available_threads.to_string()),
+    );
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::Adam;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let data = TrainingData {
+            inputs: vec![vec![0.0, 2.0], vec![2.0, 4.0]],
+            outputs: vec![vec![1.0], vec![1.0]],
+        };
+        let bundle = ExperimentBundle::new(network.clone())
+            .with_training_config(&Adam::new(0.01))
+            .with_metric("final_error", 0.25)
+            .with_data(&data)
+            .with_seed(42)
+            .with_ordering_log(&OrderingLog {
+                seed: Some(7),
+                epochs: vec![vec![1, 0], vec![0, 1]],
+            });
+
+        let path = std::env::temp_dir().join(format!("bundle_{}.json", std::process::id()));
+        export_bundle(&bundle, &path).unwrap();
+        let loaded: ExperimentBundle<f64> = import_bundle(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.seed, Some(42));
+        assert_eq!(loaded.data_ordering, bundle.data_ordering);
+        assert_eq!(loaded.training_config["learning_rate"], vec![0.01]);
+        assert_eq!(loaded.metrics["final_error"], 0.25);
+        assert_eq!(loaded.network.get_weights(), network.get_weights());
+        assert_eq!(loaded.environment, EnvironmentInfo::capture());
+
+        let stats = loaded.data_statistics.unwrap();
+        assert_eq!(stats.input_mean, vec![1.0, 3.0]);
+        assert_eq!(stats.input_std_dev, vec![1.0, 1.0]);
+        assert_eq!(stats.output_std_dev, vec![0.0]);
+    }
+
+    #[test]
+    fn test_compare_runs() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let mut retrained = network.clone();
+        let mut weights = retrained.get_weights();
+        weights[0] += 0.5;
+        retrained.set_weights(&weights).unwrap();
+
+        let left = ExperimentBundle::new(network)
+            .with_training_config(&Adam::new(0.01))
+            .with_metric("final_error", 0.25)
+            .with_seed(1)
+            .with_curve_point(0, 0.5, Some(0.6))
+            .with_curve_point(10, 0.3, Some(0.4));
+        let right = ExperimentBundle::new(retrained)
+            .with_training_config(&Adam::new(0.05))
+            .with_metric("final_error", 0.2)
+            .with_metric("epochs", 20.0)
+            .with_seed(1)
+            .with_curve_point(10, 0.25, None)
+            .with_curve_point(20, 0.2, None);
+
+        let comparison = compare(&left, &right);
+        assert!(!comparison.same_setup());
+        assert_eq!(comparison.config.len(), 1);
+        assert_eq!(comparison.config[0].name, "learning_rate");
+        assert_eq!(comparison.config[0].right, Some(vec![0.05]));
+        assert!(comparison.context.is_empty());
+
+        assert_eq!(comparison.metrics[0].name, "epochs");
+        assert_eq!(comparison.metrics[0].delta(), None);
+        let final_error = &comparison.metrics[1];
+        assert!((final_error.delta().unwrap() + 0.05).abs() < 1e-12);
+
+        let epochs: Vec<usize> = comparison.curve.iter().map(|p| p.epoch).collect();
+        assert_eq!(epochs, [0, 10, 20]);
+        assert_eq!(comparison.last_shared_epoch().unwrap().epoch, 10);
+        assert!(comparison.network.topology_matches());
+        assert!((comparison.network.total_l2_distance() - 0.5).abs() < 1e-12);
+
+        let report = comparison.to_string();
+        assert!(report.contains("learning_rate"));
+        assert!(report.contains("final_error: 0.25 -> 0.2 ("));
+
+        let path = std::env::temp_dir().join(format!("compare_{}.json", std::process::id()));
+        export_bundle(&left.clone().with_seed(2), &path).unwrap();
+        let reloaded: ExperimentComparison<f64> = compare_bundles(&path, &path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(reloaded.same_setup());
+        assert!(reloaded.network.is_identical());
+        assert_eq!(
+            compare(&left, &left.clone().with_seed(2)).context[0].name,
+            "seed"
+        );
+    }
+}
@@ -0,0 +1,436 @@
+//! Networks as arbitrary directed acyclic graphs
+//!
+//! [`Network`] evaluates strictly layer by layer. [`GraphNetwork`] lifts that
+//! restriction: any neuron may feed any later neuron, so shortcut connections,
+//! several independent input groups and cascade-grown topologies all run on
+//! the same engine. The evaluation order is a topological sort computed once
+//! by [`GraphBuilder::build`]; forward and backward passes then simply walk
+//! that order.
+//!
+//! Nodes reuse [`Neuron`]: each connection's `from_neuron` is the global node
+//! index of its source, so a neuron can be evaluated directly against the
+//! vector of all node values.
+//!
+//! The graph is a separate engine: [`Network`], its optimizers and the
+//! cascade trainer keep evaluating layer by layer. A layered network is
+//! copied in with [`GraphNetwork::from_network`]; training on the copy does
+//! not change the original. Gradients use the same [`ErrorFunction`]
+//! output derivatives as the layered
+//! [`calculate_gradients`](crate::training::helpers::calculate_gradients).
+
+use crate::training::{ErrorFunction, MseError};
+use crate::{ActivationFunction, Network, Neuron};
+use num_traits::Float;
+use thiserror::Error;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Errors raised while building or running a graph network
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum GraphError {
+    #[error("Node {0} does not exist")]
+    UnknownNode(usize),
+
+    #[error("Node {0} is an input or bias node and cannot have incoming connections")]
+    NotConnectable(usize),
+
+    #[error("Connections form a cycle through nodes {0:?}")]
+    Cycle(Vec<usize>),
+
+    #[error("The graph has no output nodes")]
+    NoOutputs,
+
+    #[error("Expected {expected} values, got {actual}")]
+    SizeMismatch { expected: usize, actual: usize },
+}
+
+/// Role of a node in the graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NodeKind {
+    Input,
+    Bias,
+    Neuron,
+}
+
+/// Incrementally assembles a [`GraphNetwork`]
+#[derive(Debug, Clone, Default)]
+pub struct GraphBuilder<T: Float> {
+    neurons: Vec<Neuron<T>>,
+    kinds: Vec<NodeKind>,
+    outputs: Vec<usize>,
+}
+
+impl<T: Float> GraphBuilder<T> {
+    pub fn new() -> Self {
+        Self {
+            neurons: Vec::new(),
+            kinds: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, neuron: Neuron<T>, kind: NodeKind) -> usize {
+        self.neurons.push(neuron);
+        self.kinds.push(kind);
+        self.neurons.len() - 1
+    }
+
+    /// Add an input node; inputs are fed in the order they were added
+    pub fn add_input(&mut self) -> usize {
+        self.push(
+            Neuron::new(ActivationFunction::Linear, T::one()),
+            NodeKind::Input,
+        )
+    }
+
+    /// Add a bias node with constant output 1
+    pub fn add_bias(&mut self) -> usize {
+        self.push(Neuron::new_bias(), NodeKind::Bias)
+    }
+
+    /// Add a computing neuron
+    pub fn add_neuron(&mut self, activation_function: ActivationFunction, steepness: T) -> usize {
+        self.push(
+            Neuron::new(activation_function, steepness),
+            NodeKind::Neuron,
+        )
+    }
+
+    /// Connect node `from` to node `to`
+    pub fn connect(&mut self, from: usize, to: usize, weight: T) -> Result<(), GraphError> {
+        if from >= self.neurons.len() {
+            return Err(GraphError::UnknownNode(from));
+        }
+        match self.kinds.get(to) {
+            None => Err(GraphError::UnknownNode(to)),
+            Some(NodeKind::Neuron) => {
+                self.neurons[to].add_connection(from, weight);
+                Ok(())
+            }
+            Some(_) => Err(GraphError::NotConnectable(to)),
+        }
+    }
+
+    /// Mark a node as a network output; outputs are returned in this order
+    pub fn add_output(&mut self, node: usize) -> Result<(), GraphError> {
+        if node >= self.neurons.len() {
+            return Err(GraphError::UnknownNode(node));
+        }
+        self.outputs.push(node);
+        Ok(())
+    }
+
+    /// Sort the graph topologically and produce a runnable network
+    pub fn build(self) -> Result<GraphNetwork<T>, GraphError> {
+        if self.outputs.is_empty() {
+            return Err(GraphError::NoOutputs);
+        }
+        let order = topological_order(&self.neurons, &self.kinds)?;
+        let inputs = self
+            .kinds
+            .iter()
+            .enumerate()
+            .filter(|(_, kind)| **kind == NodeKind::Input)
+            .map(|(i, _)| i)
+            .collect();
+        let values = self.neurons.iter().map(|n| n.value).collect();
+
+        Ok(GraphNetwork {
+            neurons: self.neurons,
+            kinds: self.kinds,
+            inputs,
+            outputs: self.outputs,
+            order,
+            values,
+        })
+    }
+}
+
+/// Kahn's algorithm over the computing neurons
+fn topological_order<T: Float>(
+    neurons: &[Neuron<T>],
+    kinds: &[NodeKind],
+) -> Result<Vec<usize>, GraphError> {
+    let n = neurons.len();
+    let mut successors = vec![Vec::new(); n];
+    let mut pending = vec![0usize; n];
+    for (node, neuron) in neurons.iter().enumerate() {
+        for connection in &neuron.connections {
+            successors[connection.from_neuron].push(node);
+            pending[node] += 1;
+        }
+    }
+
+    let mut ready: Vec<usize> = (0..n).filter(|&i| pending[i] == 0).collect();
+    let mut order = Vec::with_capacity(n);
+    while let Some(node) = ready.pop() {
+        if kinds[node] == NodeKind::Neuron {
+            order.push(node);
+        }
+        for &next in &successors[node] {
+            pending[next] -= 1;
+            if pending[next] == 0 {
+                ready.push(next);
+            }
+        }
+    }
+
+    let blocked: Vec<usize> = (0..n).filter(|&i| pending[i] > 0).collect();
+    if blocked.is_empty() {
+        Ok(order)
+    } else {
+        Err(GraphError::Cycle(blocked))
+    }
+}
+
+/// A network whose neurons form an arbitrary DAG
+#[derive(Debug, Clone)]
+pub struct GraphNetwork<T: Float> {
+    neurons: Vec<Neuron<T>>,
+    kinds: Vec<NodeKind>,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+    order: Vec<usize>,
+    values: Vec<T>,
+}
+
+impl<T: Float> GraphNetwork<T> {
+    /// Convert a layered network, keeping FANN's global neuron numbering
+    pub fn from_network(network: &Network<T>) -> Result<Self, GraphError> {
+        let mut builder = GraphBuilder::new();
+        let mut offset = 0;
+        let last = network.layers.len().saturating_sub(1);
+        for (layer_index, layer) in network.layers.iter().enumerate() {
+            for (neuron_index, neuron) in layer.neurons.iter().enumerate() {
+                let node = if neuron.is_bias {
+                    builder.add_bias()
+                } else if layer_index == 0 {
+                    builder.add_input()
+                } else {
+                    builder.add_neuron(neuron.activation_function, neuron.activation_steepness)
+                };
+                if layer_index == last && !neuron.is_bias {
+                    builder.add_output(node)?;
+                }
+                if layer_index > 0 && !neuron.is_bias {
+                    let previous = offset - network.layers[layer_index - 1].size();
+                    for connection in &neuron.connections {
+                        builder.connect(
+                            previous + connection.from_neuron,
+                            offset + neuron_index,
+                            connection.weight,
+                        )?;
+                    }
+                }
+            }
+            offset += layer.size();
+        }
+        builder.build()
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.neurons.len()
+    }
+
+    pub fn node_kind(&self, node: usize) -> Option<NodeKind> {
+        self.kinds.get(node).copied()
+    }
+
+    pub fn neuron(&self, node: usize) -> Option<&Neuron<T>> {
+        self.neurons.get(node)
+    }
+
+    /// Evaluation order of the computing neurons
+    pub fn execution_order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// All connection weights, grouped by destination node
+    pub fn get_weights(&self) -> Vec<T> {
+        self.neurons
+            .iter()
+            .flat_map(|n| n.connections.iter().map(|c| c.weight))
+            .collect()
+    }
+
+    /// Run a forward pass
+    pub fn run(&mut self, inputs: &[T]) -> Result<Vec<T>, GraphError> {
+        if inputs.len() != self.inputs.len() {
+            return Err(GraphError::SizeMismatch {
+                expected: self.inputs.len(),
+                actual: inputs.len(),
+            });
+        }
+        for (&node, &value) in self.inputs.iter().zip(inputs) {
+            self.neurons[node].set_value(value);
+            self.values[node] = value;
+        }
+        for &node in &self.order {
+            self.neurons[node].calculate(&self.values);
+            self.values[node] = self.neurons[node].value;
+        }
+        Ok(self.outputs.iter().map(|&node| self.values[node]).collect())
+    }
+
+    /// Gradient of `error_function` for one sample with respect to every
+    /// weight, indexed like the nodes' connections
+    ///
+    /// Deltas are propagated in reverse execution order, so connections that
+    /// skip layers receive their gradient like any other. Leaves the node
+    /// values of the forward pass in place.
+    pub fn calculate_gradients(
+        &mut self,
+        inputs: &[T],
+        targets: &[T],
+        error_function: &dyn ErrorFunction<T>,
+    ) -> Result<Vec<Vec<T>>, GraphError> {
+        if targets.len() != self.outputs.len() {
+            return Err(GraphError::SizeMismatch {
+                expected: self.outputs.len(),
+                actual: targets.len(),
+            });
+        }
+        let outputs = self.run(inputs)?;
+
+        // Error derivative with respect to each node's output
+        let mut upstream = vec![T::zero(); self.neurons.len()];
+        for (&node, derivative) in self
+            .outputs
+            .iter()
+            .zip(error_function.output_derivatives(&outputs, targets))
+        {
+            upstream[node] = upstream[node] + derivative;
+        }
+
+        let mut gradients: Vec<Vec<T>> = self
+            .neurons
+            .iter()
+            .map(|n| vec![T::zero(); n.connections.len()])
+            .collect();
+        for &node in self.order.iter().rev() {
+            let neuron = &self.neurons[node];
+            let delta = upstream[node] * neuron.activation_derivative();
+            for (gradient, connection) in gradients[node].iter_mut().zip(&neuron.connections) {
+                let source = connection.from_neuron;
+                upstream[source] = upstream[source] + delta * connection.weight;
+                *gradient = delta * self.values[source];
+            }
+        }
+        Ok(gradients)
+    }
+
+    /// One step of gradient descent on the mean squared error of a single
+    /// sample; returns that error before the update
+    pub fn train_sample(
+        &mut self,
+        inputs: &[T],
+        targets: &[T],
+        learning_rate: T,
+    ) -> Result<T, GraphError> {
+        let gradients = self.calculate_gradients(inputs, targets, &MseError)?;
+        let outputs: Vec<T> = self.outputs.iter().map(|&node| self.values[node]).collect();
+        for (neuron, gradients) in self.neurons.iter_mut().zip(gradients) {
+            for (connection, gradient) in neuron.connections.iter_mut().zip(gradients) {
+                connection.weight = connection.weight - learning_rate * gradient;
+            }
+        }
+        Ok(MseError.calculate(&outputs, targets))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_matches_layered_network() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(3)
+            .hidden_layer(4)
+            .hidden_layer(2)
+            .output_layer(2)
+            .build();
+        let mut graph = GraphNetwork::from_network(&network).unwrap();
+
+        assert_eq!(graph.get_weights(), network.get_weights());
+        for input in [[0.1, 0.5, -0.3], [1.0, -1.0, 0.0]] {
+            let expected = network.run(&input);
+            let actual = graph.run(&input).unwrap();
+            for (a, e) in actual.iter().zip(&expected) {
+                assert!((a - e).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shortcut_connections_and_training() {
+        // Two inputs feed the output both directly and through a hidden neuron
+        let mut builder = GraphBuilder::<f64>::new();
+        let a = builder.add_input();
+        let b = builder.add_input();
+        let bias = builder.add_bias();
+        let hidden = builder.add_neuron(ActivationFunction::Tanh, 1.0);
+        let output = builder.add_neuron(ActivationFunction::Linear, 1.0);
+        for (from, to, w) in [
+            (a, hidden, 0.3),
+            (b, hidden, -0.2),
+            (bias, hidden, 0.1),
+            (hidden, output, 0.5),
+            (a, output, 0.1),
+            (b, output, 0.1),
+            (bias, output, 0.0),
+        ] {
+            builder.connect(from, to, w).unwrap();
+        }
+        builder.add_output(output).unwrap();
+        let mut graph = builder.build().unwrap();
+        assert_eq!(graph.execution_order(), &[hidden, output]);
+
+        let samples = [
+            ([0.0, 1.0], [0.5]),
+            ([1.0, 0.0], [-0.5]),
+            ([1.0, 1.0], [0.2]),
+        ];
+        let mse = |graph: &mut GraphNetwork<f64>| {
+            samples
+                .iter()
+                .map(|(x, y)| (graph.run(x).unwrap()[0] - y[0]).powi(2))
+                .sum::<f64>()
+        };
+        let before = mse(&mut graph);
+        for _ in 0..200 {
+            for (x, y) in &samples {
+                graph.train_sample(x, y, 0.1).unwrap();
+            }
+        }
+        assert!(mse(&mut graph) < before * 0.1);
+    }
+
+    #[test]
+    fn test_rejects_invalid_graphs() {
+        let mut builder = GraphBuilder::<f32>::new();
+        let input = builder.add_input();
+        let first = builder.add_neuron(ActivationFunction::Sigmoid, 1.0);
+        let second = builder.add_neuron(ActivationFunction::Sigmoid, 1.0);
+        assert_eq!(
+            builder.connect(first, input, 1.0),
+            Err(GraphError::NotConnectable(input))
+        );
+        builder.connect(input, first, 1.0).unwrap();
+        builder.connect(first, second, 1.0).unwrap();
+        builder.connect(second, first, 1.0).unwrap();
+        builder.add_output(second).unwrap();
+        assert_eq!(builder.build().unwrap_err(), GraphError::Cycle(vec![1, 2]));
+    }
+}
@@ -4,6 +4,7 @@
 //! for ensuring all agent implementations work together seamlessly to achieve
 //! 100% FANN compatibility with optimal performance.
 
+use crate::numeric::cast;
 use num_traits::Float;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
@@ -115,7 +116,7 @@ pub struct IntegrationTestSuite<T: Float + Send + Default> {
     phantom: std::marker::PhantomData<T>,
 }
 
-impl<T: Float + Send + Default> IntegrationTestSuite<T> {
+impl<T: Float + Send + Sync + Default> IntegrationTestSuite<T> {
     /// Create a new integration test suite
     pub fn new(config: IntegrationConfig) -> Self {
         Self {
@@ -228,10 +229,10 @@ impl<T: Float + Send + Default> IntegrationTestSuite<T> {
         let mut outputs = Vec::new();
 
         for _ in 0..100 {
-            let input: Vec<T> = (0..4).map(|_| T::from(rng.gen::<f64>()).unwrap()).collect();
+            let input: Vec<T> = (0..4).map(|_| cast(rng.gen::<f64>())).collect();
 
             // Simple classification rule
-            let class = if input[0] > T::from(0.5).unwrap() {
+            let class = if input[0] > cast(0.5) {
                 0
             } else {
                 1
@@ -378,7 +379,7 @@ impl<T: Float + Send + Default> IntegrationTestSuite<T> {
         }
 
         // Test forward propagation
-        let input = vec![T::from(0.5).unwrap(); network.num_inputs()];
+        let input = vec![cast(0.5); network.num_inputs()];
         let output = network.run(&input);
 
         if output.len() != network.num_outputs() {
@@ -451,7 +452,7 @@ impl<T: Float + Send + Default> IntegrationTestSuite<T> {
         // Test different training algorithms
         use crate::training::IncrementalBackprop;
 
-        let _trainer = IncrementalBackprop::new(T::from(0.1).unwrap());
+        let _trainer = IncrementalBackprop::new(cast::<T>(0.1));
 
         // Train for a few epochs
         let total_error = T::zero();
@@ -520,7 +521,7 @@ impl<T: Float + Send + Default> IntegrationTestSuite<T> {
             num_candidates: 2,
             output_max_epochs: 50,
             candidate_max_epochs: 50,
-            output_target_error: T::from(0.1).unwrap(),
+            output_target_error: cast(0.1),
             verbose: false,
             ..CascadeConfig::default()
         };
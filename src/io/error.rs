@@ -23,6 +23,10 @@ pub enum IoError {
     InvalidNetwork(String),
     /// Invalid training data
     InvalidTrainingData(String),
+    /// Missing or invalid signature
+    InvalidSignature(String),
+    /// Model refused by a load policy
+    PolicyViolation(String),
 }
 
 impl fmt::Display for IoError {
@@ -35,6 +39,8 @@ impl fmt::Display for IoError {
             IoError::CompressionError(msg) => write!(f, "Compression error: {msg}"),
             IoError::InvalidNetwork(msg) => write!(f, "Invalid network: {msg}"),
             IoError::InvalidTrainingData(msg) => write!(f, "Invalid training data: {msg}"),
+            IoError::InvalidSignature(msg) => write!(f, "Invalid signature: {msg}"),
+            IoError::PolicyViolation(msg) => write!(f, "Model refused by load policy: {msg}"),
         }
     }
 }
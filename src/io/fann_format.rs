@@ -1,9 +1,113 @@
 //! FANN native file format reader and writer
+//!
+//! Reads and writes the `.net` text format of the original FANN library
+//! (`FANN_FLO_2.1`, written by FANN 2.1 to 2.3), so networks trained with
+//! FANN load without retraining and networks trained here load in FANN:
+//!
+//! - `layer_sizes` counts every layer's bias neuron, including the unused
+//!   one FANN gives the output layer.
+//! - `neurons (num_inputs, activation_function, activation_steepness)` lists
+//!   every neuron layer by layer, bias last.
+//! - `connections (connected_to_neuron, weight)` lists the incoming
+//!   connections of each neuron in the same order, addressed by global
+//!   neuron index, so sparse networks (`connection_rate` below 1) round-trip.
+//!
+//! Fixed-point `FANN_FIX` files are read by dividing by `2^decimal_point`.
+//! Files written by earlier versions of this crate (`FANN_FLO:2.1` with a
+//! `weights=` line) are still read. Shortcut networks (`network_type=1`) and
+//! files with input/output scaling have no equivalent here and are rejected,
+//! as are networks using activations FANN lacks ([`Gelu`], [`Swish`]).
+//!
+//! [`Gelu`]: ActivationFunction::Gelu
+//! [`Swish`]: ActivationFunction::Swish
 
 use crate::io::error::{IoError, IoResult};
-use crate::{Network, NetworkBuilder};
+use crate::network::NetworkSchema;
+use crate::numeric::cast;
+use crate::{ActivationFunction, Layer, Network, NetworkBuilder};
 use num_traits::Float;
-use std::io::{BufRead, BufReader, Write};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// FANN's `fann_activationfunc_enum` value for `function`
+fn fann_activation_id(function: ActivationFunction) -> Option<u32> {
+    use ActivationFunction::*;
+    Some(match function {
+        Linear => 0,
+        Threshold => 1,
+        ThresholdSymmetric => 2,
+        Sigmoid => 3,
+        SigmoidSymmetric | Tanh => 5,
+        Gaussian => 7,
+        GaussianSymmetric => 8,
+        Elliot => 10,
+        ElliotSymmetric => 11,
+        LinearPiece => 12,
+        LinearPieceSymmetric => 13,
+        SinSymmetric => 14,
+        CosSymmetric => 15,
+        Sin => 16,
+        Cos => 17,
+        ReLU => 18,
+        ReLULeaky => 19,
+        Gelu | Swish => return None,
+    })
+}
+
+/// FANN's sigmoids (3 and 4) compute `1 / (1 + exp(-2 * s * x))`, so their
+/// steepness is half of [`ActivationFunction::Sigmoid`]'s
+fn is_fann_sigmoid(id: u32) -> bool {
+    matches!(id, 3 | 4)
+}
+
+/// Activation for a FANN enum value; the stepwise sigmoids are piecewise
+/// approximations of the smooth ones and load as those
+fn activation_from_fann_id(id: u32) -> Option<ActivationFunction> {
+    use ActivationFunction::*;
+    Some(match id {
+        0 => Linear,
+        1 => Threshold,
+        2 => ThresholdSymmetric,
+        3 | 4 => Sigmoid,
+        5 | 6 => SigmoidSymmetric,
+        7 => Gaussian,
+        8 => GaussianSymmetric,
+        10 => Elliot,
+        11 => ElliotSymmetric,
+        12 => LinearPiece,
+        13 => LinearPieceSymmetric,
+        14 => SinSymmetric,
+        15 => CosSymmetric,
+        16 => Sin,
+        17 => Cos,
+        18 => ReLU,
+        19 => ReLULeaky,
+        _ => return None,
+    })
+}
+
+fn parse<V: FromStr>(key: &str, value: &str) -> IoResult<V>
+where
+    V::Err: Debug,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|e| IoError::ParseError(format!("Invalid {key} '{value}': {e:?}")))
+}
+
+/// Split `(a, b, c) (d, e, f) ...` into the fields of each tuple
+fn tuples(value: &str) -> impl Iterator<Item = Vec<&str>> {
+    value
+        .split(')')
+        .map(|tuple| tuple.trim().trim_start_matches('('))
+        .filter(|tuple| !tuple.is_empty())
+        .map(|tuple| tuple.split(',').map(str::trim).collect())
+}
 
 /// FANN file format reader
 pub struct FannReader {
@@ -17,119 +121,191 @@ impl FannReader {
     }
 
     /// Read a neural network from a FANN format file
-    pub fn read_network<T: Float + std::str::FromStr, R: std::io::Read>(
+    pub fn read_network<T: Float + FromStr, R: std::io::Read>(
         &self,
         reader: &mut R,
     ) -> IoResult<Network<T>>
     where
-        T::Err: std::fmt::Debug,
+        T::Err: Debug,
     {
         let mut buf_reader = BufReader::new(reader);
-        let mut line = String::new();
-
-        // Read version line
-        buf_reader.read_line(&mut line)?;
-        if !line.starts_with("FANN_FLO") && !line.starts_with("FANN_FIX") {
+        let mut header = String::new();
+        buf_reader.read_line(&mut header)?;
+        let header = header.trim();
+        if !header.starts_with("FANN_FLO") && !header.starts_with("FANN_FIX") {
             return Err(IoError::InvalidFileFormat(
                 "Missing FANN version header".to_string(),
             ));
         }
 
-        let mut num_layers = 0;
-        let mut connection_rate = T::one();
-        let mut layer_sizes = Vec::new();
-        let mut weights = Vec::new();
-
-        // Parse network parameters
-        loop {
-            line.clear();
-            let bytes_read = buf_reader.read_line(&mut line)?;
-            if bytes_read == 0 {
-                break; // EOF
+        // Keys like `neurons (num_inputs, ...)` are stored by their first word
+        let mut fields = HashMap::new();
+        for line in buf_reader.lines() {
+            let line = line?;
+            if let Some((key, value)) = line.split_once('=') {
+                let key = key.split(" (").next().unwrap_or(key).trim();
+                fields.insert(key.to_string(), value.trim().to_string());
             }
+        }
 
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
+        if header.starts_with("FANN_FLO:") {
+            return read_legacy(&fields);
+        }
+        let multiplier = if header.starts_with("FANN_FIX") {
+            let decimal_point: u32 = parse("decimal_point", field(&fields, "decimal_point")?)?;
+            if decimal_point > 30 {
+                return Err(IoError::InvalidNetwork(format!(
+                    "decimal_point {decimal_point} is out of range"
+                )));
             }
-
-            if let Some((key, value)) = line.split_once('=') {
-                match key {
-                    "num_layers" => {
-                        num_layers = value.parse().map_err(|e| {
-                            IoError::ParseError(format!("Invalid num_layers: {e:?}"))
-                        })?;
-                    }
-                    "connection_rate" => {
-                        connection_rate = value.parse().map_err(|e| {
-                            IoError::ParseError(format!("Invalid connection_rate: {e:?}"))
-                        })?;
-                    }
-                    "layer_sizes" => {
-                        layer_sizes = value
-                            .split_whitespace()
-                            .map(|s| s.parse())
-                            .collect::<Result<Vec<_>, _>>()
-                            .map_err(|e| {
-                                IoError::ParseError(format!("Invalid layer_sizes: {e:?}"))
-                            })?;
-                    }
-                    "weights" => {
-                        weights = value
-                            .split_whitespace()
-                            .map(|s| s.parse())
-                            .collect::<Result<Vec<_>, _>>()
-                            .map_err(|e| IoError::ParseError(format!("Invalid weights: {e:?}")))?;
-                    }
-                    _ => {
-                        // Skip unknown parameters for now
-                    }
-                }
+            Some(cast::<T>(1u64 << decimal_point))
+        } else {
+            None
+        };
+        let value = |key: &str, text: &str| -> IoResult<T> {
+            match multiplier {
+                Some(multiplier) => Ok(cast::<T>(parse::<i64>(key, text)?) / multiplier),
+                None => parse(key, text),
             }
-        }
+        };
 
-        // Validate network parameters
-        if num_layers == 0 {
+        if fields
+            .get("network_type")
+            .is_some_and(|network_type| network_type != "0")
+        {
             return Err(IoError::InvalidNetwork(
-                "num_layers must be > 0".to_string(),
+                "shortcut networks (network_type=1) are not supported".to_string(),
             ));
         }
-
-        if layer_sizes.is_empty() {
+        if fields
+            .get("scale_included")
+            .is_some_and(|scale| scale != "0")
+        {
             return Err(IoError::InvalidNetwork(
-                "layer_sizes must not be empty".to_string(),
+                "input/output scaling parameters are not supported".to_string(),
             ));
         }
 
-        if layer_sizes.len() != num_layers {
+        let connection_rate = match fields.get("connection_rate") {
+            Some(rate) => parse("connection_rate", rate)?,
+            None => T::one(),
+        };
+        let sizes: Vec<usize> = field(&fields, "layer_sizes")?
+            .split_whitespace()
+            .map(|size| parse("layer_sizes", size))
+            .collect::<IoResult<_>>()?;
+        let num_layers: usize = parse("num_layers", field(&fields, "num_layers")?)?;
+        if sizes.len() != num_layers || num_layers < 2 {
+            return Err(IoError::InvalidNetwork(format!(
+                "num_layers={num_layers} but {} layer sizes",
+                sizes.len()
+            )));
+        }
+        if sizes.iter().any(|&size| size < 2) {
             return Err(IoError::InvalidNetwork(
-                "layer_sizes length must match num_layers".to_string(),
+                "every layer needs a bias and at least one neuron".to_string(),
             ));
         }
 
-        // Build network using NetworkBuilder
-        let mut builder = NetworkBuilder::<T>::new();
+        let neurons = tuples(field(&fields, "neurons")?)
+            .map(|neuron| match neuron.as_slice() {
+                [inputs, function, steepness] => Ok((
+                    parse::<usize>("neuron inputs", inputs)?,
+                    parse::<u32>("activation function", function)?,
+                    value("activation steepness", steepness)?,
+                )),
+                _ => Err(IoError::ParseError(format!("Invalid neuron {neuron:?}"))),
+            })
+            .collect::<IoResult<Vec<_>>>()?;
+        let mut connections = tuples(fields.get("connections").map_or("", String::as_str))
+            .map(|connection| match connection.as_slice() {
+                [from, weight] => Ok((
+                    parse::<usize>("connection", from)?,
+                    value("weight", weight)?,
+                )),
+                _ => Err(IoError::ParseError(format!(
+                    "Invalid connection {connection:?}"
+                ))),
+            })
+            .collect::<IoResult<Vec<_>>>()?
+            .into_iter();
+        if neurons.len() != sizes.iter().sum::<usize>() {
+            return Err(IoError::InvalidNetwork(format!(
+                "layer_sizes describe {} neurons but {} are listed",
+                sizes.iter().sum::<usize>(),
+                neurons.len()
+            )));
+        }
+        if connections.len() != neurons.iter().map(|neuron| neuron.0).sum::<usize>() {
+            return Err(IoError::InvalidNetwork(
+                "connection count does not match the neurons' inputs".to_string(),
+            ));
+        }
 
-        for (i, &size) in layer_sizes.iter().enumerate() {
-            if i == 0 {
-                builder = builder.input_layer(size);
-            } else if i == layer_sizes.len() - 1 {
-                builder = builder.output_layer(size);
+        let mut layers = Vec::with_capacity(num_layers);
+        let mut first = 0;
+        let mut previous = 0..0;
+        for (l, &size) in sizes.iter().enumerate() {
+            let output = l == num_layers - 1;
+            let mut layer = if output {
+                Layer::new(size - 1, ActivationFunction::Linear, T::one())
             } else {
-                builder = builder.hidden_layer(size);
+                Layer::with_bias(size - 1, ActivationFunction::Linear, T::one())
+            };
+            for (i, &(num_inputs, function, steepness)) in
+                neurons[first..first + size].iter().enumerate()
+            {
+                let incoming: Vec<_> = connections.by_ref().take(num_inputs).collect();
+                // Inputs and biases have no incoming connections, and FANN
+                // ignores their activation
+                if l == 0 || i == size - 1 {
+                    if num_inputs != 0 {
+                        return Err(IoError::InvalidNetwork(format!(
+                            "neuron {} cannot have inputs",
+                            first + i
+                        )));
+                    }
+                    continue;
+                }
+                let id = function;
+                let function = activation_from_fann_id(id).ok_or_else(|| {
+                    IoError::InvalidNetwork(format!("unsupported activation function {id}"))
+                })?;
+                let steepness = if is_fann_sigmoid(id) {
+                    steepness + steepness
+                } else {
+                    steepness
+                };
+                // ReLU ignores steepness here; fold FANN's into the weights,
+                // which is exact since the steepness scales the sum
+                let (steepness, scale) = if function.uses_steepness() {
+                    (steepness, T::one())
+                } else {
+                    (T::one(), steepness)
+                };
+                let neuron = &mut layer.neurons[i];
+                neuron.activation_function = function;
+                neuron.activation_steepness = steepness;
+                for (from, weight) in incoming {
+                    if !previous.contains(&from) {
+                        return Err(IoError::InvalidNetwork(format!(
+                            "neuron {} is connected to neuron {from} outside the previous layer",
+                            first + i
+                        )));
+                    }
+                    neuron.add_connection(from - previous.start, weight * scale);
+                }
             }
+            layers.push(layer);
+            previous = first..first + size;
+            first += size;
         }
 
-        let mut network = builder.connection_rate(connection_rate).build();
-
-        // Set weights if provided
-        if !weights.is_empty() {
-            network
-                .set_weights(&weights)
-                .map_err(|e| IoError::InvalidNetwork(format!("Failed to set weights: {e}")))?;
-        }
-
-        Ok(network)
+        Ok(Network {
+            layers,
+            connection_rate,
+            schema: NetworkSchema::default(),
+        })
     }
 }
 
@@ -139,6 +315,50 @@ impl Default for FannReader {
     }
 }
 
+fn field<'a>(fields: &'a HashMap<String, String>, key: &str) -> IoResult<&'a str> {
+    fields
+        .get(key)
+        .map(String::as_str)
+        .ok_or_else(|| IoError::InvalidFileFormat(format!("Missing {key}")))
+}
+
+/// Layout written by earlier versions of this crate: layer sizes without
+/// biases and the weights in [`Network::get_weights`] order
+fn read_legacy<T: Float + FromStr>(fields: &HashMap<String, String>) -> IoResult<Network<T>>
+where
+    T::Err: Debug,
+{
+    let num_layers: usize = parse("num_layers", field(fields, "num_layers")?)?;
+    let connection_rate = match fields.get("connection_rate") {
+        Some(rate) => parse("connection_rate", rate)?,
+        None => T::one(),
+    };
+    let layer_sizes: Vec<usize> = field(fields, "layer_sizes")?
+        .split_whitespace()
+        .map(|size| parse("layer_sizes", size))
+        .collect::<IoResult<_>>()?;
+    if num_layers == 0 || layer_sizes.len() != num_layers {
+        return Err(IoError::InvalidNetwork(
+            "layer_sizes length must match num_layers".to_string(),
+        ));
+    }
+
+    let mut network = NetworkBuilder::<T>::new()
+        .layers_from_sizes(&layer_sizes)
+        .connection_rate(connection_rate)
+        .build();
+    if let Some(weights) = fields.get("weights") {
+        let weights: Vec<T> = weights
+            .split_whitespace()
+            .map(|weight| parse("weights", weight))
+            .collect::<IoResult<_>>()?;
+        network
+            .set_weights(&weights)
+            .map_err(|e| IoError::InvalidNetwork(format!("Failed to set weights: {e}")))?;
+    }
+    Ok(network)
+}
+
 /// FANN file format writer
 pub struct FannWriter {
     // Configuration options could go here
@@ -151,16 +371,34 @@ impl FannWriter {
     }
 
     /// Write a neural network to FANN format
+    ///
+    /// Fails with [`IoError::InvalidNetwork`] for recurrent networks and if
+    /// a neuron uses an activation function FANN does not have.
     pub fn write_network<T: Float + std::fmt::Display, W: Write>(
         &self,
         network: &Network<T>,
         writer: &mut W,
     ) -> IoResult<()> {
-        // Write version header
-        writeln!(writer, "FANN_FLO:2.1")?;
+        // Check before writing anything
+        network
+            .check_feed_forward()
+            .map_err(|e| IoError::InvalidNetwork(e.to_string()))?;
+        let mut neuron_ids = Vec::new();
+        for layer in network.layers.iter().skip(1) {
+            for neuron in layer.neurons.iter().filter(|neuron| !neuron.is_bias) {
+                let id = fann_activation_id(neuron.activation_function).ok_or_else(|| {
+                    IoError::InvalidNetwork(format!(
+                        "{} has no FANN equivalent",
+                        neuron.activation_function.name()
+                    ))
+                })?;
+                neuron_ids.push(id);
+            }
+        }
 
-        // Write network parameters
+        writeln!(writer, "FANN_FLO_2.1")?;
         writeln!(writer, "num_layers={}", network.num_layers())?;
+        writeln!(writer, "learning_rate=0.700000")?;
         writeln!(writer, "connection_rate={:.6}", network.connection_rate)?;
         writeln!(writer, "network_type=0")?;
         writeln!(writer, "learning_momentum=0.000000")?;
@@ -197,22 +435,69 @@ impl FannWriter {
             "cascade_activation_steepnesses=0.250000 0.500000 0.750000 1.000000 "
         )?;
 
-        // Write layer sizes
+        // FANN gives the output layer a bias neuron too
+        let last = network.layers.len().saturating_sub(1);
         write!(writer, "layer_sizes=")?;
         for layer in &network.layers {
-            write!(writer, "{} ", layer.num_regular_neurons())?;
+            write!(writer, "{} ", layer.num_regular_neurons() + 1)?;
+        }
+        writeln!(writer)?;
+        writeln!(writer, "scale_included=0")?;
+
+        let inactive = "(0, 0, 0.00000000000000000000e+00) ";
+        write!(
+            writer,
+            "neurons (num_inputs, activation_function, activation_steepness)="
+        )?;
+        let mut ids = neuron_ids.into_iter();
+        for (l, layer) in network.layers.iter().enumerate() {
+            for neuron in &layer.neurons {
+                if l == 0 || neuron.is_bias {
+                    write!(writer, "{inactive}")?;
+                    continue;
+                }
+                let id = ids.next().unwrap_or_default();
+                let steepness = if neuron.activation_function.uses_steepness() {
+                    let steepness = neuron.activation_steepness.to_f64().unwrap_or(f64::NAN);
+                    if is_fann_sigmoid(id) {
+                        steepness / 2.0
+                    } else {
+                        steepness
+                    }
+                } else {
+                    1.0
+                };
+                write!(
+                    writer,
+                    "({}, {id}, {steepness:.20e}) ",
+                    neuron.connections.len()
+                )?;
+            }
+            if l == last {
+                write!(writer, "{inactive}")?;
+            }
         }
         writeln!(writer)?;
 
-        // Write weights
-        let weights = network.get_weights();
-        if !weights.is_empty() {
-            write!(writer, "weights=")?;
-            for weight in weights {
-                write!(writer, "{weight:.6} ")?;
+        write!(writer, "connections (connected_to_neuron, weight)=")?;
+        let mut first = 0;
+        for (l, layer) in network.layers.iter().enumerate() {
+            if l > 0 {
+                let previous = first - network.layers[l - 1].neurons.len();
+                for neuron in &layer.neurons {
+                    for connection in &neuron.connections {
+                        let weight = connection.weight.to_f64().unwrap_or(f64::NAN);
+                        write!(
+                            writer,
+                            "({}, {weight:.20e}) ",
+                            previous + connection.from_neuron
+                        )?;
+                    }
+                }
             }
-            writeln!(writer)?;
+            first += layer.neurons.len();
         }
+        writeln!(writer)?;
 
         Ok(())
     }
@@ -223,3 +508,220 @@ impl Default for FannWriter {
         Self::new()
     }
 }
+
+impl<T: Float> Network<T> {
+    /// Load a network saved by FANN's `fann_save` (or by
+    /// [`save_fann_file`](Self::save_fann_file))
+    pub fn from_fann_file<P: AsRef<Path>>(path: P) -> IoResult<Self>
+    where
+        T: FromStr,
+        T::Err: Debug,
+    {
+        let mut file = File::open(path)?;
+        FannReader::new().read_network(&mut file)
+    }
+
+    /// Save in FANN's `.net` format, loadable with `fann_create_from_file`
+    pub fn save_fann_file<P: AsRef<Path>>(&self, path: P) -> IoResult<()>
+    where
+        T: std::fmt::Display,
+    {
+        let mut writer = BufWriter::new(File::create(path)?);
+        FannWriter::new().write_network(self, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2-3-1 network saved by FANN 2.2 (`fann_save`), with a sigmoid
+    /// symmetric hidden layer and a linear output
+    const FANN_NET: &str = "FANN_FLO_2.1
+num_layers=3
+learning_rate=0.700000
+connection_rate=1.000000
+network_type=0
+learning_momentum=0.000000
+training_algorithm=2
+train_error_function=1
+train_stop_function=0
+cascade_output_change_fraction=0.010000
+quickprop_decay=-0.000100
+quickprop_mu=1.750000
+rprop_increase_factor=1.200000
+rprop_decrease_factor=0.500000
+rprop_delta_min=0.000000
+rprop_delta_max=50.000000
+rprop_delta_zero=0.100000
+cascade_output_stagnation_epochs=12
+cascade_candidate_change_fraction=0.010000
+cascade_candidate_stagnation_epochs=12
+cascade_max_out_epochs=150
+cascade_min_out_epochs=50
+cascade_max_cand_epochs=150
+cascade_min_cand_epochs=50
+cascade_num_candidate_groups=2
+bit_fail_limit=3.49999994039535522461e-01
+cascade_candidate_limit=1.00000000000000000000e+03
+cascade_weight_multiplier=4.00000005960464477539e-01
+cascade_activation_functions_count=10
+cascade_activation_functions=3 5 7 8 10 11 14 15 16 17
+cascade_activation_steepnesses_count=4
+cascade_activation_steepnesses=2.50000000000000000000e-01 5.00000000000000000000e-01 7.50000000000000000000e-01 1.00000000000000000000e+00
+layer_sizes=3 4 2
+scale_included=0
+neurons (num_inputs, activation_function, activation_steepness)=(0, 0, 0.00000000000000000000e+00) (0, 0, 0.00000000000000000000e+00) (0, 0, 0.00000000000000000000e+00) (3, 5, 5.00000000000000000000e-01) (3, 5, 5.00000000000000000000e-01) (3, 5, 5.00000000000000000000e-01) (0, 5, 5.00000000000000000000e-01) (4, 0, 1.00000000000000000000e+00) (0, 0, 1.00000000000000000000e+00)
+connections (connected_to_neuron, weight)=(0, 1.50000000000000000000e+00) (1, -2.00000000000000000000e+00) (2, 2.50000000000000000000e-01) (0, -1.00000000000000000000e+00) (1, 5.00000000000000000000e-01) (2, 0.00000000000000000000e+00) (0, 7.50000000000000000000e-01) (1, 7.50000000000000000000e-01) (2, -5.00000000000000000000e-01) (3, 1.00000000000000000000e+00) (4, -1.00000000000000000000e+00) (5, 2.00000000000000000000e+00) (6, 1.00000000000000000000e-01)
+";
+
+    #[test]
+    fn test_reads_fann_file_and_round_trips() {
+        let mut network: Network<f64> = FannReader::new()
+            .read_network(&mut FANN_NET.as_bytes())
+            .unwrap();
+        assert_eq!(network.num_inputs(), 2);
+        assert_eq!(network.num_outputs(), 1);
+        assert_eq!(network.total_connections(), 13);
+        let hidden = &network.layers[1].neurons[0];
+        assert_eq!(
+            hidden.activation_function,
+            ActivationFunction::SigmoidSymmetric
+        );
+        assert_eq!(hidden.activation_steepness, 0.5);
+
+        // FANN: hidden_j = tanh(0.5 * (w_j0 x0 + w_j1 x1 + w_j2)),
+        // output = h0 - h1 + 2 h2 + 0.1
+        let (x0, x1) = (0.3, -0.8);
+        let h = [
+            (0.5 * (1.5 * x0 - 2.0 * x1 + 0.25f64)).tanh(),
+            (0.5 * (0.5 * x1 - x0)).tanh(),
+            (0.5 * (0.75 * x0 + 0.75 * x1 - 0.5)).tanh(),
+        ];
+        let expected = h[0] - h[1] + 2.0 * h[2] + 0.1;
+        assert!((network.run(&[x0, x1])[0] - expected).abs() < 1e-12);
+
+        let mut written = Vec::new();
+        FannWriter::new()
+            .write_network(&network, &mut written)
+            .unwrap();
+        let text = String::from_utf8(written).unwrap();
+        assert!(text.starts_with("FANN_FLO_2.1\n"));
+        assert!(text.contains("\nlayer_sizes=3 4 2 \n"));
+        let mut reread: Network<f64> = FannReader::new()
+            .read_network(&mut text.as_bytes())
+            .unwrap();
+        assert_eq!(reread.get_weights(), network.get_weights());
+        assert_eq!(reread.run(&[x0, x1]), network.run(&[x0, x1]));
+    }
+
+    #[test]
+    fn test_sigmoid_steepness_round_trips() {
+        // FANN's sigmoid output: 1 / (1 + exp(-2 * 0.5 * sum))
+        let output = "(4, 0, 1.00000000000000000000e+00)";
+        let sigmoid = "(4, 3, 5.00000000000000000000e-01)";
+        let fann = FANN_NET.replace(output, sigmoid);
+        let mut network: Network<f64> = FannReader::new()
+            .read_network(&mut fann.as_bytes())
+            .unwrap();
+        let neuron = &network.layers[2].neurons[0];
+        assert_eq!(neuron.activation_function, ActivationFunction::Sigmoid);
+        assert_eq!(neuron.activation_steepness, 1.0);
+
+        let (x0, x1) = (0.3, -0.8);
+        let h = [
+            (0.5 * (1.5 * x0 - 2.0 * x1 + 0.25f64)).tanh(),
+            (0.5 * (0.5 * x1 - x0)).tanh(),
+            (0.5 * (0.75 * x0 + 0.75 * x1 - 0.5)).tanh(),
+        ];
+        let sum = h[0] - h[1] + 2.0 * h[2] + 0.1;
+        let expected = 1.0 / (1.0 + (-2.0 * 0.5 * sum).exp());
+        assert!((network.run(&[x0, x1])[0] - expected).abs() < 1e-12);
+
+        let mut written = Vec::new();
+        FannWriter::new()
+            .write_network(&network, &mut written)
+            .unwrap();
+        let text = String::from_utf8(written).unwrap();
+        assert!(text.contains("(4, 3, 5.00000000000000000000e-1)"));
+        let mut reread: Network<f64> = FannReader::new()
+            .read_network(&mut text.as_bytes())
+            .unwrap();
+        assert_eq!(reread.run(&[x0, x1]), network.run(&[x0, x1]));
+
+        // The stepwise approximation loads as the smooth sigmoid
+        let stepwise = FANN_NET.replace(output, "(4, 4, 5.00000000000000000000e-01)");
+        let network: Network<f64> = FannReader::new()
+            .read_network(&mut stepwise.as_bytes())
+            .unwrap();
+        assert_eq!(network.layers[2].neurons[0].activation_steepness, 1.0);
+    }
+
+    #[test]
+    fn test_sparse_fixed_and_unsupported() {
+        // Sparse networks keep exactly their connections
+        let mut sparse: Network<f32> = NetworkBuilder::new()
+            .input_layer(6)
+            .hidden_layer(5)
+            .output_layer(2)
+            .connection_rate(0.5)
+            .build();
+        let mut written = Vec::new();
+        FannWriter::new()
+            .write_network(&sparse, &mut written)
+            .unwrap();
+        let mut reread: Network<f32> = FannReader::new()
+            .read_network(&mut written.as_slice())
+            .unwrap();
+        assert_eq!(reread.total_connections(), sparse.total_connections());
+        assert_eq!(reread.get_weights(), sparse.get_weights());
+        let input = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6];
+        assert_eq!(reread.run(&input), sparse.run(&input));
+
+        // Fixed point: 2.5 and -1.0 with decimal_point=4, ReLU steepness
+        // folded into the weights
+        let fixed = "FANN_FIX_2.1\nnum_layers=2\ndecimal_point=4\nconnection_rate=1.000000\n\
+                     network_type=0\nlayer_sizes=2 2 \n\
+                     neurons (num_inputs, activation_function, activation_steepness)=\
+                     (0, 0, 0) (0, 0, 0) (2, 18, 32) (0, 0, 0) \n\
+                     connections (connected_to_neuron, weight)=(0, 40) (1, -16) \n";
+        let mut network: Network<f32> = FannReader::new()
+            .read_network(&mut fixed.as_bytes())
+            .unwrap();
+        assert_eq!(network.get_weights(), vec![5.0, -2.0]);
+        assert_eq!(network.run(&[1.0])[0], 3.0);
+
+        let shortcut = FANN_NET.replace("network_type=0", "network_type=1");
+        assert!(FannReader::new()
+            .read_network::<f32, _>(&mut shortcut.as_bytes())
+            .is_err());
+        let gelu: Network<f32> = NetworkBuilder::new()
+            .input_layer(1)
+            .output_layer_with_activation(1, ActivationFunction::Gelu, 1.0)
+            .build();
+        assert!(matches!(
+            FannWriter::new().write_network(&gelu, &mut Vec::new()),
+            Err(IoError::InvalidNetwork(_))
+        ));
+    }
+
+    #[test]
+    fn test_reads_legacy_layout_and_files() {
+        let legacy = "FANN_FLO:2.1\nnum_layers=2\nconnection_rate=1.000000\n\
+                      layer_sizes=1 1 \nweights=0.5 -0.25 \n";
+        let network: Network<f32> = FannReader::new()
+            .read_network(&mut legacy.as_bytes())
+            .unwrap();
+        assert_eq!(network.get_weights(), vec![0.5, -0.25]);
+
+        let path = std::env::temp_dir().join(format!("fann_{}.net", std::process::id()));
+        std::fs::write(&path, FANN_NET).unwrap();
+        let network = Network::<f32>::from_fann_file(&path).unwrap();
+        network.save_fann_file(&path).unwrap();
+        let reloaded = Network::<f32>::from_fann_file(&path).unwrap();
+        assert_eq!(reloaded.get_weights(), network.get_weights());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
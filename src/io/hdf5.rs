@@ -0,0 +1,826 @@
+//! Minimal read-only HDF5 parser
+//!
+//! Enough of the HDF5 file format to read Keras `.h5` model files without
+//! linking the HDF5 C library: groups, fixed-length string attributes and
+//! uncompressed floating-point datasets. Both old-style groups (symbol
+//! tables, as `h5py` writes by default) and compact new-style groups (link
+//! messages) are followed, in files with superblock versions 0 to 3.
+//!
+//! Chunked or compressed datasets, variable-length strings, dense link or
+//! attribute storage and shared datatypes are reported as unsupported
+//! rather than misread. Checksums are not verified.
+
+use crate::io::error::{IoError, IoResult};
+use std::ops::Range;
+
+const SIGNATURE: &[u8] = b"\x89HDF\r\n\x1a\n";
+
+const MSG_DATASPACE: u16 = 0x0001;
+const MSG_LINK_INFO: u16 = 0x0002;
+const MSG_DATATYPE: u16 = 0x0003;
+const MSG_LINK: u16 = 0x0006;
+const MSG_LAYOUT: u16 = 0x0008;
+const MSG_ATTRIBUTE: u16 = 0x000C;
+const MSG_CONTINUATION: u16 = 0x0010;
+const MSG_SYMBOL_TABLE: u16 = 0x0011;
+const MSG_ATTRIBUTE_INFO: u16 = 0x0015;
+
+fn invalid(reason: impl std::fmt::Display) -> IoError {
+    IoError::InvalidFileFormat(format!("bad HDF5 file: {reason}"))
+}
+
+/// Value of an attribute or dataset this parser can decode
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Hdf5Value {
+    Strings(Vec<String>),
+    Floats { shape: Vec<usize>, data: Vec<f64> },
+}
+
+/// Element type of an attribute or dataset
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Datatype {
+    Float { size: usize, big_endian: bool },
+    FixedString { size: usize },
+}
+
+/// Messages of one object header, as ranges into the file
+struct ObjectHeader {
+    messages: Vec<(u16, Range<usize>)>,
+}
+
+impl ObjectHeader {
+    fn find(&self, kind: u16) -> Option<Range<usize>> {
+        self.messages
+            .iter()
+            .find(|(t, _)| *t == kind)
+            .map(|(_, range)| range.clone())
+    }
+}
+
+/// An HDF5 file held in memory
+pub(crate) struct Hdf5File<'a> {
+    bytes: &'a [u8],
+    offset_size: usize,
+    length_size: usize,
+    base: u64,
+    root: u64,
+}
+
+impl<'a> Hdf5File<'a> {
+    /// Locate and parse the superblock
+    pub(crate) fn parse(bytes: &'a [u8]) -> IoResult<Self> {
+        // The superblock sits at 0 or after a user block of 512, 1024, ...
+        let mut start = 0usize;
+        while !bytes
+            .get(start..)
+            .is_some_and(|rest| rest.starts_with(SIGNATURE))
+        {
+            start = if start == 0 { 512 } else { start * 2 };
+            if start >= bytes.len() {
+                return Err(invalid("no superblock"));
+            }
+        }
+        let byte = |i: usize| {
+            bytes
+                .get(start + i)
+                .copied()
+                .ok_or_else(|| invalid("truncated superblock"))
+        };
+        let version = byte(8)?;
+        let mut file = Self {
+            bytes,
+            offset_size: 8,
+            length_size: 8,
+            base: 0,
+            root: 0,
+        };
+        match version {
+            0 | 1 => {
+                file.offset_size = byte(13)? as usize;
+                file.length_size = byte(14)? as usize;
+                file.check_sizes()?;
+                // Flags, then the group K values, plus indexed storage K in v1
+                let addresses = start + 24 + if version == 1 { 4 } else { 0 };
+                file.base = file.read_offset(addresses)?;
+                // Root symbol table entry: link name offset, object header
+                let entry = addresses + 4 * file.offset_size;
+                file.root = file.read_offset(entry + file.offset_size)?;
+            }
+            2 | 3 => {
+                file.offset_size = byte(9)? as usize;
+                file.length_size = byte(10)? as usize;
+                file.check_sizes()?;
+                let addresses = start + 12;
+                file.base = file.read_offset(addresses)?;
+                file.root = file.read_offset(addresses + 3 * file.offset_size)?;
+            }
+            other => return Err(invalid(format!("unsupported superblock version {other}"))),
+        }
+        Ok(file)
+    }
+
+    fn check_sizes(&self) -> IoResult<()> {
+        for size in [self.offset_size, self.length_size] {
+            if ![2, 4, 8].contains(&size) {
+                return Err(invalid(format!("unsupported field size {size}")));
+            }
+        }
+        Ok(())
+    }
+
+    fn slice(&self, at: usize, len: usize) -> IoResult<&'a [u8]> {
+        at.checked_add(len)
+            .and_then(|end| self.bytes.get(at..end))
+            .ok_or_else(|| invalid("read past the end of the file"))
+    }
+
+    /// End of the `len` bytes at `at`, which must lie inside the file
+    fn end_of(&self, at: usize, len: usize) -> IoResult<usize> {
+        Ok(at + self.slice(at, len)?.len())
+    }
+
+    fn read_uint(&self, at: usize, size: usize) -> IoResult<u64> {
+        Ok(self
+            .slice(at, size)?
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &b| (acc << 8) | u64::from(b)))
+    }
+
+    fn read_offset(&self, at: usize) -> IoResult<u64> {
+        self.read_uint(at, self.offset_size)
+    }
+
+    fn read_length(&self, at: usize) -> IoResult<u64> {
+        self.read_uint(at, self.length_size)
+    }
+
+    fn is_undefined(&self, address: u64) -> bool {
+        address == u64::MAX >> (64 - 8 * self.offset_size)
+    }
+
+    /// File position of a stored address
+    fn position(&self, address: u64) -> IoResult<usize> {
+        address
+            .checked_add(self.base)
+            .and_then(|p| usize::try_from(p).ok())
+            .filter(|&p| p < self.bytes.len())
+            .ok_or_else(|| invalid(format!("address {address} is outside the file")))
+    }
+
+    fn expect_signature(&self, at: usize, signature: &[u8]) -> IoResult<()> {
+        if self.slice(at, signature.len())? != signature {
+            return Err(invalid(format!(
+                "expected {} at {at}",
+                String::from_utf8_lossy(signature)
+            )));
+        }
+        Ok(())
+    }
+
+    fn object_header(&self, address: u64) -> IoResult<ObjectHeader> {
+        let at = self.position(address)?;
+        let mut header = ObjectHeader {
+            messages: Vec::new(),
+        };
+        let mut blocks = Vec::new();
+        let v2 = self.slice(at, 4)? == b"OHDR";
+        let mut creation_order = false;
+        if v2 {
+            let flags = self.slice(at + 5, 1)?[0];
+            creation_order = flags & 0x04 != 0;
+            let mut pos = at + 6;
+            if flags & 0x20 != 0 {
+                pos += 16;
+            }
+            if flags & 0x10 != 0 {
+                pos += 4;
+            }
+            let size_len = 1usize << (flags & 0x03);
+            let size = self.read_uint(pos, size_len)? as usize;
+            pos += size_len;
+            blocks.push(pos..self.end_of(pos, size)?);
+        } else {
+            if self.slice(at, 1)?[0] != 1 {
+                return Err(invalid("unsupported object header version"));
+            }
+            let size = self.read_uint(at + 8, 4)? as usize;
+            blocks.push(at + 16..self.end_of(at + 16, size)?);
+        }
+
+        while let Some(block) = blocks.pop() {
+            let mut pos = block.start;
+            // v2 messages need at least a 4-byte prefix; the tail is a gap
+            let prefix = if v2 {
+                4 + 2 * usize::from(creation_order)
+            } else {
+                8
+            };
+            while pos + prefix <= block.end {
+                let (kind, size) = if v2 {
+                    (
+                        u16::from(self.slice(pos, 1)?[0]),
+                        self.read_uint(pos + 1, 2)? as usize,
+                    )
+                } else {
+                    (
+                        self.read_uint(pos, 2)? as u16,
+                        self.read_uint(pos + 2, 2)? as usize,
+                    )
+                };
+                let data = pos + prefix..pos + prefix + size;
+                self.slice(data.start, size)?;
+                if kind == MSG_CONTINUATION {
+                    let start = self.position(self.read_offset(data.start)?)?;
+                    let len = self.read_length(data.start + self.offset_size)? as usize;
+                    if v2 {
+                        self.expect_signature(start, b"OCHK")?;
+                        // Signature in front, checksum behind
+                        blocks.push(start + 4..self.end_of(start, len)?.saturating_sub(4));
+                    } else {
+                        blocks.push(start..self.end_of(start, len)?);
+                    }
+                } else if kind != 0 {
+                    header.messages.push((kind, data.clone()));
+                }
+                pos = data.end;
+            }
+        }
+        Ok(header)
+    }
+
+    /// Address of the object at `path` (components separated by `/`)
+    /// below the root group
+    pub(crate) fn lookup(&self, path: &str) -> IoResult<u64> {
+        self.lookup_from(self.root, path)
+    }
+
+    /// Address of the object at `path` below the group at `group`
+    pub(crate) fn lookup_from(&self, group: u64, path: &str) -> IoResult<u64> {
+        let mut address = group;
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            address = self
+                .child(address, name)?
+                .ok_or_else(|| invalid(format!("no object named {path:?}")))?;
+        }
+        Ok(address)
+    }
+
+    /// Address of the link `name` in the group at `group`
+    fn child(&self, group: u64, name: &str) -> IoResult<Option<u64>> {
+        let header = self.object_header(group)?;
+        if let Some(table) = header.find(MSG_SYMBOL_TABLE) {
+            let btree = self.read_offset(table.start)?;
+            let heap = self.read_offset(table.start + self.offset_size)?;
+            let heap_data = self.local_heap(heap)?;
+            return self.find_in_btree(btree, heap_data, name);
+        }
+        for (kind, data) in &header.messages {
+            if *kind == MSG_LINK {
+                if let Some((link, address)) = self.parse_link(data.clone())? {
+                    if link == name.as_bytes() {
+                        return Ok(Some(address));
+                    }
+                }
+            }
+        }
+        if let Some(info) = header.find(MSG_LINK_INFO) {
+            let flags = self.slice(info.start + 1, 1)?[0];
+            let heap_at = info.start + 2 + if flags & 0x01 != 0 { 8 } else { 0 };
+            if !self.is_undefined(self.read_offset(heap_at)?) {
+                return Err(invalid("groups with dense link storage are not supported"));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Start of the data segment of the local heap at `address`
+    fn local_heap(&self, address: u64) -> IoResult<usize> {
+        let at = self.position(address)?;
+        self.expect_signature(at, b"HEAP")?;
+        let data = self.read_offset(at + 8 + 2 * self.length_size)?;
+        self.position(data)
+    }
+
+    fn heap_name(&self, heap_data: usize, offset: u64) -> IoResult<&'a [u8]> {
+        let start = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| heap_data.checked_add(offset))
+            .ok_or_else(|| invalid("link name outside the heap"))?;
+        let rest = self
+            .bytes
+            .get(start..)
+            .ok_or_else(|| invalid("link name outside the file"))?;
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| invalid("unterminated link name"))?;
+        Ok(&rest[..len])
+    }
+
+    fn find_in_btree(&self, address: u64, heap_data: usize, name: &str) -> IoResult<Option<u64>> {
+        let at = self.position(address)?;
+        self.expect_signature(at, b"TREE")?;
+        let level = self.slice(at + 5, 1)?[0];
+        let entries = self.read_uint(at + 6, 2)? as usize;
+        let children = at + 8 + 2 * self.offset_size;
+        let stride = self.length_size + self.offset_size;
+        for i in 0..entries {
+            let child = self.read_offset(children + i * stride + self.length_size)?;
+            let found = if level > 0 {
+                self.find_in_btree(child, heap_data, name)?
+            } else {
+                self.find_in_symbol_node(child, heap_data, name)?
+            };
+            if found.is_some() {
+                return Ok(found);
+            }
+        }
+        Ok(None)
+    }
+
+    fn find_in_symbol_node(
+        &self,
+        address: u64,
+        heap_data: usize,
+        name: &str,
+    ) -> IoResult<Option<u64>> {
+        let at = self.position(address)?;
+        self.expect_signature(at, b"SNOD")?;
+        let count = self.read_uint(at + 6, 2)? as usize;
+        let entry_size = 2 * self.offset_size + 24;
+        for i in 0..count {
+            let entry = at + 8 + i * entry_size;
+            let link = self.heap_name(heap_data, self.read_offset(entry)?)?;
+            if link == name.as_bytes() {
+                return Ok(Some(self.read_offset(entry + self.offset_size)?));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Name and target of a hard link message; `None` for soft and
+    /// external links
+    fn parse_link(&self, data: Range<usize>) -> IoResult<Option<(&'a [u8], u64)>> {
+        let flags = self.slice(data.start + 1, 1)?[0];
+        let mut pos = data.start + 2;
+        let mut link_type = 0;
+        if flags & 0x08 != 0 {
+            link_type = self.slice(pos, 1)?[0];
+            pos += 1;
+        }
+        if flags & 0x04 != 0 {
+            pos += 8;
+        }
+        if flags & 0x10 != 0 {
+            pos += 1;
+        }
+        let len_size = 1usize << (flags & 0x03);
+        let len = self.read_uint(pos, len_size)? as usize;
+        pos += len_size;
+        let name = self.slice(pos, len)?;
+        if link_type != 0 {
+            return Ok(None);
+        }
+        Ok(Some((name, self.read_offset(pos + len)?)))
+    }
+
+    /// Decode the attribute `name` of the object at `address`
+    pub(crate) fn attribute(&self, address: u64, name: &str) -> IoResult<Option<Hdf5Value>> {
+        let header = self.object_header(address)?;
+        for (kind, data) in &header.messages {
+            if *kind != MSG_ATTRIBUTE {
+                continue;
+            }
+            let at = data.start;
+            let version = self.slice(at, 1)?[0];
+            let flags = self.slice(at + 1, 1)?[0];
+            let name_size = self.read_uint(at + 2, 2)? as usize;
+            let type_size = self.read_uint(at + 4, 2)? as usize;
+            let space_size = self.read_uint(at + 6, 2)? as usize;
+            let (mut pos, pad) = match version {
+                1 => (at + 8, 8),
+                2 => (at + 8, 1),
+                3 => (at + 9, 1),
+                other => return Err(invalid(format!("unsupported attribute version {other}"))),
+            };
+            let padded = |size: usize| size.div_ceil(pad) * pad;
+            let attribute = self.slice(pos, name_size)?;
+            let attribute = attribute.split(|&b| b == 0).next().unwrap_or_default();
+            pos += padded(name_size);
+            if attribute != name.as_bytes() {
+                continue;
+            }
+            if version > 1 && flags & 0x03 != 0 {
+                return Err(invalid("shared attribute datatypes are not supported"));
+            }
+            let datatype = self.datatype(pos..pos + type_size)?;
+            pos += padded(type_size);
+            let shape = self.dataspace(pos..pos + space_size)?;
+            pos += padded(space_size);
+            return self.decode(datatype, shape, pos).map(Some);
+        }
+        if let Some(info) = header.find(MSG_ATTRIBUTE_INFO) {
+            let flags = self.slice(info.start + 1, 1)?[0];
+            let heap_at = info.start + 2 + if flags & 0x01 != 0 { 2 } else { 0 };
+            if !self.is_undefined(self.read_offset(heap_at)?) {
+                return Err(invalid("dense attribute storage is not supported"));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Decode the dataset at `address`
+    pub(crate) fn dataset(&self, address: u64) -> IoResult<Hdf5Value> {
+        let header = self.object_header(address)?;
+        let message = |kind, what| {
+            header
+                .find(kind)
+                .ok_or_else(|| invalid(format!("no {what}")))
+        };
+        let datatype = self.datatype(message(MSG_DATATYPE, "datatype")?)?;
+        let shape = self.dataspace(message(MSG_DATASPACE, "dataspace")?)?;
+        let layout = message(MSG_LAYOUT, "data layout")?;
+
+        let version = self.slice(layout.start, 1)?[0];
+        let data = match version {
+            3 => match self.slice(layout.start + 1, 1)?[0] {
+                0 => layout.start + 4,
+                1 => {
+                    let address = self.read_offset(layout.start + 2)?;
+                    if self.is_undefined(address) {
+                        return Err(invalid("dataset was never written"));
+                    }
+                    self.position(address)?
+                }
+                _ => return Err(invalid("chunked datasets are not supported")),
+            },
+            1 | 2 => {
+                let rank = self.slice(layout.start + 1, 1)?[0] as usize;
+                match self.slice(layout.start + 2, 1)?[0] {
+                    0 => layout.start + 8 + 4 * rank + 4,
+                    1 => self.position(self.read_offset(layout.start + 8)?)?,
+                    _ => return Err(invalid("chunked datasets are not supported")),
+                }
+            }
+            other => return Err(invalid(format!("unsupported layout version {other}"))),
+        };
+        self.decode(datatype, shape, data)
+    }
+
+    fn datatype(&self, range: Range<usize>) -> IoResult<Datatype> {
+        let bytes = self.slice(range.start, 8)?;
+        let size = self.read_uint(range.start + 4, 4)? as usize;
+        match bytes[0] & 0x0f {
+            1 if size == 4 || size == 8 => Ok(Datatype::Float {
+                size,
+                big_endian: bytes[1] & 0x01 != 0,
+            }),
+            3 => Ok(Datatype::FixedString { size }),
+            9 => Err(invalid("variable-length strings are not supported")),
+            class => Err(invalid(format!(
+                "unsupported datatype class {class} of size {size}"
+            ))),
+        }
+    }
+
+    fn dataspace(&self, range: Range<usize>) -> IoResult<Vec<usize>> {
+        let version = self.slice(range.start, 1)?[0];
+        let rank = self.slice(range.start + 1, 1)?[0] as usize;
+        let dims = match version {
+            1 => range.start + 8,
+            2 => {
+                // Type 2 is a null dataspace without elements
+                if self.slice(range.start + 3, 1)?[0] == 2 {
+                    return Ok(vec![0]);
+                }
+                range.start + 4
+            }
+            other => return Err(invalid(format!("unsupported dataspace version {other}"))),
+        };
+        (0..rank)
+            .map(|i| {
+                let dim = self.read_length(dims + i * self.length_size)?;
+                usize::try_from(dim).map_err(|_| invalid("dimension too large"))
+            })
+            .collect()
+    }
+
+    fn decode(&self, datatype: Datatype, shape: Vec<usize>, at: usize) -> IoResult<Hdf5Value> {
+        let count = shape
+            .iter()
+            .try_fold(1usize, |acc, &dim| acc.checked_mul(dim))
+            .ok_or_else(|| invalid("dataspace is too large"))?;
+        match datatype {
+            Datatype::Float { size, big_endian } => {
+                let bytes = self.slice(
+                    at,
+                    count
+                        .checked_mul(size)
+                        .ok_or_else(|| invalid("dataset is too large"))?,
+                )?;
+                let data = bytes
+                    .chunks_exact(size)
+                    .map(|chunk| {
+                        let mut raw = [0u8; 8];
+                        raw[..size].copy_from_slice(chunk);
+                        if big_endian {
+                            raw[..size].reverse();
+                        }
+                        if size == 4 {
+                            f64::from(f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]))
+                        } else {
+                            f64::from_le_bytes(raw)
+                        }
+                    })
+                    .collect();
+                Ok(Hdf5Value::Floats { shape, data })
+            }
+            Datatype::FixedString { size } => {
+                let bytes = self.slice(
+                    at,
+                    count
+                        .checked_mul(size)
+                        .ok_or_else(|| invalid("attribute is too large"))?,
+                )?;
+                bytes
+                    .chunks(size.max(1))
+                    .take(count)
+                    .map(|chunk| {
+                        let end = chunk.iter().position(|&b| b == 0).unwrap_or(chunk.len());
+                        std::str::from_utf8(&chunk[..end])
+                            .map(|s| s.trim_end().to_string())
+                            .map_err(|_| invalid("string is not UTF-8"))
+                    })
+                    .collect::<IoResult<_>>()
+                    .map(Hdf5Value::Strings)
+            }
+        }
+    }
+}
+
+/// Writer for the subset of HDF5 the parser reads, in the layout `h5py`
+/// produces by default: superblock 0, version 1 object headers and
+/// symbol-table groups
+#[cfg(test)]
+pub(crate) mod writer {
+    /// Group or dataset to write
+    pub(crate) enum Node {
+        Group {
+            attributes: Vec<(String, Vec<String>)>,
+            children: Vec<(String, Node)>,
+        },
+        Dataset {
+            shape: Vec<usize>,
+            data: Vec<f32>,
+        },
+    }
+
+    pub(crate) fn group(attributes: Vec<(&str, Vec<&str>)>, children: Vec<(&str, Node)>) -> Node {
+        Node::Group {
+            attributes: attributes
+                .into_iter()
+                .map(|(name, values)| {
+                    (
+                        name.to_string(),
+                        values.into_iter().map(str::to_string).collect(),
+                    )
+                })
+                .collect(),
+            children: children
+                .into_iter()
+                .map(|(name, node)| (name.to_string(), node))
+                .collect(),
+        }
+    }
+
+    const UNDEFINED: u64 = u64::MAX;
+    const SUPERBLOCK_SIZE: usize = 96;
+
+    fn pad8(bytes: &mut Vec<u8>) {
+        bytes.resize(bytes.len().div_ceil(8) * 8, 0);
+    }
+
+    fn message(kind: u16, mut data: Vec<u8>) -> Vec<u8> {
+        pad8(&mut data);
+        let mut bytes = kind.to_le_bytes().to_vec();
+        bytes.extend((data.len() as u16).to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes.extend(data);
+        bytes
+    }
+
+    fn object_header(messages: Vec<Vec<u8>>) -> Vec<u8> {
+        let body: Vec<u8> = messages.concat();
+        let mut bytes = vec![1, 0];
+        bytes.extend((messages.len() as u16).to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend((body.len() as u32).to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes.extend(body);
+        bytes
+    }
+
+    fn dataspace(shape: &[usize]) -> Vec<u8> {
+        let mut bytes = vec![1, shape.len() as u8, 0, 0, 0, 0, 0, 0];
+        for &dim in shape {
+            bytes.extend((dim as u64).to_le_bytes());
+        }
+        bytes
+    }
+
+    fn string_type(size: usize) -> Vec<u8> {
+        let mut bytes = vec![0x13, 0, 0, 0];
+        bytes.extend((size as u32).to_le_bytes());
+        bytes
+    }
+
+    fn float_type() -> Vec<u8> {
+        let mut bytes = vec![0x11, 0x20, 31, 0];
+        bytes.extend(4u32.to_le_bytes());
+        bytes.extend([0, 0, 32, 0, 23, 8, 0, 23]);
+        bytes.extend(127u32.to_le_bytes());
+        bytes
+    }
+
+    fn attribute(name: &str, values: &[String]) -> Vec<u8> {
+        let size = values.iter().map(String::len).max().unwrap_or(0).max(1);
+        let mut name = name.as_bytes().to_vec();
+        name.push(0);
+        let (datatype, space) = (string_type(size), dataspace(&[values.len()]));
+        let mut bytes = vec![1, 0];
+        for part in [&name, &datatype, &space] {
+            bytes.extend((part.len() as u16).to_le_bytes());
+        }
+        for part in [name, datatype, space] {
+            bytes.extend(part);
+            pad8(&mut bytes);
+        }
+        for value in values {
+            let mut value = value.as_bytes().to_vec();
+            value.resize(size, 0);
+            bytes.extend(value);
+        }
+        bytes
+    }
+
+    fn append(file: &mut Vec<u8>, bytes: Vec<u8>) -> u64 {
+        pad8(file);
+        let address = file.len() as u64;
+        file.extend(bytes);
+        address
+    }
+
+    /// Write `node` and everything below it; returns its header address
+    fn write_node(file: &mut Vec<u8>, node: &Node) -> u64 {
+        match node {
+            Node::Dataset { shape, data } => {
+                let raw: Vec<u8> = data.iter().flat_map(|v| v.to_le_bytes()).collect();
+                let size = raw.len() as u64;
+                let address = append(file, raw);
+                let mut layout = vec![3, 1];
+                layout.extend(address.to_le_bytes());
+                layout.extend(size.to_le_bytes());
+                let header = object_header(vec![
+                    message(0x0001, dataspace(shape)),
+                    message(0x0003, float_type()),
+                    message(0x0008, layout),
+                ]);
+                append(file, header)
+            }
+            Node::Group {
+                attributes,
+                children,
+            } => {
+                let mut sorted: Vec<_> = children.iter().collect();
+                sorted.sort_by(|a, b| a.0.cmp(&b.0));
+                let addresses: Vec<u64> = sorted
+                    .iter()
+                    .map(|(_, child)| write_node(file, child))
+                    .collect();
+
+                // Local heap: the empty name at 0, then each link name
+                let mut heap = vec![0u8; 8];
+                let mut offsets = Vec::new();
+                for (name, _) in &sorted {
+                    offsets.push(heap.len() as u64);
+                    heap.extend(name.as_bytes());
+                    heap.push(0);
+                    pad8(&mut heap);
+                }
+                let heap_size = heap.len() as u64;
+                let heap_data = append(file, heap);
+                let mut heap_header = b"HEAP".to_vec();
+                heap_header.extend([0; 4]);
+                heap_header.extend(heap_size.to_le_bytes());
+                heap_header.extend(UNDEFINED.to_le_bytes());
+                heap_header.extend(heap_data.to_le_bytes());
+                let heap_address = append(file, heap_header);
+
+                let mut node = b"SNOD".to_vec();
+                node.extend([1, 0]);
+                node.extend((sorted.len() as u16).to_le_bytes());
+                for (&offset, &address) in offsets.iter().zip(&addresses) {
+                    node.extend(offset.to_le_bytes());
+                    node.extend(address.to_le_bytes());
+                    node.extend([0; 24]);
+                }
+                let node_address = append(file, node);
+
+                let mut tree = b"TREE".to_vec();
+                tree.extend([0, 0]);
+                tree.extend(1u16.to_le_bytes());
+                tree.extend(UNDEFINED.to_le_bytes());
+                tree.extend(UNDEFINED.to_le_bytes());
+                tree.extend(0u64.to_le_bytes());
+                tree.extend(node_address.to_le_bytes());
+                tree.extend(offsets.last().copied().unwrap_or(0).to_le_bytes());
+                let tree_address = append(file, tree);
+
+                let mut table = tree_address.to_le_bytes().to_vec();
+                table.extend(heap_address.to_le_bytes());
+                let mut messages = vec![message(0x0011, table)];
+                messages.extend(
+                    attributes
+                        .iter()
+                        .map(|(name, values)| message(0x000C, attribute(name, values))),
+                );
+                append(file, object_header(messages))
+            }
+        }
+    }
+
+    /// Bytes of an HDF5 file whose root group is `root`
+    pub(crate) fn write(root: &Node) -> Vec<u8> {
+        let mut file = vec![0u8; SUPERBLOCK_SIZE];
+        let root_address = write_node(&mut file, root);
+        let end = file.len() as u64;
+
+        let mut superblock = super::SIGNATURE.to_vec();
+        superblock.extend([0, 0, 0, 0, 0, 8, 8, 0]);
+        superblock.extend(4u16.to_le_bytes());
+        superblock.extend(16u16.to_le_bytes());
+        superblock.extend(0u32.to_le_bytes());
+        for address in [0, UNDEFINED, end, UNDEFINED] {
+            superblock.extend(address.to_le_bytes());
+        }
+        superblock.extend(0u64.to_le_bytes());
+        superblock.extend(root_address.to_le_bytes());
+        superblock.extend([0; 24]);
+        file[..SUPERBLOCK_SIZE].copy_from_slice(&superblock);
+        file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::writer::{group, write, Node};
+    use super::*;
+
+    #[test]
+    fn test_reads_groups_attributes_and_datasets() {
+        let bytes = write(&group(
+            vec![("note", vec!["hello"])],
+            vec![(
+                "outer",
+                group(
+                    vec![("names", vec!["a", "bcd"])],
+                    vec![(
+                        "weights:0",
+                        Node::Dataset {
+                            shape: vec![2, 3],
+                            data: vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.5],
+                        },
+                    )],
+                ),
+            )],
+        ));
+        let file = Hdf5File::parse(&bytes).unwrap();
+        assert_eq!(
+            file.attribute(file.root, "note").unwrap(),
+            Some(Hdf5Value::Strings(vec!["hello".to_string()]))
+        );
+        let outer = file.lookup("outer").unwrap();
+        assert_eq!(
+            file.attribute(outer, "names").unwrap(),
+            Some(Hdf5Value::Strings(vec!["a".to_string(), "bcd".to_string()]))
+        );
+        assert_eq!(file.attribute(outer, "missing").unwrap(), None);
+        assert_eq!(
+            file.dataset(file.lookup("/outer/weights:0").unwrap())
+                .unwrap(),
+            Hdf5Value::Floats {
+                shape: vec![2, 3],
+                data: vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.5],
+            }
+        );
+        assert!(file.lookup("outer/missing").is_err());
+
+        // Truncated files fail cleanly
+        assert!(Hdf5File::parse(&bytes[..40]).is_err());
+        let truncated = &bytes[..bytes.len() - 8];
+        assert!(Hdf5File::parse(truncated)
+            .and_then(|file| file.lookup("outer/weights:0"))
+            .is_err());
+    }
+}
@@ -0,0 +1,409 @@
+//! Image folders as training data
+//!
+//! [`ImageFolderLoader`] reads a directory laid out as `root/<class>/<image>`
+//! into [`TrainingData`]: every image is converted to grayscale or RGB,
+//! optionally resized, normalized to `[0, 1]` and flattened row by row
+//! (channels interleaved), and its class folder becomes a one-hot target.
+//! Classes are sorted by name so the label order is stable.
+//!
+//! Images are decoded from the Netpbm formats (PGM and PPM, ASCII or binary),
+//! which every image tool can write, and, with the `image` feature, from PNG
+//! and JPEG. Other files in the class folders are skipped.
+
+use crate::io::error::{IoError, IoResult};
+use crate::numeric::cast;
+use crate::training::TrainingData;
+use num_traits::Float;
+use std::fs;
+use std::path::Path;
+
+/// Channel layout of loaded images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// One luminance value per pixel
+    #[default]
+    Grayscale,
+    /// Red, green and blue per pixel
+    Rgb,
+}
+
+impl ColorMode {
+    pub fn channels(&self) -> usize {
+        match self {
+            ColorMode::Grayscale => 1,
+            ColorMode::Rgb => 3,
+        }
+    }
+}
+
+/// Decoded image with values in `[0, 1]`, stored row-major with interleaved
+/// channels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub mode: ColorMode,
+    pub pixels: Vec<f32>,
+}
+
+/// File extensions the loader reads
+#[cfg(not(feature = "image"))]
+const EXTENSIONS: &[&str] = &["pgm", "ppm", "pnm"];
+#[cfg(feature = "image")]
+const EXTENSIONS: &[&str] = &["pgm", "ppm", "pnm", "png", "jpg", "jpeg"];
+
+impl Image {
+    /// Decode a Netpbm image, or with the `image` feature a PNG or JPEG
+    /// image; the format is recognized from its leading bytes
+    pub fn decode(bytes: &[u8]) -> IoResult<Self> {
+        #[cfg(feature = "image")]
+        if !matches!(bytes.get(..2), Some(b"P2" | b"P3" | b"P5" | b"P6")) {
+            return Self::decode_with_image_crate(bytes);
+        }
+        Self::decode_netpbm(bytes)
+    }
+
+    /// Decode any format the `image` crate was built with; images with
+    /// color become RGB and others grayscale, and alpha is dropped
+    #[cfg(feature = "image")]
+    fn decode_with_image_crate(bytes: &[u8]) -> IoResult<Self> {
+        let decoded = image::load_from_memory(bytes)
+            .map_err(|e| IoError::InvalidFileFormat(format!("cannot decode image: {e}")))?;
+        let (width, height) = (decoded.width() as usize, decoded.height() as usize);
+        let (mode, pixels) = if decoded.color().has_color() {
+            (ColorMode::Rgb, decoded.to_rgb32f().into_raw())
+        } else {
+            (ColorMode::Grayscale, decoded.to_luma32f().into_raw())
+        };
+        Ok(Self {
+            width,
+            height,
+            mode,
+            pixels,
+        })
+    }
+
+    /// Decode a PGM (`P2`/`P5`) or PPM (`P3`/`P6`) image
+    pub fn decode_netpbm(bytes: &[u8]) -> IoResult<Self> {
+        let (magic, mode) = match bytes.get(..2) {
+            Some(b"P2") => (2, ColorMode::Grayscale),
+            Some(b"P5") => (5, ColorMode::Grayscale),
+            Some(b"P3") => (3, ColorMode::Rgb),
+            Some(b"P6") => (6, ColorMode::Rgb),
+            _ => {
+                return Err(IoError::InvalidFileFormat(
+                    "not a PGM or PPM image".to_string(),
+                ))
+            }
+        };
+        let mut pos = 2;
+        let mut header = [0usize; 3];
+        for value in &mut header {
+            *value = next_token(bytes, &mut pos)?.parse()?;
+        }
+        let [width, height, max_value] = header;
+        if width == 0 || height == 0 || max_value == 0 || max_value > u16::MAX as usize {
+            return Err(IoError::InvalidFileFormat(format!(
+                "invalid image header {width}x{height} with maximum {max_value}"
+            )));
+        }
+
+        let count = width * height * mode.channels();
+        let raw: Vec<usize> = if magic == 2 || magic == 3 {
+            (0..count)
+                .map(|_| Ok(next_token(bytes, &mut pos)?.parse()?))
+                .collect::<IoResult<_>>()?
+        } else {
+            // A single whitespace byte separates the header from the raster
+            let raster = bytes.get(pos + 1..).unwrap_or_default();
+            let sample_bytes = if max_value > 255 { 2 } else { 1 };
+            if raster.len() < count * sample_bytes {
+                return Err(IoError::InvalidFileFormat(
+                    "image raster is truncated".to_string(),
+                ));
+            }
+            raster
+                .chunks(sample_bytes)
+                .take(count)
+                .map(|c| c.iter().fold(0, |acc, &b| acc << 8 | b as usize))
+                .collect()
+        };
+        let scale = 1.0 / max_value as f32;
+        Ok(Self {
+            width,
+            height,
+            mode,
+            pixels: raw
+                .into_iter()
+                .map(|v| (v.min(max_value) as f32) * scale)
+                .collect(),
+        })
+    }
+
+    /// Convert to `mode`; RGB to grayscale uses the ITU-R BT.601 luma weights
+    pub fn to_mode(&self, mode: ColorMode) -> Self {
+        let pixels = match (self.mode, mode) {
+            (a, b) if a == b => self.pixels.clone(),
+            (ColorMode::Rgb, ColorMode::Grayscale) => self
+                .pixels
+                .chunks(3)
+                .map(|p| 0.299 * p[0] + 0.587 * p[1] + 0.114 * p[2])
+                .collect(),
+            _ => self.pixels.iter().flat_map(|&v| [v; 3]).collect(),
+        };
+        Self {
+            mode,
+            pixels,
+            ..*self
+        }
+    }
+
+    /// Bilinear resize to `width` x `height`
+    pub fn resize(&self, width: usize, height: usize) -> Self {
+        let channels = self.mode.channels();
+        let sample =
+            |x: usize, y: usize, c: usize| self.pixels[(y * self.width + x) * channels + c];
+        // Map output pixel centers onto input pixel centers
+        let source = |i: usize, out: usize, len: usize| {
+            let s = ((i as f32 + 0.5) * len as f32 / out as f32 - 0.5).max(0.0);
+            let lo = (s.floor() as usize).min(len - 1);
+            (lo, (lo + 1).min(len - 1), s - lo as f32)
+        };
+        let mut pixels = Vec::with_capacity(width * height * channels);
+        for y in 0..height {
+            let (y0, y1, fy) = source(y, height, self.height);
+            for x in 0..width {
+                let (x0, x1, fx) = source(x, width, self.width);
+                for c in 0..channels {
+                    let top = sample(x0, y0, c) * (1.0 - fx) + sample(x1, y0, c) * fx;
+                    let bottom = sample(x0, y1, c) * (1.0 - fx) + sample(x1, y1, c) * fx;
+                    pixels.push(top * (1.0 - fy) + bottom * fy);
+                }
+            }
+        }
+        Self {
+            width,
+            height,
+            mode: self.mode,
+            pixels,
+        }
+    }
+}
+
+/// Next whitespace-separated header or ASCII raster token, skipping comments
+fn next_token<'a>(bytes: &'a [u8], pos: &mut usize) -> IoResult<&'a str> {
+    loop {
+        match bytes.get(*pos) {
+            Some(b'#') => {
+                while bytes.get(*pos).is_some_and(|&b| b != b'\n') {
+                    *pos += 1;
+                }
+            }
+            Some(b) if b.is_ascii_whitespace() => *pos += 1,
+            Some(_) => break,
+            None => {
+                return Err(IoError::InvalidFileFormat(
+                    "unexpected end of image".to_string(),
+                ))
+            }
+        }
+    }
+    let start = *pos;
+    while bytes.get(*pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+        *pos += 1;
+    }
+    std::str::from_utf8(&bytes[start..*pos])
+        .map_err(|_| IoError::InvalidFileFormat("non-ASCII image header".to_string()))
+}
+
+/// Training data loaded from an image folder
+#[derive(Debug, Clone)]
+pub struct ImageDataset<T: Float> {
+    pub data: TrainingData<T>,
+    /// Class names in one-hot order
+    pub classes: Vec<String>,
+    /// Width and height of every sample
+    pub size: (usize, usize),
+    pub mode: ColorMode,
+}
+
+/// Loads `root/<class>/<image>` folders
+#[derive(Debug, Clone, Default)]
+pub struct ImageFolderLoader {
+    mode: ColorMode,
+    size: Option<(usize, usize)>,
+}
+
+impl ImageFolderLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_color_mode(mut self, mode: ColorMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Resize every image to `width` x `height`; without it all images must
+    /// already share one size
+    pub fn with_size(mut self, width: usize, height: usize) -> Self {
+        self.size = Some((width.max(1), height.max(1)));
+        self
+    }
+
+    pub fn load<T: Float>(&self, root: impl AsRef<Path>) -> IoResult<ImageDataset<T>> {
+        let mut classes: Vec<(String, std::path::PathBuf)> = fs::read_dir(root)?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| {
+                (
+                    entry.file_name().to_string_lossy().into_owned(),
+                    entry.path(),
+                )
+            })
+            .collect();
+        classes.sort();
+        if classes.is_empty() {
+            return Err(IoError::InvalidTrainingData(
+                "image folder has no class directories".to_string(),
+            ));
+        }
+
+        let mut data = TrainingData {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        };
+        let mut size = self.size;
+        for (label, (_, dir)) in classes.iter().enumerate() {
+            let mut files: Vec<_> = fs::read_dir(dir)?
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let extension = path.extension().and_then(|e| e.to_str());
+                    extension.is_some_and(|e| EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+                })
+                .collect();
+            files.sort();
+            for path in files {
+                let image = Image::decode(&fs::read(&path)?)
+                    .map_err(|e| IoError::InvalidTrainingData(format!("{}: {e}", path.display())))?
+                    .to_mode(self.mode);
+                let (width, height) = *size.get_or_insert((image.width, image.height));
+                let image = if (image.width, image.height) == (width, height) {
+                    image
+                } else if self.size.is_some() {
+                    image.resize(width, height)
+                } else {
+                    return Err(IoError::InvalidTrainingData(format!(
+                        "{} is {}x{}, expected {width}x{height}; set a size to resize",
+                        path.display(),
+                        image.width,
+                        image.height
+                    )));
+                };
+                data.inputs
+                    .push(image.pixels.iter().map(|&v| cast(v)).collect());
+                let mut target = vec![T::zero(); classes.len()];
+                target[label] = T::one();
+                data.outputs.push(target);
+            }
+        }
+        let Some(size) = size else {
+            return Err(IoError::InvalidTrainingData(
+                "image folder contains no readable images".to_string(),
+            ));
+        };
+        Ok(ImageDataset {
+            data,
+            classes: classes.into_iter().map(|(name, _)| name).collect(),
+            size,
+            mode: self.mode,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_convert_and_resize() {
+        let ascii = b"P2\n# gradient\n2 2\n4\n0 1\n2 4\n";
+        let image = Image::decode_netpbm(ascii).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.pixels, vec![0.0, 0.25, 0.5, 1.0]);
+
+        let mut binary = b"P6 1 1 255\n".to_vec();
+        binary.extend([255, 0, 0]);
+        let red = Image::decode_netpbm(&binary).unwrap();
+        assert_eq!(red.pixels, vec![1.0, 0.0, 0.0]);
+        assert!((red.to_mode(ColorMode::Grayscale).pixels[0] - 0.299).abs() < 1e-6);
+        assert_eq!(image.to_mode(ColorMode::Rgb).pixels.len(), 12);
+
+        // Downscaling to one pixel averages the image, upscaling keeps corners
+        let small = image.resize(1, 1);
+        assert!((small.pixels[0] - 0.4375).abs() < 1e-6);
+        let large = image.resize(4, 4);
+        assert_eq!((large.pixels[0], large.pixels[15]), (0.0, 1.0));
+
+        assert!(Image::decode_netpbm(b"P5 2 2 255\n\x00").is_err());
+        assert!(Image::decode_netpbm(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn test_load_image_folder() {
+        let root = std::env::temp_dir().join(format!("image_folder_{}", std::process::id()));
+        for (class, value) in [("dog", 200u8), ("cat", 50)] {
+            fs::create_dir_all(root.join(class)).unwrap();
+            for i in 0..2 {
+                let mut pgm = b"P5 2 2 255\n".to_vec();
+                pgm.extend([value; 4]);
+                fs::write(root.join(class).join(format!("{i}.pgm")), pgm).unwrap();
+            }
+            fs::write(root.join(class).join("notes.txt"), "skipped").unwrap();
+        }
+        let mut ppm = b"P3 4 4 1\n".to_vec();
+        ppm.extend("1 1 1 ".repeat(16).bytes());
+        fs::write(root.join("cat").join("big.ppm"), ppm).unwrap();
+
+        let mismatched = ImageFolderLoader::new().load::<f32>(&root);
+        let dataset = ImageFolderLoader::new().with_size(2, 2).load::<f32>(&root);
+        fs::remove_dir_all(&root).ok();
+
+        assert!(mismatched.is_err());
+        let dataset = dataset.unwrap();
+        assert_eq!(dataset.classes, vec!["cat", "dog"]);
+        assert_eq!(dataset.data.inputs.len(), 5);
+        assert!((dataset.data.inputs[0][0] - 50.0 / 255.0).abs() < 1e-6);
+        assert_eq!(dataset.data.inputs[2], vec![1.0; 4]);
+        assert_eq!(dataset.data.outputs[4], vec![0.0, 1.0]);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_decode_png_and_jpeg() {
+        use image::{GrayImage, ImageFormat, RgbImage};
+        use std::io::Cursor;
+
+        let encode = |image: image::DynamicImage, format| {
+            let mut bytes = Cursor::new(Vec::new());
+            image.write_to(&mut bytes, format).unwrap();
+            bytes.into_inner()
+        };
+        let rgb = RgbImage::from_fn(3, 2, |x, y| {
+            image::Rgb([255, (x * 100) as u8, (y * 255) as u8])
+        });
+        let png = Image::decode(&encode(rgb.into(), ImageFormat::Png)).unwrap();
+        assert_eq!((png.width, png.height, png.mode), (3, 2, ColorMode::Rgb));
+        assert_eq!(&png.pixels[3..6], &[1.0, 100.0 / 255.0, 0.0]);
+
+        let gray = GrayImage::from_pixel(8, 8, image::Luma([128]));
+        let jpeg = Image::decode(&encode(gray.into(), ImageFormat::Jpeg)).unwrap();
+        assert_eq!(jpeg.mode, ColorMode::Grayscale);
+        assert!(jpeg
+            .pixels
+            .iter()
+            .all(|&v| (v - 128.0 / 255.0).abs() < 0.02));
+        assert!(Image::decode(b"\x89PNG truncated").is_err());
+    }
+}
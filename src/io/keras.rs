@@ -0,0 +1,447 @@
+//! Import of small Keras dense models
+//!
+//! A Keras `.h5` model file holds the architecture as a JSON `model_config`
+//! attribute and the weights as one `kernel` and one `bias` dataset per
+//! layer. [`KerasImporter`] turns the two into a [`Network`]:
+//!
+//! - `Dense` layers become fully connected layers with the layer's
+//!   activation. A `kernel` of shape `(inputs, units)` is given row-major,
+//!   as `h5py` and NumPy return it.
+//! - A standalone `Activation` layer replaces the activation of the `Dense`
+//!   layer before it.
+//! - `InputLayer` fixes the input size; `Dropout` does nothing at inference
+//!   and is skipped.
+//!
+//! Only sequential stacks of these layers can be expressed as a [`Network`].
+//! Other layers and activations without an equivalent here (`softmax`,
+//! `elu`, ...) are rejected.
+//!
+//! With the `hdf5` feature, [`KerasImporter::from_h5`] reads both parts
+//! from the file. Without it the caller extracts them, e.g. with `h5py`:
+//! `f["model_weights"][name][name]["kernel:0"]`.
+
+use crate::io::error::{IoError, IoResult};
+#[cfg(feature = "hdf5")]
+use crate::io::hdf5::{Hdf5File, Hdf5Value};
+#[cfg(feature = "hdf5")]
+use crate::numeric::cast;
+use crate::{ActivationFunction, Network, NetworkBuilder};
+use num_traits::Float;
+use serde_json::Value;
+use std::collections::HashMap;
+#[cfg(feature = "hdf5")]
+use std::path::Path;
+
+/// Architecture of one Keras `Dense` layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct KerasDenseLayer {
+    pub name: String,
+    pub units: usize,
+    pub activation: ActivationFunction,
+    pub use_bias: bool,
+}
+
+/// Builds a [`Network`] from a Keras `model_config` and per-layer weights
+#[derive(Debug, Clone)]
+pub struct KerasImporter<T: Float> {
+    input_size: Option<usize>,
+    layers: Vec<KerasDenseLayer>,
+    weights: HashMap<String, (Vec<T>, Vec<T>)>,
+}
+
+impl<T: Float> KerasImporter<T> {
+    /// Parse the `model_config` JSON of a `Sequential` model
+    pub fn from_model_config(json: &str) -> IoResult<Self> {
+        let config: Value = serde_json::from_str(json)?;
+        let class = config["class_name"].as_str().unwrap_or_default();
+        if class != "Sequential" {
+            return Err(IoError::InvalidNetwork(format!(
+                "only Sequential models can be imported, not {class:?}"
+            )));
+        }
+        // Keras 2.1 and earlier store the layer list as the config itself
+        let layers = config["config"]["layers"]
+            .as_array()
+            .or_else(|| config["config"].as_array())
+            .ok_or_else(|| IoError::ParseError("model_config has no layer list".to_string()))?;
+
+        let mut importer = Self {
+            input_size: None,
+            layers: Vec::new(),
+            weights: HashMap::new(),
+        };
+        for layer in layers {
+            let class = layer["class_name"].as_str().unwrap_or_default();
+            let config = &layer["config"];
+            if importer.layers.is_empty() && importer.input_size.is_none() {
+                importer.input_size = input_size(config);
+            }
+            match class {
+                "InputLayer" | "Dropout" => {}
+                "Dense" => {
+                    let name = config["name"].as_str().unwrap_or_default().to_string();
+                    let units = config["units"]
+                        .as_u64()
+                        .filter(|&units| units > 0)
+                        .ok_or_else(|| {
+                            IoError::ParseError(format!("Dense layer {name:?} has no units"))
+                        })?;
+                    importer.layers.push(KerasDenseLayer {
+                        units: units as usize,
+                        activation: activation(&config["activation"])?,
+                        use_bias: config["use_bias"].as_bool().unwrap_or(true),
+                        name,
+                    });
+                }
+                "Activation" => {
+                    let dense = importer.layers.last_mut().ok_or_else(|| {
+                        IoError::InvalidNetwork("Activation layer before any Dense layer".into())
+                    })?;
+                    if dense.activation != ActivationFunction::Linear {
+                        return Err(IoError::InvalidNetwork(format!(
+                            "Dense layer {:?} already has an activation",
+                            dense.name
+                        )));
+                    }
+                    dense.activation = activation(&config["activation"])?;
+                }
+                other => {
+                    return Err(IoError::InvalidNetwork(format!(
+                        "unsupported Keras layer {other:?}"
+                    )))
+                }
+            }
+        }
+        if importer.layers.is_empty() {
+            return Err(IoError::InvalidNetwork(
+                "model has no Dense layers".to_string(),
+            ));
+        }
+        Ok(importer)
+    }
+
+    /// Read the architecture and weights of a Keras `.h5` model file
+    #[cfg(feature = "hdf5")]
+    pub fn from_h5<P: AsRef<Path>>(path: P) -> IoResult<Self> {
+        Self::from_h5_bytes(&std::fs::read(path)?)
+    }
+
+    /// Read the architecture and weights from the bytes of a `.h5` file
+    ///
+    /// Weights are found through each layer group's `weight_names`
+    /// attribute, so files from `save_weights` (without a `model_weights`
+    /// group) work as long as the root carries `model_config`.
+    #[cfg(feature = "hdf5")]
+    pub fn from_h5_bytes(bytes: &[u8]) -> IoResult<Self> {
+        let file = Hdf5File::parse(bytes)?;
+        let root = file.lookup("")?;
+        let config = h5_strings(&file, root, "model_config")?;
+        let [config] = config.as_slice() else {
+            return Err(IoError::ParseError("model_config is not a string".into()));
+        };
+        let mut importer = Self::from_model_config(config)?;
+
+        let weights = file.lookup("model_weights").unwrap_or(root);
+        for layer in importer.layers.clone() {
+            let group = file.lookup_from(weights, &layer.name)?;
+            let (mut kernel, mut bias) = (Vec::new(), Vec::new());
+            for path in h5_strings(&file, group, "weight_names")? {
+                let Hdf5Value::Floats { data, .. } =
+                    file.dataset(file.lookup_from(group, &path)?)?
+                else {
+                    return Err(IoError::InvalidFileFormat(format!(
+                        "weight {path:?} is not numeric"
+                    )));
+                };
+                let values = data.into_iter().map(cast).collect();
+                match path.rsplit('/').next().unwrap_or_default() {
+                    name if name.starts_with("kernel") => kernel = values,
+                    name if name.starts_with("bias") => bias = values,
+                    other => {
+                        return Err(IoError::InvalidNetwork(format!(
+                            "unexpected weight {other:?} in layer {:?}",
+                            layer.name
+                        )))
+                    }
+                }
+            }
+            importer.set_weights(&layer.name, kernel, bias)?;
+        }
+        Ok(importer)
+    }
+
+    /// The `Dense` layers in order
+    pub fn layers(&self) -> &[KerasDenseLayer] {
+        &self.layers
+    }
+
+    /// Number of inputs, from the model config or the first kernel
+    pub fn input_size(&self) -> Option<usize> {
+        self.input_size.or_else(|| {
+            let first = &self.layers[0];
+            let (kernel, _) = self.weights.get(&first.name)?;
+            Some(kernel.len() / first.units)
+        })
+    }
+
+    /// Weights of the `Dense` layer `name`: a row-major `(inputs, units)`
+    /// kernel and `units` biases, empty for layers without bias
+    pub fn set_weights(&mut self, name: &str, kernel: Vec<T>, bias: Vec<T>) -> IoResult<()> {
+        let layer = self
+            .layers
+            .iter()
+            .find(|layer| layer.name == name)
+            .ok_or_else(|| IoError::InvalidNetwork(format!("no Dense layer named {name:?}")))?;
+        let expected_bias = if layer.use_bias { layer.units } else { 0 };
+        if kernel.is_empty() || kernel.len() % layer.units != 0 || bias.len() != expected_bias {
+            return Err(IoError::InvalidNetwork(format!(
+                "layer {name:?} with {} units cannot take a kernel of {} and {} biases",
+                layer.units,
+                kernel.len(),
+                bias.len()
+            )));
+        }
+        self.weights.insert(name.to_string(), (kernel, bias));
+        Ok(())
+    }
+
+    /// Network with the imported architecture and weights
+    ///
+    /// Fails unless every `Dense` layer has weights and each kernel matches
+    /// the size of the layer before it.
+    pub fn build(&self) -> IoResult<Network<T>> {
+        let inputs = self.input_size().ok_or_else(|| {
+            IoError::InvalidNetwork("input size unknown without weights".to_string())
+        })?;
+        let mut builder = NetworkBuilder::<T>::new().input_layer(inputs);
+        let (last, hidden) = self
+            .layers
+            .split_last()
+            .ok_or_else(|| IoError::InvalidNetwork("no Dense layers".to_string()))?;
+        for layer in hidden {
+            builder = builder.hidden_layer_with_activation(layer.units, layer.activation, T::one());
+        }
+        builder = builder.output_layer_with_activation(last.units, last.activation, T::one());
+        let mut network = builder.build();
+
+        // Each neuron's connections run over the previous layer, bias last
+        let mut weights = Vec::with_capacity(network.total_connections());
+        let mut fan_in = inputs;
+        for layer in &self.layers {
+            let (kernel, bias) = self.weights.get(&layer.name).ok_or_else(|| {
+                IoError::InvalidNetwork(format!("no weights for layer {:?}", layer.name))
+            })?;
+            if kernel.len() != fan_in * layer.units {
+                return Err(IoError::InvalidNetwork(format!(
+                    "kernel of layer {:?} has {} values, expected {fan_in}x{}",
+                    layer.name,
+                    kernel.len(),
+                    layer.units
+                )));
+            }
+            for unit in 0..layer.units {
+                weights.extend((0..fan_in).map(|input| kernel[input * layer.units + unit]));
+                weights.push(bias.get(unit).copied().unwrap_or_else(T::zero));
+            }
+            fan_in = layer.units;
+        }
+        network
+            .set_weights(&weights)
+            .map_err(|e| IoError::InvalidNetwork(e.to_string()))?;
+        Ok(network)
+    }
+}
+
+/// Input width from a `batch_input_shape` (Keras 2) or `batch_shape`
+/// (Keras 3) of `[null, n]`
+fn input_size(config: &Value) -> Option<usize> {
+    let shape = config
+        .get("batch_input_shape")
+        .or_else(|| config.get("batch_shape"))?
+        .as_array()?;
+    match shape.as_slice() {
+        [_, n] => n.as_u64().map(|n| n as usize),
+        _ => None,
+    }
+}
+
+/// String attribute `name`; Keras splits large ones into `name0`,
+/// `name1`, ...
+#[cfg(feature = "hdf5")]
+fn h5_strings(file: &Hdf5File, address: u64, name: &str) -> IoResult<Vec<String>> {
+    let strings = |value| match value {
+        Hdf5Value::Strings(values) => Ok(values),
+        Hdf5Value::Floats { .. } => Err(IoError::InvalidFileFormat(format!(
+            "attribute {name} is not a string"
+        ))),
+    };
+    if let Some(value) = file.attribute(address, name)? {
+        return strings(value);
+    }
+    let mut values = Vec::new();
+    let mut part = 0;
+    while let Some(value) = file.attribute(address, &format!("{name}{part}"))? {
+        values.extend(strings(value)?);
+        part += 1;
+    }
+    if part == 0 {
+        return Err(IoError::InvalidFileFormat(format!("no {name} attribute")));
+    }
+    Ok(values)
+}
+
+fn activation(value: &Value) -> IoResult<ActivationFunction> {
+    let name = value.as_str().unwrap_or("linear");
+    Ok(match name {
+        "linear" => ActivationFunction::Linear,
+        "sigmoid" => ActivationFunction::Sigmoid,
+        "tanh" => ActivationFunction::Tanh,
+        "relu" => ActivationFunction::ReLU,
+        "gelu" => ActivationFunction::Gelu,
+        "swish" | "silu" => ActivationFunction::Swish,
+        "hard_sigmoid" | "softmax" | "elu" | "selu" | "softplus" | "softsign" | "exponential" => {
+            return Err(IoError::InvalidNetwork(format!(
+                "Keras activation {name:?} has no equivalent"
+            )))
+        }
+        other => {
+            return Err(IoError::ParseError(format!(
+                "unknown Keras activation {other:?}"
+            )))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+        "class_name": "Sequential",
+        "config": {"name": "sequential", "layers": [
+            {"class_name": "InputLayer", "config": {"batch_input_shape": [null, 2], "name": "input"}},
+            {"class_name": "Dense", "config": {"name": "dense", "units": 3, "activation": "linear", "use_bias": true}},
+            {"class_name": "Activation", "config": {"name": "act", "activation": "relu"}},
+            {"class_name": "Dropout", "config": {"name": "dropout", "rate": 0.5}},
+            {"class_name": "Dense", "config": {"name": "dense_1", "units": 1, "activation": "sigmoid", "use_bias": true}}
+        ]}
+    }"#;
+
+    #[test]
+    fn test_imports_dense_stack() {
+        let mut importer = KerasImporter::<f64>::from_model_config(CONFIG).unwrap();
+        let layers = importer.layers();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].activation, ActivationFunction::ReLU);
+        assert_eq!(importer.input_size(), Some(2));
+        assert!(importer.build().is_err());
+
+        // kernel[i][j] connects input i to unit j
+        importer
+            .set_weights(
+                "dense",
+                vec![1.0, -1.0, 0.5, 2.0, 1.0, 0.0],
+                vec![0.0, 0.5, -1.0],
+            )
+            .unwrap();
+        importer
+            .set_weights("dense_1", vec![1.0, 1.0, 1.0], vec![-1.0])
+            .unwrap();
+        let mut network = importer.build().unwrap();
+
+        let (x0, x1) = (0.5, 0.25);
+        let hidden = [
+            (x0 + 2.0 * x1).max(0.0),
+            (-x0 + x1 + 0.5f64).max(0.0),
+            (0.5 * x0 - 1.0f64).max(0.0),
+        ];
+        let expected = 1.0 / (1.0 + (-(hidden.iter().sum::<f64>() - 1.0)).exp());
+        assert!((network.run(&[x0, x1])[0] - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_rejects_unsupported_models() {
+        let softmax = CONFIG.replace("\"sigmoid\"", "\"softmax\"");
+        assert!(KerasImporter::<f32>::from_model_config(&softmax).is_err());
+        let conv = CONFIG.replace("\"Dropout\"", "\"Conv2D\"");
+        assert!(KerasImporter::<f32>::from_model_config(&conv).is_err());
+        let functional = CONFIG.replace("\"Sequential\"", "\"Functional\"");
+        assert!(KerasImporter::<f32>::from_model_config(&functional).is_err());
+
+        let mut importer = KerasImporter::<f32>::from_model_config(CONFIG).unwrap();
+        assert!(importer
+            .set_weights("dense", vec![0.0; 4], vec![0.0; 3])
+            .is_err());
+        assert!(importer
+            .set_weights("missing", vec![0.0; 6], vec![0.0; 3])
+            .is_err());
+    }
+
+    #[cfg(feature = "hdf5")]
+    #[test]
+    fn test_reads_h5_model_file() {
+        use crate::io::hdf5::writer::{group, write, Node};
+
+        // The layout `model.save("model.h5")` produces
+        let dense = |name: &'static str, shape: Vec<usize>, kernel: Vec<f32>, bias: Vec<f32>| {
+            let units = bias.len();
+            let kernel_path = format!("{name}/kernel:0");
+            let bias_path = format!("{name}/bias:0");
+            let names = vec![kernel_path.as_str(), bias_path.as_str()];
+            let weights = group(
+                vec![],
+                vec![
+                    (
+                        "kernel:0",
+                        Node::Dataset {
+                            shape,
+                            data: kernel,
+                        },
+                    ),
+                    (
+                        "bias:0",
+                        Node::Dataset {
+                            shape: vec![units],
+                            data: bias,
+                        },
+                    ),
+                ],
+            );
+            (
+                name,
+                group(vec![("weight_names", names)], vec![(name, weights)]),
+            )
+        };
+        let bytes = write(&group(
+            vec![
+                ("model_config", vec![CONFIG]),
+                ("backend", vec!["tensorflow"]),
+            ],
+            vec![(
+                "model_weights",
+                group(
+                    vec![("layer_names", vec!["input", "dense", "act", "dense_1"])],
+                    vec![
+                        dense(
+                            "dense",
+                            vec![2, 3],
+                            vec![1.0, -1.0, 0.5, 2.0, 1.0, 0.0],
+                            vec![0.0, 0.5, -1.0],
+                        ),
+                        dense("dense_1", vec![3, 1], vec![1.0, 1.0, 1.0], vec![-1.0]),
+                    ],
+                ),
+            )],
+        ));
+
+        let mut network = KerasImporter::<f64>::from_h5_bytes(&bytes)
+            .unwrap()
+            .build()
+            .unwrap();
+        // Same weights as test_imports_dense_stack: hidden = [1.0, 0.25, 0.0]
+        let expected = 1.0 / (1.0 + (-(1.25f64 - 1.0)).exp());
+        assert!((network.run(&[0.5, 0.25])[0] - expected).abs() < 1e-12);
+
+        assert!(KerasImporter::<f64>::from_h5_bytes(&bytes[..bytes.len() / 2]).is_err());
+    }
+}
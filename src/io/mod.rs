@@ -7,8 +7,20 @@ mod compression;
 mod dot_export;
 mod error;
 mod fann_format;
+#[cfg(feature = "hdf5")]
+mod hdf5;
+mod images;
 #[cfg(feature = "serde")]
 mod json;
+#[cfg(feature = "serde")]
+mod keras;
+mod model_file;
+mod npz;
+mod pmml;
+mod policy;
+mod scoring;
+#[cfg(feature = "signing")]
+mod signing;
 mod streaming;
 mod training_data;
 
@@ -16,11 +28,24 @@ mod training_data;
 pub use dot_export::DotExporter;
 pub use error::{IoError, IoResult};
 pub use fann_format::{FannReader, FannWriter};
+pub use images::{ColorMode, Image, ImageDataset, ImageFolderLoader};
+pub use model_file::{
+    MetadataValue, ModelFile, ModelFileWriter, TensorInfo, TensorType, DEFAULT_ALIGNMENT,
+};
+pub use pmml::PmmlExporter;
+pub use policy::{License, LicensePolicy, LoadPolicy};
+pub use scoring::{score_file, BatchScorer, ScoreStats};
 pub use training_data::{TrainingDataReader, TrainingDataStreamReader, TrainingDataWriter};
 
 #[cfg(feature = "serde")]
 pub use json::{read_json, write_json};
 
+#[cfg(feature = "serde")]
+pub use keras::{KerasDenseLayer, KerasImporter};
+
+#[cfg(feature = "signing")]
+pub use signing::{sign_bytes, sign_file, verify, verify_bytes, SigningKey, VerifyingKey};
+
 #[cfg(feature = "binary")]
 pub use binary::{read_binary, write_binary};
 
@@ -42,4 +67,6 @@ pub enum FileFormat {
     CompressedFann,
     /// Compressed binary format
     CompressedBinary,
+    /// Single-file model container with metadata and aligned tensors
+    ModelFile,
 }
@@ -0,0 +1,985 @@
+//! Single-file model container
+//!
+//! A model file holds a deployable network in one file, laid out after
+//! GGUF so it can be memory-mapped and read piecemeal:
+//!
+//! ```text
+//! magic "DFMF" | version u32 | tensor count u64 | metadata count u64
+//! metadata      key, value type u32, value                       (repeated)
+//! tensor infos  name, rank u32, shape u64.., type u32,
+//!               [Q8: scale f32, zero point i8], data offset u64  (repeated)
+//! padding up to the alignment
+//! tensor data, every tensor starting at an aligned file offset
+//! ```
+//!
+//! Numbers are little-endian; strings are a `u64` byte length followed by
+//! UTF-8. The alignment (32 unless set otherwise) is stored under the
+//! `general.alignment` key.
+//!
+//! [`ModelFile::read_header`] reads only the metadata and tensor table, after
+//! which [`ModelFile::read_tensor`] seeks to a single tensor, and
+//! [`ModelFile::tensor_bytes`] borrows a tensor straight from a mapped or
+//! in-memory file. Tensors are stored as `f32`, `f64`, `u8`, or quantized to
+//! `i8` with one [`QuantizationParams`] per tensor ([`TensorType::Q8`]).
+//!
+//! [`Network::write_model_file`] stores a network as one `layer{l}.weight`
+//! tensor of shape `(neurons, inputs)` and one `layer{l}.bias` tensor per
+//! layer, plus a `u8` `layer{l}.mask` of shape `(neurons, inputs + 1)` for
+//! layers that are not fully connected. Topology, activations, steepnesses
+//! and the [schema](crate::network::NetworkSchema) go into `mlp.*` metadata.
+
+use crate::io::error::{IoError, IoResult};
+use crate::network::InputRange;
+use crate::numeric::cast;
+use crate::quantization::QuantizationParams;
+use crate::{ActivationFunction, Layer, Network};
+use num_traits::Float;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"DFMF";
+const VERSION: u32 = 1;
+/// Value of `general.architecture` for networks
+const ARCHITECTURE: &str = "do-fann.mlp";
+/// Longest string accepted on read, to reject corrupt lengths early
+const MAX_STRING: u64 = 1 << 24;
+
+/// Alignment of tensor data unless [`ModelFileWriter::with_alignment`] says otherwise
+pub const DEFAULT_ALIGNMENT: usize = 32;
+
+const ACTIVATIONS: [ActivationFunction; 20] = [
+    ActivationFunction::Linear,
+    ActivationFunction::Threshold,
+    ActivationFunction::ThresholdSymmetric,
+    ActivationFunction::Sigmoid,
+    ActivationFunction::SigmoidSymmetric,
+    ActivationFunction::Tanh,
+    ActivationFunction::Gaussian,
+    ActivationFunction::GaussianSymmetric,
+    ActivationFunction::Elliot,
+    ActivationFunction::ElliotSymmetric,
+    ActivationFunction::LinearPiece,
+    ActivationFunction::LinearPieceSymmetric,
+    ActivationFunction::ReLU,
+    ActivationFunction::ReLULeaky,
+    ActivationFunction::Sin,
+    ActivationFunction::Cos,
+    ActivationFunction::SinSymmetric,
+    ActivationFunction::CosSymmetric,
+    ActivationFunction::Gelu,
+    ActivationFunction::Swish,
+];
+
+/// Value stored under a metadata key
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+    String(String),
+    U64Array(Vec<u64>),
+    F64Array(Vec<f64>),
+    StringArray(Vec<String>),
+}
+
+impl MetadataValue {
+    fn type_id(&self) -> u32 {
+        match self {
+            Self::U64(_) => 0,
+            Self::I64(_) => 1,
+            Self::F64(_) => 2,
+            Self::Bool(_) => 3,
+            Self::String(_) => 4,
+            Self::U64Array(_) => 5,
+            Self::F64Array(_) => 6,
+            Self::StringArray(_) => 7,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::U64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::F64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Element type of a stored tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TensorType {
+    F32,
+    F64,
+    /// `i8` with a per-tensor scale and zero point
+    Q8,
+    U8,
+}
+
+impl TensorType {
+    fn id(self) -> u32 {
+        match self {
+            Self::F32 => 0,
+            Self::F64 => 1,
+            Self::Q8 => 2,
+            Self::U8 => 3,
+        }
+    }
+
+    fn from_id(id: u32) -> Option<Self> {
+        Some(match id {
+            0 => Self::F32,
+            1 => Self::F64,
+            2 => Self::Q8,
+            3 => Self::U8,
+            _ => return None,
+        })
+    }
+
+    /// Bytes per element
+    pub fn size(self) -> usize {
+        match self {
+            Self::F32 => 4,
+            Self::F64 => 8,
+            Self::Q8 | Self::U8 => 1,
+        }
+    }
+}
+
+/// Entry of the tensor table
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorInfo {
+    pub name: String,
+    pub shape: Vec<usize>,
+    pub tensor_type: TensorType,
+    /// Present for [`TensorType::Q8`] tensors
+    pub quantization: Option<QuantizationParams>,
+    /// Offset of the data from the start of the data section
+    pub offset: u64,
+}
+
+impl TensorInfo {
+    /// Number of elements
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Size of the data in bytes
+    pub fn byte_len(&self) -> usize {
+        self.len() * self.tensor_type.size()
+    }
+
+    /// Values of the tensor from its raw data
+    pub fn decode(&self, bytes: &[u8]) -> IoResult<Vec<f64>> {
+        if bytes.len() != self.byte_len() {
+            return Err(IoError::InvalidFileFormat(format!(
+                "tensor {} has {} bytes, expected {}",
+                self.name,
+                bytes.len(),
+                self.byte_len()
+            )));
+        }
+        Ok(match self.tensor_type {
+            TensorType::F32 => bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+                .collect(),
+            TensorType::F64 => bytes
+                .chunks_exact(8)
+                .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+                .collect(),
+            TensorType::Q8 => {
+                let params = self.quantization.ok_or_else(|| {
+                    IoError::InvalidFileFormat(format!("tensor {} lacks Q8 parameters", self.name))
+                })?;
+                bytes
+                    .iter()
+                    .map(|&b| params.dequantize(b as i8) as f64)
+                    .collect()
+            }
+            TensorType::U8 => bytes.iter().map(|&b| b as f64).collect(),
+        })
+    }
+}
+
+/// Header of a model file: metadata and tensor table
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelFile {
+    metadata: BTreeMap<String, MetadataValue>,
+    tensors: Vec<TensorInfo>,
+    data_offset: u64,
+}
+
+impl ModelFile {
+    /// Read the header from the start of a file, leaving `reader` at the
+    /// start of the tensor data
+    pub fn read_header<R: Read>(reader: &mut R) -> IoResult<Self> {
+        let mut reader = CountingReader {
+            inner: reader,
+            count: 0,
+        };
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(IoError::InvalidFileFormat(
+                "not a model file (bad magic)".to_string(),
+            ));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(IoError::InvalidFileFormat(format!(
+                "unsupported model file version {version}"
+            )));
+        }
+        let tensor_count = read_u64(&mut reader)?;
+        let metadata_count = read_u64(&mut reader)?;
+
+        let mut metadata = BTreeMap::new();
+        for _ in 0..metadata_count {
+            let key = read_string(&mut reader)?;
+            let value = read_value(&mut reader)?;
+            metadata.insert(key, value);
+        }
+        let mut tensors = Vec::new();
+        for _ in 0..tensor_count {
+            tensors.push(read_tensor_info(&mut reader)?);
+        }
+
+        let alignment = match metadata.get("general.alignment") {
+            Some(value) => value
+                .as_u64()
+                .and_then(|a| usize::try_from(a).ok())
+                .filter(|a| a.is_power_of_two())
+                .ok_or_else(|| IoError::InvalidFileFormat("invalid alignment".to_string()))?,
+            None => DEFAULT_ALIGNMENT,
+        };
+        let data_offset = align(reader.count, alignment);
+        let mut padding = vec![0; (data_offset - reader.count) as usize];
+        reader.read_exact(&mut padding)?;
+        if let Some(tensor) = tensors
+            .iter()
+            .find(|t| (data_offset + t.offset) % alignment as u64 != 0)
+        {
+            return Err(IoError::InvalidFileFormat(format!(
+                "tensor {} is not aligned",
+                tensor.name
+            )));
+        }
+        Ok(Self {
+            metadata,
+            tensors,
+            data_offset,
+        })
+    }
+
+    /// Parse the header of a file held in memory, e.g. memory-mapped
+    pub fn parse(bytes: &[u8]) -> IoResult<Self> {
+        Self::read_header(&mut &bytes[..])
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, MetadataValue> {
+        &self.metadata
+    }
+
+    pub fn get(&self, key: &str) -> Option<&MetadataValue> {
+        self.metadata.get(key)
+    }
+
+    pub fn tensors(&self) -> &[TensorInfo] {
+        &self.tensors
+    }
+
+    pub fn tensor(&self, name: &str) -> Option<&TensorInfo> {
+        self.tensors.iter().find(|t| t.name == name)
+    }
+
+    /// File offset of the tensor data section
+    pub fn data_offset(&self) -> u64 {
+        self.data_offset
+    }
+
+    fn find(&self, name: &str) -> IoResult<&TensorInfo> {
+        self.tensor(name)
+            .ok_or_else(|| IoError::InvalidFileFormat(format!("no tensor named {name}")))
+    }
+
+    /// Raw data of tensor `name`, borrowed from the whole file in `file`
+    pub fn tensor_bytes<'a>(&self, file: &'a [u8], name: &str) -> IoResult<&'a [u8]> {
+        let info = self.find(name)?;
+        usize::try_from(self.data_offset + info.offset)
+            .ok()
+            .and_then(|start| file.get(start..start.checked_add(info.byte_len())?))
+            .ok_or_else(|| IoError::InvalidFileFormat(format!("tensor {name} is truncated")))
+    }
+
+    /// Read and decode only tensor `name`; `reader` must be positioned
+    /// relative to the start of the file
+    pub fn read_tensor<R: Read + Seek>(&self, reader: &mut R, name: &str) -> IoResult<Vec<f64>> {
+        let info = self.find(name)?;
+        reader.seek(SeekFrom::Start(self.data_offset + info.offset))?;
+        let mut bytes = vec![0; info.byte_len()];
+        reader.read_exact(&mut bytes)?;
+        info.decode(&bytes)
+    }
+}
+
+/// Builds and writes a model file
+#[derive(Debug, Clone)]
+pub struct ModelFileWriter {
+    alignment: usize,
+    metadata: BTreeMap<String, MetadataValue>,
+    tensors: Vec<(TensorInfo, Vec<u8>)>,
+}
+
+impl Default for ModelFileWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModelFileWriter {
+    pub fn new() -> Self {
+        Self {
+            alignment: DEFAULT_ALIGNMENT,
+            metadata: BTreeMap::new(),
+            tensors: Vec::new(),
+        }
+    }
+
+    /// Align tensor data to `alignment` bytes, rounded up to a power of two
+    pub fn with_alignment(mut self, alignment: usize) -> Self {
+        self.alignment = alignment.max(1).next_power_of_two();
+        self
+    }
+
+    pub fn set_metadata(&mut self, key: impl Into<String>, value: MetadataValue) {
+        self.metadata.insert(key.into(), value);
+    }
+
+    /// Add a tensor of `shape` with `values` in row-major order
+    pub fn add_tensor(
+        &mut self,
+        name: impl Into<String>,
+        shape: &[usize],
+        values: &[f64],
+        tensor_type: TensorType,
+    ) -> IoResult<()> {
+        let name = name.into();
+        if shape.iter().product::<usize>() != values.len() {
+            return Err(IoError::InvalidFileFormat(format!(
+                "tensor {name} of shape {shape:?} cannot hold {} values",
+                values.len()
+            )));
+        }
+        if self.tensors.iter().any(|(info, _)| info.name == name) {
+            return Err(IoError::InvalidFileFormat(format!(
+                "duplicate tensor {name}"
+            )));
+        }
+        let mut quantization = None;
+        let data = match tensor_type {
+            TensorType::F32 => values
+                .iter()
+                .flat_map(|&v| (v as f32).to_le_bytes())
+                .collect(),
+            TensorType::F64 => values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            TensorType::Q8 => {
+                let (min, max) = values
+                    .iter()
+                    .fold((0.0f64, 0.0f64), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+                let params = QuantizationParams::from_range(min as f32, max as f32);
+                quantization = Some(params);
+                values
+                    .iter()
+                    .map(|&v| params.quantize(v as f32) as u8)
+                    .collect()
+            }
+            TensorType::U8 => {
+                if let Some(v) = values
+                    .iter()
+                    .find(|&&v| v.fract() != 0.0 || !(0.0..=255.0).contains(&v))
+                {
+                    return Err(IoError::InvalidFileFormat(format!(
+                        "tensor {name} value {v} is not a u8"
+                    )));
+                }
+                values.iter().map(|&v| v as u8).collect()
+            }
+        };
+        let info = TensorInfo {
+            name,
+            shape: shape.to_vec(),
+            tensor_type,
+            quantization,
+            offset: 0,
+        };
+        self.tensors.push((info, data));
+        Ok(())
+    }
+
+    /// Write the file to `writer`
+    pub fn write<W: Write>(&self, writer: &mut W) -> IoResult<()> {
+        let mut metadata = self.metadata.clone();
+        metadata.insert(
+            "general.alignment".to_string(),
+            MetadataValue::U64(self.alignment as u64),
+        );
+
+        let mut header = Vec::new();
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&(self.tensors.len() as u64).to_le_bytes());
+        header.extend_from_slice(&(metadata.len() as u64).to_le_bytes());
+        for (key, value) in &metadata {
+            write_string(&mut header, key);
+            write_value(&mut header, value);
+        }
+        let mut offset = 0u64;
+        for (info, data) in &self.tensors {
+            write_string(&mut header, &info.name);
+            header.extend_from_slice(&(info.shape.len() as u32).to_le_bytes());
+            for &dim in &info.shape {
+                header.extend_from_slice(&(dim as u64).to_le_bytes());
+            }
+            header.extend_from_slice(&info.tensor_type.id().to_le_bytes());
+            if let Some(params) = info.quantization {
+                header.extend_from_slice(&params.scale.to_le_bytes());
+                header.push(params.zero_point as u8);
+            }
+            header.extend_from_slice(&offset.to_le_bytes());
+            offset = align(offset + data.len() as u64, self.alignment);
+        }
+
+        let data_offset = align(header.len() as u64, self.alignment);
+        header.resize(data_offset as usize, 0);
+        writer.write_all(&header)?;
+        let mut written = 0;
+        for (_, data) in &self.tensors {
+            writer.write_all(data)?;
+            written += data.len() as u64;
+            let padding = align(written, self.alignment) - written;
+            writer.write_all(&vec![0; padding as usize])?;
+            written += padding;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Float> Network<T> {
+    /// Save as a single model file with tensors of `tensor_type`
+    pub fn save_model_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        tensor_type: TensorType,
+    ) -> IoResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_model_file(&mut writer, tensor_type)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load a network saved with [`Network::save_model_file`]
+    pub fn load_model_file<P: AsRef<Path>>(path: P) -> IoResult<Self> {
+        Self::read_model_file(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Write as a model file to `writer`
+    ///
+    /// [`TensorType::Q8`] quantizes weights and biases per tensor, which
+    /// makes them a quarter of the `f32` size at some loss of precision.
+    pub fn write_model_file<W: Write>(
+        &self,
+        writer: &mut W,
+        tensor_type: TensorType,
+    ) -> IoResult<()> {
+        self.model_file_writer(tensor_type)?.write(writer)
+    }
+
+    /// Tensors and metadata of the network, ready to be written
+    pub(crate) fn model_file_writer(&self, tensor_type: TensorType) -> IoResult<ModelFileWriter> {
+        self.check_feed_forward()
+            .map_err(|e| IoError::InvalidNetwork(e.to_string()))?;
+        if tensor_type == TensorType::U8 {
+            return Err(IoError::InvalidNetwork(
+                "weights cannot be stored as u8".to_string(),
+            ));
+        }
+        let mut file = ModelFileWriter::new();
+        let mut activations = Vec::new();
+        let mut steepnesses = Vec::new();
+        for (l, pair) in self.layers.windows(2).enumerate() {
+            let (previous, layer) = (&pair[0], &pair[1]);
+            let inputs = previous.num_regular_neurons();
+            let neurons = layer.num_regular_neurons();
+            let mut weights = vec![0.0; neurons * inputs];
+            let mut biases = vec![0.0; neurons];
+            let mut mask = vec![0.0; neurons * (inputs + 1)];
+            for (j, neuron) in layer.neurons.iter().take(neurons).enumerate() {
+                activations.push(neuron.activation_function.name().to_string());
+                steepnesses.push(cast(neuron.activation_steepness));
+                for connection in &neuron.connections {
+                    let weight: f64 = cast(connection.weight);
+                    match connection.from_neuron {
+                        i if i < inputs => weights[j * inputs + i] = weight,
+                        _ => biases[j] = weight,
+                    }
+                    mask[j * (inputs + 1) + connection.from_neuron.min(inputs)] = 1.0;
+                }
+            }
+            let l = l + 1;
+            file.add_tensor(
+                format!("layer{l}.weight"),
+                &[neurons, inputs],
+                &weights,
+                tensor_type,
+            )?;
+            file.add_tensor(format!("layer{l}.bias"), &[neurons], &biases, tensor_type)?;
+            if mask.contains(&0.0) {
+                file.add_tensor(
+                    format!("layer{l}.mask"),
+                    &[neurons, inputs + 1],
+                    &mask,
+                    TensorType::U8,
+                )?;
+            }
+        }
+
+        let schema = self.schema();
+        let meta = [
+            (
+                "general.architecture",
+                MetadataValue::String(ARCHITECTURE.to_string()),
+            ),
+            (
+                "general.generator",
+                MetadataValue::String(format!("do-fann {}", env!("CARGO_PKG_VERSION"))),
+            ),
+            (
+                "mlp.layer_sizes",
+                MetadataValue::U64Array(
+                    self.layers
+                        .iter()
+                        .map(|layer| layer.num_regular_neurons() as u64)
+                        .collect(),
+                ),
+            ),
+            (
+                "mlp.connection_rate",
+                MetadataValue::F64(cast(self.connection_rate)),
+            ),
+            ("mlp.activations", MetadataValue::StringArray(activations)),
+            ("mlp.steepnesses", MetadataValue::F64Array(steepnesses)),
+        ];
+        for (key, value) in meta {
+            file.set_metadata(key, value);
+        }
+        if !schema.input_names.is_empty() {
+            file.set_metadata(
+                "mlp.input_names",
+                MetadataValue::StringArray(schema.input_names.clone()),
+            );
+        }
+        if !schema.output_names.is_empty() {
+            file.set_metadata(
+                "mlp.output_names",
+                MetadataValue::StringArray(schema.output_names.clone()),
+            );
+        }
+        if !schema.input_ranges.is_empty() {
+            file.set_metadata(
+                "mlp.input_ranges",
+                MetadataValue::F64Array(
+                    schema
+                        .input_ranges
+                        .iter()
+                        .flat_map(|range| [range.min, range.max])
+                        .collect(),
+                ),
+            );
+        }
+        Ok(file)
+    }
+
+    /// Read a network from a model file at the start of `reader`
+    pub fn read_model_file<R: Read + Seek>(reader: &mut R) -> IoResult<Self> {
+        let file = ModelFile::read_header(reader)?;
+        let invalid = |reason: String| IoError::InvalidNetwork(reason);
+        let missing = |key: &str| invalid(format!("missing or mistyped metadata {key}"));
+
+        let architecture = file
+            .get("general.architecture")
+            .and_then(MetadataValue::as_str);
+        if architecture != Some(ARCHITECTURE) {
+            return Err(invalid(format!(
+                "model file holds {architecture:?}, not a network"
+            )));
+        }
+        let sizes: Vec<usize> = match file.get("mlp.layer_sizes") {
+            Some(MetadataValue::U64Array(sizes)) if sizes.len() >= 2 => {
+                sizes.iter().map(|&s| s as usize).collect()
+            }
+            _ => return Err(missing("mlp.layer_sizes")),
+        };
+        let neurons: usize = sizes[1..].iter().sum();
+        let activations = match file.get("mlp.activations") {
+            Some(MetadataValue::StringArray(names)) if names.len() == neurons => names
+                .iter()
+                .map(|name| {
+                    ACTIVATIONS
+                        .into_iter()
+                        .find(|f| f.name() == name)
+                        .ok_or_else(|| invalid(format!("unknown activation {name}")))
+                })
+                .collect::<IoResult<Vec<_>>>()?,
+            _ => return Err(missing("mlp.activations")),
+        };
+        let steepnesses = match file.get("mlp.steepnesses") {
+            Some(MetadataValue::F64Array(values)) if values.len() == neurons => values,
+            _ => return Err(missing("mlp.steepnesses")),
+        };
+        let connection_rate = file
+            .get("mlp.connection_rate")
+            .and_then(MetadataValue::as_f64)
+            .ok_or_else(|| missing("mlp.connection_rate"))?;
+
+        let mut layers = vec![Layer::with_bias(
+            sizes[0],
+            ActivationFunction::Linear,
+            T::one(),
+        )];
+        let mut functions = activations.into_iter().zip(steepnesses);
+        for l in 1..sizes.len() {
+            let (inputs, count) = (sizes[l - 1], sizes[l]);
+            let mut tensor = |suffix: &str, shape: &[usize]| -> IoResult<Option<Vec<f64>>> {
+                let name = format!("layer{l}.{suffix}");
+                let Some(info) = file.tensor(&name) else {
+                    return Ok(None);
+                };
+                if info.shape != shape {
+                    return Err(invalid(format!(
+                        "tensor {name} has shape {:?}, expected {shape:?}",
+                        info.shape
+                    )));
+                }
+                file.read_tensor(reader, &name).map(Some)
+            };
+            let weights = tensor("weight", &[count, inputs])?
+                .ok_or_else(|| invalid(format!("missing tensor layer{l}.weight")))?;
+            let biases = tensor("bias", &[count])?
+                .ok_or_else(|| invalid(format!("missing tensor layer{l}.bias")))?;
+            let mask = tensor("mask", &[count, inputs + 1])?;
+
+            let mut layer = if l == sizes.len() - 1 {
+                Layer::new(count, ActivationFunction::Linear, T::one())
+            } else {
+                Layer::with_bias(count, ActivationFunction::Linear, T::one())
+            };
+            for (j, neuron) in layer.neurons.iter_mut().take(count).enumerate() {
+                let (function, &steepness) =
+                    functions.next().ok_or_else(|| missing("mlp.activations"))?;
+                neuron.activation_function = function;
+                neuron.activation_steepness = cast(steepness);
+                for i in 0..=inputs {
+                    if mask
+                        .as_ref()
+                        .is_some_and(|m| m[j * (inputs + 1) + i] == 0.0)
+                    {
+                        continue;
+                    }
+                    let weight = if i < inputs {
+                        weights[j * inputs + i]
+                    } else {
+                        biases[j]
+                    };
+                    neuron.add_connection(i, cast(weight));
+                }
+            }
+            layers.push(layer);
+        }
+
+        let mut network = Network {
+            layers,
+            connection_rate: cast(connection_rate),
+            schema: Default::default(),
+        };
+        let schema_error = |e: crate::network::NetworkError| invalid(e.to_string());
+        if let Some(MetadataValue::StringArray(names)) = file.get("mlp.input_names") {
+            network
+                .set_input_names(names.iter().cloned())
+                .map_err(schema_error)?;
+        }
+        if let Some(MetadataValue::StringArray(names)) = file.get("mlp.output_names") {
+            network
+                .set_output_names(names.iter().cloned())
+                .map_err(schema_error)?;
+        }
+        if let Some(MetadataValue::F64Array(bounds)) = file.get("mlp.input_ranges") {
+            let ranges = bounds
+                .chunks_exact(2)
+                .map(|pair| InputRange::new(pair[0], pair[1]))
+                .collect();
+            network.set_input_ranges(ranges).map_err(schema_error)?;
+        }
+        Ok(network)
+    }
+}
+
+fn align(offset: u64, alignment: usize) -> u64 {
+    offset.div_ceil(alignment as u64) * alignment as u64
+}
+
+/// Reader that counts the bytes read, to locate the padding after the header
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+fn read_array<const N: usize, R: Read>(reader: &mut R) -> IoResult<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> IoResult<u32> {
+    Ok(u32::from_le_bytes(read_array(reader)?))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> IoResult<u64> {
+    Ok(u64::from_le_bytes(read_array(reader)?))
+}
+
+fn read_string<R: Read>(reader: &mut R) -> IoResult<String> {
+    let len = read_u64(reader)?;
+    if len > MAX_STRING {
+        return Err(IoError::InvalidFileFormat(format!("string of {len} bytes")));
+    }
+    let mut bytes = vec![0; len as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| IoError::InvalidFileFormat("non-UTF-8 string".to_string()))
+}
+
+/// Read `count` items, growing the vector as they arrive so that a corrupt
+/// count fails at the end of the file instead of allocating
+fn read_items<V, R: Read>(
+    reader: &mut R,
+    mut item: impl FnMut(&mut R) -> IoResult<V>,
+) -> IoResult<Vec<V>> {
+    let count = read_u64(reader)?;
+    let mut items = Vec::with_capacity(count.min(1024) as usize);
+    for _ in 0..count {
+        items.push(item(reader)?);
+    }
+    Ok(items)
+}
+
+fn read_value<R: Read>(reader: &mut R) -> IoResult<MetadataValue> {
+    let f64_item = |r: &mut R| read_array(r).map(f64::from_le_bytes);
+    Ok(match read_u32(reader)? {
+        0 => MetadataValue::U64(read_u64(reader)?),
+        1 => MetadataValue::I64(i64::from_le_bytes(read_array(reader)?)),
+        2 => MetadataValue::F64(f64_item(reader)?),
+        3 => MetadataValue::Bool(read_array::<1, R>(reader)?[0] != 0),
+        4 => MetadataValue::String(read_string(reader)?),
+        5 => MetadataValue::U64Array(read_items(reader, read_u64)?),
+        6 => MetadataValue::F64Array(read_items(reader, f64_item)?),
+        7 => MetadataValue::StringArray(read_items(reader, read_string)?),
+        other => {
+            return Err(IoError::InvalidFileFormat(format!(
+                "unknown metadata type {other}"
+            )))
+        }
+    })
+}
+
+fn read_tensor_info<R: Read>(reader: &mut R) -> IoResult<TensorInfo> {
+    let name = read_string(reader)?;
+    let rank = read_u32(reader)?;
+    let shape = (0..rank)
+        .map(|_| {
+            usize::try_from(read_u64(reader)?)
+                .map_err(|_| IoError::InvalidFileFormat(format!("tensor {name} is too large")))
+        })
+        .collect::<IoResult<Vec<_>>>()?;
+    let type_id = read_u32(reader)?;
+    let tensor_type = TensorType::from_id(type_id).ok_or_else(|| {
+        IoError::InvalidFileFormat(format!("tensor {name} has unknown type {type_id}"))
+    })?;
+    let quantization = match tensor_type {
+        TensorType::Q8 => Some(QuantizationParams {
+            scale: f32::from_le_bytes(read_array(reader)?),
+            zero_point: read_array::<1, R>(reader)?[0] as i8,
+        }),
+        _ => None,
+    };
+    let offset = read_u64(reader)?;
+    Ok(TensorInfo {
+        name,
+        shape,
+        tensor_type,
+        quantization,
+        offset,
+    })
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn write_value(out: &mut Vec<u8>, value: &MetadataValue) {
+    out.extend_from_slice(&value.type_id().to_le_bytes());
+    match value {
+        MetadataValue::U64(v) => out.extend_from_slice(&v.to_le_bytes()),
+        MetadataValue::I64(v) => out.extend_from_slice(&v.to_le_bytes()),
+        MetadataValue::F64(v) => out.extend_from_slice(&v.to_le_bytes()),
+        MetadataValue::Bool(v) => out.push(*v as u8),
+        MetadataValue::String(v) => write_string(out, v),
+        MetadataValue::U64Array(values) => {
+            out.extend_from_slice(&(values.len() as u64).to_le_bytes());
+            values
+                .iter()
+                .for_each(|v| out.extend_from_slice(&v.to_le_bytes()));
+        }
+        MetadataValue::F64Array(values) => {
+            out.extend_from_slice(&(values.len() as u64).to_le_bytes());
+            values
+                .iter()
+                .for_each(|v| out.extend_from_slice(&v.to_le_bytes()));
+        }
+        MetadataValue::StringArray(values) => {
+            out.extend_from_slice(&(values.len() as u64).to_le_bytes());
+            values.iter().for_each(|v| write_string(out, v));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+    use std::io::Cursor;
+
+    fn network() -> Network<f32> {
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(3)
+            .hidden_layer_with_activation(4, ActivationFunction::Tanh, 0.5)
+            .output_layer_with_activation(2, ActivationFunction::Sigmoid, 1.0)
+            .connection_rate(0.6)
+            .input_names(["a", "b", "c"])
+            .build();
+        network.layers[2].neurons[1].activation_function = ActivationFunction::Gelu;
+        network
+            .set_input_ranges(vec![InputRange::new(-1.0, 1.0); 3])
+            .unwrap();
+        network
+    }
+
+    #[test]
+    fn test_network_round_trip() {
+        let mut original = network();
+        let mut bytes = Cursor::new(Vec::new());
+        original
+            .write_model_file(&mut bytes, TensorType::F32)
+            .unwrap();
+        let mut bytes = bytes.into_inner();
+        let mut loaded = Network::<f32>::read_model_file(&mut Cursor::new(&bytes)).unwrap();
+
+        assert_eq!(loaded.get_weights(), original.get_weights());
+        assert_eq!(loaded.total_connections(), original.total_connections());
+        assert_eq!(loaded.schema(), original.schema());
+        assert_eq!(
+            loaded.layers[2].neurons[1].activation_function,
+            ActivationFunction::Gelu
+        );
+        let input = [0.3, -0.2, 0.9];
+        assert_eq!(loaded.run(&input), original.run(&input));
+
+        // Quantized weights stay close
+        let mut quantized = Cursor::new(Vec::new());
+        original
+            .write_model_file(&mut quantized, TensorType::Q8)
+            .unwrap();
+        let header = ModelFile::parse(quantized.get_ref()).unwrap();
+        let weights = header.tensor("layer1.weight").unwrap();
+        assert_eq!(weights.tensor_type, TensorType::Q8);
+        assert_eq!(weights.byte_len(), 12);
+        let mut loaded =
+            Network::<f32>::read_model_file(&mut Cursor::new(quantized.get_ref())).unwrap();
+        for (a, b) in loaded.run(&input).iter().zip(original.run(&input)) {
+            assert!((a - b).abs() < 0.05);
+        }
+
+        bytes[0] = b'X';
+        assert!(Network::<f32>::read_model_file(&mut Cursor::new(&bytes)).is_err());
+    }
+
+    #[test]
+    fn test_partial_and_borrowed_reads() {
+        let mut writer = ModelFileWriter::new().with_alignment(64);
+        writer.set_metadata("general.name", MetadataValue::String("demo".into()));
+        writer
+            .add_tensor("small", &[3], &[1.0, 2.0, 3.0], TensorType::F64)
+            .unwrap();
+        writer
+            .add_tensor("grid", &[2, 2], &[0.0, 1.5, -2.0, 4.0], TensorType::F32)
+            .unwrap();
+        assert!(writer
+            .add_tensor("small", &[1], &[0.0], TensorType::F32)
+            .is_err());
+        assert!(writer
+            .add_tensor("bad", &[2, 2], &[0.0], TensorType::F32)
+            .is_err());
+        let mut bytes = Vec::new();
+        writer.write(&mut bytes).unwrap();
+
+        let file = ModelFile::parse(&bytes).unwrap();
+        assert_eq!(
+            file.get("general.name").and_then(MetadataValue::as_str),
+            Some("demo")
+        );
+        assert_eq!(file.get("general.alignment"), Some(&MetadataValue::U64(64)));
+        assert_eq!(file.data_offset() % 64, 0);
+
+        let grid = file.tensor("grid").unwrap();
+        assert_eq!((file.data_offset() + grid.offset) % 64, 0);
+        let raw = file.tensor_bytes(&bytes, "grid").unwrap();
+        assert_eq!(grid.decode(raw).unwrap(), vec![0.0, 1.5, -2.0, 4.0]);
+        assert_eq!(
+            file.read_tensor(&mut Cursor::new(&bytes), "small").unwrap(),
+            vec![1.0, 2.0, 3.0]
+        );
+        assert!(file
+            .read_tensor(&mut Cursor::new(&bytes), "missing")
+            .is_err());
+        assert!(file
+            .tensor_bytes(&bytes[..bytes.len() - 60], "grid")
+            .is_err());
+    }
+}
@@ -0,0 +1,477 @@
+//! NumPy `.npz` weight export and import
+//!
+//! [`Network::export_npz`] writes the weights of every layer as NumPy
+//! arrays, so they can be inspected or edited with
+//! `numpy.load("net.npz")`, and [`Network::import_npz`] reads them back into
+//! a network of the same topology. For each layer `l` after the input layer
+//! there are two arrays:
+//!
+//! - `layer{l}_weights` with shape `(neurons, inputs)`: row `j` holds the
+//!   weights into neuron `j` from each regular neuron of the layer before.
+//! - `layer{l}_biases` with shape `(neurons,)`: the weights from the bias
+//!   neuron of the layer before.
+//!
+//! Arrays are `<f4` for `f32` networks and `<f8` otherwise; both are
+//! accepted on import, in C or Fortran order. Missing connections of sparse
+//! networks export as 0 and stay missing on import. Files are stored
+//! uncompressed like `numpy.savez`; `numpy.savez_compressed` files can be
+//! read with the `compression` feature.
+
+use crate::io::error::{IoError, IoResult};
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+/// DOS date of 1980-01-01, the earliest a zip entry can carry
+const ZIP_DATE: u16 = 0x21;
+
+/// Array read from one `.npy` entry of an archive
+#[derive(Debug, Clone, PartialEq)]
+struct NpyArray {
+    shape: Vec<usize>,
+    /// Values in C (row-major) order
+    data: Vec<f64>,
+}
+
+impl<T: Float> Network<T> {
+    /// Write the weights of every layer to an `.npz` file
+    pub fn export_npz<P: AsRef<Path>>(&self, path: P) -> IoResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_npz(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load weights exported by [`Network::export_npz`], or edited in
+    /// NumPy, into this network
+    ///
+    /// Nothing is changed unless every array is present with the shape of
+    /// the corresponding layer.
+    pub fn import_npz<P: AsRef<Path>>(&mut self, path: P) -> IoResult<()> {
+        self.read_npz(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Write the weights of every layer as an `.npz` archive to `writer`
+    pub fn write_npz<W: Write>(&self, writer: &mut W) -> IoResult<()> {
+        self.check_feed_forward()
+            .map_err(|e| IoError::InvalidNetwork(e.to_string()))?;
+        let single = std::mem::size_of::<T>() == 4;
+        let mut files = Vec::new();
+        for (l, pair) in self.layers.windows(2).enumerate() {
+            let (previous, layer) = (&pair[0], &pair[1]);
+            let inputs = previous.num_regular_neurons();
+            let neurons = layer.num_regular_neurons();
+            let mut weights = vec![0.0; neurons * inputs];
+            let mut biases = vec![0.0; neurons];
+            for (j, neuron) in layer.neurons.iter().take(neurons).enumerate() {
+                for connection in &neuron.connections {
+                    let weight: f64 = cast(connection.weight);
+                    match connection.from_neuron {
+                        i if i < inputs => weights[j * inputs + i] = weight,
+                        _ => biases[j] = weight,
+                    }
+                }
+            }
+            files.push((
+                format!("layer{}_weights.npy", l + 1),
+                npy_bytes(&[neurons, inputs], &weights, single),
+            ));
+            files.push((
+                format!("layer{}_biases.npy", l + 1),
+                npy_bytes(&[neurons], &biases, single),
+            ));
+        }
+        write_zip(writer, &files)
+    }
+
+    /// Load weights from an `.npz` archive read from `reader`
+    pub fn read_npz<R: Read>(&mut self, reader: &mut R) -> IoResult<()> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut arrays = HashMap::new();
+        for (name, data) in read_zip(&bytes)? {
+            if let Some(name) = name.strip_suffix(".npy") {
+                arrays.insert(name.to_string(), parse_npy(&data)?);
+            }
+        }
+
+        // Check every array before touching the network
+        let mut updates = Vec::new();
+        for (l, pair) in self.layers.windows(2).enumerate() {
+            let inputs = pair[0].num_regular_neurons();
+            let neurons = pair[1].num_regular_neurons();
+            let array = |suffix: &str, shape: &[usize]| {
+                let name = format!("layer{}_{suffix}", l + 1);
+                let array = arrays
+                    .get(&name)
+                    .ok_or_else(|| IoError::InvalidNetwork(format!("missing array {name}")))?;
+                if array.shape != shape {
+                    return Err(IoError::InvalidNetwork(format!(
+                        "array {name} has shape {:?}, expected {shape:?}",
+                        array.shape
+                    )));
+                }
+                Ok(&array.data)
+            };
+            updates.push((
+                array("weights", &[neurons, inputs])?,
+                array("biases", &[neurons])?,
+            ));
+        }
+
+        for (layer, (weights, biases)) in self.layers.iter_mut().skip(1).zip(updates) {
+            let neurons = layer.num_regular_neurons();
+            let inputs = weights.len() / neurons.max(1);
+            for (j, neuron) in layer.neurons.iter_mut().take(neurons).enumerate() {
+                for connection in &mut neuron.connections {
+                    connection.weight = cast(match connection.from_neuron {
+                        i if i < inputs => weights[j * inputs + i],
+                        _ => biases[j],
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `.npy` version 1.0 file holding `data` with `shape`
+fn npy_bytes(shape: &[usize], data: &[f64], single: bool) -> Vec<u8> {
+    let shape = match shape {
+        [n] => format!("({n},)"),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let descr = if single { "<f4" } else { "<f8" };
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+    // NumPy pads the header with spaces so the data starts 64-byte aligned
+    let unpadded = NPY_MAGIC.len() + 4 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    let mut bytes = NPY_MAGIC.to_vec();
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    for &value in data {
+        if single {
+            bytes.extend_from_slice(&(value as f32).to_le_bytes());
+        } else {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    bytes
+}
+
+fn parse_npy(bytes: &[u8]) -> IoResult<NpyArray> {
+    let invalid = |reason: &str| IoError::InvalidFileFormat(format!("bad .npy array: {reason}"));
+    if bytes.len() < 10 || &bytes[..6] != NPY_MAGIC {
+        return Err(invalid("missing magic string"));
+    }
+    let (header_len, header_start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10usize),
+        2 | 3 if bytes.len() >= 12 => (
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+            12,
+        ),
+        version => return Err(invalid(&format!("unsupported version {version}"))),
+    };
+    let data_start = header_start
+        .checked_add(header_len)
+        .ok_or_else(|| invalid("truncated header"))?;
+    let header = bytes
+        .get(header_start..data_start)
+        .and_then(|header| std::str::from_utf8(header).ok())
+        .ok_or_else(|| invalid("truncated header"))?;
+    let field = |key: &str| {
+        let start = header.find(&format!("'{key}':"))? + key.len() + 3;
+        Some(header[start..].trim_start())
+    };
+
+    let descr = field("descr").ok_or_else(|| invalid("no descr"))?;
+    let width = if descr.starts_with("'<f4'") {
+        4
+    } else if descr.starts_with("'<f8'") {
+        8
+    } else {
+        return Err(invalid(&format!(
+            "unsupported dtype {}",
+            descr.split(',').next().unwrap_or_default()
+        )));
+    };
+    let fortran_order = field("fortran_order").is_some_and(|value| value.starts_with("True"));
+    let shape: Vec<usize> = field("shape")
+        .and_then(|value| value.strip_prefix('('))
+        .and_then(|value| value.split(')').next())
+        .ok_or_else(|| invalid("no shape"))?
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse().map_err(|_| invalid("bad shape")))
+        .collect::<IoResult<_>>()?;
+
+    // A crafted shape can overflow the element or byte count
+    let size = shape
+        .iter()
+        .try_fold(1usize, |acc, &dim| acc.checked_mul(dim))
+        .and_then(|count| count.checked_mul(width))
+        .ok_or_else(|| invalid("shape is too large"))?;
+    let data = bytes
+        .get(data_start..)
+        .filter(|data| data.len() == size)
+        .ok_or_else(|| invalid("data does not match shape"))?;
+    let mut values: Vec<f64> = data
+        .chunks_exact(width)
+        .map(|chunk| match *chunk {
+            [a, b, c, d] => Ok(f64::from(f32::from_le_bytes([a, b, c, d]))),
+            [a, b, c, d, e, f, g, h] => Ok(f64::from_le_bytes([a, b, c, d, e, f, g, h])),
+            _ => Err(invalid("bad value width")),
+        })
+        .collect::<IoResult<_>>()?;
+    if fortran_order && shape.len() == 2 {
+        let (rows, cols) = (shape[0], shape[1]);
+        values = (0..rows * cols)
+            .map(|k| values[(k % cols) * rows + k / cols])
+            .collect();
+    } else if fortran_order && shape.len() > 2 {
+        return Err(invalid(
+            "Fortran order is only supported up to two dimensions",
+        ));
+    }
+    Ok(NpyArray {
+        shape,
+        data: values,
+    })
+}
+
+/// Write `files` as an uncompressed zip archive
+fn write_zip<W: Write>(writer: &mut W, files: &[(String, Vec<u8>)]) -> IoResult<()> {
+    let mut central = Vec::new();
+    let mut offset = 0u32;
+    for (name, data) in files {
+        let crc = crc32(data);
+        let size = u32::try_from(data.len())
+            .map_err(|_| IoError::SerializationError("array too large for .npz".to_string()))?;
+        let mut local = Vec::with_capacity(30 + name.len());
+        local.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        // Version 2.0, no flags, stored, time 0
+        for field in [20u16, 0, 0, 0, ZIP_DATE] {
+            local.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [crc, size, size] {
+            local.extend_from_slice(&field.to_le_bytes());
+        }
+        local.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        local.extend_from_slice(&0u16.to_le_bytes());
+        local.extend_from_slice(name.as_bytes());
+
+        central.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+        for field in [20u16, 20, 0, 0, 0, ZIP_DATE] {
+            central.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [crc, size, size] {
+            central.extend_from_slice(&field.to_le_bytes());
+        }
+        // Name length, then no extra field, comment, disk or attributes
+        for field in [name.len() as u16, 0, 0, 0, 0] {
+            central.extend_from_slice(&field.to_le_bytes());
+        }
+        central.extend_from_slice(&0u32.to_le_bytes());
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+
+        writer.write_all(&local)?;
+        writer.write_all(data)?;
+        offset += (local.len() + data.len()) as u32;
+    }
+    writer.write_all(&central)?;
+
+    let entries = files.len() as u16;
+    writer.write_all(&END_OF_CENTRAL_DIRECTORY.to_le_bytes())?;
+    for field in [0u16, 0, entries, entries] {
+        writer.write_all(&field.to_le_bytes())?;
+    }
+    writer.write_all(&(central.len() as u32).to_le_bytes())?;
+    writer.write_all(&offset.to_le_bytes())?;
+    writer.write_all(&0u16.to_le_bytes())?;
+    Ok(())
+}
+
+/// Names and contents of the entries of a zip archive
+fn read_zip(bytes: &[u8]) -> IoResult<Vec<(String, Vec<u8>)>> {
+    let invalid = |reason: &str| IoError::InvalidFileFormat(format!("bad .npz archive: {reason}"));
+    let u16_at = |at: usize| -> IoResult<usize> {
+        let field = bytes.get(at..at + 2).ok_or_else(|| invalid("truncated"))?;
+        Ok(u16::from_le_bytes([field[0], field[1]]) as usize)
+    };
+    let u32_at = |at: usize| -> IoResult<u32> {
+        let field = bytes.get(at..at + 4).ok_or_else(|| invalid("truncated"))?;
+        Ok(u32::from_le_bytes([field[0], field[1], field[2], field[3]]))
+    };
+
+    // The end record is last, followed only by a comment of up to 64 KiB
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .take(u16::MAX as usize + 1)
+        .find(|&at| u32_at(at).ok() == Some(END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| invalid("no end of central directory"))?;
+    let entries = u16_at(end + 10)?;
+    let mut at = u32_at(end + 16)? as usize;
+
+    let mut files = Vec::with_capacity(entries);
+    for _ in 0..entries {
+        if u32_at(at)? != CENTRAL_HEADER {
+            return Err(invalid("bad central directory"));
+        }
+        let method = u16_at(at + 10)?;
+        let crc = u32_at(at + 16)?;
+        let compressed = u32_at(at + 20)? as usize;
+        let name_len = u16_at(at + 28)?;
+        let skip = name_len + u16_at(at + 30)? + u16_at(at + 32)?;
+        let offset = u32_at(at + 42)? as usize;
+        let name = bytes
+            .get(at + 46..at + 46 + name_len)
+            .and_then(|name| std::str::from_utf8(name).ok())
+            .ok_or_else(|| invalid("bad entry name"))?
+            .to_string();
+        at += 46 + skip;
+
+        if u32_at(offset)? != LOCAL_HEADER {
+            return Err(invalid("bad local header"));
+        }
+        let start = offset + 30 + u16_at(offset + 26)? + u16_at(offset + 28)?;
+        let raw = bytes
+            .get(start..start + compressed)
+            .ok_or_else(|| invalid("truncated entry"))?;
+        let data = match method {
+            0 => raw.to_vec(),
+            #[cfg(feature = "compression")]
+            8 => {
+                let mut data = Vec::new();
+                flate2::read::DeflateDecoder::new(raw).read_to_end(&mut data)?;
+                data
+            }
+            _ => {
+                return Err(invalid(&format!(
+                    "entry {name} uses unsupported compression method {method}"
+                )))
+            }
+        };
+        if crc32(&data) != crc {
+            return Err(invalid(&format!("checksum mismatch in {name}")));
+        }
+        files.push((name, data));
+    }
+    Ok(files)
+}
+
+/// CRC-32 as used by zip (reflected polynomial 0xEDB88320)
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    fn network() -> Network<f32> {
+        NetworkBuilder::<f32>::new()
+            .input_layer(3)
+            .hidden_layer(4)
+            .output_layer(2)
+            .build()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = network();
+        let mut bytes = Vec::new();
+        source.write_npz(&mut bytes).unwrap();
+
+        let files = read_zip(&bytes).unwrap();
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "layer1_weights.npy",
+                "layer1_biases.npy",
+                "layer2_weights.npy",
+                "layer2_biases.npy"
+            ]
+        );
+        let weights = parse_npy(&files[2].1).unwrap();
+        assert_eq!(weights.shape, vec![2, 4]);
+        // Row 1 holds the weights into output neuron 1, its bias last
+        let into_output: Vec<f64> = source.layers[2].neurons[1]
+            .connections
+            .iter()
+            .map(|c| f64::from(c.weight))
+            .collect();
+        assert_eq!(&weights.data[4..8], &into_output[..4]);
+        assert_eq!(parse_npy(&files[3].1).unwrap().data[1], into_output[4]);
+
+        let mut target = network();
+        target.read_npz(&mut bytes.as_slice()).unwrap();
+        assert_eq!(target.get_weights(), source.get_weights());
+
+        let path = std::env::temp_dir().join(format!("weights_{}.npz", std::process::id()));
+        source.export_npz(&path).unwrap();
+        let mut loaded = network();
+        loaded.import_npz(&path).unwrap();
+        assert_eq!(loaded.get_weights(), source.get_weights());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_mismatched_arrays() {
+        let mut bytes = Vec::new();
+        network().write_npz(&mut bytes).unwrap();
+
+        let mut other = NetworkBuilder::<f32>::new()
+            .input_layer(3)
+            .hidden_layer(5)
+            .output_layer(2)
+            .build();
+        let before = other.get_weights();
+        assert!(other.read_npz(&mut bytes.as_slice()).is_err());
+        assert_eq!(other.get_weights(), before);
+
+        // A Fortran-ordered f8 array is read in row-major order
+        let mut npy = npy_bytes(&[2, 3], &[1.0, 4.0, 2.0, 5.0, 3.0, 6.0], false);
+        let header = String::from_utf8_lossy(&npy[10..64]).replace("False", "True ");
+        npy.splice(10..64, header.bytes());
+        let array = parse_npy(&npy).unwrap();
+        assert_eq!(array.data, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+        // A shape whose byte count overflows usize is rejected
+        let huge = npy_bytes(&[usize::MAX / 4, 2], &[], false);
+        assert!(matches!(
+            parse_npy(&huge),
+            Err(IoError::InvalidFileFormat(_))
+        ));
+
+        // Flip a weight inside the first array
+        bytes[120] ^= 0xff;
+        assert!(network().read_npz(&mut bytes.as_slice()).is_err());
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+}
@@ -0,0 +1,351 @@
+//! PMML export
+//!
+//! Writes a network as a PMML 4.4 `NeuralNetwork` regression model, the
+//! format many scoring engines in banking and insurance accept. Each layer
+//! becomes a `NeuralLayer` with one activation function; neurons list their
+//! incoming connections as `Con` elements, so sparse networks export as they
+//! are. Activation steepness is folded into the weights and biases, since
+//! PMML applies activations to the plain weighted sum.
+//!
+//! Input and output fields take their names from the network's
+//! [schema](crate::network::NetworkSchema), and fitted input ranges become
+//! `Interval`s in the data dictionary. PMML has no equivalent of several
+//! activations here (the `[0, 1]` Elliot and sine variants, leaky ReLU,
+//! GELU, Swish, ...); networks using them are rejected, as are layers mixing
+//! activations.
+
+use crate::io::error::{IoError, IoResult};
+use crate::numeric::cast;
+use crate::{ActivationFunction, Network};
+use num_traits::Float;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// PMML name of `function`, if PMML has it
+fn pmml_activation(function: ActivationFunction) -> Option<&'static str> {
+    use ActivationFunction::*;
+    Some(match function {
+        Linear => "identity",
+        Threshold => "threshold",
+        Sigmoid => "logistic",
+        SigmoidSymmetric | Tanh => "tanh",
+        Gaussian => "Gauss",
+        ElliotSymmetric => "Elliott",
+        SinSymmetric => "sine",
+        CosSymmetric => "cosine",
+        ReLU => "rectifier",
+        ThresholdSymmetric | GaussianSymmetric | Elliot | LinearPiece | LinearPieceSymmetric
+        | ReLULeaky | Sin | Cos | Gelu | Swish => return None,
+    })
+}
+
+/// Escape `value` for use in an XML attribute
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// PMML `NeuralNetwork` writer
+#[derive(Debug, Clone)]
+pub struct PmmlExporter {
+    model_name: String,
+    description: Option<String>,
+}
+
+impl Default for PmmlExporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PmmlExporter {
+    pub fn new() -> Self {
+        Self {
+            model_name: "do-fann".to_string(),
+            description: None,
+        }
+    }
+
+    /// `modelName` of the exported model
+    pub fn with_model_name(mut self, name: impl Into<String>) -> Self {
+        self.model_name = name.into();
+        self
+    }
+
+    /// Description written to the PMML header
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Write `network` as a PMML document
+    pub fn export_network<T: Float, W: Write>(
+        &self,
+        network: &Network<T>,
+        writer: &mut W,
+    ) -> IoResult<()> {
+        let layers = self.layer_activations(network)?;
+        let schema = network.schema();
+        let (inputs, outputs) = (network.num_inputs(), network.num_outputs());
+        let input_names: Vec<String> = (0..inputs)
+            .map(|i| xml_escape(&schema.input_name(i)))
+            .collect();
+        let output_names: Vec<String> = (0..outputs)
+            .map(|i| xml_escape(&schema.output_name(i)))
+            .collect();
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<PMML xmlns="http://www.dmg.org/PMML-4_4" version="4.4">"#
+        )?;
+        match &self.description {
+            Some(description) => writeln!(
+                writer,
+                r#"  <Header description="{}">"#,
+                xml_escape(description)
+            )?,
+            None => writeln!(writer, "  <Header>")?,
+        }
+        writeln!(
+            writer,
+            r#"    <Application name="do-fann" version="{}"/>"#,
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(writer, "  </Header>")?;
+
+        writeln!(
+            writer,
+            r#"  <DataDictionary numberOfFields="{}">"#,
+            inputs + outputs
+        )?;
+        for (i, name) in input_names.iter().enumerate() {
+            match schema.input_ranges.get(i) {
+                Some(range) => {
+                    writeln!(
+                        writer,
+                        r#"    <DataField name="{name}" optype="continuous" dataType="double">"#
+                    )?;
+                    writeln!(
+                        writer,
+                        r#"      <Interval closure="closedClosed" leftMargin="{}" rightMargin="{}"/>"#,
+                        range.min, range.max
+                    )?;
+                    writeln!(writer, "    </DataField>")?;
+                }
+                None => writeln!(
+                    writer,
+                    r#"    <DataField name="{name}" optype="continuous" dataType="double"/>"#
+                )?,
+            }
+        }
+        for name in &output_names {
+            writeln!(
+                writer,
+                r#"    <DataField name="{name}" optype="continuous" dataType="double"/>"#
+            )?;
+        }
+        writeln!(writer, "  </DataDictionary>")?;
+
+        writeln!(
+            writer,
+            r#"  <NeuralNetwork modelName="{}" functionName="regression" activationFunction="{}">"#,
+            xml_escape(&self.model_name),
+            layers.first().copied().unwrap_or("identity")
+        )?;
+        writeln!(writer, "    <MiningSchema>")?;
+        for name in &input_names {
+            writeln!(writer, r#"      <MiningField name="{name}"/>"#)?;
+        }
+        for name in &output_names {
+            writeln!(
+                writer,
+                r#"      <MiningField name="{name}" usageType="target"/>"#
+            )?;
+        }
+        writeln!(writer, "    </MiningSchema>")?;
+
+        writeln!(writer, r#"    <NeuralInputs numberOfInputs="{inputs}">"#)?;
+        for (i, name) in input_names.iter().enumerate() {
+            writeln!(writer, r#"      <NeuralInput id="0,{i}">"#)?;
+            writeln!(
+                writer,
+                r#"        <DerivedField optype="continuous" dataType="double"><FieldRef field="{name}"/></DerivedField>"#
+            )?;
+            writeln!(writer, "      </NeuralInput>")?;
+        }
+        writeln!(writer, "    </NeuralInputs>")?;
+
+        for (l, activation) in layers.iter().enumerate() {
+            let (previous, layer) = (&network.layers[l], &network.layers[l + 1]);
+            let neurons = layer.num_regular_neurons();
+            writeln!(
+                writer,
+                r#"    <NeuralLayer numberOfNeurons="{neurons}" activationFunction="{activation}">"#
+            )?;
+            for (j, neuron) in layer.neurons.iter().take(neurons).enumerate() {
+                let scale = if neuron.activation_function.uses_steepness() {
+                    neuron.activation_steepness
+                } else {
+                    T::one()
+                };
+                let mut bias = 0.0;
+                let mut connections = Vec::new();
+                for connection in &neuron.connections {
+                    let weight: f64 = cast(connection.weight * scale);
+                    match previous.neurons.get(connection.from_neuron) {
+                        Some(source) if source.is_bias => bias += weight,
+                        Some(_) => connections.push((connection.from_neuron, weight)),
+                        None => {}
+                    }
+                }
+                writeln!(writer, r#"      <Neuron id="{},{j}" bias="{bias}">"#, l + 1)?;
+                for (from, weight) in connections {
+                    writeln!(
+                        writer,
+                        r#"        <Con from="{l},{from}" weight="{weight}"/>"#
+                    )?;
+                }
+                writeln!(writer, "      </Neuron>")?;
+            }
+            writeln!(writer, "    </NeuralLayer>")?;
+        }
+
+        writeln!(writer, r#"    <NeuralOutputs numberOfOutputs="{outputs}">"#)?;
+        for (i, name) in output_names.iter().enumerate() {
+            writeln!(
+                writer,
+                r#"      <NeuralOutput outputNeuron="{},{i}">"#,
+                layers.len()
+            )?;
+            writeln!(
+                writer,
+                r#"        <DerivedField optype="continuous" dataType="double"><FieldRef field="{name}"/></DerivedField>"#
+            )?;
+            writeln!(writer, "      </NeuralOutput>")?;
+        }
+        writeln!(writer, "    </NeuralOutputs>")?;
+        writeln!(writer, "  </NeuralNetwork>")?;
+        writeln!(writer, "</PMML>")?;
+        Ok(())
+    }
+
+    /// PMML activation of every layer after the input layer
+    fn layer_activations<T: Float>(&self, network: &Network<T>) -> IoResult<Vec<&'static str>> {
+        network
+            .check_feed_forward()
+            .map_err(|e| IoError::InvalidNetwork(e.to_string()))?;
+        if network.layers.len() < 2 {
+            return Err(IoError::InvalidNetwork(
+                "PMML export needs an input and an output layer".to_string(),
+            ));
+        }
+        network.layers[1..]
+            .iter()
+            .enumerate()
+            .map(|(l, layer)| {
+                let mut functions = layer
+                    .neurons
+                    .iter()
+                    .filter(|neuron| !neuron.is_bias)
+                    .map(|neuron| neuron.activation_function);
+                let first = functions.next().unwrap_or(ActivationFunction::Linear);
+                if functions.any(|function| function != first) {
+                    return Err(IoError::InvalidNetwork(format!(
+                        "layer {} mixes activation functions, which PMML cannot express",
+                        l + 1
+                    )));
+                }
+                pmml_activation(first).ok_or_else(|| {
+                    IoError::InvalidNetwork(format!(
+                        "activation {} of layer {} has no PMML equivalent",
+                        first.name(),
+                        l + 1
+                    ))
+                })
+            })
+            .collect()
+    }
+}
+
+impl<T: Float> Network<T> {
+    /// Save as a PMML 4.4 `NeuralNetwork` regression model
+    pub fn save_pmml_file<P: AsRef<Path>>(&self, path: P) -> IoResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        PmmlExporter::new().export_network(self, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::InputRange;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_exports_neural_network() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer_with_activation(2, ActivationFunction::Tanh, 0.5)
+            .output_layer_with_activation(1, ActivationFunction::Linear, 1.0)
+            .input_names(["age", "income & assets"])
+            .output_names(["risk"])
+            .build();
+        network
+            .set_weights(&[1.0, -2.0, 0.5, 3.0, 4.0, -1.0, 0.25, 0.75, 0.1])
+            .unwrap();
+        network
+            .set_input_ranges(vec![InputRange::new(18.0, 90.0), InputRange::new(0.0, 1e6)])
+            .unwrap();
+
+        let mut bytes = Vec::new();
+        PmmlExporter::new()
+            .with_model_name("credit")
+            .export_network(&network, &mut bytes)
+            .unwrap();
+        let pmml = String::from_utf8(bytes).unwrap();
+
+        assert!(pmml.contains(r#"<DataField name="income &amp; assets""#));
+        assert!(pmml.contains(r#"leftMargin="18" rightMargin="90""#));
+        assert!(pmml.contains(r#"<MiningField name="risk" usageType="target"/>"#));
+        assert!(pmml.contains(r#"<NeuralLayer numberOfNeurons="2" activationFunction="tanh">"#));
+        // Steepness 0.5 is folded into the hidden weights and bias
+        assert!(pmml.contains(r#"<Neuron id="1,0" bias="0.25">"#));
+        assert!(pmml.contains(r#"<Con from="0,1" weight="-1"/>"#));
+        assert!(pmml.contains(r#"<Neuron id="2,0" bias="0.1">"#));
+        assert!(pmml.contains(r#"<Con from="1,1" weight="0.75"/>"#));
+        assert!(pmml.contains(r#"<NeuralOutput outputNeuron="2,0">"#));
+        assert_eq!(pmml.matches("<Neuron ").count(), 3);
+        assert!(pmml.trim_end().ends_with("</PMML>"));
+    }
+
+    #[test]
+    fn test_rejects_inexpressible_networks() {
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer_with_activation(3, ActivationFunction::Gelu, 1.0)
+            .output_layer(1)
+            .build();
+        let export =
+            |network: &Network<f32>| PmmlExporter::new().export_network(network, &mut Vec::new());
+        assert!(export(&network).is_err());
+
+        network.layers[1].set_activation_function(ActivationFunction::ReLU);
+        assert!(export(&network).is_ok());
+        network.layers[1].neurons[0].activation_function = ActivationFunction::Tanh;
+        assert!(export(&network).is_err());
+    }
+}
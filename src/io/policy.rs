@@ -0,0 +1,325 @@
+//! Load policies for model files
+//!
+//! A [`LoadPolicy`] sees the header of a [model file](super::ModelFile)
+//! before any weights are read and can refuse the model, in which case
+//! loading fails with [`IoError::PolicyViolation`]. Any
+//! `Fn(&ModelFile) -> Result<(), String>` is a policy.
+//!
+//! [`License`] stores usage terms as `license.*` metadata when a model is
+//! saved, and [`LicensePolicy`] enforces them when it is loaded:
+//!
+//! | key                            | type         | meaning                                |
+//! |--------------------------------|--------------|----------------------------------------|
+//! | `license.licensee`             | string       | who the model was issued to            |
+//! | `license.allowed_environments` | string array | environments the model may run in      |
+//! | `license.expires`              | u64          | expiry as seconds since the Unix epoch |
+//!
+//! The checks keep honest deployments within their terms; they are not
+//! tamper-proof on their own, so pair them with a signature check where that
+//! matters.
+
+use crate::io::error::{IoError, IoResult};
+use crate::io::{MetadataValue, ModelFile, TensorType};
+use crate::Network;
+use num_traits::Float;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const LICENSEE: &str = "license.licensee";
+const ALLOWED_ENVIRONMENTS: &str = "license.allowed_environments";
+const EXPIRES: &str = "license.expires";
+
+/// Decides whether a model file may be loaded
+pub trait LoadPolicy {
+    /// `Err` with the reason to refuse the model
+    fn check(&self, file: &ModelFile) -> Result<(), String>;
+}
+
+impl<F> LoadPolicy for F
+where
+    F: Fn(&ModelFile) -> Result<(), String>,
+{
+    fn check(&self, file: &ModelFile) -> Result<(), String> {
+        self(file)
+    }
+}
+
+/// Usage terms stored in a model file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct License {
+    pub licensee: Option<String>,
+    /// Environments the model may be loaded in; empty allows any
+    pub allowed_environments: Vec<String>,
+    pub expires: Option<SystemTime>,
+}
+
+impl License {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_licensee(mut self, licensee: impl Into<String>) -> Self {
+        self.licensee = Some(licensee.into());
+        self
+    }
+
+    pub fn allow_environment(mut self, environment: impl Into<String>) -> Self {
+        self.allowed_environments.push(environment.into());
+        self
+    }
+
+    /// Refuse the model from `expires` on
+    pub fn with_expiry(mut self, expires: SystemTime) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// The license in `file`, or `None` if it has no `license.*` metadata
+    pub fn from_model_file(file: &ModelFile) -> IoResult<Option<Self>> {
+        let mistyped = |key: &str| IoError::InvalidFileFormat(format!("mistyped metadata {key}"));
+        if !file
+            .metadata()
+            .keys()
+            .any(|key| key.starts_with("license."))
+        {
+            return Ok(None);
+        }
+        let licensee = match file.get(LICENSEE) {
+            None => None,
+            Some(value) => Some(
+                value
+                    .as_str()
+                    .ok_or_else(|| mistyped(LICENSEE))?
+                    .to_string(),
+            ),
+        };
+        let allowed_environments = match file.get(ALLOWED_ENVIRONMENTS) {
+            None => Vec::new(),
+            Some(MetadataValue::StringArray(environments)) => environments.clone(),
+            Some(_) => return Err(mistyped(ALLOWED_ENVIRONMENTS)),
+        };
+        let expires = match file.get(EXPIRES) {
+            None => None,
+            Some(value) => {
+                let seconds = value.as_u64().ok_or_else(|| mistyped(EXPIRES))?;
+                Some(UNIX_EPOCH + Duration::from_secs(seconds))
+            }
+        };
+        Ok(Some(Self {
+            licensee,
+            allowed_environments,
+            expires,
+        }))
+    }
+
+    /// Metadata entries that store the license
+    pub fn metadata(&self) -> Vec<(String, MetadataValue)> {
+        let mut entries = Vec::new();
+        if let Some(licensee) = &self.licensee {
+            entries.push((
+                LICENSEE.to_string(),
+                MetadataValue::String(licensee.clone()),
+            ));
+        }
+        if !self.allowed_environments.is_empty() {
+            entries.push((
+                ALLOWED_ENVIRONMENTS.to_string(),
+                MetadataValue::StringArray(self.allowed_environments.clone()),
+            ));
+        }
+        if let Some(expires) = self.expires {
+            let seconds = expires
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs());
+            entries.push((EXPIRES.to_string(), MetadataValue::U64(seconds)));
+        }
+        entries
+    }
+}
+
+/// Enforces the [`License`] stored in a model file
+///
+/// Models without a license load unless [`LicensePolicy::require_license`]
+/// is set.
+#[derive(Debug, Clone, Default)]
+pub struct LicensePolicy {
+    environment: Option<String>,
+    now: Option<SystemTime>,
+    require_license: bool,
+}
+
+impl LicensePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Environment this process runs in, matched against the license's
+    /// allowed environments
+    pub fn with_environment(mut self, environment: impl Into<String>) -> Self {
+        self.environment = Some(environment.into());
+        self
+    }
+
+    /// Check expiry against `now` instead of the system clock
+    pub fn with_time(mut self, now: SystemTime) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Refuse models that carry no license
+    pub fn require_license(mut self, require: bool) -> Self {
+        self.require_license = require;
+        self
+    }
+}
+
+impl LoadPolicy for LicensePolicy {
+    fn check(&self, file: &ModelFile) -> Result<(), String> {
+        let license = match License::from_model_file(file).map_err(|e| e.to_string())? {
+            Some(license) => license,
+            None if self.require_license => return Err("model has no license".to_string()),
+            None => return Ok(()),
+        };
+        if !license.allowed_environments.is_empty() {
+            match &self.environment {
+                Some(environment) if license.allowed_environments.contains(environment) => {}
+                Some(environment) => {
+                    return Err(format!(
+                        "model is not licensed for environment {environment:?}"
+                    ))
+                }
+                None => return Err("model is licensed per environment, none is set".to_string()),
+            }
+        }
+        if let Some(expires) = license.expires {
+            if self.now.unwrap_or_else(SystemTime::now) >= expires {
+                return Err("model license has expired".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Float> Network<T> {
+    /// Save as a model file carrying `license`
+    pub fn save_model_file_licensed<P: AsRef<Path>>(
+        &self,
+        path: P,
+        tensor_type: TensorType,
+        license: &License,
+    ) -> IoResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_model_file_licensed(&mut writer, tensor_type, license)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write as a model file carrying `license` to `writer`
+    pub fn write_model_file_licensed<W: Write>(
+        &self,
+        writer: &mut W,
+        tensor_type: TensorType,
+        license: &License,
+    ) -> IoResult<()> {
+        let mut file = self.model_file_writer(tensor_type)?;
+        for (key, value) in license.metadata() {
+            file.set_metadata(key, value);
+        }
+        file.write(writer)
+    }
+
+    /// Load a model file if `policy` accepts it
+    pub fn load_model_file_with_policy<P: AsRef<Path>>(
+        path: P,
+        policy: &dyn LoadPolicy,
+    ) -> IoResult<Self> {
+        Self::read_model_file_with_policy(&mut BufReader::new(File::open(path)?), policy)
+    }
+
+    /// Read a network from a model file at the start of `reader`, checking
+    /// its header against `policy` before reading any weights
+    pub fn read_model_file_with_policy<R: Read + Seek>(
+        reader: &mut R,
+        policy: &dyn LoadPolicy,
+    ) -> IoResult<Self> {
+        let start = reader.stream_position()?;
+        let file = ModelFile::read_header(reader)?;
+        policy.check(&file).map_err(IoError::PolicyViolation)?;
+        reader.seek(SeekFrom::Start(start))?;
+        Self::read_model_file(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+    use std::io::Cursor;
+
+    fn licensed(license: &License) -> Cursor<Vec<u8>> {
+        let network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        let mut bytes = Cursor::new(Vec::new());
+        network
+            .write_model_file_licensed(&mut bytes, TensorType::F32, license)
+            .unwrap();
+        bytes.set_position(0);
+        bytes
+    }
+
+    fn load(bytes: &mut Cursor<Vec<u8>>, policy: &dyn LoadPolicy) -> IoResult<Network<f32>> {
+        bytes.set_position(0);
+        Network::read_model_file_with_policy(bytes, policy)
+    }
+
+    #[test]
+    fn test_license_policy() {
+        let issued = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let day = Duration::from_secs(86_400);
+        let license = License::new()
+            .with_licensee("Example Corp")
+            .allow_environment("production")
+            .allow_environment("staging")
+            .with_expiry(issued + 30 * day);
+        let mut bytes = licensed(&license);
+        let header = ModelFile::read_header(&mut bytes).unwrap();
+        assert_eq!(License::from_model_file(&header).unwrap(), Some(license));
+
+        let policy = LicensePolicy::new()
+            .with_environment("production")
+            .with_time(issued + day);
+        assert!(load(&mut bytes, &policy).is_ok());
+        let refused = [
+            policy.clone().with_environment("development"),
+            LicensePolicy::new().with_time(issued),
+            policy.clone().with_time(issued + 30 * day),
+        ];
+        for policy in &refused {
+            assert!(matches!(
+                load(&mut bytes, policy),
+                Err(IoError::PolicyViolation(_))
+            ));
+        }
+
+        let mut unlicensed = licensed(&License::new());
+        assert!(load(&mut unlicensed, &LicensePolicy::new()).is_ok());
+        assert!(load(&mut unlicensed, &LicensePolicy::new().require_license(true)).is_err());
+    }
+
+    #[test]
+    fn test_custom_policy() {
+        let mut bytes = licensed(&License::new().with_licensee("Example Corp"));
+        let small = |file: &ModelFile| match file.tensors().iter().map(|t| t.len()).sum::<usize>() {
+            0..=16 => Ok(()),
+            n => Err(format!("{n} parameters")),
+        };
+        assert!(load(&mut bytes, &small).is_ok());
+        let refuse = |_: &ModelFile| Err("refused".to_string());
+        let error = load(&mut bytes, &refuse).unwrap_err();
+        assert_eq!(error.to_string(), "Model refused by load policy: refused");
+    }
+}
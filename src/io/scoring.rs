@@ -0,0 +1,470 @@
+//! Batch scoring of delimited text and Parquet files
+//!
+//! [`score_file`] streams a CSV file through an optional fitted input
+//! transform and the network, writing one row of predictions per input row,
+//! without loading the whole file into memory. [`BatchScorer`] exposes the
+//! same pipeline with options for the delimiter, header, batch size and
+//! handling of malformed rows, and works on any reader/writer pair.
+//!
+//! Input rows hold exactly `num_inputs` numeric fields. Blank lines and lines
+//! starting with `#` are ignored and fields may be quoted. With
+//! [`BatchScorer::with_header`] the first row is a header and the output gets
+//! an `output_0,output_1,...` header as well; otherwise a first row that
+//! does not parse is malformed like any other.
+//!
+//! With the `parquet` feature, [`BatchScorer::score_parquet_file`] scores a
+//! Parquet file whose columns are the network inputs into a Parquet file of
+//! predictions.
+
+use crate::io::error::{IoError, IoResult};
+use crate::preprocessing::Transform;
+use crate::Network;
+use num_traits::Float;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Summary of a scoring run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScoreStats {
+    /// Rows written to the output
+    pub rows_scored: usize,
+    /// Malformed rows dropped with [`BatchScorer::with_skip_invalid`]
+    pub rows_skipped: usize,
+    /// Network batches run
+    pub batches: usize,
+}
+
+/// Configurable streaming scorer
+pub struct BatchScorer<'a, T: Float> {
+    scaler: Option<&'a dyn Transform<T>>,
+    batch_size: usize,
+    delimiter: char,
+    has_header: bool,
+    skip_invalid: bool,
+}
+
+impl<T: Float> Default for BatchScorer<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: Float> BatchScorer<'a, T> {
+    pub fn new() -> Self {
+        Self {
+            scaler: None,
+            batch_size: 1024,
+            delimiter: ',',
+            has_header: false,
+            skip_invalid: false,
+        }
+    }
+
+    /// Apply a fitted transform to every row before it reaches the network
+    pub fn with_scaler(mut self, scaler: &'a dyn Transform<T>) -> Self {
+        self.scaler = Some(scaler);
+        self
+    }
+
+    /// Rows per network batch (at least 1)
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Field separator of input and output, `,` by default
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Whether the first row of the input is a header, which is skipped and
+    /// answered with an `output_0,output_1,...` header; off by default
+    pub fn with_header(mut self, has_header: bool) -> Self {
+        self.has_header = has_header;
+        self
+    }
+
+    /// Drop malformed rows instead of failing; they are counted in
+    /// [`ScoreStats::rows_skipped`]
+    pub fn with_skip_invalid(mut self, skip_invalid: bool) -> Self {
+        self.skip_invalid = skip_invalid;
+        self
+    }
+
+    /// Score the file at `input_path` into `output_path`
+    pub fn score_file(
+        &self,
+        network: &Network<T>,
+        input_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+    ) -> IoResult<ScoreStats> {
+        let mut reader = File::open(input_path)?;
+        let mut writer = BufWriter::new(File::create(output_path)?);
+        let stats = self.score(network, &mut reader, &mut writer)?;
+        writer.flush()?;
+        Ok(stats)
+    }
+
+    /// Score rows read from `reader` into `writer`
+    pub fn score<R: Read, W: Write>(
+        &self,
+        network: &Network<T>,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> IoResult<ScoreStats> {
+        let mut network = network.clone();
+        let num_inputs = network.num_inputs();
+        let mut stats = ScoreStats::default();
+        let mut batch = Vec::with_capacity(self.batch_size);
+        let mut first_row = true;
+
+        for (line_idx, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if std::mem::take(&mut first_row) && self.has_header {
+                self.write_header(writer, network.num_outputs())?;
+                continue;
+            }
+            let row = match self.parse_row(line, num_inputs) {
+                Ok(row) => row,
+                Err(_) if self.skip_invalid => {
+                    stats.rows_skipped += 1;
+                    continue;
+                }
+                Err(message) => {
+                    return Err(IoError::ParseError(format!(
+                        "line {}: {message}",
+                        line_idx + 1
+                    )));
+                }
+            };
+            batch.push(row);
+            if batch.len() == self.batch_size {
+                self.flush_batch(&mut network, &mut batch, writer, &mut stats)?;
+            }
+        }
+        self.flush_batch(&mut network, &mut batch, writer, &mut stats)?;
+        Ok(stats)
+    }
+
+    fn parse_row(&self, line: &str, num_inputs: usize) -> Result<Vec<T>, String> {
+        let row = line
+            .split(self.delimiter)
+            .map(|field| {
+                let field = field.trim().trim_matches('"').trim();
+                field
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(T::from)
+                    .ok_or_else(|| format!("invalid number '{field}'"))
+            })
+            .collect::<Result<Vec<T>, String>>()?;
+        if row.len() != num_inputs {
+            return Err(format!("expected {num_inputs} fields, got {}", row.len()));
+        }
+        Ok(row)
+    }
+
+    fn write_header<W: Write>(&self, writer: &mut W, num_outputs: usize) -> IoResult<()> {
+        let names: Vec<String> = (0..num_outputs).map(|i| format!("output_{i}")).collect();
+        writeln!(writer, "{}", names.join(&self.delimiter.to_string()))?;
+        Ok(())
+    }
+
+    fn flush_batch<W: Write>(
+        &self,
+        network: &mut Network<T>,
+        batch: &mut Vec<Vec<T>>,
+        writer: &mut W,
+        stats: &mut ScoreStats,
+    ) -> IoResult<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let delimiter = self.delimiter.to_string();
+        for outputs in self.predict(network, batch, stats)? {
+            let fields: Vec<String> = outputs
+                .iter()
+                .map(|o| o.to_f64().unwrap_or(f64::NAN).to_string())
+                .collect();
+            writeln!(writer, "{}", fields.join(&delimiter))?;
+        }
+        batch.clear();
+        Ok(())
+    }
+
+    /// Network outputs for a non-empty batch, counted in `stats`
+    fn predict(
+        &self,
+        network: &mut Network<T>,
+        batch: &[Vec<T>],
+        stats: &mut ScoreStats,
+    ) -> IoResult<Vec<Vec<T>>> {
+        let outputs = match self.scaler {
+            Some(scaler) => {
+                let scaled = scaler
+                    .transform_batch(batch)
+                    .map_err(|e| IoError::InvalidTrainingData(e.to_string()))?;
+                network.run_batch(&scaled)
+            }
+            None => network.run_batch(batch),
+        };
+        stats.rows_scored += batch.len();
+        stats.batches += 1;
+        Ok(outputs)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl<T: Float> BatchScorer<'_, T> {
+    /// Score the Parquet file at `input_path` into a Parquet file of
+    /// `Float64` columns `output_0`, `output_1`, ... at `output_path`
+    ///
+    /// The input columns are the network inputs in file order and must be
+    /// numeric, or text that parses as numbers; rows with nulls are
+    /// malformed. The delimiter and header settings do not apply.
+    pub fn score_parquet_file(
+        &self,
+        network: &Network<T>,
+        input_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+    ) -> IoResult<ScoreStats> {
+        use arrow::array::{Array, ArrayRef, AsArray, Float64Array, RecordBatchReader};
+        use arrow::compute::cast;
+        use arrow::datatypes::{DataType, Field, Float64Type, Schema};
+        use arrow::record_batch::RecordBatch;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        use parquet::arrow::ArrowWriter;
+        use std::sync::Arc;
+
+        let format = |e: &dyn std::fmt::Display| IoError::InvalidFileFormat(e.to_string());
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(input_path)?)
+            .and_then(|builder| builder.with_batch_size(self.batch_size).build())
+            .map_err(|e| format(&e))?;
+        let num_inputs = network.num_inputs();
+        let columns = reader.schema().fields().len();
+        if columns != num_inputs {
+            return Err(IoError::InvalidTrainingData(format!(
+                "expected {num_inputs} columns, got {columns}"
+            )));
+        }
+        let fields: Vec<Field> = (0..network.num_outputs())
+            .map(|i| Field::new(format!("output_{i}"), DataType::Float64, false))
+            .collect();
+        let schema = Arc::new(Schema::new(fields));
+        let mut writer = ArrowWriter::try_new(File::create(output_path)?, schema.clone(), None)
+            .map_err(|e| format(&e))?;
+
+        let mut network = network.clone();
+        let mut stats = ScoreStats::default();
+        let mut rows_read = 0;
+        for record_batch in reader {
+            let record_batch = record_batch.map_err(|e| format(&e))?;
+            let columns = record_batch
+                .columns()
+                .iter()
+                .map(|column| cast(column, &DataType::Float64))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| IoError::InvalidTrainingData(e.to_string()))?;
+            let columns: Vec<&Float64Array> = columns
+                .iter()
+                .map(|c| c.as_primitive::<Float64Type>())
+                .collect();
+            let mut batch = Vec::with_capacity(record_batch.num_rows());
+            for row in 0..record_batch.num_rows() {
+                if columns.iter().any(|c| c.is_null(row)) {
+                    if self.skip_invalid {
+                        stats.rows_skipped += 1;
+                        continue;
+                    }
+                    return Err(IoError::ParseError(format!(
+                        "row {}: missing or invalid value",
+                        rows_read + row + 1
+                    )));
+                }
+                batch.push(
+                    columns
+                        .iter()
+                        .map(|c| crate::numeric::cast(c.value(row)))
+                        .collect(),
+                );
+            }
+            rows_read += record_batch.num_rows();
+            if batch.is_empty() {
+                continue;
+            }
+            let outputs = self.predict(&mut network, &batch, &mut stats)?;
+            let output_columns: Vec<ArrayRef> = (0..schema.fields().len())
+                .map(|i| {
+                    let values = outputs.iter().map(|o| o[i].to_f64().unwrap_or(f64::NAN));
+                    Arc::new(Float64Array::from_iter_values(values)) as ArrayRef
+                })
+                .collect();
+            let predictions =
+                RecordBatch::try_new(schema.clone(), output_columns).map_err(|e| format(&e))?;
+            writer.write(&predictions).map_err(|e| format(&e))?;
+        }
+        writer.close().map_err(|e| format(&e))?;
+        Ok(stats)
+    }
+}
+
+/// Score the CSV file at `input_path` with `network`, writing one row of
+/// comma-separated predictions per input row to `output_path`
+pub fn score_file<T: Float>(
+    network: &Network<T>,
+    input_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+) -> IoResult<ScoreStats> {
+    BatchScorer::new().score_file(network, input_path, output_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocessing::OnlineScaler;
+    use crate::tests::fixtures::xor_network;
+
+    fn parse(output: &[u8]) -> Vec<f64> {
+        String::from_utf8_lossy(output)
+            .lines()
+            .filter(|l| !l.starts_with("output"))
+            .map(|l| l.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_scores_file_in_batches() {
+        let network = xor_network::<f64>();
+        let rows: Vec<Vec<f64>> = (0..7).map(|i| vec![i as f64 * 0.1, 1.0]).collect();
+        let csv: String = std::iter::once("x,y\n".to_string())
+            .chain(rows.iter().map(|r| format!("\"{}\", {}\n\n", r[0], r[1])))
+            .collect();
+        let input = std::env::temp_dir().join(format!("score_in_{}.csv", std::process::id()));
+        let output = std::env::temp_dir().join(format!("score_out_{}.csv", std::process::id()));
+        std::fs::write(&input, csv).unwrap();
+
+        let stats = BatchScorer::new()
+            .with_header(true)
+            .score_file(&network, &input, &output)
+            .unwrap();
+        let written = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&input).ok();
+        std::fs::remove_file(&output).ok();
+        assert_eq!(stats.rows_scored, 7);
+        assert!(written.starts_with(b"output_0\n"));
+
+        let expected: Vec<f64> = rows.iter().map(|r| network.clone().run(r)[0]).collect();
+        assert_eq!(parse(&written), expected);
+
+        // Batch size does not change the predictions
+        let mut small = Vec::new();
+        let csv: String = rows
+            .iter()
+            .map(|r| format!("{} {}\n", r[0], r[1]))
+            .collect();
+        let stats = BatchScorer::new()
+            .with_batch_size(3)
+            .with_delimiter(' ')
+            .score(&network, &mut csv.as_bytes(), &mut small)
+            .unwrap();
+        assert_eq!(stats.batches, 3);
+        assert_eq!(parse(&small), expected);
+
+        // Without a declared header, a malformed first row is an error
+        let mut headerless = Vec::new();
+        let err = BatchScorer::new().score(&network, &mut "x,y\n0,1\n".as_bytes(), &mut headerless);
+        assert!(matches!(err, Err(IoError::ParseError(m)) if m.starts_with("line 1")));
+        let stats = score_file(&network, &input, &output);
+        assert!(stats.is_err());
+    }
+
+    #[test]
+    fn test_invalid_rows_and_scaler() {
+        let network = xor_network::<f64>();
+        let csv = "0.5,1.0\n1.0,oops\n# comment\n0.2\n2.0,3.0\n";
+        let mut output = Vec::new();
+        let err = BatchScorer::new().score(&network, &mut csv.as_bytes(), &mut output);
+        assert!(matches!(err, Err(IoError::ParseError(m)) if m.starts_with("line 2")));
+
+        let mut scaler = OnlineScaler::new();
+        scaler.fit(&[vec![0.0, 0.0], vec![2.0, 4.0]]).unwrap();
+        let mut output = Vec::new();
+        let stats = BatchScorer::new()
+            .with_scaler(&scaler)
+            .with_skip_invalid(true)
+            .score(&network, &mut csv.as_bytes(), &mut output)
+            .unwrap();
+        assert_eq!((stats.rows_scored, stats.rows_skipped), (2, 2));
+
+        let mut reference = network.clone();
+        let expected: Vec<f64> = [vec![0.5, 1.0], vec![2.0, 3.0]]
+            .iter()
+            .map(|r| reference.run(&scaler.transform(r).unwrap())[0])
+            .collect();
+        assert_eq!(parse(&output), expected);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_scores_parquet_file() {
+        use arrow::array::{ArrayRef, AsArray, Float32Array, Int64Array};
+        use arrow::datatypes::Float64Type;
+        use arrow::record_batch::RecordBatch;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+        use parquet::arrow::ArrowWriter;
+        use std::sync::Arc;
+
+        let dir = std::env::temp_dir().join(format!("score_parquet_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("in.parquet"), dir.join("out.parquet"));
+        let x: ArrayRef = Arc::new(Float32Array::from(vec![Some(0.5), None, Some(1.5)]));
+        let y: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
+        let batch = RecordBatch::try_from_iter([("x", x), ("y", y)]).unwrap();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&input).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let network = xor_network::<f64>();
+        assert!(BatchScorer::new()
+            .score_parquet_file(&network, &input, &output)
+            .is_err());
+        let stats = BatchScorer::new()
+            .with_skip_invalid(true)
+            .with_batch_size(2)
+            .score_parquet_file(&network, &input, &output)
+            .unwrap();
+        assert_eq!(
+            (stats.rows_scored, stats.rows_skipped, stats.batches),
+            (2, 1, 2)
+        );
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&output).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let scored: Vec<f64> = reader
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                assert_eq!(batch.schema().field(0).name(), "output_0");
+                batch
+                    .column(0)
+                    .as_primitive::<Float64Type>()
+                    .values()
+                    .to_vec()
+            })
+            .collect();
+        std::fs::remove_dir_all(&dir).ok();
+        let mut reference = network.clone();
+        assert_eq!(
+            scored,
+            vec![reference.run(&[0.5, 1.0])[0], reference.run(&[1.5, 3.0])[0]]
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Ed25519 signatures on model files
+//!
+//! A signed file is the unsigned file followed by a 72-byte trailer: the
+//! 64-byte Ed25519 signature of everything before it, then the marker
+//! `DFSIG001`. Readers of the [model file](super::ModelFile) format never
+//! look past the tensor data, so a signed file still loads anywhere, while
+//! [`verify`] and [`Network::load_model_file_verified`] reject files that
+//! are unsigned, were signed by another key or changed after signing.
+//!
+//! The training pipeline keeps the [`SigningKey`] (or its 32-byte seed);
+//! deployments only need the [`VerifyingKey`].
+
+use crate::io::error::{IoError, IoResult};
+use crate::io::TensorType;
+use crate::Network;
+use ed25519_compact::{KeyPair, PublicKey, Seed, Signature};
+use num_traits::Float;
+use rand::RngCore;
+use std::fs::{self, File};
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+const MARKER: &[u8; 8] = b"DFSIG001";
+const TRAILER: usize = Signature::BYTES + MARKER.len();
+
+/// Private key used to sign model files
+#[derive(Clone)]
+pub struct SigningKey(KeyPair);
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SigningKey")
+            .field(&self.verifying_key())
+            .finish()
+    }
+}
+
+impl SigningKey {
+    /// New key from the operating system's random number generator
+    pub fn generate() -> Self {
+        let mut seed = [0; Seed::BYTES];
+        rand::rngs::OsRng.fill_bytes(&mut seed);
+        Self::from_seed(seed)
+    }
+
+    /// Key derived from a stored 32-byte seed
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self(KeyPair::from_seed(Seed::new(seed)))
+    }
+
+    /// Seed to store the key with; keep it secret
+    pub fn seed(&self) -> [u8; 32] {
+        *self.0.sk.seed()
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey(self.0.pk)
+    }
+}
+
+/// Public key that checks model file signatures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyingKey(PublicKey);
+
+impl VerifyingKey {
+    pub fn from_bytes(bytes: &[u8]) -> IoResult<Self> {
+        PublicKey::from_slice(bytes)
+            .map(Self)
+            .map_err(|_| IoError::InvalidSignature("public key must be 32 bytes".to_string()))
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        *self.0
+    }
+
+    /// Parse the 64-digit hex form, as kept in deployment configuration
+    pub fn from_hex(hex: &str) -> IoResult<Self> {
+        let invalid = || IoError::InvalidSignature(format!("invalid public key {hex:?}"));
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let bytes = (0..32)
+            .map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid()))
+            .collect::<IoResult<Vec<u8>>>()?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+/// Split a signed file into its content and signature, if it has a trailer
+fn split_signed(bytes: &[u8]) -> Option<(&[u8], Signature)> {
+    let content_len = bytes.len().checked_sub(TRAILER)?;
+    let (content, trailer) = bytes.split_at(content_len);
+    let (signature, marker) = trailer.split_at(Signature::BYTES);
+    if marker != MARKER {
+        return None;
+    }
+    Some((content, Signature::from_slice(signature).ok()?))
+}
+
+/// `bytes` with an existing signature trailer removed
+fn unsigned(bytes: &[u8]) -> &[u8] {
+    split_signed(bytes).map_or(bytes, |(content, _)| content)
+}
+
+/// Append a signature of `bytes` (replacing any existing one)
+pub fn sign_bytes(bytes: &[u8], key: &SigningKey) -> Vec<u8> {
+    let content = unsigned(bytes);
+    let signature = key.0.sk.sign(content, None);
+    let mut signed = Vec::with_capacity(content.len() + TRAILER);
+    signed.extend_from_slice(content);
+    signed.extend_from_slice(signature.as_ref());
+    signed.extend_from_slice(MARKER);
+    signed
+}
+
+/// Check the signature of a signed file held in memory and return the
+/// signed content
+pub fn verify_bytes<'a>(bytes: &'a [u8], key: &VerifyingKey) -> IoResult<&'a [u8]> {
+    let (content, signature) = split_signed(bytes)
+        .ok_or_else(|| IoError::InvalidSignature("file is not signed".to_string()))?;
+    key.0.verify(content, &signature).map_err(|_| {
+        IoError::InvalidSignature("signature does not match the file or key".to_string())
+    })?;
+    Ok(content)
+}
+
+/// Sign the file at `path` in place
+///
+/// The signed copy is written next to the file and renamed over it, so the
+/// file is never left half-written.
+pub fn sign_file<P: AsRef<Path>>(path: P, key: &SigningKey) -> IoResult<()> {
+    let path = path.as_ref();
+    let signed = sign_bytes(&fs::read(path)?, key);
+    let temp = path.with_extension("sig.tmp");
+    let mut file = File::create(&temp)?;
+    file.write_all(&signed)?;
+    file.sync_all()?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// Check that the file at `path` carries a valid signature from `key`
+pub fn verify<P: AsRef<Path>>(path: P, key: &VerifyingKey) -> IoResult<()> {
+    verify_bytes(&fs::read(path)?, key).map(|_| ())
+}
+
+impl<T: Float> Network<T> {
+    /// Save as a model file signed with `key`
+    pub fn save_model_file_signed<P: AsRef<Path>>(
+        &self,
+        path: P,
+        tensor_type: TensorType,
+        key: &SigningKey,
+    ) -> IoResult<()> {
+        let mut bytes = Cursor::new(Vec::new());
+        self.write_model_file(&mut bytes, tensor_type)?;
+        let mut file = File::create(path)?;
+        file.write_all(&sign_bytes(bytes.get_ref(), key))?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Load a model file only if it carries a valid signature from `key`
+    ///
+    /// The file is read once and checked before any of it is parsed.
+    pub fn load_model_file_verified<P: AsRef<Path>>(path: P, key: &VerifyingKey) -> IoResult<Self> {
+        let bytes = fs::read(path)?;
+        let content = verify_bytes(&bytes, key)?;
+        Self::read_model_file(&mut Cursor::new(content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::ModelFile;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_sign_and_verify_bytes() {
+        let key = SigningKey::from_seed([7; 32]);
+        let public = key.verifying_key();
+        assert_eq!(SigningKey::from_seed(key.seed()).verifying_key(), public);
+        assert_eq!(VerifyingKey::from_hex(&public.to_hex()).unwrap(), public);
+        assert!(VerifyingKey::from_hex("zz").is_err());
+
+        let mut bytes = Cursor::new(Vec::new());
+        NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build()
+            .write_model_file(&mut bytes, TensorType::F32)
+            .unwrap();
+        let bytes = bytes.into_inner();
+        let signed = sign_bytes(&bytes, &key);
+        assert_eq!(verify_bytes(&signed, &public).unwrap(), &bytes[..]);
+        // Re-signing replaces the trailer, and readers ignore it
+        assert_eq!(sign_bytes(&signed, &key), signed);
+        assert!(ModelFile::parse(&signed).is_ok());
+
+        let other = SigningKey::from_seed([8; 32]).verifying_key();
+        assert!(verify_bytes(&signed, &other).is_err());
+        assert!(verify_bytes(&bytes, &public).is_err());
+        let mut tampered = signed.clone();
+        tampered[40] ^= 1;
+        assert!(verify_bytes(&tampered, &public).is_err());
+    }
+
+    #[test]
+    fn test_signed_model_files() {
+        let dir = std::env::temp_dir().join(format!("do-fann-signing-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.dfm");
+        let key = SigningKey::generate();
+        let public = key.verifying_key();
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(3)
+            .hidden_layer(2)
+            .output_layer(1)
+            .build();
+
+        network.save_model_file(&path, TensorType::F64).unwrap();
+        assert!(verify(&path, &public).is_err());
+        assert!(Network::<f64>::load_model_file_verified(&path, &public).is_err());
+        sign_file(&path, &key).unwrap();
+        verify(&path, &public).unwrap();
+
+        network
+            .save_model_file_signed(&path, TensorType::F64, &key)
+            .unwrap();
+        let mut loaded = Network::<f64>::load_model_file_verified(&path, &public).unwrap();
+        assert_eq!(loaded.run(&[0.1, 0.2, 0.3]), network.run(&[0.1, 0.2, 0.3]));
+        assert!(Network::<f64>::load_model_file(&path).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
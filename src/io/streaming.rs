@@ -299,10 +299,8 @@ pub mod memory {
         num_output: usize,
     ) -> usize {
         let sample_memory = estimate_batch_memory(1, num_input, num_output);
-        if sample_memory == 0 {
-            1000 // Default fallback
-        } else {
-            (memory_limit_bytes / sample_memory).max(1)
-        }
+        memory_limit_bytes
+            .checked_div(sample_memory)
+            .map_or(1000, |samples| samples.max(1))
     }
 }
@@ -1,3 +1,4 @@
+use crate::numeric::cast;
 use crate::{ActivationFunction, Neuron};
 use num_traits::Float;
 use rand::Rng;
@@ -5,11 +6,108 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 /// Represents a layer of neurons in the neural network
+///
+/// Build layers with [`Layer::new`], [`Layer::with_bias`] or
+/// [`Layer::from_neurons`]; the struct is `#[non_exhaustive]` so fields can
+/// be added without breaking callers.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub struct Layer<T: Float> {
     /// The neurons in this layer
     pub neurons: Vec<Neuron<T>>,
+    /// Fraction of the layer's neurons dropped in each training sample;
+    /// ignored by [`Network::run`](crate::Network::run)
+    ///
+    /// Networks saved before the field existed load with 0: self-describing
+    /// formats such as JSON fill in the serde default, and
+    /// [`Network::from_bytes`](crate::Network::from_bytes) upgrades
+    /// unversioned `to_bytes` blobs. Other bincode written without the field
+    /// cannot be read.
+    #[cfg_attr(feature = "serde", serde(default = "no_dropout"))]
+    pub dropout: T,
+    /// Feedback from the previous time step, for recurrent layers
+    #[cfg_attr(feature = "serde", serde(default = "no_recurrence"))]
+    pub recurrence: Option<Recurrence<T>>,
+}
+
+/// Where a recurrent layer's context comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RecurrenceKind {
+    /// Elman network: the layer's own outputs from the previous time step
+    Elman,
+    /// Jordan network: the network's outputs from the previous time step
+    Jordan,
+}
+
+/// Context units of a recurrent layer
+///
+/// Each regular neuron of the layer adds the weighted context values to its
+/// input sum. The context holds the values of the previous time step and is
+/// updated by [`Network::run`](crate::Network::run).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Recurrence<T: Float> {
+    pub kind: RecurrenceKind,
+    /// `weights[i][j]` scales context value `j` into neuron `i`
+    pub weights: Vec<Vec<T>>,
+    /// Context values from the previous time step
+    pub context: Vec<T>,
+}
+
+impl<T: Float> Recurrence<T> {
+    /// Context of `context_size` units feeding `num_neurons` neurons, with
+    /// random weights between -0.1 and 0.1 and a zero context
+    pub fn new(kind: RecurrenceKind, num_neurons: usize, context_size: usize) -> Self {
+        Self::with_rng(kind, num_neurons, context_size, &mut rand::thread_rng())
+    }
+
+    /// [`Recurrence::new`] drawing the weights from `rng`
+    pub fn with_rng<R: Rng + ?Sized>(
+        kind: RecurrenceKind,
+        num_neurons: usize,
+        context_size: usize,
+        rng: &mut R,
+    ) -> Self {
+        let weights = (0..num_neurons)
+            .map(|_| {
+                (0..context_size)
+                    .map(|_| cast(rng.gen::<f64>() * 0.2 - 0.1))
+                    .collect()
+            })
+            .collect();
+        Recurrence {
+            kind,
+            weights,
+            context: vec![T::zero(); context_size],
+        }
+    }
+
+    /// Weighted context input of neuron `neuron`
+    pub fn feedback(&self, neuron: usize) -> T {
+        self.weights.get(neuron).map_or(T::zero(), |weights| {
+            weights
+                .iter()
+                .zip(&self.context)
+                .fold(T::zero(), |sum, (&w, &c)| sum + w * c)
+        })
+    }
+
+    /// Clear the context, as at the start of a sequence
+    pub fn reset(&mut self) {
+        self.context.iter_mut().for_each(|c| *c = T::zero());
+    }
+}
+
+#[cfg(feature = "serde")]
+fn no_dropout<T: Float>() -> T {
+    T::zero()
+}
+
+#[cfg(feature = "serde")]
+fn no_recurrence<T: Float>() -> Option<Recurrence<T>> {
+    None
 }
 
 impl<T: Float> Layer<T> {
@@ -36,7 +134,26 @@ impl<T: Float> Layer<T> {
             .map(|_| Neuron::new(activation_function, activation_steepness))
             .collect();
 
-        Layer { neurons }
+        Layer {
+            neurons,
+            dropout: T::zero(),
+            recurrence: None,
+        }
+    }
+
+    /// Wraps existing neurons in a layer without dropout or recurrence
+    pub fn from_neurons(neurons: Vec<Neuron<T>>) -> Self {
+        Layer {
+            neurons,
+            dropout: T::zero(),
+            recurrence: None,
+        }
+    }
+
+    /// Sets the dropout rate used during training, clamped to `[0, 0.95]`
+    pub fn with_dropout(mut self, dropout: T) -> Self {
+        self.dropout = dropout.max(T::zero()).min(cast(0.95));
+        self
     }
 
     /// Creates a new layer with a bias neuron
@@ -60,7 +177,11 @@ impl<T: Float> Layer<T> {
         // Add bias neuron
         neurons.push(Neuron::new_bias());
 
-        Layer { neurons }
+        Layer {
+            neurons,
+            dropout: T::zero(),
+            recurrence: None,
+        }
     }
 
     /// Returns the number of neurons in the layer (including bias if present)
@@ -100,6 +221,20 @@ impl<T: Float> Layer<T> {
         }
     }
 
+    /// Neuron at `index`, bias included
+    pub fn neuron(&self, index: usize) -> Option<&Neuron<T>> {
+        self.neurons.get(index)
+    }
+
+    pub fn neuron_mut(&mut self, index: usize) -> Option<&mut Neuron<T>> {
+        self.neurons.get_mut(index)
+    }
+
+    /// Weighted input sums of all neurons from the most recent forward pass
+    pub fn get_sums(&self) -> Vec<T> {
+        self.neurons.iter().map(Neuron::input_sum).collect()
+    }
+
     /// Resets all neurons in the layer
     pub fn reset(&mut self) {
         for neuron in &mut self.neurons {
@@ -127,29 +262,56 @@ impl<T: Float> Layer<T> {
         }
     }
 
-    /// Connects all neurons in this layer to all neurons in the next layer
-    /// with random weights
+    /// Connects this layer to the regular neurons of the next layer with
+    /// random weights
+    ///
+    /// Below a `connection_rate` of 1 each connection is kept with that
+    /// probability, as in FANN's `fann_create_sparse`: the bias still feeds
+    /// every neuron, and every neuron keeps at least one input and one
+    /// output. Connections are added in order of their source neuron.
     pub fn connect_to(&self, next_layer: &mut Layer<T>, connection_rate: T) {
-        let one = T::one();
-        let should_connect = connection_rate >= one;
-        let mut rng = rand::thread_rng();
-
-        // For each neuron in the next layer (except bias)
-        let next_layer_size = next_layer.num_regular_neurons();
-        for i in 0..next_layer_size {
-            let next_neuron = &mut next_layer.neurons[i];
-
-            // Connect from each neuron in this layer
-            for (j, _) in self.neurons.iter().enumerate() {
-                let random_val = T::from(rng.gen::<f64>()).unwrap();
-                if should_connect || random_val < connection_rate {
-                    // Random weight between -0.1 and 0.1
-                    let weight_val: f64 = rng.gen::<f64>() * 0.2 - 0.1;
-                    let weight = T::from(weight_val).unwrap();
-                    next_neuron.add_connection(j, weight);
+        self.connect_to_with_rng(next_layer, connection_rate, &mut rand::thread_rng());
+    }
+
+    /// [`Layer::connect_to`] drawing connections and weights from `rng`
+    pub fn connect_to_with_rng<R: Rng + ?Sized>(
+        &self,
+        next_layer: &mut Layer<T>,
+        connection_rate: T,
+        rng: &mut R,
+    ) {
+        let sources = self.num_regular_neurons();
+        let targets = next_layer.num_regular_neurons();
+        let rate = connection_rate.to_f64().unwrap_or(1.0);
+
+        let mut connected: Vec<Vec<bool>> = (0..targets)
+            .map(|_| {
+                (0..sources)
+                    .map(|_| rate >= 1.0 || rng.gen::<f64>() < rate)
+                    .collect()
+            })
+            .collect();
+        if sources > 0 && targets > 0 {
+            for row in &mut connected {
+                if !row.contains(&true) {
+                    row[rng.gen_range(0..sources)] = true;
+                }
+            }
+            for source in 0..sources {
+                if !connected.iter().any(|row| row[source]) {
+                    connected[rng.gen_range(0..targets)][source] = true;
                 }
             }
         }
+
+        for (next_neuron, row) in next_layer.neurons.iter_mut().zip(connected) {
+            let inputs = row.iter().enumerate().filter(|&(_, &c)| c).map(|(j, _)| j);
+            for j in inputs.chain(self.has_bias().then_some(sources)) {
+                // Random weight between -0.1 and 0.1
+                let weight_val: f64 = rng.gen::<f64>() * 0.2 - 0.1;
+                next_neuron.add_connection(j, cast(weight_val));
+            }
+        }
     }
 
     /// Gets the output values of all neurons in the layer
@@ -172,10 +334,13 @@ impl<T: Float> Layer<T> {
     }
 
     /// Calculates outputs for all neurons in the layer based on previous layer outputs
+    /// and, for recurrent layers, the context from the previous time step
     pub fn calculate(&mut self, prev_outputs: &[T]) {
-        for neuron in &mut self.neurons {
+        let recurrence = &self.recurrence;
+        for (i, neuron) in self.neurons.iter_mut().enumerate() {
             if !neuron.is_bias {
-                neuron.calculate(prev_outputs);
+                let feedback = recurrence.as_ref().map_or(T::zero(), |r| r.feedback(i));
+                neuron.calculate_with_feedback(prev_outputs, feedback);
             }
         }
     }
@@ -216,6 +381,16 @@ mod tests {
         assert_eq!(bias.value, 1.0);
     }
 
+    #[test]
+    fn test_from_neurons_with_dropout() {
+        let neurons = Layer::<f32>::with_bias(2, ActivationFunction::ReLU, 1.0).neurons;
+        let layer = Layer::from_neurons(neurons).with_dropout(0.5);
+        assert_eq!(layer.num_regular_neurons(), 2);
+        assert_eq!(layer.dropout, 0.5);
+        assert!(layer.recurrence.is_none());
+        assert_eq!(layer.with_dropout(2.0).dropout, 0.95);
+    }
+
     #[test]
     fn test_set_activation_function() {
         let mut layer = Layer::<f32>::with_bias(2, ActivationFunction::Sigmoid, 1.0);
@@ -258,6 +433,28 @@ mod tests {
         assert!(layer.set_inputs(&inputs).is_err());
     }
 
+    #[test]
+    fn test_sparse_connect_to() {
+        let source = Layer::<f32>::with_bias(8, ActivationFunction::Linear, 1.0);
+        let mut next = Layer::<f32>::with_bias(6, ActivationFunction::Sigmoid, 1.0);
+        source.connect_to(&mut next, 0.05);
+
+        let regular = &next.neurons[..6];
+        for neuron in regular {
+            let from: Vec<usize> = neuron.connections.iter().map(|c| c.from_neuron).collect();
+            // At least one input, bias last, sources in order
+            assert!(from.len() >= 2);
+            assert_eq!(from.last(), Some(&8));
+            assert!(from.windows(2).all(|w| w[0] < w[1]));
+        }
+        for j in 0..8 {
+            assert!(regular
+                .iter()
+                .any(|n| n.connections.iter().any(|c| c.from_neuron == j)));
+        }
+        assert!(next.neurons[6].connections.is_empty());
+    }
+
     #[test]
     fn test_get_outputs() {
         let mut layer = Layer::<f32>::with_bias(2, ActivationFunction::Linear, 1.0);
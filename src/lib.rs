@@ -3,18 +3,38 @@
 //! This crate provides a modern, safe, and efficient implementation of neural networks
 //! inspired by the original FANN library, with support for generic floating-point types.
 //! Includes full cascade correlation support for dynamic network topology optimization.
+//!
+//! With the `safe-only` feature the crate is compiled under
+//! `forbid(unsafe_code)`: SIMD kernels, huge-page arenas, prefetching and
+//! thread pinning fall back to their safe implementations.
+//!
+//! # API stability
+//!
+//! The [`prelude`] and the modules it draws from ([`network`], [`training`],
+//! [`activation`], [`errors`] and `io`) form the stable API and follow
+//! semver. The low-level tiers, `memory_manager` (allocators, arenas, NUMA
+//! placement, prefetching) and `simd` (CPU kernels), change as the
+//! implementation does; they are only public with the `experimental`
+//! feature, which is exempt from semver. The stable types they provide,
+//! such as [`training::NumaPolicy`] and [`network::MemoryPrefetcher`], are
+//! re-exported where the stable API uses them.
+
+#![cfg_attr(feature = "safe-only", forbid(unsafe_code))]
+#![cfg_attr(not(test), deny(clippy::unwrap_used, clippy::expect_used))]
 
 // Re-export main types
 pub use activation::ActivationFunction;
 pub use connection::Connection;
-pub use layer::Layer;
-pub use network::{Network, NetworkBuilder, NetworkError};
+pub use layer::{Layer, Recurrence, RecurrenceKind};
+pub use network::{
+    InputRange, Network, NetworkBuilder, NetworkDiff, NetworkError, NetworkSchema, TopologyIssue,
+};
 pub use neuron::Neuron;
 
 // Re-export training types
 pub use training::{
-    ParallelTrainingOptions, TrainingAlgorithm as TrainingAlgorithmTrait, TrainingData,
-    TrainingError, TrainingState,
+    CancellationToken, ParallelTrainingOptions, TrainingAlgorithm as TrainingAlgorithmTrait,
+    TrainingData, TrainingError, TrainingState,
 };
 
 /// Enumeration of available training algorithms
@@ -29,33 +49,74 @@ pub enum TrainingAlgorithm {
 }
 
 // Re-export cascade training types
-pub use cascade::{CascadeConfig, CascadeError, CascadeNetwork, CascadeTrainer};
+pub use cascade::{
+    CascadeCheckpoint, CascadeConfig, CascadeError, CascadeNetwork, CascadeStopReason,
+    CascadeTrainer, PruningCriterion,
+};
 
 // Re-export comprehensive error handling
 pub use errors::{ErrorCategory, RuvFannError, ValidationError};
+pub use execution::{ExecutionBackend, ExecutionConfig, ExecutionError, Executor, Precision};
 
 // Modules
 pub mod activation;
+pub mod active_learning;
+pub mod architecture_search;
 pub mod cascade;
 pub mod connection;
+pub mod embedding;
+pub mod ensemble;
 pub mod errors;
+pub mod evolution;
+pub mod graph;
 pub mod integration;
 pub mod layer;
-pub mod memory_manager;
 pub mod network;
 pub mod neuron;
+pub mod online;
+pub mod polynomial;
+pub mod prelude;
+pub mod preprocessing;
+pub mod quantization;
 pub mod training;
+pub mod tuning;
+
+// Memory pools, weight arenas and placement; public only as an
+// experimental API
+#[cfg(feature = "experimental")]
+pub mod memory_manager;
+#[cfg(not(feature = "experimental"))]
+#[allow(dead_code)]
+pub(crate) mod memory_manager;
 
 // Optional I/O module
 #[cfg(feature = "io")]
 pub mod io;
 
+// Reproducible experiment bundles
+#[cfg(feature = "io")]
+pub mod experiment;
+
+// WAV decoding and MFCC/spectrogram features
+#[cfg(feature = "audio")]
+pub mod audio;
+
+// Inference execution settings
+pub mod execution;
+
 // WebGPU acceleration module
 pub mod webgpu;
 
-// SIMD acceleration module (CPU optimizations)
-#[cfg(feature = "parallel")]
+// SIMD acceleration module (CPU optimizations); public only as an
+// experimental API
+#[cfg(all(feature = "parallel", feature = "experimental"))]
 pub mod simd;
+#[cfg(all(feature = "parallel", not(feature = "experimental")))]
+#[allow(dead_code)]
+pub(crate) mod simd;
+
+// Panic-free numeric conversions
+mod numeric;
 
 // Test module
 #[cfg(test)]
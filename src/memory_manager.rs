@@ -5,7 +5,14 @@
 
 use num_traits::Float;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+mod huge_pages;
+mod numa;
+
+pub use huge_pages::{ArenaBacking, HugePagePolicy, WeightArena, HUGE_PAGE_SIZE};
+pub use numa::{NumaNode, NumaPolicy, NumaTopology};
 
 /// Memory manager for neural network operations
 pub struct MemoryManager<T: Float> {
@@ -15,6 +22,10 @@ pub struct MemoryManager<T: Float> {
     total_allocated: usize,
     /// Memory usage statistics
     stats: MemoryStats,
+    /// Device-side allocation tracker shared with the GPU backends
+    device_tracker: Arc<DeviceMemoryTracker>,
+    /// When weight arenas use huge pages
+    huge_pages: HugePagePolicy,
 }
 
 /// Memory pool for efficient allocation/deallocation
@@ -40,6 +51,128 @@ pub struct MemoryStats {
     pub buffer_count: usize,
     /// Memory fragmentation ratio (0.0 = no fragmentation, 1.0 = highly fragmented)
     pub fragmentation_ratio: f64,
+    /// Device memory usage, present once a GPU backend has allocated or transferred data
+    pub device: Option<DeviceMemoryStats>,
+}
+
+impl MemoryStats {
+    /// Combined host and device bytes currently allocated
+    pub fn total_bytes(&self) -> usize {
+        self.total_allocated + self.device.as_ref().map_or(0, |d| d.allocated_bytes)
+    }
+}
+
+/// Device (GPU) memory usage statistics
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceMemoryStats {
+    /// Number of live device buffers
+    pub buffer_count: usize,
+    /// Bytes currently allocated on the device
+    pub allocated_bytes: usize,
+    /// Highest number of bytes allocated on the device at any point
+    pub peak_allocated_bytes: usize,
+    /// Total number of device buffer allocations
+    pub total_allocations: usize,
+    /// Bytes transferred from host to device
+    pub host_to_device_bytes: usize,
+    /// Bytes transferred from device to host
+    pub device_to_host_bytes: usize,
+}
+
+impl DeviceMemoryStats {
+    /// Total bytes moved across the host/device boundary
+    pub fn transfer_bytes(&self) -> usize {
+        self.host_to_device_bytes + self.device_to_host_bytes
+    }
+}
+
+/// Lock-free tracker for device buffer allocations and transfers
+///
+/// GPU backends report into the global tracker (see [`device_memory_tracker`]);
+/// [`MemoryManager::get_stats`] surfaces its snapshot alongside host statistics.
+#[derive(Debug, Default)]
+pub struct DeviceMemoryTracker {
+    buffer_count: AtomicUsize,
+    allocated_bytes: AtomicUsize,
+    peak_allocated_bytes: AtomicUsize,
+    total_allocations: AtomicUsize,
+    host_to_device_bytes: AtomicUsize,
+    device_to_host_bytes: AtomicUsize,
+}
+
+impl DeviceMemoryTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a device buffer allocation of `bytes`
+    pub fn record_allocation(&self, bytes: usize) {
+        self.buffer_count.fetch_add(1, Ordering::Relaxed);
+        self.total_allocations.fetch_add(1, Ordering::Relaxed);
+        let current = self.allocated_bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak_allocated_bytes
+            .fetch_max(current, Ordering::Relaxed);
+    }
+
+    /// Record that a device buffer of `bytes` was released
+    pub fn record_deallocation(&self, bytes: usize) {
+        let _ = self
+            .buffer_count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |c| {
+                Some(c.saturating_sub(1))
+            });
+        let _ = self
+            .allocated_bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |b| {
+                Some(b.saturating_sub(bytes))
+            });
+    }
+
+    /// Record a host-to-device upload
+    pub fn record_upload(&self, bytes: usize) {
+        self.host_to_device_bytes
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Record a device-to-host download
+    pub fn record_download(&self, bytes: usize) {
+        self.device_to_host_bytes
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Whether any device activity has been recorded
+    pub fn is_active(&self) -> bool {
+        self.total_allocations.load(Ordering::Relaxed) > 0
+            || self.host_to_device_bytes.load(Ordering::Relaxed) > 0
+            || self.device_to_host_bytes.load(Ordering::Relaxed) > 0
+    }
+
+    /// Take a snapshot of the current counters
+    pub fn snapshot(&self) -> DeviceMemoryStats {
+        DeviceMemoryStats {
+            buffer_count: self.buffer_count.load(Ordering::Relaxed),
+            allocated_bytes: self.allocated_bytes.load(Ordering::Relaxed),
+            peak_allocated_bytes: self.peak_allocated_bytes.load(Ordering::Relaxed),
+            total_allocations: self.total_allocations.load(Ordering::Relaxed),
+            host_to_device_bytes: self.host_to_device_bytes.load(Ordering::Relaxed),
+            device_to_host_bytes: self.device_to_host_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Reset all counters, including the peak
+    pub fn reset(&self) {
+        for counter in [
+            &self.buffer_count,
+            &self.allocated_bytes,
+            &self.peak_allocated_bytes,
+            &self.total_allocations,
+            &self.host_to_device_bytes,
+            &self.device_to_host_bytes,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
 }
 
 impl<T: Float> MemoryManager<T> {
@@ -53,10 +186,55 @@ impl<T: Float> MemoryManager<T> {
                 available: 0,
                 buffer_count: 0,
                 fragmentation_ratio: 0.0,
+                device: None,
             },
+            device_tracker: GLOBAL_DEVICE_TRACKER.clone(),
+            huge_pages: HugePagePolicy::default(),
         }
     }
 
+    /// Create a memory manager reporting device usage from the given tracker
+    pub fn with_device_tracker(device_tracker: Arc<DeviceMemoryTracker>) -> Self {
+        Self {
+            device_tracker,
+            ..Self::new()
+        }
+    }
+
+    /// Use `policy` for weight arenas allocated by this manager
+    pub fn with_huge_page_policy(mut self, policy: HugePagePolicy) -> Self {
+        self.huge_pages = policy;
+        self
+    }
+
+    /// Turn huge-page backed weight arenas on or off
+    pub fn set_huge_pages(&mut self, enabled: bool) {
+        self.huge_pages.enabled = enabled;
+    }
+
+    pub fn huge_page_policy(&self) -> HugePagePolicy {
+        self.huge_pages
+    }
+
+    /// Zeroed arena of `len` weights, on huge pages when the policy applies
+    /// and the platform supports it
+    pub fn allocate_arena(&self, len: usize) -> WeightArena<T> {
+        WeightArena::zeroed(len, &self.huge_pages)
+    }
+
+    /// Like [`Self::allocate_arena`], with the pages placed on NUMA `node`
+    pub fn allocate_arena_on_node(
+        &self,
+        len: usize,
+        topology: &NumaTopology,
+        node: usize,
+    ) -> WeightArena<T>
+    where
+        T: Send,
+    {
+        topology.allocate_arena(node, len, &self.huge_pages)
+    }
+
     /// Create a memory pool with the given name and buffer size
     pub fn create_pool(&mut self, name: &str, buffer_size: usize) {
         let pool = MemoryPool::new(name.to_string(), buffer_size);
@@ -90,7 +268,11 @@ impl<T: Float> MemoryManager<T> {
 
     /// Get memory usage statistics
     pub fn get_stats(&self) -> MemoryStats {
-        self.stats.clone()
+        let mut stats = self.stats.clone();
+        if self.device_tracker.is_active() {
+            stats.device = Some(self.device_tracker.snapshot());
+        }
+        stats
     }
 
     /// Clear all memory pools
@@ -121,6 +303,7 @@ impl<T: Float> MemoryManager<T> {
             } else {
                 0.0
             },
+            device: None,
         };
     }
 }
@@ -182,9 +365,133 @@ impl<T: Float> MemoryPool<T> {
     }
 }
 
+/// Cache level a prefetch should fill
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrefetchHint {
+    /// All cache levels (`T0`)
+    L1,
+    /// L2 and below (`T1`); leaves L1 to the data being computed on
+    #[default]
+    L2,
+    /// Last-level cache only (`T2`)
+    L3,
+}
+
+/// Software prefetcher for weight buffers
+///
+/// Inference walks the layers in a fixed order, so the next layer's weights
+/// are known while the current one is computed. [`Self::prefetch`] issues
+/// one prefetch per cache line on x86_64; elsewhere it warms the cache by
+/// reading one byte per line. Prefetches are hints and never fault.
+///
+/// Whether it pays off depends on the CPU: hardware prefetchers already
+/// follow sequential weight streams well, so measure with the `inference`
+/// bench (`inference/wide`) before enabling it.
+#[derive(Debug)]
+pub struct MemoryPrefetcher {
+    hint: PrefetchHint,
+    lookahead: usize,
+    lines_issued: AtomicUsize,
+}
+
+impl Default for MemoryPrefetcher {
+    fn default() -> Self {
+        Self::new(PrefetchHint::default())
+    }
+}
+
+impl MemoryPrefetcher {
+    /// Bytes covered by one prefetch
+    pub const CACHE_LINE: usize = 64;
+
+    /// Hardware prefetchers take over once a sequential stream is running,
+    /// so only the head of the next buffer needs a software hint
+    pub const DEFAULT_LOOKAHEAD: usize = 16 * 1024;
+
+    pub fn new(hint: PrefetchHint) -> Self {
+        Self {
+            hint,
+            lookahead: Self::DEFAULT_LOOKAHEAD,
+            lines_issued: AtomicUsize::new(0),
+        }
+    }
+
+    /// Prefetch at most `bytes` from the start of each upcoming buffer
+    pub fn with_lookahead(mut self, bytes: usize) -> Self {
+        self.lookahead = bytes;
+        self
+    }
+
+    pub fn hint(&self) -> PrefetchHint {
+        self.hint
+    }
+
+    pub fn lookahead(&self) -> usize {
+        self.lookahead
+    }
+
+    /// Prefetch every cache line of `data`, returning the number of lines
+    pub fn prefetch<T>(&self, data: &[T]) -> usize {
+        let bytes = std::mem::size_of_val(data);
+        let start = data.as_ptr() as *const u8;
+        let lines = bytes.div_ceil(Self::CACHE_LINE);
+        for line in 0..lines {
+            // In bounds, as every line starts inside `data`
+            self.prefetch_line(start.wrapping_add(line * Self::CACHE_LINE));
+        }
+        self.lines_issued.fetch_add(lines, Ordering::Relaxed);
+        lines
+    }
+
+    #[cfg(all(target_arch = "x86_64", not(any(feature = "safe-only", miri))))]
+    fn prefetch_line(&self, address: *const u8) {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0, _MM_HINT_T1, _MM_HINT_T2};
+        let address = address as *const i8;
+        // SAFETY: SSE is part of the x86_64 baseline and prefetching does
+        // not access memory architecturally
+        unsafe {
+            match self.hint {
+                PrefetchHint::L1 => _mm_prefetch::<_MM_HINT_T0>(address),
+                PrefetchHint::L2 => _mm_prefetch::<_MM_HINT_T1>(address),
+                PrefetchHint::L3 => _mm_prefetch::<_MM_HINT_T2>(address),
+            }
+        }
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", feature = "safe-only")))]
+    fn prefetch_line(&self, address: *const u8) {
+        // SAFETY: callers pass addresses inside a live slice; the byte may
+        // be padding of `T`, so it is read as possibly uninitialized
+        std::hint::black_box(unsafe {
+            std::ptr::read_volatile(address as *const std::mem::MaybeUninit<u8>)
+        });
+    }
+
+    /// Safe-only builds, and Miri, which does not model prefetching
+    #[cfg(any(feature = "safe-only", all(target_arch = "x86_64", miri)))]
+    fn prefetch_line(&self, _address: *const u8) {}
+
+    /// Cache lines prefetched since creation or the last reset
+    pub fn lines_issued(&self) -> usize {
+        self.lines_issued.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        self.lines_issued.store(0, Ordering::Relaxed);
+    }
+}
+
 lazy_static::lazy_static! {
     /// Global memory manager instance
     static ref GLOBAL_MEMORY_MANAGER: Arc<Mutex<MemoryManager<f32>>> = Arc::new(Mutex::new(MemoryManager::new()));
+
+    /// Global device memory tracker fed by the GPU backends
+    static ref GLOBAL_DEVICE_TRACKER: Arc<DeviceMemoryTracker> = Arc::new(DeviceMemoryTracker::new());
+}
+
+/// Get the global device memory tracker
+pub fn device_memory_tracker() -> Arc<DeviceMemoryTracker> {
+    GLOBAL_DEVICE_TRACKER.clone()
 }
 
 /// Get the global memory manager
@@ -194,7 +501,9 @@ pub fn get_global_memory_manager() -> Arc<Mutex<MemoryManager<f32>>> {
 
 /// Initialize default memory pools
 pub fn init_default_pools() {
-    let mut manager = GLOBAL_MEMORY_MANAGER.lock().unwrap();
+    let mut manager = GLOBAL_MEMORY_MANAGER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
     manager.create_pool("weights", 1024);
     manager.create_pool("activations", 512);
     manager.create_pool("gradients", 512);
@@ -250,6 +559,57 @@ mod tests {
         assert!(stats.total_allocated > 0);
     }
 
+    #[test]
+    fn test_device_stats_reported() {
+        let tracker = Arc::new(DeviceMemoryTracker::new());
+        let manager: MemoryManager<f32> = MemoryManager::with_device_tracker(tracker.clone());
+        assert!(manager.get_stats().device.is_none());
+
+        tracker.record_allocation(1024);
+        tracker.record_allocation(2048);
+        tracker.record_upload(1024);
+        tracker.record_deallocation(2048);
+        tracker.record_download(256);
+
+        let device = manager.get_stats().device.unwrap();
+        assert_eq!(device.buffer_count, 1);
+        assert_eq!(device.allocated_bytes, 1024);
+        assert_eq!(device.peak_allocated_bytes, 3072);
+        assert_eq!(device.total_allocations, 2);
+        assert_eq!(device.transfer_bytes(), 1280);
+
+        tracker.reset();
+        assert!(manager.get_stats().device.is_none());
+    }
+
+    #[test]
+    fn test_manager_arena_toggle() {
+        let mut manager: MemoryManager<f32> =
+            MemoryManager::new().with_huge_page_policy(HugePagePolicy {
+                enabled: true,
+                threshold_bytes: 4096,
+            });
+        manager.set_huge_pages(false);
+        assert!(!manager.huge_page_policy().enabled);
+        let arena = manager.allocate_arena(HUGE_PAGE_SIZE);
+        assert_eq!(arena.len(), HUGE_PAGE_SIZE);
+        assert_eq!(arena.backing(), ArenaBacking::Heap);
+    }
+
+    #[test]
+    fn test_prefetcher_counts_cache_lines() {
+        let prefetcher = MemoryPrefetcher::new(PrefetchHint::L1);
+        let weights = vec![0.5f32; 100];
+        // 400 bytes span 7 lines of 64 bytes
+        assert_eq!(prefetcher.prefetch(&weights), 7);
+        assert_eq!(prefetcher.prefetch::<f32>(&[]), 0);
+        assert_eq!(prefetcher.lines_issued(), 7);
+        prefetcher.reset();
+        assert_eq!(prefetcher.lines_issued(), 0);
+        assert_eq!(MemoryPrefetcher::default().hint(), PrefetchHint::L2);
+        assert_eq!(prefetcher.with_lookahead(4096).lookahead(), 4096);
+    }
+
     #[test]
     fn test_pool_reuse() {
         let mut pool: MemoryPool<f32> = MemoryPool::new("test".to_string(), 100);
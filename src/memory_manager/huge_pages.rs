@@ -0,0 +1,285 @@
+//! Huge-page backed weight arenas
+//!
+//! Large weight matrices span thousands of 4 KiB pages, and streaming them
+//! once per forward pass misses the TLB on most of them. A [`WeightArena`]
+//! above the [`HugePagePolicy`] threshold is mapped directly with `mmap` and
+//! advised with `madvise(MADV_HUGEPAGE)`, so Linux backs it with 2 MiB
+//! transparent huge pages where available.
+//!
+//! Everything degrades gracefully: without the `huge-pages` feature, with
+//! `safe-only`, under Miri, on non-Unix targets, when the policy is
+//! disabled, below the threshold or when `mmap` fails, the arena is an
+//! ordinary heap allocation. The [`ArenaBacking`] of an arena reports what it
+//! actually got.
+//!
+//! All raw-pointer handling lives in the private `Mapping` type; when mapping
+//! is compiled out it is replaced by an uninhabited stand-in, so the arena
+//! itself contains no unsafe code.
+
+use num_traits::Float;
+use std::ops::{Deref, DerefMut};
+
+/// Transparent huge page size on x86_64 and most aarch64 kernels
+pub const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// When weight arenas should use huge pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HugePagePolicy {
+    pub enabled: bool,
+    /// Arenas smaller than this many bytes always use the heap
+    pub threshold_bytes: usize,
+}
+
+impl Default for HugePagePolicy {
+    /// Enabled with the `huge-pages` feature, for arenas of at least one
+    /// huge page
+    fn default() -> Self {
+        Self {
+            enabled: cfg!(feature = "huge-pages"),
+            threshold_bytes: HUGE_PAGE_SIZE,
+        }
+    }
+}
+
+impl HugePagePolicy {
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::default()
+        }
+    }
+
+    fn applies_to(&self, bytes: usize) -> bool {
+        self.enabled && bytes >= self.threshold_bytes
+    }
+}
+
+/// Memory behind a [`WeightArena`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArenaBacking {
+    /// Regular heap allocation
+    Heap,
+    /// Anonymous mapping; `huge_pages` is whether the kernel accepted the
+    /// huge page advice
+    Mapped { huge_pages: bool },
+}
+
+enum Storage<T> {
+    Heap(Vec<T>),
+    Mapped(Mapping<T>),
+}
+
+/// Fixed-size, zero-initialized buffer for layer weights
+pub struct WeightArena<T> {
+    storage: Storage<T>,
+}
+
+impl<T: Float> WeightArena<T> {
+    /// Arena of `len` zeros, on huge pages if `policy` applies
+    pub fn zeroed(len: usize, policy: &HugePagePolicy) -> Self {
+        let bytes = len * std::mem::size_of::<T>();
+        if policy.applies_to(bytes) {
+            if let Some(mapping) = Mapping::new(len, bytes) {
+                return Self {
+                    storage: Storage::Mapped(mapping),
+                };
+            }
+        }
+        Self {
+            storage: Storage::Heap(vec![T::zero(); len]),
+        }
+    }
+
+    /// Arena holding a copy of `values`
+    pub fn from_slice(values: &[T], policy: &HugePagePolicy) -> Self {
+        let mut arena = Self::zeroed(values.len(), policy);
+        arena.copy_from_slice(values);
+        arena
+    }
+}
+
+impl<T> WeightArena<T> {
+    pub fn backing(&self) -> ArenaBacking {
+        match &self.storage {
+            Storage::Heap(_) => ArenaBacking::Heap,
+            Storage::Mapped(mapping) => ArenaBacking::Mapped {
+                huge_pages: mapping.huge_pages(),
+            },
+        }
+    }
+}
+
+impl<T> Deref for WeightArena<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match &self.storage {
+            Storage::Heap(values) => values,
+            Storage::Mapped(mapping) => mapping.as_slice(),
+        }
+    }
+}
+
+impl<T> DerefMut for WeightArena<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        match &mut self.storage {
+            Storage::Heap(values) => values,
+            Storage::Mapped(mapping) => mapping.as_mut_slice(),
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for WeightArena<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeightArena")
+            .field("len", &self.len())
+            .field("backing", &self.backing())
+            .finish()
+    }
+}
+
+/// Anonymous memory mapping holding `len` initialized values
+#[cfg(all(unix, feature = "huge-pages", not(any(feature = "safe-only", miri))))]
+struct Mapping<T> {
+    pointer: std::ptr::NonNull<T>,
+    len: usize,
+    bytes: usize,
+    huge_pages: bool,
+}
+
+// SAFETY: the mapping is owned exclusively, like the buffer of a `Vec<T>`
+#[cfg(all(unix, feature = "huge-pages", not(any(feature = "safe-only", miri))))]
+unsafe impl<T: Send> Send for Mapping<T> {}
+// SAFETY: shared access only hands out `&[T]`
+#[cfg(all(unix, feature = "huge-pages", not(any(feature = "safe-only", miri))))]
+unsafe impl<T: Sync> Sync for Mapping<T> {}
+
+#[cfg(all(unix, feature = "huge-pages", not(any(feature = "safe-only", miri))))]
+impl<T> Mapping<T> {
+    /// Map `bytes` rounded up to whole huge pages and fill them with `len`
+    /// zeros; `None` if the kernel refuses the mapping
+    fn new(len: usize, bytes: usize) -> Option<Self>
+    where
+        T: Float,
+    {
+        let bytes = bytes.div_ceil(HUGE_PAGE_SIZE) * HUGE_PAGE_SIZE;
+        // SAFETY: anonymous private mapping with no address hint; the
+        // result is checked before use
+        let address = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                bytes,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if address == libc::MAP_FAILED {
+            return None;
+        }
+        #[cfg(target_os = "linux")]
+        // SAFETY: `address..address + bytes` is the mapping created above
+        let huge_pages = unsafe { libc::madvise(address, bytes, libc::MADV_HUGEPAGE) } == 0;
+        #[cfg(not(target_os = "linux"))]
+        let huge_pages = false;
+
+        let pointer = std::ptr::NonNull::new(address as *mut T)?;
+        // Page-aligned memory satisfies any float alignment. Write zeros
+        // explicitly rather than relying on the bit pattern of `T::zero()`;
+        // this also faults the pages in after the advice.
+        for i in 0..len {
+            // SAFETY: `i < len` and `len * size_of::<T>() <= bytes`
+            unsafe { pointer.as_ptr().add(i).write(T::zero()) };
+        }
+        Some(Self {
+            pointer,
+            len,
+            bytes,
+            huge_pages,
+        })
+    }
+
+    fn huge_pages(&self) -> bool {
+        self.huge_pages
+    }
+
+    fn as_slice(&self) -> &[T] {
+        // SAFETY: the mapping holds `len` initialized values
+        unsafe { std::slice::from_raw_parts(self.pointer.as_ptr(), self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: the mapping holds `len` initialized values and is
+        // borrowed mutably through `self`
+        unsafe { std::slice::from_raw_parts_mut(self.pointer.as_ptr(), self.len) }
+    }
+}
+
+#[cfg(all(unix, feature = "huge-pages", not(any(feature = "safe-only", miri))))]
+impl<T> Drop for Mapping<T> {
+    fn drop(&mut self) {
+        // Floats need no drop; just release the mapping
+        // SAFETY: unmaps exactly the region mapped in `new`
+        unsafe { libc::munmap(self.pointer.as_ptr() as *mut libc::c_void, self.bytes) };
+    }
+}
+
+/// Stand-in when mapping is compiled out; it cannot be constructed, so
+/// `Storage::Mapped` never exists
+#[cfg(not(all(unix, feature = "huge-pages", not(any(feature = "safe-only", miri)))))]
+struct Mapping<T>(std::convert::Infallible, std::marker::PhantomData<T>);
+
+#[cfg(not(all(unix, feature = "huge-pages", not(any(feature = "safe-only", miri)))))]
+impl<T> Mapping<T> {
+    fn new(_len: usize, _bytes: usize) -> Option<Self> {
+        None
+    }
+
+    fn huge_pages(&self) -> bool {
+        match self.0 {}
+    }
+
+    fn as_slice(&self) -> &[T] {
+        match self.0 {}
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        match self.0 {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arena_backing_follows_policy() {
+        let small = WeightArena::<f32>::zeroed(16, &HugePagePolicy::default());
+        assert_eq!(small.backing(), ArenaBacking::Heap);
+        assert!(small.iter().all(|&v| v == 0.0));
+
+        let len = HUGE_PAGE_SIZE / 4 + 3;
+        let disabled = WeightArena::<f32>::zeroed(len, &HugePagePolicy::disabled());
+        assert_eq!(disabled.backing(), ArenaBacking::Heap);
+
+        let policy = HugePagePolicy {
+            enabled: true,
+            threshold_bytes: 1024,
+        };
+        let values: Vec<f64> = (0..len).map(|i| i as f64).collect();
+        let mut arena = WeightArena::from_slice(&values, &policy);
+        assert_eq!(&arena[..], &values[..]);
+        arena[len - 1] = -1.0;
+        assert_eq!(arena.last(), Some(&-1.0));
+        if cfg!(all(
+            unix,
+            feature = "huge-pages",
+            not(any(feature = "safe-only", miri))
+        )) {
+            assert!(matches!(arena.backing(), ArenaBacking::Mapped { .. }));
+        } else {
+            assert_eq!(arena.backing(), ArenaBacking::Heap);
+        }
+    }
+}
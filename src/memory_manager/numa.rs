@@ -0,0 +1,258 @@
+//! NUMA topology detection and node-local placement
+//!
+//! On multi-socket servers memory is attached to one socket (node) and every
+//! access from another node crosses the interconnect. [`NumaTopology`] reads
+//! the node layout from `/sys/devices/system/node` on Linux; everywhere else,
+//! and when the layout cannot be read, it reports a single node holding all
+//! CPUs so callers never need a separate code path.
+//!
+//! Placement relies on the kernel's first-touch policy: a page lands on the
+//! node of the thread that first writes it. [`NumaTopology::allocate_arena`]
+//! therefore allocates and zero-fills an arena on a thread pinned to the
+//! target node, and [`NumaTopology::bind_current_thread`] pins rayon workers
+//! so the samples they process stay near them. Pinning needs the `numa`
+//! feature on Linux and is a no-op otherwise.
+
+use super::{HugePagePolicy, WeightArena};
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How parallel training places worker threads across NUMA nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NumaPolicy {
+    /// Leave placement to the operating system
+    #[default]
+    Disabled,
+    /// Split workers into contiguous blocks, one block per node, and pin
+    /// each worker to the CPUs of its node
+    Spread,
+    /// Pin every worker to the CPUs of one node
+    Node(usize),
+}
+
+/// One NUMA node and the CPUs attached to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumaNode {
+    pub id: usize,
+    pub cpus: Vec<usize>,
+}
+
+/// NUMA nodes of the machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumaTopology {
+    nodes: Vec<NumaNode>,
+}
+
+impl NumaTopology {
+    /// Read the node layout of this machine
+    pub fn detect() -> Self {
+        #[cfg(target_os = "linux")]
+        if let Some(topology) = Self::from_sysfs("/sys/devices/system/node") {
+            return topology;
+        }
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::single_node(cpus)
+    }
+
+    /// One node holding CPUs `0..cpus`
+    pub fn single_node(cpus: usize) -> Self {
+        Self::from_nodes(vec![NumaNode {
+            id: 0,
+            cpus: (0..cpus.max(1)).collect(),
+        }])
+    }
+
+    /// Topology from an explicit node list, sorted by node id
+    pub fn from_nodes(mut nodes: Vec<NumaNode>) -> Self {
+        nodes.retain(|node| !node.cpus.is_empty());
+        nodes.sort_by_key(|node| node.id);
+        if nodes.is_empty() {
+            return Self::single_node(1);
+        }
+        Self { nodes }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn from_sysfs(root: &str) -> Option<Self> {
+        let mut nodes = Vec::new();
+        for entry in std::fs::read_dir(root).ok()?.flatten() {
+            let name = entry.file_name();
+            let Some(id) = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("node"))
+                .and_then(|id| id.parse().ok())
+            else {
+                continue;
+            };
+            let list = std::fs::read_to_string(entry.path().join("cpulist")).ok()?;
+            nodes.push(NumaNode {
+                id,
+                cpus: parse_cpu_list(&list)?,
+            });
+        }
+        if nodes.iter().all(|node| node.cpus.is_empty()) {
+            return None;
+        }
+        Some(Self::from_nodes(nodes))
+    }
+
+    pub fn nodes(&self) -> &[NumaNode] {
+        &self.nodes
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_multi_node(&self) -> bool {
+        self.nodes.len() > 1
+    }
+
+    pub fn node(&self, id: usize) -> Option<&NumaNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Node the CPU belongs to
+    pub fn node_of_cpu(&self, cpu: usize) -> Option<usize> {
+        self.nodes
+            .iter()
+            .find(|node| node.cpus.contains(&cpu))
+            .map(|node| node.id)
+    }
+
+    /// Node worker `worker` of `workers` should run on under `policy`
+    ///
+    /// `Spread` hands out contiguous blocks so that neighbouring workers, which
+    /// rayon gives neighbouring chunks of a batch, share a node.
+    pub fn worker_node(&self, policy: NumaPolicy, worker: usize, workers: usize) -> Option<usize> {
+        match policy {
+            NumaPolicy::Disabled => None,
+            NumaPolicy::Node(id) => self.node(id).map(|node| node.id),
+            NumaPolicy::Spread => {
+                let block = workers.max(1).div_ceil(self.nodes.len());
+                self.nodes
+                    .get((worker / block).min(self.nodes.len() - 1))
+                    .map(|node| node.id)
+            }
+        }
+    }
+
+    /// Pin the calling thread to the CPUs of `node`
+    ///
+    /// Returns whether the thread was pinned; always `false` without the
+    /// `numa` feature, with `safe-only` or off Linux.
+    pub fn bind_current_thread(&self, node: usize) -> bool {
+        self.node(node).is_some_and(|node| bind_to_cpus(&node.cpus))
+    }
+
+    /// Zeroed arena of `len` weights whose pages live on `node`
+    ///
+    /// The arena is allocated and written on a thread pinned to the node.
+    /// Without pinning support, or for an unknown node, it is allocated on
+    /// the calling thread as [`WeightArena::zeroed`] would.
+    pub fn allocate_arena<T: Float + Send>(
+        &self,
+        node: usize,
+        len: usize,
+        policy: &HugePagePolicy,
+    ) -> WeightArena<T> {
+        if self.node(node).is_none() {
+            return WeightArena::zeroed(len, policy);
+        }
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    self.bind_current_thread(node);
+                    let mut arena = WeightArena::zeroed(len, policy);
+                    // Zeroed heap memory may come straight from the kernel
+                    // untouched; write it so the pages fault in here
+                    arena.fill(T::zero());
+                    arena
+                })
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
+}
+
+/// Parse a kernel CPU list such as `0-3,8,10-11`
+pub(crate) fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => {
+                let (start, end): (usize, usize) = (start.parse().ok()?, end.parse().ok()?);
+                cpus.extend(start..=end);
+            }
+            None => cpus.push(range.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+#[cfg(all(target_os = "linux", feature = "numa", not(feature = "safe-only")))]
+fn bind_to_cpus(cpus: &[usize]) -> bool {
+    // SAFETY: `cpu_set_t` is plain data and all-zero is the empty set
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for &cpu in cpus.iter().filter(|&&cpu| cpu < libc::CPU_SETSIZE as usize) {
+        // SAFETY: `cpu` is within the set
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    // SAFETY: pid 0 is the calling thread and `set` outlives the call
+    unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0 }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "numa", not(feature = "safe-only"))))]
+fn bind_to_cpus(_cpus: &[usize]) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topology_and_worker_placement() {
+        assert_eq!(
+            parse_cpu_list("0-3,8,10-11\n"),
+            Some(vec![0, 1, 2, 3, 8, 10, 11])
+        );
+        assert_eq!(parse_cpu_list(""), Some(vec![]));
+        assert_eq!(parse_cpu_list("0-x"), None);
+
+        let topology = NumaTopology::from_nodes(vec![
+            NumaNode {
+                id: 1,
+                cpus: vec![4, 5, 6, 7],
+            },
+            NumaNode {
+                id: 0,
+                cpus: vec![0, 1, 2, 3],
+            },
+            NumaNode {
+                id: 2,
+                cpus: vec![],
+            },
+        ]);
+        assert_eq!(topology.num_nodes(), 2);
+        assert_eq!(topology.node_of_cpu(5), Some(1));
+        let spread: Vec<_> = (0..6)
+            .map(|w| topology.worker_node(NumaPolicy::Spread, w, 6))
+            .collect();
+        assert_eq!(spread, [0, 0, 0, 1, 1, 1].map(Some));
+        assert_eq!(topology.worker_node(NumaPolicy::Node(1), 3, 6), Some(1));
+        assert_eq!(topology.worker_node(NumaPolicy::Node(7), 0, 6), None);
+        assert_eq!(topology.worker_node(NumaPolicy::Disabled, 0, 6), None);
+
+        let detected = NumaTopology::detect();
+        assert!(detected.num_nodes() >= 1);
+        let node = detected.nodes()[0].id;
+        let arena: WeightArena<f32> =
+            detected.allocate_arena(node, 1000, &HugePagePolicy::disabled());
+        assert_eq!(arena.len(), 1000);
+        assert!(arena.iter().all(|&v| v == 0.0));
+    }
+}
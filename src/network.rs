@@ -1,13 +1,35 @@
-use crate::{ActivationFunction, Layer, TrainingAlgorithm};
+use crate::{ActivationFunction, Layer, Recurrence, RecurrenceKind, TrainingAlgorithm};
 use num_traits::Float;
 use rand::distributions::Uniform;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+mod cleanup;
+mod connections;
+mod diff;
+mod finalize;
+mod inspection;
+mod interval;
+#[cfg(all(feature = "binary", feature = "serde"))]
+mod legacy;
+mod recurrent;
+mod schema;
+mod validation;
+
+pub use crate::memory_manager::{MemoryPrefetcher, PrefetchHint};
+pub use cleanup::{CleanupReport, RemovalReason, RemovedNeuron};
+pub use diff::{LayerWeightDiff, NetworkDiff, TopologyDifference};
+pub(crate) use finalize::FusedLayer;
+pub use finalize::{FinalizedNetwork, FusionReport};
+pub use interval::{NoiseDistribution, NoiseModel, PredictionInterval};
+pub use schema::{InputRange, NetworkSchema};
+pub use validation::TopologyIssue;
+
 /// Errors that can occur during network operations
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone, PartialEq)]
 pub enum NetworkError {
     #[error("Input size mismatch: expected {expected}, got {actual}")]
     InputSizeMismatch { expected: usize, actual: usize },
@@ -20,8 +42,42 @@ pub enum NetworkError {
 
     #[error("Network has no layers")]
     NoLayers,
+
+    #[error("Neuron index {index} out of range for {total} neurons")]
+    NeuronIndexOutOfRange { index: usize, total: usize },
+
+    #[error("No connection from neuron {from} to neuron {to}")]
+    ConnectionNotFound { from: usize, to: usize },
+
+    #[error("Expected {expected} schema entries, got {actual}")]
+    SchemaMismatch { expected: usize, actual: usize },
+
+    #[error("Name '{0}' is used more than once")]
+    DuplicateName(String),
+
+    #[error("Missing value for input '{0}'")]
+    MissingInput(String),
+
+    #[error("Unknown input '{0}'")]
+    UnknownInput(String),
+
+    #[error("Calibration data is empty")]
+    EmptyCalibration,
+
+    #[error("Unsupported binary format version {0}")]
+    UnsupportedFormatVersion(u32),
+
+    #[error("Recurrent networks are not supported by this operation")]
+    Recurrent,
 }
 
+/// Leading bytes of [`Network::to_bytes`] output
+#[cfg(all(feature = "binary", feature = "serde"))]
+const BINARY_MAGIC: &[u8; 4] = b"DFNN";
+/// Bumped whenever the serialized layout of [`Network`] changes
+#[cfg(all(feature = "binary", feature = "serde"))]
+const BINARY_VERSION: u32 = 1;
+
 /// A feedforward neural network
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -31,6 +87,10 @@ pub struct Network<T: Float> {
 
     /// Connection rate (1.0 = fully connected, 0.0 = no connections)
     pub connection_rate: T,
+
+    /// Input and output names
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub schema: NetworkSchema,
 }
 
 impl<T: Float> Network<T> {
@@ -116,6 +176,7 @@ impl<T: Float> Network<T> {
             let prev_outputs = self.layers[i - 1].get_outputs();
             self.layers[i].calculate(&prev_outputs);
         }
+        self.advance_state();
 
         // Return output layer values (excluding bias if present)
         if let Some(output_layer) = self.layers.last() {
@@ -332,35 +393,35 @@ impl<T: Float> Network<T> {
         for layer_idx in (1..num_layers - 1).rev() {
             let current_layer = &self.layers[layer_idx];
             let next_layer = &self.layers[layer_idx + 1];
-            let next_errors = layer_errors[layer_idx + 1].clone(); // Clone to avoid borrowing issues
-
-            let mut current_errors = Vec::new();
+            let next_errors = &layer_errors[layer_idx + 1];
 
-            for (i, neuron) in current_layer.neurons.iter().enumerate() {
-                if neuron.is_bias {
-                    current_errors.push(T::zero());
+            // Sum errors from next layer weighted by connections, visiting
+            // only the connections that exist
+            let mut error_sums = vec![T::zero(); current_layer.size()];
+            for (next_neuron, &next_error) in next_layer.neurons.iter().zip(next_errors) {
+                if next_neuron.is_bias {
                     continue;
                 }
-
-                let mut error_sum = T::zero();
-
-                // Sum errors from next layer weighted by connections
-                for (j, next_neuron) in next_layer.neurons.iter().enumerate() {
-                    if !next_neuron.is_bias && j < next_errors.len() {
-                        // Find connection from current neuron to next neuron
-                        for connection in &next_neuron.connections {
-                            if connection.from_neuron == i {
-                                error_sum = error_sum + next_errors[j] * connection.weight;
-                                break;
-                            }
-                        }
+                for connection in &next_neuron.connections {
+                    if let Some(sum) = error_sums.get_mut(connection.from_neuron) {
+                        *sum = *sum + next_error * connection.weight;
                     }
                 }
-
-                let delta = error_sum * neuron.activation_derivative();
-                current_errors.push(delta);
             }
 
+            let current_errors = current_layer
+                .neurons
+                .iter()
+                .zip(error_sums)
+                .map(|(neuron, error_sum)| {
+                    if neuron.is_bias {
+                        T::zero()
+                    } else {
+                        error_sum * neuron.activation_derivative()
+                    }
+                })
+                .collect();
+
             layer_errors[layer_idx] = current_errors;
         }
 
@@ -401,13 +462,22 @@ impl<T: Float> Network<T> {
     }
 
     /// Serialize the network to bytes
+    ///
+    /// The bincode encoding follows a magic number and a format version, so
+    /// later layouts can still read it.
     #[cfg(all(feature = "binary", feature = "serde"))]
     pub fn to_bytes(&self) -> Vec<u8>
     where
         T: serde::Serialize,
         Network<T>: serde::Serialize,
     {
-        bincode::serialize(self).unwrap_or_default()
+        let Ok(body) = bincode::serialize(self) else {
+            return Vec::new();
+        };
+        let mut bytes = BINARY_MAGIC.to_vec();
+        bytes.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&body);
+        bytes
     }
 
     #[cfg(feature = "binary")]
@@ -418,13 +488,27 @@ impl<T: Float> Network<T> {
     }
 
     /// Deserialize a network from bytes
+    ///
+    /// Also reads the unversioned bytes written by earlier releases; their
+    /// layers have no dropout and the network no schema.
     #[cfg(all(feature = "binary", feature = "serde"))]
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, NetworkError>
     where
         T: serde::de::DeserializeOwned,
         Network<T>: serde::de::DeserializeOwned,
     {
-        bincode::deserialize(bytes).map_err(|_| NetworkError::InvalidLayerConfiguration)
+        let Some(versioned) = bytes.strip_prefix(BINARY_MAGIC) else {
+            return legacy::from_legacy_bytes(bytes);
+        };
+        let (version, body) = versioned
+            .split_first_chunk::<4>()
+            .ok_or(NetworkError::InvalidLayerConfiguration)?;
+        match u32::from_le_bytes(*version) {
+            BINARY_VERSION => {
+                bincode::deserialize(body).map_err(|_| NetworkError::InvalidLayerConfiguration)
+            }
+            version => Err(NetworkError::UnsupportedFormatVersion(version)),
+        }
     }
 
     #[cfg(feature = "binary")]
@@ -437,8 +521,14 @@ impl<T: Float> Network<T> {
 
 /// Builder for creating neural networks with a fluent API
 pub struct NetworkBuilder<T: Float> {
-    layers: Vec<(usize, ActivationFunction, T)>,
+    /// Size, activation, steepness and dropout rate of each layer
+    layers: Vec<(usize, ActivationFunction, T, T)>,
+    /// Index and kind of each recurrent layer
+    recurrent: Vec<(usize, RecurrenceKind)>,
     connection_rate: T,
+    schema: NetworkSchema,
+    /// Seed for connections and initial weights; random if unset
+    seed: Option<u64>,
 }
 
 impl<T: Float> NetworkBuilder<T> {
@@ -457,7 +547,10 @@ impl<T: Float> NetworkBuilder<T> {
     pub fn new() -> Self {
         NetworkBuilder {
             layers: Vec::new(),
+            recurrent: Vec::new(),
             connection_rate: T::one(),
+            schema: NetworkSchema::default(),
+            seed: None,
         }
     }
 
@@ -469,12 +562,12 @@ impl<T: Float> NetworkBuilder<T> {
 
         // First layer is input
         self.layers
-            .push((sizes[0], ActivationFunction::Linear, T::one()));
+            .push((sizes[0], ActivationFunction::Linear, T::one(), T::zero()));
 
         // Middle layers are hidden with sigmoid activation
         for &size in &sizes[1..sizes.len() - 1] {
             self.layers
-                .push((size, ActivationFunction::Sigmoid, T::one()));
+                .push((size, ActivationFunction::Sigmoid, T::one(), T::zero()));
         }
 
         // Last layer is output
@@ -483,6 +576,7 @@ impl<T: Float> NetworkBuilder<T> {
                 sizes[sizes.len() - 1],
                 ActivationFunction::Sigmoid,
                 T::one(),
+                T::zero(),
             ));
         }
 
@@ -492,14 +586,14 @@ impl<T: Float> NetworkBuilder<T> {
     /// Adds an input layer to the network
     pub fn input_layer(mut self, size: usize) -> Self {
         self.layers
-            .push((size, ActivationFunction::Linear, T::one()));
+            .push((size, ActivationFunction::Linear, T::one(), T::zero()));
         self
     }
 
     /// Adds a hidden layer with default activation (Sigmoid)
     pub fn hidden_layer(mut self, size: usize) -> Self {
         self.layers
-            .push((size, ActivationFunction::Sigmoid, T::one()));
+            .push((size, ActivationFunction::Sigmoid, T::one(), T::zero()));
         self
     }
 
@@ -510,14 +604,43 @@ impl<T: Float> NetworkBuilder<T> {
         activation: ActivationFunction,
         steepness: T,
     ) -> Self {
-        self.layers.push((size, activation, steepness));
+        self.layers.push((size, activation, steepness, T::zero()));
+        self
+    }
+
+    /// Adds a hidden layer (Sigmoid) that drops a fraction `rate` of its
+    /// neurons in every training sample
+    ///
+    /// Training uses inverted dropout: kept activations are scaled by
+    /// `1 / (1 - rate)`, so [`Network::run`] needs no rescaling and never
+    /// drops anything. `rate` is clamped to `[0, 0.95]`.
+    pub fn hidden_layer_with_dropout(mut self, size: usize, rate: T) -> Self {
+        let rate = rate.max(T::zero()).min(crate::numeric::cast(0.95));
+        self.layers
+            .push((size, ActivationFunction::Sigmoid, T::one(), rate));
         self
     }
 
+    /// Adds a hidden layer (Sigmoid) whose neurons also receive the layer's
+    /// own outputs from the previous time step, as in an Elman network
+    pub fn elman_layer(mut self, size: usize) -> Self {
+        self.recurrent
+            .push((self.layers.len(), RecurrenceKind::Elman));
+        self.hidden_layer(size)
+    }
+
+    /// Adds a hidden layer (Sigmoid) whose neurons also receive the network
+    /// outputs from the previous time step, as in a Jordan network
+    pub fn jordan_layer(mut self, size: usize) -> Self {
+        self.recurrent
+            .push((self.layers.len(), RecurrenceKind::Jordan));
+        self.hidden_layer(size)
+    }
+
     /// Adds an output layer with default activation (Sigmoid)
     pub fn output_layer(mut self, size: usize) -> Self {
         self.layers
-            .push((size, ActivationFunction::Sigmoid, T::one()));
+            .push((size, ActivationFunction::Sigmoid, T::one(), T::zero()));
         self
     }
 
@@ -528,23 +651,68 @@ impl<T: Float> NetworkBuilder<T> {
         activation: ActivationFunction,
         steepness: T,
     ) -> Self {
-        self.layers.push((size, activation, steepness));
+        self.layers.push((size, activation, steepness, T::zero()));
         self
     }
 
     /// Sets the connection rate (0.0 to 1.0)
+    ///
+    /// Below 1.0 the layers are only partially connected, like networks
+    /// from FANN's `fann_create_sparse`; see [`Layer::connect_to`].
     pub fn connection_rate(mut self, rate: T) -> Self {
         self.connection_rate = rate;
         self
     }
 
+    /// Draw the connections and initial weights, including those of context
+    /// units, from a generator seeded with `seed`, so equal builders build
+    /// equal networks
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Names of the inputs, one per input neuron
+    pub fn input_names<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.schema.input_names = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Names of the outputs, one per output neuron
+    pub fn output_names<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.schema.output_names = names.into_iter().map(Into::into).collect();
+        self
+    }
+
     /// Builds the network
+    ///
+    /// # Panics
+    ///
+    /// Panics if input or output names were given that do not match the
+    /// layer sizes or contain duplicates; [`NetworkBuilder::try_build`]
+    /// returns the error instead.
     pub fn build(self) -> Network<T> {
+        match self.try_build() {
+            Ok(network) => network,
+            Err(e) => panic!("invalid network schema: {e}"),
+        }
+    }
+
+    /// Builds the network, failing if input or output names were given that
+    /// do not match the layer sizes or contain duplicates
+    pub fn try_build(self) -> Result<Network<T>, NetworkError> {
+        if self.layers.is_empty() {
+            return Err(NetworkError::NoLayers);
+        }
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
         let mut network_layers = Vec::new();
 
         // Create layers
-        for (i, &(size, activation, steepness)) in self.layers.iter().enumerate() {
-            let layer = if i == 0 {
+        for (i, &(size, activation, steepness, dropout)) in self.layers.iter().enumerate() {
+            let mut layer = if i == 0 {
                 // Input layer with bias
                 Layer::with_bias(size, activation, steepness)
             } else if i == self.layers.len() - 1 {
@@ -554,19 +722,45 @@ impl<T: Float> NetworkBuilder<T> {
                 // Hidden layer with bias
                 Layer::with_bias(size, activation, steepness)
             };
+            layer.dropout = dropout;
             network_layers.push(layer);
         }
 
         // Connect layers
         for i in 0..network_layers.len() - 1 {
             let (before, after) = network_layers.split_at_mut(i + 1);
-            before[i].connect_to(&mut after[0], self.connection_rate);
+            before[i].connect_to_with_rng(&mut after[0], self.connection_rate, &mut rng);
+        }
+
+        // Add context units
+        let num_outputs = self.layers.last().map_or(0, |&(size, ..)| size);
+        for &(i, kind) in &self.recurrent {
+            if let Some(layer) = network_layers.get_mut(i) {
+                let context_size = match kind {
+                    RecurrenceKind::Elman => layer.num_regular_neurons(),
+                    RecurrenceKind::Jordan => num_outputs,
+                };
+                layer.recurrence = Some(Recurrence::with_rng(
+                    kind,
+                    layer.num_regular_neurons(),
+                    context_size,
+                    &mut rng,
+                ));
+            }
         }
 
-        Network {
+        let mut network = Network {
             layers: network_layers,
             connection_rate: self.connection_rate,
+            schema: NetworkSchema::default(),
+        };
+        if !self.schema.input_names.is_empty() {
+            network.set_input_names(self.schema.input_names)?;
+        }
+        if !self.schema.output_names.is_empty() {
+            network.set_output_names(self.schema.output_names)?;
         }
+        Ok(network)
     }
 }
 
@@ -0,0 +1,301 @@
+//! Dead-neuron elimination and constant folding
+//!
+//! Pruning and cascade training leave hidden neurons behind that no longer
+//! contribute anything. [`Network::eliminate_dead_neurons`] removes two kinds:
+//!
+//! - dead neurons, whose outgoing weights are all zero, and
+//! - constant neurons, whose incoming weights from regular neurons are all
+//!   zero so they output the same value for every input. Their contribution
+//!   is folded into the bias weights of the next layer first.
+//!
+//! Removal can expose further candidates (a neuron whose only consumer was
+//! removed is now dead), so the pass repeats until nothing changes. Input
+//! and output neurons are never removed, and every hidden layer keeps at
+//! least one regular neuron. The outputs of the network are unchanged.
+//!
+//! [`Network::remove_hidden_neuron`] removes a single hidden neuron chosen
+//! by the caller, e.g. a pruning pass that ranks neurons by contribution.
+//!
+//! Both fail with [`NetworkError::Recurrent`] for recurrent networks, whose
+//! context weights would still refer to the removed neurons.
+
+use super::{Network, NetworkError};
+use num_traits::Float;
+
+/// Why a neuron was removed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemovalReason<T: Float> {
+    /// All outgoing weights were zero
+    NoOutgoingWeights,
+    /// The neuron always output `value`, which was folded into the next
+    /// layer's bias weights
+    Constant { value: T },
+    /// The neuron's contribution `score` fell below a pruning threshold
+    LowContribution { score: T },
+}
+
+/// One removed neuron
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RemovedNeuron<T: Float> {
+    pub layer: usize,
+    /// Position within the layer before the pass ran
+    pub neuron: usize,
+    pub reason: RemovalReason<T>,
+}
+
+impl<T: Float> std::fmt::Display for RemovedNeuron<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (layer, neuron) = (self.layer, self.neuron);
+        match self.reason {
+            RemovalReason::NoOutgoingWeights => write!(
+                f,
+                "removed neuron {neuron} in layer {layer}: all outgoing weights are zero"
+            ),
+            RemovalReason::Constant { value } => write!(
+                f,
+                "removed neuron {neuron} in layer {layer}: constant output {} folded into bias",
+                value.to_f64().unwrap_or(f64::NAN)
+            ),
+            RemovalReason::LowContribution { score } => write!(
+                f,
+                "removed neuron {neuron} in layer {layer}: contribution {} below threshold",
+                score.to_f64().unwrap_or(f64::NAN)
+            ),
+        }
+    }
+}
+
+/// Audit report of [`Network::eliminate_dead_neurons`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CleanupReport<T: Float> {
+    /// Removed neurons, in removal order
+    pub removed: Vec<RemovedNeuron<T>>,
+    /// Connections deleted together with the neurons
+    pub connections_removed: usize,
+}
+
+impl<T: Float> Default for CleanupReport<T> {
+    fn default() -> Self {
+        Self {
+            removed: Vec::new(),
+            connections_removed: 0,
+        }
+    }
+}
+
+impl<T: Float> CleanupReport<T> {
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty()
+    }
+}
+
+impl<T: Float> Network<T> {
+    /// Remove dead and constant hidden neurons, returning what was removed
+    pub fn eliminate_dead_neurons(&mut self) -> Result<CleanupReport<T>, NetworkError> {
+        self.check_feed_forward()?;
+        let mut report = CleanupReport::default();
+        // Original position of every neuron still in each layer
+        let mut positions: Vec<Vec<usize>> = self
+            .layers
+            .iter()
+            .map(|layer| (0..layer.size()).collect())
+            .collect();
+
+        while let Some((layer, neuron, reason)) = self.find_removable() {
+            if let RemovalReason::Constant { value } = reason {
+                self.fold_constant(layer, neuron, value);
+            }
+            report.connections_removed += self.remove_neuron(layer, neuron);
+            report.removed.push(RemovedNeuron {
+                layer,
+                neuron: positions[layer].remove(neuron),
+                reason,
+            });
+        }
+        Ok(report)
+    }
+
+    /// Remove regular neuron `neuron` of hidden layer `layer` with its
+    /// connections; returns the number of connections deleted
+    ///
+    /// With `fold_value`, the neuron's contribution at that output value
+    /// (e.g. its mean activation) is added to the next layer's bias weights
+    /// first, as for constant neurons. Fails for input and output layers,
+    /// bias neurons and the last regular neuron of a layer.
+    pub fn remove_hidden_neuron(
+        &mut self,
+        layer: usize,
+        neuron: usize,
+        fold_value: Option<T>,
+    ) -> Result<usize, NetworkError> {
+        self.check_feed_forward()?;
+        let current = self
+            .layers
+            .get(layer)
+            .filter(|_| layer > 0 && layer + 1 < self.layers.len())
+            .ok_or(NetworkError::InvalidLayerConfiguration)?;
+        let removable = current.neurons.get(neuron).is_some_and(|n| !n.is_bias)
+            && current.num_regular_neurons() > 1;
+        if !removable {
+            return Err(NetworkError::InvalidLayerConfiguration);
+        }
+        if let Some(value) = fold_value {
+            self.fold_constant(layer, neuron, value);
+        }
+        Ok(self.remove_neuron(layer, neuron))
+    }
+
+    fn find_removable(&self) -> Option<(usize, usize, RemovalReason<T>)> {
+        let hidden = 1..self.layers.len().saturating_sub(1);
+        for layer in hidden {
+            let current = &self.layers[layer];
+            if current.num_regular_neurons() <= 1 {
+                continue;
+            }
+            let next = &self.layers[layer + 1];
+            let previous = &self.layers[layer - 1];
+            for (index, neuron) in current.neurons.iter().enumerate() {
+                if neuron.is_bias {
+                    continue;
+                }
+                let dead = next
+                    .neurons
+                    .iter()
+                    .flat_map(|n| &n.connections)
+                    .filter(|c| c.from_neuron == index)
+                    .all(|c| c.weight == T::zero());
+                if dead {
+                    return Some((layer, index, RemovalReason::NoOutgoingWeights));
+                }
+
+                let constant = current.has_bias()
+                    && neuron.connections.iter().all(|c| {
+                        c.weight == T::zero()
+                            || previous
+                                .neurons
+                                .get(c.from_neuron)
+                                .is_some_and(|n| n.is_bias)
+                    });
+                if constant {
+                    // Only bias connections remain, so any input gives the value
+                    let inputs: Vec<T> = previous
+                        .neurons
+                        .iter()
+                        .map(|n| if n.is_bias { T::one() } else { T::zero() })
+                        .collect();
+                    let mut probe = neuron.clone();
+                    probe.calculate(&inputs);
+                    let value = probe.value;
+                    return Some((layer, index, RemovalReason::Constant { value }));
+                }
+            }
+        }
+        None
+    }
+
+    /// Add the constant output `value` of `neuron` in `layer` to the bias
+    /// weights of the next layer
+    fn fold_constant(&mut self, layer: usize, neuron: usize, value: T) {
+        let bias = self.layers[layer].size() - 1;
+        for next in self.layers[layer + 1].neurons.iter_mut() {
+            if next.is_bias {
+                continue;
+            }
+            let contribution = next
+                .connections
+                .iter()
+                .filter(|c| c.from_neuron == neuron)
+                .fold(T::zero(), |acc, c| acc + c.weight * value);
+            if contribution == T::zero() {
+                continue;
+            }
+            match next.connections.iter_mut().find(|c| c.from_neuron == bias) {
+                Some(connection) => connection.weight = connection.weight + contribution,
+                None => next.add_connection(bias, contribution),
+            }
+        }
+    }
+
+    /// Delete `neuron` from `layer` and renumber the next layer's
+    /// connections; returns the number of connections deleted
+    fn remove_neuron(&mut self, layer: usize, neuron: usize) -> usize {
+        let mut removed = self.layers[layer].neurons.remove(neuron).connections.len();
+        for next in self.layers[layer + 1].neurons.iter_mut() {
+            let before = next.connections.len();
+            next.connections.retain(|c| c.from_neuron != neuron);
+            removed += before - next.connections.len();
+            for connection in next.connections.iter_mut() {
+                if connection.from_neuron > neuron {
+                    connection.from_neuron -= 1;
+                }
+            }
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_eliminate_dead_and_constant_neurons() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let mut weight = 0.1;
+        for layer in &mut network.layers {
+            for neuron in &mut layer.neurons {
+                for connection in &mut neuron.connections {
+                    weight = -weight * 1.3;
+                    connection.weight = weight;
+                }
+            }
+        }
+        // Hidden neuron 1 of layer 1 feeds nothing
+        for neuron in &mut network.layers[2].neurons {
+            for connection in &mut neuron.connections {
+                if connection.from_neuron == 1 {
+                    connection.weight = 0.0;
+                }
+            }
+        }
+        // Hidden neuron 2 of layer 2 ignores its inputs and only sees the bias
+        for connection in &mut network.layers[2].neurons[2].connections {
+            if connection.from_neuron != 4 {
+                connection.weight = 0.0;
+            }
+        }
+        let inputs = [[0.2, 0.9], [-1.0, 0.4], [0.0, 0.0]];
+        let expected: Vec<Vec<f64>> = inputs.iter().map(|i| network.run(i)).collect();
+
+        let report = network.eliminate_dead_neurons().unwrap();
+        assert_eq!(report.removed.len(), 2);
+        assert_eq!(report.removed[0].layer, 1);
+        assert_eq!(report.removed[0].neuron, 1);
+        assert_eq!(report.removed[0].reason, RemovalReason::NoOutgoingWeights);
+        assert_eq!(report.removed[1].layer, 2);
+        assert_eq!(report.removed[1].neuron, 2);
+        assert!(matches!(
+            report.removed[1].reason,
+            RemovalReason::Constant { .. }
+        ));
+        assert!(report.removed[1].to_string().contains("folded into bias"));
+        // 3 incoming + 3 outgoing, then 4 incoming (3 after the first
+        // removal plus bias) + 1 outgoing
+        assert_eq!(report.connections_removed, 6 + 5);
+        assert_eq!(network.layers[1].num_regular_neurons(), 3);
+        assert_eq!(network.layers[2].num_regular_neurons(), 2);
+        assert!(network.validate_topology().is_ok());
+
+        for (input, expected) in inputs.iter().zip(&expected) {
+            let output = network.run(input);
+            assert!((output[0] - expected[0]).abs() < 1e-12);
+        }
+        assert!(network.eliminate_dead_neurons().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+//! Connection-level access using FANN's global neuron numbering
+//!
+//! libfann addresses neurons by a single index that runs over all layers in
+//! order, bias neurons included. Internally each connection stores the index
+//! of its source within the previous layer; the methods here translate between
+//! the two so individual weights can be read and written like
+//! `fann_get_connection_array` and `fann_set_weight` do.
+
+use super::{Network, NetworkError};
+use crate::Connection;
+use num_traits::Float;
+
+impl<T: Float> Network<T> {
+    /// Global index of the first neuron of every layer
+    fn layer_offsets(&self) -> Vec<usize> {
+        self.layers
+            .iter()
+            .scan(0, |offset, layer| {
+                let start = *offset;
+                *offset += layer.size();
+                Some(start)
+            })
+            .collect()
+    }
+
+    /// Layer and in-layer position of the neuron with global index `neuron`
+    pub fn neuron_position(&self, neuron: usize) -> Option<(usize, usize)> {
+        let mut offset = 0;
+        for (layer_index, layer) in self.layers.iter().enumerate() {
+            if neuron < offset + layer.size() {
+                return Some((layer_index, neuron - offset));
+            }
+            offset += layer.size();
+        }
+        None
+    }
+
+    /// Global index of neuron `index` in layer `layer`
+    pub fn neuron_index(&self, layer: usize, index: usize) -> Option<usize> {
+        let offsets = self.layer_offsets();
+        let size = self.layers.get(layer)?.size();
+        (index < size).then(|| offsets[layer] + index)
+    }
+
+    /// Resolve a global `from -> to` pair to (layer of `to`, neuron in layer,
+    /// source index in the previous layer)
+    fn resolve_pair(&self, from: usize, to: usize) -> Result<(usize, usize, usize), NetworkError> {
+        let total = self.total_neurons();
+        let position = |neuron| {
+            self.neuron_position(neuron)
+                .ok_or(NetworkError::NeuronIndexOutOfRange {
+                    index: neuron,
+                    total,
+                })
+        };
+        let (from_layer, from_index) = position(from)?;
+        let (to_layer, to_index) = position(to)?;
+        if to_layer != from_layer + 1 {
+            return Err(NetworkError::ConnectionNotFound { from, to });
+        }
+        Ok((to_layer, to_index, from_index))
+    }
+
+    /// The connection from global neuron `from` to global neuron `to`, if any
+    pub fn get_connection(&self, from: usize, to: usize) -> Option<Connection<T>> {
+        let (layer, neuron, source) = self.resolve_pair(from, to).ok()?;
+        self.layers[layer].neurons[neuron]
+            .connections
+            .iter()
+            .find(|c| c.from_neuron == source)
+            .map(|c| Connection::new(from, to, c.weight))
+    }
+
+    /// Set the weight of an existing connection, like `fann_set_weight`
+    pub fn set_weight(&mut self, from: usize, to: usize, weight: T) -> Result<(), NetworkError> {
+        let (layer, neuron, source) = self.resolve_pair(from, to)?;
+        let connection = self.layers[layer].neurons[neuron]
+            .connections
+            .iter_mut()
+            .find(|c| c.from_neuron == source)
+            .ok_or(NetworkError::ConnectionNotFound { from, to })?;
+        connection.weight = weight;
+        Ok(())
+    }
+
+    /// All connections with global neuron indices, like `fann_get_connection_array`
+    ///
+    /// Connections are ordered by destination neuron and then by the order in
+    /// which they are stored, which is also the order of [`Network::get_weights`].
+    pub fn get_connection_array(&self) -> Vec<Connection<T>> {
+        let offsets = self.layer_offsets();
+        let mut connections = Vec::with_capacity(self.total_connections());
+        for (layer_index, layer) in self.layers.iter().enumerate().skip(1) {
+            for (neuron_index, neuron) in layer.neurons.iter().enumerate() {
+                let to = offsets[layer_index] + neuron_index;
+                connections.extend(neuron.connections.iter().map(|c| {
+                    Connection::new(offsets[layer_index - 1] + c.from_neuron, to, c.weight)
+                }));
+            }
+        }
+        connections
+    }
+
+    /// Set the weights of the given connections, like `fann_set_weight_array`
+    ///
+    /// Stops at the first connection that does not exist in the network.
+    pub fn set_weight_array(&mut self, connections: &[Connection<T>]) -> Result<(), NetworkError> {
+        for connection in connections {
+            self.set_weight(
+                connection.from_neuron,
+                connection.to_neuron,
+                connection.weight,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Connection, NetworkBuilder, NetworkError};
+
+    #[test]
+    fn test_connection_array_round_trip() {
+        // Global numbering: inputs 0-1, bias 2, hidden 3-5, bias 6, output 7
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+
+        let connections = network.get_connection_array();
+        assert_eq!(connections.len(), network.total_connections());
+        let weights: Vec<f32> = connections.iter().map(|c| c.weight).collect();
+        assert_eq!(weights, network.get_weights());
+        assert_eq!(connections[0].from_neuron, 0);
+        assert_eq!(connections[0].to_neuron, 3);
+        assert_eq!(connections.last().unwrap().from_neuron, 6);
+        assert_eq!(connections.last().unwrap().to_neuron, 7);
+
+        network.set_weight(2, 4, 0.75).unwrap();
+        assert_eq!(
+            network.get_connection(2, 4),
+            Some(Connection::new(2, 4, 0.75))
+        );
+        assert_eq!(network.layers[1].neurons[1].connections[2].weight, 0.75);
+
+        let doubled: Vec<Connection<f32>> = connections
+            .iter()
+            .map(|c| Connection::new(c.from_neuron, c.to_neuron, c.weight * 2.0))
+            .collect();
+        network.set_weight_array(&doubled).unwrap();
+        assert_eq!(network.get_connection_array(), doubled);
+    }
+
+    #[test]
+    fn test_invalid_connections() {
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+
+        assert_eq!(network.neuron_position(3), Some((1, 0)));
+        assert_eq!(network.neuron_index(1, 0), Some(3));
+        assert!(network.get_connection(0, 1).is_none());
+        assert!(matches!(
+            network.set_weight(0, 9, 1.0),
+            Err(NetworkError::NeuronIndexOutOfRange { index: 9, total: 4 })
+        ));
+        assert!(matches!(
+            network.set_weight(3, 0, 1.0),
+            Err(NetworkError::ConnectionNotFound { from: 3, to: 0 })
+        ));
+    }
+}
@@ -0,0 +1,319 @@
+//! Structural and numerical comparison of two networks
+
+use super::Network;
+use crate::ActivationFunction;
+use num_traits::Float;
+
+/// A structural difference between two networks
+#[derive(Debug, Clone, PartialEq)]
+pub enum TopologyDifference<T: Float> {
+    /// The networks have a different number of layers
+    LayerCount { left: usize, right: usize },
+    /// A layer has a different number of neurons (bias included)
+    LayerSize {
+        layer: usize,
+        left: usize,
+        right: usize,
+    },
+    /// A neuron is connected to a different set of source neurons
+    Connectivity { layer: usize, neuron: usize },
+    /// A neuron uses a different activation function
+    Activation {
+        layer: usize,
+        neuron: usize,
+        left: ActivationFunction,
+        right: ActivationFunction,
+    },
+    /// A neuron uses a different activation steepness
+    Steepness {
+        layer: usize,
+        neuron: usize,
+        left: T,
+        right: T,
+    },
+}
+
+impl<T: Float> std::fmt::Display for TopologyDifference<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LayerCount { left, right } => {
+                write!(f, "layer count differs ({left} vs {right})")
+            }
+            Self::LayerSize { layer, left, right } => {
+                write!(f, "layer {layer} size differs ({left} vs {right})")
+            }
+            Self::Connectivity { layer, neuron } => {
+                write!(
+                    f,
+                    "neuron {neuron} in layer {layer} has different connections"
+                )
+            }
+            Self::Activation {
+                layer,
+                neuron,
+                left,
+                right,
+            } => write!(
+                f,
+                "neuron {neuron} in layer {layer} uses {} vs {}",
+                left.name(),
+                right.name()
+            ),
+            Self::Steepness {
+                layer,
+                neuron,
+                left,
+                right,
+            } => write!(
+                f,
+                "neuron {neuron} in layer {layer} has steepness {} vs {}",
+                left.to_f64().unwrap_or(f64::NAN),
+                right.to_f64().unwrap_or(f64::NAN)
+            ),
+        }
+    }
+}
+
+/// Weight distance metrics for one layer with identical connectivity
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerWeightDiff<T: Float> {
+    /// Layer index
+    pub layer: usize,
+    /// Number of compared weights (incoming connections of the layer)
+    pub num_weights: usize,
+    /// Euclidean distance between the two weight vectors
+    pub l2_distance: T,
+    /// Cosine similarity between the two weight vectors (1.0 when both are zero)
+    pub cosine_similarity: T,
+    /// Largest absolute difference of a single weight
+    pub max_abs_difference: T,
+}
+
+/// Result of [`Network::diff`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkDiff<T: Float> {
+    /// Structural differences, empty when the topologies match
+    pub topology: Vec<TopologyDifference<T>>,
+    /// Weight metrics for every layer whose connectivity matches
+    pub layers: Vec<LayerWeightDiff<T>>,
+}
+
+impl<T: Float> NetworkDiff<T> {
+    /// Whether both networks have the same structure
+    pub fn topology_matches(&self) -> bool {
+        self.topology.is_empty()
+    }
+
+    /// Whether the networks have the same structure and identical weights
+    pub fn is_identical(&self) -> bool {
+        self.topology_matches() && self.layers.iter().all(|l| l.l2_distance == T::zero())
+    }
+
+    /// Whether the structures match and no weight differs by more than `tolerance`
+    pub fn is_close(&self, tolerance: T) -> bool {
+        self.topology_matches()
+            && self
+                .layers
+                .iter()
+                .all(|l| l.max_abs_difference <= tolerance)
+    }
+
+    /// Euclidean distance over all compared weights
+    pub fn total_l2_distance(&self) -> T {
+        self.layers
+            .iter()
+            .fold(T::zero(), |acc, l| acc + l.l2_distance * l.l2_distance)
+            .sqrt()
+    }
+
+    /// Largest per-layer L2 distance
+    pub fn max_layer_l2_distance(&self) -> T {
+        self.layers
+            .iter()
+            .fold(T::zero(), |acc, l| acc.max(l.l2_distance))
+    }
+}
+
+impl<T: Float> Network<T> {
+    /// Compare this network with another one
+    ///
+    /// Reports topology differences (layer counts and sizes, connectivity,
+    /// activation functions and steepness) and, for every layer whose
+    /// connectivity matches, L2 distance and cosine similarity of the incoming
+    /// weights.
+    pub fn diff(&self, other: &Network<T>) -> NetworkDiff<T> {
+        let mut topology = Vec::new();
+        let mut layers = Vec::new();
+
+        if self.layers.len() != other.layers.len() {
+            topology.push(TopologyDifference::LayerCount {
+                left: self.layers.len(),
+                right: other.layers.len(),
+            });
+        }
+
+        for (layer_idx, (left, right)) in self.layers.iter().zip(&other.layers).enumerate() {
+            if left.neurons.len() != right.neurons.len() {
+                topology.push(TopologyDifference::LayerSize {
+                    layer: layer_idx,
+                    left: left.neurons.len(),
+                    right: right.neurons.len(),
+                });
+                continue;
+            }
+
+            let mut connectivity_matches = true;
+            for (neuron_idx, (a, b)) in left.neurons.iter().zip(&right.neurons).enumerate() {
+                if a.activation_function != b.activation_function {
+                    topology.push(TopologyDifference::Activation {
+                        layer: layer_idx,
+                        neuron: neuron_idx,
+                        left: a.activation_function,
+                        right: b.activation_function,
+                    });
+                }
+                if a.activation_steepness != b.activation_steepness {
+                    topology.push(TopologyDifference::Steepness {
+                        layer: layer_idx,
+                        neuron: neuron_idx,
+                        left: a.activation_steepness,
+                        right: b.activation_steepness,
+                    });
+                }
+                let same_sources = a.connections.len() == b.connections.len()
+                    && a.connections
+                        .iter()
+                        .zip(&b.connections)
+                        .all(|(ca, cb)| ca.from_neuron == cb.from_neuron);
+                if !same_sources {
+                    topology.push(TopologyDifference::Connectivity {
+                        layer: layer_idx,
+                        neuron: neuron_idx,
+                    });
+                    connectivity_matches = false;
+                }
+            }
+
+            if connectivity_matches {
+                let pairs = left
+                    .neurons
+                    .iter()
+                    .zip(&right.neurons)
+                    .flat_map(|(a, b)| a.connections.iter().zip(&b.connections))
+                    .map(|(ca, cb)| (ca.weight, cb.weight));
+                if let Some(metrics) = weight_metrics(layer_idx, pairs) {
+                    layers.push(metrics);
+                }
+            }
+        }
+
+        NetworkDiff { topology, layers }
+    }
+}
+
+fn weight_metrics<T: Float>(
+    layer: usize,
+    pairs: impl Iterator<Item = (T, T)>,
+) -> Option<LayerWeightDiff<T>> {
+    let mut num_weights = 0;
+    let mut squared_distance = T::zero();
+    let mut max_abs_difference = T::zero();
+    let mut dot = T::zero();
+    let mut left_norm = T::zero();
+    let mut right_norm = T::zero();
+
+    for (a, b) in pairs {
+        let d = a - b;
+        num_weights += 1;
+        squared_distance = squared_distance + d * d;
+        max_abs_difference = max_abs_difference.max(d.abs());
+        dot = dot + a * b;
+        left_norm = left_norm + a * a;
+        right_norm = right_norm + b * b;
+    }
+
+    if num_weights == 0 {
+        return None;
+    }
+
+    let norms = (left_norm * right_norm).sqrt();
+    let cosine_similarity = if norms > T::zero() {
+        dot / norms
+    } else if left_norm == right_norm {
+        T::one()
+    } else {
+        T::zero()
+    };
+
+    Some(LayerWeightDiff {
+        layer,
+        num_weights,
+        l2_distance: squared_distance.sqrt(),
+        cosine_similarity,
+        max_abs_difference,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ActivationFunction, NetworkBuilder};
+
+    #[test]
+    fn test_identical_networks() {
+        let network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+
+        let diff = network.diff(&network.clone());
+        assert!(diff.is_identical());
+        assert_eq!(diff.layers.len(), 2);
+        assert!(diff
+            .layers
+            .iter()
+            .all(|l| (l.cosine_similarity - 1.0).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_weight_distance() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        let mut other = network.clone();
+
+        let mut weights = other.get_weights();
+        weights[0] += 3.0;
+        weights[1] -= 4.0;
+        other.set_weights(&weights).unwrap();
+
+        let diff = network.diff(&other);
+        assert!(diff.topology_matches());
+        assert!(!diff.is_identical());
+        assert!((diff.total_l2_distance() - 5.0).abs() < 1e-12);
+        assert!((diff.layers[0].max_abs_difference - 4.0).abs() < 1e-12);
+        assert!(diff.is_close(4.0 + 1e-9));
+    }
+
+    #[test]
+    fn test_topology_differences() {
+        let a = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let b = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .output_layer(1)
+            .build();
+        assert!(!a.diff(&b).topology_matches());
+
+        let mut c = a.clone();
+        c.set_activation_function_output(ActivationFunction::Linear);
+        let diff = a.diff(&c);
+        assert_eq!(diff.topology.len(), 1);
+        assert_eq!(diff.layers.len(), 2);
+    }
+}
@@ -0,0 +1,375 @@
+//! Inference graph optimization
+//!
+//! [`Network::finalize`] lowers a network into a [`FinalizedNetwork`]: one
+//! dense kernel per layer that computes `activation(W x + b)` in a single
+//! pass, instead of walking neurons and connections. While lowering, the
+//! pass
+//!
+//! - folds activation steepness into the weights and biases,
+//! - folds scaling layers (linear layers where neuron `i` only reads input
+//!   `i`) into the weights of the following layer, and
+//! - drops identity layers entirely.
+//!
+//! Deep, narrow networks spend most of their time in per-layer overhead, so
+//! fewer and flatter layers pay off directly. Large models, whose weights
+//! do not fit in cache, can use [`FinalizedNetwork::run_prefetched`] to
+//! stream the next layer's weights in while the current one is computed.
+//! The source network is left untouched; finalize again after further
+//! training.
+
+use super::{Network, NetworkError};
+use crate::memory_manager::MemoryPrefetcher;
+use crate::ActivationFunction;
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// What [`Network::finalize`] changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FusionReport {
+    /// Layers of the finalized network, each one fused kernel
+    pub fused_layers: usize,
+    /// Scaling layers folded into the next layer's weights
+    pub folded_scaling_layers: usize,
+    /// Identity layers removed
+    pub removed_identity_layers: usize,
+}
+
+/// A layer as one fused matrix-vector product, bias add and activation
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct FusedLayer<T: Float> {
+    pub(crate) inputs: usize,
+    /// Row-major, one row per neuron, steepness already applied
+    pub(crate) weights: Vec<T>,
+    bias: Vec<T>,
+    activations: Vec<ActivationFunction>,
+}
+
+impl<T: Float> FusedLayer<T> {
+    pub(crate) fn rows(&self) -> usize {
+        self.bias.len()
+    }
+
+    /// Add the bias and apply the activation to precomputed `W x` products,
+    /// for kernels that compute the product themselves
+    pub(crate) fn finish(&self, products: &mut [T]) {
+        for ((value, &bias), &activation) in
+            products.iter_mut().zip(&self.bias).zip(&self.activations)
+        {
+            *value = activation.activate(*value + bias);
+        }
+    }
+
+    /// Activated output of neuron `row`
+    fn neuron(&self, row: usize, input: &[T]) -> T {
+        let weights = &self.weights[row * self.inputs..(row + 1) * self.inputs];
+        let sum = weights
+            .iter()
+            .zip(input)
+            .fold(self.bias[row], |acc, (&w, &x)| acc + w * x);
+        self.activations[row].activate(sum)
+    }
+
+    fn forward(&self, input: &[T], output: &mut Vec<T>) {
+        output.clear();
+        output.extend((0..self.rows()).map(|row| self.neuron(row, input)));
+    }
+
+    /// [`Self::forward`] that prefetches the head of `upcoming` (up to the
+    /// prefetcher's lookahead) in even slices, one per row, so the
+    /// prefetches overlap the layer's arithmetic
+    fn forward_prefetching(
+        &self,
+        input: &[T],
+        output: &mut Vec<T>,
+        upcoming: &[T],
+        prefetcher: &MemoryPrefetcher,
+    ) {
+        let head = prefetcher.lookahead() / std::mem::size_of::<T>().max(1);
+        let upcoming = &upcoming[..head.min(upcoming.len())];
+        let slice = upcoming.len().div_ceil(self.rows().max(1)).max(1);
+        let mut slices = upcoming.chunks(slice);
+        output.clear();
+        for row in 0..self.rows() {
+            if let Some(next) = slices.next() {
+                prefetcher.prefetch(next);
+            }
+            output.push(self.neuron(row, input));
+        }
+    }
+
+    /// `(scale, offset)` per neuron if the layer computes `scale * x + offset`
+    /// element-wise
+    fn as_scaling(&self) -> Option<Vec<(T, T)>> {
+        if self.rows() != self.inputs
+            || self
+                .activations
+                .iter()
+                .any(|&a| a != ActivationFunction::Linear)
+        {
+            return None;
+        }
+        let mut scaling = Vec::with_capacity(self.rows());
+        for (i, row) in self.weights.chunks_exact(self.inputs.max(1)).enumerate() {
+            if row
+                .iter()
+                .enumerate()
+                .any(|(j, &w)| j != i && w != T::zero())
+            {
+                return None;
+            }
+            scaling.push((row[i], self.bias[i]));
+        }
+        Some(scaling)
+    }
+
+    /// Feed this layer `scale * x + offset` instead of `x`
+    fn absorb(&mut self, scaling: &[(T, T)]) {
+        for (row, bias) in self
+            .weights
+            .chunks_exact_mut(self.inputs.max(1))
+            .zip(&mut self.bias)
+        {
+            for (w, &(scale, offset)) in row.iter_mut().zip(scaling) {
+                *bias = *bias + *w * offset;
+                *w = *w * scale;
+            }
+        }
+    }
+}
+
+/// Inference-only form of a [`Network`] produced by [`Network::finalize`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FinalizedNetwork<T: Float> {
+    num_inputs: usize,
+    layers: Vec<FusedLayer<T>>,
+    report: FusionReport,
+}
+
+impl<T: Float> FinalizedNetwork<T> {
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.layers.last().map_or(self.num_inputs, FusedLayer::rows)
+    }
+
+    /// Number of fused kernels run per sample
+    pub fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn report(&self) -> &FusionReport {
+        &self.report
+    }
+
+    pub(crate) fn layers(&self) -> &[FusedLayer<T>] {
+        &self.layers
+    }
+
+    pub fn run(&self, input: &[T]) -> Result<Vec<T>, NetworkError> {
+        if input.len() != self.num_inputs {
+            return Err(NetworkError::InputSizeMismatch {
+                expected: self.num_inputs,
+                actual: input.len(),
+            });
+        }
+        let mut current = input.to_vec();
+        let mut next = Vec::new();
+        for layer in &self.layers {
+            layer.forward(&current, &mut next);
+            std::mem::swap(&mut current, &mut next);
+        }
+        Ok(current)
+    }
+
+    pub fn run_batch(&self, inputs: &[Vec<T>]) -> Result<Vec<Vec<T>>, NetworkError> {
+        inputs.iter().map(|input| self.run(input)).collect()
+    }
+
+    /// [`Self::run`] that prefetches each layer's weights while the previous
+    /// layer is computed, for models whose weights do not fit in cache
+    pub fn run_prefetched(
+        &self,
+        input: &[T],
+        prefetcher: &MemoryPrefetcher,
+    ) -> Result<Vec<T>, NetworkError> {
+        if input.len() != self.num_inputs {
+            return Err(NetworkError::InputSizeMismatch {
+                expected: self.num_inputs,
+                actual: input.len(),
+            });
+        }
+        if let Some(first) = self.layers.first() {
+            prefetcher.prefetch(&first.weights);
+        }
+        let mut current = input.to_vec();
+        let mut next = Vec::new();
+        for (index, layer) in self.layers.iter().enumerate() {
+            let upcoming = self.layers.get(index + 1).map_or(&[][..], |l| &l.weights);
+            layer.forward_prefetching(&current, &mut next, upcoming, prefetcher);
+            std::mem::swap(&mut current, &mut next);
+        }
+        Ok(current)
+    }
+}
+
+impl<T: Float> Network<T> {
+    /// Lower the network into fused per-layer kernels for inference
+    ///
+    /// Fails with [`NetworkError::Recurrent`] for recurrent networks, whose
+    /// context weights the kernels cannot hold.
+    pub fn finalize(&self) -> Result<FinalizedNetwork<T>, NetworkError> {
+        self.check_feed_forward()?;
+        let mut layers: Vec<FusedLayer<T>> = self
+            .layers
+            .windows(2)
+            .map(|pair| {
+                let (previous, layer) = (&pair[0], &pair[1]);
+                // Column of each previous-layer neuron, `None` for bias neurons
+                let mut columns = Vec::with_capacity(previous.neurons.len());
+                let mut inputs = 0;
+                for neuron in &previous.neurons {
+                    if neuron.is_bias {
+                        columns.push(None);
+                    } else {
+                        columns.push(Some(inputs));
+                        inputs += 1;
+                    }
+                }
+
+                let neurons: Vec<_> = layer.neurons.iter().filter(|n| !n.is_bias).collect();
+                let mut fused = FusedLayer {
+                    inputs,
+                    weights: vec![T::zero(); neurons.len() * inputs],
+                    bias: vec![T::zero(); neurons.len()],
+                    activations: neurons.iter().map(|n| n.activation_function).collect(),
+                };
+                for (row, neuron) in neurons.iter().enumerate() {
+                    let scale = if neuron.activation_function.uses_steepness() {
+                        neuron.activation_steepness
+                    } else {
+                        T::one()
+                    };
+                    for connection in &neuron.connections {
+                        let weight = connection.weight * scale;
+                        match columns.get(connection.from_neuron) {
+                            Some(Some(column)) => {
+                                let w = &mut fused.weights[row * inputs + column];
+                                *w = *w + weight;
+                            }
+                            Some(None) => fused.bias[row] = fused.bias[row] + weight,
+                            None => {}
+                        }
+                    }
+                }
+                fused
+            })
+            .collect();
+
+        let mut report = FusionReport::default();
+        let mut index = 0;
+        while index < layers.len() {
+            let Some(scaling) = layers[index].as_scaling() else {
+                index += 1;
+                continue;
+            };
+            let identity = scaling
+                .iter()
+                .all(|&(scale, offset)| scale == T::one() && offset == T::zero());
+            if identity {
+                layers.remove(index);
+                report.removed_identity_layers += 1;
+            } else if index + 1 < layers.len() {
+                layers.remove(index);
+                layers[index].absorb(&scaling);
+                report.folded_scaling_layers += 1;
+            } else {
+                // A scaling output layer has nothing to fold into
+                index += 1;
+            }
+        }
+        report.fused_layers = layers.len();
+
+        Ok(FinalizedNetwork {
+            num_inputs: self.num_inputs(),
+            layers,
+            report,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory_manager::MemoryPrefetcher;
+    use crate::{ActivationFunction, Network, NetworkBuilder};
+
+    /// Make layer `layer` compute `scale * x + offset` element-wise
+    fn make_scaling(network: &mut Network<f64>, layer: usize, scale: f64, offset: f64) {
+        let bias = network.layers[layer - 1].neurons.len() - 1;
+        for (i, neuron) in network.layers[layer].neurons.iter_mut().enumerate() {
+            for connection in &mut neuron.connections {
+                connection.weight = match connection.from_neuron {
+                    from if from == i => scale,
+                    from if from == bias => offset,
+                    _ => 0.0,
+                };
+            }
+        }
+    }
+
+    #[test]
+    fn test_finalize_folds_and_matches_network() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(3)
+            .hidden_layer_with_activation(3, ActivationFunction::Linear, 1.0)
+            .hidden_layer_with_activation(4, ActivationFunction::Tanh, 0.7)
+            .hidden_layer_with_activation(4, ActivationFunction::Linear, 0.5)
+            .hidden_layer_with_activation(4, ActivationFunction::ReLU, 2.0)
+            .output_layer(2)
+            .build();
+        make_scaling(&mut network, 1, 1.0, 0.0);
+        make_scaling(&mut network, 3, 3.0, -0.2);
+
+        let finalized = network.finalize().unwrap();
+        let report = finalized.report();
+        assert_eq!(report.removed_identity_layers, 1);
+        assert_eq!(report.folded_scaling_layers, 1);
+        assert_eq!(report.fused_layers, 3);
+        assert_eq!(finalized.num_layers(), 3);
+        assert_eq!(finalized.num_outputs(), 2);
+
+        for input in [[0.1, -0.4, 0.9], [1.0, 0.0, -1.0], [-0.3, 0.3, 0.5]] {
+            let expected = network.run(&input);
+            let outputs = finalized.run(&input).unwrap();
+            for (a, b) in outputs.iter().zip(&expected) {
+                assert!((a - b).abs() < 1e-12);
+            }
+        }
+        assert!(finalized.run(&[1.0]).is_err());
+
+        let prefetcher = MemoryPrefetcher::default();
+        let input = [0.1, -0.4, 0.9];
+        assert_eq!(
+            finalized.run_prefetched(&input, &prefetcher).unwrap(),
+            finalized.run(&input).unwrap()
+        );
+        assert!(prefetcher.lines_issued() > 0);
+
+        // Plain networks keep one kernel per layer
+        let plain = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build()
+            .finalize()
+            .unwrap();
+        assert_eq!(plain.report().fused_layers, 2);
+        assert_eq!(plain.report().folded_scaling_layers, 0);
+    }
+}
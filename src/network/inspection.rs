@@ -0,0 +1,114 @@
+//! Per-neuron inspection, mirroring libfann's `fann_get_neuron_*` functions
+//!
+//! Neurons are addressed by layer and position within the layer (bias
+//! included), or by FANN's global index via [`Network::neuron_by_index`].
+//! Sums and values reflect the most recent call to [`Network::run`].
+
+use super::{Network, NetworkError};
+use crate::{ActivationFunction, Neuron};
+use num_traits::Float;
+
+impl<T: Float> Network<T> {
+    /// Neuron `index` of layer `layer`
+    pub fn neuron(&self, layer: usize, index: usize) -> Option<&Neuron<T>> {
+        self.layers.get(layer)?.neuron(index)
+    }
+
+    /// Neuron with FANN global index `neuron`
+    pub fn neuron_by_index(&self, neuron: usize) -> Option<&Neuron<T>> {
+        let (layer, index) = self.neuron_position(neuron)?;
+        self.neuron(layer, index)
+    }
+
+    pub fn get_activation_function(
+        &self,
+        layer: usize,
+        index: usize,
+    ) -> Option<ActivationFunction> {
+        self.neuron(layer, index).map(Neuron::activation_function)
+    }
+
+    pub fn get_activation_steepness(&self, layer: usize, index: usize) -> Option<T> {
+        self.neuron(layer, index).map(Neuron::steepness)
+    }
+
+    /// Set the activation function of a single neuron
+    pub fn set_neuron_activation_function(
+        &mut self,
+        layer: usize,
+        index: usize,
+        activation_function: ActivationFunction,
+    ) -> Result<(), NetworkError> {
+        self.regular_neuron_mut(layer, index)?.activation_function = activation_function;
+        Ok(())
+    }
+
+    /// Set the activation steepness of a single neuron
+    pub fn set_neuron_activation_steepness(
+        &mut self,
+        layer: usize,
+        index: usize,
+        steepness: T,
+    ) -> Result<(), NetworkError> {
+        self.regular_neuron_mut(layer, index)?.activation_steepness = steepness;
+        Ok(())
+    }
+
+    /// Bias neurons have a fixed linear activation and cannot be reconfigured
+    fn regular_neuron_mut(
+        &mut self,
+        layer: usize,
+        index: usize,
+    ) -> Result<&mut Neuron<T>, NetworkError> {
+        let total = self.total_neurons();
+        let global = self
+            .neuron_index(layer, index)
+            .ok_or(NetworkError::InvalidLayerConfiguration)?;
+        match self.layers[layer].neuron_mut(index) {
+            Some(neuron) if !neuron.is_bias => Ok(neuron),
+            _ => Err(NetworkError::NeuronIndexOutOfRange {
+                index: global,
+                total,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ActivationFunction, NetworkBuilder};
+
+    #[test]
+    fn test_neuron_inspection() {
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer_with_activation(2, ActivationFunction::Tanh, 0.5)
+            .output_layer(1)
+            .build();
+        network.run(&[0.5, -1.0]);
+
+        let hidden = network.neuron(1, 0).unwrap();
+        assert_eq!(hidden.activation_function(), ActivationFunction::Tanh);
+        assert_eq!(hidden.steepness(), 0.5);
+        assert_eq!(hidden.num_inputs(), 3);
+        assert_eq!(hidden.output_value(), (0.5 * hidden.input_sum()).tanh());
+        assert_eq!(
+            network.layers[1].get_sums()[1],
+            network.neuron_by_index(4).unwrap().input_sum()
+        );
+
+        network
+            .set_neuron_activation_function(2, 0, ActivationFunction::Linear)
+            .unwrap();
+        network.set_neuron_activation_steepness(2, 0, 2.0).unwrap();
+        assert_eq!(
+            network.get_activation_function(2, 0),
+            Some(ActivationFunction::Linear)
+        );
+        assert_eq!(network.get_activation_steepness(2, 0), Some(2.0));
+
+        // The bias neuron of the hidden layer and missing neurons are rejected
+        assert!(network.set_neuron_activation_steepness(1, 2, 1.0).is_err());
+        assert!(network.get_activation_function(5, 0).is_none());
+    }
+}
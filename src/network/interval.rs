@@ -0,0 +1,313 @@
+//! Empirical prediction intervals by input perturbation
+//!
+//! [`Network::predict_with_interval`] draws `n` noisy copies of an input from
+//! a [`NoiseModel`], runs them through the batch inference path of
+//! [`Network::run_batch_with`] and summarizes the spread of each output. The
+//! interval reflects how sensitive the prediction is to input noise of the
+//! given size, not the uncertainty of the weights.
+
+use super::Network;
+use crate::execution::{ExecutionConfig, ExecutionError};
+use crate::numeric::cast;
+use num_traits::Float;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::StandardNormal;
+
+/// Confidence level of [`PredictionInterval::lower`] and
+/// [`PredictionInterval::upper`]
+pub const DEFAULT_CONFIDENCE: f64 = 0.95;
+
+/// Distribution of the noise added to each input
+#[derive(Debug, Clone, PartialEq)]
+pub enum NoiseDistribution<T: Float> {
+    /// Zero-mean Gaussian noise with the given standard deviation per input
+    Gaussian(Vec<T>),
+    /// Uniform noise in `[-w, w]` with half-width `w` per input
+    Uniform(Vec<T>),
+}
+
+/// How inputs are perturbed for [`Network::predict_with_interval`]
+#[derive(Debug, Clone)]
+pub struct NoiseModel<T: Float> {
+    distribution: NoiseDistribution<T>,
+    confidence: f64,
+    seed: Option<u64>,
+    config: ExecutionConfig,
+}
+
+impl<T: Float> NoiseModel<T> {
+    /// Gaussian noise with standard deviation `std_dev` on every input
+    pub fn gaussian(std_dev: T) -> Self {
+        Self::new(NoiseDistribution::Gaussian(vec![std_dev]))
+    }
+
+    /// Uniform noise in `[-half_width, half_width]` on every input
+    pub fn uniform(half_width: T) -> Self {
+        Self::new(NoiseDistribution::Uniform(vec![half_width]))
+    }
+
+    /// Noise with a separate scale for each input, e.g. the measurement
+    /// error of each sensor
+    ///
+    /// A single scale applies to all inputs; otherwise there must be one
+    /// scale per network input.
+    pub fn new(distribution: NoiseDistribution<T>) -> Self {
+        Self {
+            distribution,
+            confidence: DEFAULT_CONFIDENCE,
+            seed: None,
+            config: ExecutionConfig::default(),
+        }
+    }
+
+    /// Two-sided confidence level of the reported bounds, clamped to
+    /// `[0, 1]`
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Draw the noise from a seeded generator
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Threads, batch size and precision used to run the perturbed inputs
+    pub fn with_execution_config(mut self, config: ExecutionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn distribution(&self) -> &NoiseDistribution<T> {
+        &self.distribution
+    }
+
+    pub fn confidence(&self) -> f64 {
+        self.confidence
+    }
+
+    fn scales(&self) -> &[T] {
+        match &self.distribution {
+            NoiseDistribution::Gaussian(scales) | NoiseDistribution::Uniform(scales) => scales,
+        }
+    }
+
+    /// `n` perturbed copies of `input`
+    fn perturb(&self, input: &[T], n: usize) -> Vec<Vec<T>> {
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let scales = self.scales();
+        let scale = |i: usize| scales.get(i).or(scales.first()).copied();
+        (0..n)
+            .map(|_| {
+                input
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &x)| {
+                        let scale = scale(i).unwrap_or_else(T::zero);
+                        let unit: f64 = match self.distribution {
+                            NoiseDistribution::Gaussian(_) => rng.sample(StandardNormal),
+                            NoiseDistribution::Uniform(_) => rng.gen_range(-1.0..=1.0),
+                        };
+                        x + scale * cast(unit)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Per-output summary of the predictions for perturbed inputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredictionInterval<T: Float> {
+    /// Prediction for the unperturbed input
+    pub prediction: Vec<T>,
+    pub mean: Vec<T>,
+    pub std_dev: Vec<T>,
+    /// Lower bound at the noise model's confidence level
+    pub lower: Vec<T>,
+    /// Upper bound at the noise model's confidence level
+    pub upper: Vec<T>,
+    pub confidence: f64,
+    /// Sorted sampled values of each output
+    samples: Vec<Vec<T>>,
+}
+
+impl<T: Float> PredictionInterval<T> {
+    fn from_samples(prediction: Vec<T>, outputs: &[Vec<T>], confidence: f64) -> Self {
+        let n: T = cast(outputs.len());
+        let mut samples: Vec<Vec<T>> = (0..prediction.len())
+            .map(|o| outputs.iter().map(|output| output[o]).collect())
+            .collect();
+        for values in &mut samples {
+            values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        }
+        let mean: Vec<T> = samples
+            .iter()
+            .map(|values| values.iter().fold(T::zero(), |sum, &v| sum + v) / n)
+            .collect();
+        let std_dev = samples
+            .iter()
+            .zip(&mean)
+            .map(|(values, &mean)| {
+                let squares = values
+                    .iter()
+                    .fold(T::zero(), |sum, &v| sum + (v - mean) * (v - mean));
+                (squares / (n - T::one()).max(T::one())).sqrt()
+            })
+            .collect();
+        let mut interval = Self {
+            prediction,
+            mean,
+            std_dev,
+            lower: Vec::new(),
+            upper: Vec::new(),
+            confidence,
+            samples,
+        };
+        (interval.lower, interval.upper) = interval.bounds(confidence);
+        interval
+    }
+
+    /// Number of perturbed inputs the interval is based on
+    pub fn sample_count(&self) -> usize {
+        self.samples.first().map_or(0, Vec::len)
+    }
+
+    /// Empirical quantile `q` in `[0, 1]` of each output, interpolating
+    /// linearly between samples
+    pub fn quantile(&self, q: f64) -> Vec<T> {
+        let q = q.clamp(0.0, 1.0);
+        self.samples
+            .iter()
+            .map(|values| {
+                let position = q * (values.len() - 1) as f64;
+                let (below, fraction) = (position.floor() as usize, position.fract());
+                let above = (below + 1).min(values.len() - 1);
+                values[below] + (values[above] - values[below]) * cast(fraction)
+            })
+            .collect()
+    }
+
+    /// Central interval holding a `confidence` fraction of the samples
+    pub fn bounds(&self, confidence: f64) -> (Vec<T>, Vec<T>) {
+        let tail = (1.0 - confidence.clamp(0.0, 1.0)) / 2.0;
+        (self.quantile(tail), self.quantile(1.0 - tail))
+    }
+
+    /// Whether every output of `output` lies within the bounds
+    pub fn contains(&self, output: &[T]) -> bool {
+        output.len() == self.lower.len()
+            && output
+                .iter()
+                .zip(self.lower.iter().zip(&self.upper))
+                .all(|(&v, (&lower, &upper))| lower <= v && v <= upper)
+    }
+}
+
+impl<T: Float + Send + Sync> Network<T> {
+    /// Prediction for `input` with an empirical interval from `n` perturbed
+    /// copies of it
+    ///
+    /// ```
+    /// use do_fann::network::NoiseModel;
+    /// use do_fann::NetworkBuilder;
+    ///
+    /// let network = NetworkBuilder::<f64>::new()
+    ///     .input_layer(2)
+    ///     .hidden_layer(4)
+    ///     .output_layer(1)
+    ///     .build();
+    /// let noise = NoiseModel::gaussian(0.05).with_seed(7).with_confidence(0.9);
+    /// let interval = network.predict_with_interval(&[0.3, 0.6], &noise, 200)?;
+    /// assert!(interval.lower[0] <= interval.mean[0] && interval.mean[0] <= interval.upper[0]);
+    /// # Ok::<(), do_fann::ExecutionError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails when `input` or a per-input noise scale list does not match the
+    /// network's input count, or the execution config cannot be applied.
+    pub fn predict_with_interval(
+        &self,
+        input: &[T],
+        noise_model: &NoiseModel<T>,
+        n: usize,
+    ) -> Result<PredictionInterval<T>, ExecutionError> {
+        let scales = noise_model.scales().len();
+        if scales > 1 && scales != self.num_inputs() {
+            return Err(ExecutionError::InputSizeMismatch {
+                expected: self.num_inputs(),
+                actual: scales,
+            });
+        }
+        let mut batch = vec![input.to_vec()];
+        batch.extend(noise_model.perturb(input, n.max(1)));
+        let mut outputs = self.run_batch_with(&batch, &noise_model.config)?;
+        let prediction = outputs.remove(0);
+        Ok(PredictionInterval::from_samples(
+            prediction,
+            &outputs,
+            noise_model.confidence,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    fn network() -> Network<f64> {
+        NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .output_layer(2)
+            .build()
+    }
+
+    #[test]
+    fn test_interval_widens_with_noise() {
+        let network = network();
+        let input = [0.2, 0.7];
+        let width = |noise: NoiseModel<f64>| {
+            let interval = network
+                .predict_with_interval(&input, &noise.with_seed(3), 500)
+                .unwrap();
+            assert_eq!(interval.sample_count(), 500);
+            assert!(interval.contains(&interval.quantile(0.5)));
+            interval.upper[0] - interval.lower[0]
+        };
+
+        assert_eq!(width(NoiseModel::gaussian(0.0)), 0.0);
+        assert!(width(NoiseModel::gaussian(0.5)) > width(NoiseModel::gaussian(0.05)));
+        assert!(width(NoiseModel::uniform(0.5)) > 0.0);
+
+        let noise = NoiseModel::gaussian(0.1).with_seed(9);
+        assert_eq!(
+            network.predict_with_interval(&input, &noise, 50).unwrap(),
+            network.predict_with_interval(&input, &noise, 50).unwrap()
+        );
+        let interval = network.predict_with_interval(&input, &noise, 50).unwrap();
+        assert_eq!(interval.prediction, network.clone().run(&input));
+        let (lower, upper) = interval.bounds(0.5);
+        assert!(lower[1] >= interval.lower[1] && upper[1] <= interval.upper[1]);
+    }
+
+    #[test]
+    fn test_rejects_mismatched_sizes() {
+        let network = network();
+        let per_input = NoiseModel::new(NoiseDistribution::Gaussian(vec![0.1, 0.2, 0.3]));
+        assert!(matches!(
+            network.predict_with_interval(&[0.0, 0.0], &per_input, 10),
+            Err(ExecutionError::InputSizeMismatch { actual: 3, .. })
+        ));
+        assert!(network
+            .predict_with_interval(&[0.0], &NoiseModel::gaussian(0.1), 10)
+            .is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Decoding of [`Network::to_bytes`] output written before the format was
+//! versioned
+//!
+//! Those blobs are plain bincode of a network whose layers held only their
+//! neurons and which had no schema. Bincode is not self-describing, so they
+//! have to be decoded with that layout and upgraded.
+
+use super::{Network, NetworkError, NetworkSchema};
+use crate::{Layer, Neuron};
+use num_traits::Float;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct LegacyNetwork<T: Float> {
+    layers: Vec<LegacyLayer<T>>,
+    connection_rate: T,
+}
+
+#[derive(Deserialize)]
+struct LegacyLayer<T: Float> {
+    neurons: Vec<Neuron<T>>,
+}
+
+/// Decode an unversioned blob
+pub(super) fn from_legacy_bytes<T>(bytes: &[u8]) -> Result<Network<T>, NetworkError>
+where
+    T: Float + serde::de::DeserializeOwned,
+{
+    let legacy: LegacyNetwork<T> =
+        bincode::deserialize(bytes).map_err(|_| NetworkError::InvalidLayerConfiguration)?;
+    let layers = legacy
+        .layers
+        .into_iter()
+        .map(|layer| Layer::from_neurons(layer.neurons))
+        .collect();
+    Ok(Network {
+        layers,
+        connection_rate: legacy.connection_rate,
+        schema: NetworkSchema::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Network;
+
+    /// A 2-2-1 network serialized by `to_bytes` before the format was
+    /// versioned
+    const V0: &[u8] = include_bytes!("testdata/network_v0.bincode");
+
+    #[test]
+    fn test_from_legacy_bytes() {
+        let mut network = Network::<f64>::from_bytes(V0).unwrap();
+        assert_eq!(network.num_inputs(), 2);
+        assert_eq!(network.num_outputs(), 1);
+        assert!(network.layers.iter().all(|l| l.dropout == 0.0));
+        assert!(!network.is_recurrent());
+        let output = network.run(&[0.5, -0.5])[0];
+        assert!((output - 0.8281723859109797).abs() < 1e-12);
+
+        // Saved again, it uses the current format and keeps its weights
+        let mut restored = Network::<f64>::from_bytes(&network.to_bytes()).unwrap();
+        assert_eq!(restored.run(&[0.5, -0.5])[0], output);
+        assert!(Network::<f64>::from_bytes(&V0[..V0.len() - 1]).is_err());
+    }
+}
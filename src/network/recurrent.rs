@@ -0,0 +1,162 @@
+//! Stateful execution of recurrent networks
+//!
+//! Layers built with [`NetworkBuilder::elman_layer`] or
+//! [`NetworkBuilder::jordan_layer`] carry a [`Recurrence`] whose context
+//! holds values from the previous time step: the layer's own outputs
+//! (Elman) or the network outputs (Jordan). Every [`Network::run`] is one
+//! time step and updates the contexts afterwards, so a sequence is fed one
+//! element per call, or all at once with [`Network::run_sequence`].
+//!
+//! Recurrent networks are trained with [`Bptt`](crate::training::Bptt).
+//! Serde serialization and [`Network::to_bytes`] keep the context weights.
+//! Everything that would drop them fails with [`NetworkError::Recurrent`] or
+//! an error wrapping it instead: the other optimizers, [`Network::finalize`],
+//! neuron removal and the FANN, model file, NPZ and PMML writers.
+//!
+//! [`NetworkBuilder::elman_layer`]: super::NetworkBuilder::elman_layer
+//! [`NetworkBuilder::jordan_layer`]: super::NetworkBuilder::jordan_layer
+//! [`Recurrence`]: crate::Recurrence
+
+use super::{Network, NetworkError};
+use crate::RecurrenceKind;
+use num_traits::Float;
+
+impl<T: Float> Network<T> {
+    /// Whether any layer feeds back context from the previous time step
+    pub fn is_recurrent(&self) -> bool {
+        self.layers.iter().any(|layer| layer.recurrence.is_some())
+    }
+
+    /// Fail with [`NetworkError::Recurrent`] for recurrent networks
+    pub(crate) fn check_feed_forward(&self) -> Result<(), NetworkError> {
+        if self.is_recurrent() {
+            return Err(NetworkError::Recurrent);
+        }
+        Ok(())
+    }
+
+    /// Clear all recurrent context, as at the start of a new sequence
+    pub fn reset_state(&mut self) {
+        for recurrence in self.layers.iter_mut().filter_map(|l| l.recurrence.as_mut()) {
+            recurrence.reset();
+        }
+    }
+
+    /// Run `inputs` as consecutive time steps and return the output of
+    /// each step
+    ///
+    /// The state carries over from earlier calls; call
+    /// [`Network::reset_state`] first to start a fresh sequence.
+    pub fn run_sequence(&mut self, inputs: &[Vec<T>]) -> Vec<Vec<T>> {
+        inputs.iter().map(|input| self.run(input)).collect()
+    }
+
+    /// Copy the values of the step just computed into the contexts
+    pub(crate) fn advance_state(&mut self) {
+        if !self.is_recurrent() {
+            return;
+        }
+        let outputs: Vec<T> = match self.layers.last() {
+            Some(layer) => layer.get_outputs()[..layer.num_regular_neurons()].to_vec(),
+            None => return,
+        };
+        for layer in &mut self.layers {
+            let values = match layer.recurrence.as_ref().map(|r| r.kind) {
+                Some(RecurrenceKind::Elman) => {
+                    layer.get_outputs()[..layer.num_regular_neurons()].to_vec()
+                }
+                Some(RecurrenceKind::Jordan) => outputs.clone(),
+                None => continue,
+            };
+            if let Some(recurrence) = layer.recurrence.as_mut() {
+                for (context, value) in recurrence.context.iter_mut().zip(values) {
+                    *context = value;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::training::{Adam, Bptt, TrainingAlgorithm, TrainingData, TrainingError};
+    use crate::{NetworkBuilder, NetworkError, RecurrenceKind};
+
+    #[test]
+    fn test_elman_and_jordan_state() {
+        for kind in [RecurrenceKind::Elman, RecurrenceKind::Jordan] {
+            let builder = NetworkBuilder::<f64>::new().input_layer(1);
+            let builder = match kind {
+                RecurrenceKind::Elman => builder.elman_layer(3),
+                RecurrenceKind::Jordan => builder.jordan_layer(3),
+            };
+            let mut network = builder.output_layer(2).build();
+            assert!(network.is_recurrent());
+            let recurrence = network.layers[1].recurrence.as_mut().unwrap();
+            assert_eq!(recurrence.kind, kind);
+            let context = match kind {
+                RecurrenceKind::Elman => 3,
+                RecurrenceKind::Jordan => 2,
+            };
+            assert_eq!(recurrence.context.len(), context);
+            for row in &mut recurrence.weights {
+                row.iter_mut().for_each(|w| *w = 0.5);
+            }
+
+            // The same input gives different outputs as the state builds up
+            let inputs = vec![vec![1.0]; 3];
+            let first = network.run_sequence(&inputs);
+            assert_ne!(first[0], first[1]);
+            network.reset_state();
+            assert_eq!(network.run_sequence(&inputs), first);
+        }
+
+        let mut plain = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .hidden_layer(2)
+            .output_layer(1)
+            .build();
+        assert!(!plain.is_recurrent());
+        let outputs = plain.run_sequence(&[vec![1.0], vec![1.0]]);
+        assert_eq!(outputs[0], outputs[1]);
+    }
+
+    #[test]
+    fn test_seeded_context_and_rejected_operations() {
+        let build = || {
+            NetworkBuilder::<f64>::new()
+                .input_layer(1)
+                .elman_layer(3)
+                .output_layer(1)
+                .seed(11)
+                .build()
+        };
+        let (mut network, other) = (build(), build());
+        let context = |n: &crate::Network<f64>| n.layers[1].recurrence.clone().unwrap().weights;
+        assert_eq!(context(&network), context(&other));
+        assert_eq!(network.get_weights(), other.get_weights());
+
+        // Everything that would drop the context weights refuses
+        assert!(matches!(network.finalize(), Err(NetworkError::Recurrent)));
+        assert!(network.eliminate_dead_neurons().is_err());
+        assert!(network.remove_hidden_neuron(1, 0, None).is_err());
+        #[cfg(feature = "io")]
+        {
+            assert!(network.write_npz(&mut Vec::new()).is_err());
+            let mut fann = Vec::new();
+            assert!(crate::io::FannWriter::new()
+                .write_network(&network, &mut fann)
+                .is_err());
+            assert!(fann.is_empty());
+        }
+        let data = TrainingData {
+            inputs: vec![vec![0.5], vec![-0.5]],
+            outputs: vec![vec![1.0], vec![0.0]],
+        };
+        assert!(matches!(
+            Adam::new(0.01).train_epoch(&mut network, &data),
+            Err(TrainingError::NetworkError(_))
+        ));
+        assert!(Bptt::new(0.1).train_epoch(&mut network, &data).is_ok());
+    }
+}
@@ -0,0 +1,321 @@
+//! Named inputs and outputs
+//!
+//! A [`NetworkSchema`] travels with the network through serialization, so a
+//! deployed model documents which column each input expects. [`Network::run_named`]
+//! feeds inputs by name, which protects callers from column-order mistakes.
+//! Optional per-input ranges, usually fitted from the training data, let
+//! [`Network::run_checked`] reject inputs far outside what the network saw.
+
+use super::{Network, NetworkError};
+use crate::errors::ValidationError;
+use crate::TrainingData;
+use num_traits::Float;
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Valid range of one input, inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InputRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl InputRange {
+    pub fn new(min: f64, max: f64) -> Self {
+        Self { min, max }
+    }
+
+    /// Whether `value` lies within the range widened by `tolerance` times
+    /// its width on each side
+    pub fn contains(&self, value: f64, tolerance: f64) -> bool {
+        let margin = (self.max - self.min) * tolerance;
+        value >= self.min - margin && value <= self.max + margin
+    }
+}
+
+/// Names of a network's inputs and outputs, plus optional input ranges;
+/// empty lists mean unnamed or unchecked
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NetworkSchema {
+    pub input_names: Vec<String>,
+    pub output_names: Vec<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub input_ranges: Vec<InputRange>,
+}
+
+impl NetworkSchema {
+    /// Name of input `index`, falling back to `input_<index>`
+    pub fn input_name(&self, index: usize) -> String {
+        self.input_names
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| format!("input_{index}"))
+    }
+
+    /// Name of output `index`, falling back to `output_<index>`
+    pub fn output_name(&self, index: usize) -> String {
+        self.output_names
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| format!("output_{index}"))
+    }
+}
+
+fn check_names(names: &[String], expected: usize) -> Result<(), NetworkError> {
+    if names.len() != expected {
+        return Err(NetworkError::SchemaMismatch {
+            expected,
+            actual: names.len(),
+        });
+    }
+    let mut seen = std::collections::HashSet::new();
+    if let Some(duplicate) = names.iter().find(|name| !seen.insert(name.as_str())) {
+        return Err(NetworkError::DuplicateName(duplicate.clone()));
+    }
+    Ok(())
+}
+
+impl<T: Float> Network<T> {
+    pub fn schema(&self) -> &NetworkSchema {
+        &self.schema
+    }
+
+    /// Name the inputs; there must be one unique name per input
+    pub fn set_input_names<S: Into<String>>(
+        &mut self,
+        names: impl IntoIterator<Item = S>,
+    ) -> Result<(), NetworkError> {
+        let names: Vec<String> = names.into_iter().map(Into::into).collect();
+        check_names(&names, self.num_inputs())?;
+        self.schema.input_names = names;
+        Ok(())
+    }
+
+    /// Name the outputs; there must be one unique name per output
+    pub fn set_output_names<S: Into<String>>(
+        &mut self,
+        names: impl IntoIterator<Item = S>,
+    ) -> Result<(), NetworkError> {
+        let names: Vec<String> = names.into_iter().map(Into::into).collect();
+        check_names(&names, self.num_outputs())?;
+        self.schema.output_names = names;
+        Ok(())
+    }
+
+    /// Set the valid range of every input; there must be one per input
+    pub fn set_input_ranges(&mut self, ranges: Vec<InputRange>) -> Result<(), NetworkError> {
+        if ranges.len() != self.num_inputs() {
+            return Err(NetworkError::SchemaMismatch {
+                expected: self.num_inputs(),
+                actual: ranges.len(),
+            });
+        }
+        self.schema.input_ranges = ranges;
+        Ok(())
+    }
+
+    /// Record the observed minimum and maximum of every input in `data`
+    pub fn fit_input_ranges(&mut self, data: &TrainingData<T>) -> Result<(), NetworkError> {
+        let num_inputs = self.num_inputs();
+        let mut ranges = vec![InputRange::new(f64::INFINITY, f64::NEG_INFINITY); num_inputs];
+        for sample in &data.inputs {
+            if sample.len() != num_inputs {
+                return Err(NetworkError::InputSizeMismatch {
+                    expected: num_inputs,
+                    actual: sample.len(),
+                });
+            }
+            for (range, value) in ranges.iter_mut().zip(sample) {
+                let value = value.to_f64().unwrap_or(f64::NAN);
+                if value.is_finite() {
+                    range.min = range.min.min(value);
+                    range.max = range.max.max(value);
+                }
+            }
+        }
+        // An input without a single finite value has no usable range
+        if let Some(i) = ranges.iter().position(|range| range.min > range.max) {
+            return Err(NetworkError::MissingInput(self.schema.input_name(i)));
+        }
+        self.set_input_ranges(ranges)
+    }
+
+    /// Run the network after checking the inputs against the stored ranges
+    ///
+    /// Each input may exceed its range by `tolerance` times the range width
+    /// on either side, so `0.0` enforces the training-data range exactly.
+    /// Non-finite inputs are always rejected. Without stored ranges only the
+    /// input count and finiteness are checked.
+    pub fn run_checked(&mut self, input: &[T], tolerance: f64) -> Result<Vec<T>, ValidationError> {
+        if input.len() != self.num_inputs() {
+            return Err(ValidationError::DataFormat {
+                message: format!("expected {} inputs, got {}", self.num_inputs(), input.len()),
+            });
+        }
+        for (i, value) in input.iter().enumerate() {
+            let value = value.to_f64().unwrap_or(f64::NAN);
+            let range = self.schema.input_ranges.get(i);
+            let valid = value.is_finite() && range.map_or(true, |r| r.contains(value, tolerance));
+            if !valid {
+                let (min, max) = range.map_or((f64::MIN, f64::MAX), |r| (r.min, r.max));
+                return Err(ValidationError::OutOfRange {
+                    parameter: self.schema.input_name(i),
+                    value,
+                    min,
+                    max,
+                });
+            }
+        }
+        Ok(self.run(input))
+    }
+
+    /// Run the network with inputs given by name
+    ///
+    /// Every named input must be present and no unknown names are accepted.
+    /// Outputs are keyed by their names, or `output_<index>` when unnamed.
+    pub fn run_named(
+        &mut self,
+        inputs: &HashMap<String, T>,
+    ) -> Result<HashMap<String, T>, NetworkError> {
+        if self.schema.input_names.is_empty() {
+            return Err(NetworkError::SchemaMismatch {
+                expected: self.num_inputs(),
+                actual: 0,
+            });
+        }
+        if let Some(unknown) = inputs
+            .keys()
+            .find(|name| !self.schema.input_names.contains(name))
+        {
+            return Err(NetworkError::UnknownInput(unknown.clone()));
+        }
+        let values = self
+            .schema
+            .input_names
+            .iter()
+            .map(|name| {
+                inputs
+                    .get(name)
+                    .copied()
+                    .ok_or_else(|| NetworkError::MissingInput(name.clone()))
+            })
+            .collect::<Result<Vec<T>, _>>()?;
+
+        let outputs = self.run(&values);
+        Ok(outputs
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (self.schema.output_name(i), value))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InputRange;
+    use crate::errors::ValidationError;
+    use crate::{NetworkBuilder, NetworkError, TrainingData};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_run_named() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .input_names(["temperature", "pressure"])
+            .output_names(["risk"])
+            .build();
+
+        let inputs = HashMap::from([
+            ("pressure".to_string(), 0.2),
+            ("temperature".to_string(), 0.9),
+        ]);
+        let outputs = network.run_named(&inputs).unwrap();
+        assert_eq!(outputs["risk"], network.run(&[0.9, 0.2])[0]);
+
+        let missing = HashMap::from([("temperature".to_string(), 0.9)]);
+        assert!(matches!(
+            network.run_named(&missing),
+            Err(NetworkError::MissingInput(name)) if name == "pressure"
+        ));
+        let mut unknown = inputs.clone();
+        unknown.insert("humidity".to_string(), 0.5);
+        assert!(matches!(
+            network.run_named(&unknown),
+            Err(NetworkError::UnknownInput(name)) if name == "humidity"
+        ));
+    }
+
+    #[test]
+    fn test_names_are_validated_and_serialized() {
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        assert!(network.set_input_names(["a"]).is_err());
+        assert!(network.set_input_names(["a", "a"]).is_err());
+        network.set_input_names(["a", "b"]).unwrap();
+
+        let builder = || NetworkBuilder::<f32>::new().input_layer(2).output_layer(1);
+        assert!(matches!(
+            builder().input_names(["a", "a"]).try_build(),
+            Err(NetworkError::DuplicateName(name)) if name == "a"
+        ));
+        assert!(matches!(
+            builder().output_names(["x", "y"]).try_build(),
+            Err(NetworkError::SchemaMismatch {
+                expected: 1,
+                actual: 2
+            })
+        ));
+        assert!(matches!(
+            NetworkBuilder::<f32>::new().try_build(),
+            Err(NetworkError::NoLayers)
+        ));
+
+        #[cfg(all(feature = "binary", feature = "serde"))]
+        {
+            let restored = crate::Network::<f32>::from_bytes(&network.to_bytes()).unwrap();
+            assert_eq!(restored.schema().input_names, vec!["a", "b"]);
+        }
+    }
+
+    #[test]
+    fn test_run_checked_against_fitted_ranges() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .input_names(["age", "income"])
+            .build();
+        let data = TrainingData {
+            inputs: vec![vec![20.0, 1.0], vec![60.0, 3.0], vec![40.0, 2.0]],
+            outputs: vec![vec![0.0], vec![1.0], vec![0.5]],
+        };
+        network.fit_input_ranges(&data).unwrap();
+        assert_eq!(
+            network.schema().input_ranges[0],
+            InputRange::new(20.0, 60.0)
+        );
+
+        assert!(network.run_checked(&[65.0, 2.0], 0.5).is_ok());
+        assert!(matches!(
+            network.run_checked(&[65.0, 2.0], 0.0),
+            Err(ValidationError::OutOfRange { parameter, .. }) if parameter == "age"
+        ));
+        assert!(matches!(
+            network.run_checked(&[30.0, 2000.0], 0.5),
+            Err(ValidationError::OutOfRange { parameter, value, .. })
+                if parameter == "income" && value == 2000.0
+        ));
+        assert!(network.run_checked(&[30.0, f64::NAN], 0.5).is_err());
+        assert!(matches!(
+            network.run_checked(&[30.0], 0.5),
+            Err(ValidationError::DataFormat { .. })
+        ));
+    }
+}
@@ -0,0 +1,289 @@
+//! Structural validation of hand-built networks
+//!
+//! Networks assembled connection by connection can end up with connections to
+//! neurons that do not exist, neurons that are never used, or missing bias
+//! inputs. [`Network::validate_topology`] walks the whole graph and reports
+//! every problem it finds, each tagged with a [`NetworkErrorCategory`].
+
+use super::Network;
+use crate::errors::{NetworkErrorCategory, RuvFannError};
+use num_traits::Float;
+use std::collections::HashSet;
+
+/// A structural problem found by [`Network::validate_topology`]
+///
+/// Neurons are identified by layer and position within the layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopologyIssue {
+    /// The network has no layers, or only an input layer
+    TooFewLayers { layers: usize },
+    /// A connection refers to a source neuron that does not exist in the
+    /// previous layer; it does not count as a use of any neuron
+    DanglingConnection {
+        layer: usize,
+        neuron: usize,
+        source: usize,
+    },
+    /// The same source is connected to a neuron more than once
+    DuplicateConnection {
+        layer: usize,
+        neuron: usize,
+        source: usize,
+    },
+    /// A bias neuron has incoming connections, which are never evaluated
+    BiasWithInputs { layer: usize, neuron: usize },
+    /// A bias neuron is not the last neuron of its layer
+    MisplacedBias { layer: usize, neuron: usize },
+    /// A neuron outside the input layer has no incoming connections
+    NoInputs { layer: usize, neuron: usize },
+    /// A neuron outside the output layer feeds no other neuron
+    NoOutputs { layer: usize, neuron: usize },
+    /// A neuron of a fully connected network is not connected to the bias of
+    /// the previous layer
+    MissingBiasConnection { layer: usize, neuron: usize },
+}
+
+impl TopologyIssue {
+    pub fn category(&self) -> NetworkErrorCategory {
+        match self {
+            Self::TooFewLayers { .. } => NetworkErrorCategory::Layers,
+            Self::BiasWithInputs { .. }
+            | Self::MisplacedBias { .. }
+            | Self::MissingBiasConnection { .. } => NetworkErrorCategory::Weights,
+            Self::NoInputs { .. } | Self::NoOutputs { .. } => NetworkErrorCategory::Topology,
+            Self::DanglingConnection { .. } | Self::DuplicateConnection { .. } => {
+                NetworkErrorCategory::Connections
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for TopologyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooFewLayers { layers } => {
+                write!(f, "network needs at least 2 layers, has {layers}")
+            }
+            Self::DanglingConnection {
+                layer,
+                neuron,
+                source,
+            } => write!(
+                f,
+                "neuron {neuron} in layer {layer} is connected to missing neuron {source} of layer {}",
+                layer - 1
+            ),
+            Self::DuplicateConnection {
+                layer,
+                neuron,
+                source,
+            } => write!(
+                f,
+                "neuron {neuron} in layer {layer} is connected to neuron {source} more than once"
+            ),
+            Self::BiasWithInputs { layer, neuron } => {
+                write!(f, "bias neuron {neuron} in layer {layer} has inputs")
+            }
+            Self::MisplacedBias { layer, neuron } => write!(
+                f,
+                "bias neuron {neuron} in layer {layer} is not the last neuron of its layer"
+            ),
+            Self::NoInputs { layer, neuron } => {
+                write!(f, "neuron {neuron} in layer {layer} has no inputs")
+            }
+            Self::NoOutputs { layer, neuron } => {
+                write!(f, "neuron {neuron} in layer {layer} feeds no other neuron")
+            }
+            Self::MissingBiasConnection { layer, neuron } => write!(
+                f,
+                "neuron {neuron} in layer {layer} is not connected to the bias of layer {}",
+                layer - 1
+            ),
+        }
+    }
+}
+
+impl From<TopologyIssue> for RuvFannError {
+    fn from(issue: TopologyIssue) -> Self {
+        RuvFannError::Network {
+            category: issue.category(),
+            message: issue.to_string(),
+            context: None,
+        }
+    }
+}
+
+impl<T: Float> Network<T> {
+    /// Check the network graph and report every structural problem
+    ///
+    /// Missing bias connections are only reported for fully connected
+    /// networks (`connection_rate >= 1`), since sparse networks drop
+    /// connections, bias ones included, at random. Connections only run from
+    /// one layer to the next, so the graph cannot contain a cycle.
+    pub fn validate_topology(&self) -> Result<(), Vec<TopologyIssue>> {
+        let mut issues = Vec::new();
+        if self.layers.len() < 2 {
+            issues.push(TopologyIssue::TooFewLayers {
+                layers: self.layers.len(),
+            });
+            return Err(issues);
+        }
+
+        let last_layer = self.layers.len() - 1;
+        let fully_connected = self.connection_rate >= T::one();
+        let mut used: Vec<Vec<bool>> = self
+            .layers
+            .iter()
+            .map(|layer| vec![false; layer.size()])
+            .collect();
+
+        for (layer_index, layer) in self.layers.iter().enumerate() {
+            let bias_count = layer.neurons.iter().filter(|n| n.is_bias).count();
+            for (neuron_index, neuron) in layer.neurons.iter().enumerate() {
+                if neuron.is_bias {
+                    if neuron_index + 1 != layer.size() || bias_count > 1 {
+                        issues.push(TopologyIssue::MisplacedBias {
+                            layer: layer_index,
+                            neuron: neuron_index,
+                        });
+                    }
+                    if !neuron.connections.is_empty() {
+                        issues.push(TopologyIssue::BiasWithInputs {
+                            layer: layer_index,
+                            neuron: neuron_index,
+                        });
+                    }
+                    continue;
+                }
+                if layer_index == 0 {
+                    continue;
+                }
+
+                let previous = &self.layers[layer_index - 1];
+                if neuron.connections.is_empty() {
+                    issues.push(TopologyIssue::NoInputs {
+                        layer: layer_index,
+                        neuron: neuron_index,
+                    });
+                }
+
+                let mut seen = HashSet::new();
+                for connection in &neuron.connections {
+                    let source = connection.from_neuron;
+                    if source >= previous.size() {
+                        issues.push(TopologyIssue::DanglingConnection {
+                            layer: layer_index,
+                            neuron: neuron_index,
+                            source,
+                        });
+                    } else if !seen.insert(source) {
+                        issues.push(TopologyIssue::DuplicateConnection {
+                            layer: layer_index,
+                            neuron: neuron_index,
+                            source,
+                        });
+                    } else {
+                        used[layer_index - 1][source] = true;
+                    }
+                }
+
+                if fully_connected && previous.has_bias() && !seen.contains(&(previous.size() - 1))
+                {
+                    issues.push(TopologyIssue::MissingBiasConnection {
+                        layer: layer_index,
+                        neuron: neuron_index,
+                    });
+                }
+            }
+        }
+
+        for (layer_index, layer) in self.layers.iter().enumerate().take(last_layer) {
+            for (neuron_index, neuron) in layer.neurons.iter().enumerate() {
+                if !neuron.is_bias && !used[layer_index][neuron_index] {
+                    issues.push(TopologyIssue::NoOutputs {
+                        layer: layer_index,
+                        neuron: neuron_index,
+                    });
+                }
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_valid_network_passes() {
+        let network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        assert_eq!(network.validate_topology(), Ok(()));
+    }
+
+    #[test]
+    fn test_reports_structural_problems() {
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(2)
+            .output_layer(1)
+            .build();
+
+        // Hidden neuron 0 loses its inputs, hidden neuron 1 gets a dangling
+        // connection (whose global index would be the neuron itself) and a
+        // duplicate one, and nothing reads input 1 any more
+        network.layers[1].neurons[0].connections.clear();
+        let hidden = &mut network.layers[1].neurons[1];
+        hidden.connections.retain(|c| c.from_neuron != 1);
+        hidden.add_connection(4, 0.1);
+        hidden.add_connection(0, 0.1);
+
+        let issues = network.validate_topology().unwrap_err();
+        assert_eq!(
+            issues,
+            [
+                TopologyIssue::NoInputs {
+                    layer: 1,
+                    neuron: 0
+                },
+                TopologyIssue::MissingBiasConnection {
+                    layer: 1,
+                    neuron: 0
+                },
+                TopologyIssue::DanglingConnection {
+                    layer: 1,
+                    neuron: 1,
+                    source: 4
+                },
+                TopologyIssue::DuplicateConnection {
+                    layer: 1,
+                    neuron: 1,
+                    source: 0
+                },
+                TopologyIssue::NoOutputs {
+                    layer: 0,
+                    neuron: 1
+                },
+            ]
+        );
+
+        let error = RuvFannError::from(issues[0].clone());
+        assert!(matches!(
+            error,
+            RuvFannError::Network {
+                category: NetworkErrorCategory::Topology,
+                ..
+            }
+        ));
+    }
+}
@@ -97,13 +97,19 @@ impl<T: Float> Neuron<T> {
     /// # Arguments
     /// * `inputs` - Values from neurons in the previous layer
     pub fn calculate(&mut self, inputs: &[T]) {
+        self.calculate_with_feedback(inputs, T::zero());
+    }
+
+    /// Like [`Neuron::calculate`], adding `feedback` from recurrent context
+    /// to the weighted sum
+    pub(crate) fn calculate_with_feedback(&mut self, inputs: &[T], feedback: T) {
         if self.is_bias {
             // Bias neurons always output 1.0
             return;
         }
 
         // Calculate weighted sum
-        self.sum = T::zero();
+        self.sum = feedback;
         for connection in &self.connections {
             if connection.from_neuron < inputs.len() {
                 self.sum = self.sum + inputs[connection.from_neuron] * connection.weight;
@@ -116,71 +122,27 @@ impl<T: Float> Neuron<T> {
 
     /// Apply the activation function to the given input
     fn apply_activation_function(&self, x: T) -> T {
-        match self.activation_function {
-            ActivationFunction::Linear => x * self.activation_steepness,
-            ActivationFunction::Sigmoid => {
-                let exp_val = (-self.activation_steepness * x).exp();
-                T::one() / (T::one() + exp_val)
-            }
-            ActivationFunction::ReLU => {
-                if x > T::zero() {
-                    x
-                } else {
-                    T::zero()
-                }
-            }
-            ActivationFunction::ReLULeaky => {
-                let alpha = T::from(0.01).unwrap_or(T::zero());
-                if x > T::zero() {
-                    x
-                } else {
-                    alpha * x
-                }
-            }
-            ActivationFunction::Tanh => (self.activation_steepness * x).tanh(),
-            ActivationFunction::SigmoidSymmetric => (self.activation_steepness * x).tanh(),
-            ActivationFunction::Gaussian => {
-                let x_scaled = x * self.activation_steepness;
-                (-x_scaled * x_scaled).exp()
-            }
-            _ => x, // Fallback for other functions
-        }
+        self.activation_function
+            .activate(x * self.steepness_factor())
     }
 
     /// Calculate the derivative of the activation function at the current value
     pub fn activation_derivative(&self) -> T {
-        match self.activation_function {
-            ActivationFunction::Linear => self.activation_steepness,
-            ActivationFunction::Sigmoid => {
-                // For sigmoid: f'(x) = f(x) * (1 - f(x)) * steepness
-                self.value * (T::one() - self.value) * self.activation_steepness
-            }
-            ActivationFunction::ReLU => {
-                if self.sum > T::zero() {
-                    T::one()
-                } else {
-                    T::zero()
-                }
-            }
-            ActivationFunction::ReLULeaky => {
-                let alpha = T::from(0.01).unwrap_or(T::zero());
-                if self.sum > T::zero() {
-                    T::one()
-                } else {
-                    alpha
-                }
-            }
-            ActivationFunction::Tanh | ActivationFunction::SigmoidSymmetric => {
-                // For tanh: f'(x) = (1 - f(x)²) * steepness
-                (T::one() - self.value * self.value) * self.activation_steepness
-            }
-            ActivationFunction::Gaussian => {
-                // For gaussian: f'(x) = -2 * steepness² * x * f(x)
-                let x_scaled = self.sum * self.activation_steepness;
-                let neg_two = T::from(-2.0).unwrap_or(T::zero());
-                neg_two * self.activation_steepness * x_scaled * self.value
-            }
-            _ => T::one(), // Fallback
+        self.derivative_at(self.sum, self.value)
+    }
+
+    /// Derivative of the activation function for a stored `sum` and `value`
+    pub(crate) fn derivative_at(&self, sum: T, value: T) -> T {
+        let steepness = self.steepness_factor();
+        self.activation_function.derivative(sum * steepness, value) * steepness
+    }
+
+    /// Factor applied to the input sum before activation
+    fn steepness_factor(&self) -> T {
+        if self.activation_function.uses_steepness() {
+            self.activation_steepness
+        } else {
+            T::one()
         }
     }
 
@@ -192,6 +154,29 @@ impl<T: Float> Neuron<T> {
         }
     }
 
+    /// Weighted input sum from the most recent forward pass
+    pub fn input_sum(&self) -> T {
+        self.sum
+    }
+
+    /// Output value from the most recent forward pass
+    pub fn output_value(&self) -> T {
+        self.value
+    }
+
+    pub fn activation_function(&self) -> ActivationFunction {
+        self.activation_function
+    }
+
+    pub fn steepness(&self) -> T {
+        self.activation_steepness
+    }
+
+    /// Number of incoming connections
+    pub fn num_inputs(&self) -> usize {
+        self.connections.len()
+    }
+
     /// Gets the weight of a specific connection by index
     pub fn get_connection_weight(&self, index: usize) -> Option<T> {
         self.connections.get(index).map(|c| c.weight)
@@ -0,0 +1,12 @@
+//! Numeric conversions that cannot panic
+
+use num_traits::{Float, ToPrimitive};
+
+/// Convert a primitive number to `T`
+///
+/// Conversions into `f32` and `f64` always succeed. A custom [`Float`] that
+/// cannot represent `n` gets NaN, which training reports through its error
+/// values, rather than a panic inside library code.
+pub(crate) fn cast<T: Float>(n: impl ToPrimitive) -> T {
+    T::from(n).unwrap_or_else(T::nan)
+}
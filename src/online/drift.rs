@@ -0,0 +1,308 @@
+//! Concept-drift detection over a stream of prediction errors
+
+use crate::numeric::cast;
+use num_traits::Float;
+use std::collections::VecDeque;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Outcome of feeding one observation to a drift detector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriftStatus {
+    Stable,
+    /// The statistic is approaching the drift threshold
+    Warning,
+    /// A change in the error distribution was detected
+    Drift,
+}
+
+/// Detected drift, passed to [`DriftCallback`]s
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriftEvent<T: Float> {
+    /// Name of the detector that fired
+    pub detector: &'static str,
+    /// Index of the observation that triggered the detection
+    pub sample_index: u64,
+    /// Detector statistic at the time of detection
+    pub statistic: T,
+}
+
+/// Callback invoked when drift is detected
+pub type DriftCallback<T> = Box<dyn FnMut(&DriftEvent<T>) + Send>;
+
+/// Sequential change detector over a scalar stream, typically prediction error
+pub trait DriftDetector<T: Float>: Send {
+    /// Feed one observation
+    fn update(&mut self, value: T) -> DriftStatus;
+
+    /// Current value of the detection statistic
+    fn statistic(&self) -> T;
+
+    /// Forget all observations
+    fn reset(&mut self);
+
+    /// Prepare for the next concept after [`Self::update`] reported drift;
+    /// forgets all observations unless the detector already dropped the
+    /// outdated ones itself
+    fn after_drift(&mut self) {
+        self.reset();
+    }
+
+    /// Short detector name used in events
+    fn name(&self) -> &'static str;
+}
+
+/// Page–Hinkley test for an increase in the mean of the stream
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PageHinkley<T: Float> {
+    /// Magnitude of changes that are tolerated
+    pub delta: T,
+    /// Detection threshold (lambda)
+    pub threshold: T,
+    /// Fraction of the threshold at which a warning is reported
+    pub warning_ratio: T,
+    /// Observations required before drift can be reported
+    pub min_samples: u64,
+    count: u64,
+    mean: T,
+    cumulative: T,
+    minimum: T,
+}
+
+impl<T: Float> PageHinkley<T> {
+    pub fn new(delta: T, threshold: T) -> Self {
+        Self {
+            delta,
+            threshold,
+            warning_ratio: cast(0.5),
+            min_samples: 30,
+            count: 0,
+            mean: T::zero(),
+            cumulative: T::zero(),
+            minimum: T::zero(),
+        }
+    }
+
+    pub fn with_min_samples(mut self, min_samples: u64) -> Self {
+        self.min_samples = min_samples;
+        self
+    }
+}
+
+impl<T: Float> Default for PageHinkley<T> {
+    fn default() -> Self {
+        Self::new(cast(0.005), cast(50.0))
+    }
+}
+
+impl<T: Float + Send> DriftDetector<T> for PageHinkley<T> {
+    fn update(&mut self, value: T) -> DriftStatus {
+        self.count += 1;
+        self.mean = self.mean + (value - self.mean) / cast(self.count);
+        self.cumulative = self.cumulative + value - self.mean - self.delta;
+        self.minimum = self.minimum.min(self.cumulative);
+
+        if self.count < self.min_samples {
+            return DriftStatus::Stable;
+        }
+
+        let statistic = self.statistic();
+        if statistic > self.threshold {
+            DriftStatus::Drift
+        } else if statistic > self.threshold * self.warning_ratio {
+            DriftStatus::Warning
+        } else {
+            DriftStatus::Stable
+        }
+    }
+
+    fn statistic(&self) -> T {
+        self.cumulative - self.minimum
+    }
+
+    fn reset(&mut self) {
+        self.count = 0;
+        self.mean = T::zero();
+        self.cumulative = T::zero();
+        self.minimum = T::zero();
+    }
+
+    fn name(&self) -> &'static str {
+        "page_hinkley"
+    }
+}
+
+/// Adaptive windowing (ADWIN) over a bounded window
+///
+/// Keeps a window of recent observations and reports drift when two
+/// sub-windows have means that differ by more than the Hoeffding bound for
+/// confidence `delta`. On drift the older sub-window is dropped, so the window
+/// only holds data from the current concept.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Adwin<T: Float> {
+    /// Confidence parameter; smaller values make detection more conservative
+    pub delta: T,
+    /// Maximum number of observations kept
+    pub max_window: usize,
+    /// Minimum size of each compared sub-window
+    pub min_subwindow: usize,
+    window: VecDeque<T>,
+    last_cut: T,
+}
+
+impl<T: Float> Adwin<T> {
+    pub fn new(delta: T) -> Self {
+        Self {
+            delta,
+            max_window: 1000,
+            min_subwindow: 5,
+            window: VecDeque::new(),
+            last_cut: T::zero(),
+        }
+    }
+
+    pub fn with_max_window(mut self, max_window: usize) -> Self {
+        self.max_window = max_window.max(2);
+        self
+    }
+
+    /// Number of observations in the current window
+    pub fn window_len(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Mean of the current window
+    pub fn mean(&self) -> T {
+        if self.window.is_empty() {
+            return T::zero();
+        }
+        self.window.iter().fold(T::zero(), |acc, &v| acc + v) / cast(self.window.len())
+    }
+
+    /// Find the oldest split whose sub-window means differ significantly
+    fn find_cut(&self) -> Option<(usize, T)> {
+        let n = self.window.len();
+        if n < 2 * self.min_subwindow {
+            return None;
+        }
+
+        let total = self.window.iter().fold(T::zero(), |acc, &v| acc + v);
+        let log_term = (cast::<T>(4 * n) / self.delta).ln();
+        let mut head_sum = T::zero();
+
+        for (i, &value) in self.window.iter().enumerate().take(n - self.min_subwindow) {
+            head_sum = head_sum + value;
+            let n0 = i + 1;
+            if n0 < self.min_subwindow {
+                continue;
+            }
+            let n1 = n - n0;
+            let (n0f, n1f): (T, T) = (cast(n0), cast(n1));
+            let difference = (head_sum / n0f - (total - head_sum) / n1f).abs();
+            let harmonic = T::one() / (T::one() / n0f + T::one() / n1f);
+            let bound = (log_term / (cast::<T>(2.0) * harmonic)).sqrt();
+            if difference > bound {
+                return Some((n0, difference));
+            }
+        }
+        None
+    }
+}
+
+impl<T: Float> Default for Adwin<T> {
+    fn default() -> Self {
+        Self::new(cast(0.002))
+    }
+}
+
+impl<T: Float + Send> DriftDetector<T> for Adwin<T> {
+    fn update(&mut self, value: T) -> DriftStatus {
+        self.window.push_back(value);
+        if self.window.len() > self.max_window {
+            self.window.pop_front();
+        }
+
+        match self.find_cut() {
+            Some((cut, difference)) => {
+                self.window.drain(..cut);
+                self.last_cut = difference;
+                DriftStatus::Drift
+            }
+            None => DriftStatus::Stable,
+        }
+    }
+
+    fn statistic(&self) -> T {
+        self.last_cut
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.last_cut = T::zero();
+    }
+
+    /// The window was already cut back to the current concept in `update`
+    fn after_drift(&mut self) {}
+
+    fn name(&self) -> &'static str {
+        "adwin"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_drift<D: DriftDetector<f64>>(detector: &mut D, stream: &[f64]) -> Option<usize> {
+        stream
+            .iter()
+            .position(|&v| detector.update(v) == DriftStatus::Drift)
+    }
+
+    fn shifted_stream() -> Vec<f64> {
+        (0..400)
+            .map(|i| {
+                let noise = ((i * 37) % 11) as f64 / 100.0;
+                if i < 200 {
+                    0.1 + noise
+                } else {
+                    0.6 + noise
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_page_hinkley_detects_increase() {
+        let stream = shifted_stream();
+        let mut detector = PageHinkley::new(0.01, 5.0);
+
+        assert_eq!(first_drift(&mut detector, &stream[..200]), None);
+        let index = first_drift(&mut detector, &stream[200..]).unwrap();
+        assert!(index < 50);
+
+        detector.reset();
+        assert_eq!(detector.statistic(), 0.0);
+    }
+
+    #[test]
+    fn test_adwin_detects_change_and_shrinks_window() {
+        let stream = shifted_stream();
+        let mut detector = Adwin::default();
+
+        assert_eq!(first_drift(&mut detector, &stream[..200]), None);
+        assert_eq!(detector.window_len(), 200);
+
+        let index = first_drift(&mut detector, &stream[200..]).unwrap();
+        assert!(index < 50);
+        assert!(detector.window_len() < 200);
+
+        let window_len = detector.window_len();
+        detector.after_drift();
+        assert!(window_len > 0);
+        assert_eq!(detector.window_len(), window_len);
+    }
+}
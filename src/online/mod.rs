@@ -0,0 +1,259 @@
+//! Online learning
+//!
+//! [`OnlineLearner`] trains a network one sample at a time while keeping the
+//! input standardization statistics up to date. The scaler lives behind a
+//! [`SharedScaler`] handle so inference code running elsewhere normalizes its
+//! inputs with exactly the statistics the network is being trained on.
+//!
+//! A [`DriftDetector`] can be attached to watch the per-sample training error;
+//! detected drift is reported through a [`DriftCallback`] so applications can
+//! trigger retraining.
+
+use crate::preprocessing::{OnlineScaler, PreprocessingError, Transform};
+use crate::training::{IncrementalBackprop, TrainingAlgorithm, TrainingData, TrainingError};
+use crate::Network;
+use num_traits::Float;
+use std::sync::{Arc, RwLock};
+
+mod drift;
+
+pub use drift::{Adwin, DriftCallback, DriftDetector, DriftEvent, DriftStatus, PageHinkley};
+
+/// Scaler shared between an online trainer and inference
+pub type SharedScaler<T> = Arc<RwLock<OnlineScaler<T>>>;
+
+/// Incremental trainer with streaming input standardization
+pub struct OnlineLearner<T: Float + Send + Default> {
+    network: Network<T>,
+    algorithm: Box<dyn TrainingAlgorithm<T>>,
+    scaler: SharedScaler<T>,
+    update_scaler: bool,
+    samples_seen: u64,
+    drift_detector: Option<Box<dyn DriftDetector<T>>>,
+    drift_callback: Option<DriftCallback<T>>,
+    drift_status: DriftStatus,
+    drift_count: usize,
+}
+
+impl<T: Float + Send + Sync + Default + 'static> OnlineLearner<T> {
+    /// Create a learner using incremental backpropagation
+    pub fn new(network: Network<T>, learning_rate: T) -> Self {
+        Self {
+            network,
+            algorithm: Box::new(IncrementalBackprop::new(learning_rate)),
+            scaler: Arc::new(RwLock::new(OnlineScaler::new())),
+            update_scaler: true,
+            samples_seen: 0,
+            drift_detector: None,
+            drift_callback: None,
+            drift_status: DriftStatus::Stable,
+            drift_count: 0,
+        }
+    }
+}
+
+impl<T: Float + Send + Default> OnlineLearner<T> {
+    /// Use a different training algorithm for the per-sample updates
+    pub fn with_algorithm(mut self, algorithm: Box<dyn TrainingAlgorithm<T>>) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Use a preconfigured (or already shared) scaler
+    pub fn with_scaler(mut self, scaler: SharedScaler<T>) -> Self {
+        self.scaler = scaler;
+        self
+    }
+
+    /// Monitor the per-sample training error for concept drift
+    pub fn with_drift_detector(mut self, detector: Box<dyn DriftDetector<T>>) -> Self {
+        self.drift_detector = Some(detector);
+        self
+    }
+
+    /// Set the callback invoked whenever drift is detected
+    pub fn set_drift_callback(&mut self, callback: DriftCallback<T>) {
+        self.drift_callback = Some(callback);
+    }
+
+    /// Status reported by the drift detector for the last learned sample
+    pub fn drift_status(&self) -> DriftStatus {
+        self.drift_status
+    }
+
+    /// Number of drifts detected so far
+    pub fn drift_count(&self) -> usize {
+        self.drift_count
+    }
+
+    /// Freeze or resume updates of the normalization statistics
+    pub fn set_update_scaler(&mut self, update: bool) {
+        self.update_scaler = update;
+    }
+
+    /// Handle to the scaler, for use by inference code
+    pub fn scaler(&self) -> SharedScaler<T> {
+        Arc::clone(&self.scaler)
+    }
+
+    /// Number of samples learned so far
+    pub fn samples_seen(&self) -> u64 {
+        self.samples_seen
+    }
+
+    pub fn network(&self) -> &Network<T> {
+        &self.network
+    }
+
+    pub fn network_mut(&mut self) -> &mut Network<T> {
+        &mut self.network
+    }
+
+    pub fn into_network(self) -> Network<T> {
+        self.network
+    }
+
+    /// Learn from one sample and return its error before the update
+    ///
+    /// The scaler is updated with the raw input first, so the network always
+    /// sees inputs standardized with the latest statistics.
+    pub fn learn(&mut self, input: &[T], target: &[T]) -> Result<T, TrainingError> {
+        let scaled = {
+            let mut scaler = self.scaler.write().map_err(|_| poisoned())?;
+            if self.update_scaler {
+                scaler.update(input).map_err(preprocessing_error)?;
+            }
+            scaler.transform(input).map_err(preprocessing_error)?
+        };
+
+        let sample = TrainingData {
+            inputs: vec![scaled],
+            outputs: vec![target.to_vec()],
+        };
+        let error = self.algorithm.train_epoch(&mut self.network, &sample)?;
+        self.samples_seen += 1;
+        self.observe_error(error);
+        Ok(error)
+    }
+
+    fn observe_error(&mut self, error: T) {
+        let Some(detector) = self.drift_detector.as_mut() else {
+            return;
+        };
+
+        self.drift_status = detector.update(error);
+        if self.drift_status == DriftStatus::Drift {
+            let event = DriftEvent {
+                detector: detector.name(),
+                sample_index: self.samples_seen - 1,
+                statistic: detector.statistic(),
+            };
+            detector.after_drift();
+            self.drift_count += 1;
+            if let Some(callback) = self.drift_callback.as_mut() {
+                callback(&event);
+            }
+        }
+    }
+
+    /// Standardize an input with the shared statistics and run the network
+    pub fn predict(&mut self, input: &[T]) -> Result<Vec<T>, TrainingError> {
+        let scaled = standardize(&self.scaler, input)?;
+        Ok(self.network.run(&scaled))
+    }
+}
+
+/// Standardize an input with a shared scaler
+pub fn standardize<T: Float>(
+    scaler: &SharedScaler<T>,
+    input: &[T],
+) -> Result<Vec<T>, TrainingError> {
+    scaler
+        .read()
+        .map_err(|_| poisoned())?
+        .transform(input)
+        .map_err(preprocessing_error)
+}
+
+fn preprocessing_error(error: PreprocessingError) -> TrainingError {
+    TrainingError::InvalidData(error.to_string())
+}
+
+fn poisoned() -> TrainingError {
+    TrainingError::TrainingFailed("scaler lock poisoned".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_learner_shares_scaler() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .hidden_layer(4)
+            .output_layer(1)
+            .build();
+        let mut learner = OnlineLearner::new(network, 0.1);
+        let scaler = learner.scaler();
+
+        for i in 0..200 {
+            let x = 1000.0 + (i % 10) as f64;
+            learner.learn(&[x], &[0.5]).unwrap();
+        }
+
+        assert_eq!(learner.samples_seen(), 200);
+        assert_eq!(scaler.read().unwrap().count(), 200);
+        assert!((scaler.read().unwrap().mean()[0] - 1004.5).abs() < 1e-9);
+
+        let scaled = standardize(&scaler, &[1004.5]).unwrap();
+        assert!(scaled[0].abs() < 1e-9);
+
+        let output = learner.predict(&[1004.0]).unwrap();
+        assert_eq!(output.len(), 1);
+    }
+
+    #[test]
+    fn test_frozen_scaler() {
+        let network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        let mut learner = OnlineLearner::new(network, 0.1);
+        assert!(learner.predict(&[1.0, 2.0]).is_err());
+
+        learner.learn(&[1.0, 2.0], &[1.0]).unwrap();
+        learner.set_update_scaler(false);
+        learner.learn(&[3.0, 4.0], &[1.0]).unwrap();
+        assert_eq!(learner.scaler().read().unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_drift_callback() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .output_layer(1)
+            .build();
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+
+        let mut learner = OnlineLearner::new(network, 0.0)
+            .with_drift_detector(Box::new(PageHinkley::new(0.001, 0.5)));
+        learner.set_drift_callback(Box::new(move |event| {
+            sink.lock().unwrap().push(event.clone());
+        }));
+
+        // With a zero learning rate the error only changes when the target does
+        for i in 0..100 {
+            let target = if i < 50 { 0.5 } else { -1.0 };
+            learner.learn(&[(i % 3) as f64], &[target]).unwrap();
+        }
+
+        let events = events.lock().unwrap();
+        assert_eq!(learner.drift_count(), events.len());
+        assert!(!events.is_empty());
+        assert_eq!(events[0].detector, "page_hinkley");
+        assert!(events[0].sample_index >= 50);
+    }
+}
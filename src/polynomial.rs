@@ -0,0 +1,564 @@
+//! Polynomial activations for homomorphic-encryption inference
+//!
+//! Encrypted inference engines (CKKS/BFV schemes as in SEAL, OpenFHE or
+//! TenSEAL) can only add and multiply ciphertexts, so sigmoids, tanh and
+//! ReLU have to be replaced with low-degree polynomials. A
+//! [`PolynomialNetwork`] is a dense copy of a [`Network`] in which every
+//! layer's activation is a least-squares [`PolynomialActivation`] fitted on
+//! an input range. The approximation can be checked with
+//! [`PolynomialNetwork::max_deviation`] and retrained with polynomial
+//! activations through [`PolynomialNetwork::fine_tune`].
+//!
+//! [`export_he`] writes the result as JSON: per layer a row-major weight
+//! matrix (`outputs × inputs`), a bias vector and the activation
+//! coefficients in ascending powers of the pre-activation sum, plus the
+//! multiplicative depth an engine has to provision.
+//!
+//! ```
+//! use do_fann::polynomial::PolynomialNetwork;
+//! use do_fann::NetworkBuilder;
+//!
+//! let network = NetworkBuilder::<f64>::new()
+//!     .input_layer(2)
+//!     .hidden_layer(3)
+//!     .output_layer(1)
+//!     .build();
+//! let he = PolynomialNetwork::from_network(&network, 3, 4.0)?;
+//! assert_eq!(he.layers().len(), 2);
+//! assert_eq!(he.multiplicative_depth(), 6);
+//! # Ok::<(), do_fann::TrainingError>(())
+//! ```
+
+use crate::numeric::cast;
+use crate::training::{TrainingData, TrainingError};
+use crate::{ActivationFunction, Network};
+use num_traits::Float;
+
+#[cfg(feature = "io")]
+use crate::io::IoResult;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Sample points used for each least-squares fit
+const FIT_POINTS: usize = 256;
+
+/// Highest supported polynomial degree
+pub const MAX_DEGREE: usize = 8;
+
+/// Activation `f(x) = Σ coefficients[k] · x^k` of the pre-activation sum `x`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PolynomialActivation {
+    /// Coefficients in ascending powers
+    pub coefficients: Vec<f64>,
+}
+
+impl PolynomialActivation {
+    /// Least-squares fit of `activation` with `steepness` on
+    /// `[-range, range]`
+    ///
+    /// The steepness is folded into the coefficients, so the polynomial takes
+    /// the raw neuron sum like [`Network::run`] does.
+    pub fn fit(
+        activation: ActivationFunction,
+        steepness: f64,
+        degree: usize,
+        range: f64,
+    ) -> Result<Self, TrainingError> {
+        if !(1..=MAX_DEGREE).contains(&degree) {
+            return Err(TrainingError::InvalidData(format!(
+                "polynomial degree must be between 1 and {MAX_DEGREE}, got {degree}"
+            )));
+        }
+        if !(range.is_finite() && range > 0.0) {
+            return Err(TrainingError::InvalidData(format!(
+                "fit range must be positive, got {range}"
+            )));
+        }
+        let steepness = if activation.uses_steepness() {
+            steepness
+        } else {
+            1.0
+        };
+        // Fit on u = x / range in [-1, 1] for a well-conditioned system
+        let size = degree + 1;
+        let mut normal = vec![vec![0.0; size + 1]; size];
+        for i in 0..FIT_POINTS {
+            let u = -1.0 + 2.0 * i as f64 / (FIT_POINTS - 1) as f64;
+            let y = activation.activate(u * range * steepness);
+            let powers: Vec<f64> = (0..size).map(|k| u.powi(k as i32)).collect();
+            for (row, &p) in normal.iter_mut().zip(&powers) {
+                for (cell, &q) in row.iter_mut().zip(&powers) {
+                    *cell += p * q;
+                }
+                row[size] += p * y;
+            }
+        }
+        let scaled = solve(normal).ok_or_else(|| {
+            TrainingError::TrainingFailed(format!("singular fit for {}", activation.name()))
+        })?;
+        let coefficients = scaled
+            .iter()
+            .enumerate()
+            .map(|(k, c)| c / range.powi(k as i32))
+            .collect();
+        Ok(Self { coefficients })
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coefficients.len().saturating_sub(1)
+    }
+
+    /// Ciphertext multiplications in sequence needed to evaluate the
+    /// polynomial, `ceil(log2(degree))`
+    pub fn multiplicative_depth(&self) -> usize {
+        let degree = self.degree();
+        if degree <= 1 {
+            0
+        } else {
+            (usize::BITS - (degree - 1).leading_zeros()) as usize
+        }
+    }
+
+    pub fn evaluate<T: Float>(&self, x: T) -> T {
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(T::zero(), |acc, &c| acc * x + cast(c))
+    }
+
+    pub fn derivative<T: Float>(&self, x: T) -> T {
+        self.coefficients
+            .iter()
+            .enumerate()
+            .skip(1)
+            .rev()
+            .fold(T::zero(), |acc, (k, &c)| acc * x + cast::<T>(c * k as f64))
+    }
+}
+
+/// Gaussian elimination with partial pivoting on an augmented matrix
+fn solve(mut matrix: Vec<Vec<f64>>) -> Option<Vec<f64>> {
+    let n = matrix.len();
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|&a, &b| matrix[a][col].abs().total_cmp(&matrix[b][col].abs()))?;
+        if matrix[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        for row in col + 1..n {
+            let factor = matrix[row][col] / matrix[col][col];
+            for k in col..=n {
+                matrix[row][k] -= factor * matrix[col][k];
+            }
+        }
+    }
+    let mut solution = vec![0.0; n];
+    for row in (0..n).rev() {
+        let tail: f64 = (row + 1..n).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (matrix[row][n] - tail) / matrix[row][row];
+    }
+    Some(solution)
+}
+
+/// Dense layer with a polynomial activation
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PolynomialLayer<T: Float> {
+    /// One row of input weights per output neuron
+    pub weights: Vec<Vec<T>>,
+    pub biases: Vec<T>,
+    pub activation: PolynomialActivation,
+}
+
+impl<T: Float> PolynomialLayer<T> {
+    pub fn num_inputs(&self) -> usize {
+        self.weights.first().map_or(0, Vec::len)
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.weights.len()
+    }
+
+    fn sums(&self, input: &[T]) -> Vec<T> {
+        self.weights
+            .iter()
+            .zip(&self.biases)
+            .map(|(row, &bias)| {
+                row.iter()
+                    .zip(input)
+                    .fold(bias, |sum, (&w, &x)| sum + w * x)
+            })
+            .collect()
+    }
+}
+
+/// Feed-forward network restricted to additions, multiplications and
+/// polynomial activations
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PolynomialNetwork<T: Float> {
+    num_inputs: usize,
+    layers: Vec<PolynomialLayer<T>>,
+}
+
+impl<T: Float> PolynomialNetwork<T> {
+    /// Copy the weights of `network` and replace each layer's activation with
+    /// a polynomial of `degree` fitted on `[-range, range]`
+    ///
+    /// Pick `range` to cover the neuron sums seen in practice; outside it the
+    /// polynomial diverges quickly. Fails if a layer mixes activation
+    /// functions or steepnesses, since HE engines apply one activation per
+    /// layer.
+    pub fn from_network(
+        network: &Network<T>,
+        degree: usize,
+        range: f64,
+    ) -> Result<Self, TrainingError> {
+        let mut layers = Vec::new();
+        for (index, pair) in network.layers.windows(2).enumerate() {
+            let (previous, layer) = (&pair[0], &pair[1]);
+            let columns: Vec<Option<usize>> = previous
+                .neurons
+                .iter()
+                .scan(0, |regular, neuron| {
+                    Some((!neuron.is_bias).then(|| {
+                        *regular += 1;
+                        *regular - 1
+                    }))
+                })
+                .collect();
+            let inputs = previous.num_regular_neurons();
+            let neurons: Vec<_> = layer.neurons.iter().filter(|n| !n.is_bias).collect();
+            let Some(first) = neurons.first() else {
+                continue;
+            };
+            if neurons.iter().any(|n| {
+                n.activation_function != first.activation_function
+                    || n.activation_steepness != first.activation_steepness
+            }) {
+                return Err(TrainingError::NetworkError(format!(
+                    "layer {} mixes activation functions",
+                    index + 1
+                )));
+            }
+            let mut weights = vec![vec![T::zero(); inputs]; neurons.len()];
+            let mut biases = vec![T::zero(); neurons.len()];
+            for (neuron, (row, bias)) in neurons.iter().zip(weights.iter_mut().zip(&mut biases)) {
+                for connection in &neuron.connections {
+                    match columns.get(connection.from_neuron) {
+                        Some(Some(column)) => row[*column] = connection.weight,
+                        Some(None) => *bias = connection.weight,
+                        None => {}
+                    }
+                }
+            }
+            let steepness = first.activation_steepness.to_f64().unwrap_or(1.0);
+            let activation =
+                PolynomialActivation::fit(first.activation_function, steepness, degree, range)?;
+            layers.push(PolynomialLayer {
+                weights,
+                biases,
+                activation,
+            });
+        }
+        Ok(Self {
+            num_inputs: network.num_inputs(),
+            layers,
+        })
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.layers
+            .last()
+            .map_or(self.num_inputs, PolynomialLayer::num_outputs)
+    }
+
+    pub fn layers(&self) -> &[PolynomialLayer<T>] {
+        &self.layers
+    }
+
+    /// Multiplicative depth of one inference: one plaintext-ciphertext
+    /// product per layer plus each activation's depth
+    pub fn multiplicative_depth(&self) -> usize {
+        self.layers
+            .iter()
+            .map(|layer| 1 + layer.activation.multiplicative_depth())
+            .sum()
+    }
+
+    pub fn run(&self, input: &[T]) -> Vec<T> {
+        self.layers.iter().fold(input.to_vec(), |values, layer| {
+            layer
+                .sums(&values)
+                .into_iter()
+                .map(|sum| layer.activation.evaluate(sum))
+                .collect()
+        })
+    }
+
+    /// Mean squared error on `data`
+    pub fn calculate_error(&self, data: &TrainingData<T>) -> T {
+        let total = data
+            .inputs
+            .iter()
+            .zip(&data.outputs)
+            .fold(T::zero(), |total, (input, desired)| {
+                total + mse(&self.run(input), desired)
+            });
+        total / cast(data.inputs.len().max(1))
+    }
+
+    /// Largest absolute output difference from `network` over the inputs of
+    /// `data`
+    pub fn max_deviation(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
+        let mut network = network.clone();
+        data.inputs.iter().fold(T::zero(), |worst, input| {
+            self.run(input)
+                .iter()
+                .zip(network.run(input))
+                .fold(worst, |worst, (&a, b)| worst.max((a - b).abs()))
+        })
+    }
+
+    /// Retrain the weights with the polynomial activations in place, using
+    /// full-batch gradient descent on the mean squared error
+    ///
+    /// Returns the error after the last epoch.
+    pub fn fine_tune(
+        &mut self,
+        data: &TrainingData<T>,
+        learning_rate: T,
+        epochs: usize,
+    ) -> Result<T, TrainingError> {
+        self.check_shapes(data)?;
+        let batch: T = cast(data.inputs.len());
+        for _ in 0..epochs {
+            let mut weight_gradients: Vec<Vec<Vec<T>>> = self
+                .layers
+                .iter()
+                .map(|layer| vec![vec![T::zero(); layer.num_inputs()]; layer.num_outputs()])
+                .collect();
+            let mut bias_gradients: Vec<Vec<T>> = self
+                .layers
+                .iter()
+                .map(|layer| vec![T::zero(); layer.num_outputs()])
+                .collect();
+            for (input, desired) in data.inputs.iter().zip(&data.outputs) {
+                let mut values = vec![input.clone()];
+                let mut sums = Vec::with_capacity(self.layers.len());
+                for layer in &self.layers {
+                    let layer_sums = layer.sums(&values[values.len() - 1]);
+                    values.push(
+                        layer_sums
+                            .iter()
+                            .map(|&s| layer.activation.evaluate(s))
+                            .collect(),
+                    );
+                    sums.push(layer_sums);
+                }
+                let scale: T = cast::<T>(2.0) / cast(desired.len().max(1));
+                let mut deltas: Vec<T> = values[values.len() - 1]
+                    .iter()
+                    .zip(desired)
+                    .map(|(&y, &t)| (y - t) * scale)
+                    .collect();
+                for (l, layer) in self.layers.iter().enumerate().rev() {
+                    for (delta, &sum) in deltas.iter_mut().zip(&sums[l]) {
+                        *delta = *delta * layer.activation.derivative(sum);
+                    }
+                    for ((row, bias), &delta) in weight_gradients[l]
+                        .iter_mut()
+                        .zip(&mut bias_gradients[l])
+                        .zip(&deltas)
+                    {
+                        *bias = *bias + delta;
+                        for (g, &x) in row.iter_mut().zip(&values[l]) {
+                            *g = *g + delta * x;
+                        }
+                    }
+                    deltas = (0..layer.num_inputs())
+                        .map(|i| {
+                            layer
+                                .weights
+                                .iter()
+                                .zip(&deltas)
+                                .fold(T::zero(), |acc, (row, &d)| acc + row[i] * d)
+                        })
+                        .collect();
+                }
+            }
+            for (layer, (weights, biases)) in self
+                .layers
+                .iter_mut()
+                .zip(weight_gradients.iter().zip(&bias_gradients))
+            {
+                for (row, gradients) in layer.weights.iter_mut().zip(weights) {
+                    for (w, &g) in row.iter_mut().zip(gradients) {
+                        *w = *w - learning_rate * g / batch;
+                    }
+                }
+                for (b, &g) in layer.biases.iter_mut().zip(biases) {
+                    *b = *b - learning_rate * g / batch;
+                }
+            }
+        }
+        let error = self.calculate_error(data);
+        if !error.is_finite() {
+            return Err(TrainingError::TrainingFailed(
+                "polynomial network diverged; lower the learning rate or the fit range".to_string(),
+            ));
+        }
+        Ok(error)
+    }
+
+    fn check_shapes(&self, data: &TrainingData<T>) -> Result<(), TrainingError> {
+        if data.inputs.is_empty() || data.inputs.len() != data.outputs.len() {
+            return Err(TrainingError::InvalidData(format!(
+                "{} inputs but {} outputs",
+                data.inputs.len(),
+                data.outputs.len()
+            )));
+        }
+        let shapes_match = data.inputs.iter().all(|i| i.len() == self.num_inputs)
+            && data.outputs.iter().all(|o| o.len() == self.num_outputs());
+        if !shapes_match {
+            return Err(TrainingError::InvalidData(format!(
+                "samples must have {} inputs and {} outputs",
+                self.num_inputs,
+                self.num_outputs()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn mse<T: Float>(actual: &[T], desired: &[T]) -> T {
+    let sum = actual
+        .iter()
+        .zip(desired)
+        .fold(T::zero(), |acc, (&a, &d)| acc + (a - d) * (a - d));
+    sum / cast(actual.len().max(1))
+}
+
+/// Serialized layout of [`export_he`]
+#[cfg(feature = "io")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeModel {
+    pub format: String,
+    pub version: u32,
+    pub input_size: usize,
+    pub output_size: usize,
+    pub multiplicative_depth: usize,
+    pub layers: Vec<HeLayer>,
+}
+
+/// One layer of an [`HeModel`]
+#[cfg(feature = "io")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeLayer {
+    pub inputs: usize,
+    pub outputs: usize,
+    /// Row-major `outputs × inputs`
+    pub weights: Vec<f64>,
+    pub bias: Vec<f64>,
+    /// Activation coefficients in ascending powers
+    pub activation: Vec<f64>,
+}
+
+#[cfg(feature = "io")]
+impl<T: Float> From<&PolynomialNetwork<T>> for HeModel {
+    fn from(network: &PolynomialNetwork<T>) -> Self {
+        let to_f64 = |v: &T| v.to_f64().unwrap_or(f64::NAN);
+        Self {
+            format: "do-fann-he".to_string(),
+            version: 1,
+            input_size: network.num_inputs(),
+            output_size: network.num_outputs(),
+            multiplicative_depth: network.multiplicative_depth(),
+            layers: network
+                .layers
+                .iter()
+                .map(|layer| HeLayer {
+                    inputs: layer.num_inputs(),
+                    outputs: layer.num_outputs(),
+                    weights: layer.weights.iter().flatten().map(to_f64).collect(),
+                    bias: layer.biases.iter().map(to_f64).collect(),
+                    activation: layer.activation.coefficients.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Write `network` as an [`HeModel`] JSON document
+#[cfg(feature = "io")]
+pub fn export_he<T: Float, W: std::io::Write>(
+    network: &PolynomialNetwork<T>,
+    writer: W,
+) -> IoResult<()> {
+    serde_json::to_writer_pretty(writer, &HeModel::from(network))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_fit_tracks_activation() {
+        let linear = PolynomialActivation::fit(ActivationFunction::Linear, 1.0, 2, 3.0).unwrap();
+        assert!((linear.evaluate(2.5) - 2.5_f64).abs() < 1e-9);
+        assert!(linear.coefficients[2].abs() < 1e-9);
+
+        let sigmoid = PolynomialActivation::fit(ActivationFunction::Sigmoid, 1.0, 3, 4.0).unwrap();
+        for x in [-4.0, -1.0, 0.0, 0.5, 3.0_f64] {
+            let exact = ActivationFunction::Sigmoid.activate(x);
+            assert!((sigmoid.evaluate(x) - exact).abs() < 0.05);
+        }
+        assert!((sigmoid.derivative(0.0_f64) - sigmoid.coefficients[1]).abs() < 1e-12);
+        assert_eq!(sigmoid.multiplicative_depth(), 2);
+        assert!(PolynomialActivation::fit(ActivationFunction::Sigmoid, 1.0, 0, 4.0).is_err());
+    }
+
+    #[test]
+    fn test_network_conversion_fine_tune_and_export() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .output_layer(1)
+            .build();
+        let data = TrainingData {
+            inputs: (0..16)
+                .map(|i| vec![(i % 4) as f64 / 4.0, (i / 4) as f64 / 4.0])
+                .collect(),
+            outputs: (0..16)
+                .map(|i| vec![0.3 + ((i % 4) + (i / 4)) as f64 / 16.0])
+                .collect(),
+        };
+        let mut he = PolynomialNetwork::from_network(&network, 3, 4.0).unwrap();
+        assert_eq!((he.num_inputs(), he.num_outputs()), (2, 1));
+        assert!(he.max_deviation(&network, &data) < 0.05);
+
+        let before = he.calculate_error(&data);
+        let after = he.fine_tune(&data, 0.5, 200).unwrap();
+        assert!(after < before);
+
+        #[cfg(feature = "io")]
+        {
+            let mut json = Vec::new();
+            export_he(&he, &mut json).unwrap();
+            let model: HeModel = serde_json::from_slice(&json).unwrap();
+            assert_eq!(model.multiplicative_depth, 6);
+            assert_eq!(model.layers[0].weights.len(), 8);
+            assert_eq!(model.layers[0].weights[2], he.layers()[0].weights[1][0]);
+            assert_eq!(model.layers[1].activation.len(), 4);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Stable API surface
+//!
+//! `use do_fann::prelude::*;` imports what most programs need to build,
+//! train, evaluate and save networks. Everything here follows semver;
+//! additions may come in minor releases, removals and signature changes
+//! only in major ones. The `memory_manager` and `simd` tiers are deliberately
+//! left out (see the crate documentation on API stability).
+//!
+//! ```
+//! use do_fann::prelude::*;
+//!
+//! let mut network = NetworkBuilder::<f32>::new()
+//!     .input_layer(2)
+//!     .hidden_layer(3)
+//!     .output_layer(1)
+//!     .build();
+//! let data = TrainingData {
+//!     inputs: vec![vec![0.0, 1.0], vec![1.0, 0.0]],
+//!     outputs: vec![vec![1.0], vec![1.0]],
+//! };
+//! let mut trainer = IncrementalBackprop::new(0.5);
+//! let error = trainer.train_epoch(&mut network, &data)?;
+//! assert!(error.is_finite());
+//! # Ok::<(), RuvFannError>(())
+//! ```
+
+pub use crate::activation::ActivationFunction;
+pub use crate::errors::{RuvFannError, RuvFannResult};
+pub use crate::network::{FinalizedNetwork, Network, NetworkBuilder, NetworkError};
+pub use crate::training::{
+    Adam, AdamW, BatchBackprop, CancellationToken, ErrorFunction, IncrementalBackprop, MaeError,
+    MseError, ParallelTrainingOptions, Quickprop, Rprop, TanhError, Trainer, TrainingAlgorithm,
+    TrainingData, TrainingError, TrainingReport,
+};
+
+#[cfg(feature = "io")]
+pub use crate::io::{
+    FannReader, FannWriter, IoError, IoResult, TrainingDataReader, TrainingDataWriter,
+};
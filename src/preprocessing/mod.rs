@@ -0,0 +1,82 @@
+//! Input preprocessing transforms
+//!
+//! Transforms are fitted on training inputs and then applied to every sample
+//! before it reaches the network. All transforms are serializable so they can be
+//! stored next to the model they were fitted for.
+
+use num_traits::Float;
+use thiserror::Error;
+
+mod one_hot;
+mod online_scaler;
+mod pca;
+mod table;
+mod text;
+
+pub use one_hot::OneHotEncoder;
+pub use online_scaler::OnlineScaler;
+pub use pca::{ComponentSelection, Pca};
+pub use table::{Column, Table, TableEncoder};
+pub use text::{tokenize, HashingVectorizer, TfidfVectorizer};
+
+/// Errors raised while fitting or applying a preprocessing transform
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum PreprocessingError {
+    #[error("Transform has not been fitted")]
+    NotFitted,
+
+    #[error("Insufficient data: {0}")]
+    InsufficientData(String),
+
+    #[error("Dimension mismatch: expected {expected}, got {actual}")]
+    DimensionMismatch { expected: usize, actual: usize },
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfiguration(String),
+}
+
+/// Common interface for fitted input transforms
+pub trait Transform<T: Float> {
+    /// Fit the transform on a set of samples
+    fn fit(&mut self, samples: &[Vec<T>]) -> Result<(), PreprocessingError>;
+
+    /// Transform a single sample
+    fn transform(&self, sample: &[T]) -> Result<Vec<T>, PreprocessingError>;
+
+    /// Number of values produced per sample
+    fn output_dim(&self) -> usize;
+
+    /// Transform a batch of samples
+    fn transform_batch(&self, samples: &[Vec<T>]) -> Result<Vec<Vec<T>>, PreprocessingError> {
+        samples.iter().map(|s| self.transform(s)).collect()
+    }
+
+    /// Fit on the samples and return them transformed
+    fn fit_transform(&mut self, samples: &[Vec<T>]) -> Result<Vec<Vec<T>>, PreprocessingError> {
+        self.fit(samples)?;
+        self.transform_batch(samples)
+    }
+}
+
+/// Check that all samples share the same, non-zero dimension
+pub(crate) fn sample_dimension<T: Float>(samples: &[Vec<T>]) -> Result<usize, PreprocessingError> {
+    let dim = samples
+        .first()
+        .map(|s| s.len())
+        .ok_or_else(|| PreprocessingError::InsufficientData("no samples provided".to_string()))?;
+
+    if dim == 0 {
+        return Err(PreprocessingError::InsufficientData(
+            "samples have no features".to_string(),
+        ));
+    }
+
+    if let Some(bad) = samples.iter().find(|s| s.len() != dim) {
+        return Err(PreprocessingError::DimensionMismatch {
+            expected: dim,
+            actual: bad.len(),
+        });
+    }
+
+    Ok(dim)
+}
@@ -0,0 +1,96 @@
+//! One-hot encoding of categorical values
+
+use super::PreprocessingError;
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Maps category labels to one-hot vectors
+///
+/// Categories are sorted when fitting, so the encoding does not depend on the
+/// order of the samples. Labels not seen during fitting encode as all zeros.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OneHotEncoder {
+    categories: Vec<String>,
+}
+
+impl OneHotEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Learn the distinct labels of `values`
+    pub fn fit<S: AsRef<str>>(&mut self, values: &[S]) -> Result<(), PreprocessingError> {
+        if values.is_empty() {
+            return Err(PreprocessingError::InsufficientData(
+                "no categories provided".to_string(),
+            ));
+        }
+        let mut categories: Vec<String> = values.iter().map(|v| v.as_ref().to_string()).collect();
+        categories.sort_unstable();
+        categories.dedup();
+        self.categories = categories;
+        Ok(())
+    }
+
+    /// Fitted labels, in encoding order
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
+
+    /// Width of an encoded value
+    pub fn output_dim(&self) -> usize {
+        self.categories.len()
+    }
+
+    /// Position of `value` in the encoding, if it was seen while fitting
+    pub fn index_of(&self, value: &str) -> Option<usize> {
+        self.categories
+            .binary_search_by(|c| c.as_str().cmp(value))
+            .ok()
+    }
+
+    /// One-hot vector for `value`
+    pub fn encode<T: Float>(&self, value: &str) -> Result<Vec<T>, PreprocessingError> {
+        if self.categories.is_empty() {
+            return Err(PreprocessingError::NotFitted);
+        }
+        let mut encoded = vec![T::zero(); self.categories.len()];
+        if let Some(index) = self.index_of(value) {
+            encoded[index] = T::one();
+        }
+        Ok(encoded)
+    }
+
+    /// Label of the largest entry of an encoded (or predicted) vector
+    pub fn decode<T: Float>(&self, encoded: &[T]) -> Option<&str> {
+        encoded
+            .iter()
+            .take(self.categories.len())
+            .enumerate()
+            .filter(|(_, v)| !v.is_nan())
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| self.categories[i].as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_and_decode() {
+        let mut encoder = OneHotEncoder::new();
+        assert_eq!(
+            encoder.encode::<f32>("a"),
+            Err(PreprocessingError::NotFitted)
+        );
+        encoder.fit(&["red", "green", "red", "blue"]).unwrap();
+        assert_eq!(encoder.categories(), ["blue", "green", "red"]);
+        assert_eq!(encoder.encode::<f64>("red").unwrap(), vec![0.0, 0.0, 1.0]);
+        assert_eq!(encoder.encode::<f64>("pink").unwrap(), vec![0.0; 3]);
+        assert_eq!(encoder.decode(&[0.1, 0.7, 0.2]), Some("green"));
+    }
+}
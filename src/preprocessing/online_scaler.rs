@@ -0,0 +1,254 @@
+//! Streaming standardization with Welford's running mean and variance
+
+use super::{sample_dimension, PreprocessingError, Transform};
+use crate::numeric::cast;
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Standardizes inputs to zero mean and unit variance using statistics that
+/// are updated one sample at a time
+///
+/// Statistics are accumulated with Welford's algorithm, which is numerically
+/// stable for long streams. With [`OnlineScaler::with_max_count`] the
+/// effective sample count is capped, so older samples are gradually forgotten
+/// and the scaler follows a drifting input distribution.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OnlineScaler<T: Float> {
+    count: u64,
+    max_count: Option<u64>,
+    epsilon: T,
+    mean: Vec<T>,
+    /// Sum of squared deviations from the running mean
+    m2: Vec<T>,
+}
+
+impl<T: Float> Default for OnlineScaler<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Float> OnlineScaler<T> {
+    /// Create an empty scaler
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+            max_count: None,
+            epsilon: cast(1e-8),
+            mean: Vec::new(),
+            m2: Vec::new(),
+        }
+    }
+
+    /// Cap the effective sample count so that old samples are forgotten
+    ///
+    /// Once `max_count` samples have been seen each new sample carries a
+    /// weight of roughly `1 / max_count`.
+    pub fn with_max_count(mut self, max_count: u64) -> Self {
+        self.max_count = Some(max_count.max(2));
+        self
+    }
+
+    /// Set the standard deviation floor used to avoid division by zero
+    pub fn with_epsilon(mut self, epsilon: T) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Number of samples the statistics are based on
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Whether at least one sample has been seen
+    pub fn is_fitted(&self) -> bool {
+        self.count > 0
+    }
+
+    /// Running mean per feature
+    pub fn mean(&self) -> &[T] {
+        &self.mean
+    }
+
+    /// Running sample variance per feature (zero until two samples are seen)
+    pub fn variance(&self) -> Vec<T> {
+        if self.count < 2 {
+            return vec![T::zero(); self.mean.len()];
+        }
+        let denom = cast(self.count - 1);
+        self.m2.iter().map(|&m| m / denom).collect()
+    }
+
+    /// Running standard deviation per feature
+    pub fn std_dev(&self) -> Vec<T> {
+        self.variance().into_iter().map(|v| v.sqrt()).collect()
+    }
+
+    /// Forget all statistics
+    pub fn reset(&mut self) {
+        self.count = 0;
+        self.mean.clear();
+        self.m2.clear();
+    }
+
+    /// Update the statistics with one sample
+    pub fn update(&mut self, sample: &[T]) -> Result<(), PreprocessingError> {
+        if self.count == 0 {
+            if sample.is_empty() {
+                return Err(PreprocessingError::InsufficientData(
+                    "samples have no features".to_string(),
+                ));
+            }
+            self.mean = vec![T::zero(); sample.len()];
+            self.m2 = vec![T::zero(); sample.len()];
+        } else {
+            self.check_dimension(sample)?;
+        }
+
+        let n = match self.max_count {
+            Some(max) if self.count >= max => {
+                // Decay the accumulated deviations so the window stays at `max`
+                let keep = cast::<T>(max - 1) / cast::<T>(max);
+                for m in &mut self.m2 {
+                    *m = *m * keep;
+                }
+                max
+            }
+            _ => {
+                self.count += 1;
+                self.count
+            }
+        };
+        let n = cast(n);
+
+        for ((mean, m2), &x) in self.mean.iter_mut().zip(&mut self.m2).zip(sample) {
+            let delta = x - *mean;
+            *mean = *mean + delta / n;
+            *m2 = *m2 + delta * (x - *mean);
+        }
+        Ok(())
+    }
+
+    /// Update the statistics with a batch of samples
+    pub fn partial_fit(&mut self, samples: &[Vec<T>]) -> Result<(), PreprocessingError> {
+        samples.iter().try_for_each(|s| self.update(s))
+    }
+
+    /// Map standardized values back to the input space
+    pub fn inverse_transform(&self, values: &[T]) -> Result<Vec<T>, PreprocessingError> {
+        if !self.is_fitted() {
+            return Err(PreprocessingError::NotFitted);
+        }
+        self.check_dimension(values)?;
+
+        Ok(values
+            .iter()
+            .zip(&self.mean)
+            .zip(self.scales())
+            .map(|((&v, &mean), scale)| v * scale + mean)
+            .collect())
+    }
+
+    fn scales(&self) -> Vec<T> {
+        if self.count < 2 {
+            return vec![T::one(); self.mean.len()];
+        }
+        self.std_dev()
+            .into_iter()
+            .map(|s| if s > self.epsilon { s } else { T::one() })
+            .collect()
+    }
+
+    fn check_dimension(&self, sample: &[T]) -> Result<(), PreprocessingError> {
+        if sample.len() != self.mean.len() {
+            return Err(PreprocessingError::DimensionMismatch {
+                expected: self.mean.len(),
+                actual: sample.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<T: Float> Transform<T> for OnlineScaler<T> {
+    fn fit(&mut self, samples: &[Vec<T>]) -> Result<(), PreprocessingError> {
+        sample_dimension(samples)?;
+        self.reset();
+        self.partial_fit(samples)
+    }
+
+    fn transform(&self, sample: &[T]) -> Result<Vec<T>, PreprocessingError> {
+        if !self.is_fitted() {
+            return Err(PreprocessingError::NotFitted);
+        }
+        self.check_dimension(sample)?;
+
+        Ok(sample
+            .iter()
+            .zip(&self.mean)
+            .zip(self.scales())
+            .map(|((&x, &mean), scale)| (x - mean) / scale)
+            .collect())
+    }
+
+    fn output_dim(&self) -> usize {
+        self.mean.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_batch_statistics() {
+        let samples: Vec<Vec<f64>> = (0..100)
+            .map(|i| vec![i as f64, 3.0 * i as f64 - 7.0])
+            .collect();
+
+        let mut scaler = OnlineScaler::new();
+        let transformed = scaler.fit_transform(&samples).unwrap();
+        assert_eq!(scaler.count(), 100);
+        assert!((scaler.mean()[0] - 49.5).abs() < 1e-9);
+
+        for feature in 0..2 {
+            let column: Vec<f64> = transformed.iter().map(|s| s[feature]).collect();
+            let mean = column.iter().sum::<f64>() / 100.0;
+            let var = column.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / 99.0;
+            assert!(mean.abs() < 1e-9);
+            assert!((var - 1.0).abs() < 1e-9);
+        }
+
+        let restored = scaler.inverse_transform(&transformed[10]).unwrap();
+        assert!((restored[1] - samples[10][1]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_windowed_scaler_follows_drift() {
+        let mut scaler = OnlineScaler::<f64>::new().with_max_count(50);
+        for i in 0..500 {
+            let shift = if i < 250 { 0.0 } else { 10.0 };
+            scaler.update(&[shift + (i % 2) as f64]).unwrap();
+        }
+        assert!((scaler.mean()[0] - 10.5).abs() < 0.1);
+        assert!(scaler.std_dev()[0] < 1.0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut scaler = OnlineScaler::<f32>::new();
+        assert_eq!(
+            scaler.transform(&[1.0]).unwrap_err(),
+            PreprocessingError::NotFitted
+        );
+        scaler.update(&[1.0, 2.0]).unwrap();
+        assert_eq!(scaler.transform(&[1.0, 2.0]).unwrap(), vec![0.0, 0.0]);
+        assert!(matches!(
+            scaler.update(&[1.0]),
+            Err(PreprocessingError::DimensionMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,412 @@
+//! Principal component analysis with optional whitening
+
+use super::{sample_dimension, PreprocessingError, Transform};
+use crate::numeric::cast;
+use num_traits::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of Jacobi sweeps used for the eigendecomposition
+const MAX_SWEEPS: usize = 100;
+
+/// How many principal components to keep after fitting
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ComponentSelection<T: Float> {
+    /// Keep a fixed number of components
+    Count(usize),
+    /// Keep the smallest number of components whose cumulative explained
+    /// variance ratio reaches the given fraction (0, 1]
+    ExplainedVariance(T),
+}
+
+/// PCA transform fitted on training inputs
+///
+/// Projects inputs onto the leading principal components of the training set,
+/// optionally scaling each component to unit variance (whitening).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Pca<T: Float> {
+    selection: ComponentSelection<T>,
+    whiten: bool,
+    epsilon: T,
+    mean: Vec<T>,
+    /// Component vectors, one row per kept component
+    components: Vec<Vec<T>>,
+    explained_variance: Vec<T>,
+    explained_variance_ratio: Vec<T>,
+}
+
+impl<T: Float> Pca<T> {
+    /// Create an unfitted PCA transform
+    pub fn new(selection: ComponentSelection<T>) -> Self {
+        Self {
+            selection,
+            whiten: false,
+            epsilon: cast(1e-8),
+            mean: Vec::new(),
+            components: Vec::new(),
+            explained_variance: Vec::new(),
+            explained_variance_ratio: Vec::new(),
+        }
+    }
+
+    /// Keep a fixed number of components
+    pub fn with_components(n_components: usize) -> Self {
+        Self::new(ComponentSelection::Count(n_components))
+    }
+
+    /// Keep enough components to explain the given fraction of variance
+    pub fn with_explained_variance(ratio: T) -> Self {
+        Self::new(ComponentSelection::ExplainedVariance(ratio))
+    }
+
+    /// Scale projected components to unit variance
+    pub fn whiten(mut self, whiten: bool) -> Self {
+        self.whiten = whiten;
+        self
+    }
+
+    /// Set the variance floor used when whitening
+    pub fn with_epsilon(mut self, epsilon: T) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Whether the transform has been fitted
+    pub fn is_fitted(&self) -> bool {
+        !self.components.is_empty()
+    }
+
+    /// Number of input features the transform was fitted on
+    pub fn input_dim(&self) -> usize {
+        self.mean.len()
+    }
+
+    /// Number of kept components
+    pub fn n_components(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Per-feature mean of the training inputs
+    pub fn mean(&self) -> &[T] {
+        &self.mean
+    }
+
+    /// Kept principal axes, one row per component
+    pub fn components(&self) -> &[Vec<T>] {
+        &self.components
+    }
+
+    /// Variance captured by each kept component
+    pub fn explained_variance(&self) -> &[T] {
+        &self.explained_variance
+    }
+
+    /// Fraction of total variance captured by each kept component
+    pub fn explained_variance_ratio(&self) -> &[T] {
+        &self.explained_variance_ratio
+    }
+
+    /// Map projected values back to the original feature space
+    pub fn inverse_transform(&self, projected: &[T]) -> Result<Vec<T>, PreprocessingError> {
+        if !self.is_fitted() {
+            return Err(PreprocessingError::NotFitted);
+        }
+        if projected.len() != self.n_components() {
+            return Err(PreprocessingError::DimensionMismatch {
+                expected: self.n_components(),
+                actual: projected.len(),
+            });
+        }
+
+        let mut output = self.mean.clone();
+        for ((component, &value), &variance) in self
+            .components
+            .iter()
+            .zip(projected)
+            .zip(&self.explained_variance)
+        {
+            let value = if self.whiten {
+                value * (variance + self.epsilon).sqrt()
+            } else {
+                value
+            };
+            for (out, &axis) in output.iter_mut().zip(component) {
+                *out = *out + value * axis;
+            }
+        }
+        Ok(output)
+    }
+
+    fn select_count(&self, ratios: &[T]) -> Result<usize, PreprocessingError> {
+        match self.selection {
+            ComponentSelection::Count(n) => {
+                if n == 0 || n > ratios.len() {
+                    return Err(PreprocessingError::InvalidConfiguration(format!(
+                        "n_components must be in 1..={}, got {n}",
+                        ratios.len()
+                    )));
+                }
+                Ok(n)
+            }
+            ComponentSelection::ExplainedVariance(target) => {
+                if target <= T::zero() || target > T::one() {
+                    return Err(PreprocessingError::InvalidConfiguration(
+                        "explained variance ratio must be in (0, 1]".to_string(),
+                    ));
+                }
+                let mut cumulative = T::zero();
+                for (i, &ratio) in ratios.iter().enumerate() {
+                    cumulative = cumulative + ratio;
+                    // Small tolerance so a target of 1.0 is reachable despite rounding
+                    if cumulative >= target - cast(1e-6) {
+                        return Ok(i + 1);
+                    }
+                }
+                Ok(ratios.len())
+            }
+        }
+    }
+}
+
+impl<T: Float> Transform<T> for Pca<T> {
+    fn fit(&mut self, samples: &[Vec<T>]) -> Result<(), PreprocessingError> {
+        let dim = sample_dimension(samples)?;
+        if samples.len() < 2 {
+            return Err(PreprocessingError::InsufficientData(
+                "PCA needs at least two samples".to_string(),
+            ));
+        }
+
+        let n = cast(samples.len());
+        let mut mean = vec![T::zero(); dim];
+        for sample in samples {
+            for (m, &x) in mean.iter_mut().zip(sample) {
+                *m = *m + x;
+            }
+        }
+        for m in &mut mean {
+            *m = *m / n;
+        }
+
+        let mut covariance = vec![vec![T::zero(); dim]; dim];
+        for sample in samples {
+            for i in 0..dim {
+                let di = sample[i] - mean[i];
+                for j in i..dim {
+                    covariance[i][j] = covariance[i][j] + di * (sample[j] - mean[j]);
+                }
+            }
+        }
+        let denom = n - T::one();
+        for i in 0..dim {
+            for j in i..dim {
+                let value = covariance[i][j] / denom;
+                covariance[i][j] = value;
+                covariance[j][i] = value;
+            }
+        }
+
+        let (eigenvalues, eigenvectors) = symmetric_eigen(covariance);
+
+        let total = eigenvalues
+            .iter()
+            .fold(T::zero(), |acc, &v| acc + v.max(T::zero()));
+        let ratios: Vec<T> = eigenvalues
+            .iter()
+            .map(|&v| {
+                if total > T::zero() {
+                    v.max(T::zero()) / total
+                } else {
+                    T::zero()
+                }
+            })
+            .collect();
+
+        let keep = self.select_count(&ratios)?;
+
+        self.mean = mean;
+        self.components = eigenvectors.into_iter().take(keep).collect();
+        self.explained_variance = eigenvalues
+            .into_iter()
+            .take(keep)
+            .map(|v| v.max(T::zero()))
+            .collect();
+        self.explained_variance_ratio = ratios.into_iter().take(keep).collect();
+        Ok(())
+    }
+
+    fn transform(&self, sample: &[T]) -> Result<Vec<T>, PreprocessingError> {
+        if !self.is_fitted() {
+            return Err(PreprocessingError::NotFitted);
+        }
+        if sample.len() != self.input_dim() {
+            return Err(PreprocessingError::DimensionMismatch {
+                expected: self.input_dim(),
+                actual: sample.len(),
+            });
+        }
+
+        Ok(self
+            .components
+            .iter()
+            .zip(&self.explained_variance)
+            .map(|(component, &variance)| {
+                let projection = component
+                    .iter()
+                    .zip(sample.iter().zip(&self.mean))
+                    .fold(T::zero(), |acc, (&axis, (&x, &m))| acc + axis * (x - m));
+                if self.whiten {
+                    projection / (variance + self.epsilon).sqrt()
+                } else {
+                    projection
+                }
+            })
+            .collect())
+    }
+
+    fn output_dim(&self) -> usize {
+        self.n_components()
+    }
+}
+
+/// Eigendecomposition of a symmetric matrix using cyclic Jacobi rotations.
+///
+/// Returns eigenvalues in descending order together with the matching
+/// eigenvectors (one vector per entry).
+fn symmetric_eigen<T: Float>(mut a: Vec<Vec<T>>) -> (Vec<T>, Vec<Vec<T>>) {
+    let n = a.len();
+    let mut v = vec![vec![T::zero(); n]; n];
+    for (i, row) in v.iter_mut().enumerate() {
+        row[i] = T::one();
+    }
+
+    let tolerance = T::epsilon() * cast(n * n);
+    for _ in 0..MAX_SWEEPS {
+        let off_diagonal = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .fold(T::zero(), |acc, (i, j)| acc + a[i][j] * a[i][j]);
+        let scale = (0..n).fold(T::zero(), |acc, i| acc + a[i][i] * a[i][i]);
+        if off_diagonal <= tolerance * tolerance * scale.max(T::min_positive_value()) {
+            break;
+        }
+
+        for p in 0..n {
+            for q in (p + 1)..n {
+                if a[p][q] == T::zero() {
+                    continue;
+                }
+                let two: T = cast(2.0);
+                let theta = (a[q][q] - a[p][p]) / (two * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + T::one()).sqrt());
+                let c = T::one() / (t * t + T::one()).sqrt();
+                let s = t * c;
+
+                for k in 0..n {
+                    let akp = a[k][p];
+                    let akq = a[k][q];
+                    a[k][p] = c * akp - s * akq;
+                    a[k][q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let apk = a[p][k];
+                    let aqk = a[q][k];
+                    a[p][k] = c * apk - s * aqk;
+                    a[q][k] = s * apk + c * aqk;
+                }
+                for row in v.iter_mut() {
+                    let vkp = row[p];
+                    let vkq = row[q];
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| {
+        a[j][j]
+            .partial_cmp(&a[i][i])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let eigenvalues = order.iter().map(|&i| a[i][i]).collect();
+    let eigenvectors = order
+        .iter()
+        .map(|&i| v.iter().map(|row| row[i]).collect())
+        .collect();
+    (eigenvalues, eigenvectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn correlated_samples() -> Vec<Vec<f64>> {
+        // Second feature is almost a copy of the first, third is small noise
+        (0..50)
+            .map(|i| {
+                let x = i as f64 / 10.0;
+                let noise = ((i * 7) % 5) as f64 * 0.01;
+                vec![x, 2.0 * x + noise, noise]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_explained_variance_selection() {
+        let samples = correlated_samples();
+        let mut pca = Pca::with_explained_variance(0.99);
+        pca.fit(&samples).unwrap();
+
+        assert_eq!(pca.n_components(), 1);
+        assert!(pca.explained_variance_ratio()[0] > 0.99);
+    }
+
+    #[test]
+    fn test_roundtrip_with_all_components() {
+        let samples = correlated_samples();
+        let mut pca = Pca::with_components(3);
+        pca.fit(&samples).unwrap();
+
+        for sample in &samples {
+            let projected = pca.transform(sample).unwrap();
+            let restored = pca.inverse_transform(&projected).unwrap();
+            for (a, b) in sample.iter().zip(&restored) {
+                assert_relative_eq!(*a, *b, epsilon = 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_whitening_gives_unit_variance() {
+        let samples = correlated_samples();
+        let mut pca = Pca::with_components(2).whiten(true);
+        let projected = pca.fit_transform(&samples).unwrap();
+
+        let n = projected.len() as f64;
+        for c in 0..2 {
+            let mean = projected.iter().map(|p| p[c]).sum::<f64>() / n;
+            let var = projected.iter().map(|p| (p[c] - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            assert_relative_eq!(mean, 0.0, epsilon = 1e-9);
+            assert_relative_eq!(var, 1.0, epsilon = 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let pca = Pca::<f32>::with_components(1);
+        assert_eq!(pca.transform(&[1.0]), Err(PreprocessingError::NotFitted));
+
+        let mut pca = Pca::<f32>::with_components(5);
+        let samples = vec![vec![1.0, 2.0], vec![2.0, 1.0]];
+        assert!(matches!(
+            pca.fit(&samples),
+            Err(PreprocessingError::InvalidConfiguration(_))
+        ));
+    }
+}
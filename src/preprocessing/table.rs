@@ -0,0 +1,389 @@
+//! Column-oriented tables as training data
+//!
+//! A [`Table`] holds named, typed columns the way a dataframe does, so
+//! tabular data from CSV readers or dataframe libraries can be turned into
+//! [`TrainingData`] by naming the feature and target columns. Dtypes are
+//! coerced as follows:
+//!
+//! - float, integer and boolean columns become one numeric value each
+//! - text columns whose values all parse as numbers are read as numbers
+//! - any other text column is categorical and expands to one value per
+//!   category through a [`OneHotEncoder`]
+//!
+//! [`TableEncoder`] keeps the fitted encoders so inference data is encoded
+//! with the categories seen in training.
+//!
+//! With the `polars` feature a `DataFrame` converts into a [`Table`], and
+//! [`TrainingData::from_dataframe`] reads training data from it directly.
+
+use super::{OneHotEncoder, PreprocessingError};
+use crate::numeric::cast;
+use crate::training::TrainingData;
+use num_traits::Float;
+
+/// Values of one column
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Float(Vec<f64>),
+    Int(Vec<i64>),
+    Bool(Vec<bool>),
+    Text(Vec<String>),
+}
+
+impl Column {
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Float(v) => v.len(),
+            Column::Int(v) => v.len(),
+            Column::Bool(v) => v.len(),
+            Column::Text(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Numeric values of the column, or `None` if it is categorical
+    fn numeric(&self) -> Option<Vec<f64>> {
+        match self {
+            Column::Float(v) => Some(v.clone()),
+            Column::Int(v) => Some(v.iter().map(|&x| x as f64).collect()),
+            Column::Bool(v) => Some(v.iter().map(|&x| f64::from(u8::from(x))).collect()),
+            Column::Text(v) => v.iter().map(|s| s.trim().parse().ok()).collect(),
+        }
+    }
+}
+
+/// Named columns of equal length
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    columns: Vec<(String, Column)>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a column; it must have as many rows as the columns already present
+    pub fn with_column(
+        mut self,
+        name: impl Into<String>,
+        column: Column,
+    ) -> Result<Self, PreprocessingError> {
+        if let Some((_, first)) = self.columns.first() {
+            if first.len() != column.len() {
+                return Err(PreprocessingError::DimensionMismatch {
+                    expected: first.len(),
+                    actual: column.len(),
+                });
+            }
+        }
+        self.columns.push((name.into(), column));
+        Ok(self)
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|(n, _)| n == name).map(|(_, c)| c)
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.columns.first().map_or(0, |(_, c)| c.len())
+    }
+
+    fn require(&self, name: &str) -> Result<&Column, PreprocessingError> {
+        self.column(name).ok_or_else(|| {
+            PreprocessingError::InvalidConfiguration(format!("unknown column '{name}'"))
+        })
+    }
+}
+
+/// How a fitted column is turned into values
+#[derive(Debug, Clone, PartialEq)]
+enum ColumnEncoding {
+    Numeric,
+    Categorical(OneHotEncoder),
+}
+
+/// Feature and target encodings fitted on a [`Table`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableEncoder {
+    features: Vec<(String, ColumnEncoding)>,
+    targets: Vec<(String, ColumnEncoding)>,
+}
+
+impl TableEncoder {
+    /// Decide the encoding of every named column and fit categorical ones
+    pub fn fit(
+        table: &Table,
+        feature_cols: &[&str],
+        target_cols: &[&str],
+    ) -> Result<Self, PreprocessingError> {
+        if feature_cols.is_empty() || target_cols.is_empty() {
+            return Err(PreprocessingError::InvalidConfiguration(
+                "at least one feature and one target column are required".to_string(),
+            ));
+        }
+        let fit = |names: &[&str]| {
+            names
+                .iter()
+                .map(|&name| {
+                    let column = table.require(name)?;
+                    let encoding = match (column, column.numeric()) {
+                        (_, Some(_)) => ColumnEncoding::Numeric,
+                        (Column::Text(values), None) => {
+                            let mut encoder = OneHotEncoder::new();
+                            encoder.fit(values)?;
+                            ColumnEncoding::Categorical(encoder)
+                        }
+                        (_, None) => unreachable!("only text columns can fail to be numeric"),
+                    };
+                    Ok((name.to_string(), encoding))
+                })
+                .collect::<Result<Vec<_>, PreprocessingError>>()
+        };
+        Ok(Self {
+            features: fit(feature_cols)?,
+            targets: fit(target_cols)?,
+        })
+    }
+
+    /// One-hot encoder of a categorical column
+    pub fn encoder(&self, name: &str) -> Option<&OneHotEncoder> {
+        self.features
+            .iter()
+            .chain(&self.targets)
+            .find_map(|(n, encoding)| match encoding {
+                ColumnEncoding::Categorical(encoder) if n == name => Some(encoder),
+                _ => None,
+            })
+    }
+
+    /// Width of an encoded input row
+    pub fn num_inputs(&self) -> usize {
+        width(&self.features)
+    }
+
+    /// Width of an encoded target row
+    pub fn num_outputs(&self) -> usize {
+        width(&self.targets)
+    }
+
+    /// Encoded feature rows of `table`, e.g. for inference
+    pub fn encode_features<T: Float>(
+        &self,
+        table: &Table,
+    ) -> Result<Vec<Vec<T>>, PreprocessingError> {
+        encode_rows(table, &self.features)
+    }
+
+    /// Encoded features and targets of `table`
+    pub fn encode<T: Float>(&self, table: &Table) -> Result<TrainingData<T>, PreprocessingError> {
+        Ok(TrainingData {
+            inputs: encode_rows(table, &self.features)?,
+            outputs: encode_rows(table, &self.targets)?,
+        })
+    }
+}
+
+fn width(columns: &[(String, ColumnEncoding)]) -> usize {
+    columns
+        .iter()
+        .map(|(_, encoding)| match encoding {
+            ColumnEncoding::Numeric => 1,
+            ColumnEncoding::Categorical(encoder) => encoder.output_dim(),
+        })
+        .sum()
+}
+
+fn encode_rows<T: Float>(
+    table: &Table,
+    columns: &[(String, ColumnEncoding)],
+) -> Result<Vec<Vec<T>>, PreprocessingError> {
+    let mut rows = vec![Vec::with_capacity(width(columns)); table.num_rows()];
+    for (name, encoding) in columns {
+        let column = table.require(name)?;
+        match encoding {
+            ColumnEncoding::Numeric => {
+                let values = column.numeric().ok_or_else(|| {
+                    PreprocessingError::InvalidConfiguration(format!(
+                        "column '{name}' is no longer numeric"
+                    ))
+                })?;
+                for (row, value) in rows.iter_mut().zip(values) {
+                    if value.is_nan() {
+                        return Err(PreprocessingError::InvalidConfiguration(format!(
+                            "missing value in column '{name}'"
+                        )));
+                    }
+                    row.push(cast(value));
+                }
+            }
+            ColumnEncoding::Categorical(encoder) => {
+                let Column::Text(values) = column else {
+                    return Err(PreprocessingError::InvalidConfiguration(format!(
+                        "column '{name}' is no longer categorical"
+                    )));
+                };
+                for (row, value) in rows.iter_mut().zip(values) {
+                    row.extend(encoder.encode::<T>(value)?);
+                }
+            }
+        }
+    }
+    Ok(rows)
+}
+
+impl<T: Float> TrainingData<T> {
+    /// Training data from the named columns of `table`; numeric columns are
+    /// used as is and categorical text columns are one-hot encoded
+    pub fn from_table(
+        table: &Table,
+        feature_cols: &[&str],
+        target_cols: &[&str],
+    ) -> Result<Self, PreprocessingError> {
+        TableEncoder::fit(table, feature_cols, target_cols)?.encode(table)
+    }
+}
+
+/// Table with the columns of a dataframe
+///
+/// Float columns keep nulls as NaN, which [`TableEncoder`] reports as
+/// missing values if the column is used; nulls in other columns and
+/// unsupported dtypes are errors.
+#[cfg(feature = "polars")]
+impl TryFrom<&polars::prelude::DataFrame> for Table {
+    type Error = PreprocessingError;
+
+    fn try_from(frame: &polars::prelude::DataFrame) -> Result<Self, Self::Error> {
+        use polars::prelude::DataType;
+
+        let mut table = Table::new();
+        for series in frame.iter() {
+            let name = series.name().to_string();
+            let error = |e: &dyn std::fmt::Display| {
+                PreprocessingError::InvalidConfiguration(format!("column '{name}': {e}"))
+            };
+            let nulls = || error(&"null values");
+            let dtype = series.dtype();
+            let column = if dtype.is_float() {
+                let values = series.cast(&DataType::Float64).map_err(|e| error(&e))?;
+                let values = values.f64().map_err(|e| error(&e))?;
+                Column::Float(values.iter().map(|v| v.unwrap_or(f64::NAN)).collect())
+            } else if dtype.is_integer() {
+                let values = series.cast(&DataType::Int64).map_err(|e| error(&e))?;
+                let values = values.i64().map_err(|e| error(&e))?;
+                Column::Int(values.iter().collect::<Option<_>>().ok_or_else(nulls)?)
+            } else if dtype.is_bool() {
+                let values = series.bool().map_err(|e| error(&e))?;
+                Column::Bool(values.iter().collect::<Option<_>>().ok_or_else(nulls)?)
+            } else if dtype.is_string() {
+                let values = series.str().map_err(|e| error(&e))?;
+                let values = values.iter().map(|v| v.map(str::to_string));
+                Column::Text(values.collect::<Option<_>>().ok_or_else(nulls)?)
+            } else {
+                return Err(error(&format!("unsupported dtype {dtype}")));
+            };
+            table = table.with_column(name, column)?;
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(feature = "polars")]
+impl<T: Float> TrainingData<T> {
+    /// Training data from the named columns of a dataframe, coerced as by
+    /// [`TrainingData::from_table`]
+    pub fn from_dataframe(
+        frame: &polars::prelude::DataFrame,
+        feature_cols: &[&str],
+        target_cols: &[&str],
+    ) -> Result<Self, PreprocessingError> {
+        Self::from_table(&Table::try_from(frame)?, feature_cols, target_cols)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        let text = |v: &[&str]| Column::Text(v.iter().map(|s| s.to_string()).collect());
+        Table::new()
+            .with_column("size", Column::Float(vec![1.5, 2.0, 0.5]))
+            .and_then(|t| t.with_column("count", Column::Int(vec![3, 1, 2])))
+            .and_then(|t| t.with_column("color", text(&["red", "blue", "red"])))
+            .and_then(|t| t.with_column("weight", text(&["10", " 20", "30"])))
+            .and_then(|t| t.with_column("label", Column::Bool(vec![true, false, true])))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_from_table_coerces_dtypes() {
+        let data =
+            TrainingData::<f32>::from_table(&table(), &["size", "color", "weight"], &["label"])
+                .unwrap();
+        assert_eq!(data.inputs[0], vec![1.5, 0.0, 1.0, 10.0]);
+        assert_eq!(data.inputs[1], vec![2.0, 1.0, 0.0, 20.0]);
+        assert_eq!(data.outputs, vec![vec![1.0], vec![0.0], vec![1.0]]);
+
+        // Categorical targets are one-hot too
+        let data = TrainingData::<f64>::from_table(&table(), &["count"], &["color"]).unwrap();
+        assert_eq!(data.outputs[1], vec![1.0, 0.0]);
+
+        assert!(TrainingData::<f64>::from_table(&table(), &["missing"], &["label"]).is_err());
+        assert!(Table::new()
+            .with_column("a", Column::Int(vec![1]))
+            .unwrap()
+            .with_column("b", Column::Int(vec![1, 2]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_encoder_reuses_training_categories() {
+        let encoder = TableEncoder::fit(&table(), &["color", "count"], &["label"]).unwrap();
+        assert_eq!((encoder.num_inputs(), encoder.num_outputs()), (3, 1));
+        assert_eq!(
+            encoder.encoder("color").unwrap().categories(),
+            ["blue", "red"]
+        );
+
+        let unseen = Table::new()
+            .with_column("color", Column::Text(vec!["green".to_string()]))
+            .and_then(|t| t.with_column("count", Column::Int(vec![7])))
+            .unwrap();
+        let rows: Vec<Vec<f64>> = encoder.encode_features(&unseen).unwrap();
+        assert_eq!(rows, vec![vec![0.0, 0.0, 7.0]]);
+    }
+
+    #[cfg(feature = "polars")]
+    #[test]
+    fn test_from_dataframe() {
+        use polars::prelude::{df, NamedFrom, Series};
+
+        let frame = df! {
+            "size" => [1.5f32, 2.0, 0.5],
+            "count" => [3i32, 1, 2],
+            "color" => ["red", "blue", "red"],
+            "label" => [true, false, true],
+        }
+        .unwrap();
+        let data =
+            TrainingData::<f64>::from_dataframe(&frame, &["size", "color"], &["label"]).unwrap();
+        assert_eq!(data.inputs[1], vec![2.0, 1.0, 0.0]);
+        assert_eq!(data.outputs, vec![vec![1.0], vec![0.0], vec![1.0]]);
+        assert_eq!(
+            Table::try_from(&frame).unwrap().column("count"),
+            Some(&Column::Int(vec![3, 1, 2]))
+        );
+
+        let gaps = df! { "x" => [Some(1.0), None], "y" => [Some(1i64), None] }.unwrap();
+        let table = Table::try_from(&gaps.select(["x"]).unwrap()).unwrap();
+        assert!(TrainingData::<f64>::from_table(&table, &["x"], &["x"]).is_err());
+        assert!(Table::try_from(&gaps).is_err());
+        let nulls = Series::new_null("n".into(), 2);
+        let frame = polars::prelude::DataFrame::new(vec![nulls.into()]).unwrap();
+        assert!(Table::try_from(&frame).is_err());
+    }
+}
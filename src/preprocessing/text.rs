@@ -0,0 +1,303 @@
+//! Text vectorization
+//!
+//! Both vectorizers split text with [`tokenize`] (lowercase alphanumeric
+//! runs) and produce fixed-width, L2-normalized vectors for small
+//! text-classification networks:
+//!
+//! - [`HashingVectorizer`] needs no fitting: tokens are hashed into a fixed
+//!   number of buckets, so unseen words still land somewhere
+//! - [`TfidfVectorizer`] fits a vocabulary and inverse document frequencies
+//!   on a corpus and weights term counts by them
+//!
+//! Hashing uses FNV-1a, so vectors are stable across runs and platforms.
+
+use super::PreprocessingError;
+use crate::numeric::cast;
+use num_traits::Float;
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Lowercase alphanumeric tokens of `text`
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn fnv1a(token: &str) -> u64 {
+    token.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Scale `values` to unit L2 norm; all-zero vectors are left alone
+fn l2_normalize<T: Float>(values: &mut [T]) {
+    let norm = values.iter().fold(T::zero(), |acc, &v| acc + v * v).sqrt();
+    if norm > T::zero() {
+        values.iter_mut().for_each(|v| *v = *v / norm);
+    }
+}
+
+/// Stateless bag-of-words vectorizer using the hashing trick
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HashingVectorizer {
+    n_features: usize,
+    binary: bool,
+    normalize: bool,
+}
+
+impl HashingVectorizer {
+    /// Vectorizer with `n_features` buckets (at least 1)
+    pub fn new(n_features: usize) -> Self {
+        Self {
+            n_features: n_features.max(1),
+            binary: false,
+            normalize: true,
+        }
+    }
+
+    /// Record token presence instead of counts
+    pub fn with_binary(mut self, binary: bool) -> Self {
+        self.binary = binary;
+        self
+    }
+
+    /// Scale vectors to unit length (on by default)
+    pub fn with_normalization(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    pub fn output_dim(&self) -> usize {
+        self.n_features
+    }
+
+    pub fn transform<T: Float>(&self, text: &str) -> Vec<T> {
+        let mut values = vec![T::zero(); self.n_features];
+        for token in tokenize(text) {
+            let bucket = (fnv1a(&token) % self.n_features as u64) as usize;
+            values[bucket] = if self.binary {
+                T::one()
+            } else {
+                values[bucket] + T::one()
+            };
+        }
+        if self.normalize {
+            l2_normalize(&mut values);
+        }
+        values
+    }
+
+    pub fn transform_batch<T: Float>(&self, texts: &[impl AsRef<str>]) -> Vec<Vec<T>> {
+        texts.iter().map(|t| self.transform(t.as_ref())).collect()
+    }
+}
+
+/// TF-IDF vectorizer with a fitted vocabulary
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TfidfVectorizer {
+    min_df: usize,
+    max_features: Option<usize>,
+    sublinear_tf: bool,
+    /// Sorted terms
+    vocabulary: Vec<String>,
+    /// Inverse document frequency of each term
+    idf: Vec<f64>,
+}
+
+impl TfidfVectorizer {
+    pub fn new() -> Self {
+        Self {
+            min_df: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Ignore terms that appear in fewer than `min_df` documents
+    pub fn with_min_df(mut self, min_df: usize) -> Self {
+        self.min_df = min_df.max(1);
+        self
+    }
+
+    /// Keep only the `max_features` most frequent terms
+    pub fn with_max_features(mut self, max_features: usize) -> Self {
+        self.max_features = Some(max_features);
+        self
+    }
+
+    /// Use `1 + ln(count)` instead of raw term counts
+    pub fn with_sublinear_tf(mut self, sublinear_tf: bool) -> Self {
+        self.sublinear_tf = sublinear_tf;
+        self
+    }
+
+    /// Learn the vocabulary and smoothed inverse document frequencies
+    /// `ln((1 + n) / (1 + df)) + 1` of `documents`
+    pub fn fit(&mut self, documents: &[impl AsRef<str>]) -> Result<(), PreprocessingError> {
+        if documents.is_empty() {
+            return Err(PreprocessingError::InsufficientData(
+                "no documents provided".to_string(),
+            ));
+        }
+        let mut document_frequency: HashMap<String, (usize, usize)> = HashMap::new();
+        for document in documents {
+            let mut tokens = tokenize(document.as_ref());
+            tokens.sort_unstable();
+            let mut last: Option<&String> = None;
+            for token in &tokens {
+                let entry = document_frequency.entry(token.clone()).or_default();
+                entry.1 += 1;
+                if last != Some(token) {
+                    entry.0 += 1;
+                }
+                last = Some(token);
+            }
+        }
+
+        let mut terms: Vec<(String, usize, usize)> = document_frequency
+            .into_iter()
+            .filter(|(_, (df, _))| *df >= self.min_df)
+            .map(|(term, (df, count))| (term, df, count))
+            .collect();
+        if let Some(max_features) = self.max_features {
+            // Most frequent first, ties broken alphabetically for determinism
+            terms.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+            terms.truncate(max_features);
+        }
+        if terms.is_empty() {
+            return Err(PreprocessingError::InsufficientData(
+                "no terms left in the vocabulary".to_string(),
+            ));
+        }
+        terms.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let n = documents.len() as f64;
+        self.idf = terms
+            .iter()
+            .map(|(_, df, _)| ((1.0 + n) / (1.0 + *df as f64)).ln() + 1.0)
+            .collect();
+        self.vocabulary = terms.into_iter().map(|(term, _, _)| term).collect();
+        Ok(())
+    }
+
+    pub fn is_fitted(&self) -> bool {
+        !self.vocabulary.is_empty()
+    }
+
+    /// Fitted terms, in vector order
+    pub fn vocabulary(&self) -> &[String] {
+        &self.vocabulary
+    }
+
+    pub fn idf(&self) -> &[f64] {
+        &self.idf
+    }
+
+    pub fn output_dim(&self) -> usize {
+        self.vocabulary.len()
+    }
+
+    /// L2-normalized TF-IDF vector of `text`; unknown terms are ignored
+    pub fn transform<T: Float>(&self, text: &str) -> Result<Vec<T>, PreprocessingError> {
+        if !self.is_fitted() {
+            return Err(PreprocessingError::NotFitted);
+        }
+        let mut counts = vec![0usize; self.vocabulary.len()];
+        for token in tokenize(text) {
+            if let Ok(index) = self.vocabulary.binary_search(&token) {
+                counts[index] += 1;
+            }
+        }
+        let mut values: Vec<T> = counts
+            .iter()
+            .zip(&self.idf)
+            .map(|(&count, &idf)| {
+                let tf = match count {
+                    0 => 0.0,
+                    _ if self.sublinear_tf => 1.0 + (count as f64).ln(),
+                    _ => count as f64,
+                };
+                cast(tf * idf)
+            })
+            .collect();
+        l2_normalize(&mut values);
+        Ok(values)
+    }
+
+    pub fn transform_batch<T: Float>(
+        &self,
+        texts: &[impl AsRef<str>],
+    ) -> Result<Vec<Vec<T>>, PreprocessingError> {
+        texts.iter().map(|t| self.transform(t.as_ref())).collect()
+    }
+
+    pub fn fit_transform<T: Float>(
+        &mut self,
+        documents: &[impl AsRef<str>],
+    ) -> Result<Vec<Vec<T>>, PreprocessingError> {
+        self.fit(documents)?;
+        self.transform_batch(documents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_and_hashing() {
+        assert_eq!(
+            tokenize("Hello, world! hello-42"),
+            ["hello", "world", "hello", "42"]
+        );
+
+        let vectorizer = HashingVectorizer::new(16).with_normalization(false);
+        let counts: Vec<f64> = vectorizer.transform("a b a");
+        assert_eq!(counts.iter().sum::<f64>(), 3.0);
+        assert!(counts.contains(&2.0));
+        assert_eq!(counts, vectorizer.transform::<f64>("A, B; A"));
+
+        let binary: Vec<f64> = vectorizer.with_binary(true).transform("a b a");
+        assert_eq!(binary.iter().sum::<f64>(), 2.0);
+
+        let normalized: Vec<f32> = HashingVectorizer::new(8).transform("x y z");
+        let norm: f32 = normalized.iter().map(|v| v * v).sum();
+        assert!((norm - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_tfidf() {
+        let documents = ["the cat sat", "the dog sat", "the cat ran away"];
+        let mut vectorizer = TfidfVectorizer::new();
+        assert_eq!(
+            vectorizer.transform::<f64>("cat"),
+            Err(PreprocessingError::NotFitted)
+        );
+        let vectors: Vec<Vec<f64>> = vectorizer.fit_transform(&documents).unwrap();
+        assert_eq!(
+            vectorizer.vocabulary(),
+            ["away", "cat", "dog", "ran", "sat", "the"]
+        );
+        // "the" appears everywhere and gets the smallest weight
+        let the = vectorizer.idf()[5];
+        assert!((the - 1.0).abs() < 1e-12);
+        assert!(vectorizer.idf().iter().all(|&idf| idf >= the));
+        assert!(vectors[0][1] > vectors[0][5]);
+        assert_eq!(
+            vectorizer.transform::<f64>("unknown words").unwrap(),
+            vec![0.0; 6]
+        );
+
+        let mut small = TfidfVectorizer::new().with_min_df(2);
+        small.fit(&documents).unwrap();
+        assert_eq!(small.vocabulary(), ["cat", "sat", "the"]);
+        let mut top = TfidfVectorizer::new().with_max_features(1);
+        top.fit(&documents).unwrap();
+        assert_eq!(top.vocabulary(), ["the"]);
+    }
+}
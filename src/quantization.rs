@@ -0,0 +1,399 @@
+//! Post-training int8 quantization
+//!
+//! [`QuantizedNetwork::quantize`] turns a trained `Network<f32>` into an
+//! inference-only network with `i8` weights, a quarter of the `f32` size.
+//! Every layer of the [finalized](Network::finalize) network gets
+//!
+//! - symmetric weights: one scale per layer maps the largest weight
+//!   magnitude to 127, with a zero point of 0, and
+//! - affine inputs: a scale and zero point per layer, calibrated from the
+//!   range of the values that reach the layer when a representative dataset
+//!   is run through the `f32` network.
+//!
+//! At run time each layer quantizes its input, computes `W x` with an
+//! integer dot product (AVX2 on x86_64, NEON on aarch64, scalar otherwise)
+//! and rescales the `i32` sums to `f32` before the bias and activation, so
+//! outputs come back dequantized. Inputs outside the calibrated range are
+//! clamped, so the calibration data should cover the inputs expected in
+//! deployment.
+
+use crate::network::{FusedLayer, NetworkError};
+use crate::Network;
+
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+use std::arch::x86_64::*;
+
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+use std::arch::aarch64::*;
+
+/// Affine mapping `real = scale * (q - zero_point)` between `f32` and `i8`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantizationParams {
+    pub scale: f32,
+    pub zero_point: i8,
+}
+
+impl QuantizationParams {
+    /// Parameters covering `[min, max]`, widened to include 0 so that zero
+    /// is represented exactly
+    pub fn from_range(min: f32, max: f32) -> Self {
+        let (min, max) = (min.min(0.0), max.max(0.0));
+        let scale = if max > min { (max - min) / 255.0 } else { 1.0 };
+        let zero_point = (-128.0 - min / scale).round().clamp(-128.0, 127.0) as i8;
+        Self { scale, zero_point }
+    }
+
+    pub fn quantize(&self, value: f32) -> i8 {
+        (value / self.scale + self.zero_point as f32)
+            .round()
+            .clamp(-128.0, 127.0) as i8
+    }
+
+    pub fn dequantize(&self, value: i8) -> f32 {
+        self.scale * (value as i32 - self.zero_point as i32) as f32
+    }
+}
+
+/// Integer dot-product kernel used by [`QuantizedNetwork::run`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Int8Kernel {
+    Scalar,
+    /// 16 products per step, widened to `i16` and summed pairwise into `i32`
+    Avx2,
+    /// 16 products per step with widening multiplies and pairwise
+    /// accumulation
+    Neon,
+}
+
+impl Int8Kernel {
+    /// Fastest kernel supported by the running CPU
+    pub fn detect() -> Self {
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if is_x86_feature_detected!("avx2") {
+            return Self::Avx2;
+        }
+        #[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+        return Self::Neon;
+        #[allow(unreachable_code)]
+        Self::Scalar
+    }
+
+    /// Whether the kernel can run on this CPU
+    pub fn is_supported(self) -> bool {
+        match self {
+            Self::Scalar => true,
+            #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+            Self::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+            Self::Neon => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// `sum(a[i] * b[i])` over the common length
+    pub fn dot(self, a: &[i8], b: &[i8]) -> i32 {
+        let n = a.len().min(b.len());
+        let (a, b) = (&a[..n], &b[..n]);
+        match self {
+            // SAFETY: AVX2 support is checked and both slices have length `n`
+            #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+            Self::Avx2 if self.is_supported() => unsafe { dot_avx2(a, b) },
+            // SAFETY: NEON is part of the aarch64 baseline and both slices
+            // have length `n`
+            #[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+            Self::Neon => unsafe { dot_neon(a, b) },
+            _ => dot_scalar(a, b),
+        }
+    }
+}
+
+fn dot_scalar(a: &[i8], b: &[i8]) -> i32 {
+    a.iter().zip(b).map(|(&x, &y)| x as i32 * y as i32).sum()
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+#[target_feature(enable = "avx2")]
+unsafe fn dot_avx2(a: &[i8], b: &[i8]) -> i32 {
+    const STEP: usize = 16;
+    let chunks = a.len() / STEP;
+    let mut acc = _mm256_setzero_si256();
+    for chunk in 0..chunks {
+        let j = chunk * STEP;
+        // Sign-extend 16 x i8 to i16; madd sums adjacent products into i32
+        let x = _mm256_cvtepi8_epi16(_mm_loadu_si128(a.as_ptr().add(j) as *const __m128i));
+        let y = _mm256_cvtepi8_epi16(_mm_loadu_si128(b.as_ptr().add(j) as *const __m128i));
+        acc = _mm256_add_epi32(acc, _mm256_madd_epi16(x, y));
+    }
+    let lanes = std::mem::transmute::<__m256i, [i32; 8]>(acc);
+    lanes.iter().sum::<i32>() + dot_scalar(&a[chunks * STEP..], &b[chunks * STEP..])
+}
+
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+#[target_feature(enable = "neon")]
+unsafe fn dot_neon(a: &[i8], b: &[i8]) -> i32 {
+    const STEP: usize = 16;
+    let chunks = a.len() / STEP;
+    let mut acc = vdupq_n_s32(0);
+    for chunk in 0..chunks {
+        let j = chunk * STEP;
+        let x = vld1q_s8(a.as_ptr().add(j));
+        let y = vld1q_s8(b.as_ptr().add(j));
+        // i8 x i8 products fit in i16; pairwise-add them into i32 lanes
+        acc = vpadalq_s16(acc, vmull_s8(vget_low_s8(x), vget_low_s8(y)));
+        acc = vpadalq_s16(acc, vmull_high_s8(x, y));
+    }
+    vaddvq_s32(acc) + dot_scalar(&a[chunks * STEP..], &b[chunks * STEP..])
+}
+
+/// One fused layer with `i8` weights
+#[derive(Debug, Clone)]
+struct QuantizedLayer {
+    inputs: usize,
+    /// Row-major, one row per neuron
+    weights: Vec<i8>,
+    weight_scale: f32,
+    /// Sum of each weight row, to remove the input zero point from the
+    /// integer products
+    row_sums: Vec<i32>,
+    input: QuantizationParams,
+    /// Bias and activations (its `f32` weights are dropped)
+    layer: FusedLayer<f32>,
+}
+
+impl QuantizedLayer {
+    fn new(layer: &FusedLayer<f32>, input: QuantizationParams) -> Self {
+        let max = layer.weights.iter().fold(0.0f32, |acc, w| acc.max(w.abs()));
+        let weight_scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+        let weights: Vec<i8> = layer
+            .weights
+            .iter()
+            .map(|&w| (w / weight_scale).round().clamp(-127.0, 127.0) as i8)
+            .collect();
+        let row_sums = weights
+            .chunks_exact(layer.inputs.max(1))
+            .take(layer.rows())
+            .map(|row| row.iter().map(|&w| w as i32).sum())
+            .collect();
+        let mut layer = layer.clone();
+        layer.weights = Vec::new();
+        Self {
+            inputs: layer.inputs,
+            weights,
+            weight_scale,
+            row_sums,
+            input,
+            layer,
+        }
+    }
+
+    fn forward(&self, kernel: Int8Kernel, input: &[f32], quantized: &mut Vec<i8>) -> Vec<f32> {
+        quantized.clear();
+        quantized.extend(input.iter().map(|&x| self.input.quantize(x)));
+        let scale = self.weight_scale * self.input.scale;
+        let zero_point = self.input.zero_point as i32;
+        let mut output: Vec<f32> = self
+            .row_sums
+            .iter()
+            .enumerate()
+            .map(|(row, &row_sum)| {
+                let weights = &self.weights[row * self.inputs..(row + 1) * self.inputs];
+                let sum = kernel.dot(weights, quantized) - zero_point * row_sum;
+                sum as f32 * scale
+            })
+            .collect();
+        self.layer.finish(&mut output);
+        output
+    }
+}
+
+/// Inference-only network with `i8` weights and calibrated `i8` inputs
+#[derive(Debug, Clone)]
+pub struct QuantizedNetwork {
+    num_inputs: usize,
+    layers: Vec<QuantizedLayer>,
+    kernel: Int8Kernel,
+}
+
+impl QuantizedNetwork {
+    /// Quantize a finalized copy of `network`, calibrating the input range
+    /// of every layer on `calibration` samples
+    ///
+    /// # Errors
+    ///
+    /// Fails when `calibration` is empty or a sample does not match the
+    /// network's input count.
+    pub fn quantize(
+        network: &Network<f32>,
+        calibration: &[Vec<f32>],
+    ) -> Result<Self, NetworkError> {
+        if calibration.is_empty() {
+            return Err(NetworkError::EmptyCalibration);
+        }
+        let finalized = network.finalize()?;
+        let fused = finalized.layers();
+        let mut ranges = vec![(f32::INFINITY, f32::NEG_INFINITY); fused.len()];
+        for sample in calibration {
+            if sample.len() != finalized.num_inputs() {
+                return Err(NetworkError::InputSizeMismatch {
+                    expected: finalized.num_inputs(),
+                    actual: sample.len(),
+                });
+            }
+            let mut current = sample.clone();
+            for (layer, range) in fused.iter().zip(&mut ranges) {
+                for &x in &current {
+                    *range = (range.0.min(x), range.1.max(x));
+                }
+                let mut next: Vec<f32> = layer
+                    .weights
+                    .chunks_exact(layer.inputs.max(1))
+                    .take(layer.rows())
+                    .map(|row| row.iter().zip(&current).map(|(&w, &x)| w * x).sum())
+                    .collect();
+                next.resize(layer.rows(), 0.0);
+                layer.finish(&mut next);
+                current = next;
+            }
+        }
+
+        let layers = fused
+            .iter()
+            .zip(ranges)
+            .map(|(layer, (min, max))| {
+                QuantizedLayer::new(layer, QuantizationParams::from_range(min, max))
+            })
+            .collect();
+        Ok(Self {
+            num_inputs: finalized.num_inputs(),
+            layers,
+            kernel: Int8Kernel::detect(),
+        })
+    }
+
+    /// Run with `kernel` instead of the detected one; kernels the CPU lacks
+    /// fall back to [`Int8Kernel::Scalar`]
+    pub fn with_kernel(mut self, kernel: Int8Kernel) -> Self {
+        self.kernel = if kernel.is_supported() {
+            kernel
+        } else {
+            Int8Kernel::Scalar
+        };
+        self
+    }
+
+    pub fn kernel(&self) -> Int8Kernel {
+        self.kernel
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.layers
+            .last()
+            .map_or(self.num_inputs, |layer| layer.layer.rows())
+    }
+
+    /// Input quantization of each fused layer
+    pub fn input_params(&self) -> Vec<QuantizationParams> {
+        self.layers.iter().map(|layer| layer.input).collect()
+    }
+
+    /// Bytes of quantized weights plus one `f32` scale per layer
+    pub fn weight_bytes(&self) -> usize {
+        self.layers
+            .iter()
+            .map(|layer| layer.weights.len() + std::mem::size_of::<f32>())
+            .sum()
+    }
+
+    /// Dequantized outputs for `input`
+    pub fn run(&self, input: &[f32]) -> Result<Vec<f32>, NetworkError> {
+        if input.len() != self.num_inputs {
+            return Err(NetworkError::InputSizeMismatch {
+                expected: self.num_inputs,
+                actual: input.len(),
+            });
+        }
+        let mut quantized = Vec::with_capacity(self.num_inputs);
+        let mut current = input.to_vec();
+        for layer in &self.layers {
+            current = layer.forward(self.kernel, &current, &mut quantized);
+        }
+        Ok(current)
+    }
+
+    pub fn run_batch(&self, inputs: &[Vec<f32>]) -> Result<Vec<Vec<f32>>, NetworkError> {
+        inputs.iter().map(|input| self.run(input)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ActivationFunction, NetworkBuilder};
+
+    fn samples(n: usize, width: usize) -> Vec<Vec<f32>> {
+        (0..n)
+            .map(|i| {
+                (0..width)
+                    .map(|j| ((i * 7 + j * 13) % 23) as f32 / 11.0 - 1.0)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_params_round_trip() {
+        let params = QuantizationParams::from_range(0.5, 4.0);
+        // The range is widened to include zero, which maps exactly
+        assert_eq!(params.dequantize(params.quantize(0.0)), 0.0);
+        assert_eq!(params.quantize(4.0), 127);
+        assert_eq!(params.quantize(100.0), 127);
+        for x in [0.3f32, 1.7, 3.9] {
+            assert!((params.dequantize(params.quantize(x)) - x).abs() <= params.scale / 2.0);
+        }
+
+        let a: Vec<i8> = (0..37).map(|i| (i * 29 % 255) as u8 as i8).collect();
+        let b: Vec<i8> = (0..37).map(|i| (i * 53 % 251) as u8 as i8).collect();
+        assert_eq!(Int8Kernel::detect().dot(&a, &b), dot_scalar(&a, &b));
+        assert_eq!(
+            Int8Kernel::detect().dot(&[-128; 40], &[-128; 40]),
+            40 * 16384
+        );
+    }
+
+    #[test]
+    fn test_quantized_network_tracks_f32() {
+        let network = NetworkBuilder::<f32>::new()
+            .input_layer(20)
+            .hidden_layer_with_activation(24, ActivationFunction::ReLU, 1.0)
+            .hidden_layer(12)
+            .output_layer_with_activation(3, ActivationFunction::Linear, 1.0)
+            .build();
+        let calibration = samples(64, 20);
+        let quantized = QuantizedNetwork::quantize(&network, &calibration).unwrap();
+        assert_eq!(quantized.num_outputs(), 3);
+        assert_eq!(quantized.input_params().len(), 3);
+        assert!(quantized.weight_bytes() * 3 < network.total_connections() * 4);
+
+        let finalized = network.finalize().unwrap();
+        let scalar = quantized.clone().with_kernel(Int8Kernel::Scalar);
+        for input in samples(16, 20) {
+            let expected = finalized.run(&input).unwrap();
+            let actual = quantized.run(&input).unwrap();
+            assert_eq!(actual, scalar.run(&input).unwrap());
+            for (a, e) in actual.iter().zip(&expected) {
+                assert!((a - e).abs() < 0.02, "{a} vs {e}");
+            }
+        }
+
+        assert!(matches!(
+            QuantizedNetwork::quantize(&network, &[]),
+            Err(NetworkError::EmptyCalibration)
+        ));
+        assert!(quantized.run(&[0.0; 3]).is_err());
+    }
+}
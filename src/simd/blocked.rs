@@ -0,0 +1,212 @@
+//! Row-panel weight layout for matrix-vector products
+//!
+//! A row-major `matvec` walks one row at a time and finishes every row with a
+//! horizontal sum. [`PanelMatrix`] repacks the matrix into panels of
+//! [`PANEL_WIDTH`] rows stored column by column, so the kernel keeps one
+//! accumulator register per panel, reads each panel front to back as a single
+//! contiguous stream of 32-byte aligned vectors, and broadcasts one input
+//! value per column. No horizontal sums, no unaligned loads and no per-row
+//! tail handling; the last panel is padded with extra rows instead. Padding
+//! is zero in release builds and NaN in debug builds, so a kernel that lets a
+//! padding lane reach its output fails loudly in tests.
+//!
+//! Repack once after training (the layout is read-only) and call
+//! [`CpuSimdOps::matvec_panels`]. The `inference` bench compares both layouts.
+
+use super::{check_len, CpuSimdOps, SimdError};
+
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+use std::arch::x86_64::*;
+
+/// Rows per panel: one AVX2 register, or two NEON registers
+pub const PANEL_WIDTH: usize = 8;
+
+/// One column of a panel, aligned for full-width vector loads
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C, align(32))]
+struct PanelColumn([f32; PANEL_WIDTH]);
+
+/// Matrix stored as column-major panels of [`PANEL_WIDTH`] rows
+#[derive(Debug, Clone)]
+pub struct PanelMatrix {
+    rows: usize,
+    cols: usize,
+    /// `cols` columns per panel, panels in row order
+    panels: Vec<PanelColumn>,
+}
+
+impl PanelMatrix {
+    /// Repack a row-major `rows x cols` matrix
+    pub fn pack(matrix: &[f32], rows: usize, cols: usize) -> Self {
+        assert_eq!(matrix.len(), rows * cols, "matrix size mismatch");
+        let num_panels = rows.div_ceil(PANEL_WIDTH);
+        let padding = if cfg!(debug_assertions) {
+            f32::NAN
+        } else {
+            0.0
+        };
+        let mut panels = vec![PanelColumn([padding; PANEL_WIDTH]); num_panels * cols];
+        for (row, values) in matrix.chunks_exact(cols.max(1)).take(rows).enumerate() {
+            let (panel, lane) = (row / PANEL_WIDTH, row % PANEL_WIDTH);
+            for (column, &value) in values.iter().enumerate() {
+                panels[panel * cols + column].0[lane] = value;
+            }
+        }
+        Self { rows, cols, panels }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// Back to row-major
+    pub fn unpack(&self) -> Vec<f32> {
+        let mut matrix = vec![0.0; self.rows * self.cols];
+        for (row, values) in matrix.chunks_exact_mut(self.cols.max(1)).enumerate() {
+            let (panel, lane) = (row / PANEL_WIDTH, row % PANEL_WIDTH);
+            for (column, value) in values.iter_mut().enumerate() {
+                *value = self.panels[panel * self.cols + column].0[lane];
+            }
+        }
+        matrix
+    }
+
+    /// Columns of each panel, in row order
+    fn panels(&self) -> impl Iterator<Item = &[PanelColumn]> {
+        self.panels.chunks_exact(self.cols.max(1))
+    }
+}
+
+impl PartialEq for PanelMatrix {
+    /// Compares the entries only; padding is not part of the matrix
+    fn eq(&self, other: &Self) -> bool {
+        self.rows == other.rows && self.cols == other.cols && self.unpack() == other.unpack()
+    }
+}
+
+/// Store a panel's accumulated rows, dropping the zero-padded ones
+fn store_panel(y: &mut [f32], panel: usize, rows: usize, sums: &[f32; PANEL_WIDTH]) {
+    let start = panel * PANEL_WIDTH;
+    let end = (start + PANEL_WIDTH).min(rows);
+    y[start..end].copy_from_slice(&sums[..end - start]);
+}
+
+impl CpuSimdOps {
+    /// `y = A x` for a panel-packed matrix; fails if `x` or `y` is too short
+    pub fn matvec_panels(
+        &self,
+        a: &PanelMatrix,
+        x: &[f32],
+        y: &mut [f32],
+    ) -> Result<(), SimdError> {
+        check_len("x", x.len(), &[a.cols])?;
+        check_len("y", y.len(), &[a.rows])?;
+        if a.cols == 0 {
+            y[..a.rows].fill(0.0);
+            return Ok(());
+        }
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 and FMA were detected, panel columns are 32-byte
+            // aligned and the lengths were checked
+            unsafe { matvec_panels_avx2(a, x, y) };
+            return Ok(());
+        }
+        // SAFETY: NEON is part of the aarch64 baseline and the lengths were
+        // checked above
+        #[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+        unsafe {
+            matvec_panels_neon(a, x, y)
+        }
+        #[cfg(not(all(target_arch = "aarch64", not(feature = "safe-only"))))]
+        matvec_panels_scalar(a, x, y);
+        Ok(())
+    }
+}
+
+#[cfg_attr(
+    all(target_arch = "aarch64", not(feature = "safe-only")),
+    allow(dead_code)
+)]
+fn matvec_panels_scalar(a: &PanelMatrix, x: &[f32], y: &mut [f32]) {
+    for (panel, columns) in a.panels().enumerate() {
+        let mut sums = [0.0f32; PANEL_WIDTH];
+        for (column, &value) in columns.iter().zip(x) {
+            for (sum, &weight) in sums.iter_mut().zip(&column.0) {
+                *sum += weight * value;
+            }
+        }
+        store_panel(y, panel, a.rows, &sums);
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn matvec_panels_avx2(a: &PanelMatrix, x: &[f32], y: &mut [f32]) {
+    for (panel, columns) in a.panels().enumerate() {
+        let mut sum = _mm256_setzero_ps();
+        for (column, &value) in columns.iter().zip(x) {
+            let weights = _mm256_load_ps(column.0.as_ptr());
+            sum = _mm256_fmadd_ps(weights, _mm256_set1_ps(value), sum);
+        }
+        let sums = std::mem::transmute::<__m256, [f32; PANEL_WIDTH]>(sum);
+        store_panel(y, panel, a.rows, &sums);
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+unsafe fn matvec_panels_neon(a: &PanelMatrix, x: &[f32], y: &mut [f32]) {
+    use std::arch::aarch64::*;
+
+    for (panel, columns) in a.panels().enumerate() {
+        let mut low = vdupq_n_f32(0.0);
+        let mut high = vdupq_n_f32(0.0);
+        for (column, &value) in columns.iter().zip(x) {
+            let pointer = column.0.as_ptr();
+            let broadcast = vdupq_n_f32(value);
+            low = vfmaq_f32(low, vld1q_f32(pointer), broadcast);
+            high = vfmaq_f32(high, vld1q_f32(pointer.add(4)), broadcast);
+        }
+        let mut sums = [0.0f32; PANEL_WIDTH];
+        vst1q_f32(sums.as_mut_ptr(), low);
+        vst1q_f32(sums.as_mut_ptr().add(4), high);
+        store_panel(y, panel, a.rows, &sums);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::SimdConfig;
+    use super::*;
+
+    #[test]
+    fn test_panel_matvec_matches_row_major() {
+        for (rows, cols) in [(1, 1), (8, 8), (13, 21), (30, 3)] {
+            let matrix: Vec<f32> = (0..rows * cols)
+                .map(|i| ((i * 29 % 53) as f32 - 26.0) / 13.0)
+                .collect();
+            let x: Vec<f32> = (0..cols).map(|i| (i as f32 * 0.7).cos()).collect();
+            let packed = PanelMatrix::pack(&matrix, rows, cols);
+            assert_eq!(packed.unpack(), matrix);
+            assert_eq!(packed.panels.as_ptr() as usize % 32, 0);
+
+            let scalar = CpuSimdOps::new(SimdConfig {
+                use_avx2: false,
+                ..SimdConfig::default()
+            });
+            let mut expected = vec![0.0; rows];
+            scalar.matvec_scalar(&matrix, &x, &mut expected, rows, cols);
+            for ops in [scalar, CpuSimdOps::new_with_defaults()] {
+                let mut y = vec![f32::NAN; rows];
+                ops.matvec_panels(&packed, &x, &mut y).unwrap();
+                for (a, b) in y.iter().zip(&expected) {
+                    assert!((a - b).abs() < 1e-4, "{rows}x{cols}: {a} vs {b}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,414 @@
+//! Kernel configuration
+//!
+//! [`SimdConfig::default`] detects the instruction sets of the running CPU.
+//! Deployments that need to tune the kernels build one with
+//! [`SimdConfig::builder`], which validates every setting, or load it from a
+//! config file; fields missing from the file keep their detected defaults:
+//!
+//! ```json
+//! { "use_avx512": false, "block_size": 128, "num_threads": 8 }
+//! ```
+//!
+//! [`SimdConfigBuilder::env_overrides`] then applies `DO_FANN_SIMD_*`
+//! environment variables on top, so one machine can be retuned without
+//! editing the file.
+
+use thiserror::Error;
+
+use super::CpuFeatures;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Environment variable overriding [`SimdConfig::use_avx2`]
+pub const ENV_AVX2: &str = "DO_FANN_SIMD_AVX2";
+/// Environment variable overriding [`SimdConfig::use_avx512`]
+pub const ENV_AVX512: &str = "DO_FANN_SIMD_AVX512";
+/// Environment variable overriding [`SimdConfig::block_size`]
+pub const ENV_BLOCK_SIZE: &str = "DO_FANN_SIMD_BLOCK_SIZE";
+/// Environment variable overriding [`SimdConfig::num_threads`]; 0 uses all
+/// cores
+pub const ENV_THREADS: &str = "DO_FANN_SIMD_THREADS";
+
+/// Errors raised when validating a [`SimdConfig`]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SimdConfigError {
+    #[error(
+        "Block size {0} must be a power of two between {min} and {max}",
+        min = SimdConfig::MIN_BLOCK_SIZE,
+        max = SimdConfig::MAX_BLOCK_SIZE
+    )]
+    BlockSize(usize),
+
+    #[error("{requested} threads requested, the limit is {limit}")]
+    TooManyThreads { requested: usize, limit: usize },
+
+    #[error("{0} was requested but this CPU does not support it")]
+    UnsupportedInstructions(&'static str),
+
+    #[error("Invalid value {value:?} for {variable}")]
+    Environment {
+        variable: &'static str,
+        value: String,
+    },
+
+    #[error("Invalid SIMD config: {0}")]
+    Config(String),
+}
+
+/// Configuration for SIMD operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SimdConfig {
+    /// Use AVX2 instructions if available
+    pub use_avx2: bool,
+    /// Use AVX-512 instructions if available
+    ///
+    /// Recorded and validated, but no kernel has an AVX-512 path yet: the
+    /// AVX-512 intrinsics need Rust 1.89, above this crate's MSRV, so
+    /// every kernel runs its AVX2 or scalar path regardless of this flag.
+    pub use_avx512: bool,
+    /// Block size for cache-friendly matrix operations
+    pub block_size: usize,
+    /// Number of threads for parallel operations
+    pub num_threads: usize,
+    /// Smallest `m·n·k` for which `matmul` runs through the
+    /// `matrixmultiply` crate (`matrixmultiply` feature); below it the
+    /// built-in kernels are faster
+    pub gemm_threshold: usize,
+    /// Smallest number of multiply-adds (`m·n·k` for `matmul`, `m·n` for
+    /// `matvec`) handed to CBLAS (`blas` or `accelerate` feature); smaller
+    /// products stay on the built-in kernels
+    pub blas_threshold: usize,
+}
+
+impl Default for SimdConfig {
+    fn default() -> Self {
+        Self {
+            use_avx2: CpuFeatures::detect().avx2,
+            use_avx512: CpuFeatures::detect().avx512f,
+            block_size: 64, // Good balance for most L1 cache sizes
+            num_threads: num_cpus::get(),
+            gemm_threshold: Self::DEFAULT_GEMM_THRESHOLD,
+            blas_threshold: Self::DEFAULT_BLAS_THRESHOLD,
+        }
+    }
+}
+
+impl SimdConfig {
+    /// Smallest block size accepted by validation
+    pub const MIN_BLOCK_SIZE: usize = 8;
+    /// Largest block size accepted by validation; a 4096-wide `f32` block
+    /// row is already 16 KiB
+    pub const MAX_BLOCK_SIZE: usize = 4096;
+    /// Upper bound on `num_threads`, whatever the machine
+    pub const MAX_THREADS: usize = 1024;
+    /// Default [`Self::gemm_threshold`], from the `matmul/*` benchmark on
+    /// AVX2: the built-in kernels win up to 8×8×8 and `matrixmultiply`
+    /// from 12×12×12 on
+    pub const DEFAULT_GEMM_THRESHOLD: usize = 12 * 12 * 12;
+    /// Default [`Self::blas_threshold`]: every CBLAS call pays for argument
+    /// checks and the library's own dispatch, which a product smaller than
+    /// 16×16×16 does not earn back. This is a conservative starting point
+    /// rather than a measurement of a particular library; tune it with the
+    /// `matmul/*` benchmark under `--features blas`
+    pub const DEFAULT_BLAS_THRESHOLD: usize = 16 * 16 * 16;
+
+    /// Builder starting from the detected defaults
+    pub fn builder() -> SimdConfigBuilder {
+        SimdConfigBuilder::new()
+    }
+
+    /// Check the block size and thread count, and that the enabled
+    /// instruction sets exist on this CPU
+    pub fn validate(&self) -> Result<(), SimdConfigError> {
+        if !self.block_size.is_power_of_two()
+            || !(Self::MIN_BLOCK_SIZE..=Self::MAX_BLOCK_SIZE).contains(&self.block_size)
+        {
+            return Err(SimdConfigError::BlockSize(self.block_size));
+        }
+        if self.num_threads == 0 || self.num_threads > Self::MAX_THREADS {
+            return Err(SimdConfigError::TooManyThreads {
+                requested: self.num_threads,
+                limit: Self::MAX_THREADS,
+            });
+        }
+        if self.use_avx2 && !CpuFeatures::detect().avx2 {
+            return Err(SimdConfigError::UnsupportedInstructions("AVX2"));
+        }
+        if self.use_avx512 && !CpuFeatures::detect().avx512f {
+            return Err(SimdConfigError::UnsupportedInstructions("AVX-512"));
+        }
+        Ok(())
+    }
+
+    /// Parse and validate a config; missing fields keep their defaults
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, SimdConfigError> {
+        let config: Self =
+            serde_json::from_str(json).map_err(|e| SimdConfigError::Config(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, SimdConfigError> {
+        serde_json::to_string_pretty(self).map_err(|e| SimdConfigError::Config(e.to_string()))
+    }
+}
+
+/// Validating builder for [`SimdConfig`]
+///
+/// Setters only record values; [`Self::build`] checks them all and
+/// reports the first problem.
+#[derive(Debug, Clone)]
+pub struct SimdConfigBuilder {
+    config: SimdConfig,
+    /// Requested threads before the cap; 0 uses all cores
+    threads: usize,
+    max_threads: Option<usize>,
+    error: Option<SimdConfigError>,
+}
+
+impl Default for SimdConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimdConfigBuilder {
+    pub fn new() -> Self {
+        Self::from_config(SimdConfig::default())
+    }
+
+    /// Builder starting from an existing config, e.g. one read from a file
+    pub fn from_config(config: SimdConfig) -> Self {
+        Self {
+            threads: config.num_threads,
+            config,
+            max_threads: None,
+            error: None,
+        }
+    }
+
+    pub fn use_avx2(mut self, enabled: bool) -> Self {
+        self.config.use_avx2 = enabled;
+        self
+    }
+
+    pub fn use_avx512(mut self, enabled: bool) -> Self {
+        self.config.use_avx512 = enabled;
+        self
+    }
+
+    /// Cache block size; must be a power of two between
+    /// [`SimdConfig::MIN_BLOCK_SIZE`] and [`SimdConfig::MAX_BLOCK_SIZE`]
+    pub fn block_size(mut self, block_size: usize) -> Self {
+        self.config.block_size = block_size;
+        self
+    }
+
+    /// Smallest `m·n·k` for which `matmul` uses the `matrixmultiply` crate
+    pub fn gemm_threshold(mut self, threshold: usize) -> Self {
+        self.config.gemm_threshold = threshold;
+        self
+    }
+
+    /// Smallest `m·n·k` (or `m·n` for `matvec`) handed to CBLAS
+    pub fn blas_threshold(mut self, threshold: usize) -> Self {
+        self.config.blas_threshold = threshold;
+        self
+    }
+
+    /// Worker threads; 0 uses all available cores
+    pub fn num_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Clamp the thread count to `cap`, e.g. a container's CPU quota
+    pub fn max_threads(mut self, cap: usize) -> Self {
+        self.max_threads = Some(cap);
+        self
+    }
+
+    /// Apply the `DO_FANN_SIMD_*` environment variables (see [`ENV_AVX2`],
+    /// [`ENV_AVX512`], [`ENV_BLOCK_SIZE`] and [`ENV_THREADS`])
+    ///
+    /// Booleans accept `1`/`0`, `true`/`false`, `on`/`off` and `yes`/`no`. An
+    /// unparsable value is reported by [`Self::build`].
+    pub fn env_overrides(self) -> Self {
+        self.overrides_from(|variable| std::env::var(variable).ok())
+    }
+
+    /// [`Self::env_overrides`] with the variables read through `lookup`
+    pub fn overrides_from(mut self, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        if let Some(value) = lookup(ENV_AVX2) {
+            match self.parse_flag(ENV_AVX2, &value) {
+                Some(enabled) => self.config.use_avx2 = enabled,
+                None => return self,
+            }
+        }
+        if let Some(value) = lookup(ENV_AVX512) {
+            match self.parse_flag(ENV_AVX512, &value) {
+                Some(enabled) => self.config.use_avx512 = enabled,
+                None => return self,
+            }
+        }
+        if let Some(value) = lookup(ENV_BLOCK_SIZE) {
+            match self.parse_count(ENV_BLOCK_SIZE, &value) {
+                Some(block_size) => self.config.block_size = block_size,
+                None => return self,
+            }
+        }
+        if let Some(value) = lookup(ENV_THREADS) {
+            match self.parse_count(ENV_THREADS, &value) {
+                Some(threads) => self.threads = threads,
+                None => return self,
+            }
+        }
+        self
+    }
+
+    fn parse_flag(&mut self, variable: &'static str, value: &str) -> Option<bool> {
+        let flag = match value.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "on" | "yes" => Some(true),
+            "0" | "false" | "off" | "no" => Some(false),
+            _ => None,
+        };
+        if flag.is_none() {
+            self.reject(variable, value);
+        }
+        flag
+    }
+
+    fn parse_count(&mut self, variable: &'static str, value: &str) -> Option<usize> {
+        let count = value.trim().parse().ok();
+        if count.is_none() {
+            self.reject(variable, value);
+        }
+        count
+    }
+
+    fn reject(&mut self, variable: &'static str, value: &str) {
+        self.error.get_or_insert(SimdConfigError::Environment {
+            variable,
+            value: value.to_string(),
+        });
+    }
+
+    /// Resolve the thread count and validate the config
+    pub fn build(self) -> Result<SimdConfig, SimdConfigError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let mut config = self.config;
+        let threads = match self.threads {
+            0 => num_cpus::get(),
+            n => n,
+        };
+        config.num_threads = self
+            .max_threads
+            .map_or(threads, |cap| threads.min(cap.max(1)));
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar() -> SimdConfigBuilder {
+        SimdConfig::builder().use_avx2(false).use_avx512(false)
+    }
+
+    #[test]
+    fn test_builder_validates_settings() {
+        let config = scalar().block_size(128).num_threads(3).build().unwrap();
+        assert_eq!((config.block_size, config.num_threads), (128, 3));
+        assert_eq!(
+            scalar()
+                .num_threads(0)
+                .max_threads(2)
+                .build()
+                .unwrap()
+                .num_threads,
+            num_cpus::get().min(2)
+        );
+
+        for block_size in [0, 4, 48, 8192] {
+            assert_eq!(
+                scalar().block_size(block_size).build(),
+                Err(SimdConfigError::BlockSize(block_size))
+            );
+        }
+        assert!(matches!(
+            scalar().num_threads(SimdConfig::MAX_THREADS + 1).build(),
+            Err(SimdConfigError::TooManyThreads { .. })
+        ));
+        if !CpuFeatures::detect().avx512f {
+            assert_eq!(
+                scalar().use_avx512(true).build(),
+                Err(SimdConfigError::UnsupportedInstructions("AVX-512"))
+            );
+        }
+        assert!(SimdConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_environment_overrides() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |variable: &str| {
+                pairs
+                    .iter()
+                    .find(|(name, _)| *name == variable)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let config = scalar()
+            .block_size(32)
+            .overrides_from(env(&[(ENV_BLOCK_SIZE, " 256 "), (ENV_THREADS, "2")]))
+            .build()
+            .unwrap();
+        assert_eq!((config.block_size, config.num_threads), (256, 2));
+
+        let config = SimdConfig::builder()
+            .overrides_from(env(&[(ENV_AVX2, "off"), (ENV_AVX512, "No")]))
+            .build()
+            .unwrap();
+        assert!(!config.use_avx2 && !config.use_avx512);
+
+        assert_eq!(
+            scalar()
+                .overrides_from(env(&[(ENV_THREADS, "many")]))
+                .build(),
+            Err(SimdConfigError::Environment {
+                variable: ENV_THREADS,
+                value: "many".to_string(),
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip_keeps_defaults() {
+        let config = SimdConfig::from_json(r#"{ "use_avx2": false, "block_size": 16 }"#).unwrap();
+        assert_eq!(config.block_size, 16);
+        assert!(!config.use_avx2);
+        assert_eq!(config.num_threads, SimdConfig::default().num_threads);
+        assert_eq!(
+            SimdConfig::from_json(&config.to_json().unwrap()).unwrap(),
+            config
+        );
+        assert_eq!(
+            SimdConfig::from_json(r#"{ "block_size": 100 }"#).unwrap_err(),
+            SimdConfigError::BlockSize(100)
+        );
+        assert!(matches!(
+            SimdConfig::from_json("{ nope"),
+            Err(SimdConfigError::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,681 @@
+//! CPU feature detection and kernel selection
+//!
+//! [`CpuFeatures::detect`] reports the vector extensions of the running
+//! CPU, and [`CpuSimdOps::dense_path`] picks the path the dense kernels
+//! (`matmul`, `matvec`) take on it:
+//!
+//! - With the `accelerate` feature on macOS they call Apple's Accelerate
+//!   BLAS, which runs on the AMX units of Apple Silicon.
+//! - With the `blas` feature they call the system CBLAS. The build links
+//!   `openblas` unless `DO_FANN_BLAS_LIB` names other libraries
+//!   (comma-separated, e.g. `mkl_rt`), searched for in
+//!   `DO_FANN_BLAS_LIB_DIR` if set.
+//! - On x86_64 with AVX2 and FMA enabled in the config they use the AVX2
+//!   kernels.
+//! - Everything else uses the scalar kernels.
+//!
+//! The `f64` kernels ([`CpuSimdOps::dense_path_f64`]) follow the same order
+//! with `dgemm`/`dgemv` and 4-wide AVX2 kernels, and use 2-wide NEON kernels
+//! on aarch64, where the `f32` dense kernels have no NEON path yet. There is
+//! no AVX-512 kernel for either type (see
+//! [`SimdConfig::use_avx512`](super::SimdConfig::use_avx512)).
+//!
+//! A linked library only gets products of at least
+//! [`SimdConfig::blas_threshold`](super::SimdConfig::blas_threshold)
+//! multiply-adds, since the call overhead swamps smaller ones;
+//! [`CpuSimdOps::matvec_path`] reports the choice for a given shape.
+//!
+//! Without a BLAS library, or below its threshold, the `matrixmultiply`
+//! feature hands `matmul` products of at least
+//! [`SimdConfig::gemm_threshold`](super::SimdConfig::gemm_threshold)
+//! multiply-adds to the `matrixmultiply` crate, whose packed kernels win
+//! once the matrices outgrow the cache; [`CpuSimdOps::matmul_path`] reports
+//! the choice for a given shape. Smaller products, and `matvec`, which has
+//! nothing to pack, stay on the built-in kernels, cache-blocked by
+//! [`SimdConfig::block_size`](super::SimdConfig::block_size).
+//!
+//! SVE and SVE2 are detected on ARM servers, but Rust has no stable SVE
+//! intrinsics, so those machines run the NEON kernels every aarch64 CPU
+//! supports (see [`PanelMatrix`](super::PanelMatrix)).
+
+use super::CpuSimdOps;
+
+/// Vector extensions of the running CPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CpuFeatures {
+    pub avx2: bool,
+    pub fma: bool,
+    pub avx512f: bool,
+    pub neon: bool,
+    pub sve: bool,
+    pub sve2: bool,
+}
+
+impl CpuFeatures {
+    /// Detect the features of this CPU; the standard library caches the
+    /// underlying queries, so this is cheap to call repeatedly
+    pub fn detect() -> Self {
+        #[allow(unused_mut)]
+        let mut features = Self::default();
+        #[cfg(target_arch = "x86_64")]
+        {
+            features.avx2 = is_x86_feature_detected!("avx2");
+            features.fma = is_x86_feature_detected!("fma");
+            features.avx512f = is_x86_feature_detected!("avx512f");
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            features.neon = std::arch::is_aarch64_feature_detected!("neon");
+            features.sve = std::arch::is_aarch64_feature_detected!("sve");
+            features.sve2 = std::arch::is_aarch64_feature_detected!("sve2");
+        }
+        features
+    }
+
+    /// Names of the detected features, for logs and reports
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            ("avx2", self.avx2),
+            ("fma", self.fma),
+            ("avx512f", self.avx512f),
+            ("neon", self.neon),
+            ("sve", self.sve),
+            ("sve2", self.sve2),
+        ]
+        .into_iter()
+        .filter_map(|(name, present)| present.then_some(name))
+        .collect()
+    }
+}
+
+/// Implementation used by the dense kernels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelPath {
+    Scalar,
+    Avx2,
+    /// NEON (aarch64, `f64` kernels only)
+    Neon,
+    /// Apple Accelerate BLAS (`accelerate` feature, macOS only)
+    Accelerate,
+    /// System CBLAS (`blas` feature)
+    Blas,
+    /// The `matrixmultiply` crate (`matrixmultiply` feature, `matmul` only)
+    MatrixMultiply,
+}
+
+impl KernelPath {
+    /// Elements of type `T` one vector instruction of this path processes:
+    /// 8 `f32` or 4 `f64` for AVX2, 4 `f32` or 2 `f64` for NEON, and 1 for
+    /// the scalar path and the libraries, whose internals are opaque
+    pub fn vector_width<T>(self) -> usize {
+        let register_bytes = match self {
+            KernelPath::Avx2 => 32,
+            KernelPath::Neon => 16,
+            KernelPath::Scalar
+            | KernelPath::Accelerate
+            | KernelPath::Blas
+            | KernelPath::MatrixMultiply => {
+                return 1;
+            }
+        };
+        (register_bytes / std::mem::size_of::<T>().max(1)).max(1)
+    }
+}
+
+impl CpuSimdOps {
+    /// Path `f32` `matmul` and `matvec` take with this config on this CPU,
+    /// for products of at least
+    /// [`SimdConfig::blas_threshold`](super::SimdConfig::blas_threshold)
+    /// multiply-adds when a BLAS library is linked
+    pub fn dense_path(&self) -> KernelPath {
+        if cfg!(all(
+            target_os = "macos",
+            feature = "accelerate",
+            not(feature = "safe-only")
+        )) {
+            return KernelPath::Accelerate;
+        }
+        if cfg!(all(feature = "blas", not(feature = "safe-only"))) {
+            return KernelPath::Blas;
+        }
+        self.builtin_path()
+    }
+
+    /// Path of the built-in `f32` kernels, whatever library is linked
+    fn builtin_path(&self) -> KernelPath {
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            return KernelPath::Avx2;
+        }
+        KernelPath::Scalar
+    }
+
+    /// Path `f32` `matmul` takes for an `m`×`k` by `k`×`n` product: a linked
+    /// BLAS library from [`SimdConfig::blas_threshold`] multiply-adds on,
+    /// then the `matrixmultiply` crate from [`SimdConfig::gemm_threshold`]
+    /// on, and otherwise the built-in kernels
+    ///
+    /// [`SimdConfig::blas_threshold`]: super::SimdConfig::blas_threshold
+    /// [`SimdConfig::gemm_threshold`]: super::SimdConfig::gemm_threshold
+    pub fn matmul_path(&self, m: usize, n: usize, k: usize) -> KernelPath {
+        match self.dense_path() {
+            path @ (KernelPath::Accelerate | KernelPath::Blas)
+                if self.use_blas(m.saturating_mul(n).saturating_mul(k)) =>
+            {
+                path
+            }
+            _ if self.use_packed_gemm(m, n, k) => KernelPath::MatrixMultiply,
+            _ => self.builtin_path(),
+        }
+    }
+
+    /// Path `f32` `matvec` takes for an `m`×`n` matrix: a linked BLAS library
+    /// from [`SimdConfig::blas_threshold`](super::SimdConfig::blas_threshold)
+    /// multiply-adds on, and otherwise the built-in kernels
+    pub fn matvec_path(&self, m: usize, n: usize) -> KernelPath {
+        match self.dense_path() {
+            path @ (KernelPath::Accelerate | KernelPath::Blas)
+                if self.use_blas(m.saturating_mul(n)) =>
+            {
+                path
+            }
+            _ => self.builtin_path(),
+        }
+    }
+
+    /// `f64` counterpart of [`Self::matmul_path`]
+    pub fn matmul_path_f64(&self, m: usize, n: usize, k: usize) -> KernelPath {
+        match self.matmul_path(m, n, k) {
+            KernelPath::Scalar => self.builtin_path_f64(),
+            path => path,
+        }
+    }
+
+    /// `f64` counterpart of [`Self::matvec_path`]
+    pub fn matvec_path_f64(&self, m: usize, n: usize) -> KernelPath {
+        match self.matvec_path(m, n) {
+            KernelPath::Scalar => self.builtin_path_f64(),
+            path => path,
+        }
+    }
+
+    /// Whether a product of `work` multiply-adds goes to a linked BLAS
+    /// library
+    pub(super) fn use_blas(&self, work: usize) -> bool {
+        work >= self.config.blas_threshold
+    }
+
+    /// Whether an `m`×`k` by `k`×`n` product goes to `matrixmultiply`
+    pub(super) fn use_packed_gemm(&self, m: usize, n: usize, k: usize) -> bool {
+        cfg!(all(feature = "matrixmultiply", not(feature = "safe-only")))
+            && m.saturating_mul(n).saturating_mul(k) >= self.config.gemm_threshold
+    }
+
+    /// Path `f64` `matmul` and `matvec` take with this config on this CPU
+    pub fn dense_path_f64(&self) -> KernelPath {
+        match self.dense_path() {
+            KernelPath::Scalar => self.builtin_path_f64(),
+            path => path,
+        }
+    }
+
+    /// Path of the built-in `f64` kernels: NEON on aarch64
+    fn builtin_path_f64(&self) -> KernelPath {
+        match self.builtin_path() {
+            KernelPath::Scalar
+                if cfg!(all(target_arch = "aarch64", not(feature = "safe-only"))) =>
+            {
+                KernelPath::Neon
+            }
+            path => path,
+        }
+    }
+}
+
+/// Bindings to the CBLAS interface of Accelerate or the system BLAS; the
+/// build script links the latter
+#[cfg(all(
+    any(feature = "blas", all(target_os = "macos", feature = "accelerate")),
+    not(feature = "safe-only")
+))]
+pub(super) mod cblas {
+    use std::os::raw::c_int;
+
+    const ROW_MAJOR: c_int = 101;
+    const NO_TRANS: c_int = 111;
+
+    #[cfg_attr(
+        all(target_os = "macos", feature = "accelerate"),
+        link(name = "Accelerate", kind = "framework")
+    )]
+    extern "C" {
+        fn cblas_sgemm(
+            order: c_int,
+            trans_a: c_int,
+            trans_b: c_int,
+            m: c_int,
+            n: c_int,
+            k: c_int,
+            alpha: f32,
+            a: *const f32,
+            lda: c_int,
+            b: *const f32,
+            ldb: c_int,
+            beta: f32,
+            c: *mut f32,
+            ldc: c_int,
+        );
+        fn cblas_sgemv(
+            order: c_int,
+            trans: c_int,
+            m: c_int,
+            n: c_int,
+            alpha: f32,
+            a: *const f32,
+            lda: c_int,
+            x: *const f32,
+            incx: c_int,
+            beta: f32,
+            y: *mut f32,
+            incy: c_int,
+        );
+        fn cblas_dgemm(
+            order: c_int,
+            trans_a: c_int,
+            trans_b: c_int,
+            m: c_int,
+            n: c_int,
+            k: c_int,
+            alpha: f64,
+            a: *const f64,
+            lda: c_int,
+            b: *const f64,
+            ldb: c_int,
+            beta: f64,
+            c: *mut f64,
+            ldc: c_int,
+        );
+        fn cblas_dgemv(
+            order: c_int,
+            trans: c_int,
+            m: c_int,
+            n: c_int,
+            alpha: f64,
+            a: *const f64,
+            lda: c_int,
+            x: *const f64,
+            incx: c_int,
+            beta: f64,
+            y: *mut f64,
+            incy: c_int,
+        );
+    }
+
+    /// `C = A B` for row-major `A` (m×k) and `B` (k×n); `false` if a
+    /// dimension does not fit CBLAS's `int` or a slice is too short
+    pub(in crate::simd) fn sgemm(
+        a: &[f32],
+        b: &[f32],
+        c: &mut [f32],
+        m: usize,
+        n: usize,
+        k: usize,
+    ) -> bool {
+        let (Ok(mi), Ok(ni), Ok(ki)) = (c_int::try_from(m), c_int::try_from(n), c_int::try_from(k))
+        else {
+            return false;
+        };
+        if a.len() < m * k || b.len() < k * n || c.len() < m * n {
+            return false;
+        }
+        if m == 0 || n == 0 {
+            return true;
+        }
+        if k == 0 {
+            c[..m * n].fill(0.0);
+            return true;
+        }
+        // SAFETY: the slices hold the row-major matrices the dimensions
+        // and leading dimensions describe, as checked above
+        unsafe {
+            cblas_sgemm(
+                ROW_MAJOR,
+                NO_TRANS,
+                NO_TRANS,
+                mi,
+                ni,
+                ki,
+                1.0,
+                a.as_ptr(),
+                ki,
+                b.as_ptr(),
+                ni,
+                0.0,
+                c.as_mut_ptr(),
+                ni,
+            )
+        };
+        true
+    }
+
+    /// `y = A x` for row-major `A` (m×n); `false` if a dimension does not
+    /// fit CBLAS's `int` or a slice is too short
+    pub(in crate::simd) fn sgemv(a: &[f32], x: &[f32], y: &mut [f32], m: usize, n: usize) -> bool {
+        let (Ok(mi), Ok(ni)) = (c_int::try_from(m), c_int::try_from(n)) else {
+            return false;
+        };
+        if a.len() < m * n || x.len() < n || y.len() < m {
+            return false;
+        }
+        if m == 0 {
+            return true;
+        }
+        if n == 0 {
+            y[..m].fill(0.0);
+            return true;
+        }
+        // SAFETY: the slices hold the matrix and vectors the dimensions
+        // describe, as checked above
+        unsafe {
+            cblas_sgemv(
+                ROW_MAJOR,
+                NO_TRANS,
+                mi,
+                ni,
+                1.0,
+                a.as_ptr(),
+                ni,
+                x.as_ptr(),
+                1,
+                0.0,
+                y.as_mut_ptr(),
+                1,
+            )
+        };
+        true
+    }
+
+    /// `f64` counterpart of [`sgemm`]
+    pub(in crate::simd) fn dgemm(
+        a: &[f64],
+        b: &[f64],
+        c: &mut [f64],
+        m: usize,
+        n: usize,
+        k: usize,
+    ) -> bool {
+        let (Ok(mi), Ok(ni), Ok(ki)) = (c_int::try_from(m), c_int::try_from(n), c_int::try_from(k))
+        else {
+            return false;
+        };
+        if a.len() < m * k || b.len() < k * n || c.len() < m * n {
+            return false;
+        }
+        if m == 0 || n == 0 {
+            return true;
+        }
+        if k == 0 {
+            c[..m * n].fill(0.0);
+            return true;
+        }
+        // SAFETY: as for `sgemm`
+        unsafe {
+            cblas_dgemm(
+                ROW_MAJOR,
+                NO_TRANS,
+                NO_TRANS,
+                mi,
+                ni,
+                ki,
+                1.0,
+                a.as_ptr(),
+                ki,
+                b.as_ptr(),
+                ni,
+                0.0,
+                c.as_mut_ptr(),
+                ni,
+            )
+        };
+        true
+    }
+
+    /// `f64` counterpart of [`sgemv`]
+    pub(in crate::simd) fn dgemv(a: &[f64], x: &[f64], y: &mut [f64], m: usize, n: usize) -> bool {
+        let (Ok(mi), Ok(ni)) = (c_int::try_from(m), c_int::try_from(n)) else {
+            return false;
+        };
+        if a.len() < m * n || x.len() < n || y.len() < m {
+            return false;
+        }
+        if m == 0 {
+            return true;
+        }
+        if n == 0 {
+            y[..m].fill(0.0);
+            return true;
+        }
+        // SAFETY: as for `sgemv`
+        unsafe {
+            cblas_dgemv(
+                ROW_MAJOR,
+                NO_TRANS,
+                mi,
+                ni,
+                1.0,
+                a.as_ptr(),
+                ni,
+                x.as_ptr(),
+                1,
+                0.0,
+                y.as_mut_ptr(),
+                1,
+            )
+        };
+        true
+    }
+}
+
+/// GEMM through the `matrixmultiply` crate, whose packing only pays off
+/// for products of at least [`SimdConfig::gemm_threshold`] multiply-adds
+///
+/// [`SimdConfig::gemm_threshold`]: super::SimdConfig::gemm_threshold
+#[cfg(all(feature = "matrixmultiply", not(feature = "safe-only")))]
+pub(super) mod packed {
+    /// `C = A B` for row-major `A` (m×k) and `B` (k×n); `false` if a
+    /// dimension does not fit a stride
+    pub(in crate::simd) fn sgemm(
+        a: &[f32],
+        b: &[f32],
+        c: &mut [f32],
+        m: usize,
+        n: usize,
+        k: usize,
+    ) -> bool {
+        let (Ok(ni), Ok(ki)) = (isize::try_from(n), isize::try_from(k)) else {
+            return false;
+        };
+        if a.len() < m * k || b.len() < k * n || c.len() < m * n {
+            return false;
+        }
+        // SAFETY: the slices hold the row-major matrices the dimensions and
+        // strides describe, as checked above; with beta 0, `C` is only
+        // written
+        unsafe {
+            matrixmultiply::sgemm(
+                m,
+                k,
+                n,
+                1.0,
+                a.as_ptr(),
+                ki,
+                1,
+                b.as_ptr(),
+                ni,
+                1,
+                0.0,
+                c.as_mut_ptr(),
+                ni,
+                1,
+            )
+        };
+        true
+    }
+
+    /// `f64` counterpart of [`sgemm`]
+    pub(in crate::simd) fn dgemm(
+        a: &[f64],
+        b: &[f64],
+        c: &mut [f64],
+        m: usize,
+        n: usize,
+        k: usize,
+    ) -> bool {
+        let (Ok(ni), Ok(ki)) = (isize::try_from(n), isize::try_from(k)) else {
+            return false;
+        };
+        if a.len() < m * k || b.len() < k * n || c.len() < m * n {
+            return false;
+        }
+        // SAFETY: as for `sgemm`
+        unsafe {
+            matrixmultiply::dgemm(
+                m,
+                k,
+                n,
+                1.0,
+                a.as_ptr(),
+                ki,
+                1,
+                b.as_ptr(),
+                ni,
+                1,
+                0.0,
+                c.as_mut_ptr(),
+                ni,
+                1,
+            )
+        };
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{SimdConfig, SimdMatrixOps};
+    use super::*;
+
+    #[test]
+    fn test_detection_and_dense_path() {
+        let features = CpuFeatures::detect();
+        assert_eq!(features, CpuFeatures::detect());
+        if cfg!(target_arch = "aarch64") {
+            assert!(features.neon);
+        }
+        // SVE2 implies SVE and AVX-512 is only reported next to AVX2
+        assert!(!features.sve2 || features.sve);
+        assert!(!features.avx512f || features.avx2);
+        assert_eq!(features.names().len(), {
+            let flags = [
+                features.avx2,
+                features.fma,
+                features.avx512f,
+                features.neon,
+                features.sve,
+                features.sve2,
+            ];
+            flags.iter().filter(|&&f| f).count()
+        });
+
+        let scalar = CpuSimdOps::new(SimdConfig {
+            use_avx2: false,
+            ..SimdConfig::default()
+        });
+        let path = scalar.dense_path();
+        if cfg!(all(
+            target_os = "macos",
+            feature = "accelerate",
+            not(feature = "safe-only")
+        )) {
+            assert_eq!(path, KernelPath::Accelerate);
+        } else if cfg!(all(feature = "blas", not(feature = "safe-only"))) {
+            assert_eq!(path, KernelPath::Blas);
+        } else {
+            assert_eq!(path, KernelPath::Scalar);
+        }
+        assert_eq!(KernelPath::Avx2.vector_width::<f32>(), 8);
+        assert_eq!(KernelPath::Avx2.vector_width::<f64>(), 4);
+        assert_eq!(KernelPath::Neon.vector_width::<f64>(), 2);
+        assert_eq!(KernelPath::Blas.vector_width::<f64>(), 1);
+
+        // Whatever the path, results agree with the definition
+        let ops = CpuSimdOps::new_with_defaults();
+        let a: Vec<f32> = (0..15).map(|i| i as f32 * 0.5 - 3.0).collect();
+        let b: Vec<f32> = (0..10).map(|i| 1.0 - i as f32 * 0.25).collect();
+        let mut c = vec![f32::NAN; 6];
+        ops.matmul(&a, &b, &mut c, 3, 2, 5).unwrap();
+        let mut y = vec![f32::NAN; 3];
+        ops.matvec(&a, &b[..5], &mut y, 3, 5).unwrap();
+        for i in 0..3 {
+            for j in 0..2 {
+                let expected: f32 = (0..5).map(|l| a[i * 5 + l] * b[l * 2 + j]).sum();
+                assert!((c[i * 2 + j] - expected).abs() < 1e-4);
+            }
+            let expected: f32 = (0..5).map(|l| a[i * 5 + l] * b[l]).sum();
+            assert!((y[i] - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_matmul_path_threshold() {
+        let with_threshold = |gemm_threshold| {
+            CpuSimdOps::new(SimdConfig {
+                gemm_threshold,
+                ..SimdConfig::default()
+            })
+        };
+        let (builtin, packed) = (with_threshold(usize::MAX), with_threshold(0));
+        assert_eq!(builtin.matmul_path(64, 64, 64), builtin.dense_path());
+        let library = matches!(
+            packed.dense_path(),
+            KernelPath::Accelerate | KernelPath::Blas
+        );
+        if cfg!(all(feature = "matrixmultiply", not(feature = "safe-only"))) && !library {
+            assert_eq!(packed.matmul_path(2, 2, 2), KernelPath::MatrixMultiply);
+            assert_eq!(packed.matmul_path_f64(2, 2, 2), KernelPath::MatrixMultiply);
+        } else {
+            assert_eq!(packed.matmul_path(2, 2, 2), packed.dense_path());
+        }
+
+        // A linked library only gets products from blas_threshold on
+        let library_only_above = |blas_threshold| {
+            CpuSimdOps::new(SimdConfig {
+                blas_threshold,
+                gemm_threshold: usize::MAX,
+                ..SimdConfig::default()
+            })
+        };
+        let (never, always) = (library_only_above(usize::MAX), library_only_above(0));
+        assert_eq!(never.matmul_path(64, 64, 64), never.builtin_path());
+        assert_eq!(never.matvec_path(64, 64), never.builtin_path());
+        assert_eq!(never.matvec_path_f64(64, 64), never.builtin_path_f64());
+        assert_eq!(always.matmul_path(1, 1, 1), always.dense_path());
+        assert_eq!(always.matvec_path_f64(1, 1), always.dense_path_f64());
+
+        let (m, n, k) = (13, 11, 17);
+        let a: Vec<f64> = (0..m * k).map(|i| (i % 7) as f64 - 3.0).collect();
+        let b: Vec<f64> = (0..k * n).map(|i| (i % 5) as f64 * 0.5).collect();
+        let (mut expected, mut c) = (vec![0.0; m * n], vec![f64::NAN; m * n]);
+        builtin.matmul(&a, &b, &mut expected, m, n, k).unwrap();
+        packed.matmul(&a, &b, &mut c, m, n, k).unwrap();
+        assert_eq!(c, expected);
+        let (a, b): (Vec<f32>, Vec<f32>) = (
+            a.iter().map(|&v| v as f32).collect(),
+            b.iter().map(|&v| v as f32).collect(),
+        );
+        let (mut expected, mut c) = (vec![0.0; m * n], vec![f32::NAN; m * n]);
+        builtin.matmul(&a, &b, &mut expected, m, n, k).unwrap();
+        packed.matmul(&a, &b, &mut c, m, n, k).unwrap();
+        assert_eq!(c, expected);
+    }
+}
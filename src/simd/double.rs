@@ -0,0 +1,472 @@
+//! `f64` kernels
+//!
+//! The dense kernels take the system BLAS (`dgemm`/`dgemv`) when built with
+//! it, then AVX2 with FMA on x86_64 (4 lanes) or NEON on aarch64 (2 lanes),
+//! and otherwise the same cache-blocked scalar code as `f32`. Activations
+//! vectorize ReLU and fall back to [`ActivationFunction::activate`] for
+//! everything else, as the `f32` kernels do.
+
+use super::{check_len, ActivationFunction, CpuSimdOps, SimdError, SimdMatrixOps};
+
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+use std::arch::x86_64::*;
+
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+use std::arch::aarch64::*;
+
+#[cfg(all(
+    any(
+        feature = "blas",
+        feature = "matrixmultiply",
+        all(target_os = "macos", feature = "accelerate")
+    ),
+    not(feature = "safe-only")
+))]
+use super::dispatch;
+
+impl SimdMatrixOps<f64> for CpuSimdOps {
+    fn matmul(
+        &self,
+        a: &[f64],
+        b: &[f64],
+        c: &mut [f64],
+        m: usize,
+        n: usize,
+        k: usize,
+    ) -> Result<(), SimdError> {
+        check_len("a", a.len(), &[m, k])?;
+        check_len("b", b.len(), &[k, n])?;
+        check_len("c", c.len(), &[m, n])?;
+        #[cfg(all(
+            any(feature = "blas", all(target_os = "macos", feature = "accelerate")),
+            not(feature = "safe-only")
+        ))]
+        if self.use_blas(m.saturating_mul(n).saturating_mul(k))
+            && dispatch::cblas::dgemm(a, b, c, m, n, k)
+        {
+            return Ok(());
+        }
+        #[cfg(all(feature = "matrixmultiply", not(feature = "safe-only")))]
+        if self.use_packed_gemm(m, n, k) && dispatch::packed::dgemm(a, b, c, m, n, k) {
+            return Ok(());
+        }
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 and FMA were detected and the lengths checked
+            unsafe { matmul_avx2(a, b, c, m, n, k, self.config.block_size) };
+            return Ok(());
+        }
+        // SAFETY: NEON is part of the aarch64 baseline and the lengths were
+        // checked above
+        #[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+        unsafe {
+            matmul_neon(a, b, c, m, n, k, self.config.block_size)
+        }
+        #[cfg(not(all(target_arch = "aarch64", not(feature = "safe-only"))))]
+        self.matmul_scalar(a, b, c, m, n, k);
+        Ok(())
+    }
+
+    fn matvec(
+        &self,
+        a: &[f64],
+        x: &[f64],
+        y: &mut [f64],
+        m: usize,
+        n: usize,
+    ) -> Result<(), SimdError> {
+        check_len("a", a.len(), &[m, n])?;
+        check_len("x", x.len(), &[n])?;
+        check_len("y", y.len(), &[m])?;
+        #[cfg(all(
+            any(feature = "blas", all(target_os = "macos", feature = "accelerate")),
+            not(feature = "safe-only")
+        ))]
+        if self.use_blas(m.saturating_mul(n)) && dispatch::cblas::dgemv(a, x, y, m, n) {
+            return Ok(());
+        }
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 and FMA were detected and the lengths checked
+            unsafe { matvec_avx2(a, x, y, m, n) };
+            return Ok(());
+        }
+        // SAFETY: NEON is part of the aarch64 baseline and the lengths were
+        // checked above
+        #[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+        unsafe {
+            matvec_neon(a, x, y, m, n)
+        }
+        #[cfg(not(all(target_arch = "aarch64", not(feature = "safe-only"))))]
+        self.matvec_scalar(a, x, y, m, n);
+        Ok(())
+    }
+
+    fn add_bias(
+        &self,
+        matrix: &mut [f64],
+        bias: &[f64],
+        rows: usize,
+        cols: usize,
+    ) -> Result<(), SimdError> {
+        check_len("matrix", matrix.len(), &[rows, cols])?;
+        check_len("bias", bias.len(), &[cols])?;
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 was detected and the lengths checked
+            unsafe { add_bias_avx2(matrix, bias, rows, cols) };
+            return Ok(());
+        }
+        // SAFETY: NEON is part of the aarch64 baseline and the lengths were
+        // checked above
+        #[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+        unsafe {
+            add_bias_neon(matrix, bias, rows, cols)
+        }
+        #[cfg(not(all(target_arch = "aarch64", not(feature = "safe-only"))))]
+        self.add_bias_scalar(matrix, bias, rows, cols);
+        Ok(())
+    }
+
+    fn apply_activation(&self, data: &mut [f64], activation: ActivationFunction) {
+        let done = match activation {
+            ActivationFunction::ReLU => self.relu(data),
+            _ => 0,
+        };
+        self.apply_activation_scalar(&mut data[done..], activation);
+    }
+
+    fn activation_derivatives(
+        &self,
+        data: &[f64],
+        derivatives: &mut [f64],
+        activation: ActivationFunction,
+    ) -> Result<(), SimdError> {
+        check_len("derivatives", derivatives.len(), &[data.len()])?;
+        let done = match activation {
+            ActivationFunction::ReLU => self.relu_derivatives(data, derivatives),
+            _ => 0,
+        };
+        self.activation_derivatives_scalar(&data[done..], &mut derivatives[done..], activation);
+        Ok(())
+    }
+}
+
+impl CpuSimdOps {
+    /// ReLU over the longest prefix of `data` that fills whole vectors;
+    /// returns the number of elements done
+    #[allow(unused_variables)]
+    fn relu(&self, data: &mut [f64]) -> usize {
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 was detected; the kernel stays within `data`
+            return unsafe { relu_avx2(data) };
+        }
+        // SAFETY: NEON is part of the aarch64 baseline; the kernel stays
+        // within `data`
+        #[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+        return unsafe { relu_neon(data) };
+        #[allow(unreachable_code)]
+        0
+    }
+
+    /// ReLU derivatives over the longest whole-vector prefix of `data`;
+    /// returns the number of elements done
+    #[allow(unused_variables)]
+    fn relu_derivatives(&self, data: &[f64], derivatives: &mut [f64]) -> usize {
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 was detected and `derivatives` is at least as long
+            // as `data`
+            return unsafe { relu_derivatives_avx2(data, derivatives) };
+        }
+        // SAFETY: NEON is part of the aarch64 baseline and `derivatives` is
+        // at least as long as `data`
+        #[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+        return unsafe { relu_derivatives_neon(data, derivatives) };
+        #[allow(unreachable_code)]
+        0
+    }
+}
+
+/// AVX2 lanes per `f64` vector
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+const AVX2_WIDTH: usize = 4;
+
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn matmul_avx2(
+    a: &[f64],
+    b: &[f64],
+    c: &mut [f64],
+    m: usize,
+    n: usize,
+    k: usize,
+    block_size: usize,
+) {
+    c[..m * n].fill(0.0);
+    for i_block in (0..m).step_by(block_size) {
+        for j_block in (0..n).step_by(block_size) {
+            for k_block in (0..k).step_by(block_size) {
+                let i_end = (i_block + block_size).min(m);
+                let j_end = (j_block + block_size).min(n);
+                let k_end = (k_block + block_size).min(k);
+
+                for i in i_block..i_end {
+                    let mut j = j_block;
+                    while j + AVX2_WIDTH <= j_end {
+                        let mut sum = _mm256_setzero_pd();
+                        for l in k_block..k_end {
+                            let a_value = _mm256_set1_pd(a[i * k + l]);
+                            let b_vec = _mm256_loadu_pd(b.as_ptr().add(l * n + j));
+                            sum = _mm256_fmadd_pd(a_value, b_vec, sum);
+                        }
+                        let c_ptr = c.as_mut_ptr().add(i * n + j);
+                        _mm256_storeu_pd(c_ptr, _mm256_add_pd(_mm256_loadu_pd(c_ptr), sum));
+                        j += AVX2_WIDTH;
+                    }
+                    for j in j..j_end {
+                        let mut sum = 0.0;
+                        for l in k_block..k_end {
+                            sum += a[i * k + l] * b[l * n + j];
+                        }
+                        c[i * n + j] += sum;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn matvec_avx2(a: &[f64], x: &[f64], y: &mut [f64], m: usize, n: usize) {
+    let chunks = n / AVX2_WIDTH;
+    for i in 0..m {
+        let mut sum_vec = _mm256_setzero_pd();
+        for chunk in 0..chunks {
+            let j = chunk * AVX2_WIDTH;
+            let a_vec = _mm256_loadu_pd(a.as_ptr().add(i * n + j));
+            let x_vec = _mm256_loadu_pd(x.as_ptr().add(j));
+            sum_vec = _mm256_fmadd_pd(a_vec, x_vec, sum_vec);
+        }
+        let lanes = std::mem::transmute::<__m256d, [f64; AVX2_WIDTH]>(sum_vec);
+        let mut sum = lanes.iter().sum::<f64>();
+        for j in (chunks * AVX2_WIDTH)..n {
+            sum += a[i * n + j] * x[j];
+        }
+        y[i] = sum;
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn add_bias_avx2(matrix: &mut [f64], bias: &[f64], rows: usize, cols: usize) {
+    for i in 0..rows {
+        let mut j = 0;
+        while j + AVX2_WIDTH <= cols {
+            let row_ptr = matrix.as_mut_ptr().add(i * cols + j);
+            let sum = _mm256_add_pd(
+                _mm256_loadu_pd(row_ptr),
+                _mm256_loadu_pd(bias.as_ptr().add(j)),
+            );
+            _mm256_storeu_pd(row_ptr, sum);
+            j += AVX2_WIDTH;
+        }
+        for j in j..cols {
+            matrix[i * cols + j] += bias[j];
+        }
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn relu_avx2(data: &mut [f64]) -> usize {
+    let zero = _mm256_setzero_pd();
+    let mut i = 0;
+    while i + AVX2_WIDTH <= data.len() {
+        let ptr = data.as_mut_ptr().add(i);
+        _mm256_storeu_pd(ptr, _mm256_max_pd(_mm256_loadu_pd(ptr), zero));
+        i += AVX2_WIDTH;
+    }
+    i
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn relu_derivatives_avx2(data: &[f64], derivatives: &mut [f64]) -> usize {
+    let zero = _mm256_setzero_pd();
+    let one = _mm256_set1_pd(1.0);
+    let mut i = 0;
+    while i + AVX2_WIDTH <= data.len() {
+        let mask = _mm256_cmp_pd(_mm256_loadu_pd(data.as_ptr().add(i)), zero, _CMP_GT_OS);
+        _mm256_storeu_pd(derivatives.as_mut_ptr().add(i), _mm256_and_pd(mask, one));
+        i += AVX2_WIDTH;
+    }
+    i
+}
+
+/// NEON lanes per `f64` vector
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+const NEON_WIDTH: usize = 2;
+
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+unsafe fn matmul_neon(
+    a: &[f64],
+    b: &[f64],
+    c: &mut [f64],
+    m: usize,
+    n: usize,
+    k: usize,
+    block_size: usize,
+) {
+    c[..m * n].fill(0.0);
+    for i_block in (0..m).step_by(block_size) {
+        for j_block in (0..n).step_by(block_size) {
+            for k_block in (0..k).step_by(block_size) {
+                let i_end = (i_block + block_size).min(m);
+                let j_end = (j_block + block_size).min(n);
+                let k_end = (k_block + block_size).min(k);
+
+                for i in i_block..i_end {
+                    let mut j = j_block;
+                    while j + NEON_WIDTH <= j_end {
+                        let mut sum = vdupq_n_f64(0.0);
+                        for l in k_block..k_end {
+                            let b_vec = vld1q_f64(b.as_ptr().add(l * n + j));
+                            sum = vfmaq_f64(sum, b_vec, vdupq_n_f64(a[i * k + l]));
+                        }
+                        let c_ptr = c.as_mut_ptr().add(i * n + j);
+                        vst1q_f64(c_ptr, vaddq_f64(vld1q_f64(c_ptr), sum));
+                        j += NEON_WIDTH;
+                    }
+                    for j in j..j_end {
+                        let mut sum = 0.0;
+                        for l in k_block..k_end {
+                            sum += a[i * k + l] * b[l * n + j];
+                        }
+                        c[i * n + j] += sum;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+unsafe fn matvec_neon(a: &[f64], x: &[f64], y: &mut [f64], m: usize, n: usize) {
+    let chunks = n / NEON_WIDTH;
+    for i in 0..m {
+        let mut sum_vec = vdupq_n_f64(0.0);
+        for chunk in 0..chunks {
+            let j = chunk * NEON_WIDTH;
+            let a_vec = vld1q_f64(a.as_ptr().add(i * n + j));
+            sum_vec = vfmaq_f64(sum_vec, a_vec, vld1q_f64(x.as_ptr().add(j)));
+        }
+        let mut sum = vaddvq_f64(sum_vec);
+        for j in (chunks * NEON_WIDTH)..n {
+            sum += a[i * n + j] * x[j];
+        }
+        y[i] = sum;
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+unsafe fn add_bias_neon(matrix: &mut [f64], bias: &[f64], rows: usize, cols: usize) {
+    for i in 0..rows {
+        let mut j = 0;
+        while j + NEON_WIDTH <= cols {
+            let row_ptr = matrix.as_mut_ptr().add(i * cols + j);
+            let sum = vaddq_f64(vld1q_f64(row_ptr), vld1q_f64(bias.as_ptr().add(j)));
+            vst1q_f64(row_ptr, sum);
+            j += NEON_WIDTH;
+        }
+        for j in j..cols {
+            matrix[i * cols + j] += bias[j];
+        }
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+unsafe fn relu_neon(data: &mut [f64]) -> usize {
+    let zero = vdupq_n_f64(0.0);
+    let mut i = 0;
+    while i + NEON_WIDTH <= data.len() {
+        let ptr = data.as_mut_ptr().add(i);
+        vst1q_f64(ptr, vmaxq_f64(vld1q_f64(ptr), zero));
+        i += NEON_WIDTH;
+    }
+    i
+}
+
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+unsafe fn relu_derivatives_neon(data: &[f64], derivatives: &mut [f64]) -> usize {
+    let zero = vdupq_n_f64(0.0);
+    let one = vdupq_n_f64(1.0);
+    let mut i = 0;
+    while i + NEON_WIDTH <= data.len() {
+        let positive = vcgtq_f64(vld1q_f64(data.as_ptr().add(i)), zero);
+        let result = vbslq_f64(positive, one, zero);
+        vst1q_f64(derivatives.as_mut_ptr().add(i), result);
+        i += NEON_WIDTH;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::SimdConfig;
+    use super::*;
+
+    #[test]
+    fn test_f64_kernels_match_scalar() {
+        let scalar = CpuSimdOps::new(SimdConfig {
+            use_avx2: false,
+            block_size: 8,
+            ..SimdConfig::default()
+        });
+        let vectorized = CpuSimdOps::new(SimdConfig {
+            block_size: 8,
+            ..SimdConfig::default()
+        });
+        for (m, n, k) in [(1, 1, 1), (3, 5, 7), (9, 12, 17), (16, 4, 2)] {
+            let a: Vec<f64> = (0..m * k)
+                .map(|i| ((i * 29 % 53) as f64 - 26.0) / 13.0)
+                .collect();
+            let b: Vec<f64> = (0..k * n).map(|i| (i as f64 * 0.7).cos()).collect();
+            let bias: Vec<f64> = (0..n).map(|i| i as f64 * 0.1 - 0.3).collect();
+
+            let mut expected = vec![0.0; m * n];
+            scalar.matmul_scalar(&a, &b, &mut expected, m, n, k);
+            scalar.add_bias_scalar(&mut expected, &bias, m, n);
+            let mut expected_y = vec![0.0; m];
+            scalar.matvec_scalar(&a, &b[..k], &mut expected_y, m, k);
+
+            for ops in [&scalar, &vectorized] {
+                let mut c = vec![f64::NAN; m * n];
+                SimdMatrixOps::<f64>::matmul(ops, &a, &b, &mut c, m, n, k).unwrap();
+                SimdMatrixOps::<f64>::add_bias(ops, &mut c, &bias, m, n).unwrap();
+                let mut y = vec![f64::NAN; m];
+                SimdMatrixOps::<f64>::matvec(ops, &a, &b[..k], &mut y, m, k).unwrap();
+                for (got, want) in c.iter().chain(&y).zip(expected.iter().chain(&expected_y)) {
+                    assert!((got - want).abs() < 1e-12, "{m}x{n}x{k}: {got} vs {want}");
+                }
+            }
+        }
+
+        let data = [-1.5, 0.0, 2.0, -0.25, 3.0, 1e-300, -1e-300];
+        for ops in [&scalar, &vectorized] {
+            let mut activated = data;
+            ops.apply_activation(&mut activated, ActivationFunction::ReLU);
+            assert_eq!(activated, [0.0, 0.0, 2.0, 0.0, 3.0, 1e-300, 0.0]);
+            let mut derivatives = [f64::NAN; 7];
+            ops.activation_derivatives(&data, &mut derivatives, ActivationFunction::ReLU)
+                .unwrap();
+            assert_eq!(derivatives, [0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0]);
+
+            let mut sigmoid = data;
+            ops.apply_activation(&mut sigmoid, ActivationFunction::Sigmoid);
+            assert!((sigmoid[2] - ActivationFunction::Sigmoid.activate(2.0)).abs() < 1e-15);
+        }
+    }
+}
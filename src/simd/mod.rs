@@ -1,7 +1,7 @@
 //! SIMD optimizations for neural network computations
 //!
 //! This module provides vectorized implementations of critical operations:
-//! - Matrix multiplication with AVX2/AVX-512 support
+//! - Matrix multiplication with AVX2 and, for `f64`, NEON kernels
 //! - Vectorized activation functions
 //! - Parallel gradient computation
 //!
@@ -12,84 +12,89 @@
 
 use num_traits::Float;
 use std::sync::Arc;
+use thiserror::Error;
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
 use std::arch::x86_64::*;
 
-/// Configuration for SIMD operations
-#[derive(Debug, Clone)]
-pub struct SimdConfig {
-    /// Use AVX2 instructions if available
-    pub use_avx2: bool,
-    /// Use AVX-512 instructions if available
-    pub use_avx512: bool,
-    /// Block size for cache-friendly matrix operations
-    pub block_size: usize,
-    /// Number of threads for parallel operations
-    pub num_threads: usize,
-}
-
-impl Default for SimdConfig {
-    fn default() -> Self {
-        Self {
-            use_avx2: {
-                #[cfg(target_arch = "x86_64")]
-                {
-                    is_x86_feature_detected!("avx2")
-                }
-                #[cfg(not(target_arch = "x86_64"))]
-                {
-                    false
-                }
-            },
-            use_avx512: {
-                #[cfg(target_arch = "x86_64")]
-                {
-                    is_x86_feature_detected!("avx512f")
-                }
-                #[cfg(not(target_arch = "x86_64"))]
-                {
-                    false
-                }
-            },
-            block_size: 64, // Good balance for most L1 cache sizes
-            num_threads: num_cpus::get(),
-        }
-    }
-}
+mod blocked;
+mod config;
+mod dispatch;
+mod double;
+#[cfg(test)]
+mod proptests;
+mod quantized;
+
+pub use crate::ActivationFunction;
+pub use blocked::{PanelMatrix, PANEL_WIDTH};
+pub use config::{
+    SimdConfig, SimdConfigBuilder, SimdConfigError, ENV_AVX2, ENV_AVX512, ENV_BLOCK_SIZE,
+    ENV_THREADS,
+};
+pub use dispatch::{CpuFeatures, KernelPath};
+pub use quantized::{Int16Matrix, Int16Network};
 
 /// Trait for SIMD-accelerated matrix operations
+///
+/// Methods taking slices with dimensions fail with
+/// [`SimdError::DimensionMismatch`], before touching the output, when a
+/// slice is shorter than its dimensions require.
 pub trait SimdMatrixOps<T: Float + Send + Sync> {
     /// Perform matrix multiplication: C = A * B
-    fn matmul(&self, a: &[T], b: &[T], c: &mut [T], m: usize, n: usize, k: usize);
+    fn matmul(
+        &self,
+        a: &[T],
+        b: &[T],
+        c: &mut [T],
+        m: usize,
+        n: usize,
+        k: usize,
+    ) -> Result<(), SimdError>;
 
     /// Perform matrix-vector multiplication: y = A * x
-    fn matvec(&self, a: &[T], x: &[T], y: &mut [T], m: usize, n: usize);
+    fn matvec(&self, a: &[T], x: &[T], y: &mut [T], m: usize, n: usize) -> Result<(), SimdError>;
 
     /// Add bias vector to matrix rows
-    fn add_bias(&self, matrix: &mut [T], bias: &[T], rows: usize, cols: usize);
+    fn add_bias(
+        &self,
+        matrix: &mut [T],
+        bias: &[T],
+        rows: usize,
+        cols: usize,
+    ) -> Result<(), SimdError>;
 
-    /// Apply activation function element-wise
+    /// Apply activation function element-wise, at steepness 1
     fn apply_activation(&self, data: &mut [T], activation: ActivationFunction);
 
-    /// Compute activation derivatives
+    /// Compute activation derivatives at the pre-activation values in `data`
     fn activation_derivatives(
         &self,
         data: &[T],
         derivatives: &mut [T],
         activation: ActivationFunction,
-    );
+    ) -> Result<(), SimdError>;
 }
 
-/// Supported activation functions for SIMD optimization
-#[derive(Debug, Clone, Copy)]
-pub enum ActivationFunction {
-    Sigmoid,
-    Tanh,
-    Relu,
-    LeakyRelu(f32),
-    Gelu,
-    Swish,
+/// Errors raised by [`SimdMatrixOps`] kernels
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SimdError {
+    #[error("{name} holds {len} values but its dimensions need {needed}")]
+    DimensionMismatch {
+        name: &'static str,
+        len: usize,
+        needed: usize,
+    },
+}
+
+/// Fails unless a slice named `name` of `len` values holds the product of
+/// `dims`
+pub(crate) fn check_len(name: &'static str, len: usize, dims: &[usize]) -> Result<(), SimdError> {
+    // An overflowing product saturates, which no slice can hold
+    let needed = dims.iter().fold(1usize, |acc, &d| acc.saturating_mul(d));
+    if len < needed {
+        return Err(SimdError::DimensionMismatch { name, len, needed });
+    }
+    Ok(())
 }
 
 /// CPU-based SIMD implementation
@@ -107,75 +112,107 @@ impl CpuSimdOps {
             config: SimdConfig::default(),
         }
     }
+
+    /// Whether the AVX2 kernels may run: enabled in the config and
+    /// supported by this CPU. The config is public, so it alone cannot be
+    /// trusted before executing AVX2 instructions.
+    #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+    fn avx2_fma(&self) -> bool {
+        self.config.use_avx2 && is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma")
+    }
 }
 
 impl SimdMatrixOps<f32> for CpuSimdOps {
-    fn matmul(&self, a: &[f32], b: &[f32], c: &mut [f32], m: usize, n: usize, k: usize) {
-        #[cfg(target_arch = "x86_64")]
+    fn matmul(
+        &self,
+        a: &[f32],
+        b: &[f32],
+        c: &mut [f32],
+        m: usize,
+        n: usize,
+        k: usize,
+    ) -> Result<(), SimdError> {
+        check_len("a", a.len(), &[m, k])?;
+        check_len("b", b.len(), &[k, n])?;
+        check_len("c", c.len(), &[m, n])?;
+        #[cfg(all(
+            any(feature = "blas", all(target_os = "macos", feature = "accelerate")),
+            not(feature = "safe-only")
+        ))]
+        if self.use_blas(m.saturating_mul(n).saturating_mul(k))
+            && dispatch::cblas::sgemm(a, b, c, m, n, k)
         {
-            if self.config.use_avx2 {
-                unsafe {
-                    self.matmul_avx2(a, b, c, m, n, k);
-                }
-            } else {
-                self.matmul_scalar(a, b, c, m, n, k);
-            }
+            return Ok(());
         }
-        #[cfg(not(target_arch = "x86_64"))]
-        {
-            self.matmul_scalar(a, b, c, m, n, k);
+        #[cfg(all(feature = "matrixmultiply", not(feature = "safe-only")))]
+        if self.use_packed_gemm(m, n, k) && dispatch::packed::sgemm(a, b, c, m, n, k) {
+            return Ok(());
         }
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 and FMA were detected and the lengths checked
+            unsafe { self.matmul_avx2(a, b, c, m, n, k) };
+            return Ok(());
+        }
+        self.matmul_scalar(a, b, c, m, n, k);
+        Ok(())
     }
 
-    fn matvec(&self, a: &[f32], x: &[f32], y: &mut [f32], m: usize, n: usize) {
-        #[cfg(target_arch = "x86_64")]
-        {
-            if self.config.use_avx2 {
-                unsafe {
-                    self.matvec_avx2(a, x, y, m, n);
-                }
-            } else {
-                self.matvec_scalar(a, x, y, m, n);
-            }
+    fn matvec(
+        &self,
+        a: &[f32],
+        x: &[f32],
+        y: &mut [f32],
+        m: usize,
+        n: usize,
+    ) -> Result<(), SimdError> {
+        check_len("a", a.len(), &[m, n])?;
+        check_len("x", x.len(), &[n])?;
+        check_len("y", y.len(), &[m])?;
+        #[cfg(all(
+            any(feature = "blas", all(target_os = "macos", feature = "accelerate")),
+            not(feature = "safe-only")
+        ))]
+        if self.use_blas(m.saturating_mul(n)) && dispatch::cblas::sgemv(a, x, y, m, n) {
+            return Ok(());
         }
-        #[cfg(not(target_arch = "x86_64"))]
-        {
-            self.matvec_scalar(a, x, y, m, n);
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 and FMA were detected and the lengths checked
+            unsafe { self.matvec_avx2(a, x, y, m, n) };
+            return Ok(());
         }
+        self.matvec_scalar(a, x, y, m, n);
+        Ok(())
     }
 
-    fn add_bias(&self, matrix: &mut [f32], bias: &[f32], rows: usize, cols: usize) {
-        #[cfg(target_arch = "x86_64")]
-        {
-            if self.config.use_avx2 {
-                unsafe {
-                    self.add_bias_avx2(matrix, bias, rows, cols);
-                }
-            } else {
-                self.add_bias_scalar(matrix, bias, rows, cols);
-            }
-        }
-        #[cfg(not(target_arch = "x86_64"))]
-        {
-            self.add_bias_scalar(matrix, bias, rows, cols);
+    fn add_bias(
+        &self,
+        matrix: &mut [f32],
+        bias: &[f32],
+        rows: usize,
+        cols: usize,
+    ) -> Result<(), SimdError> {
+        check_len("matrix", matrix.len(), &[rows, cols])?;
+        check_len("bias", bias.len(), &[cols])?;
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 was detected and the lengths checked
+            unsafe { self.add_bias_avx2(matrix, bias, rows, cols) };
+            return Ok(());
         }
+        self.add_bias_scalar(matrix, bias, rows, cols);
+        Ok(())
     }
 
     fn apply_activation(&self, data: &mut [f32], activation: ActivationFunction) {
-        #[cfg(target_arch = "x86_64")]
-        {
-            if self.config.use_avx2 {
-                unsafe {
-                    self.apply_activation_avx2(data, activation);
-                }
-            } else {
-                self.apply_activation_scalar(data, activation);
-            }
-        }
-        #[cfg(not(target_arch = "x86_64"))]
-        {
-            self.apply_activation_scalar(data, activation);
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 was detected; the kernel stays within `data`
+            unsafe { self.apply_activation_avx2(data, activation) };
+            return;
         }
+        self.apply_activation_scalar(data, activation);
     }
 
     fn activation_derivatives(
@@ -183,29 +220,24 @@ impl SimdMatrixOps<f32> for CpuSimdOps {
         data: &[f32],
         derivatives: &mut [f32],
         activation: ActivationFunction,
-    ) {
-        #[cfg(target_arch = "x86_64")]
-        {
-            if self.config.use_avx2 {
-                unsafe {
-                    self.activation_derivatives_avx2(data, derivatives, activation);
-                }
-            } else {
-                self.activation_derivatives_scalar(data, derivatives, activation);
-            }
-        }
-        #[cfg(not(target_arch = "x86_64"))]
-        {
-            self.activation_derivatives_scalar(data, derivatives, activation);
+    ) -> Result<(), SimdError> {
+        check_len("derivatives", derivatives.len(), &[data.len()])?;
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 was detected and the lengths checked
+            unsafe { self.activation_derivatives_avx2(data, derivatives, activation) };
+            return Ok(());
         }
+        self.activation_derivatives_scalar(data, derivatives, activation);
+        Ok(())
     }
 }
 
 impl CpuSimdOps {
     /// Scalar fallback for matrix multiplication
-    fn matmul_scalar(&self, a: &[f32], b: &[f32], c: &mut [f32], m: usize, n: usize, k: usize) {
+    fn matmul_scalar<T: Float>(&self, a: &[T], b: &[T], c: &mut [T], m: usize, n: usize, k: usize) {
         // Initialize output to zero
-        c.fill(0.0);
+        c.fill(T::zero());
 
         // Use blocking for better cache performance
         let block_size = self.config.block_size;
@@ -219,11 +251,11 @@ impl CpuSimdOps {
 
                     for i in i_block..i_end {
                         for j in j_block..j_end {
-                            let mut sum = 0.0;
+                            let mut sum = T::zero();
                             for k_idx in k_block..k_end {
-                                sum += a[i * k + k_idx] * b[k_idx * n + j];
+                                sum = sum + a[i * k + k_idx] * b[k_idx * n + j];
                             }
-                            c[i * n + j] += sum;
+                            c[i * n + j] = c[i * n + j] + sum;
                         }
                     }
                 }
@@ -232,7 +264,8 @@ impl CpuSimdOps {
     }
 
     /// AVX2 optimized matrix multiplication
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+    #[target_feature(enable = "avx2,fma")]
     unsafe fn matmul_avx2(
         &self,
         a: &[f32],
@@ -293,18 +326,19 @@ impl CpuSimdOps {
     }
 
     /// Scalar matrix-vector multiplication
-    fn matvec_scalar(&self, a: &[f32], x: &[f32], y: &mut [f32], m: usize, n: usize) {
+    fn matvec_scalar<T: Float>(&self, a: &[T], x: &[T], y: &mut [T], m: usize, n: usize) {
         for i in 0..m {
-            let mut sum = 0.0;
+            let mut sum = T::zero();
             for j in 0..n {
-                sum += a[i * n + j] * x[j];
+                sum = sum + a[i * n + j] * x[j];
             }
             y[i] = sum;
         }
     }
 
     /// AVX2 optimized matrix-vector multiplication
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+    #[target_feature(enable = "avx2,fma")]
     unsafe fn matvec_avx2(&self, a: &[f32], x: &[f32], y: &mut [f32], m: usize, n: usize) {
         const SIMD_WIDTH: usize = 8;
 
@@ -338,16 +372,17 @@ impl CpuSimdOps {
     }
 
     /// Scalar bias addition
-    fn add_bias_scalar(&self, matrix: &mut [f32], bias: &[f32], rows: usize, cols: usize) {
+    fn add_bias_scalar<T: Float>(&self, matrix: &mut [T], bias: &[T], rows: usize, cols: usize) {
         for i in 0..rows {
             for j in 0..cols {
-                matrix[i * cols + j] += bias[j];
+                matrix[i * cols + j] = matrix[i * cols + j] + bias[j];
             }
         }
     }
 
     /// AVX2 optimized bias addition
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+    #[target_feature(enable = "avx2,fma")]
     unsafe fn add_bias_avx2(&self, matrix: &mut [f32], bias: &[f32], rows: usize, cols: usize) {
         const SIMD_WIDTH: usize = 8;
 
@@ -376,52 +411,22 @@ impl CpuSimdOps {
     }
 
     /// Scalar activation function application
-    fn apply_activation_scalar(&self, data: &mut [f32], activation: ActivationFunction) {
-        match activation {
-            ActivationFunction::Sigmoid => {
-                for x in data.iter_mut() {
-                    *x = 1.0 / (1.0 + (-*x).exp());
-                }
-            }
-            ActivationFunction::Tanh => {
-                for x in data.iter_mut() {
-                    *x = x.tanh();
-                }
-            }
-            ActivationFunction::Relu => {
-                for x in data.iter_mut() {
-                    *x = x.max(0.0);
-                }
-            }
-            ActivationFunction::LeakyRelu(alpha) => {
-                for x in data.iter_mut() {
-                    *x = if *x > 0.0 { *x } else { alpha * *x };
-                }
-            }
-            ActivationFunction::Gelu => {
-                for x in data.iter_mut() {
-                    // GELU approximation: 0.5 * x * (1 + tanh(sqrt(2/π) * (x + 0.044715 * x³)))
-                    let sqrt_2_over_pi = (2.0f32 / std::f32::consts::PI).sqrt();
-                    *x = *x * 0.5 * (1.0 + (sqrt_2_over_pi * (*x + 0.044715 * x.powi(3))).tanh());
-                }
-            }
-            ActivationFunction::Swish => {
-                for x in data.iter_mut() {
-                    *x = *x / (1.0 + (-*x).exp());
-                }
-            }
+    fn apply_activation_scalar<T: Float>(&self, data: &mut [T], activation: ActivationFunction) {
+        for x in data.iter_mut() {
+            *x = activation.activate(*x);
         }
     }
 
     /// AVX2 optimized activation function application
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+    #[target_feature(enable = "avx2,fma")]
     unsafe fn apply_activation_avx2(&self, data: &mut [f32], activation: ActivationFunction) {
         const SIMD_WIDTH: usize = 8;
         let len = data.len();
         let mut i = 0;
 
         match activation {
-            ActivationFunction::Relu => {
+            ActivationFunction::ReLU => {
                 let zero = _mm256_setzero_ps();
 
                 while i + SIMD_WIDTH <= len {
@@ -440,69 +445,24 @@ impl CpuSimdOps {
         }
 
         // Handle remaining elements
-        while i < len {
-            match activation {
-                ActivationFunction::Relu => {
-                    data[i] = data[i].max(0.0);
-                }
-                _ => unreachable!(),
-            }
-            i += 1;
-        }
+        self.apply_activation_scalar(&mut data[i..], activation);
     }
 
     /// Scalar activation derivatives
-    fn activation_derivatives_scalar(
+    fn activation_derivatives_scalar<T: Float>(
         &self,
-        data: &[f32],
-        derivatives: &mut [f32],
+        data: &[T],
+        derivatives: &mut [T],
         activation: ActivationFunction,
     ) {
-        match activation {
-            ActivationFunction::Sigmoid => {
-                for (i, &x) in data.iter().enumerate() {
-                    derivatives[i] = x * (1.0 - x);
-                }
-            }
-            ActivationFunction::Tanh => {
-                for (i, &x) in data.iter().enumerate() {
-                    derivatives[i] = 1.0 - x * x;
-                }
-            }
-            ActivationFunction::Relu => {
-                for (i, &x) in data.iter().enumerate() {
-                    derivatives[i] = if x > 0.0 { 1.0 } else { 0.0 };
-                }
-            }
-            ActivationFunction::LeakyRelu(alpha) => {
-                for (i, &x) in data.iter().enumerate() {
-                    derivatives[i] = if x > 0.0 { 1.0 } else { alpha };
-                }
-            }
-            ActivationFunction::Gelu => {
-                for (i, &x) in data.iter().enumerate() {
-                    let sqrt_2_over_pi = (2.0f32 / std::f32::consts::PI).sqrt();
-                    let tanh_arg = sqrt_2_over_pi * (x + 0.044715 * x.powi(3));
-                    let tanh_val = tanh_arg.tanh();
-                    derivatives[i] = 0.5
-                        * (1.0
-                            + tanh_val
-                            + x * sqrt_2_over_pi
-                                * (1.0 - tanh_val * tanh_val)
-                                * (1.0 + 0.134145 * x * x));
-                }
-            }
-            ActivationFunction::Swish => {
-                for (i, &x) in data.iter().enumerate() {
-                    let sigmoid = 1.0 / (1.0 + (-x).exp());
-                    derivatives[i] = sigmoid * (1.0 + x * (1.0 - sigmoid));
-                }
-            }
+        for (derivative, &x) in derivatives.iter_mut().zip(data) {
+            *derivative = activation.derivative(x, activation.activate(x));
         }
     }
 
     /// AVX2 optimized activation derivatives
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+    #[target_feature(enable = "avx2,fma")]
     unsafe fn activation_derivatives_avx2(
         &self,
         data: &[f32],
@@ -514,7 +474,7 @@ impl CpuSimdOps {
         let mut i = 0;
 
         match activation {
-            ActivationFunction::Relu => {
+            ActivationFunction::ReLU => {
                 let zero = _mm256_setzero_ps();
                 let one = _mm256_set1_ps(1.0);
 
@@ -538,15 +498,7 @@ impl CpuSimdOps {
         }
 
         // Handle remaining elements
-        while i < len {
-            match activation {
-                ActivationFunction::Relu => {
-                    derivatives[i] = if data[i] > 0.0 { 1.0 } else { 0.0 };
-                }
-                _ => unreachable!(),
-            }
-            i += 1;
-        }
+        self.activation_derivatives_scalar(&data[i..], &mut derivatives[i..], activation);
     }
 }
 
@@ -583,20 +535,22 @@ impl ParallelTraining {
             });
     }
 
-    /// Parallel gradient computation
+    /// Parallel gradient computation: each layer's gradients are the outer
+    /// product of its errors and activations. Fails if a gradient buffer
+    /// is too short.
     pub fn compute_gradients_parallel(
         &self,
         network_weights: &[Vec<f32>],
         activations: &[Vec<f32>],
         errors: &[Vec<f32>],
         gradients: &mut [Vec<f32>],
-    ) {
+    ) -> Result<(), SimdError> {
         use rayon::prelude::*;
 
         gradients
             .par_iter_mut()
             .enumerate()
-            .for_each(|(layer_idx, layer_gradients)| {
+            .try_for_each(|(layer_idx, layer_gradients)| {
                 if layer_idx < network_weights.len()
                     && layer_idx < activations.len()
                     && layer_idx < errors.len()
@@ -606,11 +560,12 @@ impl ParallelTraining {
                         &activations[layer_idx],
                         layer_gradients,
                         errors[layer_idx].len(),
-                        1,
                         activations[layer_idx].len(),
-                    );
+                        1,
+                    )?;
                 }
-            });
+                Ok(())
+            })
     }
 }
 
@@ -645,7 +600,7 @@ mod tests {
         let b = vec![5.0, 6.0, 7.0, 8.0]; // 2x2 matrix
         let mut c = vec![0.0; 4]; // 2x2 result
 
-        ops.matmul(&a, &b, &mut c, 2, 2, 2);
+        ops.matmul(&a, &b, &mut c, 2, 2, 2).unwrap();
 
         // Expected result: [19, 22, 43, 50]
         assert!((c[0] - 19.0).abs() < 1e-6);
@@ -659,7 +614,7 @@ mod tests {
         let ops = CpuSimdOps::new_with_defaults();
         let mut data = vec![-1.0, 0.0, 1.0, -2.0, 3.0];
 
-        ops.apply_activation(&mut data, ActivationFunction::Relu);
+        ops.apply_activation(&mut data, ActivationFunction::ReLU);
 
         assert_eq!(data, vec![0.0, 0.0, 1.0, 0.0, 3.0]);
     }
@@ -670,7 +625,8 @@ mod tests {
         let data = vec![-1.0, 0.0, 1.0, -2.0, 3.0];
         let mut derivatives = vec![0.0; 5];
 
-        ops.activation_derivatives(&data, &mut derivatives, ActivationFunction::Relu);
+        ops.activation_derivatives(&data, &mut derivatives, ActivationFunction::ReLU)
+            .unwrap();
 
         assert_eq!(derivatives, vec![0.0, 0.0, 1.0, 0.0, 1.0]);
     }
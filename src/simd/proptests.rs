@@ -0,0 +1,203 @@
+//! Property tests: every SIMD kernel against its scalar reference
+//!
+//! Each kernel runs at every instruction level this machine supports (scalar
+//! and, on x86_64, AVX2) on random shapes up to a few SIMD widths, so
+//! remainder lengths that do not fill a vector come up constantly. Inputs are
+//! sliced at a random element offset from a larger buffer to exercise
+//! unaligned loads. Sums are compared with a tolerance scaled by the
+//! magnitude of their terms, since SIMD kernels reassociate them.
+
+use super::*;
+use proptest::prelude::*;
+
+/// Every instruction level supported here, scalar first
+fn levels() -> Vec<(&'static str, CpuSimdOps)> {
+    let scalar = SimdConfig {
+        use_avx2: false,
+        use_avx512: false,
+        ..SimdConfig::default()
+    };
+    let mut levels = vec![("scalar", CpuSimdOps::new(scalar.clone()))];
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+        let avx2 = SimdConfig {
+            use_avx2: true,
+            ..scalar
+        };
+        levels.push(("avx2", CpuSimdOps::new(avx2)));
+    }
+    levels
+}
+
+/// Buffer and element offset of a slice within it
+type Buffer = (Vec<f32>, usize);
+
+/// `len` values starting `offset` elements into a fresh buffer, so the slice
+/// is misaligned for any offset that is not a multiple of 8
+fn values(len: usize) -> impl Strategy<Value = Buffer> {
+    (
+        prop::collection::vec(-8.0f32..8.0, len + 7..=len + 7),
+        0..8usize,
+    )
+}
+
+fn view(buffer: &Buffer, len: usize) -> &[f32] {
+    &buffer.0[buffer.1..buffer.1 + len]
+}
+
+/// Allowed difference for a sum whose terms have absolute values summing
+/// to `magnitude`
+fn tolerance(magnitude: f32) -> f32 {
+    1e-5 * (1.0 + magnitude)
+}
+
+/// `(m, n, k)` and operands for `C = A B`
+fn matmul_case() -> impl Strategy<Value = (usize, usize, usize, Buffer, Buffer)> {
+    (0..20usize, 0..28usize, 0..20usize)
+        .prop_flat_map(|(m, n, k)| (Just(m), Just(n), Just(k), values(m * k), values(k * n)))
+}
+
+/// `(rows, cols)`, matrix and vector for `y = A x`
+fn matvec_case() -> impl Strategy<Value = (usize, usize, Buffer, Buffer)> {
+    (0..27usize, 0..37usize)
+        .prop_flat_map(|(rows, cols)| (Just(rows), Just(cols), values(rows * cols), values(cols)))
+}
+
+/// Per-row magnitudes `sum |a_ij x_j|` of a matrix-vector product
+fn row_magnitudes(a: &[f32], x: &[f32], rows: usize, cols: usize) -> Vec<f32> {
+    (0..rows)
+        .map(|i| (0..cols).map(|j| (a[i * cols + j] * x[j]).abs()).sum())
+        .collect()
+}
+
+fn activations() -> impl Strategy<Value = ActivationFunction> {
+    use ActivationFunction::*;
+    prop::sample::select(vec![
+        Linear,
+        Threshold,
+        ThresholdSymmetric,
+        Sigmoid,
+        SigmoidSymmetric,
+        Tanh,
+        Gaussian,
+        GaussianSymmetric,
+        Elliot,
+        ElliotSymmetric,
+        LinearPiece,
+        LinearPieceSymmetric,
+        ReLU,
+        ReLULeaky,
+        Sin,
+        Cos,
+        SinSymmetric,
+        CosSymmetric,
+        Gelu,
+        Swish,
+    ])
+}
+
+proptest! {
+    #[test]
+    fn matmul_matches_scalar((m, n, k, a, b) in matmul_case(), block in 1..70usize) {
+        let (a, b) = (view(&a, m * k), view(&b, k * n));
+        let mut expected = vec![0.0; m * n];
+        for i in 0..m {
+            for j in 0..n {
+                expected[i * n + j] = (0..k).map(|l| a[i * k + l] * b[l * n + j]).sum();
+            }
+        }
+        for (level, mut ops) in levels() {
+            ops.config.block_size = block;
+            let mut c = vec![f32::NAN; m * n];
+            ops.matmul(a, b, &mut c, m, n, k).unwrap();
+            for i in 0..m {
+                for j in 0..n {
+                    let magnitude: f32 = (0..k).map(|l| (a[i * k + l] * b[l * n + j]).abs()).sum();
+                    let (actual, expected) = (c[i * n + j], expected[i * n + j]);
+                    prop_assert!(
+                        (actual - expected).abs() <= tolerance(magnitude),
+                        "{level} {m}x{n}x{k} block {block} at ({i}, {j}): {actual} vs {expected}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matvec_kernels_match_scalar((rows, cols, a, x) in matvec_case()) {
+        let (a, x) = (view(&a, rows * cols), view(&x, cols));
+        let magnitudes = row_magnitudes(a, x, rows, cols);
+        let mut expected = vec![0.0; rows];
+        levels()[0].1.matvec_scalar(a, x, &mut expected, rows, cols);
+
+        let panels = PanelMatrix::pack(a, rows, cols);
+        let quantized = Int16Matrix::quantize(a, rows, cols);
+        for (level, ops) in levels() {
+            let mut row_major = vec![f32::NAN; rows];
+            ops.matvec(a, x, &mut row_major, rows, cols).unwrap();
+            let mut panel = vec![f32::NAN; rows];
+            ops.matvec_panels(&panels, x, &mut panel).unwrap();
+            let mut int16 = vec![f32::NAN; rows];
+            ops.matvec_i16(&quantized, x, &mut int16).unwrap();
+
+            for i in 0..rows {
+                let tol = tolerance(magnitudes[i]);
+                prop_assert!((row_major[i] - expected[i]).abs() <= tol, "{level} matvec row {i}");
+                prop_assert!((panel[i] - expected[i]).abs() <= tol, "{level} panels row {i}");
+                // Rounding each weight moves it by at most half a scale step
+                let sum_x: f32 = x.iter().map(|v| v.abs()).sum();
+                let quantization = quantized.scales()[i] * 0.5 * sum_x;
+                prop_assert!(
+                    (int16[i] - expected[i]).abs() <= tol + quantization * (1.0 + 1e-3),
+                    "{level} int16 row {i}: {} vs {}", int16[i], expected[i]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn add_bias_matches_scalar(
+        (rows, cols, matrix, bias) in (0..9usize, 0..37usize)
+            .prop_flat_map(|(r, c)| (Just(r), Just(c), values(r * c), values(c)))
+    ) {
+        let (matrix, bias) = (view(&matrix, rows * cols), view(&bias, cols));
+        let mut expected = matrix.to_vec();
+        levels()[0].1.add_bias_scalar(&mut expected, bias, rows, cols);
+        for (level, ops) in levels() {
+            let mut actual = matrix.to_vec();
+            ops.add_bias(&mut actual, bias, rows, cols).unwrap();
+            prop_assert_eq!(&actual, &expected, "{}", level);
+        }
+    }
+
+    #[test]
+    fn activations_match_scalar(
+        (len, data) in (0..45usize).prop_flat_map(|len| (Just(len), values(len))),
+        activation in activations(),
+    ) {
+        let data = view(&data, len);
+        let mut expected = data.to_vec();
+        levels()[0].1.apply_activation_scalar(&mut expected, activation);
+        let mut expected_derivatives = vec![0.0; data.len()];
+        levels()[0]
+            .1
+            .activation_derivatives_scalar(data, &mut expected_derivatives, activation);
+
+        for (level, ops) in levels() {
+            let mut actual = data.to_vec();
+            ops.apply_activation(&mut actual, activation);
+            let mut derivatives = vec![f32::NAN; data.len()];
+            ops.activation_derivatives(data, &mut derivatives, activation).unwrap();
+            for i in 0..data.len() {
+                prop_assert!(
+                    (actual[i] - expected[i]).abs() <= 1e-6,
+                    "{level} {activation:?} at {i}"
+                );
+                prop_assert!(
+                    (derivatives[i] - expected_derivatives[i]).abs() <= 1e-6,
+                    "{level} {activation:?} derivative at {i}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Int16 weight compression
+//!
+//! Small dense layers are bound by memory bandwidth rather than arithmetic,
+//! so storing weights as `i16` with one `f32` scale per row halves the bytes
+//! streamed per matrix-vector product. Weights are dequantized inside the
+//! kernel ([`CpuSimdOps::matvec_i16`]): each lane of 8 `i16` values is
+//! widened to `f32` in registers, multiplied with the input, and the row sum
+//! is scaled once at the end; on aarch64 NEON does the same in two lanes of
+//! 4. With 15 bits per weight the relative error of
+//! a row is below `1 / 32767` of its largest weight.
+//!
+//! [`Int16Network`] runs a whole network this way, on top of the fused
+//! layers produced by [`Network::finalize`].
+
+use super::{check_len, CpuSimdOps, SimdError};
+use crate::network::{FinalizedNetwork, FusedLayer, Network, NetworkError};
+
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+use std::arch::aarch64::*;
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+use std::arch::x86_64::*;
+
+/// Row-major `i16` matrix with a dequantization scale per row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Int16Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<i16>,
+    scales: Vec<f32>,
+}
+
+impl Int16Matrix {
+    /// Quantize a row-major `rows x cols` matrix, scaling every row so its
+    /// largest magnitude maps to `i16::MAX`
+    pub fn quantize(matrix: &[f32], rows: usize, cols: usize) -> Self {
+        assert_eq!(matrix.len(), rows * cols, "matrix size mismatch");
+        let mut data = Vec::with_capacity(rows * cols);
+        let mut scales = Vec::with_capacity(rows);
+        for row in matrix.chunks_exact(cols.max(1)).take(rows) {
+            let max = row.iter().fold(0.0f32, |acc, w| acc.max(w.abs()));
+            let scale = max / i16::MAX as f32;
+            scales.push(scale);
+            data.extend(row.iter().map(|&w| {
+                if scale > 0.0 {
+                    (w / scale)
+                        .round()
+                        .clamp(-(i16::MAX as f32), i16::MAX as f32) as i16
+                } else {
+                    0
+                }
+            }));
+        }
+        scales.resize(rows, 0.0);
+        Self {
+            rows,
+            cols,
+            data,
+            scales,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn scales(&self) -> &[f32] {
+        &self.scales
+    }
+
+    /// Back to `f32`, row-major
+    pub fn dequantize(&self) -> Vec<f32> {
+        self.data
+            .chunks_exact(self.cols.max(1))
+            .zip(&self.scales)
+            .flat_map(|(row, &scale)| row.iter().map(move |&q| q as f32 * scale))
+            .collect()
+    }
+
+    /// Bytes of weights and scales
+    pub fn memory_bytes(&self) -> usize {
+        self.data.len() * std::mem::size_of::<i16>() + self.scales.len() * 4
+    }
+}
+
+impl CpuSimdOps {
+    /// `y = A x` with `A` dequantized on the fly; fails if `x` or `y` is too
+    /// short
+    pub fn matvec_i16(&self, a: &Int16Matrix, x: &[f32], y: &mut [f32]) -> Result<(), SimdError> {
+        check_len("x", x.len(), &[a.cols])?;
+        check_len("y", y.len(), &[a.rows])?;
+        #[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+        if self.avx2_fma() {
+            // SAFETY: AVX2 and FMA were detected and the lengths were checked
+            unsafe { matvec_i16_avx2(a, x, y) };
+            return Ok(());
+        }
+        // SAFETY: NEON is part of the aarch64 baseline and the lengths were
+        // checked above
+        #[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+        unsafe {
+            matvec_i16_neon(a, x, y)
+        }
+        #[cfg(not(all(target_arch = "aarch64", not(feature = "safe-only"))))]
+        matvec_i16_scalar(a, x, y);
+        Ok(())
+    }
+}
+
+#[cfg_attr(
+    all(target_arch = "aarch64", not(feature = "safe-only")),
+    allow(dead_code)
+)]
+fn matvec_i16_scalar(a: &Int16Matrix, x: &[f32], y: &mut [f32]) {
+    for (i, out) in y.iter_mut().enumerate().take(a.rows) {
+        let row = &a.data[i * a.cols..(i + 1) * a.cols];
+        let sum: f32 = row.iter().zip(x).map(|(&q, &v)| q as f32 * v).sum();
+        *out = sum * a.scales[i];
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "safe-only")))]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn matvec_i16_avx2(a: &Int16Matrix, x: &[f32], y: &mut [f32]) {
+    const SIMD_WIDTH: usize = 8;
+    let n = a.cols;
+    let chunks = n / SIMD_WIDTH;
+
+    for (i, out) in y.iter_mut().enumerate().take(a.rows) {
+        let row = &a.data[i * n..(i + 1) * n];
+        let mut sum_vec = _mm256_setzero_ps();
+        for chunk in 0..chunks {
+            let j = chunk * SIMD_WIDTH;
+            // Widen 8 x i16 -> 8 x i32 -> 8 x f32
+            let packed = _mm_loadu_si128(row.as_ptr().add(j) as *const __m128i);
+            let weights = _mm256_cvtepi32_ps(_mm256_cvtepi16_epi32(packed));
+            let inputs = _mm256_loadu_ps(x.as_ptr().add(j));
+            sum_vec = _mm256_fmadd_ps(weights, inputs, sum_vec);
+        }
+
+        let lanes = std::mem::transmute::<__m256, [f32; 8]>(sum_vec);
+        let mut sum = lanes.iter().sum::<f32>();
+        for (&q, &v) in row[chunks * SIMD_WIDTH..]
+            .iter()
+            .zip(&x[chunks * SIMD_WIDTH..n])
+        {
+            sum += q as f32 * v;
+        }
+        *out = sum * a.scales[i];
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", not(feature = "safe-only")))]
+unsafe fn matvec_i16_neon(a: &Int16Matrix, x: &[f32], y: &mut [f32]) {
+    const SIMD_WIDTH: usize = 8;
+    let n = a.cols;
+    let chunks = n / SIMD_WIDTH;
+
+    for (i, out) in y.iter_mut().enumerate().take(a.rows) {
+        let row = &a.data[i * n..(i + 1) * n];
+        let mut low_sum = vdupq_n_f32(0.0);
+        let mut high_sum = vdupq_n_f32(0.0);
+        for chunk in 0..chunks {
+            let j = chunk * SIMD_WIDTH;
+            // Widen 8 x i16 -> 2 x (4 x i32) -> 2 x (4 x f32)
+            let packed = vld1q_s16(row.as_ptr().add(j));
+            let low = vcvtq_f32_s32(vmovl_s16(vget_low_s16(packed)));
+            let high = vcvtq_f32_s32(vmovl_high_s16(packed));
+            low_sum = vfmaq_f32(low_sum, low, vld1q_f32(x.as_ptr().add(j)));
+            high_sum = vfmaq_f32(high_sum, high, vld1q_f32(x.as_ptr().add(j + 4)));
+        }
+
+        let mut sum = vaddvq_f32(vaddq_f32(low_sum, high_sum));
+        for (&q, &v) in row[chunks * SIMD_WIDTH..]
+            .iter()
+            .zip(&x[chunks * SIMD_WIDTH..n])
+        {
+            sum += q as f32 * v;
+        }
+        *out = sum * a.scales[i];
+    }
+}
+
+/// Inference-only network with `i16` weights
+pub struct Int16Network {
+    num_inputs: usize,
+    /// Quantized weights and the fused layer supplying bias and activation
+    /// (its `f32` weights are dropped)
+    layers: Vec<(Int16Matrix, FusedLayer<f32>)>,
+    ops: CpuSimdOps,
+}
+
+impl Int16Network {
+    /// Finalize `network` and quantize its weights
+    pub fn from_network(network: &Network<f32>) -> Result<Self, NetworkError> {
+        Ok(Self::from_finalized(&network.finalize()?))
+    }
+
+    pub fn from_finalized(network: &FinalizedNetwork<f32>) -> Self {
+        let layers = network
+            .layers()
+            .iter()
+            .map(|layer| {
+                let matrix = Int16Matrix::quantize(&layer.weights, layer.rows(), layer.inputs);
+                let mut layer = layer.clone();
+                layer.weights = Vec::new();
+                (matrix, layer)
+            })
+            .collect();
+        Self {
+            num_inputs: network.num_inputs(),
+            layers,
+            ops: CpuSimdOps::new_with_defaults(),
+        }
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.layers
+            .last()
+            .map_or(self.num_inputs, |(matrix, _)| matrix.rows())
+    }
+
+    /// Bytes of quantized weights and scales across all layers
+    pub fn weight_bytes(&self) -> usize {
+        self.layers.iter().map(|(m, _)| m.memory_bytes()).sum()
+    }
+
+    pub fn run(&self, input: &[f32]) -> Result<Vec<f32>, NetworkError> {
+        if input.len() != self.num_inputs {
+            return Err(NetworkError::InputSizeMismatch {
+                expected: self.num_inputs,
+                actual: input.len(),
+            });
+        }
+        let mut current = input.to_vec();
+        for (matrix, layer) in &self.layers {
+            let mut next = vec![0.0; matrix.rows()];
+            self.ops.matvec_i16(matrix, &current, &mut next).map_err(
+                |SimdError::DimensionMismatch { len, needed, .. }| {
+                    NetworkError::InputSizeMismatch {
+                        expected: needed,
+                        actual: len,
+                    }
+                },
+            )?;
+            layer.finish(&mut next);
+            current = next;
+        }
+        Ok(current)
+    }
+
+    pub fn run_batch(&self, inputs: &[Vec<f32>]) -> Result<Vec<Vec<f32>>, NetworkError> {
+        inputs.iter().map(|input| self.run(input)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::SimdConfig;
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_int16_matvec_and_network() {
+        let (rows, cols) = (5, 19);
+        let matrix: Vec<f32> = (0..rows * cols)
+            .map(|i| ((i * 37 % 101) as f32 - 50.0) / 40.0)
+            .collect();
+        let x: Vec<f32> = (0..cols).map(|i| (i as f32 * 0.3).sin()).collect();
+        let quantized = Int16Matrix::quantize(&matrix, rows, cols);
+        for (a, b) in quantized.dequantize().iter().zip(&matrix) {
+            assert!((a - b).abs() <= 1.25 / 32767.0 * 0.5 + 1e-7);
+        }
+        assert_eq!(quantized.memory_bytes(), rows * cols * 2 + rows * 4);
+
+        let scalar = CpuSimdOps::new(SimdConfig {
+            use_avx2: false,
+            ..SimdConfig::default()
+        });
+        let mut expected = vec![0.0; rows];
+        let mut y = vec![0.0; rows];
+        scalar.matvec_scalar(&matrix, &x, &mut expected, rows, cols);
+        for ops in [scalar, CpuSimdOps::new_with_defaults()] {
+            ops.matvec_i16(&quantized, &x, &mut y).unwrap();
+            for (a, b) in y.iter().zip(&expected) {
+                assert!((a - b).abs() < 1e-3);
+            }
+        }
+        assert_eq!(
+            CpuSimdOps::new_with_defaults().matvec_i16(&quantized, &x[1..], &mut y),
+            Err(SimdError::DimensionMismatch {
+                name: "x",
+                len: cols - 1,
+                needed: cols
+            })
+        );
+
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(12)
+            .hidden_layer(16)
+            .hidden_layer(16)
+            .output_layer(3)
+            .build();
+        let compressed = Int16Network::from_network(&network).unwrap();
+        let f32_bytes = network
+            .finalize()
+            .unwrap()
+            .layers()
+            .iter()
+            .map(|l| l.weights.len() * 4)
+            .sum::<usize>();
+        assert!(compressed.weight_bytes() * 10 < f32_bytes * 6);
+
+        let input: Vec<f32> = (0..12).map(|i| i as f32 / 12.0 - 0.4).collect();
+        let expected = network.run(&input);
+        let outputs = compressed.run(&input).unwrap();
+        assert_eq!(outputs.len(), compressed.num_outputs());
+        for (a, b) in outputs.iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-4);
+        }
+        assert!(compressed.run(&[0.0]).is_err());
+    }
+}
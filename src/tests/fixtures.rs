@@ -0,0 +1,24 @@
+//! Datasets and networks shared by the unit tests and the benches
+//!
+//! The benches include this file with `#[path]`, so it names everything
+//! through `super`, which imports the same items in both places.
+
+use super::{Float, Network, NetworkBuilder, TrainingData};
+
+/// The four XOR samples
+pub fn xor<T: Float>() -> TrainingData<T> {
+    let (o, l) = (T::zero(), T::one());
+    TrainingData {
+        inputs: vec![vec![o, o], vec![o, l], vec![l, o], vec![l, l]],
+        outputs: vec![vec![o], vec![l], vec![l], vec![o]],
+    }
+}
+
+/// A 2-3-1 sigmoid network for [`xor`]
+pub fn xor_network<T: Float>() -> Network<T> {
+    NetworkBuilder::<T>::new()
+        .input_layer(2)
+        .hidden_layer(3)
+        .output_layer(1)
+        .build()
+}
@@ -1 +1,8 @@
+pub(crate) mod fixtures;
 mod network_tests;
+mod panic_free;
+mod unsafe_paths;
+
+use crate::training::TrainingData;
+use crate::{Network, NetworkBuilder};
+use num_traits::Float;
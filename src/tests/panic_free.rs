@@ -0,0 +1,121 @@
+//! Library calls must not panic on adversarial input
+//!
+//! Every training algorithm is run on malformed and non-finite data under
+//! `catch_unwind`. Malformed data must come back as
+//! [`TrainingError::InvalidData`]; non-finite values may produce NaN errors
+//! but never a panic. Embedders behind FFI or WASM cannot unwind through
+//! the boundary, so a panic here is a bug.
+
+use crate::errors::RuvFannResult;
+use crate::training::*;
+use crate::{Network, NetworkBuilder};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+fn algorithms() -> Vec<(&'static str, Box<dyn TrainingAlgorithm<f64>>)> {
+    vec![
+        ("incremental", Box::new(IncrementalBackprop::new(0.1))),
+        ("batch", Box::new(BatchBackprop::new(0.1))),
+        ("rprop", Box::new(Rprop::new())),
+        ("quickprop", Box::new(Quickprop::new())),
+        ("adam", Box::new(Adam::new(0.1))),
+        ("adamw", Box::new(AdamW::new(0.1))),
+        ("nadam", Box::new(Nadam::new(0.1))),
+        ("radam", Box::new(RAdam::new(0.1))),
+        ("lion", Box::new(Lion::new(0.1))),
+        ("pipeline", Box::new(PipelineParallel::new(0.1))),
+        ("lamb", Box::new(Lamb::new(Adam::new(0.1), 0.01))),
+    ]
+}
+
+fn network() -> Network<f64> {
+    NetworkBuilder::<f64>::new()
+        .input_layer(2)
+        .hidden_layer(3)
+        .output_layer(1)
+        .build()
+}
+
+fn data(inputs: Vec<Vec<f64>>, outputs: Vec<Vec<f64>>) -> TrainingData<f64> {
+    TrainingData { inputs, outputs }
+}
+
+#[test]
+fn test_training_rejects_malformed_data_without_panicking() {
+    let malformed = [
+        ("count", data(vec![vec![0.0, 1.0]; 2], vec![vec![1.0]])),
+        ("narrow input", data(vec![vec![0.0]], vec![vec![1.0]])),
+        ("wide input", data(vec![vec![0.0; 5]], vec![vec![1.0]])),
+        ("narrow output", data(vec![vec![0.0, 1.0]], vec![vec![]])),
+        (
+            "wide output",
+            data(vec![vec![0.0, 1.0]], vec![vec![1.0; 3]]),
+        ),
+        (
+            "ragged",
+            data(vec![vec![0.0, 1.0], vec![1.0]], vec![vec![1.0], vec![0.0]]),
+        ),
+    ];
+    for (case, data) in &malformed {
+        for (name, mut algorithm) in algorithms() {
+            let mut network = network();
+            let weights = network.get_weights();
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                algorithm.train_epoch(&mut network, data)
+            }));
+            match result {
+                Ok(Err(TrainingError::InvalidData(_))) => {}
+                Ok(other) => panic!("{name} on {case}: expected InvalidData, got {other:?}"),
+                Err(_) => panic!("{name} panicked on {case}"),
+            }
+            assert_eq!(
+                network.get_weights(),
+                weights,
+                "{name} changed weights on {case}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_training_survives_degenerate_values() {
+    let degenerate = [
+        ("empty", data(vec![], vec![])),
+        (
+            "non-finite",
+            data(
+                vec![vec![f64::NAN, f64::INFINITY], vec![f64::MAX, -f64::MAX]],
+                vec![vec![f64::NAN], vec![f64::NEG_INFINITY]],
+            ),
+        ),
+        ("huge", data(vec![vec![1e300, -1e300]], vec![vec![1e300]])),
+    ];
+    for (case, data) in &degenerate {
+        for (name, mut algorithm) in algorithms() {
+            let mut network = network();
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                for _ in 0..3 {
+                    let _ = algorithm.train_epoch(&mut network, data);
+                }
+                let _ = algorithm.calculate_error(&network, data);
+                let _ = algorithm.count_bit_fails(&network, data, 0.1);
+                let _ = network.run(&[f64::NAN, 0.0]);
+            }));
+            assert!(result.is_ok(), "{name} panicked on {case}");
+        }
+    }
+}
+
+#[test]
+fn test_schedules_and_errors_into_library_result() {
+    let mut step = StepDecay::new(0.1f64, 0.5, 0);
+    assert_eq!(step.get_rate(3), 0.1 * 0.125);
+    let mut exponential = ExponentialDecay::new(0.1f64, 0.5);
+    assert_eq!(exponential.get_rate(usize::MAX), 0.0);
+
+    // Training errors propagate with `?` into the crate-wide result type
+    fn train(network: &mut Network<f64>) -> RuvFannResult<f64> {
+        let data = data(vec![vec![0.0]], vec![vec![1.0]]);
+        Ok(Adam::new(0.1).train_epoch(network, &data)?)
+    }
+    assert!(train(&mut network()).is_err());
+}
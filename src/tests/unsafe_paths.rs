@@ -0,0 +1,132 @@
+//! Tests for every code path that contains unsafe code
+//!
+//! They are small enough to run under Miri and AddressSanitizer; CI selects
+//! them by the `unsafe_paths` module name:
+//!
+//! ```text
+//! cargo +nightly miri test --lib unsafe_paths
+//! RUSTFLAGS=-Zsanitizer=address cargo +nightly test --lib \
+//!     --target x86_64-unknown-linux-gnu --features huge-pages,numa unsafe_paths
+//! ```
+//!
+//! Under Miri the SIMD kernels take their scalar paths, as Miri's default
+//! target has no AVX2, and arenas are heap-backed.
+
+use crate::memory_manager::{
+    HugePagePolicy, MemoryPrefetcher, NumaTopology, PrefetchHint, WeightArena,
+};
+
+#[cfg(feature = "parallel")]
+use crate::simd::{
+    ActivationFunction, CpuSimdOps, Int16Matrix, PanelMatrix, SimdError, SimdMatrixOps, PANEL_WIDTH,
+};
+
+#[test]
+fn test_weight_arena_lifecycle() {
+    let policy = HugePagePolicy {
+        enabled: true,
+        threshold_bytes: 0,
+    };
+    for len in [0, 1, 1000] {
+        let values: Vec<f64> = (0..len).map(|i| i as f64).collect();
+        let mut arena = WeightArena::from_slice(&values, &policy);
+        assert_eq!(&arena[..], &values[..]);
+        if let Some(last) = arena.last_mut() {
+            *last = -1.0;
+        }
+        let moved = std::thread::spawn(move || arena.iter().sum::<f64>())
+            .join()
+            .unwrap();
+        let expected = values.iter().sum::<f64>() - values.last().map_or(0.0, |v| v + 1.0);
+        assert_eq!(moved, expected);
+    }
+}
+
+#[test]
+fn test_numa_arena_and_binding() {
+    let topology = NumaTopology::detect();
+    let node = topology.nodes()[0].id;
+    let arena: WeightArena<f32> = topology.allocate_arena(node, 64, &HugePagePolicy::default());
+    assert!(arena.iter().all(|&v| v == 0.0));
+    // Pinning may be unavailable, but must never misbehave
+    let _ = topology.bind_current_thread(node);
+    assert!(!topology.bind_current_thread(usize::MAX));
+}
+
+#[test]
+fn test_prefetch_padded_and_empty_slices() {
+    // `(u8, u32)` has padding bytes, which the prefetcher may touch
+    let padded = vec![(1u8, 2u32); 40];
+    let prefetcher = MemoryPrefetcher::new(PrefetchHint::L1);
+    assert_eq!(prefetcher.prefetch(&padded), 5);
+    assert_eq!(prefetcher.prefetch::<f32>(&[]), 0);
+    assert_eq!(prefetcher.prefetch(&[(); 8]), 0);
+    // Unaligned start
+    let bytes = [0u8; 200];
+    assert_eq!(prefetcher.prefetch(&bytes[3..]), 4);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_simd_kernels_on_unaligned_remainders() {
+    let ops = CpuSimdOps::new_with_defaults();
+    let buffer: Vec<f32> = (0..80).map(|i| (i as f32 * 0.37).sin()).collect();
+    for (rows, cols) in [(0, 0), (1, 1), (3, 9), (PANEL_WIDTH + 1, 7)] {
+        let a = &buffer[1..1 + rows * cols];
+        let x = &buffer[3..3 + cols];
+        let mut expected = vec![0.0; rows];
+        for (i, out) in expected.iter_mut().enumerate() {
+            *out = (0..cols).map(|j| a[i * cols + j] * x[j]).sum();
+        }
+
+        let mut y = vec![0.0; rows];
+        ops.matvec(a, x, &mut y, rows, cols).unwrap();
+        let panels = PanelMatrix::pack(a, rows, cols);
+        let mut panel_y = vec![0.0; rows];
+        ops.matvec_panels(&panels, x, &mut panel_y).unwrap();
+        let mut int16_y = vec![0.0; rows];
+        ops.matvec_i16(&Int16Matrix::quantize(a, rows, cols), x, &mut int16_y)
+            .unwrap();
+        for i in 0..rows {
+            assert!((y[i] - expected[i]).abs() < 1e-5);
+            // Padding lanes are NaN in debug builds and must not leak
+            assert!((panel_y[i] - expected[i]).abs() < 1e-5);
+            assert!((int16_y[i] - expected[i]).abs() < 1e-3);
+        }
+
+        let mut c = vec![0.0; rows];
+        ops.matmul(a, x, &mut c, rows, 1, cols).unwrap();
+        let mut biased = buffer[5..5 + rows * cols].to_vec();
+        ops.add_bias(&mut biased, x, rows, cols).unwrap();
+        let mut activated = buffer[1..1 + rows * cols].to_vec();
+        ops.apply_activation(&mut activated, ActivationFunction::ReLU);
+        let mut derivatives = vec![0.0; rows * cols];
+        ops.activation_derivatives(a, &mut derivatives, ActivationFunction::ReLU)
+            .unwrap();
+        assert!(activated.iter().all(|&v| v >= 0.0));
+    }
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_simd_rejects_short_buffers() {
+    // The kernels index with raw pointers, so a short output must be caught
+    // before they run
+    let ops = CpuSimdOps::new_with_defaults();
+    let mut y = vec![0.0; 2];
+    let error = ops.matvec(&[1.0; 12], &[1.0; 4], &mut y, 3, 4).unwrap_err();
+    assert_eq!(
+        error,
+        SimdError::DimensionMismatch {
+            name: "y",
+            len: 2,
+            needed: 3
+        }
+    );
+    assert_eq!(y, [0.0; 2]);
+    // Dimensions whose product overflows are rejected rather than wrapped
+    let mut c = vec![0.0; 4];
+    assert!(ops
+        .matmul(&[1.0; 4], &[1.0; 4], &mut c, usize::MAX, 2, 2)
+        .is_err());
+}
@@ -1,9 +1,13 @@
 //! Adam and AdamW optimizers for neural network training
 //!
 //! These optimizers provide significant convergence improvements over traditional SGD:
-//! - Adam: Adaptive moment estimation with bias correction
+//! - Adam: Adaptive moment estimation with bias correction, optionally with
+//!   the AMSGrad variant that keeps the maximum of past second moments
 //! - AdamW: Adam with decoupled weight decay (better regularization)
 //!
+//! Both take their weight decay as a [`Regularization`]; they only differ in
+//! the [`DecayMode`] that `with_weight_decay` selects.
+//!
 //! Expected performance gains:
 //! - 2-5x faster convergence in terms of epochs needed
 //! - Better handling of sparse gradients and noisy data
@@ -11,9 +15,13 @@
 
 #![allow(clippy::needless_range_loop)]
 
+use super::centralization::centralize_gradients;
+use super::statistics::{buffer_bytes, gradient_norm};
 use super::*;
+use crate::numeric::cast;
 use num_traits::Float;
 use std::collections::HashMap;
+use std::time::Instant;
 
 /// Adam optimizer implementation
 /// Uses adaptive moment estimation with bias correction for faster convergence
@@ -22,8 +30,11 @@ pub struct Adam<T: Float + Send + Default> {
     beta1: T,
     beta2: T,
     epsilon: T,
-    weight_decay: T,
+    regularization: Regularization<T>,
+    amsgrad: bool,
+    options: TrainingOptions,
     error_function: Box<dyn ErrorFunction<T>>,
+    privacy: Option<DifferentialPrivacy<T>>,
 
     // Moment estimates
     m_weights: Vec<Vec<T>>, // First moment (momentum)
@@ -31,10 +42,16 @@ pub struct Adam<T: Float + Send + Default> {
     m_biases: Vec<Vec<T>>,
     v_biases: Vec<Vec<T>>,
 
+    // Maximum second moments seen so far (AMSGrad only)
+    v_max_weights: Vec<Vec<T>>,
+    v_max_biases: Vec<Vec<T>>,
+
     // Step counter for bias correction
     step: usize,
 
-    callback: Option<TrainingCallback<T>>,
+    trust_ratio: Option<TrustRatio<T>>,
+    callbacks: EventDispatcher<T>,
+    statistics: TrainingStatistics<T>,
 }
 
 impl<T: Float + Send + Default> Adam<T> {
@@ -42,17 +59,24 @@ impl<T: Float + Send + Default> Adam<T> {
     pub fn new(learning_rate: T) -> Self {
         Self {
             learning_rate,
-            beta1: T::from(0.9).unwrap(),
-            beta2: T::from(0.999).unwrap(),
-            epsilon: T::from(1e-8).unwrap(),
-            weight_decay: T::zero(),
+            beta1: cast(0.9),
+            beta2: cast(0.999),
+            epsilon: cast(1e-8),
+            regularization: Regularization::none(),
+            amsgrad: false,
+            options: TrainingOptions::new(),
             error_function: Box::new(MseError),
+            privacy: None,
             m_weights: Vec::new(),
             v_weights: Vec::new(),
             m_biases: Vec::new(),
             v_biases: Vec::new(),
+            v_max_weights: Vec::new(),
+            v_max_biases: Vec::new(),
             step: 0,
-            callback: None,
+            trust_ratio: None,
+            callbacks: EventDispatcher::new(),
+            statistics: TrainingStatistics::new(),
         }
     }
 
@@ -74,9 +98,23 @@ impl<T: Float + Send + Default> Adam<T> {
         self
     }
 
-    /// Set weight decay (L2 regularization)
+    /// Set weight decay as an L2 penalty on the gradient
+    /// ([`DecayMode::Coupled`]); use [`AdamW`] for decoupled decay
     pub fn with_weight_decay(mut self, weight_decay: T) -> Self {
-        self.weight_decay = weight_decay;
+        self.regularization = Regularization::coupled(weight_decay);
+        self
+    }
+
+    /// Set the weight decay configuration
+    pub fn with_regularization(mut self, regularization: Regularization<T>) -> Self {
+        self.regularization = regularization;
+        self
+    }
+
+    /// Use AMSGrad: normalize by the maximum of all past second moments
+    /// instead of their running average, so the effective step size never grows
+    pub fn with_amsgrad(mut self, amsgrad: bool) -> Self {
+        self.amsgrad = amsgrad;
         self
     }
 
@@ -86,23 +124,21 @@ impl<T: Float + Send + Default> Adam<T> {
         self
     }
 
+    /// Train on clipped, noised per-sample gradients (DP-SGD)
+    pub fn with_differential_privacy(mut self, privacy: DifferentialPrivacy<T>) -> Self {
+        self.privacy = Some(privacy);
+        self
+    }
+
+    /// Privacy configuration and the budget spent so far, if enabled
+    pub fn privacy(&self) -> Option<&DifferentialPrivacy<T>> {
+        self.privacy.as_ref()
+    }
+
     /// Initialize moment estimates for the network
     fn initialize_moments(&mut self, network: &Network<T>) {
         if self.m_weights.is_empty() {
-            self.m_weights = network
-                .layers
-                .iter()
-                .skip(1) // Skip input layer
-                .map(|layer| {
-                    let num_neurons = layer.neurons.len();
-                    let num_connections = if layer.neurons.is_empty() {
-                        0
-                    } else {
-                        layer.neurons[0].connections.len()
-                    };
-                    vec![T::zero(); num_neurons * num_connections]
-                })
-                .collect();
+            self.m_weights = super::helpers::weight_buffers(network, T::zero());
 
             self.v_weights = self.m_weights.clone();
 
@@ -115,6 +151,10 @@ impl<T: Float + Send + Default> Adam<T> {
 
             self.v_biases = self.m_biases.clone();
         }
+        if self.amsgrad && self.v_max_weights.is_empty() {
+            self.v_max_weights = self.v_weights.clone();
+            self.v_max_biases = self.v_biases.clone();
+        }
     }
 
     /// Update parameters using Adam algorithm
@@ -145,9 +185,16 @@ impl<T: Float + Send + Default> Adam<T> {
                 self.v_weights[layer_idx][i] = self.beta2 * self.v_weights[layer_idx][i]
                     + (T::one() - self.beta2) * grad * grad;
 
+                let v = if self.amsgrad {
+                    let v_max = &mut self.v_max_weights[layer_idx][i];
+                    *v_max = v_max.max(self.v_weights[layer_idx][i]);
+                    *v_max
+                } else {
+                    self.v_weights[layer_idx][i]
+                };
+
                 // Compute parameter update
-                let update = lr_t * self.m_weights[layer_idx][i]
-                    / (self.v_weights[layer_idx][i].sqrt() + self.epsilon);
+                let update = lr_t * self.m_weights[layer_idx][i] / (v.sqrt() + self.epsilon);
 
                 layer_updates.push(-update);
             }
@@ -169,30 +216,46 @@ impl<T: Float + Send + Default> Adam<T> {
                 self.v_biases[layer_idx][i] = self.beta2 * self.v_biases[layer_idx][i]
                     + (T::one() - self.beta2) * grad * grad;
 
+                let v = if self.amsgrad {
+                    let v_max = &mut self.v_max_biases[layer_idx][i];
+                    *v_max = v_max.max(self.v_biases[layer_idx][i]);
+                    *v_max
+                } else {
+                    self.v_biases[layer_idx][i]
+                };
+
                 // Compute parameter update
-                let update = lr_t * self.m_biases[layer_idx][i]
-                    / (self.v_biases[layer_idx][i].sqrt() + self.epsilon);
+                let update = lr_t * self.m_biases[layer_idx][i] / (v.sqrt() + self.epsilon);
 
                 layer_updates.push(-update);
             }
             bias_updates.push(layer_updates);
         }
 
-        // Apply weight decay if specified (Adam approach - apply to gradients)
-        if self.weight_decay > T::zero() {
-            for layer_updates in &mut weight_updates {
-                for update in layer_updates {
-                    *update = *update - self.learning_rate * self.weight_decay;
-                }
-            }
-        }
-
         // Apply updates using existing helper
+        if let Some(trust_ratio) = self.trust_ratio.as_mut() {
+            trust_ratio.scale(
+                network,
+                self.learning_rate,
+                &mut weight_updates,
+                &mut bias_updates,
+            );
+        }
         super::helpers::apply_updates_to_network(network, &weight_updates, &bias_updates);
     }
 }
 
-impl<T: Float + Send + Default> TrainingAlgorithm<T> for Adam<T> {
+impl<T: Float + Send + Default> TrustRatioUpdates<T> for Adam<T> {
+    fn trust_ratio(&self) -> Option<&TrustRatio<T>> {
+        self.trust_ratio.as_ref()
+    }
+
+    fn set_trust_ratio(&mut self, trust_ratio: Option<TrustRatio<T>>) {
+        self.trust_ratio = trust_ratio;
+    }
+}
+
+impl<T: Float + Send + Sync + Default> TrainingAlgorithm<T> for Adam<T> {
     fn train_epoch(
         &mut self,
         network: &mut Network<T>,
@@ -200,69 +263,47 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Adam<T> {
     ) -> Result<T, TrainingError> {
         use super::helpers::*;
 
-        self.initialize_moments(network);
+        if let Some(mut batches) = self.options.mini_batches.take() {
+            if let Some(privacy) = self.privacy.as_mut() {
+                privacy.set_population(Some(data.inputs.len()));
+            }
+            let error = batches.train_epoch(self, network, data);
+            if let Some(privacy) = self.privacy.as_mut() {
+                privacy.set_population(None);
+            }
+            self.options.mini_batches = Some(batches);
+            return error;
+        }
+        data.check_shapes(network)?;
+        let trimmed = self
+            .options
+            .trimmed_loss
+            .trim(network, data, self.error_function.as_ref());
+        let data = trimmed.as_ref();
+        let epoch_start = Instant::now();
 
-        let mut total_error = T::zero();
+        self.initialize_moments(network);
 
         // Convert network to simplified form for easier manipulation
         let simple_network = network_to_simple(network);
 
-        // Accumulate gradients over entire batch
-        let mut accumulated_weight_gradients = simple_network
-            .weights
-            .iter()
-            .map(|w| vec![T::zero(); w.len()])
-            .collect::<Vec<_>>();
-        let mut accumulated_bias_gradients = simple_network
-            .biases
-            .iter()
-            .map(|b| vec![T::zero(); b.len()])
-            .collect::<Vec<_>>();
-
-        // Process all samples in the batch
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            // Forward propagation to get all layer activations
-            let activations = forward_propagate(&simple_network, input);
+        // Mean gradients over the batch, privatized when configured
+        let (error, mut accumulated_weight_gradients, mut accumulated_bias_gradients) =
+            match self.privacy.as_mut() {
+                Some(privacy) => privacy.gradients(network, data, self.error_function.as_ref()),
+                None => batch_gradients(network, data, self.error_function.as_ref()),
+            };
 
-            // Get output from last layer
-            let output = &activations[activations.len() - 1];
-
-            // Calculate error
-            total_error = total_error + self.error_function.calculate(output, desired_output);
-
-            // Calculate gradients using backpropagation
-            let (weight_gradients, bias_gradients) = calculate_gradients(
-                &simple_network,
-                &activations,
-                desired_output,
-                self.error_function.as_ref(),
-            );
-
-            // Accumulate gradients
-            for layer_idx in 0..weight_gradients.len() {
-                for i in 0..weight_gradients[layer_idx].len() {
-                    accumulated_weight_gradients[layer_idx][i] =
-                        accumulated_weight_gradients[layer_idx][i] + weight_gradients[layer_idx][i];
-                }
-                for i in 0..bias_gradients[layer_idx].len() {
-                    accumulated_bias_gradients[layer_idx][i] =
-                        accumulated_bias_gradients[layer_idx][i] + bias_gradients[layer_idx][i];
-                }
-            }
-        }
-
-        // Average gradients over batch size
-        let batch_size = T::from(data.inputs.len()).unwrap();
-        for layer_idx in 0..accumulated_weight_gradients.len() {
-            for i in 0..accumulated_weight_gradients[layer_idx].len() {
-                accumulated_weight_gradients[layer_idx][i] =
-                    accumulated_weight_gradients[layer_idx][i] / batch_size;
-            }
-            for i in 0..accumulated_bias_gradients[layer_idx].len() {
-                accumulated_bias_gradients[layer_idx][i] =
-                    accumulated_bias_gradients[layer_idx][i] / batch_size;
-            }
+        if self.options.gradient_centralization {
+            centralize_gradients(network, &mut accumulated_weight_gradients);
         }
+        self.regularization.add_to_gradients(
+            network,
+            &mut accumulated_weight_gradients,
+            &mut accumulated_bias_gradients,
+        );
+        self.regularization
+            .decay_weights(network, self.learning_rate);
 
         // Update parameters using Adam
         self.update_parameters(
@@ -271,19 +312,29 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Adam<T> {
             &accumulated_bias_gradients,
         );
 
-        Ok(total_error / batch_size)
+        self.statistics.record_epoch(
+            epoch_start.elapsed(),
+            data.inputs.len(),
+            gradient_norm(&accumulated_weight_gradients, &accumulated_bias_gradients),
+            buffer_bytes(&[
+                &simple_network.weights,
+                &simple_network.biases,
+                &accumulated_weight_gradients,
+                &accumulated_bias_gradients,
+                &self.m_weights,
+                &self.v_weights,
+                &self.m_biases,
+                &self.v_biases,
+            ]),
+        );
+        self.statistics
+            .record_gradients(accumulated_weight_gradients);
+
+        Ok(error)
     }
 
     fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
-        let mut total_error = T::zero();
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-            total_error = total_error + self.error_function.calculate(&output, desired_output);
-        }
-
-        total_error / T::from(data.inputs.len()).unwrap()
+        super::evaluation::batch_error(network, data, self.error_function.as_ref())
     }
 
     fn count_bit_fails(
@@ -292,19 +343,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Adam<T> {
         data: &TrainingData<T>,
         bit_fail_limit: T,
     ) -> usize {
-        let mut bit_fails = 0;
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-            for (&actual, &desired) in output.iter().zip(desired_output.iter()) {
-                if (actual - desired).abs() > bit_fail_limit {
-                    bit_fails += 1;
-                }
-            }
-        }
-
-        bit_fails
+        super::evaluation::batch_bit_fails(network, data, bit_fail_limit)
     }
 
     fn save_state(&self) -> TrainingState<T> {
@@ -313,12 +352,22 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Adam<T> {
         state.insert("beta1".to_string(), vec![self.beta1]);
         state.insert("beta2".to_string(), vec![self.beta2]);
         state.insert("epsilon".to_string(), vec![self.epsilon]);
-        state.insert("weight_decay".to_string(), vec![self.weight_decay]);
-        state.insert("step".to_string(), vec![T::from(self.step).unwrap()]);
+        self.regularization.save(&mut state);
+        state.insert("step".to_string(), vec![cast(self.step)]);
+        state.insert(
+            "amsgrad".to_string(),
+            vec![if self.amsgrad { T::one() } else { T::zero() }],
+        );
+        save_buffers(&mut state, "m_weights", &self.m_weights);
+        save_buffers(&mut state, "v_weights", &self.v_weights);
+        save_buffers(&mut state, "m_biases", &self.m_biases);
+        save_buffers(&mut state, "v_biases", &self.v_biases);
+        save_buffers(&mut state, "v_max_weights", &self.v_max_weights);
+        save_buffers(&mut state, "v_max_biases", &self.v_max_biases);
 
         TrainingState {
             epoch: 0,
-            best_error: T::from(f32::MAX).unwrap(),
+            best_error: cast(f32::MAX),
             algorithm_specific: state,
         }
     }
@@ -344,20 +393,52 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Adam<T> {
                 self.epsilon = eps[0];
             }
         }
-        if let Some(wd) = state.algorithm_specific.get("weight_decay") {
-            if !wd.is_empty() {
-                self.weight_decay = wd[0];
-            }
-        }
+        self.regularization.restore(&state.algorithm_specific);
         if let Some(s) = state.algorithm_specific.get("step") {
             if !s.is_empty() {
                 self.step = s[0].to_usize().unwrap_or(0);
             }
         }
+        if let Some(amsgrad) = state.algorithm_specific.get("amsgrad") {
+            if !amsgrad.is_empty() {
+                self.amsgrad = amsgrad[0] != T::zero();
+            }
+        }
+        let state = &state.algorithm_specific;
+        self.m_weights = restore_buffers(state, "m_weights");
+        self.v_weights = restore_buffers(state, "v_weights");
+        self.m_biases = restore_buffers(state, "m_biases");
+        self.v_biases = restore_buffers(state, "v_biases");
+        self.v_max_weights = restore_buffers(state, "v_max_weights");
+        self.v_max_biases = restore_buffers(state, "v_max_biases");
+    }
+
+    fn learning_rate(&self) -> Option<T> {
+        Some(self.learning_rate)
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.learning_rate = rate;
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.statistics.last_gradient_norm()
+    }
+
+    fn last_weight_gradients(&self) -> Option<&[Vec<T>]> {
+        self.statistics.last_weight_gradients()
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.options.cancellation = token;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.options.is_cancelled()
     }
 
     fn set_callback(&mut self, callback: TrainingCallback<T>) {
-        self.callback = Some(callback);
+        self.callbacks.subscribe(callback);
     }
 
     fn call_callback(
@@ -366,12 +447,17 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Adam<T> {
         network: &Network<T>,
         data: &TrainingData<T>,
     ) -> bool {
-        let error = self.calculate_error(network, data);
-        if let Some(ref mut callback) = self.callback {
-            callback(epoch, error)
-        } else {
-            true
+        if self.callbacks.is_empty() {
+            return true;
         }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
     }
 }
 
@@ -382,7 +468,8 @@ pub struct AdamW<T: Float + Send + Default> {
     beta1: T,
     beta2: T,
     epsilon: T,
-    weight_decay: T,
+    regularization: Regularization<T>,
+    options: TrainingOptions,
     error_function: Box<dyn ErrorFunction<T>>,
 
     // Moment estimates
@@ -394,7 +481,9 @@ pub struct AdamW<T: Float + Send + Default> {
     // Step counter for bias correction
     step: usize,
 
-    callback: Option<TrainingCallback<T>>,
+    trust_ratio: Option<TrustRatio<T>>,
+    callbacks: EventDispatcher<T>,
+    statistics: TrainingStatistics<T>,
 }
 
 impl<T: Float + Send + Default> AdamW<T> {
@@ -402,17 +491,20 @@ impl<T: Float + Send + Default> AdamW<T> {
     pub fn new(learning_rate: T) -> Self {
         Self {
             learning_rate,
-            beta1: T::from(0.9).unwrap(),
-            beta2: T::from(0.999).unwrap(),
-            epsilon: T::from(1e-8).unwrap(),
-            weight_decay: T::from(0.01).unwrap(), // Common default for AdamW
+            beta1: cast(0.9),
+            beta2: cast(0.999),
+            epsilon: cast(1e-8),
+            regularization: Regularization::decoupled(cast(0.01)), // Common default for AdamW
+            options: TrainingOptions::new(),
             error_function: Box::new(MseError),
             m_weights: Vec::new(),
             v_weights: Vec::new(),
             m_biases: Vec::new(),
             v_biases: Vec::new(),
             step: 0,
-            callback: None,
+            trust_ratio: None,
+            callbacks: EventDispatcher::new(),
+            statistics: TrainingStatistics::new(),
         }
     }
 
@@ -436,7 +528,13 @@ impl<T: Float + Send + Default> AdamW<T> {
 
     /// Set weight decay (decoupled from gradient-based updates)
     pub fn with_weight_decay(mut self, weight_decay: T) -> Self {
-        self.weight_decay = weight_decay;
+        self.regularization = Regularization::decoupled(weight_decay);
+        self
+    }
+
+    /// Set the weight decay configuration
+    pub fn with_regularization(mut self, regularization: Regularization<T>) -> Self {
+        self.regularization = regularization;
         self
     }
 
@@ -449,20 +547,7 @@ impl<T: Float + Send + Default> AdamW<T> {
     /// Initialize moment estimates for the network
     fn initialize_moments(&mut self, network: &Network<T>) {
         if self.m_weights.is_empty() {
-            self.m_weights = network
-                .layers
-                .iter()
-                .skip(1) // Skip input layer
-                .map(|layer| {
-                    let num_neurons = layer.neurons.len();
-                    let num_connections = if layer.neurons.is_empty() {
-                        0
-                    } else {
-                        layer.neurons[0].connections.len()
-                    };
-                    vec![T::zero(); num_neurons * num_connections]
-                })
-                .collect();
+            self.m_weights = super::helpers::weight_buffers(network, T::zero());
 
             self.v_weights = self.m_weights.clone();
 
@@ -485,21 +570,19 @@ impl<T: Float + Send + Default> AdamW<T> {
         bias_gradients: &[Vec<T>],
         lr_t: T,
     ) {
-        // Compute and apply weight updates with decoupled weight decay
+        // Weight decay was already applied by `Regularization::decay_weights`
         let mut weight_updates = Vec::new();
         for layer_idx in 0..weight_gradients.len() {
             let mut layer_updates = Vec::new();
             for i in 0..weight_gradients[layer_idx].len() {
                 let adaptive_update = lr_t * self.m_weights[layer_idx][i]
                     / (self.v_weights[layer_idx][i].sqrt() + self.epsilon);
-
-                // In AdamW, weight decay is applied directly to weights, not gradients
                 layer_updates.push(-adaptive_update);
             }
             weight_updates.push(layer_updates);
         }
 
-        // Compute and apply bias updates (no weight decay for biases)
+        // Compute and apply bias updates
         let mut bias_updates = Vec::new();
         for layer_idx in 0..bias_gradients.len() {
             let mut layer_updates = Vec::new();
@@ -512,34 +595,29 @@ impl<T: Float + Send + Default> AdamW<T> {
         }
 
         // Apply updates using existing helper
-        super::helpers::apply_updates_to_network(network, &weight_updates, &bias_updates);
-
-        // Apply decoupled weight decay directly to weights
-        if self.weight_decay > T::zero() {
-            self.apply_decoupled_weight_decay(network);
+        if let Some(trust_ratio) = self.trust_ratio.as_mut() {
+            trust_ratio.scale(
+                network,
+                self.learning_rate,
+                &mut weight_updates,
+                &mut bias_updates,
+            );
         }
+        super::helpers::apply_updates_to_network(network, &weight_updates, &bias_updates);
     }
+}
 
-    /// Apply decoupled weight decay directly to weights (AdamW approach)
-    fn apply_decoupled_weight_decay(&self, network: &mut Network<T>) {
-        let decay_factor = T::one() - self.learning_rate * self.weight_decay;
-
-        for layer_idx in 1..network.layers.len() {
-            let current_layer = &mut network.layers[layer_idx];
+impl<T: Float + Send + Default> TrustRatioUpdates<T> for AdamW<T> {
+    fn trust_ratio(&self) -> Option<&TrustRatio<T>> {
+        self.trust_ratio.as_ref()
+    }
 
-            for neuron in &mut current_layer.neurons {
-                if !neuron.is_bias {
-                    // Apply weight decay to all connections except bias (index 0)
-                    for connection in neuron.connections.iter_mut().skip(1) {
-                        connection.weight = connection.weight * decay_factor;
-                    }
-                }
-            }
-        }
+    fn set_trust_ratio(&mut self, trust_ratio: Option<TrustRatio<T>>) {
+        self.trust_ratio = trust_ratio;
     }
 }
 
-impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
+impl<T: Float + Send + Sync + Default> TrainingAlgorithm<T> for AdamW<T> {
     fn train_epoch(
         &mut self,
         network: &mut Network<T>,
@@ -547,6 +625,19 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
     ) -> Result<T, TrainingError> {
         use super::helpers::*;
 
+        if let Some(mut batches) = self.options.mini_batches.take() {
+            let error = batches.train_epoch(self, network, data);
+            self.options.mini_batches = Some(batches);
+            return error;
+        }
+        data.check_shapes(network)?;
+        let trimmed = self
+            .options
+            .trimmed_loss
+            .trim(network, data, self.error_function.as_ref());
+        let data = trimmed.as_ref();
+        let epoch_start = Instant::now();
+
         self.initialize_moments(network);
         self.step += 1;
 
@@ -568,9 +659,11 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
             .collect::<Vec<_>>();
 
         // Process all samples in the batch
+        let mut rng = rand::thread_rng();
         for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
             // Forward propagation to get all layer activations
-            let activations = forward_propagate(&simple_network, input);
+            let masks = dropout_masks(&simple_network, &mut rng);
+            let activations = forward_propagate_masked(&simple_network, input, &masks);
 
             // Get output from last layer
             let output = &activations[activations.len() - 1];
@@ -579,9 +672,10 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
             total_error = total_error + self.error_function.calculate(output, desired_output);
 
             // Calculate gradients using backpropagation
-            let (weight_gradients, bias_gradients) = calculate_gradients(
+            let (weight_gradients, bias_gradients) = calculate_gradients_masked(
                 &simple_network,
                 &activations,
+                &masks,
                 desired_output,
                 self.error_function.as_ref(),
             );
@@ -600,7 +694,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
         }
 
         // Average gradients over batch size
-        let batch_size = T::from(data.inputs.len()).unwrap();
+        let batch_size = cast(data.inputs.len());
         for layer_idx in 0..accumulated_weight_gradients.len() {
             for i in 0..accumulated_weight_gradients[layer_idx].len() {
                 accumulated_weight_gradients[layer_idx][i] =
@@ -612,6 +706,18 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
             }
         }
 
+        if self.options.gradient_centralization {
+            centralize_gradients(network, &mut accumulated_weight_gradients);
+        }
+        // Weight decay is applied before the Adam step
+        self.regularization.add_to_gradients(
+            network,
+            &mut accumulated_weight_gradients,
+            &mut accumulated_bias_gradients,
+        );
+        self.regularization
+            .decay_weights(network, self.learning_rate);
+
         // Update moment estimates
         for layer_idx in 0..accumulated_weight_gradients.len() {
             for i in 0..accumulated_weight_gradients[layer_idx].len() {
@@ -654,19 +760,29 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
             lr_t,
         );
 
+        self.statistics.record_epoch(
+            epoch_start.elapsed(),
+            data.inputs.len(),
+            gradient_norm(&accumulated_weight_gradients, &accumulated_bias_gradients),
+            buffer_bytes(&[
+                &simple_network.weights,
+                &simple_network.biases,
+                &accumulated_weight_gradients,
+                &accumulated_bias_gradients,
+                &self.m_weights,
+                &self.v_weights,
+                &self.m_biases,
+                &self.v_biases,
+            ]),
+        );
+        self.statistics
+            .record_gradients(accumulated_weight_gradients);
+
         Ok(total_error / batch_size)
     }
 
     fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
-        let mut total_error = T::zero();
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-            total_error = total_error + self.error_function.calculate(&output, desired_output);
-        }
-
-        total_error / T::from(data.inputs.len()).unwrap()
+        super::evaluation::batch_error(network, data, self.error_function.as_ref())
     }
 
     fn count_bit_fails(
@@ -675,19 +791,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
         data: &TrainingData<T>,
         bit_fail_limit: T,
     ) -> usize {
-        let mut bit_fails = 0;
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-            for (&actual, &desired) in output.iter().zip(desired_output.iter()) {
-                if (actual - desired).abs() > bit_fail_limit {
-                    bit_fails += 1;
-                }
-            }
-        }
-
-        bit_fails
+        super::evaluation::batch_bit_fails(network, data, bit_fail_limit)
     }
 
     fn save_state(&self) -> TrainingState<T> {
@@ -696,12 +800,12 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
         state.insert("beta1".to_string(), vec![self.beta1]);
         state.insert("beta2".to_string(), vec![self.beta2]);
         state.insert("epsilon".to_string(), vec![self.epsilon]);
-        state.insert("weight_decay".to_string(), vec![self.weight_decay]);
-        state.insert("step".to_string(), vec![T::from(self.step).unwrap()]);
+        self.regularization.save(&mut state);
+        state.insert("step".to_string(), vec![cast(self.step)]);
 
         TrainingState {
             epoch: 0,
-            best_error: T::from(f32::MAX).unwrap(),
+            best_error: cast(f32::MAX),
             algorithm_specific: state,
         }
     }
@@ -727,11 +831,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
                 self.epsilon = eps[0];
             }
         }
-        if let Some(wd) = state.algorithm_specific.get("weight_decay") {
-            if !wd.is_empty() {
-                self.weight_decay = wd[0];
-            }
-        }
+        self.regularization.restore(&state.algorithm_specific);
         if let Some(s) = state.algorithm_specific.get("step") {
             if !s.is_empty() {
                 self.step = s[0].to_usize().unwrap_or(0);
@@ -739,8 +839,32 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
         }
     }
 
+    fn learning_rate(&self) -> Option<T> {
+        Some(self.learning_rate)
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.learning_rate = rate;
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.statistics.last_gradient_norm()
+    }
+
+    fn last_weight_gradients(&self) -> Option<&[Vec<T>]> {
+        self.statistics.last_weight_gradients()
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.options.cancellation = token;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.options.is_cancelled()
+    }
+
     fn set_callback(&mut self, callback: TrainingCallback<T>) {
-        self.callback = Some(callback);
+        self.callbacks.subscribe(callback);
     }
 
     fn call_callback(
@@ -749,18 +873,142 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for AdamW<T> {
         network: &Network<T>,
         data: &TrainingData<T>,
     ) -> bool {
-        let error = self.calculate_error(network, data);
-        if let Some(ref mut callback) = self.callback {
-            callback(epoch, error)
-        } else {
-            true
+        if self.callbacks.is_empty() {
+            return true;
         }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
+    }
+}
+
+/// Mean error and mean weight and bias gradients over `data`
+///
+/// Gradients use the layered layout of [`helpers::SimpleNetwork`], which
+/// [`helpers::apply_updates_to_network`] expects for the updates.
+pub(super) fn batch_gradients<T: Float + Default>(
+    network: &Network<T>,
+    data: &TrainingData<T>,
+    error_function: &dyn ErrorFunction<T>,
+) -> (T, Vec<Vec<T>>, Vec<Vec<T>>) {
+    use super::helpers::*;
+
+    let simple_network = network_to_simple(network);
+    let mut weight_sums: Vec<Vec<T>> = simple_network
+        .weights
+        .iter()
+        .map(|w| vec![T::zero(); w.len()])
+        .collect();
+    let mut bias_sums: Vec<Vec<T>> = simple_network
+        .biases
+        .iter()
+        .map(|b| vec![T::zero(); b.len()])
+        .collect();
+    let mut total_error = T::zero();
+
+    let mut rng = rand::thread_rng();
+
+    for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
+        let masks = dropout_masks(&simple_network, &mut rng);
+        let activations = forward_propagate_masked(&simple_network, input, &masks);
+        let output = &activations[activations.len() - 1];
+        total_error = total_error + error_function.calculate(output, desired_output);
+
+        let (weight_gradients, bias_gradients) = calculate_gradients_masked(
+            &simple_network,
+            &activations,
+            &masks,
+            desired_output,
+            error_function,
+        );
+        for (sums, gradients) in weight_sums
+            .iter_mut()
+            .chain(bias_sums.iter_mut())
+            .zip(weight_gradients.iter().chain(bias_gradients.iter()))
+        {
+            for (sum, &gradient) in sums.iter_mut().zip(gradients) {
+                *sum = *sum + gradient;
+            }
+        }
+    }
+
+    let batch_size = cast(data.inputs.len().max(1));
+    for value in weight_sums.iter_mut().chain(bias_sums.iter_mut()).flatten() {
+        *value = *value / batch_size;
+    }
+    (total_error / batch_size, weight_sums, bias_sums)
+}
+
+/// Store layered optimizer buffers as `name.0`, `name.1`, ... in a saved state
+pub(super) fn save_buffers<T: Float>(
+    state: &mut HashMap<String, Vec<T>>,
+    name: &str,
+    buffers: &[Vec<T>],
+) {
+    for (i, buffer) in buffers.iter().enumerate() {
+        state.insert(format!("{name}.{i}"), buffer.clone());
+    }
+}
+
+/// Read back buffers written by [`save_buffers`]; empty if none were saved
+pub(super) fn restore_buffers<T: Float>(
+    state: &HashMap<String, Vec<T>>,
+    name: &str,
+) -> Vec<Vec<T>> {
+    (0..)
+        .map_while(|i| state.get(&format!("{name}.{i}")).cloned())
+        .collect()
+}
+
+impl<T: Float + Send + Default> WithTrainingOptions for Adam<T> {
+    fn training_options(&self) -> &TrainingOptions {
+        &self.options
+    }
+
+    fn training_options_mut(&mut self) -> &mut TrainingOptions {
+        &mut self.options
+    }
+}
+
+impl<T: Float + Send + Sync + Default> AdvancedTrainingAlgorithm<T> for Adam<T> {
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        &self.statistics
+    }
+
+    fn reset_statistics(&mut self) {
+        self.statistics.reset();
+    }
+}
+
+impl<T: Float + Send + Default> WithTrainingOptions for AdamW<T> {
+    fn training_options(&self) -> &TrainingOptions {
+        &self.options
+    }
+
+    fn training_options_mut(&mut self) -> &mut TrainingOptions {
+        &mut self.options
+    }
+}
+
+impl<T: Float + Send + Sync + Default> AdvancedTrainingAlgorithm<T> for AdamW<T> {
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        &self.statistics
+    }
+
+    fn reset_statistics(&mut self) {
+        self.statistics.reset();
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tests::fixtures::{xor, xor_network};
     use crate::Network;
 
     #[test]
@@ -778,7 +1026,7 @@ mod tests {
         assert_eq!(adamw.learning_rate, 0.001);
         assert_eq!(adamw.beta1, 0.9);
         assert_eq!(adamw.beta2, 0.999);
-        assert_eq!(adamw.weight_decay, 0.01);
+        assert_eq!(adamw.regularization, Regularization::decoupled(0.01));
         assert_eq!(adamw.step, 0);
     }
 
@@ -793,6 +1041,40 @@ mod tests {
         assert_eq!(adam.beta1, 0.95);
         assert_eq!(adam.beta2, 0.998);
         assert_eq!(adam.epsilon, 1e-7);
-        assert_eq!(adam.weight_decay, 0.001);
+        assert_eq!(adam.regularization, Regularization::coupled(0.001));
+    }
+
+    #[test]
+    fn test_amsgrad_keeps_maximum_second_moment() {
+        let (mut network, data) = (xor_network::<f64>(), xor());
+        let mut adam = Adam::new(0.05).with_amsgrad(true);
+        for _ in 0..5 {
+            adam.train_epoch(&mut network, &data).unwrap();
+            for (v_max, v) in adam
+                .v_max_weights
+                .iter()
+                .flatten()
+                .zip(adam.v_weights.iter().flatten())
+            {
+                assert!(v_max >= v);
+            }
+        }
+        assert!(adam.v_max_weights.iter().flatten().any(|&v| v > 0.0));
+    }
+
+    #[test]
+    fn test_adam_state_round_trip() {
+        let (mut network, data) = (xor_network::<f64>(), xor());
+        let mut adam = Adam::new(0.05).with_amsgrad(true);
+        adam.train_epoch(&mut network, &data).unwrap();
+        adam.train_epoch(&mut network, &data).unwrap();
+
+        let mut resumed = Adam::new(1.0);
+        resumed.restore_state(adam.save_state());
+        assert!(resumed.amsgrad);
+        let mut copy = network.clone();
+        adam.train_epoch(&mut network, &data).unwrap();
+        resumed.train_epoch(&mut copy, &data).unwrap();
+        assert_eq!(network.get_weights(), copy.get_weights());
     }
 }
@@ -0,0 +1,427 @@
+//! Adversarial training and robustness evaluation
+//!
+//! An [`AdversarialGenerator`] perturbs inputs against the current network
+//! with FGSM (one signed-gradient step of size `epsilon`) or PGD (several
+//! smaller steps, projected back into the `epsilon` L∞ ball around the
+//! clean input). Input gradients come from the same backward pass the
+//! optimizers use, [`helpers::calculate_gradients`](super::helpers::calculate_gradients).
+//!
+//! [`AdversarialTraining`] wraps any optimizer and appends an adversarial
+//! copy of every sample to each batch before the inner optimizer sees it.
+//! With its own [`BatchIterator`] the copies are regenerated against the
+//! weights of every mini-batch step. [`evaluate_robustness`] compares clean
+//! and attacked error and bit fails on held-out data.
+//!
+//! ```
+//! use do_fann::training::{
+//!     evaluate_robustness, AdversarialAttack, AdversarialGenerator, AdversarialTraining,
+//!     IncrementalBackprop, TrainingAlgorithm, TrainingData,
+//! };
+//! use do_fann::NetworkBuilder;
+//!
+//! let data = TrainingData {
+//!     inputs: vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]],
+//!     outputs: vec![vec![0.1], vec![0.9], vec![0.9], vec![0.1]],
+//! };
+//! let mut network = NetworkBuilder::<f64>::new()
+//!     .input_layer(2)
+//!     .hidden_layer(4)
+//!     .output_layer(1)
+//!     .build();
+//! let attack =
+//!     AdversarialGenerator::new(AdversarialAttack::pgd(0.1, 0.03, 5)).with_bounds(0.0, 1.0);
+//! let mut training = AdversarialTraining::new(IncrementalBackprop::new(0.5), attack.clone());
+//! training.train_epoch(&mut network, &data)?;
+//! let report = evaluate_robustness(&network, &data, &attack, 0.35)?;
+//! assert!(report.adversarial_error >= report.clean_error);
+//! # Ok::<(), do_fann::TrainingError>(())
+//! ```
+
+use super::evaluation::{batch_bit_fails, batch_error};
+use super::helpers::{
+    calculate_gradients, forward_propagate, input_errors, network_to_simple, SimpleNetwork,
+};
+use super::{
+    BatchIterator, CancellationToken, ErrorFunction, MseError, TrainingAlgorithm, TrainingCallback,
+    TrainingData, TrainingError, TrainingState,
+};
+use crate::Network;
+use num_traits::Float;
+use std::sync::Arc;
+
+/// How adversarial inputs are searched for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdversarialAttack<T: Float> {
+    /// Fast gradient sign method: `x + epsilon · sign(∇x loss)`
+    Fgsm { epsilon: T },
+    /// Projected gradient descent: `steps` signed steps of `step_size`, each
+    /// projected back into `[x - epsilon, x + epsilon]`
+    Pgd {
+        epsilon: T,
+        step_size: T,
+        steps: usize,
+    },
+}
+
+impl<T: Float> AdversarialAttack<T> {
+    pub fn fgsm(epsilon: T) -> Self {
+        Self::Fgsm { epsilon }
+    }
+
+    pub fn pgd(epsilon: T, step_size: T, steps: usize) -> Self {
+        Self::Pgd {
+            epsilon,
+            step_size,
+            steps,
+        }
+    }
+
+    /// Largest per-feature perturbation
+    pub fn epsilon(&self) -> T {
+        match *self {
+            Self::Fgsm { epsilon } | Self::Pgd { epsilon, .. } => epsilon,
+        }
+    }
+}
+
+/// Generates adversarial inputs against a network
+#[derive(Clone)]
+pub struct AdversarialGenerator<T: Float> {
+    attack: AdversarialAttack<T>,
+    error_function: Arc<dyn ErrorFunction<T>>,
+    bounds: Option<(T, T)>,
+}
+
+impl<T: Float> AdversarialGenerator<T> {
+    /// Attack the mean squared error
+    pub fn new(attack: AdversarialAttack<T>) -> Self {
+        Self {
+            attack,
+            error_function: Arc::new(MseError),
+            bounds: None,
+        }
+    }
+
+    /// Loss whose gradient drives the attack
+    pub fn with_error_function(mut self, error_function: Box<dyn ErrorFunction<T>>) -> Self {
+        self.error_function = Arc::from(error_function);
+        self
+    }
+
+    /// Clamp perturbed inputs to the valid input range `[min, max]`
+    pub fn with_bounds(mut self, min: T, max: T) -> Self {
+        self.bounds = Some((min.min(max), max.max(min)));
+        self
+    }
+
+    pub fn attack(&self) -> AdversarialAttack<T> {
+        self.attack
+    }
+
+    /// Adversarial version of one sample
+    pub fn perturb(&self, network: &Network<T>, input: &[T], target: &[T]) -> Vec<T> {
+        self.perturb_simple(&network_to_simple(network), input, target)
+    }
+
+    fn perturb_simple(&self, network: &SimpleNetwork<T>, input: &[T], target: &[T]) -> Vec<T> {
+        let (epsilon, step_size, steps) = match self.attack {
+            AdversarialAttack::Fgsm { epsilon } => (epsilon, epsilon, 1),
+            AdversarialAttack::Pgd {
+                epsilon,
+                step_size,
+                steps,
+            } => (epsilon, step_size, steps),
+        };
+        let mut adversarial = input.to_vec();
+        for _ in 0..steps {
+            let gradient = input_gradient(network, &adversarial, target, &*self.error_function);
+            for ((x, &clean), g) in adversarial.iter_mut().zip(input).zip(gradient) {
+                let step = if g > T::zero() {
+                    step_size
+                } else if g < T::zero() {
+                    -step_size
+                } else {
+                    T::zero()
+                };
+                *x = (*x + step).max(clean - epsilon).min(clean + epsilon);
+                if let Some((min, max)) = self.bounds {
+                    *x = x.max(min).min(max);
+                }
+            }
+        }
+        adversarial
+    }
+
+    /// Adversarial copy of every sample of `data`, with the original targets
+    pub fn perturb_data(&self, network: &Network<T>, data: &TrainingData<T>) -> TrainingData<T> {
+        let network = network_to_simple(network);
+        TrainingData {
+            inputs: data
+                .inputs
+                .iter()
+                .zip(&data.outputs)
+                .map(|(input, target)| self.perturb_simple(&network, input, target))
+                .collect(),
+            outputs: data.outputs.clone(),
+        }
+    }
+}
+
+/// Gradient of the loss of one sample with respect to the network inputs
+fn input_gradient<T: Float>(
+    network: &SimpleNetwork<T>,
+    input: &[T],
+    target: &[T],
+    error_function: &dyn ErrorFunction<T>,
+) -> Vec<T> {
+    let activations = forward_propagate(network, input);
+    let (_, bias_gradients) = calculate_gradients(network, &activations, target, error_function);
+    input_errors(network, &bias_gradients)
+}
+
+/// Trains an inner optimizer on clean plus adversarial samples
+pub struct AdversarialTraining<T: Float, A> {
+    inner: A,
+    generator: AdversarialGenerator<T>,
+    mini_batches: Option<BatchIterator>,
+}
+
+impl<T: Float, A> AdversarialTraining<T, A> {
+    pub fn new(inner: A, generator: AdversarialGenerator<T>) -> Self {
+        Self {
+            inner,
+            generator,
+            mini_batches: None,
+        }
+    }
+
+    /// Regenerate the adversarial copies for every mini-batch drawn by
+    /// `batches`; configure mini-batches here rather than on the inner
+    /// optimizer
+    pub fn with_mini_batches(mut self, batches: BatchIterator) -> Self {
+        self.mini_batches = Some(batches);
+        self
+    }
+
+    pub fn generator(&self) -> &AdversarialGenerator<T> {
+        &self.generator
+    }
+
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+}
+
+impl<T: Float + Send + Sync, A: TrainingAlgorithm<T>> TrainingAlgorithm<T>
+    for AdversarialTraining<T, A>
+{
+    /// Returns the inner optimizer's error on the augmented batch
+    fn train_epoch(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<T, TrainingError> {
+        if let Some(mut batches) = self.mini_batches.take() {
+            let error = batches.train_epoch(self, network, data);
+            self.mini_batches = Some(batches);
+            return error;
+        }
+        data.check_shapes(network)?;
+        let adversarial = self.generator.perturb_data(network, data);
+        let augmented = TrainingData {
+            inputs: [data.inputs.as_slice(), &adversarial.inputs].concat(),
+            outputs: [data.outputs.as_slice(), &adversarial.outputs].concat(),
+        };
+        self.inner.train_epoch(network, &augmented)
+    }
+
+    fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
+        self.inner.calculate_error(network, data)
+    }
+
+    fn count_bit_fails(
+        &self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+        bit_fail_limit: T,
+    ) -> usize {
+        self.inner.count_bit_fails(network, data, bit_fail_limit)
+    }
+
+    fn save_state(&self) -> TrainingState<T> {
+        self.inner.save_state()
+    }
+
+    fn restore_state(&mut self, state: TrainingState<T>) {
+        self.inner.restore_state(state);
+    }
+
+    fn learning_rate(&self) -> Option<T> {
+        self.inner.learning_rate()
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.inner.set_learning_rate(rate);
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.inner.set_cancellation(token);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.inner.last_gradient_norm()
+    }
+
+    fn last_weight_gradients(&self) -> Option<&[Vec<T>]> {
+        self.inner.last_weight_gradients()
+    }
+
+    fn set_callback(&mut self, callback: TrainingCallback<T>) {
+        self.inner.set_callback(callback);
+    }
+
+    fn call_callback(
+        &mut self,
+        epoch: usize,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> bool {
+        self.inner.call_callback(epoch, network, data)
+    }
+}
+
+/// Clean versus attacked performance of a network
+#[derive(Debug, Clone, PartialEq)]
+pub struct RobustnessReport<T: Float> {
+    pub samples: usize,
+    pub attack: AdversarialAttack<T>,
+    pub clean_error: T,
+    pub adversarial_error: T,
+    pub clean_bit_fails: usize,
+    pub adversarial_bit_fails: usize,
+    /// Mean L∞ distance between clean and adversarial inputs
+    pub mean_perturbation: T,
+}
+
+impl<T: Float> RobustnessReport<T> {
+    /// Samples that only fail under attack, as a fraction of all samples
+    pub fn attack_success_rate(&self) -> f64 {
+        let flipped = self
+            .adversarial_bit_fails
+            .saturating_sub(self.clean_bit_fails);
+        flipped as f64 / self.samples.max(1) as f64
+    }
+}
+
+/// Attack every sample of `data` and compare error and bit fails (outputs
+/// off by more than `bit_fail_limit`) before and after
+pub fn evaluate_robustness<T: Float + Send + Sync>(
+    network: &Network<T>,
+    data: &TrainingData<T>,
+    generator: &AdversarialGenerator<T>,
+    bit_fail_limit: T,
+) -> Result<RobustnessReport<T>, TrainingError> {
+    data.check_shapes(network)?;
+    if data.inputs.is_empty() {
+        return Err(TrainingError::InvalidData(
+            "no samples provided".to_string(),
+        ));
+    }
+    let adversarial = generator.perturb_data(network, data);
+    let error_function = &*generator.error_function;
+    let total_perturbation =
+        data.inputs
+            .iter()
+            .zip(&adversarial.inputs)
+            .fold(T::zero(), |total, (clean, attacked)| {
+                total
+                    + clean
+                        .iter()
+                        .zip(attacked)
+                        .fold(T::zero(), |max, (&a, &b)| max.max((a - b).abs()))
+            });
+    Ok(RobustnessReport {
+        samples: data.inputs.len(),
+        attack: generator.attack,
+        clean_error: batch_error(network, data, error_function),
+        adversarial_error: batch_error(network, &adversarial, error_function),
+        clean_bit_fails: batch_bit_fails(network, data, bit_fail_limit),
+        adversarial_bit_fails: batch_bit_fails(network, &adversarial, bit_fail_limit),
+        mean_perturbation: total_perturbation / crate::numeric::cast(data.inputs.len()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::xor_network;
+    use crate::training::IncrementalBackprop;
+
+    #[test]
+    fn test_input_gradient_and_attacks() {
+        let mut network = xor_network::<f64>();
+        let (input, target) = ([0.3, -0.6], [0.9]);
+        let gradient = input_gradient(&network_to_simple(&network), &input, &target, &MseError);
+        let loss =
+            |network: &mut Network<f64>, x: &[f64]| MseError.calculate(&network.run(x), &target);
+        for i in 0..2 {
+            let (mut up, mut down) = (input, input);
+            up[i] += 1e-6;
+            down[i] -= 1e-6;
+            let numeric = (loss(&mut network, &up) - loss(&mut network, &down)) / 2e-6;
+            assert!((numeric - gradient[i]).abs() < 1e-6);
+        }
+
+        let clean = loss(&mut network, &input);
+        let fgsm = AdversarialGenerator::new(AdversarialAttack::fgsm(0.1));
+        let attacked = fgsm.perturb(&network, &input, &target);
+        assert!(loss(&mut network, &attacked) > clean);
+
+        let pgd =
+            AdversarialGenerator::new(AdversarialAttack::pgd(0.1, 0.04, 5)).with_bounds(-0.65, 1.0);
+        let attacked = pgd.perturb(&network, &input, &target);
+        assert!(attacked
+            .iter()
+            .zip(input)
+            .all(|(a, c)| (a - c).abs() <= 0.1 + 1e-12));
+        assert!(attacked[1] >= -0.65);
+        assert!(loss(&mut network, &attacked) > clean);
+    }
+
+    #[test]
+    fn test_adversarial_training_and_report() {
+        let data = TrainingData {
+            inputs: vec![vec![0.1, 0.2], vec![0.8, 0.4], vec![0.5, 0.9]],
+            outputs: vec![vec![0.2], vec![0.7], vec![0.4]],
+        };
+        let mut network = xor_network::<f64>();
+        let generator = AdversarialGenerator::new(AdversarialAttack::fgsm(0.05));
+        let report = evaluate_robustness(&network, &data, &generator, 0.1).unwrap();
+        assert_eq!(report.samples, 3);
+        assert!(report.adversarial_error > report.clean_error);
+        assert!((report.mean_perturbation - 0.05).abs() < 1e-12);
+        assert!(report.adversarial_bit_fails >= report.clean_bit_fails);
+
+        let mut training = AdversarialTraining::new(IncrementalBackprop::new(0.3), generator)
+            .with_mini_batches(BatchIterator::new(2));
+        for _ in 0..5 {
+            assert!(training
+                .train_epoch(&mut network, &data)
+                .unwrap()
+                .is_finite());
+        }
+        let bad = TrainingData {
+            inputs: vec![vec![0.1]],
+            outputs: vec![vec![0.2]],
+        };
+        assert!(training.train_epoch(&mut network, &bad).is_err());
+    }
+}
@@ -1,18 +1,25 @@
 //! Backpropagation training algorithms
 
+use super::centralization::centralize_gradients;
+use super::statistics::{buffer_bytes, gradient_norm};
 use super::*;
+use crate::numeric::cast;
 use num_traits::Float;
 use std::collections::HashMap;
+use std::time::Instant;
 
 /// Incremental (online) backpropagation
 /// Updates weights after each training pattern
 pub struct IncrementalBackprop<T: Float + Send + Default> {
     learning_rate: T,
     momentum: T,
+    regularization: Regularization<T>,
+    gradient_centralization: bool,
     error_function: Box<dyn ErrorFunction<T>>,
     previous_weight_deltas: Vec<Vec<T>>,
     previous_bias_deltas: Vec<Vec<T>>,
-    callback: Option<TrainingCallback<T>>,
+    callbacks: EventDispatcher<T>,
+    statistics: TrainingStatistics<T>,
 }
 
 impl<T: Float + Send + Default> IncrementalBackprop<T> {
@@ -20,10 +27,13 @@ impl<T: Float + Send + Default> IncrementalBackprop<T> {
         Self {
             learning_rate,
             momentum: T::zero(),
+            regularization: Regularization::none(),
+            gradient_centralization: false,
             error_function: Box::new(MseError),
             previous_weight_deltas: Vec::new(),
             previous_bias_deltas: Vec::new(),
-            callback: None,
+            callbacks: EventDispatcher::new(),
+            statistics: TrainingStatistics::new(),
         }
     }
 
@@ -32,27 +42,28 @@ impl<T: Float + Send + Default> IncrementalBackprop<T> {
         self
     }
 
+    /// Set the weight decay configuration; for plain gradient descent the
+    /// coupled and decoupled modes take the same step
+    pub fn with_regularization(mut self, regularization: Regularization<T>) -> Self {
+        self.regularization = regularization;
+        self
+    }
+
     pub fn with_error_function(mut self, error_function: Box<dyn ErrorFunction<T>>) -> Self {
         self.error_function = error_function;
         self
     }
 
+    /// Subtract the mean of each neuron's incoming weight gradients before
+    /// the update (gradient centralization)
+    pub fn with_gradient_centralization(mut self, enabled: bool) -> Self {
+        self.gradient_centralization = enabled;
+        self
+    }
+
     fn initialize_deltas(&mut self, network: &Network<T>) {
         if self.previous_weight_deltas.is_empty() {
-            self.previous_weight_deltas = network
-                .layers
-                .iter()
-                .skip(1) // Skip input layer
-                .map(|layer| {
-                    let num_neurons = layer.neurons.len();
-                    let num_connections = if layer.neurons.is_empty() {
-                        0
-                    } else {
-                        layer.neurons[0].connections.len()
-                    };
-                    vec![T::zero(); num_neurons * num_connections]
-                })
-                .collect();
+            self.previous_weight_deltas = super::helpers::weight_buffers(network, T::zero());
             self.previous_bias_deltas = network
                 .layers
                 .iter()
@@ -63,7 +74,7 @@ impl<T: Float + Send + Default> IncrementalBackprop<T> {
     }
 }
 
-impl<T: Float + Send + Default> TrainingAlgorithm<T> for IncrementalBackprop<T> {
+impl<T: Float + Send + Sync + Default> TrainingAlgorithm<T> for IncrementalBackprop<T> {
     fn train_epoch(
         &mut self,
         network: &mut Network<T>,
@@ -71,16 +82,23 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for IncrementalBackprop<T>
     ) -> Result<T, TrainingError> {
         use super::helpers::*;
 
+        data.check_shapes(network)?;
+        let epoch_start = Instant::now();
+
         self.initialize_deltas(network);
 
         let mut total_error = T::zero();
 
         // Convert network to simplified form for easier manipulation
         let simple_network = network_to_simple(network);
+        let mut gradient_norm_sum = T::zero();
+
+        let mut rng = rand::thread_rng();
 
         for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
             // Forward propagation to get all layer activations
-            let activations = forward_propagate(&simple_network, input);
+            let masks = dropout_masks(&simple_network, &mut rng);
+            let activations = forward_propagate_masked(&simple_network, input, &masks);
 
             // Get output from last layer
             let output = &activations[activations.len() - 1];
@@ -89,26 +107,39 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for IncrementalBackprop<T>
             total_error = total_error + self.error_function.calculate(output, desired_output);
 
             // Calculate gradients using backpropagation
-            let (weight_gradients, bias_gradients) = calculate_gradients(
+            let (mut weight_gradients, mut bias_gradients) = calculate_gradients_masked(
                 &simple_network,
                 &activations,
+                &masks,
                 desired_output,
                 self.error_function.as_ref(),
             );
+            if self.gradient_centralization {
+                centralize_gradients(network, &mut weight_gradients);
+            }
+            self.regularization.add_to_gradients(
+                network,
+                &mut weight_gradients,
+                &mut bias_gradients,
+            );
+            self.regularization
+                .decay_weights(network, self.learning_rate);
+            gradient_norm_sum =
+                gradient_norm_sum + gradient_norm(&weight_gradients, &bias_gradients);
 
             // Update weights and biases immediately (incremental/online learning)
             // Apply momentum
             for layer_idx in 0..weight_gradients.len() {
                 // Update weight deltas with momentum
                 for (i, &grad) in weight_gradients[layer_idx].iter().enumerate() {
-                    let delta = self.learning_rate * grad
+                    let delta = -self.learning_rate * grad
                         + self.momentum * self.previous_weight_deltas[layer_idx][i];
                     self.previous_weight_deltas[layer_idx][i] = delta;
                 }
 
                 // Update bias deltas with momentum
                 for (i, &grad) in bias_gradients[layer_idx].iter().enumerate() {
-                    let delta = self.learning_rate * grad
+                    let delta = -self.learning_rate * grad
                         + self.momentum * self.previous_bias_deltas[layer_idx][i];
                     self.previous_bias_deltas[layer_idx][i] = delta;
                 }
@@ -122,19 +153,21 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for IncrementalBackprop<T>
             );
         }
 
-        Ok(total_error / T::from(data.inputs.len()).unwrap())
+        let samples = cast(data.inputs.len());
+        // Per-sample gradients are the same size as the simplified network
+        self.statistics.record_epoch(
+            epoch_start.elapsed(),
+            data.inputs.len(),
+            gradient_norm_sum / samples,
+            2 * buffer_bytes(&[&simple_network.weights, &simple_network.biases])
+                + buffer_bytes(&[&self.previous_weight_deltas, &self.previous_bias_deltas]),
+        );
+
+        Ok(total_error / samples)
     }
 
     fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
-        let mut total_error = T::zero();
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-            total_error = total_error + self.error_function.calculate(&output, desired_output);
-        }
-
-        total_error / T::from(data.inputs.len()).unwrap()
+        super::evaluation::batch_error(network, data, self.error_function.as_ref())
     }
 
     fn count_bit_fails(
@@ -143,29 +176,18 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for IncrementalBackprop<T>
         data: &TrainingData<T>,
         bit_fail_limit: T,
     ) -> usize {
-        let mut bit_fails = 0;
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-            for (&actual, &desired) in output.iter().zip(desired_output.iter()) {
-                if (actual - desired).abs() > bit_fail_limit {
-                    bit_fails += 1;
-                }
-            }
-        }
-
-        bit_fails
+        super::evaluation::batch_bit_fails(network, data, bit_fail_limit)
     }
 
     fn save_state(&self) -> TrainingState<T> {
         let mut state = HashMap::new();
         state.insert("learning_rate".to_string(), vec![self.learning_rate]);
         state.insert("momentum".to_string(), vec![self.momentum]);
+        self.regularization.save(&mut state);
 
         TrainingState {
             epoch: 0,
-            best_error: T::from(f32::MAX).unwrap(),
+            best_error: cast(f32::MAX),
             algorithm_specific: state,
         }
     }
@@ -181,10 +203,23 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for IncrementalBackprop<T>
                 self.momentum = mom[0];
             }
         }
+        self.regularization.restore(&state.algorithm_specific);
+    }
+
+    fn learning_rate(&self) -> Option<T> {
+        Some(self.learning_rate)
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.learning_rate = rate;
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.statistics.last_gradient_norm()
     }
 
     fn set_callback(&mut self, callback: TrainingCallback<T>) {
-        self.callback = Some(callback);
+        self.callbacks.subscribe(callback);
     }
 
     fn call_callback(
@@ -193,12 +228,17 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for IncrementalBackprop<T>
         network: &Network<T>,
         data: &TrainingData<T>,
     ) -> bool {
-        let error = self.calculate_error(network, data);
-        if let Some(ref mut callback) = self.callback {
-            callback(epoch, error)
-        } else {
-            true
+        if self.callbacks.is_empty() {
+            return true;
         }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
     }
 }
 
@@ -207,10 +247,18 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for IncrementalBackprop<T>
 pub struct BatchBackprop<T: Float + Send + Default> {
     learning_rate: T,
     momentum: T,
+    regularization: Regularization<T>,
+    options: TrainingOptions,
     error_function: Box<dyn ErrorFunction<T>>,
+    privacy: Option<DifferentialPrivacy<T>>,
     previous_weight_deltas: Vec<Vec<T>>,
     previous_bias_deltas: Vec<Vec<T>>,
-    callback: Option<TrainingCallback<T>>,
+    trust_ratio: Option<TrustRatio<T>>,
+    callbacks: EventDispatcher<T>,
+    statistics: TrainingStatistics<T>,
+    /// Scheduler for per-batch gradient jobs and samples per job
+    #[cfg(feature = "parallel")]
+    scheduler: Option<(WorkStealingScheduler, usize)>,
 }
 
 impl<T: Float + Send + Default> BatchBackprop<T> {
@@ -218,10 +266,17 @@ impl<T: Float + Send + Default> BatchBackprop<T> {
         Self {
             learning_rate,
             momentum: T::zero(),
+            regularization: Regularization::none(),
+            options: TrainingOptions::new(),
             error_function: Box::new(MseError),
+            privacy: None,
             previous_weight_deltas: Vec::new(),
             previous_bias_deltas: Vec::new(),
-            callback: None,
+            trust_ratio: None,
+            callbacks: EventDispatcher::new(),
+            statistics: TrainingStatistics::new(),
+            #[cfg(feature = "parallel")]
+            scheduler: None,
         }
     }
 
@@ -230,27 +285,48 @@ impl<T: Float + Send + Default> BatchBackprop<T> {
         self
     }
 
+    /// Set the weight decay configuration; for plain gradient descent the
+    /// coupled and decoupled modes take the same step
+    pub fn with_regularization(mut self, regularization: Regularization<T>) -> Self {
+        self.regularization = regularization;
+        self
+    }
+
     pub fn with_error_function(mut self, error_function: Box<dyn ErrorFunction<T>>) -> Self {
         self.error_function = error_function;
         self
     }
 
+    /// Train on clipped, noised per-sample gradients (DP-SGD)
+    pub fn with_differential_privacy(mut self, privacy: DifferentialPrivacy<T>) -> Self {
+        self.privacy = Some(privacy);
+        self
+    }
+
+    /// Privacy configuration and the budget spent so far, if enabled
+    pub fn privacy(&self) -> Option<&DifferentialPrivacy<T>> {
+        self.privacy.as_ref()
+    }
+
+    /// Compute gradients in jobs of `job_size` samples on `scheduler`
+    ///
+    /// The job sums are added in sample order, so the result matches the
+    /// sequential epoch up to floating-point rounding.
+    #[cfg(feature = "parallel")]
+    pub fn with_scheduler(mut self, scheduler: WorkStealingScheduler, job_size: usize) -> Self {
+        self.scheduler = Some((scheduler, job_size.max(1)));
+        self
+    }
+
+    /// Scheduler set by [`Self::with_scheduler`], for its statistics
+    #[cfg(feature = "parallel")]
+    pub fn scheduler(&self) -> Option<&WorkStealingScheduler> {
+        self.scheduler.as_ref().map(|(scheduler, _)| scheduler)
+    }
+
     fn initialize_deltas(&mut self, network: &Network<T>) {
         if self.previous_weight_deltas.is_empty() {
-            self.previous_weight_deltas = network
-                .layers
-                .iter()
-                .skip(1) // Skip input layer
-                .map(|layer| {
-                    let num_neurons = layer.neurons.len();
-                    let num_connections = if layer.neurons.is_empty() {
-                        0
-                    } else {
-                        layer.neurons[0].connections.len()
-                    };
-                    vec![T::zero(); num_neurons * num_connections]
-                })
-                .collect();
+            self.previous_weight_deltas = super::helpers::weight_buffers(network, T::zero());
             self.previous_bias_deltas = network
                 .layers
                 .iter()
@@ -261,67 +337,123 @@ impl<T: Float + Send + Default> BatchBackprop<T> {
     }
 }
 
-impl<T: Float + Send + Default> TrainingAlgorithm<T> for BatchBackprop<T> {
-    fn train_epoch(
-        &mut self,
-        network: &mut Network<T>,
-        data: &TrainingData<T>,
-    ) -> Result<T, TrainingError> {
-        use super::helpers::*;
-
-        self.initialize_deltas(network);
-
-        let mut total_error = T::zero();
-
-        // Convert network to simplified form for easier manipulation
-        let simple_network = network_to_simple(network);
-
-        // Initialize gradient accumulators
-        let mut accumulated_weight_gradients = simple_network
-            .weights
-            .iter()
-            .map(|w| vec![T::zero(); w.len()])
-            .collect::<Vec<_>>();
-        let mut accumulated_bias_gradients = simple_network
-            .biases
-            .iter()
-            .map(|b| vec![T::zero(); b.len()])
-            .collect::<Vec<_>>();
-
-        // Accumulate gradients over all patterns
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            // Forward propagation to get all layer activations
-            let activations = forward_propagate(&simple_network, input);
+/// Error and gradient sums accumulated over a set of samples
+type Accumulated<T> = (T, Vec<Vec<T>>, Vec<Vec<T>>);
+
+fn accumulate_gradients<T: Float>(
+    simple_network: &super::helpers::SimpleNetwork<T>,
+    inputs: &[Vec<T>],
+    outputs: &[Vec<T>],
+    error_function: &dyn ErrorFunction<T>,
+) -> Accumulated<T> {
+    use super::helpers::*;
+
+    let mut total_error = T::zero();
+    let mut accumulated_weight_gradients = simple_network
+        .weights
+        .iter()
+        .map(|w| vec![T::zero(); w.len()])
+        .collect::<Vec<_>>();
+    let mut accumulated_bias_gradients = simple_network
+        .biases
+        .iter()
+        .map(|b| vec![T::zero(); b.len()])
+        .collect::<Vec<_>>();
+
+    let mut rng = rand::thread_rng();
+
+    for (input, desired_output) in inputs.iter().zip(outputs) {
+        // Forward propagation to get all layer activations
+        let masks = dropout_masks(simple_network, &mut rng);
+        let activations = forward_propagate_masked(simple_network, input, &masks);
+        let output = &activations[activations.len() - 1];
+        total_error = total_error + error_function.calculate(output, desired_output);
+
+        let (weight_gradients, bias_gradients) = calculate_gradients_masked(
+            simple_network,
+            &activations,
+            &masks,
+            desired_output,
+            error_function,
+        );
+        add_gradients(&mut accumulated_weight_gradients, &weight_gradients);
+        add_gradients(&mut accumulated_bias_gradients, &bias_gradients);
+    }
+    (
+        total_error,
+        accumulated_weight_gradients,
+        accumulated_bias_gradients,
+    )
+}
 
-            // Get output from last layer
-            let output = &activations[activations.len() - 1];
+fn add_gradients<T: Float>(sum: &mut [Vec<T>], gradients: &[Vec<T>]) {
+    for (sum, gradients) in sum.iter_mut().zip(gradients) {
+        for (s, &g) in sum.iter_mut().zip(gradients) {
+            *s = *s + g;
+        }
+    }
+}
 
-            // Calculate error
-            total_error = total_error + self.error_function.calculate(output, desired_output);
+#[cfg(feature = "parallel")]
+fn add_accumulated<T: Float>(mut sum: Accumulated<T>, other: Accumulated<T>) -> Accumulated<T> {
+    sum.0 = sum.0 + other.0;
+    add_gradients(&mut sum.1, &other.1);
+    add_gradients(&mut sum.2, &other.2);
+    sum
+}
 
-            // Calculate gradients using backpropagation
-            let (weight_gradients, bias_gradients) = calculate_gradients(
-                &simple_network,
-                &activations,
-                desired_output,
-                self.error_function.as_ref(),
-            );
+impl<T: Float + Send + Sync + Default> BatchBackprop<T> {
+    /// Mean error and gradients of `data`, privatized when configured
+    fn mean_gradients(
+        &mut self,
+        network: &Network<T>,
+        simple_network: &super::helpers::SimpleNetwork<T>,
+        data: &TrainingData<T>,
+    ) -> Accumulated<T> {
+        if let Some(privacy) = self.privacy.as_mut() {
+            return privacy.gradients(network, data, self.error_function.as_ref());
+        }
 
-            // Accumulate gradients
-            for layer_idx in 0..weight_gradients.len() {
-                for i in 0..weight_gradients[layer_idx].len() {
-                    accumulated_weight_gradients[layer_idx][i] =
-                        accumulated_weight_gradients[layer_idx][i] + weight_gradients[layer_idx][i];
-                }
-                for i in 0..bias_gradients[layer_idx].len() {
-                    accumulated_bias_gradients[layer_idx][i] =
-                        accumulated_bias_gradients[layer_idx][i] + bias_gradients[layer_idx][i];
-                }
+        // Accumulate gradients over all patterns, in per-batch jobs when a
+        // scheduler is configured
+        #[cfg(feature = "parallel")]
+        let accumulated = match &self.scheduler {
+            Some((scheduler, job_size)) => {
+                let jobs = data
+                    .inputs
+                    .chunks(*job_size)
+                    .zip(data.outputs.chunks(*job_size))
+                    .collect();
+                let error_function = self.error_function.as_ref();
+                scheduler
+                    .run(jobs, |_, (inputs, outputs)| {
+                        accumulate_gradients(simple_network, inputs, outputs, error_function)
+                    })
+                    .into_iter()
+                    .reduce(add_accumulated)
             }
+            None => None,
         }
+        .unwrap_or_else(|| {
+            accumulate_gradients(
+                simple_network,
+                &data.inputs,
+                &data.outputs,
+                self.error_function.as_ref(),
+            )
+        });
+        #[cfg(not(feature = "parallel"))]
+        let accumulated = accumulate_gradients(
+            simple_network,
+            &data.inputs,
+            &data.outputs,
+            self.error_function.as_ref(),
+        );
+        let (total_error, mut accumulated_weight_gradients, mut accumulated_bias_gradients) =
+            accumulated;
 
         // Average gradients by batch size
-        let batch_size = T::from(data.inputs.len()).unwrap();
+        let batch_size = cast(data.inputs.len());
         for layer_idx in 0..accumulated_weight_gradients.len() {
             for i in 0..accumulated_weight_gradients[layer_idx].len() {
                 accumulated_weight_gradients[layer_idx][i] =
@@ -332,6 +464,69 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for BatchBackprop<T> {
                     accumulated_bias_gradients[layer_idx][i] / batch_size;
             }
         }
+        (
+            total_error / batch_size,
+            accumulated_weight_gradients,
+            accumulated_bias_gradients,
+        )
+    }
+}
+
+impl<T: Float + Send + Default> TrustRatioUpdates<T> for BatchBackprop<T> {
+    fn trust_ratio(&self) -> Option<&TrustRatio<T>> {
+        self.trust_ratio.as_ref()
+    }
+
+    fn set_trust_ratio(&mut self, trust_ratio: Option<TrustRatio<T>>) {
+        self.trust_ratio = trust_ratio;
+    }
+}
+
+impl<T: Float + Send + Sync + Default> TrainingAlgorithm<T> for BatchBackprop<T> {
+    fn train_epoch(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<T, TrainingError> {
+        use super::helpers::*;
+
+        if let Some(mut batches) = self.options.mini_batches.take() {
+            if let Some(privacy) = self.privacy.as_mut() {
+                privacy.set_population(Some(data.inputs.len()));
+            }
+            let error = batches.train_epoch(self, network, data);
+            if let Some(privacy) = self.privacy.as_mut() {
+                privacy.set_population(None);
+            }
+            self.options.mini_batches = Some(batches);
+            return error;
+        }
+        data.check_shapes(network)?;
+        let trimmed = self
+            .options
+            .trimmed_loss
+            .trim(network, data, self.error_function.as_ref());
+        let data = trimmed.as_ref();
+        let epoch_start = Instant::now();
+
+        self.initialize_deltas(network);
+
+        // Convert network to simplified form for easier manipulation
+        let simple_network = network_to_simple(network);
+
+        let (error, mut accumulated_weight_gradients, mut accumulated_bias_gradients) =
+            self.mean_gradients(network, &simple_network, data);
+
+        if self.options.gradient_centralization {
+            centralize_gradients(network, &mut accumulated_weight_gradients);
+        }
+        self.regularization.add_to_gradients(
+            network,
+            &mut accumulated_weight_gradients,
+            &mut accumulated_bias_gradients,
+        );
+        self.regularization
+            .decay_weights(network, self.learning_rate);
 
         // Update weights and biases using accumulated gradients with momentum
         let mut weight_updates = Vec::new();
@@ -343,7 +538,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for BatchBackprop<T> {
 
             // Update weights with momentum
             for (i, &grad) in accumulated_weight_gradients[layer_idx].iter().enumerate() {
-                let delta = self.learning_rate * grad
+                let delta = -self.learning_rate * grad
                     + self.momentum * self.previous_weight_deltas[layer_idx][i];
                 self.previous_weight_deltas[layer_idx][i] = delta;
                 layer_weight_updates.push(delta);
@@ -351,7 +546,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for BatchBackprop<T> {
 
             // Update biases with momentum
             for (i, &grad) in accumulated_bias_gradients[layer_idx].iter().enumerate() {
-                let delta = self.learning_rate * grad
+                let delta = -self.learning_rate * grad
                     + self.momentum * self.previous_bias_deltas[layer_idx][i];
                 self.previous_bias_deltas[layer_idx][i] = delta;
                 layer_bias_updates.push(delta);
@@ -362,21 +557,37 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for BatchBackprop<T> {
         }
 
         // Apply the updates to the actual network
+        if let Some(trust_ratio) = self.trust_ratio.as_mut() {
+            trust_ratio.scale(
+                network,
+                self.learning_rate,
+                &mut weight_updates,
+                &mut bias_updates,
+            );
+        }
         apply_updates_to_network(network, &weight_updates, &bias_updates);
 
-        Ok(total_error / batch_size)
+        self.statistics.record_epoch(
+            epoch_start.elapsed(),
+            data.inputs.len(),
+            gradient_norm(&accumulated_weight_gradients, &accumulated_bias_gradients),
+            buffer_bytes(&[
+                &simple_network.weights,
+                &simple_network.biases,
+                &accumulated_weight_gradients,
+                &accumulated_bias_gradients,
+                &self.previous_weight_deltas,
+                &self.previous_bias_deltas,
+            ]),
+        );
+        self.statistics
+            .record_gradients(accumulated_weight_gradients);
+
+        Ok(error)
     }
 
     fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
-        let mut total_error = T::zero();
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-            total_error = total_error + self.error_function.calculate(&output, desired_output);
-        }
-
-        total_error / T::from(data.inputs.len()).unwrap()
+        super::evaluation::batch_error(network, data, self.error_function.as_ref())
     }
 
     fn count_bit_fails(
@@ -385,29 +596,18 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for BatchBackprop<T> {
         data: &TrainingData<T>,
         bit_fail_limit: T,
     ) -> usize {
-        let mut bit_fails = 0;
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-            for (&actual, &desired) in output.iter().zip(desired_output.iter()) {
-                if (actual - desired).abs() > bit_fail_limit {
-                    bit_fails += 1;
-                }
-            }
-        }
-
-        bit_fails
+        super::evaluation::batch_bit_fails(network, data, bit_fail_limit)
     }
 
     fn save_state(&self) -> TrainingState<T> {
         let mut state = HashMap::new();
         state.insert("learning_rate".to_string(), vec![self.learning_rate]);
         state.insert("momentum".to_string(), vec![self.momentum]);
+        self.regularization.save(&mut state);
 
         TrainingState {
             epoch: 0,
-            best_error: T::from(f32::MAX).unwrap(),
+            best_error: cast(f32::MAX),
             algorithm_specific: state,
         }
     }
@@ -423,10 +623,35 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for BatchBackprop<T> {
                 self.momentum = mom[0];
             }
         }
+        self.regularization.restore(&state.algorithm_specific);
+    }
+
+    fn learning_rate(&self) -> Option<T> {
+        Some(self.learning_rate)
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.learning_rate = rate;
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.statistics.last_gradient_norm()
+    }
+
+    fn last_weight_gradients(&self) -> Option<&[Vec<T>]> {
+        self.statistics.last_weight_gradients()
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.options.cancellation = token;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.options.is_cancelled()
     }
 
     fn set_callback(&mut self, callback: TrainingCallback<T>) {
-        self.callback = Some(callback);
+        self.callbacks.subscribe(callback);
     }
 
     fn call_callback(
@@ -435,11 +660,46 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for BatchBackprop<T> {
         network: &Network<T>,
         data: &TrainingData<T>,
     ) -> bool {
-        let error = self.calculate_error(network, data);
-        if let Some(ref mut callback) = self.callback {
-            callback(epoch, error)
-        } else {
-            true
+        if self.callbacks.is_empty() {
+            return true;
         }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
+    }
+}
+
+impl<T: Float + Send + Sync + Default> AdvancedTrainingAlgorithm<T> for IncrementalBackprop<T> {
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        &self.statistics
+    }
+
+    fn reset_statistics(&mut self) {
+        self.statistics.reset();
+    }
+}
+
+impl<T: Float + Send + Default> WithTrainingOptions for BatchBackprop<T> {
+    fn training_options(&self) -> &TrainingOptions {
+        &self.options
+    }
+
+    fn training_options_mut(&mut self) -> &mut TrainingOptions {
+        &mut self.options
+    }
+}
+
+impl<T: Float + Send + Sync + Default> AdvancedTrainingAlgorithm<T> for BatchBackprop<T> {
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        &self.statistics
+    }
+
+    fn reset_statistics(&mut self) {
+        self.statistics.reset();
     }
 }
@@ -0,0 +1,191 @@
+//! Adaptive mini-batch size
+//!
+//! [`AdaptiveBatchSize`] resizes the mini-batch between epochs. Two signals
+//! drive it:
+//!
+//! * **Throughput** (samples per second). The batch grows while larger
+//!   batches process samples faster; once growing makes an epoch slower, the
+//!   controller steps back and caps the batch there, so the cap reflects the
+//!   hardware it is running on.
+//! * **Gradient noise scale**, when one is reported through
+//!   [`AdaptiveBatchSize::observe_noise_scale`]. The noise scale estimates the
+//!   batch size beyond which larger batches stop improving each step, so the
+//!   controller moves the batch towards it, never past the throughput cap.
+//!
+//! Without a noise estimate the batch simply grows to the fastest size.
+
+use std::time::Duration;
+
+/// Limits and step sizes of an [`AdaptiveBatchSize`] controller
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveBatchOptions {
+    /// Batch size of the first epoch
+    pub initial: usize,
+    pub min: usize,
+    pub max: usize,
+    /// Factor applied when growing or shrinking the batch
+    pub step_factor: f64,
+    /// Relative throughput change treated as noise rather than a real
+    /// difference
+    pub throughput_tolerance: f64,
+}
+
+impl Default for AdaptiveBatchOptions {
+    fn default() -> Self {
+        Self {
+            initial: 32,
+            min: 1,
+            max: 4096,
+            step_factor: 2.0,
+            throughput_tolerance: 0.05,
+        }
+    }
+}
+
+/// One epoch as seen by the controller
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatchSizeStep {
+    pub batch_size: usize,
+    /// Samples processed per second during the epoch
+    pub throughput: f64,
+}
+
+/// Controller that grows or shrinks the mini-batch between epochs
+#[derive(Debug, Clone)]
+pub struct AdaptiveBatchSize {
+    options: AdaptiveBatchOptions,
+    current: usize,
+    /// Largest batch that did not reduce throughput
+    ceiling: usize,
+    noise_scale: Option<f64>,
+    history: Vec<BatchSizeStep>,
+}
+
+impl AdaptiveBatchSize {
+    pub fn new(options: AdaptiveBatchOptions) -> Self {
+        let min = options.min.max(1);
+        let max = options.max.max(min);
+        let options = AdaptiveBatchOptions {
+            min,
+            max,
+            ..options
+        };
+        Self {
+            current: options.initial.clamp(min, max),
+            ceiling: max,
+            noise_scale: None,
+            history: Vec::new(),
+            options,
+        }
+    }
+
+    /// Batch size to use for the next epoch
+    pub fn batch_size(&self) -> usize {
+        self.current
+    }
+
+    /// Every epoch observed so far, in order
+    pub fn history(&self) -> &[BatchSizeStep] {
+        &self.history
+    }
+
+    /// Latest gradient noise scale estimate, in samples
+    pub fn noise_scale(&self) -> Option<f64> {
+        self.noise_scale
+    }
+
+    /// Report a gradient noise scale estimate; non-finite or non-positive
+    /// values are ignored
+    pub fn observe_noise_scale(&mut self, noise_scale: f64) {
+        if noise_scale.is_finite() && noise_scale > 0.0 {
+            self.noise_scale = Some(noise_scale);
+        }
+    }
+
+    /// Record an epoch of `samples` that took `elapsed` and choose the next
+    /// batch size
+    pub fn observe_epoch(&mut self, samples: usize, elapsed: Duration) -> usize {
+        let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+        let step = BatchSizeStep {
+            batch_size: self.current,
+            throughput: samples as f64 / seconds,
+        };
+
+        let tolerance = self.options.throughput_tolerance;
+        if let Some(previous) = self.history.last() {
+            let slower = step.throughput < previous.throughput * (1.0 - tolerance);
+            if step.batch_size > previous.batch_size && slower {
+                self.ceiling = previous.batch_size;
+            }
+        }
+        self.history.push(step);
+
+        let factor = self.options.step_factor.max(1.0);
+        let grown = ((self.current as f64 * factor).round() as usize).max(self.current + 1);
+        let shrunk = ((self.current as f64 / factor).round() as usize).min(self.current - 1);
+        let next = match self.noise_scale {
+            Some(target) if (self.current as f64) < target / factor => grown,
+            Some(target) if (self.current as f64) > target * factor => shrunk,
+            Some(_) => self.current,
+            None => grown,
+        };
+        self.current = next.clamp(self.options.min, self.ceiling.max(self.options.min));
+        self.current
+    }
+}
+
+impl Default for AdaptiveBatchSize {
+    fn default() -> Self {
+        Self::new(AdaptiveBatchOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller(initial: usize) -> AdaptiveBatchSize {
+        AdaptiveBatchSize::new(AdaptiveBatchOptions {
+            initial,
+            min: 4,
+            max: 256,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_grows_until_throughput_drops() {
+        let mut batch = controller(8);
+        let second = Duration::from_secs(1);
+        assert_eq!(batch.observe_epoch(1000, second), 16);
+        assert_eq!(batch.observe_epoch(1500, second), 32);
+        // Batch 32 is slower than batch 16: step back and stay there
+        assert_eq!(batch.observe_epoch(1000, second), 16);
+        assert_eq!(batch.observe_epoch(1500, second), 16);
+        assert_eq!(batch.history().len(), 4);
+    }
+
+    #[test]
+    fn test_moves_towards_noise_scale() {
+        let mut batch = controller(128);
+        batch.observe_noise_scale(10.0);
+        let second = Duration::from_secs(1);
+        assert_eq!(batch.observe_epoch(1000, second), 64);
+        assert_eq!(batch.observe_epoch(1000, second), 32);
+        assert_eq!(batch.observe_epoch(1000, second), 16);
+        assert_eq!(batch.observe_epoch(1000, second), 16);
+
+        batch.observe_noise_scale(f64::NAN);
+        assert_eq!(batch.noise_scale(), Some(10.0));
+        batch.observe_noise_scale(1000.0);
+        assert_eq!(batch.observe_epoch(1000, second), 32);
+    }
+
+    #[test]
+    fn test_respects_limits() {
+        let mut batch = controller(1);
+        assert_eq!(batch.batch_size(), 4);
+        batch.observe_noise_scale(1.0);
+        assert_eq!(batch.observe_epoch(100, Duration::from_secs(1)), 4);
+    }
+}
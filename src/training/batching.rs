@@ -0,0 +1,235 @@
+//! Mini-batch iteration
+//!
+//! A [`BatchIterator`] splits each epoch into mini-batches of a fixed size,
+//! optionally dropping a short last batch and shuffling the samples first.
+//! Seeded shuffles use [`epoch_permutation`](super::epoch_permutation), so
+//! every epoch gets its own reproducible Fisher–Yates order.
+//!
+//! The optimizers that embed [`TrainingOptions`](super::TrainingOptions)
+//! take one through
+//! [`with_mini_batches`](super::WithTrainingOptions::with_mini_batches) and
+//! then update once per mini-batch inside `train_epoch`; [`Lars`](super::Lars)
+//! and [`Lamb`](super::Lamb) have their own `with_mini_batches`.
+//! [`Rprop`](super::Rprop) and [`Quickprop`](super::Quickprop) accept one
+//! too, but they adapt their steps from the sign changes of successive
+//! gradients, which mini-batch noise defeats, so they are best left
+//! full-batch. [`IncrementalBackprop`](super::IncrementalBackprop) already
+//! updates per sample.
+//!
+//! ```
+//! use do_fann::training::{
+//!     Adam, BatchIterator, TrainingAlgorithm, TrainingData, WithTrainingOptions,
+//! };
+//! use do_fann::NetworkBuilder;
+//!
+//! let data = TrainingData {
+//!     inputs: (0..10).map(|i| vec![i as f32 / 10.0]).collect(),
+//!     outputs: (0..10).map(|i| vec![i as f32 / 20.0]).collect(),
+//! };
+//! let mut network = NetworkBuilder::<f32>::new()
+//!     .input_layer(1)
+//!     .hidden_layer(4)
+//!     .output_layer(1)
+//!     .build();
+//! let mut adam = Adam::new(0.01).with_mini_batches(
+//!     BatchIterator::new(4).with_drop_last(true).with_shuffle(Some(42)),
+//! );
+//! let error = adam.train_epoch(&mut network, &data)?;
+//! assert!(error.is_finite());
+//! # Ok::<(), do_fann::TrainingError>(())
+//! ```
+
+use super::{TrainingAlgorithm, TrainingData, TrainingError};
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+use rand::seq::SliceRandom;
+
+/// Splits epochs into (optionally shuffled) mini-batches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchIterator {
+    batch_size: usize,
+    drop_last: bool,
+    shuffle: bool,
+    seed: Option<u64>,
+    /// Epochs handed out so far
+    epoch: usize,
+}
+
+impl BatchIterator {
+    /// Batches of `batch_size` samples (at least 1) in data order
+    pub fn new(batch_size: usize) -> Self {
+        Self {
+            batch_size: batch_size.max(1),
+            drop_last: false,
+            shuffle: false,
+            seed: None,
+            epoch: 0,
+        }
+    }
+
+    /// Skip the last batch of an epoch when it is smaller than the batch
+    /// size
+    pub fn with_drop_last(mut self, drop_last: bool) -> Self {
+        self.drop_last = drop_last;
+        self
+    }
+
+    /// Shuffle the samples of every epoch; with a seed the order of each
+    /// epoch is reproducible, without one it comes from the thread RNG
+    pub fn with_shuffle(mut self, seed: Option<u64>) -> Self {
+        self.shuffle = true;
+        self.seed = seed;
+        self
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Epochs handed out so far
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    /// Number of batches in an epoch of `samples` samples
+    pub fn num_batches(&self, samples: usize) -> usize {
+        if self.drop_last {
+            samples / self.batch_size
+        } else {
+            samples.div_ceil(self.batch_size)
+        }
+    }
+
+    /// Sample indices of the next epoch, one `Vec` per batch
+    pub fn next_epoch(&mut self, samples: usize) -> Vec<Vec<usize>> {
+        let order = match (self.shuffle, self.seed) {
+            (false, _) => (0..samples).collect(),
+            (true, Some(seed)) => super::epoch_permutation(seed, self.epoch, samples),
+            (true, None) => {
+                let mut order: Vec<usize> = (0..samples).collect();
+                order.shuffle(&mut rand::thread_rng());
+                order
+            }
+        };
+        self.epoch += 1;
+        order
+            .chunks(self.batch_size)
+            .take(self.num_batches(samples))
+            .map(<[usize]>::to_vec)
+            .collect()
+    }
+
+    /// Run one epoch of `data` through `algorithm`, one
+    /// [`train_epoch`](TrainingAlgorithm::train_epoch) call per mini-batch
+    ///
+    /// Returns the mean batch error weighted by batch size. Stops after the
+    /// current batch once [`TrainingAlgorithm::is_cancelled`] is set. Fails
+    /// if the epoch has no batches, e.g. fewer samples than the batch size
+    /// with drop-last.
+    pub fn train_epoch<T: Float>(
+        &mut self,
+        algorithm: &mut dyn TrainingAlgorithm<T>,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<T, TrainingError> {
+        data.check_shapes(network)?;
+        let (mut total, mut samples) = (T::zero(), 0usize);
+        for indices in self.next_epoch(data.inputs.len()) {
+            let batch = TrainingData {
+                inputs: indices.iter().map(|&i| data.inputs[i].clone()).collect(),
+                outputs: indices.iter().map(|&i| data.outputs[i].clone()).collect(),
+            };
+            total = total + algorithm.train_epoch(network, &batch)? * cast(indices.len());
+            samples += indices.len();
+            if algorithm.is_cancelled() {
+                break;
+            }
+        }
+        if samples == 0 {
+            return Err(TrainingError::InvalidData(format!(
+                "{} samples make no batch of {}",
+                data.inputs.len(),
+                self.batch_size
+            )));
+        }
+        Ok(total / cast(samples))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::{Adam, BatchBackprop, WithTrainingOptions};
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_batches_cover_epoch() {
+        let mut batches = BatchIterator::new(4);
+        let epoch = batches.next_epoch(10);
+        assert_eq!(epoch, [vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]);
+
+        let mut seeded = BatchIterator::new(4)
+            .with_drop_last(true)
+            .with_shuffle(Some(3));
+        let first = seeded.next_epoch(10);
+        let second = seeded.next_epoch(10);
+        assert_eq!(first.len(), 2);
+        assert!(first.iter().all(|batch| batch.len() == 4));
+        assert_ne!(first, second);
+        assert_eq!(seeded.epoch(), 2);
+
+        let mut replay = BatchIterator::new(4)
+            .with_drop_last(true)
+            .with_shuffle(Some(3));
+        assert_eq!(replay.next_epoch(10), first);
+
+        let mut unseeded = BatchIterator::new(3).with_shuffle(None);
+        let mut seen: Vec<usize> = unseeded.next_epoch(10).concat();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_optimizers_update_per_batch() {
+        let data = TrainingData {
+            inputs: (0..12).map(|i| vec![i as f64 / 12.0]).collect(),
+            outputs: (0..12).map(|i| vec![0.2 + i as f64 / 24.0]).collect(),
+        };
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+
+        // Three mini-batches are three Adam steps
+        let mut full = network.clone();
+        let mut adam = Adam::new(0.01);
+        adam.train_epoch(&mut full, &data).unwrap();
+        let mut batched = network.clone();
+        let mut adam = Adam::new(0.01).with_mini_batches(BatchIterator::new(4));
+        adam.train_epoch(&mut batched, &data).unwrap();
+        assert_eq!(adam.save_state().algorithm_specific["step"], [3.0]);
+        assert_ne!(full.get_weights(), batched.get_weights());
+
+        // Same as feeding the batches by hand
+        let mut manual = network.clone();
+        let mut reference = Adam::new(0.01);
+        for start in (0..12).step_by(4) {
+            let batch = TrainingData {
+                inputs: data.inputs[start..start + 4].to_vec(),
+                outputs: data.outputs[start..start + 4].to_vec(),
+            };
+            reference.train_epoch(&mut manual, &batch).unwrap();
+        }
+        assert_eq!(manual.get_weights(), batched.get_weights());
+
+        let mut network = network;
+        let mut backprop =
+            BatchBackprop::new(0.5).with_mini_batches(BatchIterator::new(20).with_drop_last(true));
+        assert!(matches!(
+            backprop.train_epoch(&mut network, &data),
+            Err(TrainingError::InvalidData(_))
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+//! Best-model tracking and rollback
+
+use crate::Network;
+use num_traits::Float;
+
+/// Result of reporting an error to a [`BestModelKeeper`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeeperDecision {
+    /// The error improved and the weights were snapshotted
+    Improved,
+    /// The error did not improve on the best snapshot
+    NoImprovement,
+    /// The error is not finite or exceeds the divergence limit
+    Diverged,
+}
+
+/// Keeps a copy of the weights with the lowest observed error
+///
+/// Call [`BestModelKeeper::observe`] after each evaluation and
+/// [`BestModelKeeper::restore`] to roll the network back to the best snapshot,
+/// e.g. at the end of training or when training diverges.
+#[derive(Debug, Clone)]
+pub struct BestModelKeeper<T: Float> {
+    min_delta: T,
+    divergence_factor: Option<T>,
+    best_weights: Option<Vec<T>>,
+    best_error: T,
+    best_epoch: usize,
+}
+
+impl<T: Float> Default for BestModelKeeper<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Float> BestModelKeeper<T> {
+    pub fn new() -> Self {
+        Self {
+            min_delta: T::zero(),
+            divergence_factor: None,
+            best_weights: None,
+            best_error: T::infinity(),
+            best_epoch: 0,
+        }
+    }
+
+    /// Minimum decrease in error that counts as an improvement
+    pub fn with_min_delta(mut self, min_delta: T) -> Self {
+        self.min_delta = min_delta;
+        self
+    }
+
+    /// Report divergence once the error exceeds `factor` times the best error
+    pub fn with_divergence_factor(mut self, factor: T) -> Self {
+        self.divergence_factor = Some(factor);
+        self
+    }
+
+    /// Record an evaluation of `network` and snapshot it if it is the best so far
+    pub fn observe(&mut self, network: &Network<T>, error: T, epoch: usize) -> KeeperDecision {
+        if !error.is_finite() {
+            return KeeperDecision::Diverged;
+        }
+
+        if self.best_weights.is_none() || error < self.best_error - self.min_delta {
+            self.best_weights = Some(network.get_weights());
+            self.best_error = error;
+            self.best_epoch = epoch;
+            return KeeperDecision::Improved;
+        }
+
+        match self.divergence_factor {
+            Some(factor) if error > self.best_error * factor => KeeperDecision::Diverged,
+            _ => KeeperDecision::NoImprovement,
+        }
+    }
+
+    /// Copy the best snapshot back into `network`; returns `false` if there is none
+    pub fn restore(&self, network: &mut Network<T>) -> bool {
+        match &self.best_weights {
+            Some(weights) => network.set_weights(weights).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn has_snapshot(&self) -> bool {
+        self.best_weights.is_some()
+    }
+
+    /// Lowest observed error, if any evaluation has been recorded
+    pub fn best_error(&self) -> Option<T> {
+        self.best_weights.as_ref().map(|_| self.best_error)
+    }
+
+    /// Epoch at which the best snapshot was taken
+    pub fn best_epoch(&self) -> Option<usize> {
+        self.best_weights.as_ref().map(|_| self.best_epoch)
+    }
+
+    /// Forget the snapshot
+    pub fn reset(&mut self) {
+        self.best_weights = None;
+        self.best_error = T::infinity();
+        self.best_epoch = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        let original = network.get_weights();
+        let mut keeper = BestModelKeeper::new().with_divergence_factor(10.0);
+
+        assert!(!keeper.restore(&mut network));
+        assert_eq!(keeper.observe(&network, 0.5, 0), KeeperDecision::Improved);
+
+        let perturbed: Vec<f64> = original.iter().map(|w| w + 1.0).collect();
+        network.set_weights(&perturbed).unwrap();
+        assert_eq!(
+            keeper.observe(&network, 0.6, 1),
+            KeeperDecision::NoImprovement
+        );
+        assert_eq!(keeper.observe(&network, 6.0, 2), KeeperDecision::Diverged);
+        assert_eq!(
+            keeper.observe(&network, f64::NAN, 3),
+            KeeperDecision::Diverged
+        );
+
+        assert!(keeper.restore(&mut network));
+        assert_eq!(network.get_weights(), original);
+        assert_eq!(keeper.best_error(), Some(0.5));
+        assert_eq!(keeper.best_epoch(), Some(0));
+    }
+}
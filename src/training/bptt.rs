@@ -0,0 +1,530 @@
+//! Backpropagation through time
+//!
+//! [`Bptt`] trains recurrent networks (see [`Recurrence`](crate::Recurrence))
+//! on [`TrainingData`] read as one time-ordered sequence: sample `t` is the
+//! input and target of step `t`. Every epoch starts from a cleared state and
+//! walks the sequence in windows of `truncation` steps. Within a window the
+//! error flows back through the context units to earlier steps; the context
+//! entering a window is treated as a constant, so memory and time per update
+//! are bounded by the window length. Weights are updated once per window with
+//! the mean gradient of its steps.
+//!
+//! Unlike the other algorithms, `Bptt` works on the network's own layers
+//! rather than a dense copy, so it honours every activation function,
+//! steepness and sparse connection. On a feed-forward network it is plain
+//! gradient descent with one update per window.
+
+use super::statistics::{buffer_bytes, gradient_norm};
+use super::*;
+use crate::numeric::cast;
+use crate::{Layer, Recurrence, RecurrenceKind};
+use num_traits::Float;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Values of one time step that the backward pass needs
+struct Step<T> {
+    /// Outputs of every layer, bias included
+    outputs: Vec<Vec<T>>,
+    /// Input sums of every layer
+    sums: Vec<Vec<T>>,
+    /// Context each layer saw in this step; empty for layers without one
+    contexts: Vec<Vec<T>>,
+}
+
+/// Gradients of one window, per layer
+struct Gradients<T> {
+    /// Connection weights of the regular neurons, in storage order
+    connections: Vec<Vec<T>>,
+    /// Context weights, row by row
+    recurrent: Vec<Vec<T>>,
+}
+
+impl<T: Float> Gradients<T> {
+    fn zeros(network: &Network<T>) -> Self {
+        Self {
+            connections: network
+                .layers
+                .iter()
+                .map(|layer| {
+                    let len = layer
+                        .neurons
+                        .iter()
+                        .filter(|n| !n.is_bias)
+                        .map(|n| n.connections.len())
+                        .sum();
+                    vec![T::zero(); len]
+                })
+                .collect(),
+            recurrent: network
+                .layers
+                .iter()
+                .map(|layer| {
+                    let len = layer
+                        .recurrence
+                        .as_ref()
+                        .map_or(0, |r| r.weights.iter().map(Vec::len).sum());
+                    vec![T::zero(); len]
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Add the error that `deltas` of the next step send back through the
+/// context weights of `recurrence` to `errors`
+fn add_context_errors<T: Float>(recurrence: &Recurrence<T>, deltas: &[T], errors: &mut [T]) {
+    for (row, &delta) in recurrence.weights.iter().zip(deltas) {
+        for (error, &weight) in errors.iter_mut().zip(row) {
+            *error = *error + delta * weight;
+        }
+    }
+}
+
+/// The recurrence of `layer`, if it is of `kind`
+fn context_of<T: Float>(layer: &Layer<T>, kind: RecurrenceKind) -> Option<&Recurrence<T>> {
+    layer.recurrence.as_ref().filter(|r| r.kind == kind)
+}
+
+/// Truncated backpropagation through time
+pub struct Bptt<T: Float + Send + Default> {
+    learning_rate: T,
+    truncation: usize,
+    error_function: Box<dyn ErrorFunction<T>>,
+    callbacks: EventDispatcher<T>,
+    cancellation: Option<CancellationToken>,
+    statistics: TrainingStatistics<T>,
+}
+
+impl<T: Float + Send + Default> Bptt<T> {
+    pub fn new(learning_rate: T) -> Self {
+        Self {
+            learning_rate,
+            truncation: 16,
+            error_function: Box::new(MseError),
+            callbacks: EventDispatcher::new(),
+            cancellation: None,
+            statistics: TrainingStatistics::new(),
+        }
+    }
+
+    /// Number of time steps the error is propagated back, and between
+    /// weight updates (at least 1)
+    pub fn with_truncation(mut self, steps: usize) -> Self {
+        self.truncation = steps.max(1);
+        self
+    }
+
+    /// Set error function
+    pub fn with_error_function(mut self, error_function: Box<dyn ErrorFunction<T>>) -> Self {
+        self.error_function = error_function;
+        self
+    }
+
+    pub fn truncation(&self) -> usize {
+        self.truncation
+    }
+
+    /// Run one time step, recording what the backward pass needs
+    fn forward(network: &mut Network<T>, input: &[T]) -> Step<T> {
+        let contexts = network
+            .layers
+            .iter()
+            .map(|layer| {
+                layer
+                    .recurrence
+                    .as_ref()
+                    .map_or_else(Vec::new, |r| r.context.clone())
+            })
+            .collect();
+        network.run(input);
+        Step {
+            outputs: network.layers.iter().map(Layer::get_outputs).collect(),
+            sums: network.layers.iter().map(Layer::get_sums).collect(),
+            contexts,
+        }
+    }
+
+    /// Gradients of the summed error of a window of steps
+    fn backward(
+        &self,
+        network: &Network<T>,
+        steps: &[Step<T>],
+        targets: &[Vec<T>],
+    ) -> Gradients<T> {
+        let mut gradients = Gradients::zeros(network);
+        let last = network.layers.len() - 1;
+
+        // Error terms of every layer in the step after the current one
+        let mut next_deltas: Vec<Vec<T>> = vec![Vec::new(); network.layers.len()];
+        for (step, desired) in steps.iter().zip(targets).rev() {
+            let mut deltas: Vec<Vec<T>> = vec![Vec::new(); network.layers.len()];
+            for l in (1..=last).rev() {
+                let layer = &network.layers[l];
+                let size = layer.num_regular_neurons();
+                let mut errors = vec![T::zero(); size];
+                if l == last {
+                    let actual = &step.outputs[l][..size];
+                    let derivatives = self.error_function.output_derivatives(actual, desired);
+                    for (error, derivative) in errors.iter_mut().zip(derivatives) {
+                        *error = derivative;
+                    }
+                    // Jordan layers see these outputs in the next step
+                    for (other, other_deltas) in network.layers.iter().zip(&next_deltas) {
+                        if let Some(recurrence) = context_of(other, RecurrenceKind::Jordan) {
+                            add_context_errors(recurrence, other_deltas, &mut errors);
+                        }
+                    }
+                } else {
+                    let next_layer = &network.layers[l + 1];
+                    for (neuron, &delta) in next_layer.neurons.iter().zip(&deltas[l + 1]) {
+                        for connection in &neuron.connections {
+                            if let Some(error) = errors.get_mut(connection.from_neuron) {
+                                *error = *error + delta * connection.weight;
+                            }
+                        }
+                    }
+                }
+                // Elman layers see their own outputs in the next step
+                if let Some(recurrence) = context_of(layer, RecurrenceKind::Elman) {
+                    add_context_errors(recurrence, &next_deltas[l], &mut errors);
+                }
+
+                deltas[l] = layer
+                    .neurons
+                    .iter()
+                    .zip(errors)
+                    .enumerate()
+                    .map(|(i, (neuron, error))| {
+                        error * neuron.derivative_at(step.sums[l][i], step.outputs[l][i])
+                    })
+                    .collect();
+
+                let inputs = &step.outputs[l - 1];
+                let mut k = 0;
+                for (neuron, &delta) in layer.neurons.iter().zip(&deltas[l]) {
+                    for connection in &neuron.connections {
+                        let input = inputs.get(connection.from_neuron).copied();
+                        let gradient = &mut gradients.connections[l][k];
+                        *gradient = *gradient + delta * input.unwrap_or_else(T::zero);
+                        k += 1;
+                    }
+                }
+                if let Some(recurrence) = &layer.recurrence {
+                    let mut k = 0;
+                    for (row, &delta) in recurrence.weights.iter().zip(&deltas[l]) {
+                        for &context in step.contexts[l].iter().take(row.len()) {
+                            let gradient = &mut gradients.recurrent[l][k];
+                            *gradient = *gradient + delta * context;
+                            k += 1;
+                        }
+                    }
+                }
+            }
+            next_deltas = deltas;
+        }
+        gradients
+    }
+
+    /// Gradient descent step with the mean gradient of `steps` time steps
+    fn apply(&self, network: &mut Network<T>, gradients: &Gradients<T>, steps: usize) {
+        let scale = self.learning_rate / cast(steps);
+        for (l, layer) in network.layers.iter_mut().enumerate() {
+            let weights = layer
+                .neurons
+                .iter_mut()
+                .filter(|n| !n.is_bias)
+                .flat_map(|n| n.connections.iter_mut().map(|c| &mut c.weight));
+            for (weight, &gradient) in weights.zip(&gradients.connections[l]) {
+                *weight = *weight - scale * gradient;
+            }
+            if let Some(recurrence) = &mut layer.recurrence {
+                let weights = recurrence.weights.iter_mut().flatten();
+                for (weight, &gradient) in weights.zip(&gradients.recurrent[l]) {
+                    *weight = *weight - scale * gradient;
+                }
+            }
+        }
+    }
+
+    /// Outputs for the whole sequence from a cleared state
+    fn predict(network: &Network<T>, data: &TrainingData<T>) -> Vec<Vec<T>> {
+        let mut network = network.clone();
+        network.reset_state();
+        network.run_sequence(&data.inputs)
+    }
+}
+
+impl<T: Float + Send + Sync + Default> TrainingAlgorithm<T> for Bptt<T> {
+    /// One pass over the sequence; returns the mean error of its steps
+    ///
+    /// The network's state is cleared before and after the pass. Once the
+    /// cancellation token is set, the pass ends after the current window.
+    fn train_epoch(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<T, TrainingError> {
+        data.check_widths(network)?;
+        if data.inputs.is_empty() || network.layers.len() < 2 {
+            return Err(TrainingError::InvalidData(
+                "BPTT needs a non-empty sequence and at least two layers".to_string(),
+            ));
+        }
+        let epoch_start = Instant::now();
+        let outputs = network.num_outputs();
+        let mut total_error = T::zero();
+        let mut epoch_gradients = Gradients::zeros(network);
+        let mut seen = 0;
+
+        network.reset_state();
+        for (inputs, targets) in data
+            .inputs
+            .chunks(self.truncation)
+            .zip(data.outputs.chunks(self.truncation))
+        {
+            let steps: Vec<Step<T>> = inputs
+                .iter()
+                .map(|input| Self::forward(network, input))
+                .collect();
+            for (step, desired) in steps.iter().zip(targets) {
+                let actual = &step.outputs[step.outputs.len() - 1][..outputs];
+                total_error = total_error + self.error_function.calculate(actual, desired);
+            }
+            let gradients = self.backward(network, &steps, targets);
+            self.apply(network, &gradients, steps.len());
+
+            let pairs = epoch_gradients
+                .connections
+                .iter_mut()
+                .chain(&mut epoch_gradients.recurrent)
+                .zip(gradients.connections.iter().chain(&gradients.recurrent));
+            for (sum, window) in pairs {
+                for (s, &g) in sum.iter_mut().zip(window) {
+                    *s = *s + g;
+                }
+            }
+            seen += steps.len();
+            if self.is_cancelled() {
+                break;
+            }
+        }
+        network.reset_state();
+
+        self.statistics.record_epoch(
+            epoch_start.elapsed(),
+            seen,
+            gradient_norm(&epoch_gradients.connections, &epoch_gradients.recurrent),
+            buffer_bytes(&[&epoch_gradients.connections, &epoch_gradients.recurrent]),
+        );
+
+        Ok(total_error / cast(seen))
+    }
+
+    fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
+        if data.inputs.is_empty() {
+            return T::zero();
+        }
+        let total = Self::predict(network, data)
+            .iter()
+            .zip(&data.outputs)
+            .fold(T::zero(), |sum, (actual, desired)| {
+                sum + self.error_function.calculate(actual, desired)
+            });
+        total / cast(data.inputs.len())
+    }
+
+    fn count_bit_fails(
+        &self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+        bit_fail_limit: T,
+    ) -> usize {
+        Self::predict(network, data)
+            .iter()
+            .zip(&data.outputs)
+            .flat_map(|(actual, desired)| actual.iter().zip(desired))
+            .filter(|(&a, &d)| (a - d).abs() > bit_fail_limit)
+            .count()
+    }
+
+    fn save_state(&self) -> TrainingState<T> {
+        let mut state = HashMap::new();
+        state.insert("learning_rate".to_string(), vec![self.learning_rate]);
+        state.insert("truncation".to_string(), vec![cast(self.truncation)]);
+
+        TrainingState {
+            epoch: 0,
+            best_error: cast(f32::MAX),
+            algorithm_specific: state,
+        }
+    }
+
+    fn restore_state(&mut self, state: TrainingState<T>) {
+        let state = state.algorithm_specific;
+        let scalar = |name: &str| state.get(name).and_then(|v| v.first()).copied();
+        if let Some(learning_rate) = scalar("learning_rate") {
+            self.learning_rate = learning_rate;
+        }
+        if let Some(truncation) = scalar("truncation").and_then(|t| t.to_usize()) {
+            self.truncation = truncation.max(1);
+        }
+    }
+
+    fn learning_rate(&self) -> Option<T> {
+        Some(self.learning_rate)
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.learning_rate = rate;
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.statistics.last_gradient_norm()
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    fn set_callback(&mut self, callback: TrainingCallback<T>) {
+        self.callbacks.subscribe(callback);
+    }
+
+    fn call_callback(
+        &mut self,
+        epoch: usize,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> bool {
+        if self.callbacks.is_empty() {
+            return true;
+        }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
+    }
+}
+
+impl<T: Float + Send + Sync + Default> AdvancedTrainingAlgorithm<T> for Bptt<T> {
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        &self.statistics
+    }
+
+    fn reset_statistics(&mut self) {
+        self.statistics.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ActivationFunction, NetworkBuilder};
+
+    /// Total error of the sequence in `data`
+    fn sequence_error(network: &Network<f64>, data: &TrainingData<f64>) -> f64 {
+        Bptt::new(0.0).calculate_error(network, data) * data.inputs.len() as f64
+    }
+
+    #[test]
+    fn test_gradients_match_finite_differences() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .elman_layer(3)
+            .jordan_layer(2)
+            .output_layer_with_activation(2, ActivationFunction::Tanh, 0.8)
+            .build();
+        network.randomize_weights(-0.8, 0.8);
+        for layer in &mut network.layers {
+            if let Some(recurrence) = &mut layer.recurrence {
+                for (i, w) in recurrence.weights.iter_mut().flatten().enumerate() {
+                    *w = 0.3 - 0.1 * i as f64;
+                }
+            }
+        }
+        let data = TrainingData {
+            inputs: vec![vec![0.5], vec![-0.3], vec![0.8], vec![0.1]],
+            outputs: vec![
+                vec![0.2, -0.1],
+                vec![0.4, 0.3],
+                vec![-0.2, 0.0],
+                vec![0.1, 0.5],
+            ],
+        };
+
+        let bptt = Bptt::new(0.1).with_truncation(4);
+        let mut copy = network.clone();
+        let steps: Vec<_> = data
+            .inputs
+            .iter()
+            .map(|input| Bptt::forward(&mut copy, input))
+            .collect();
+        let gradients = bptt.backward(&network, &steps, &data.outputs);
+        // MSE averages over the two outputs
+        let numeric = |shift: &dyn Fn(&mut Network<f64>, f64)| {
+            let (mut plus, mut minus) = (network.clone(), network.clone());
+            shift(&mut plus, 1e-6);
+            shift(&mut minus, -1e-6);
+            (sequence_error(&plus, &data) - sequence_error(&minus, &data)) / 2e-6 * 2.0
+        };
+
+        for l in 1..network.layers.len() {
+            let connection = numeric(&|n: &mut Network<f64>, h| {
+                n.layers[l].neurons[0].connections[0].weight += h
+            });
+            assert!((connection - gradients.connections[l][0]).abs() < 1e-6);
+            if network.layers[l].recurrence.is_some() {
+                let recurrent = numeric(&|n: &mut Network<f64>, h| {
+                    n.layers[l].recurrence.as_mut().unwrap().weights[1][1] += h
+                });
+                let row = network.layers[l].recurrence.as_ref().unwrap().weights[0].len();
+                assert!((recurrent - gradients.recurrent[l][row + 1]).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_learns_sequence_that_needs_memory() {
+        // The target is the previous input, which only the state can supply
+        let inputs: Vec<f64> = (0..40).map(|i| ((i * 7) % 5) as f64 / 4.0).collect();
+        let data = TrainingData {
+            inputs: inputs.iter().map(|&x| vec![x]).collect(),
+            outputs: (0..inputs.len())
+                .map(|i| vec![if i == 0 { 0.0 } else { inputs[i - 1] }])
+                .collect(),
+        };
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .elman_layer(6)
+            .output_layer(1)
+            .build();
+        network.randomize_weights(-0.5, 0.5);
+
+        let mut bptt = Bptt::new(2.0).with_truncation(5);
+        let initial = bptt.calculate_error(&network, &data);
+        for _ in 0..400 {
+            bptt.train_epoch(&mut network, &data).unwrap();
+        }
+        assert!(bptt.calculate_error(&network, &data) < initial * 0.2);
+        assert!(bptt.last_gradient_norm().unwrap().is_finite());
+        assert_eq!(bptt.statistics().epochs, 400);
+        assert!(network.layers[1]
+            .recurrence
+            .as_ref()
+            .unwrap()
+            .context
+            .iter()
+            .all(|&c| c == 0.0));
+    }
+}
@@ -0,0 +1,111 @@
+//! Cooperative cancellation of long-running training
+//!
+//! A [`CancellationToken`] is a cheap, cloneable handle around a shared flag.
+//! Hand one clone to a trainer and keep another in the UI thread, service
+//! handler or WASM host; trainers poll it between batches and stop with the
+//! partial result gathered so far. [`Trainer::with_cancellation`] also hands
+//! it to the algorithm, whose `train_epoch` polls it between mini-batches,
+//! shard groups, windows or samples.
+//!
+//! [`Trainer::with_cancellation`]: super::Trainer::with_cancellation
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shared flag used to ask a trainer to stop
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation; every clone of the token observes it
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Clear the flag so the token can be reused for another run
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_state() {
+        let token = CancellationToken::new();
+        let handle = token.clone();
+        assert!(!token.is_cancelled());
+
+        std::thread::spawn(move || handle.cancel()).join().unwrap();
+        assert!(token.is_cancelled());
+
+        token.reset();
+        assert!(!token.is_cancelled());
+    }
+
+    #[test]
+    fn test_optimizers_stop_mid_epoch() {
+        use crate::training::{
+            Adam, BatchIterator, Bptt, DataParallel, PipelineParallel, Quickprop, Rprop,
+            TrainingAlgorithm, TrainingData, WithTrainingOptions,
+        };
+        use crate::NetworkBuilder;
+
+        let data = TrainingData {
+            inputs: (0..6).map(|i| vec![i as f64 / 6.0, 1.0]).collect(),
+            outputs: (0..6).map(|i| vec![i as f64 / 12.0]).collect(),
+        };
+        let first = TrainingData {
+            inputs: data.inputs[..1].to_vec(),
+            outputs: data.outputs[..1].to_vec(),
+        };
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let token = CancellationToken::new();
+        token.cancel();
+
+        // Each stops after its first mini-batch, shard group or window,
+        // which trains like that sample alone
+        type Make = fn() -> Box<dyn TrainingAlgorithm<f64>>;
+        let one_step: [Make; 4] = [
+            || Box::new(Adam::new(0.1).with_mini_batches(BatchIterator::new(1))),
+            || Box::new(DataParallel::new(0.1).with_replicas(1).with_batch_size(1)),
+            || Box::new(PipelineParallel::new(0.1).with_batch_size(1)),
+            || Box::new(Bptt::new(0.1).with_truncation(1)),
+        ];
+        for make in one_step {
+            let (mut cancelled, mut reference) = (network.clone(), network.clone());
+            let mut algorithm = make();
+            algorithm.set_cancellation(Some(token.clone()));
+            let error = algorithm.train_epoch(&mut cancelled, &data).unwrap();
+            assert_eq!(error, make().train_epoch(&mut reference, &first).unwrap());
+            assert_eq!(cancelled.get_weights(), reference.get_weights());
+        }
+
+        // Full-batch optimizers end the epoch without an update
+        let full_batch: [Box<dyn TrainingAlgorithm<f64>>; 2] =
+            [Box::new(Rprop::new()), Box::new(Quickprop::new())];
+        for mut algorithm in full_batch {
+            let mut cancelled = network.clone();
+            algorithm.set_cancellation(Some(token.clone()));
+            algorithm.train_epoch(&mut cancelled, &data).unwrap();
+            assert_eq!(cancelled.get_weights(), network.get_weights());
+        }
+    }
+}
@@ -0,0 +1,425 @@
+//! On-disk training checkpoints
+//!
+//! A [`Checkpoint`] holds everything needed to continue a run: the network
+//! weights, the optimizer state from [`TrainingAlgorithm::save_state`] (for
+//! Adam this includes the first and second moment estimates) and the number of
+//! completed epochs. A [`CheckpointManager`] writes one to a directory every N
+//! epochs and finds the newest one that is still intact.
+//!
+//! Files are written under a temporary name and renamed into place, so an
+//! interrupted write never replaces a good checkpoint. Every file ends with a
+//! checksum; damaged files are skipped when looking for the latest
+//! checkpoint. Values are stored as `f64`, which holds `f32` and `f64`
+//! weights exactly.
+
+use super::{TrainingAlgorithm, TrainingError, TrainingState};
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"DFCK";
+const FORMAT_VERSION: u32 = 1;
+const EXTENSION: &str = "ckpt";
+
+/// Number of checkpoints a [`CheckpointManager`] keeps by default
+pub const DEFAULT_KEEP_LAST: usize = 3;
+
+/// Network weights, optimizer state and epoch counter of a training run
+#[derive(Debug, Clone)]
+pub struct Checkpoint<T: Float> {
+    /// Number of completed epochs
+    pub epoch: usize,
+    /// Neurons per layer, including bias neurons, of the saved network
+    pub layer_sizes: Vec<usize>,
+    pub weights: Vec<T>,
+    /// Optimizer state; its `epoch` matches [`Checkpoint::epoch`]
+    pub state: TrainingState<T>,
+}
+
+impl<T: Float> Checkpoint<T> {
+    /// Snapshot `network` and `algorithm` after `epoch` completed epochs
+    pub fn capture(
+        network: &Network<T>,
+        algorithm: &dyn TrainingAlgorithm<T>,
+        epoch: usize,
+    ) -> Self {
+        let mut state = algorithm.save_state();
+        state.epoch = epoch;
+        Self {
+            epoch,
+            layer_sizes: layer_sizes(network),
+            weights: network.get_weights(),
+            state,
+        }
+    }
+
+    /// Load the weights into `network` and the optimizer state into
+    /// `algorithm`
+    ///
+    /// Nothing is changed unless the network has the saved topology.
+    pub fn apply(
+        &self,
+        network: &mut Network<T>,
+        algorithm: &mut dyn TrainingAlgorithm<T>,
+    ) -> Result<(), TrainingError> {
+        if layer_sizes(network) != self.layer_sizes {
+            return Err(TrainingError::NetworkError(format!(
+                "checkpoint was taken from a network with layers {:?}, not {:?}",
+                self.layer_sizes,
+                layer_sizes(network)
+            )));
+        }
+        network
+            .set_weights(&self.weights)
+            .map_err(|e| TrainingError::NetworkError(e.to_string()))?;
+        algorithm.restore_state(self.state.clone());
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        put_len(&mut bytes, self.epoch);
+        put_len(&mut bytes, self.layer_sizes.len());
+        for &size in &self.layer_sizes {
+            put_len(&mut bytes, size);
+        }
+        put_values(&mut bytes, &self.weights);
+        put_value(&mut bytes, self.state.best_error);
+        // Sorted so the same state always produces the same file
+        let entries: BTreeMap<_, _> = self.state.algorithm_specific.iter().collect();
+        put_len(&mut bytes, entries.len());
+        for (key, values) in entries {
+            put_len(&mut bytes, key.len());
+            bytes.extend_from_slice(key.as_bytes());
+            put_values(&mut bytes, values);
+        }
+        let checksum = fnv1a(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TrainingError> {
+        let invalid =
+            |reason: &str| TrainingError::InvalidData(format!("bad checkpoint: {reason}"));
+        let body_len = bytes
+            .len()
+            .checked_sub(8)
+            .ok_or_else(|| invalid("truncated"))?;
+        let (body, checksum) = bytes.split_at(body_len);
+        if fnv1a(body).to_le_bytes() != checksum {
+            return Err(invalid("checksum mismatch"));
+        }
+        let mut reader = Reader { bytes: body };
+        if reader.take(4)? != MAGIC {
+            return Err(invalid("not a checkpoint file"));
+        }
+        let version = u32::from_le_bytes(reader.array()?);
+        if version != FORMAT_VERSION {
+            return Err(invalid(&format!("unsupported version {version}")));
+        }
+
+        let epoch = reader.len()?;
+        let layer_sizes = (0..reader.len()?)
+            .map(|_| reader.len())
+            .collect::<Result<_, _>>()?;
+        let weights = reader.values()?;
+        let best_error = reader.value()?;
+        let mut algorithm_specific = HashMap::new();
+        for _ in 0..reader.len()? {
+            let key_len = reader.len()?;
+            let key = String::from_utf8(reader.take(key_len)?.to_vec())
+                .map_err(|_| invalid("state key is not UTF-8"))?;
+            algorithm_specific.insert(key, reader.values()?);
+        }
+        if !reader.bytes.is_empty() {
+            return Err(invalid("trailing data"));
+        }
+
+        Ok(Self {
+            epoch,
+            layer_sizes,
+            weights,
+            state: TrainingState {
+                epoch,
+                best_error,
+                algorithm_specific,
+            },
+        })
+    }
+}
+
+/// Writes checkpoints to a directory at a fixed epoch interval
+///
+/// Files are named `checkpoint-<epoch>.ckpt`; only the newest
+/// [`DEFAULT_KEEP_LAST`] are kept unless configured otherwise.
+#[derive(Debug, Clone)]
+pub struct CheckpointManager {
+    directory: PathBuf,
+    interval: usize,
+    keep_last: Option<usize>,
+}
+
+impl CheckpointManager {
+    /// Checkpoint into `directory` after every epoch
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            interval: 1,
+            keep_last: Some(DEFAULT_KEEP_LAST),
+        }
+    }
+
+    /// Checkpoint after every `epochs` epochs; 0 is treated as 1
+    pub fn with_interval(mut self, epochs: usize) -> Self {
+        self.interval = epochs.max(1);
+        self
+    }
+
+    /// Number of checkpoints to keep, or `None` to keep all of them; at least
+    /// one is always kept
+    pub fn with_keep_last(mut self, keep_last: Option<usize>) -> Self {
+        self.keep_last = keep_last.map(|n| n.max(1));
+        self
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    /// Whether a checkpoint is due after `epoch` completed epochs
+    pub fn is_due(&self, epoch: usize) -> bool {
+        epoch > 0 && epoch % self.interval == 0
+    }
+
+    /// Write `checkpoint` atomically and drop checkpoints beyond the ones to
+    /// keep; returns the path written
+    pub fn save<T: Float>(&self, checkpoint: &Checkpoint<T>) -> Result<PathBuf, TrainingError> {
+        let io_error = |path: &Path, e: std::io::Error| {
+            TrainingError::TrainingFailed(format!("checkpoint {}: {e}", path.display()))
+        };
+        fs::create_dir_all(&self.directory).map_err(|e| io_error(&self.directory, e))?;
+
+        let path = self
+            .directory
+            .join(format!("checkpoint-{:08}.{EXTENSION}", checkpoint.epoch));
+        let tmp_path = path.with_extension(format!("{EXTENSION}.tmp"));
+        let write = || -> std::io::Result<()> {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(&checkpoint.to_bytes())?;
+            file.sync_all()
+        };
+        write().map_err(|e| io_error(&tmp_path, e))?;
+        fs::rename(&tmp_path, &path).map_err(|e| io_error(&path, e))?;
+
+        if let Some(keep_last) = self.keep_last {
+            let saved = self.checkpoints();
+            for (_, old) in &saved[..saved.len().saturating_sub(keep_last)] {
+                fs::remove_file(old).map_err(|e| io_error(old, e))?;
+            }
+        }
+        Ok(path)
+    }
+
+    /// Checkpoint files in the directory with their epochs, oldest first
+    pub fn checkpoints(&self) -> Vec<(usize, PathBuf)> {
+        let Ok(entries) = fs::read_dir(&self.directory) else {
+            return Vec::new();
+        };
+        let mut checkpoints: Vec<(usize, PathBuf)> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let epoch = path
+                    .file_name()?
+                    .to_str()?
+                    .strip_prefix("checkpoint-")?
+                    .strip_suffix(&format!(".{EXTENSION}"))?
+                    .parse()
+                    .ok()?;
+                Some((epoch, path))
+            })
+            .collect();
+        checkpoints.sort();
+        checkpoints
+    }
+
+    /// The newest checkpoint that reads back intact, with its path
+    pub fn latest<T: Float>(&self) -> Option<(PathBuf, Checkpoint<T>)> {
+        self.checkpoints().into_iter().rev().find_map(|(_, path)| {
+            let checkpoint = Checkpoint::from_bytes(&fs::read(&path).ok()?).ok()?;
+            Some((path, checkpoint))
+        })
+    }
+
+    /// Restore `network` and `algorithm` from the latest valid checkpoint;
+    /// returns its epoch, or `None` if there is none
+    pub fn restore_latest<T: Float>(
+        &self,
+        network: &mut Network<T>,
+        algorithm: &mut dyn TrainingAlgorithm<T>,
+    ) -> Result<Option<usize>, TrainingError> {
+        let Some((_, checkpoint)) = self.latest() else {
+            return Ok(None);
+        };
+        checkpoint.apply(network, algorithm)?;
+        Ok(Some(checkpoint.epoch))
+    }
+}
+
+fn layer_sizes<T: Float>(network: &Network<T>) -> Vec<usize> {
+    network
+        .layers
+        .iter()
+        .map(|layer| layer.neurons.len())
+        .collect()
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn put_len(bytes: &mut Vec<u8>, len: usize) {
+    bytes.extend_from_slice(&(len as u64).to_le_bytes());
+}
+
+fn put_value<T: Float>(bytes: &mut Vec<u8>, value: T) {
+    let value: f64 = cast(value);
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_values<T: Float>(bytes: &mut Vec<u8>, values: &[T]) {
+    put_len(bytes, values.len());
+    for &value in values {
+        put_value(bytes, value);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], TrainingError> {
+        if n > self.bytes.len() {
+            return Err(TrainingError::InvalidData(
+                "bad checkpoint: truncated".to_string(),
+            ));
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], TrainingError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn len(&mut self) -> Result<usize, TrainingError> {
+        usize::try_from(u64::from_le_bytes(self.array()?)).map_err(|_| {
+            TrainingError::InvalidData("bad checkpoint: length out of range".to_string())
+        })
+    }
+
+    fn value<T: Float>(&mut self) -> Result<T, TrainingError> {
+        Ok(cast(f64::from_le_bytes(self.array()?)))
+    }
+
+    fn values<T: Float>(&mut self) -> Result<Vec<T>, TrainingError> {
+        let len = self.len()?;
+        // Each value takes 8 bytes; a corrupt length must not allocate wildly
+        if len > self.bytes.len() / 8 {
+            return Err(TrainingError::InvalidData(
+                "bad checkpoint: truncated".to_string(),
+            ));
+        }
+        (0..len).map(|_| self.value()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::{xor, xor_network};
+    use crate::training::{Adam, TrainingData};
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_restores_weights_and_adam_moments() {
+        let directory = std::env::temp_dir().join(format!("checkpoints_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let manager = CheckpointManager::new(&directory).with_keep_last(Some(2));
+        let data = xor();
+
+        let initial = xor_network();
+        let mut trained = initial.clone();
+        let mut adam = Adam::new(0.05);
+        for epoch in 1..=3 {
+            adam.train_epoch(&mut trained, &data).unwrap();
+            manager
+                .save(&Checkpoint::capture(&trained, &adam, epoch))
+                .unwrap();
+        }
+        let epochs: Vec<usize> = manager.checkpoints().iter().map(|(e, _)| *e).collect();
+        assert_eq!(epochs, vec![2, 3]);
+
+        // A damaged newest file falls back to the one before it
+        let newest = &manager.checkpoints()[1].1;
+        let mut bytes = fs::read(newest).unwrap();
+        bytes[20] ^= 0xff;
+        fs::write(newest, bytes).unwrap();
+        let (path, checkpoint) = manager.latest::<f32>().unwrap();
+        assert_eq!(checkpoint.epoch, 2);
+        assert_eq!(path, manager.checkpoints()[0].1);
+        fs::remove_file(newest).unwrap();
+
+        // Continuing from the checkpoint matches continuing the original run
+        let mut resumed = initial.clone();
+        let mut fresh = Adam::new(0.05);
+        assert_eq!(
+            manager.restore_latest(&mut resumed, &mut fresh).unwrap(),
+            Some(2)
+        );
+        let mut original = initial.clone();
+        let mut original_adam = Adam::new(0.05);
+        for _ in 0..3 {
+            original_adam.train_epoch(&mut original, &data).unwrap();
+        }
+        fresh.train_epoch(&mut resumed, &data).unwrap();
+        assert_eq!(resumed.get_weights(), original.get_weights());
+
+        let mut other = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        assert!(checkpoint.apply(&mut other, &mut fresh).is_err());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_corrupt_bytes() {
+        let checkpoint = Checkpoint::capture(&xor_network(), &Adam::new(0.01), 4);
+        let bytes = checkpoint.to_bytes();
+        let restored = Checkpoint::<f32>::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.weights, checkpoint.weights);
+        assert_eq!(restored.state.epoch, 4);
+        assert_eq!(
+            restored.state.algorithm_specific,
+            checkpoint.state.algorithm_specific
+        );
+
+        assert!(Checkpoint::<f32>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Checkpoint::<f32>::from_bytes(&[]).is_err());
+    }
+}
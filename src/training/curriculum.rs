@@ -0,0 +1,193 @@
+//! Curriculum learning
+//!
+//! A [`Curriculum`] decides, at the start of every epoch, which training
+//! samples the [`Trainer`](super::Trainer) feeds to the algorithm and in which
+//! order. The trainer's batch loop then walks that schedule, so mini-batches
+//! and sample-based validation see the samples easiest first.
+//!
+//! [`DifficultyCurriculum`] scores every sample, either with a user callback
+//! or by the network's current loss on it, sorts easy to hard and can pace the
+//! curriculum by only admitting the easiest fraction of the data in early
+//! epochs.
+
+use super::{ErrorFunction, MseError, TrainingData};
+use crate::Network;
+use num_traits::Float;
+
+/// Per-epoch sample ordering and selection
+pub trait Curriculum<T: Float>: Send {
+    /// Indices into `data` to train on in `epoch`, in training order
+    fn schedule(
+        &mut self,
+        epoch: usize,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> Vec<usize>;
+}
+
+/// User-supplied difficulty of a sample from its input and target
+pub type DifficultyFn<T> = Box<dyn Fn(&[T], &[T]) -> f64 + Send>;
+
+enum Difficulty<T: Float> {
+    /// Fixed score per sample, computed once and cached
+    Callback {
+        score: DifficultyFn<T>,
+        cache: Vec<f64>,
+    },
+    /// Current loss of the network on each sample, recomputed every epoch
+    Loss(Box<dyn ErrorFunction<T>>),
+}
+
+/// Orders samples from easy to hard by a difficulty score
+pub struct DifficultyCurriculum<T: Float> {
+    difficulty: Difficulty<T>,
+    initial_fraction: f64,
+    full_after_epochs: usize,
+}
+
+impl<T: Float> DifficultyCurriculum<T> {
+    /// Difficulty given by `score(input, target)`; lower is easier
+    pub fn from_fn(score: impl Fn(&[T], &[T]) -> f64 + Send + 'static) -> Self {
+        Self::new(Difficulty::Callback {
+            score: Box::new(score),
+            cache: Vec::new(),
+        })
+    }
+
+    /// Difficulty given by the network's current mean squared error on each
+    /// sample (self-paced learning)
+    pub fn loss_based() -> Self {
+        Self::loss_based_with(Box::new(MseError))
+    }
+
+    /// Difficulty given by the network's current error on each sample
+    pub fn loss_based_with(error_function: Box<dyn ErrorFunction<T>>) -> Self {
+        Self::new(Difficulty::Loss(error_function))
+    }
+
+    fn new(difficulty: Difficulty<T>) -> Self {
+        Self {
+            difficulty,
+            initial_fraction: 1.0,
+            full_after_epochs: 0,
+        }
+    }
+
+    /// Start with the easiest `initial_fraction` of the samples and grow the
+    /// admitted share linearly until all samples are used from epoch
+    /// `full_after_epochs` on
+    pub fn with_pacing(mut self, initial_fraction: f64, full_after_epochs: usize) -> Self {
+        self.initial_fraction = initial_fraction.clamp(0.0, 1.0);
+        self.full_after_epochs = full_after_epochs;
+        self
+    }
+
+    /// Share of the samples admitted in `epoch`
+    pub fn fraction(&self, epoch: usize) -> f64 {
+        if epoch >= self.full_after_epochs {
+            return 1.0;
+        }
+        let progress = epoch as f64 / self.full_after_epochs as f64;
+        self.initial_fraction + (1.0 - self.initial_fraction) * progress
+    }
+
+    fn scores(&mut self, network: &Network<T>, data: &TrainingData<T>) -> Vec<f64> {
+        let samples = data.inputs.iter().zip(&data.outputs);
+        match &mut self.difficulty {
+            Difficulty::Callback { score, cache } => {
+                if cache.len() != data.inputs.len() {
+                    *cache = samples
+                        .map(|(input, target)| score(input, target))
+                        .collect();
+                }
+                cache.clone()
+            }
+            Difficulty::Loss(error_function) => {
+                let mut network = network.clone();
+                samples
+                    .map(|(input, target)| {
+                        let output = network.run(input);
+                        let error = error_function.calculate(&output, target);
+                        error.to_f64().unwrap_or(f64::INFINITY)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl<T: Float> Curriculum<T> for DifficultyCurriculum<T> {
+    fn schedule(
+        &mut self,
+        epoch: usize,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> Vec<usize> {
+        // NaN scores sort as the hardest samples
+        let scores: Vec<f64> = self
+            .scores(network, data)
+            .into_iter()
+            .map(|s| if s.is_nan() { f64::INFINITY } else { s })
+            .collect();
+        let mut order: Vec<usize> = (0..scores.len()).collect();
+        order.sort_by(|&a, &b| scores[a].total_cmp(&scores[b]));
+
+        let admitted = (self.fraction(epoch) * order.len() as f64).ceil() as usize;
+        order.truncate(admitted.clamp(1, order.len().max(1)));
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    fn data() -> TrainingData<f64> {
+        TrainingData {
+            inputs: (0..8).map(|i| vec![i as f64 / 8.0]).collect(),
+            outputs: (0..8).map(|i| vec![(i % 3) as f64]).collect(),
+        }
+    }
+
+    fn network() -> Network<f64> {
+        NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .output_layer(1)
+            .build()
+    }
+
+    #[test]
+    fn test_callback_ordering_and_pacing() {
+        // Difficulty is the distance from 0.5, so the middle comes first
+        let mut curriculum =
+            DifficultyCurriculum::from_fn(|input: &[f64], _| (input[0] - 0.5).abs())
+                .with_pacing(0.25, 4);
+
+        assert_eq!(curriculum.schedule(0, &network(), &data()), vec![4, 3]);
+        assert_eq!(curriculum.fraction(2), 0.625);
+        assert_eq!(curriculum.schedule(2, &network(), &data()).len(), 5);
+        assert_eq!(
+            curriculum.schedule(4, &network(), &data()),
+            vec![4, 3, 5, 2, 6, 1, 7, 0]
+        );
+    }
+
+    #[test]
+    fn test_loss_based_follows_network() {
+        let data = data();
+        let mut network = network();
+        let mut curriculum = DifficultyCurriculum::loss_based();
+        let order = curriculum.schedule(0, &network, &data);
+
+        let errors: Vec<f64> = order
+            .iter()
+            .map(|&i| MseError.calculate(&network.run(&data.inputs[i]), &data.outputs[i]))
+            .collect();
+        assert!(errors.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(errors.len(), 8);
+
+        // Targets of 2 are out of reach of a sigmoid, so those come last
+        assert!(order[6..].iter().all(|&i| data.outputs[i][0] == 2.0));
+    }
+}
@@ -0,0 +1,451 @@
+//! Data-parallel training across thread-local replicas
+//!
+//! [`DataParallel`] splits every mini-batch into one shard per worker
+//! thread. Each worker runs forward and backward passes over its shards on
+//! its own copy of the network, the per-worker gradient sums are added up and
+//! averaged over the samples, and one synchronized update is applied to the
+//! shared weights, after which every replica starts again from the same
+//! copy. With `accumulation_steps > 1` the gradients of that many mini-batches
+//! are averaged before the update, which trains like a mini-batch
+//! `accumulation_steps` times larger without holding it all at once.
+//!
+//! The update is therefore the same as batch backpropagation over the
+//! accumulated samples, up to floating-point rounding from the order of the
+//! sums. The time each worker spends computing gradients is added to
+//! [`TrainingStatistics::worker_times`], which shows load imbalance between
+//! threads.
+//!
+//! [`DataParallel::with_numa_policy`], or the `numa_policy` of the
+//! [`ParallelTrainingOptions`] passed to [`DataParallel::with_options`], pins
+//! each replica's thread to a NUMA node, as the workers of
+//! [`ParallelTrainingOptions::build_thread_pool`] are.
+
+use super::helpers::{calculate_gradients, forward_propagate, network_to_simple, SimpleNetwork};
+use super::statistics::{buffer_bytes, gradient_norm};
+use super::*;
+use crate::memory_manager::NumaTopology;
+use crate::numeric::cast;
+use std::time::{Duration, Instant};
+
+/// Inputs and desired outputs of one mini-batch
+type Batch<'a, T> = (&'a [Vec<T>], &'a [Vec<T>]);
+
+/// Weight and bias gradients summed over a worker's shards, with the summed
+/// error and the time spent
+struct WorkerResult<T> {
+    error: T,
+    weights: Vec<Vec<T>>,
+    biases: Vec<Vec<T>>,
+    elapsed: Duration,
+}
+
+fn add_into<T: Float>(sum: &mut [Vec<T>], values: &[Vec<T>]) {
+    for (sum, values) in sum.iter_mut().zip(values) {
+        for (a, &b) in sum.iter_mut().zip(values) {
+            *a = *a + b;
+        }
+    }
+}
+
+fn zeros_like<T: Float>(layers: &[Vec<T>]) -> Vec<Vec<T>> {
+    layers.iter().map(|l| vec![T::zero(); l.len()]).collect()
+}
+
+/// Batch gradient descent with each mini-batch sharded across worker threads
+pub struct DataParallel<T: Float + Send + Sync + Default> {
+    learning_rate: T,
+    replicas: usize,
+    batch_size: usize,
+    accumulation_steps: usize,
+    numa_policy: NumaPolicy,
+    /// Detected once a policy other than `Disabled` is set
+    topology: Option<NumaTopology>,
+    error_function: Box<dyn ErrorFunction<T>>,
+    callbacks: EventDispatcher<T>,
+    cancellation: Option<CancellationToken>,
+    statistics: TrainingStatistics<T>,
+}
+
+impl<T: Float + Send + Sync + Default> DataParallel<T> {
+    /// One replica per available CPU
+    pub fn new(learning_rate: T) -> Self {
+        Self {
+            learning_rate,
+            replicas: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            batch_size: 32,
+            accumulation_steps: 1,
+            numa_policy: NumaPolicy::Disabled,
+            topology: None,
+            error_function: Box::new(MseError),
+            callbacks: EventDispatcher::new(),
+            cancellation: None,
+            statistics: TrainingStatistics::new(),
+        }
+    }
+
+    /// Number of worker threads, each with its own network copy (at least 1)
+    pub fn with_replicas(mut self, replicas: usize) -> Self {
+        self.replicas = replicas.max(1);
+        self
+    }
+
+    /// Samples per mini-batch, split across the replicas (at least 1)
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Mini-batches whose gradients are averaged into one update (at least 1)
+    pub fn with_accumulation_steps(mut self, steps: usize) -> Self {
+        self.accumulation_steps = steps.max(1);
+        self
+    }
+
+    /// Pin replica `r` of `n` to the node [`NumaTopology::worker_node`]
+    /// gives for `policy`
+    pub fn with_numa_policy(mut self, policy: NumaPolicy) -> Self {
+        self.topology = (policy != NumaPolicy::Disabled).then(NumaTopology::detect);
+        self.numa_policy = policy;
+        self
+    }
+
+    /// Take the thread count (as replicas), batch size and NUMA policy from
+    /// `options`
+    pub fn with_options(self, options: &ParallelTrainingOptions) -> Self {
+        let replicas = options.threads(&NumaTopology::detect());
+        self.with_replicas(replicas)
+            .with_batch_size(options.batch_size)
+            .with_numa_policy(options.numa_policy)
+    }
+
+    pub fn with_error_function(mut self, error_function: Box<dyn ErrorFunction<T>>) -> Self {
+        self.error_function = error_function;
+        self
+    }
+
+    pub fn replicas(&self) -> usize {
+        self.replicas
+    }
+
+    /// Run the worker threads over one group of mini-batches
+    ///
+    /// Worker `r` takes shard `r` of every mini-batch in `group`. A panic in
+    /// a worker, e.g. from a custom error function, is reported as
+    /// [`TrainingError::TrainingFailed`].
+    fn run_group(
+        &self,
+        network: &SimpleNetwork<T>,
+        group: &[Batch<'_, T>],
+    ) -> Result<Vec<WorkerResult<T>>, TrainingError> {
+        let error_function = self.error_function.as_ref();
+        let replicas = self.replicas;
+        let placement = self.topology.as_ref().map(|t| (t, self.numa_policy));
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..replicas)
+                .map(|replica| {
+                    scope.spawn(move || {
+                        if let Some((topology, policy)) = placement {
+                            if let Some(node) = topology.worker_node(policy, replica, replicas) {
+                                topology.bind_current_thread(node);
+                            }
+                        }
+                        let started = Instant::now();
+                        let local = network.clone();
+                        let mut result = WorkerResult {
+                            error: T::zero(),
+                            weights: zeros_like(&local.weights),
+                            biases: zeros_like(&local.biases),
+                            elapsed: Duration::ZERO,
+                        };
+                        for &(inputs, outputs) in group {
+                            let shard = inputs.len().div_ceil(replicas);
+                            let start = (replica * shard).min(inputs.len());
+                            let end = (start + shard).min(inputs.len());
+                            for (input, desired) in
+                                inputs[start..end].iter().zip(&outputs[start..end])
+                            {
+                                let activations = forward_propagate(&local, input);
+                                let output = &activations[activations.len() - 1];
+                                result.error =
+                                    result.error + error_function.calculate(output, desired);
+                                let (weights, biases) = calculate_gradients(
+                                    &local,
+                                    &activations,
+                                    desired,
+                                    error_function,
+                                );
+                                add_into(&mut result.weights, &weights);
+                                add_into(&mut result.biases, &biases);
+                            }
+                        }
+                        result.elapsed = started.elapsed();
+                        result
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| {
+                    worker.join().map_err(|_| {
+                        TrainingError::TrainingFailed("data-parallel worker panicked".to_string())
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+impl<T: Float + Send + Sync + Default> TrainingAlgorithm<T> for DataParallel<T> {
+    fn train_epoch(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<T, TrainingError> {
+        data.check_shapes(network)?;
+        let epoch_start = Instant::now();
+        if data.inputs.is_empty() {
+            return Err(TrainingError::InvalidData(
+                "no training samples".to_string(),
+            ));
+        }
+
+        let batches: Vec<_> = data
+            .inputs
+            .chunks(self.batch_size)
+            .zip(data.outputs.chunks(self.batch_size))
+            .collect();
+        let mut total_error = T::zero();
+        let mut gradient_norm_sum = T::zero();
+        let mut updates = 0;
+        let mut worker_times = vec![Duration::ZERO; self.replicas];
+        let mut working_set = 0;
+        let mut seen = 0;
+        for group in batches.chunks(self.accumulation_steps) {
+            let simple = network_to_simple(network);
+            let results = self.run_group(&simple, group)?;
+
+            let mut weights = zeros_like(&simple.weights);
+            let mut biases = zeros_like(&simple.biases);
+            for (result, time) in results.iter().zip(&mut worker_times) {
+                total_error = total_error + result.error;
+                add_into(&mut weights, &result.weights);
+                add_into(&mut biases, &result.biases);
+                *time += result.elapsed;
+            }
+            let samples: usize = group.iter().map(|(inputs, _)| inputs.len()).sum();
+            seen += samples;
+            let mean = T::one() / cast(samples);
+            for values in weights.iter_mut().chain(biases.iter_mut()) {
+                values.iter_mut().for_each(|g| *g = *g * mean);
+            }
+            gradient_norm_sum = gradient_norm_sum + gradient_norm(&weights, &biases);
+            updates += 1;
+
+            let step = -self.learning_rate;
+            for values in weights.iter_mut().chain(biases.iter_mut()) {
+                values.iter_mut().for_each(|g| *g = *g * step);
+            }
+            helpers::apply_updates_to_network(network, &weights, &biases);
+            // The shared copy plus one network and one gradient buffer per replica
+            working_set =
+                buffer_bytes(&[&simple.weights, &simple.biases]) * (2 * self.replicas + 1);
+            if self.is_cancelled() {
+                break;
+            }
+        }
+
+        self.statistics.record_epoch(
+            epoch_start.elapsed(),
+            seen,
+            gradient_norm_sum / cast(updates),
+            working_set,
+        );
+        self.statistics.record_worker_times(&worker_times);
+        Ok(total_error / cast(seen))
+    }
+
+    fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
+        super::evaluation::batch_error(network, data, self.error_function.as_ref())
+    }
+
+    fn count_bit_fails(
+        &self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+        bit_fail_limit: T,
+    ) -> usize {
+        super::evaluation::batch_bit_fails(network, data, bit_fail_limit)
+    }
+
+    fn save_state(&self) -> TrainingState<T> {
+        let mut state = HashMap::new();
+        state.insert("learning_rate".to_string(), vec![self.learning_rate]);
+        TrainingState {
+            epoch: 0,
+            best_error: cast(f32::MAX),
+            algorithm_specific: state,
+        }
+    }
+
+    fn restore_state(&mut self, state: TrainingState<T>) {
+        if let Some(&lr) = state
+            .algorithm_specific
+            .get("learning_rate")
+            .and_then(|v| v.first())
+        {
+            self.learning_rate = lr;
+        }
+    }
+
+    fn learning_rate(&self) -> Option<T> {
+        Some(self.learning_rate)
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.learning_rate = rate;
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.statistics.last_gradient_norm()
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    fn set_callback(&mut self, callback: TrainingCallback<T>) {
+        self.callbacks.subscribe(callback);
+    }
+
+    fn call_callback(
+        &mut self,
+        epoch: usize,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> bool {
+        if self.callbacks.is_empty() {
+            return true;
+        }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
+    }
+}
+
+impl<T: Float + Send + Sync + Default> AdvancedTrainingAlgorithm<T> for DataParallel<T> {
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        &self.statistics
+    }
+
+    fn reset_statistics(&mut self) {
+        self.statistics.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_replicas_match_batch_backprop() {
+        let mut reference = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .output_layer(1)
+            .build();
+        let mut parallel = reference.clone();
+        let data = TrainingData {
+            inputs: vec![
+                vec![0.0, 0.0],
+                vec![0.0, 1.0],
+                vec![1.0, 0.0],
+                vec![1.0, 1.0],
+                vec![0.5, 0.2],
+                vec![0.1, 0.9],
+            ],
+            outputs: vec![
+                vec![0.0],
+                vec![1.0],
+                vec![1.0],
+                vec![0.0],
+                vec![0.4],
+                vec![0.8],
+            ],
+        };
+
+        // Three mini-batches of two, accumulated into one full-batch update
+        let mut batch = BatchBackprop::new(0.5);
+        let mut trainer = DataParallel::new(0.5)
+            .with_replicas(3)
+            .with_batch_size(2)
+            .with_accumulation_steps(3);
+        for _ in 0..4 {
+            let expected = batch.train_epoch(&mut reference, &data).unwrap();
+            let error = trainer.train_epoch(&mut parallel, &data).unwrap();
+            assert!((expected - error).abs() < 1e-12);
+        }
+        for (a, b) in parallel.get_weights().iter().zip(reference.get_weights()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+
+        let stats = trainer.statistics();
+        assert_eq!(stats.epochs, 4);
+        assert_eq!(stats.samples_processed, 24);
+        assert_eq!(stats.worker_times.len(), 3);
+        assert_eq!(stats.gradient_norm_history.len(), 4);
+    }
+
+    #[test]
+    fn test_updates_per_accumulation_group() {
+        let build = || {
+            NetworkBuilder::<f32>::new()
+                .input_layer(1)
+                .output_layer(1)
+                .build()
+        };
+        let data = TrainingData {
+            inputs: vec![vec![0.0], vec![1.0], vec![0.5], vec![0.25]],
+            outputs: vec![vec![1.0], vec![0.0], vec![1.0], vec![0.0]],
+        };
+        // Per-sample updates differ from one accumulated update
+        let initial = build();
+        let mut stepwise = initial.clone();
+        let mut accumulated = initial.clone();
+        DataParallel::new(1.0)
+            .with_replicas(2)
+            .with_batch_size(1)
+            .train_epoch(&mut stepwise, &data)
+            .unwrap();
+        DataParallel::new(1.0)
+            .with_replicas(2)
+            .with_batch_size(1)
+            .with_accumulation_steps(4)
+            .train_epoch(&mut accumulated, &data)
+            .unwrap();
+        assert_ne!(stepwise.get_weights(), accumulated.get_weights());
+        assert_eq!(DataParallel::<f32>::new(0.1).with_replicas(0).replicas(), 1);
+
+        // Pinned replicas train the same as unpinned ones
+        let options = ParallelTrainingOptions::default()
+            .with_threads(2)
+            .with_batch_size(1)
+            .with_numa_policy(NumaPolicy::Spread);
+        let mut pinned = initial;
+        let mut trainer = DataParallel::new(1.0).with_options(&options);
+        assert_eq!(trainer.replicas(), 2);
+        trainer.train_epoch(&mut pinned, &data).unwrap();
+        assert_eq!(pinned.get_weights(), stepwise.get_weights());
+    }
+}
@@ -0,0 +1,215 @@
+//! Patience-based early stopping around any optimizer
+//!
+//! [`EarlyStoppingTrainer`] trains with an owned [`TrainingAlgorithm`],
+//! evaluates a validation set after every epoch and stops once the
+//! validation error has not improved by more than `min_delta` for
+//! `patience` consecutive epochs. The weights with the lowest validation
+//! error are kept in a [`BestModelKeeper`] and restored at the end unless
+//! disabled.
+//!
+//! ```
+//! use do_fann::training::{EarlyStoppingTrainer, Rprop, TrainingData};
+//! use do_fann::NetworkBuilder;
+//!
+//! let data = TrainingData {
+//!     inputs: (0..8).map(|i| vec![i as f64 / 8.0]).collect(),
+//!     outputs: (0..8).map(|i| vec![0.2 + i as f64 / 16.0]).collect(),
+//! };
+//! let validation = data.clone();
+//! let mut network = NetworkBuilder::<f64>::new()
+//!     .input_layer(1)
+//!     .hidden_layer(3)
+//!     .output_layer(1)
+//!     .build();
+//! let mut trainer = EarlyStoppingTrainer::new(Rprop::new(), validation, 200)
+//!     .with_patience(5)
+//!     .with_min_delta(1e-6);
+//! let report = trainer.train(&mut network, &data)?;
+//! assert!(report.best_error.is_some());
+//! # Ok::<(), do_fann::TrainingError>(())
+//! ```
+
+use super::trainer::validate_data;
+use super::{
+    BestModelKeeper, KeeperDecision, TrainingAlgorithm, TrainingData, TrainingError,
+    TrainingReport, ValidationMetrics,
+};
+use crate::Network;
+use num_traits::Float;
+
+/// Trains until the validation error stops improving
+pub struct EarlyStoppingTrainer<T: Float, A: TrainingAlgorithm<T>> {
+    algorithm: A,
+    validation_data: TrainingData<T>,
+    max_epochs: usize,
+    patience: usize,
+    min_delta: T,
+    restore_best: bool,
+}
+
+impl<T: Float, A: TrainingAlgorithm<T>> EarlyStoppingTrainer<T, A> {
+    /// Train with `algorithm` for at most `max_epochs`, validating on
+    /// `validation_data` with a patience of 10 epochs
+    pub fn new(algorithm: A, validation_data: TrainingData<T>, max_epochs: usize) -> Self {
+        Self {
+            algorithm,
+            validation_data,
+            max_epochs,
+            patience: 10,
+            min_delta: T::zero(),
+            restore_best: true,
+        }
+    }
+
+    /// Epochs without improvement before stopping (at least 1)
+    pub fn with_patience(mut self, patience: usize) -> Self {
+        self.patience = patience.max(1);
+        self
+    }
+
+    /// Minimum decrease in validation error that counts as an improvement
+    pub fn with_min_delta(mut self, min_delta: T) -> Self {
+        self.min_delta = min_delta;
+        self
+    }
+
+    /// Whether to roll the network back to its best validation epoch when
+    /// training ends (enabled by default)
+    pub fn with_restore_best(mut self, restore: bool) -> Self {
+        self.restore_best = restore;
+        self
+    }
+
+    pub fn algorithm(&self) -> &A {
+        &self.algorithm
+    }
+
+    pub fn algorithm_mut(&mut self) -> &mut A {
+        &mut self.algorithm
+    }
+
+    pub fn into_inner(self) -> A {
+        self.algorithm
+    }
+
+    /// Train `network` on `data`
+    ///
+    /// The report lists the validation error of every epoch. Training also
+    /// stops when the validation error is not finite (`diverged`) or an
+    /// epoch-end subscriber of the algorithm asks to stop.
+    pub fn train(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<TrainingReport<T>, TrainingError> {
+        validate_data(network, data)?;
+        validate_data(network, &self.validation_data)?;
+
+        let mut keeper = BestModelKeeper::new().with_min_delta(self.min_delta);
+        let mut history = Vec::new();
+        let (mut epochs, mut final_error) = (0, T::zero());
+        let (mut stale, mut diverged) = (0, false);
+        let samples = data.inputs.len() as u64;
+
+        while epochs < self.max_epochs {
+            let epoch = epochs;
+            final_error = self.algorithm.train_epoch(network, data)?;
+            epochs += 1;
+            let validation_error = self
+                .algorithm
+                .calculate_error(network, &self.validation_data);
+            history.push(ValidationMetrics {
+                epoch,
+                samples_seen: samples * epochs as u64,
+                train_error: final_error,
+                validation_error,
+            });
+
+            match keeper.observe(network, validation_error, epoch) {
+                KeeperDecision::Improved => stale = 0,
+                KeeperDecision::NoImprovement => stale += 1,
+                KeeperDecision::Diverged => diverged = true,
+            }
+            if diverged || stale >= self.patience {
+                break;
+            }
+            if !self.algorithm.call_callback(epoch, network, data) {
+                break;
+            }
+        }
+
+        let restored_best = self.restore_best && keeper.restore(network);
+        Ok(TrainingReport {
+            epochs,
+            final_error,
+            validation_history: history,
+            stopped_early: epochs < self.max_epochs,
+            diverged,
+            best_error: keeper.best_error(),
+            best_epoch: keeper.best_epoch(),
+            restored_best,
+            cancelled: false,
+            contamination: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::{IncrementalBackprop, Rprop};
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_stops_after_patience_and_restores_best() {
+        let train = TrainingData {
+            inputs: (0..8).map(|i| vec![i as f64 / 8.0]).collect(),
+            outputs: (0..8).map(|i| vec![0.2 + i as f64 / 16.0]).collect(),
+        };
+        // Validation targets run against the training trend, so fitting the
+        // training data soon makes the validation error worse
+        let validation = TrainingData {
+            inputs: train.inputs.clone(),
+            outputs: (0..8).map(|i| vec![0.7 - i as f64 / 16.0]).collect(),
+        };
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .hidden_layer(4)
+            .output_layer(1)
+            .build();
+        let mut trainer =
+            EarlyStoppingTrainer::new(IncrementalBackprop::new(0.7), validation.clone(), 500)
+                .with_patience(3);
+        let report = trainer.train(&mut network, &train).unwrap();
+
+        assert!(report.stopped_early);
+        assert!(report.epochs < 500);
+        assert_eq!(report.validation_history.len(), report.epochs);
+        let best_epoch = report.best_epoch.unwrap();
+        assert_eq!(report.epochs, best_epoch + 4);
+        assert!(report.restored_best);
+        let restored = trainer.algorithm().calculate_error(&network, &validation);
+        assert!((restored - report.best_error.unwrap()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_rejects_mismatched_validation_data() {
+        let data = TrainingData {
+            inputs: vec![vec![0.0]],
+            outputs: vec![vec![1.0]],
+        };
+        let validation = TrainingData {
+            inputs: vec![vec![0.0, 1.0]],
+            outputs: vec![vec![1.0]],
+        };
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .output_layer(1)
+            .build();
+        let mut trainer = EarlyStoppingTrainer::new(Rprop::new(), validation, 5);
+        assert!(matches!(
+            trainer.train(&mut network, &data),
+            Err(TrainingError::InvalidData(_))
+        ));
+    }
+}
@@ -0,0 +1,275 @@
+//! Batched error and bit-fail evaluation
+//!
+//! Predictions for a whole data set are computed (in parallel for larger
+//! sets), collected into one row-major matrix and reduced in a single pass.
+//! The reductions accumulate into fixed-width lanes so the compiler can
+//! vectorize them, and large matrices are split across threads with rayon when
+//! the `parallel` feature is enabled.
+//!
+//! [`ConformalRegressor`] and [`ConformalClassifier`] calibrate a trained
+//! network on a holdout set for split conformal prediction.
+
+use super::{ErrorFunction, TrainingData};
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+mod conformal;
+pub use conformal::{ConformalClassifier, ConformalInterval, ConformalRegressor};
+
+/// Number of independent accumulators used by the reductions
+const LANES: usize = 8;
+
+/// Minimum number of matrix elements before a reduction is split across threads
+#[cfg(feature = "parallel")]
+const PARALLEL_THRESHOLD: usize = 1 << 14;
+
+/// Minimum number of samples before forward passes are split across threads
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_SAMPLES: usize = 256;
+
+/// Elements reduced by each rayon task
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK: usize = 1 << 12;
+
+/// Run the network on every input and return the outputs as a row-major matrix
+///
+/// With the `parallel` feature, larger input sets are split across rayon
+/// workers, each running its own clone of the network so that per-neuron
+/// activation state is never shared between threads.
+pub fn predict_matrix<T: Float + Send + Sync>(network: &Network<T>, inputs: &[Vec<T>]) -> Vec<T> {
+    #[cfg(feature = "parallel")]
+    if inputs.len() >= PARALLEL_MIN_SAMPLES {
+        let chunk = inputs
+            .len()
+            .div_ceil(rayon::current_num_threads())
+            .max(PARALLEL_MIN_SAMPLES / 4);
+        return inputs
+            .par_chunks(chunk)
+            .map(|chunk| predict_sequential(network, chunk))
+            .flatten_iter()
+            .collect();
+    }
+
+    predict_sequential(network, inputs)
+}
+
+fn predict_sequential<T: Float>(network: &Network<T>, inputs: &[Vec<T>]) -> Vec<T> {
+    let mut network = network.clone();
+    let mut predictions = Vec::with_capacity(inputs.len() * network.num_outputs());
+    for input in inputs {
+        predictions.extend(network.run(input));
+    }
+    predictions
+}
+
+/// Flatten the desired outputs into a row-major matrix
+pub fn target_matrix<T: Float>(outputs: &[Vec<T>]) -> Vec<T> {
+    outputs.iter().flatten().copied().collect()
+}
+
+/// Mean error of the network over a data set, as used by `calculate_error`
+pub fn batch_error<T: Float + Send + Sync>(
+    network: &Network<T>,
+    data: &TrainingData<T>,
+    error_function: &dyn ErrorFunction<T>,
+) -> T {
+    if data.inputs.is_empty() {
+        return T::zero();
+    }
+    let predictions = predict_matrix(network, &data.inputs);
+    let targets = target_matrix(&data.outputs);
+    let rows = data.inputs.len();
+
+    #[cfg(feature = "parallel")]
+    if predictions.len() >= PARALLEL_THRESHOLD {
+        let cols = predictions.len() / rows;
+        let chunk = (PARALLEL_CHUNK / cols.max(1)).max(1) * cols;
+        let total = predictions
+            .par_chunks(chunk)
+            .zip(targets.par_chunks(chunk))
+            .map(|(p, t)| {
+                let chunk_rows = p.len() / cols;
+                error_function.calculate_batch(p, t, chunk_rows) * cast(chunk_rows)
+            })
+            .reduce(T::zero, |a, b| a + b);
+        return total / cast(rows);
+    }
+
+    error_function.calculate_batch(&predictions, &targets, rows)
+}
+
+/// Number of outputs whose absolute error exceeds `bit_fail_limit`
+pub fn batch_bit_fails<T: Float + Send + Sync>(
+    network: &Network<T>,
+    data: &TrainingData<T>,
+    bit_fail_limit: T,
+) -> usize {
+    let predictions = predict_matrix(network, &data.inputs);
+    let targets = target_matrix(&data.outputs);
+    count_exceeding(&predictions, &targets, bit_fail_limit)
+}
+
+/// Sum of squared differences between two equally sized matrices
+pub fn squared_error_sum<T: Float>(predictions: &[T], targets: &[T]) -> T {
+    reduce_lanes(predictions, targets, |a, d| {
+        let diff = a - d;
+        diff * diff
+    })
+}
+
+/// Sum of absolute differences between two equally sized matrices
+pub fn absolute_error_sum<T: Float>(predictions: &[T], targets: &[T]) -> T {
+    reduce_lanes(predictions, targets, |a, d| (a - d).abs())
+}
+
+/// Number of elements whose absolute difference exceeds `limit`
+pub fn count_exceeding<T: Float + Send + Sync>(
+    predictions: &[T],
+    targets: &[T],
+    limit: T,
+) -> usize {
+    let count = |p: &[T], t: &[T]| {
+        p.iter()
+            .zip(t)
+            .filter(|(&a, &d)| (a - d).abs() > limit)
+            .count()
+    };
+
+    #[cfg(feature = "parallel")]
+    if predictions.len() >= PARALLEL_THRESHOLD {
+        return predictions
+            .par_chunks(PARALLEL_CHUNK)
+            .zip(targets.par_chunks(PARALLEL_CHUNK))
+            .map(|(p, t)| count(p, t))
+            .sum();
+    }
+
+    count(predictions, targets)
+}
+
+fn reduce_lanes<T: Float, F: Fn(T, T) -> T>(predictions: &[T], targets: &[T], term: F) -> T {
+    let len = predictions.len().min(targets.len());
+    let (predictions, targets) = (&predictions[..len], &targets[..len]);
+
+    let mut lanes = [T::zero(); LANES];
+    let mut p_chunks = predictions.chunks_exact(LANES);
+    let mut t_chunks = targets.chunks_exact(LANES);
+    for (p, t) in (&mut p_chunks).zip(&mut t_chunks) {
+        for lane in 0..LANES {
+            lanes[lane] = lanes[lane] + term(p[lane], t[lane]);
+        }
+    }
+
+    let tail = p_chunks
+        .remainder()
+        .iter()
+        .zip(t_chunks.remainder())
+        .fold(T::zero(), |acc, (&a, &d)| acc + term(a, d));
+    lanes.iter().fold(tail, |acc, &lane| acc + lane)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::{MaeError, MseError, TanhError};
+    use crate::NetworkBuilder;
+
+    fn sample_data() -> TrainingData<f64> {
+        TrainingData {
+            inputs: (0..37)
+                .map(|i| vec![i as f64 / 37.0, (i % 5) as f64 / 5.0])
+                .collect(),
+            outputs: (0..37)
+                .map(|i| vec![(i % 2) as f64, (i % 3) as f64 / 3.0])
+                .collect(),
+        }
+    }
+
+    fn per_sample_error(
+        network: &Network<f64>,
+        data: &TrainingData<f64>,
+        error_function: &dyn ErrorFunction<f64>,
+    ) -> f64 {
+        let mut network = network.clone();
+        let total: f64 = data
+            .inputs
+            .iter()
+            .zip(&data.outputs)
+            .map(|(input, output)| error_function.calculate(&network.run(input), output))
+            .sum();
+        total / data.inputs.len() as f64
+    }
+
+    #[test]
+    fn test_batch_error_matches_per_sample() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .output_layer(2)
+            .build();
+        let data = sample_data();
+
+        let functions: [&dyn ErrorFunction<f64>; 3] = [&MseError, &MaeError, &TanhError];
+        for error_function in functions {
+            let batched = batch_error(&network, &data, error_function);
+            let expected = per_sample_error(&network, &data, error_function);
+            assert!((batched - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_parallel_predictions_preserve_order() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .hidden_layer(3)
+            .output_layer(2)
+            .build();
+        let inputs: Vec<Vec<f64>> = (0..1000).map(|i| vec![i as f64 / 1000.0]).collect();
+
+        let predictions = predict_matrix(&network, &inputs);
+        let mut sequential = network.clone();
+        assert_eq!(predictions.len(), 2000);
+        for (row, input) in inputs.iter().enumerate() {
+            assert_eq!(
+                &predictions[row * 2..row * 2 + 2],
+                &sequential.run(input)[..]
+            );
+        }
+    }
+
+    #[test]
+    fn test_large_reductions() {
+        let n = 100_003;
+        let predictions: Vec<f32> = (0..n).map(|i| (i % 7) as f32 * 0.1).collect();
+        let targets = vec![0.3f32; n];
+
+        let expected: f64 = predictions
+            .iter()
+            .map(|&p| ((p - 0.3) as f64).powi(2))
+            .sum();
+        let sum = squared_error_sum(&predictions, &targets) as f64;
+        assert!((sum - expected).abs() / expected < 1e-4);
+
+        let data = TrainingData {
+            inputs: vec![vec![0.0f32]; n],
+            outputs: targets.iter().map(|&t| vec![t]).collect(),
+        };
+        let network = NetworkBuilder::<f32>::new()
+            .input_layer(1)
+            .output_layer(1)
+            .build();
+        let output = network.clone().run(&[0.0])[0];
+        let mse = batch_error(&network, &data, &MseError);
+        assert!((mse - (output - 0.3).powi(2)).abs() < 1e-5);
+
+        let fails = predictions
+            .iter()
+            .filter(|&&p| (p - 0.3f32).abs() > 0.25)
+            .count();
+        assert_eq!(count_exceeding(&predictions, &targets, 0.25), fails);
+    }
+}
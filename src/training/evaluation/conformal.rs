@@ -0,0 +1,378 @@
+//! Split conformal prediction
+//!
+//! A trained network is calibrated on a holdout set it was not trained on.
+//! The calibration set's nonconformity scores fix a threshold such that,
+//! for a new sample exchangeable with the calibration data, the prediction
+//! interval or set contains the true target with probability at least
+//! `1 - alpha`. Nothing is assumed about the network beyond that.
+//!
+//! [`ConformalRegressor`] scores absolute residuals separately per output,
+//! so the guarantee holds for each output on its own. [`ConformalClassifier`]
+//! scores one minus the normalized output of the true class and returns
+//! every class that scores below the threshold.
+
+use super::predict_matrix;
+use crate::numeric::cast;
+use crate::training::{TrainingData, TrainingError};
+use crate::Network;
+use num_traits::Float;
+
+/// Calibrated threshold: the `ceil((n + 1)(1 - alpha))`-th smallest of `n`
+/// scores, or infinity when the calibration set is too small for `alpha`
+fn conformal_quantile<T: Float>(mut scores: Vec<T>, alpha: f64) -> T {
+    scores.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let n = scores.len();
+    let rank = ((n + 1) as f64 * (1.0 - alpha)).ceil() as usize;
+    match rank {
+        0 => T::neg_infinity(),
+        rank if rank > n => T::infinity(),
+        rank => scores[rank - 1],
+    }
+}
+
+fn check_calibration<T: Float>(
+    network: &Network<T>,
+    calibration: &TrainingData<T>,
+    alpha: f64,
+) -> Result<(), TrainingError> {
+    calibration.check_shapes(network)?;
+    if calibration.inputs.is_empty() {
+        return Err(TrainingError::InvalidData(
+            "calibration set is empty".to_string(),
+        ));
+    }
+    if !(alpha > 0.0 && alpha < 1.0) {
+        return Err(TrainingError::InvalidData(format!(
+            "miscoverage level {alpha} is not in (0, 1)"
+        )));
+    }
+    Ok(())
+}
+
+/// Prediction with a conformal interval per output
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConformalInterval<T: Float> {
+    pub prediction: Vec<T>,
+    pub lower: Vec<T>,
+    pub upper: Vec<T>,
+}
+
+impl<T: Float> ConformalInterval<T> {
+    /// Whether every output of `target` lies within the interval
+    pub fn contains(&self, target: &[T]) -> bool {
+        target.len() == self.lower.len()
+            && target
+                .iter()
+                .zip(self.lower.iter().zip(&self.upper))
+                .all(|(&v, (&lower, &upper))| lower <= v && v <= upper)
+    }
+}
+
+/// Split conformal intervals from absolute residuals
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConformalRegressor<T: Float> {
+    alpha: f64,
+    /// Half-width of the interval of each output
+    quantiles: Vec<T>,
+    calibration_samples: usize,
+}
+
+impl<T: Float + Send + Sync> ConformalRegressor<T> {
+    /// Calibrate for miscoverage `alpha` on data the network was not trained
+    /// on
+    ///
+    /// With fewer than `1 / alpha - 1` calibration samples the intervals are
+    /// unbounded.
+    pub fn calibrate(
+        network: &Network<T>,
+        calibration: &TrainingData<T>,
+        alpha: f64,
+    ) -> Result<Self, TrainingError> {
+        check_calibration(network, calibration, alpha)?;
+        let outputs = network.num_outputs();
+        let predictions = predict_matrix(network, &calibration.inputs);
+        let quantiles = (0..outputs)
+            .map(|o| {
+                let residuals = predictions
+                    .chunks(outputs)
+                    .zip(&calibration.outputs)
+                    .map(|(prediction, target)| (prediction[o] - target[o]).abs())
+                    .collect();
+                conformal_quantile(residuals, alpha)
+            })
+            .collect();
+        Ok(Self {
+            alpha,
+            quantiles,
+            calibration_samples: calibration.inputs.len(),
+        })
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Interval half-width of each output
+    pub fn quantiles(&self) -> &[T] {
+        &self.quantiles
+    }
+
+    pub fn calibration_samples(&self) -> usize {
+        self.calibration_samples
+    }
+
+    /// Intervals for a batch of inputs
+    pub fn predict_batch(
+        &self,
+        network: &Network<T>,
+        inputs: &[Vec<T>],
+    ) -> Vec<ConformalInterval<T>> {
+        let predictions = predict_matrix(network, inputs);
+        predictions
+            .chunks(network.num_outputs().max(1))
+            .map(|prediction| ConformalInterval {
+                prediction: prediction.to_vec(),
+                lower: prediction
+                    .iter()
+                    .zip(&self.quantiles)
+                    .map(|(&p, &q)| p - q)
+                    .collect(),
+                upper: prediction
+                    .iter()
+                    .zip(&self.quantiles)
+                    .map(|(&p, &q)| p + q)
+                    .collect(),
+            })
+            .collect()
+    }
+
+    pub fn predict(&self, network: &Network<T>, input: &[T]) -> ConformalInterval<T> {
+        self.predict_batch(network, &[input.to_vec()])
+            .pop()
+            .unwrap_or(ConformalInterval {
+                prediction: Vec::new(),
+                lower: Vec::new(),
+                upper: Vec::new(),
+            })
+    }
+
+    /// Fraction of samples whose targets fall inside their intervals
+    pub fn coverage(&self, network: &Network<T>, data: &TrainingData<T>) -> f64 {
+        let covered = self
+            .predict_batch(network, &data.inputs)
+            .iter()
+            .zip(&data.outputs)
+            .filter(|(interval, target)| interval.contains(target))
+            .count();
+        covered as f64 / data.inputs.len().max(1) as f64
+    }
+}
+
+/// Class index of a target vector: the largest output, or for a single
+/// output whether it reaches 0.5
+fn target_class<T: Float>(target: &[T]) -> usize {
+    match target {
+        [single] => usize::from(*single >= cast(0.5)),
+        _ => {
+            target
+                .iter()
+                .enumerate()
+                .fold((0, T::neg_infinity()), |best, (i, &v)| {
+                    if v > best.1 {
+                        (i, v)
+                    } else {
+                        best
+                    }
+                })
+                .0
+        }
+    }
+}
+
+/// Network outputs as class scores summing to one; a single output is the
+/// probability of class 1
+fn class_scores<T: Float>(output: &[T]) -> Vec<T> {
+    match output {
+        [single] => {
+            let p = single.max(T::zero()).min(T::one());
+            vec![T::one() - p, p]
+        }
+        _ => {
+            let clipped: Vec<T> = output.iter().map(|v| v.max(T::zero())).collect();
+            let total = clipped.iter().fold(T::zero(), |sum, &v| sum + v);
+            if total > T::zero() {
+                clipped.iter().map(|&v| v / total).collect()
+            } else {
+                let uniform = T::one() / cast(output.len());
+                vec![uniform; output.len()]
+            }
+        }
+    }
+}
+
+/// Split conformal prediction sets for classification networks
+///
+/// Targets are one-hot vectors, or a single 0/1 output for binary
+/// classification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConformalClassifier<T: Float> {
+    alpha: f64,
+    threshold: T,
+    calibration_samples: usize,
+}
+
+impl<T: Float + Send + Sync> ConformalClassifier<T> {
+    /// Calibrate for miscoverage `alpha` on data the network was not trained
+    /// on
+    pub fn calibrate(
+        network: &Network<T>,
+        calibration: &TrainingData<T>,
+        alpha: f64,
+    ) -> Result<Self, TrainingError> {
+        check_calibration(network, calibration, alpha)?;
+        let predictions = predict_matrix(network, &calibration.inputs);
+        let scores = predictions
+            .chunks(network.num_outputs())
+            .zip(&calibration.outputs)
+            .map(|(prediction, target)| T::one() - class_scores(prediction)[target_class(target)])
+            .collect();
+        Ok(Self {
+            alpha,
+            threshold: conformal_quantile(scores, alpha),
+            calibration_samples: calibration.inputs.len(),
+        })
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Largest nonconformity score a class may have to enter a set
+    pub fn threshold(&self) -> T {
+        self.threshold
+    }
+
+    pub fn calibration_samples(&self) -> usize {
+        self.calibration_samples
+    }
+
+    /// Prediction sets for a batch of inputs, as ascending class indices
+    pub fn predict_sets(&self, network: &Network<T>, inputs: &[Vec<T>]) -> Vec<Vec<usize>> {
+        predict_matrix(network, inputs)
+            .chunks(network.num_outputs().max(1))
+            .map(|prediction| {
+                class_scores(prediction)
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &p)| T::one() - p <= self.threshold)
+                    .map(|(class, _)| class)
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn predict_set(&self, network: &Network<T>, input: &[T]) -> Vec<usize> {
+        self.predict_sets(network, &[input.to_vec()])
+            .pop()
+            .unwrap_or_default()
+    }
+
+    /// Fraction of samples whose true class is in their prediction set
+    pub fn coverage(&self, network: &Network<T>, data: &TrainingData<T>) -> f64 {
+        let covered = self
+            .predict_sets(network, &data.inputs)
+            .iter()
+            .zip(&data.outputs)
+            .filter(|(set, target)| set.contains(&target_class(target)))
+            .count();
+        covered as f64 / data.inputs.len().max(1) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::{IncrementalBackprop, TrainingAlgorithm};
+    use crate::NetworkBuilder;
+
+    fn regression_data(range: std::ops::Range<usize>) -> TrainingData<f64> {
+        let target = |i: usize| 0.5 + 0.3 * ((i * 37 % 101) as f64 / 101.0 - 0.5);
+        TrainingData {
+            inputs: range
+                .clone()
+                .map(|i| vec![(i % 50) as f64 / 50.0])
+                .collect(),
+            outputs: range.map(|i| vec![target(i), 1.0 - target(i)]).collect(),
+        }
+    }
+
+    #[test]
+    fn test_regression_intervals_cover_holdout() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .hidden_layer(3)
+            .output_layer(2)
+            .build();
+        let calibration = regression_data(0..199);
+        let regressor = ConformalRegressor::calibrate(&network, &calibration, 0.1).unwrap();
+        assert_eq!(regressor.quantiles().len(), 2);
+        assert!(regressor.quantiles().iter().all(|q| q.is_finite()));
+
+        // Each output is covered on its own at 1 - alpha
+        let test = regression_data(1000..1400);
+        for o in 0..2 {
+            let covered = regressor
+                .predict_batch(&network, &test.inputs)
+                .iter()
+                .zip(&test.outputs)
+                .filter(|(interval, target)| {
+                    interval.lower[o] <= target[o] && target[o] <= interval.upper[o]
+                })
+                .count();
+            assert!(covered as f64 / 400.0 >= 0.85);
+        }
+
+        // Too few calibration samples for alpha gives unbounded intervals
+        let tiny = regression_data(0..5);
+        let unbounded = ConformalRegressor::calibrate(&network, &tiny, 0.1).unwrap();
+        assert!(unbounded.quantiles()[0].is_infinite());
+        assert_eq!(unbounded.coverage(&network, &test), 1.0);
+        assert!(ConformalRegressor::calibrate(&network, &tiny, 1.5).is_err());
+    }
+
+    #[test]
+    fn test_classification_sets() {
+        let data = |range: std::ops::Range<usize>| {
+            let class = |i: usize| usize::from((i * 7 % 10) as f64 / 10.0 > 0.45);
+            TrainingData {
+                inputs: range
+                    .clone()
+                    .map(|i| vec![(i * 7 % 10) as f64 / 10.0])
+                    .collect(),
+                outputs: range
+                    .map(|i| {
+                        let mut target = vec![0.0; 2];
+                        target[class(i)] = 1.0;
+                        target
+                    })
+                    .collect(),
+            }
+        };
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .hidden_layer(3)
+            .output_layer(2)
+            .build();
+        let mut trainer = IncrementalBackprop::new(0.7);
+        for _ in 0..300 {
+            trainer.train_epoch(&mut network, &data(0..40)).unwrap();
+        }
+
+        let classifier = ConformalClassifier::calibrate(&network, &data(40..140), 0.1).unwrap();
+        assert!(classifier.coverage(&network, &data(200..400)) >= 0.85);
+        let set = classifier.predict_set(&network, &[0.0]);
+        assert!(!set.is_empty() && set.iter().all(|&c| c < 2));
+        assert_eq!(target_class(&[0.7]), 1);
+        assert_eq!(class_scores(&[0.25]), [0.75, 0.25]);
+    }
+}
@@ -0,0 +1,181 @@
+//! Structured training events
+//!
+//! Training components publish [`TrainingEvent`]s through an
+//! [`EventDispatcher`], which forwards each event to every subscribed
+//! [`TrainingCallback`]. Loggers, early stopping and dashboards can therefore
+//! be attached side by side; any subscriber can ask training to stop.
+//!
+//! A [`ProgressCallback`] instead receives one [`ProgressReport`] per epoch
+//! with the learning rate, gradient norm, elapsed time and bit fails gathered
+//! in one place.
+
+use super::{GradientNoiseEstimate, ValidationMetrics};
+use num_traits::Float;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Metrics reported at the end of an epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochMetrics<T: Float> {
+    /// Mean training error of the epoch
+    pub train_error: T,
+    /// Error on the validation set, when it was evaluated in this epoch
+    pub validation_error: Option<T>,
+}
+
+/// Event emitted during training
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrainingEvent<T: Float> {
+    /// [`Trainer::train`](super::Trainer::train) started a run of up to
+    /// `total_epochs`
+    TrainingStart { total_epochs: usize },
+    /// The run ended, whether it completed, stopped early or failed
+    TrainingEnd,
+    /// An epoch finished
+    EpochEnd {
+        epoch: usize,
+        metrics: EpochMetrics<T>,
+    },
+    /// A mini-batch (or chunk of samples) finished
+    BatchEnd {
+        epoch: usize,
+        batch: usize,
+        error: T,
+    },
+    /// The validation set was evaluated
+    ValidationEnd(ValidationMetrics<T>),
+    /// A checkpoint was written
+    CheckpointSaved { epoch: usize, path: Option<PathBuf> },
+    /// The learning rate was changed
+    LrChanged { epoch: usize, old: T, new: T },
+    /// The gradient noise scale was estimated after an epoch
+    GradientNoise {
+        epoch: usize,
+        estimate: GradientNoiseEstimate<T>,
+    },
+}
+
+/// Subscriber to training events; return `false` to request that training stops
+pub type TrainingCallback<T> = Box<dyn FnMut(&TrainingEvent<T>) -> bool + Send>;
+
+/// Summary of a completed epoch for progress bars and metric logging
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressReport<T: Float> {
+    /// Zero-based index of the completed epoch
+    pub epoch: usize,
+    pub train_error: T,
+    /// Error on the validation set, when it was evaluated in this epoch
+    pub validation_error: Option<T>,
+    /// Learning rate of the algorithm, if it has a single one
+    pub learning_rate: Option<T>,
+    /// L2 norm of the gradient applied in the epoch, if the algorithm tracks it
+    pub gradient_norm: Option<T>,
+    /// Time since training started
+    pub elapsed: Duration,
+    /// Training outputs off by more than the trainer's bit-fail limit
+    pub bit_fails: usize,
+}
+
+/// Receiver of per-epoch progress reports; return `false` to request that training stops
+pub type ProgressCallback<T> = Box<dyn FnMut(&ProgressReport<T>) -> bool + Send>;
+
+/// Handle identifying a subscription, used to unsubscribe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// Forwards training events to any number of subscribers
+pub struct EventDispatcher<T: Float> {
+    subscribers: Vec<(SubscriptionId, TrainingCallback<T>)>,
+    next_id: u64,
+}
+
+impl<T: Float> Default for EventDispatcher<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Float> EventDispatcher<T> {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Add a subscriber
+    pub fn subscribe(&mut self, callback: TrainingCallback<T>) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.subscribers.push((id, callback));
+        id
+    }
+
+    /// Remove a subscriber; returns `false` if it was not subscribed
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|(sub, _)| *sub != id);
+        self.subscribers.len() != before
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    /// Deliver an event to every subscriber
+    ///
+    /// All subscribers see the event even if an earlier one asks to stop;
+    /// returns `false` if any of them did.
+    pub fn dispatch(&mut self, event: &TrainingEvent<T>) -> bool {
+        self.subscribers
+            .iter_mut()
+            .fold(true, |keep_going, (_, callback)| {
+                callback(event) && keep_going
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn epoch_end(epoch: usize) -> TrainingEvent<f32> {
+        TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: 0.5,
+                validation_error: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_all_subscribers_receive_events() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut dispatcher = EventDispatcher::new();
+
+        let sink = Arc::clone(&log);
+        let logger = dispatcher.subscribe(Box::new(move |event| {
+            sink.lock().unwrap().push(event.clone());
+            true
+        }));
+        dispatcher.subscribe(Box::new(|event| {
+            !matches!(event, TrainingEvent::EpochEnd { epoch: 2, .. })
+        }));
+
+        assert!(dispatcher.dispatch(&epoch_end(1)));
+        assert!(!dispatcher.dispatch(&epoch_end(2)));
+        assert_eq!(log.lock().unwrap().len(), 2);
+
+        assert!(dispatcher.unsubscribe(logger));
+        assert!(!dispatcher.unsubscribe(logger));
+        dispatcher.dispatch(&epoch_end(3));
+        assert_eq!(log.lock().unwrap().len(), 2);
+        assert_eq!(dispatcher.len(), 1);
+    }
+}
@@ -4,6 +4,7 @@
 //! using WebGPU shaders for all operations to maximize performance.
 
 use super::*;
+use crate::numeric::cast;
 use crate::webgpu::backend::{BackendSelector, ComputeBackend};
 use crate::webgpu::WebGPUBackend;
 use crate::webgpu::{ComputeContext, ComputeError};
@@ -39,7 +40,7 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> BatchGpuTrain
 
         // Process through each layer using batch matrix multiplication
         for (layer_idx, layer) in network.layers.iter().skip(1).enumerate() {
-            let prev_activations = &all_activations.last().unwrap();
+            let prev_activations = &all_activations[layer_idx];
             let current_layer_size = layer.neurons.iter().filter(|n| !n.is_bias).count();
 
             // Extract weights and biases once for the entire batch
@@ -82,7 +83,7 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> BatchGpuTrain
             all_activations.push(activated_outputs);
         }
 
-        Ok(all_activations.last().unwrap().clone())
+        Ok(all_activations.pop().unwrap_or_default())
     }
 
     /// Extract weights and biases from a layer
@@ -161,7 +162,7 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> BatchGpuTrain
             }
 
             // Average gradients across batch
-            let batch_size_t = T::from(batch_size).unwrap();
+            let batch_size_t = cast::<T>(batch_size);
             for grad in weight_gradients.iter_mut() {
                 *grad = *grad / batch_size_t;
             }
@@ -334,7 +335,7 @@ pub fn gpu_batch_train_step<T: Float + Send + Sync + Default + std::fmt::Debug +
     // Get final outputs from the last layer
     let batch_outputs: Vec<Vec<T>> = batch_activations
         .iter()
-        .map(|acts| acts.last().unwrap().clone())
+        .map(|acts| acts.last().cloned().unwrap_or_default())
         .collect();
 
     // Compute output errors and total loss
@@ -354,7 +355,7 @@ pub fn gpu_batch_train_step<T: Float + Send + Sync + Default + std::fmt::Debug +
         }
 
         // Divide by number of outputs to match CPU MseError implementation
-        sample_error = sample_error / T::from(output.len()).unwrap();
+        sample_error = sample_error / cast::<T>(output.len());
         total_error = total_error + sample_error;
         batch_output_errors.push(sample_errors);
     }
@@ -381,5 +382,5 @@ pub fn gpu_batch_train_step<T: Float + Send + Sync + Default + std::fmt::Debug +
     adam_params.apply_adam_updates_with_gradients(network, &weight_gradients, &bias_gradients)?;
 
     // Return average error across the batch
-    Ok(total_error / T::from(batch_size).unwrap())
+    Ok(total_error / cast::<T>(batch_size))
 }
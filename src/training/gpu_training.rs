@@ -8,6 +8,7 @@
 //! - Automatic fallback to CPU when GPU is unavailable
 
 use super::*;
+use crate::numeric::cast;
 use crate::webgpu::backend::{
     BackendSelector, ComputeBackend, ComputeProfile, MatrixSize, OperationType,
 };
@@ -48,7 +49,7 @@ pub struct GpuAdam<T: Float + Send + Sync + Default + std::fmt::Debug + 'static>
     /// CPU-side moment estimates (temporary until full GPU implementation)
     moment_estimates: Option<HashMap<String, T>>,
 
-    callback: Option<TrainingCallback<T>>,
+    callbacks: EventDispatcher<T>,
 }
 
 /// GPU-accelerated AdamW optimizer
@@ -80,7 +81,7 @@ pub struct GpuAdamW<T: Float + Send + Sync + Default + std::fmt::Debug + 'static
     /// Performance statistics
     gpu_stats: GpuPerformanceStats,
 
-    callback: Option<TrainingCallback<T>>,
+    callbacks: EventDispatcher<T>,
 }
 
 /// GPU-accelerated batch backpropagation
@@ -104,7 +105,7 @@ pub struct GpuBatchBackprop<T: Float + Send + Sync + Default + std::fmt::Debug +
     /// Performance statistics
     gpu_stats: GpuPerformanceStats,
 
-    callback: Option<TrainingCallback<T>>,
+    callbacks: EventDispatcher<T>,
 }
 
 /// Performance statistics for GPU training
@@ -135,9 +136,9 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> GpuAdam<T> {
 
         Ok(Self {
             learning_rate,
-            beta1: T::from(0.9).unwrap(),
-            beta2: T::from(0.999).unwrap(),
-            epsilon: T::from(1e-8).unwrap(),
+            beta1: cast::<T>(0.9),
+            beta2: cast::<T>(0.999),
+            epsilon: cast::<T>(1e-8),
             weight_decay: T::zero(),
             error_function: Box::new(MseError),
             compute_context,
@@ -149,7 +150,7 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> GpuAdam<T> {
             step: 0,
             gpu_stats: GpuPerformanceStats::default(),
             moment_estimates: None,
-            callback: None,
+            callbacks: EventDispatcher::new(),
         })
     }
 
@@ -210,9 +211,7 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> GpuAdam<T> {
 
     /// Initialize GPU buffers for moment estimates
     fn initialize_gpu_buffers(&mut self, network: &Network<T>) -> Result<(), ComputeError> {
-        if self.m_weights_gpu.is_none() && self.webgpu_backend.is_some() {
-            // Clone the Arc to avoid borrow checker issues
-            let backend = self.webgpu_backend.clone().unwrap();
+        if let (None, Some(backend)) = (&self.m_weights_gpu, self.webgpu_backend.clone()) {
             let memory_manager = backend.memory_manager();
 
             // Initialize weight moment buffers
@@ -467,6 +466,7 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> TrainingAlgor
         network: &mut Network<T>,
         data: &TrainingData<T>,
     ) -> Result<T, TrainingError> {
+        data.check_shapes(network)?;
         match self.gpu_train_step(network, data) {
             Ok(error) => Ok(error),
             Err(ComputeError::GpuUnavailable) => {
@@ -536,7 +536,7 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> TrainingAlgor
                         .calculate(&current_input, desired_output);
             }
 
-            total_error / T::from(data.inputs.len()).unwrap()
+            total_error / cast::<T>(data.inputs.len())
         } else {
             // Fallback to CPU calculation
             let mut total_error = T::zero();
@@ -547,7 +547,7 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> TrainingAlgor
                 total_error = total_error + self.error_function.calculate(&output, desired_output);
             }
 
-            total_error / T::from(data.inputs.len()).unwrap()
+            total_error / cast::<T>(data.inputs.len())
         }
     }
 
@@ -579,11 +579,11 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> TrainingAlgor
         state.insert("beta2".to_string(), vec![self.beta2]);
         state.insert("epsilon".to_string(), vec![self.epsilon]);
         state.insert("weight_decay".to_string(), vec![self.weight_decay]);
-        state.insert("step".to_string(), vec![T::from(self.step).unwrap()]);
+        state.insert("step".to_string(), vec![cast::<T>(self.step)]);
 
         TrainingState {
             epoch: 0,
-            best_error: T::from(f32::MAX).unwrap(),
+            best_error: cast::<T>(f32::MAX),
             algorithm_specific: state,
         }
     }
@@ -622,7 +622,7 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> TrainingAlgor
     }
 
     fn set_callback(&mut self, callback: TrainingCallback<T>) {
-        self.callback = Some(callback);
+        self.callbacks.subscribe(callback);
     }
 
     fn call_callback(
@@ -631,12 +631,17 @@ impl<T: Float + Send + Sync + Default + std::fmt::Debug + 'static> TrainingAlgor
         network: &Network<T>,
         data: &TrainingData<T>,
     ) -> bool {
-        let error = self.calculate_error(network, data);
-        if let Some(ref mut callback) = self.callback {
-            callback(epoch, error)
-        } else {
-            true
+        if self.callbacks.is_empty() {
+            return true;
         }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
     }
 }
 
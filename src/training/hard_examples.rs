@@ -0,0 +1,224 @@
+//! Per-sample loss tracking and hard-example mining
+//!
+//! [`SampleLosses`] keeps the loss of every training sample, measured with
+//! the network at the end of each epoch. A [`Trainer`](super::Trainer)
+//! updates it after every epoch when given one through
+//! [`Trainer::with_sample_losses`](super::Trainer::with_sample_losses); the
+//! [`hardest`](SampleLosses::hardest) samples are then available for error
+//! analysis. Losses can be smoothed over epochs so a sample that is hard only
+//! once does not dominate the ranking.
+//!
+//! [`HardExampleSampler`] is a [`Curriculum`] that trains on every sample
+//! once per epoch plus extra copies of the currently hardest ones, in a
+//! seeded shuffled order.
+
+use super::{epoch_permutation, Curriculum, ErrorFunction, MseError, TrainingData};
+use crate::Network;
+use num_traits::Float;
+
+/// Loss of every sample of a data set, updated once per epoch
+pub struct SampleLosses<T: Float> {
+    error_function: Box<dyn ErrorFunction<T>>,
+    smoothing: f64,
+    losses: Vec<f64>,
+    epochs: usize,
+}
+
+impl<T: Float> Default for SampleLosses<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Float> SampleLosses<T> {
+    /// Track the mean squared error of each sample
+    pub fn new() -> Self {
+        Self::with_error_function(Box::new(MseError))
+    }
+
+    pub fn with_error_function(error_function: Box<dyn ErrorFunction<T>>) -> Self {
+        Self {
+            error_function,
+            smoothing: 1.0,
+            losses: Vec::new(),
+            epochs: 0,
+        }
+    }
+
+    /// Exponential moving average over epochs: each update keeps
+    /// `1 - alpha` of the previous loss; 1 (the default) keeps only the
+    /// latest epoch
+    pub fn with_smoothing(mut self, alpha: f64) -> Self {
+        self.smoothing = alpha.clamp(f64::EPSILON, 1.0);
+        self
+    }
+
+    /// Measure every sample of `data` with `network`
+    ///
+    /// A data set of a different size than the last one starts the history
+    /// over. NaN losses are stored as infinity, so broken samples rank as the
+    /// hardest.
+    pub fn record(&mut self, network: &Network<T>, data: &TrainingData<T>) {
+        if self.losses.len() != data.inputs.len() {
+            self.losses.clear();
+            self.epochs = 0;
+        }
+        let mut network = network.clone();
+        let current = data
+            .inputs
+            .iter()
+            .zip(&data.outputs)
+            .map(|(input, target)| {
+                let output = network.run(input);
+                let loss = self.error_function.calculate(&output, target);
+                loss.to_f64()
+                    .filter(|loss| !loss.is_nan())
+                    .unwrap_or(f64::INFINITY)
+            });
+        if self.epochs == 0 {
+            self.losses = current.collect();
+        } else {
+            let alpha = self.smoothing;
+            for (smoothed, loss) in self.losses.iter_mut().zip(current) {
+                *smoothed = if smoothed.is_finite() && loss.is_finite() {
+                    (1.0 - alpha) * *smoothed + alpha * loss
+                } else {
+                    loss
+                };
+            }
+        }
+        self.epochs += 1;
+    }
+
+    /// Loss of each sample, indexed like the data set
+    pub fn losses(&self) -> &[f64] {
+        &self.losses
+    }
+
+    /// Epochs recorded since the history started
+    pub fn epochs(&self) -> usize {
+        self.epochs
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (!self.losses.is_empty())
+            .then(|| self.losses.iter().sum::<f64>() / self.losses.len() as f64)
+    }
+
+    /// The `k` samples with the highest loss as `(index, loss)`, hardest
+    /// first; ties keep data order
+    pub fn hardest(&self, k: usize) -> Vec<(usize, f64)> {
+        let mut ranked: Vec<(usize, f64)> = self.losses.iter().copied().enumerate().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked.truncate(k);
+        ranked
+    }
+}
+
+/// Oversamples the hardest samples of the previous epoch
+pub struct HardExampleSampler<T: Float> {
+    losses: SampleLosses<T>,
+    top_k: usize,
+    copies: usize,
+    seed: u64,
+}
+
+impl<T: Float> HardExampleSampler<T> {
+    /// Train on every sample plus `copies` extra copies of the `top_k`
+    /// hardest, shuffled with a permutation derived from `seed` and the
+    /// epoch
+    pub fn new(top_k: usize, copies: usize, seed: u64) -> Self {
+        Self {
+            losses: SampleLosses::new(),
+            top_k,
+            copies,
+            seed,
+        }
+    }
+
+    /// Rank samples with `losses` instead of a plain MSE tracker
+    pub fn with_losses(mut self, losses: SampleLosses<T>) -> Self {
+        self.losses = losses;
+        self
+    }
+
+    /// Losses measured at the start of the last scheduled epoch
+    pub fn losses(&self) -> &SampleLosses<T> {
+        &self.losses
+    }
+}
+
+impl<T: Float> Curriculum<T> for HardExampleSampler<T> {
+    fn schedule(
+        &mut self,
+        epoch: usize,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> Vec<usize> {
+        self.losses.record(network, data);
+        let mut pool: Vec<usize> = (0..data.inputs.len()).collect();
+        for (index, _) in self.losses.hardest(self.top_k) {
+            pool.extend(std::iter::repeat(index).take(self.copies));
+        }
+        epoch_permutation(self.seed, epoch, pool.len())
+            .into_iter()
+            .map(|i| pool[i])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::{IncrementalBackprop, Trainer};
+    use crate::NetworkBuilder;
+
+    fn setup() -> (Network<f64>, TrainingData<f64>) {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        // Sample 3 has a target a sigmoid cannot reach
+        let data = TrainingData {
+            inputs: (0..6).map(|i| vec![i as f64 / 6.0]).collect(),
+            outputs: (0..6)
+                .map(|i| vec![if i == 3 { 4.0 } else { 0.5 }])
+                .collect(),
+        };
+        (network, data)
+    }
+
+    #[test]
+    fn test_trainer_tracks_hardest_samples() {
+        let (mut network, data) = setup();
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.3)), 5)
+            .with_sample_losses(SampleLosses::new().with_smoothing(0.5));
+        trainer.train(&mut network, &data).unwrap();
+
+        let losses = trainer.sample_losses().unwrap();
+        assert_eq!(losses.epochs(), 5);
+        assert_eq!(losses.losses().len(), 6);
+        let hardest = losses.hardest(2);
+        assert_eq!(hardest[0].0, 3);
+        assert!(hardest[0].1 >= hardest[1].1);
+        assert!(losses.mean().unwrap() < hardest[0].1);
+    }
+
+    #[test]
+    fn test_sampler_oversamples_hard_examples() {
+        let (network, data) = setup();
+        let mut sampler = HardExampleSampler::new(1, 3, 9);
+        let order = sampler.schedule(0, &network, &data);
+        assert_eq!(order.len(), 9);
+        assert_eq!(order.iter().filter(|&&i| i == 3).count(), 4);
+        assert!((0..6).all(|i| order.contains(&i)));
+        assert_ne!(order, sampler.schedule(1, &network, &data));
+
+        let mut broken = data.clone();
+        broken.inputs[5][0] = f64::NAN;
+        let mut losses = SampleLosses::new();
+        losses.record(&network, &broken);
+        assert_eq!(losses.hardest(1), [(5, f64::INFINITY)]);
+    }
+}
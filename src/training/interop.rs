@@ -0,0 +1,232 @@
+//! Conversions between [`TrainingData`] and array libraries
+//!
+//! - `ndarray` feature: `(Array2<T>, Array2<T>)` of inputs and outputs, one
+//!   row per sample
+//! - `arrow` feature: `(FixedSizeListArray, FixedSizeListArray)`, one list of
+//!   `Float32` or `Float64` values per sample
+//!
+//! Both directions fail with [`TrainingError::InvalidData`] when the two
+//! sides have different numbers of samples, or when the samples of one side
+//! do not all have the same length.
+
+use super::{TrainingData, TrainingError};
+use num_traits::Float;
+
+/// Length shared by all `rows`; 0 if there are none
+fn row_width<T>(rows: &[Vec<T>], side: &str) -> Result<usize, TrainingError> {
+    let width = rows.first().map_or(0, Vec::len);
+    match rows.iter().position(|row| row.len() != width) {
+        Some(index) => Err(TrainingError::InvalidData(format!(
+            "{side} row {index} has {} values, expected {width}",
+            rows[index].len()
+        ))),
+        None => Ok(width),
+    }
+}
+
+fn check_samples(inputs: usize, outputs: usize) -> Result<(), TrainingError> {
+    if inputs != outputs {
+        return Err(TrainingError::InvalidData(format!(
+            "{inputs} input rows but {outputs} output rows"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "ndarray")]
+mod ndarray_impls {
+    use super::*;
+    use ndarray::{Array2, ArrayBase, Data, Ix2};
+
+    fn to_rows<T: Copy, S: Data<Elem = T>>(array: &ArrayBase<S, Ix2>) -> Vec<Vec<T>> {
+        array.rows().into_iter().map(|row| row.to_vec()).collect()
+    }
+
+    fn to_array<T: Copy>(rows: &[Vec<T>], side: &str) -> Result<Array2<T>, TrainingError> {
+        let width = row_width(rows, side)?;
+        Array2::from_shape_vec((rows.len(), width), rows.concat())
+            .map_err(|e| TrainingError::InvalidData(e.to_string()))
+    }
+
+    /// Training data from `(inputs, outputs)` arrays with one row per sample
+    impl<T, S, U> TryFrom<(ArrayBase<S, Ix2>, ArrayBase<U, Ix2>)> for TrainingData<T>
+    where
+        T: Float,
+        S: Data<Elem = T>,
+        U: Data<Elem = T>,
+    {
+        type Error = TrainingError;
+
+        fn try_from(
+            (inputs, outputs): (ArrayBase<S, Ix2>, ArrayBase<U, Ix2>),
+        ) -> Result<Self, Self::Error> {
+            check_samples(inputs.nrows(), outputs.nrows())?;
+            Ok(Self {
+                inputs: to_rows(&inputs),
+                outputs: to_rows(&outputs),
+            })
+        }
+    }
+
+    /// `(inputs, outputs)` arrays with one row per sample
+    impl<T: Float> TryFrom<&TrainingData<T>> for (Array2<T>, Array2<T>) {
+        type Error = TrainingError;
+
+        fn try_from(data: &TrainingData<T>) -> Result<Self, Self::Error> {
+            check_samples(data.inputs.len(), data.outputs.len())?;
+            Ok((
+                to_array(&data.inputs, "input")?,
+                to_array(&data.outputs, "output")?,
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "arrow")]
+mod arrow_impls {
+    use super::*;
+    use crate::numeric::cast;
+    use arrow::array::{Array, AsArray, FixedSizeListArray, Float32Array, Float64Array};
+    use arrow::datatypes::{DataType, Field, Float32Type, Float64Type};
+    use std::sync::Arc;
+
+    fn invalid(message: String) -> TrainingError {
+        TrainingError::InvalidData(message)
+    }
+
+    /// Rows of a list array of `Float32` or `Float64` values without nulls
+    fn to_rows<T: Float>(
+        array: &FixedSizeListArray,
+        side: &str,
+    ) -> Result<Vec<Vec<T>>, TrainingError> {
+        if array.null_count() > 0 {
+            return Err(invalid(format!("{side} array has null rows")));
+        }
+        (0..array.len())
+            .map(|i| {
+                let row = array.value(i);
+                if row.null_count() > 0 {
+                    return Err(invalid(format!("{side} row {i} has null values")));
+                }
+                match row.data_type() {
+                    DataType::Float32 => Ok(row
+                        .as_primitive::<Float32Type>()
+                        .values()
+                        .iter()
+                        .map(|&v| cast(v))
+                        .collect()),
+                    DataType::Float64 => Ok(row
+                        .as_primitive::<Float64Type>()
+                        .values()
+                        .iter()
+                        .map(|&v| cast(v))
+                        .collect()),
+                    other => Err(invalid(format!("{side} values are {other}, not floats"))),
+                }
+            })
+            .collect()
+    }
+
+    /// Training data from `(inputs, outputs)` list arrays with one list per
+    /// sample; `Float32` and `Float64` values are converted to `T`
+    impl<T: Float> TryFrom<(&FixedSizeListArray, &FixedSizeListArray)> for TrainingData<T> {
+        type Error = TrainingError;
+
+        fn try_from(
+            (inputs, outputs): (&FixedSizeListArray, &FixedSizeListArray),
+        ) -> Result<Self, Self::Error> {
+            check_samples(inputs.len(), outputs.len())?;
+            Ok(Self {
+                inputs: to_rows(inputs, "input")?,
+                outputs: to_rows(outputs, "output")?,
+            })
+        }
+    }
+
+    macro_rules! to_arrow {
+        ($float:ty, $array:ty, $data_type:expr) => {
+            /// `(inputs, outputs)` list arrays with one list per sample
+            impl TryFrom<&TrainingData<$float>> for (FixedSizeListArray, FixedSizeListArray) {
+                type Error = TrainingError;
+
+                fn try_from(data: &TrainingData<$float>) -> Result<Self, Self::Error> {
+                    check_samples(data.inputs.len(), data.outputs.len())?;
+                    let list = |rows: &[Vec<$float>], side: &str| {
+                        let width = i32::try_from(row_width(rows, side)?)
+                            .map_err(|_| invalid(format!("{side} rows are too long")))?;
+                        let field = Arc::new(Field::new("item", $data_type, false));
+                        let values = Arc::new(<$array>::from(rows.concat()));
+                        FixedSizeListArray::try_new(field, width, values, None)
+                            .map_err(|e| invalid(e.to_string()))
+                    };
+                    Ok((list(&data.inputs, "input")?, list(&data.outputs, "output")?))
+                }
+            }
+        };
+    }
+
+    to_arrow!(f32, Float32Array, DataType::Float32);
+    to_arrow!(f64, Float64Array, DataType::Float64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::xor;
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_ndarray_round_trip() {
+        use ndarray::{array, Array2};
+
+        let data = xor();
+        let (inputs, outputs): (Array2<f32>, Array2<f32>) = (&data).try_into().unwrap();
+        assert_eq!(inputs.dim(), (4, 2));
+        assert_eq!(outputs.column(0).to_vec(), vec![0.0, 1.0, 1.0, 0.0]);
+        let back = TrainingData::try_from((inputs.view(), outputs)).unwrap();
+        assert_eq!((back.inputs, back.outputs), (data.inputs, data.outputs));
+
+        let mismatched = TrainingData::<f64>::try_from((array![[1.0], [2.0]], array![[1.0]]));
+        assert!(mismatched.is_err());
+        let ragged = TrainingData {
+            inputs: vec![vec![0.0, 1.0], vec![2.0]],
+            outputs: vec![vec![1.0], vec![0.0]],
+        };
+        assert!(<(Array2<f64>, Array2<f64>)>::try_from(&ragged).is_err());
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_arrow_round_trip() {
+        use arrow::array::{Array, FixedSizeListArray};
+        use arrow::datatypes::{DataType, Float64Type};
+
+        let data = xor();
+        let (inputs, outputs): (FixedSizeListArray, FixedSizeListArray) =
+            (&data).try_into().unwrap();
+        assert_eq!((inputs.len(), inputs.value_length()), (4, 2));
+        let back = TrainingData::<f32>::try_from((&inputs, &outputs)).unwrap();
+        assert_eq!((back.inputs, back.outputs), (data.inputs, data.outputs));
+
+        // Float64 lists, as written by most data tools, convert to f32
+        let rows = [
+            Some(vec![Some(0.5), Some(1.5)]),
+            Some(vec![Some(2.5), Some(3.5)]),
+        ];
+        let wide = FixedSizeListArray::from_iter_primitive::<Float64Type, _, _>(rows, 2);
+        let labels = FixedSizeListArray::from_iter_primitive::<Float64Type, _, _>(
+            [Some(vec![Some(1.0)]), Some(vec![Some(0.0)])],
+            1,
+        );
+        assert_eq!(wide.value_type(), DataType::Float64);
+        let converted = TrainingData::<f32>::try_from((&wide, &labels)).unwrap();
+        assert_eq!(converted.inputs[1], vec![2.5, 3.5]);
+
+        let with_null = FixedSizeListArray::from_iter_primitive::<Float64Type, _, _>(
+            [Some(vec![Some(1.0)]), Some(vec![None])],
+            1,
+        );
+        assert!(TrainingData::<f32>::try_from((&wide, &with_null)).is_err());
+        assert!(TrainingData::<f32>::try_from((&wide, &labels.slice(0, 1))).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! Lion optimizer (evolved sign momentum)
+//!
+//! Lion (Chen et al., 2023) keeps a single momentum buffer and moves every
+//! parameter by exactly the learning rate in the direction of the sign of an
+//! interpolation between momentum and gradient. It uses half of Adam's
+//! optimizer memory and usually wants a learning rate 3-10x smaller than Adam,
+//! with a correspondingly larger decoupled weight decay.
+
+#![allow(clippy::needless_range_loop)]
+
+use super::adam::{batch_gradients, restore_buffers, save_buffers};
+use super::centralization::centralize_gradients;
+use super::statistics::{buffer_bytes, gradient_norm};
+use super::*;
+use crate::numeric::cast;
+use num_traits::Float;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Sign-momentum optimizer with decoupled weight decay
+pub struct Lion<T: Float + Send + Default> {
+    learning_rate: T,
+    beta1: T,
+    beta2: T,
+    regularization: Regularization<T>,
+    options: TrainingOptions,
+    error_function: Box<dyn ErrorFunction<T>>,
+
+    m_weights: Vec<Vec<T>>,
+    m_biases: Vec<Vec<T>>,
+
+    callbacks: EventDispatcher<T>,
+    statistics: TrainingStatistics<T>,
+}
+
+impl<T: Float + Send + Default> Lion<T> {
+    pub fn new(learning_rate: T) -> Self {
+        Self {
+            learning_rate,
+            beta1: cast(0.9),
+            beta2: cast(0.99),
+            regularization: Regularization::decoupled(T::zero()),
+            options: TrainingOptions::new(),
+            error_function: Box::new(MseError),
+            m_weights: Vec::new(),
+            m_biases: Vec::new(),
+            callbacks: EventDispatcher::new(),
+            statistics: TrainingStatistics::new(),
+        }
+    }
+
+    /// Interpolation factor between momentum and gradient for the update
+    pub fn with_beta1(mut self, beta1: T) -> Self {
+        self.beta1 = beta1;
+        self
+    }
+
+    /// Decay of the momentum buffer
+    pub fn with_beta2(mut self, beta2: T) -> Self {
+        self.beta2 = beta2;
+        self
+    }
+
+    /// Set weight decay (decoupled, applied to weights but not biases)
+    pub fn with_weight_decay(mut self, weight_decay: T) -> Self {
+        self.regularization = Regularization::decoupled(weight_decay);
+        self
+    }
+
+    /// Set the weight decay configuration
+    pub fn with_regularization(mut self, regularization: Regularization<T>) -> Self {
+        self.regularization = regularization;
+        self
+    }
+
+    /// Set error function
+    pub fn with_error_function(mut self, error_function: Box<dyn ErrorFunction<T>>) -> Self {
+        self.error_function = error_function;
+        self
+    }
+
+    /// Apply one Lion step for the given mean gradients
+    fn update_parameters(
+        &mut self,
+        network: &mut Network<T>,
+        weight_gradients: &[Vec<T>],
+        bias_gradients: &[Vec<T>],
+    ) {
+        let zeros = |gradients: &[Vec<T>]| -> Vec<Vec<T>> {
+            gradients.iter().map(|g| vec![T::zero(); g.len()]).collect()
+        };
+        if self.m_weights.is_empty() {
+            self.m_weights = zeros(weight_gradients);
+            self.m_biases = zeros(bias_gradients);
+        }
+
+        let one = T::one();
+        let (beta1, beta2, learning_rate) = (self.beta1, self.beta2, self.learning_rate);
+        let step = |m: &mut Vec<Vec<T>>, gradients: &[Vec<T>]| {
+            let mut updates = zeros(gradients);
+            for layer_idx in 0..gradients.len() {
+                for i in 0..gradients[layer_idx].len() {
+                    let grad = gradients[layer_idx][i];
+                    let m = &mut m[layer_idx][i];
+                    let direction = beta1 * *m + (one - beta1) * grad;
+                    updates[layer_idx][i] = -learning_rate * sign(direction);
+                    *m = beta2 * *m + (one - beta2) * grad;
+                }
+            }
+            updates
+        };
+
+        let weight_updates = step(&mut self.m_weights, weight_gradients);
+        let bias_updates = step(&mut self.m_biases, bias_gradients);
+        super::helpers::apply_updates_to_network(network, &weight_updates, &bias_updates);
+    }
+}
+
+/// Sign with `sign(0) = 0`, unlike `Float::signum`
+fn sign<T: Float>(x: T) -> T {
+    if x > T::zero() {
+        T::one()
+    } else if x < T::zero() {
+        -T::one()
+    } else {
+        T::zero()
+    }
+}
+
+impl<T: Float + Send + Sync + Default> TrainingAlgorithm<T> for Lion<T> {
+    fn train_epoch(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<T, TrainingError> {
+        if let Some(mut batches) = self.options.mini_batches.take() {
+            let error = batches.train_epoch(self, network, data);
+            self.options.mini_batches = Some(batches);
+            return error;
+        }
+        data.check_shapes(network)?;
+        let trimmed = self
+            .options
+            .trimmed_loss
+            .trim(network, data, self.error_function.as_ref());
+        let data = trimmed.as_ref();
+        let epoch_start = Instant::now();
+
+        let (error, mut weight_gradients, mut bias_gradients) =
+            batch_gradients(network, data, self.error_function.as_ref());
+        if self.options.gradient_centralization {
+            centralize_gradients(network, &mut weight_gradients);
+        }
+        self.regularization
+            .add_to_gradients(network, &mut weight_gradients, &mut bias_gradients);
+        self.regularization
+            .decay_weights(network, self.learning_rate);
+        self.update_parameters(network, &weight_gradients, &bias_gradients);
+
+        self.statistics.record_epoch(
+            epoch_start.elapsed(),
+            data.inputs.len(),
+            gradient_norm(&weight_gradients, &bias_gradients),
+            buffer_bytes(&[
+                &weight_gradients,
+                &bias_gradients,
+                &self.m_weights,
+                &self.m_biases,
+            ]),
+        );
+        self.statistics.record_gradients(weight_gradients);
+
+        Ok(error)
+    }
+
+    fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
+        super::evaluation::batch_error(network, data, self.error_function.as_ref())
+    }
+
+    fn count_bit_fails(
+        &self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+        bit_fail_limit: T,
+    ) -> usize {
+        super::evaluation::batch_bit_fails(network, data, bit_fail_limit)
+    }
+
+    fn save_state(&self) -> TrainingState<T> {
+        let mut state = HashMap::new();
+        state.insert("learning_rate".to_string(), vec![self.learning_rate]);
+        state.insert("beta1".to_string(), vec![self.beta1]);
+        state.insert("beta2".to_string(), vec![self.beta2]);
+        self.regularization.save(&mut state);
+        save_buffers(&mut state, "m_weights", &self.m_weights);
+        save_buffers(&mut state, "m_biases", &self.m_biases);
+
+        TrainingState {
+            epoch: 0,
+            best_error: cast(f32::MAX),
+            algorithm_specific: state,
+        }
+    }
+
+    fn restore_state(&mut self, state: TrainingState<T>) {
+        let state = state.algorithm_specific;
+        let scalar = |name: &str| state.get(name).and_then(|v| v.first()).copied();
+        if let Some(learning_rate) = scalar("learning_rate") {
+            self.learning_rate = learning_rate;
+        }
+        if let Some(beta1) = scalar("beta1") {
+            self.beta1 = beta1;
+        }
+        if let Some(beta2) = scalar("beta2") {
+            self.beta2 = beta2;
+        }
+        self.regularization.restore(&state);
+        self.m_weights = restore_buffers(&state, "m_weights");
+        self.m_biases = restore_buffers(&state, "m_biases");
+    }
+
+    fn learning_rate(&self) -> Option<T> {
+        Some(self.learning_rate)
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.learning_rate = rate;
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.statistics.last_gradient_norm()
+    }
+
+    fn last_weight_gradients(&self) -> Option<&[Vec<T>]> {
+        self.statistics.last_weight_gradients()
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.options.cancellation = token;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.options.is_cancelled()
+    }
+
+    fn set_callback(&mut self, callback: TrainingCallback<T>) {
+        self.callbacks.subscribe(callback);
+    }
+
+    fn call_callback(
+        &mut self,
+        epoch: usize,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> bool {
+        if self.callbacks.is_empty() {
+            return true;
+        }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
+    }
+}
+
+impl<T: Float + Send + Default> WithTrainingOptions for Lion<T> {
+    fn training_options(&self) -> &TrainingOptions {
+        &self.options
+    }
+
+    fn training_options_mut(&mut self) -> &mut TrainingOptions {
+        &mut self.options
+    }
+}
+
+impl<T: Float + Send + Sync + Default> AdvancedTrainingAlgorithm<T> for Lion<T> {
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        &self.statistics
+    }
+
+    fn reset_statistics(&mut self) {
+        self.statistics.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_steps_have_fixed_magnitude() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .output_layer(1)
+            .build();
+        let before = network.get_weights();
+
+        let mut lion = Lion::new(0.01);
+        lion.update_parameters(&mut network, &[vec![-3.0]], &[vec![1e-6]]);
+        let after = network.get_weights();
+        assert!((after[0] - before[0] - 0.01).abs() < 1e-12);
+        assert!((after[1] - before[1] + 0.01).abs() < 1e-12);
+
+        // The momentum still points the same way, so a zero gradient keeps moving
+        lion.update_parameters(&mut network, &[vec![0.0]], &[vec![0.0]]);
+        assert!((network.get_weights()[0] - after[0] - 0.01).abs() < 1e-12);
+    }
+}
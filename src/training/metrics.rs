@@ -0,0 +1,106 @@
+//! Recording of training metrics and diagnostics
+
+use std::sync::{Arc, Mutex};
+
+/// Sink for scalar training metrics and diagnostic warnings
+pub trait MetricsRecorder: Send {
+    /// Record a named scalar at a training step (usually the epoch)
+    fn record_scalar(&mut self, name: &str, step: usize, value: f64);
+
+    /// Record a diagnostic warning
+    fn record_warning(&mut self, step: usize, message: &str);
+}
+
+/// A recorded scalar value
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalarRecord {
+    pub name: String,
+    pub step: usize,
+    pub value: f64,
+}
+
+/// Recorder that keeps everything in memory
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryRecorder {
+    pub scalars: Vec<ScalarRecord>,
+    pub warnings: Vec<(usize, String)>,
+}
+
+impl InMemoryRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All values recorded under `name`, in step order
+    pub fn series(&self, name: &str) -> Vec<(usize, f64)> {
+        self.scalars
+            .iter()
+            .filter(|r| r.name == name)
+            .map(|r| (r.step, r.value))
+            .collect()
+    }
+}
+
+impl MetricsRecorder for InMemoryRecorder {
+    fn record_scalar(&mut self, name: &str, step: usize, value: f64) {
+        self.scalars.push(ScalarRecord {
+            name: name.to_string(),
+            step,
+            value,
+        });
+    }
+
+    fn record_warning(&mut self, step: usize, message: &str) {
+        self.warnings.push((step, message.to_string()));
+    }
+}
+
+/// Shared recorder, so metrics can be inspected while a trainer owns the handle
+impl<R: MetricsRecorder> MetricsRecorder for Arc<Mutex<R>> {
+    fn record_scalar(&mut self, name: &str, step: usize, value: f64) {
+        if let Ok(mut recorder) = self.lock() {
+            recorder.record_scalar(name, step, value);
+        }
+    }
+
+    fn record_warning(&mut self, step: usize, message: &str) {
+        if let Ok(mut recorder) = self.lock() {
+            recorder.record_warning(step, message);
+        }
+    }
+}
+
+/// Recorder that forwards metrics to the `log` crate
+#[cfg(feature = "logging")]
+#[derive(Debug, Clone)]
+pub struct LogRecorder {
+    level: log::Level,
+}
+
+#[cfg(feature = "logging")]
+impl Default for LogRecorder {
+    fn default() -> Self {
+        Self {
+            level: log::Level::Debug,
+        }
+    }
+}
+
+#[cfg(feature = "logging")]
+impl LogRecorder {
+    /// Log scalars at `level`; warnings are always logged at `Warn`
+    pub fn with_level(level: log::Level) -> Self {
+        Self { level }
+    }
+}
+
+#[cfg(feature = "logging")]
+impl MetricsRecorder for LogRecorder {
+    fn record_scalar(&mut self, name: &str, step: usize, value: f64) {
+        log::log!(self.level, "[step {step}] {name} = {value}");
+    }
+
+    fn record_warning(&mut self, step: usize, message: &str) {
+        log::warn!("[step {step}] {message}");
+    }
+}
@@ -7,9 +7,17 @@
 //! - Quickprop
 //!
 //! All training algorithms implement the `TrainingAlgorithm` trait for extensibility.
+//!
+//! Training code does not panic on bad input: malformed data is rejected
+//! with [`TrainingError::InvalidData`] (see [`TrainingData::check_shapes`]),
+//! and `unwrap`/`expect` are denied outside tests. `TrainingError` converts
+//! into [`RuvFannError`](crate::RuvFannError), so FFI and WASM bindings can
+//! propagate it with `?` into a `RuvFannResult`.
 
 #![allow(clippy::needless_range_loop)]
 
+use crate::memory_manager::NumaTopology;
+use crate::numeric::cast;
 use crate::Network;
 use num_traits::Float;
 use std::collections::HashMap;
@@ -24,8 +32,58 @@ pub struct TrainingData<T: Float> {
     pub outputs: Vec<Vec<T>>,
 }
 
+impl<T: Float> TrainingData<T> {
+    /// Check that there is one output per input and that every sample
+    /// matches the input and output widths of `network`
+    ///
+    /// Every training algorithm runs this before touching the network, so
+    /// malformed data is reported as [`TrainingError::InvalidData`] instead
+    /// of panicking on an out-of-bounds index. Recurrent networks fail with
+    /// [`TrainingError::NetworkError`], since only [`Bptt`] trains their
+    /// context weights.
+    pub fn check_shapes(&self, network: &Network<T>) -> Result<(), TrainingError> {
+        network
+            .check_feed_forward()
+            .map_err(|e| TrainingError::NetworkError(e.to_string()))?;
+        self.check_widths(network)
+    }
+
+    /// [`Self::check_shapes`] without rejecting recurrent networks
+    pub(crate) fn check_widths(&self, network: &Network<T>) -> Result<(), TrainingError> {
+        if self.inputs.len() != self.outputs.len() {
+            return Err(TrainingError::InvalidData(format!(
+                "{} inputs but {} outputs",
+                self.inputs.len(),
+                self.outputs.len()
+            )));
+        }
+        let (num_inputs, num_outputs) = (network.num_inputs(), network.num_outputs());
+        for (i, (input, output)) in self.inputs.iter().zip(&self.outputs).enumerate() {
+            if input.len() != num_inputs {
+                return Err(TrainingError::InvalidData(format!(
+                    "sample {i} has {} inputs, the network expects {num_inputs}",
+                    input.len()
+                )));
+            }
+            if output.len() != num_outputs {
+                return Err(TrainingError::InvalidData(format!(
+                    "sample {i} has {} outputs, the network expects {num_outputs}",
+                    output.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Options for parallel training
+///
+/// Start from [`Default`] and set fields with the `with_*` methods; more
+/// fields may be added without a breaking release. [`DataParallel`] takes
+/// them through [`DataParallel::with_options`], rayon-based work through
+/// [`Self::build_thread_pool`].
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct ParallelTrainingOptions {
     /// Number of threads to use (0 = use all available cores)
     pub num_threads: usize,
@@ -35,6 +93,8 @@ pub struct ParallelTrainingOptions {
     pub parallel_gradients: bool,
     /// Whether to use parallel error calculation
     pub parallel_error_calc: bool,
+    /// Placement of worker threads on multi-socket machines
+    pub numa_policy: NumaPolicy,
 }
 
 impl Default for ParallelTrainingOptions {
@@ -44,8 +104,73 @@ impl Default for ParallelTrainingOptions {
             batch_size: 32,
             parallel_gradients: true,
             parallel_error_calc: true,
+            numa_policy: NumaPolicy::Disabled,
+        }
+    }
+}
+
+impl ParallelTrainingOptions {
+    /// Number of threads; 0 uses all available cores, or with
+    /// [`NumaPolicy::Node`] all CPUs of that node
+    pub fn with_threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = num_threads;
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn with_parallel_gradients(mut self, enabled: bool) -> Self {
+        self.parallel_gradients = enabled;
+        self
+    }
+
+    pub fn with_parallel_error_calc(mut self, enabled: bool) -> Self {
+        self.parallel_error_calc = enabled;
+        self
+    }
+
+    pub fn with_numa_policy(mut self, policy: NumaPolicy) -> Self {
+        self.numa_policy = policy;
+        self
+    }
+
+    /// Thread count `num_threads` stands for on `topology`
+    pub(crate) fn threads(&self, topology: &NumaTopology) -> usize {
+        let threads = match (self.num_threads, self.numa_policy) {
+            (0, NumaPolicy::Node(id)) => topology.node(id).map_or(0, |node| node.cpus.len()),
+            (n, _) => n,
+        };
+        match threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
         }
     }
+
+    /// Rayon pool with `num_threads` workers placed according to
+    /// `numa_policy`
+    ///
+    /// Run training inside [`rayon::ThreadPool::install`] so the parallel
+    /// forward passes and reductions use these workers. With
+    /// [`NumaPolicy::Node`] and `num_threads == 0` the pool gets one worker per
+    /// CPU of that node.
+    #[cfg(feature = "parallel")]
+    pub fn build_thread_pool(&self) -> Result<rayon::ThreadPool, TrainingError> {
+        let topology = NumaTopology::detect();
+        let threads = self.threads(&topology);
+        let policy = self.numa_policy;
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .start_handler(move |worker| {
+                if let Some(node) = topology.worker_node(policy, worker, threads) {
+                    topology.bind_current_thread(node);
+                }
+            })
+            .build()
+            .map_err(|e| TrainingError::TrainingFailed(e.to_string()))
+    }
 }
 
 /// Error types for training operations
@@ -68,6 +193,31 @@ pub trait ErrorFunction<T: Float>: Send + Sync {
 
     /// Calculate the derivative of the error function
     fn derivative(&self, actual: T, desired: T) -> T;
+
+    /// Derivatives for a whole output vector, as used by backpropagation
+    ///
+    /// Defaults to [`Self::derivative`] per output; losses that treat
+    /// outputs differently, such as [`MultiTaskLoss`], override it.
+    fn output_derivatives(&self, actual: &[T], desired: &[T]) -> Vec<T> {
+        actual
+            .iter()
+            .zip(desired)
+            .map(|(&a, &d)| self.derivative(a, d))
+            .collect()
+    }
+
+    /// Mean error over `rows` samples stored as row-major matrices
+    fn calculate_batch(&self, predictions: &[T], targets: &[T], rows: usize) -> T {
+        if rows == 0 {
+            return T::zero();
+        }
+        let cols = predictions.len() / rows;
+        let total = predictions
+            .chunks(cols.max(1))
+            .zip(targets.chunks(cols.max(1)))
+            .fold(T::zero(), |acc, (p, t)| acc + self.calculate(p, t));
+        total / cast(rows)
+    }
 }
 
 /// Mean Squared Error (MSE)
@@ -84,11 +234,18 @@ impl<T: Float> ErrorFunction<T> for MseError {
                 diff * diff
             })
             .fold(T::zero(), |acc, x| acc + x);
-        sum / T::from(actual.len()).unwrap()
+        sum / cast(actual.len())
     }
 
     fn derivative(&self, actual: T, desired: T) -> T {
-        T::from(2.0).unwrap() * (actual - desired)
+        cast::<T>(2.0) * (actual - desired)
+    }
+
+    fn calculate_batch(&self, predictions: &[T], targets: &[T], _rows: usize) -> T {
+        if predictions.is_empty() {
+            return T::zero();
+        }
+        evaluation::squared_error_sum(predictions, targets) / cast(predictions.len())
     }
 }
 
@@ -103,7 +260,7 @@ impl<T: Float> ErrorFunction<T> for MaeError {
             .zip(desired.iter())
             .map(|(&a, &d)| (a - d).abs())
             .fold(T::zero(), |acc, x| acc + x);
-        sum / T::from(actual.len()).unwrap()
+        sum / cast(actual.len())
     }
 
     fn derivative(&self, actual: T, desired: T) -> T {
@@ -115,6 +272,13 @@ impl<T: Float> ErrorFunction<T> for MaeError {
             T::zero()
         }
     }
+
+    fn calculate_batch(&self, predictions: &[T], targets: &[T], _rows: usize) -> T {
+        if predictions.is_empty() {
+            return T::zero();
+        }
+        evaluation::absolute_error_sum(predictions, targets) / cast(predictions.len())
+    }
 }
 
 /// Tanh Error Function
@@ -132,13 +296,32 @@ impl<T: Float> ErrorFunction<T> for TanhError {
                 tanh_diff * tanh_diff
             })
             .fold(T::zero(), |acc, x| acc + x);
-        sum / T::from(actual.len()).unwrap()
+        sum / cast(actual.len())
     }
 
     fn derivative(&self, actual: T, desired: T) -> T {
         let diff = actual - desired;
         let tanh_diff = diff.tanh();
-        T::from(2.0).unwrap() * tanh_diff * (T::one() - tanh_diff * tanh_diff)
+        cast::<T>(2.0) * tanh_diff * (T::one() - tanh_diff * tanh_diff)
+    }
+}
+
+/// Shared losses, so a caller can keep a handle to a loss an optimizer owns
+impl<T: Float, E: ErrorFunction<T> + ?Sized> ErrorFunction<T> for std::sync::Arc<E> {
+    fn calculate(&self, actual: &[T], desired: &[T]) -> T {
+        (**self).calculate(actual, desired)
+    }
+
+    fn derivative(&self, actual: T, desired: T) -> T {
+        (**self).derivative(actual, desired)
+    }
+
+    fn output_derivatives(&self, actual: &[T], desired: &[T]) -> Vec<T> {
+        (**self).output_derivatives(actual, desired)
+    }
+
+    fn calculate_batch(&self, predictions: &[T], targets: &[T], rows: usize) -> T {
+        (**self).calculate_batch(predictions, targets, rows)
     }
 }
 
@@ -164,7 +347,7 @@ impl<T: Float> ExponentialDecay<T> {
 
 impl<T: Float> LearningRateSchedule<T> for ExponentialDecay<T> {
     fn get_rate(&mut self, epoch: usize) -> T {
-        self.initial_rate * self.decay_rate.powi(epoch as i32)
+        self.initial_rate * self.decay_rate.powf(cast(epoch))
     }
 }
 
@@ -176,11 +359,12 @@ pub struct StepDecay<T: Float> {
 }
 
 impl<T: Float> StepDecay<T> {
+    /// `epochs_per_drop` of 0 is treated as 1
     pub fn new(initial_rate: T, drop_rate: T, epochs_per_drop: usize) -> Self {
         Self {
             initial_rate,
             drop_rate,
-            epochs_per_drop,
+            epochs_per_drop: epochs_per_drop.max(1),
         }
     }
 }
@@ -188,7 +372,7 @@ impl<T: Float> StepDecay<T> {
 impl<T: Float> LearningRateSchedule<T> for StepDecay<T> {
     fn get_rate(&mut self, epoch: usize) -> T {
         let drops = epoch / self.epochs_per_drop;
-        self.initial_rate * self.drop_rate.powi(drops as i32)
+        self.initial_rate * self.drop_rate.powf(cast(drops))
     }
 }
 
@@ -248,9 +432,6 @@ impl<T: Float> StopCriteria<T> for BitFailStopCriteria<T> {
     }
 }
 
-/// Callback function type for training progress
-pub type TrainingCallback<T> = Box<dyn FnMut(usize, T) -> bool + Send>;
-
 /// Main trait for training algorithms
 pub trait TrainingAlgorithm<T: Float>: Send {
     /// Train for one epoch
@@ -277,19 +458,113 @@ pub trait TrainingAlgorithm<T: Float>: Send {
     /// Restore training state
     fn restore_state(&mut self, state: TrainingState<T>);
 
-    /// Set a callback function
+    /// Current learning rate, for algorithms that have a single one
+    fn learning_rate(&self) -> Option<T> {
+        None
+    }
+
+    /// Replace the learning rate; ignored by algorithms without a single one
+    fn set_learning_rate(&mut self, _rate: T) {}
+
+    /// Poll `token` between the mini-batches, chunks or samples of an epoch
+    /// and end the epoch early once it is cancelled, returning the error of
+    /// the samples seen; ignored by algorithms that do not poll
+    fn set_cancellation(&mut self, _token: Option<CancellationToken>) {}
+
+    /// Whether the token passed to [`Self::set_cancellation`] is cancelled
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+
+    /// L2 norm of the gradient applied in the most recent epoch, if tracked
+    fn last_gradient_norm(&self) -> Option<T> {
+        None
+    }
+
+    /// Per-layer weight gradients applied in the most recent epoch, if kept
+    fn last_weight_gradients(&self) -> Option<&[Vec<T>]> {
+        None
+    }
+
+    /// Subscribe a callback to the algorithm's training events
     fn set_callback(&mut self, callback: TrainingCallback<T>);
 
-    /// Call the callback if set
+    /// Emit an `EpochEnd` event to all subscribers; returns `false` if one asks to stop
     fn call_callback(&mut self, epoch: usize, network: &Network<T>, data: &TrainingData<T>)
         -> bool;
+
+    /// Train one pass over a [`DataSource`], a chunk at a time
+    ///
+    /// Each chunk goes through [`Self::train_epoch`], so only one chunk has
+    /// to be in memory and batch algorithms update once per chunk. Returns
+    /// the mean of the chunk errors weighted by chunk size.
+    fn train_epoch_from(
+        &mut self,
+        network: &mut Network<T>,
+        source: &mut dyn DataSource<T>,
+    ) -> Result<T, TrainingError> {
+        source.reset()?;
+        let (mut total, mut samples) = (T::zero(), 0usize);
+        while let Some(chunk) = source.next_chunk()? {
+            let error = self.train_epoch(network, &chunk)?;
+            total = total + error * cast(chunk.inputs.len());
+            samples += chunk.inputs.len();
+        }
+        if samples == 0 {
+            return Err(TrainingError::InvalidData(
+                "data source yielded no samples".to_string(),
+            ));
+        }
+        Ok(total / cast(samples))
+    }
 }
 
 // Module declarations for specific algorithms
 mod adam;
+mod adversarial;
 mod backprop;
+mod batch_size;
+mod batching;
+mod best_model;
+mod bptt;
+mod cancellation;
+mod centralization;
+mod checkpoint;
+mod curriculum;
+mod data_parallel;
+mod early_stopping;
+pub mod evaluation;
+mod events;
+mod hard_examples;
+#[cfg(any(feature = "ndarray", feature = "arrow"))]
+mod interop;
+mod lion;
+mod metrics;
+mod multi_task;
+mod nadam;
+mod noise_scale;
+mod options;
+mod ordering;
+mod pipeline;
+mod privacy;
+#[cfg(feature = "progress")]
+mod progress;
 mod quickprop;
+mod radam;
+mod realtime;
+mod regularization;
 mod rprop;
+#[cfg(feature = "parallel")]
+mod scheduler;
+mod split;
+mod statistics;
+mod streaming;
+mod supervisor;
+mod trainer;
+mod trimming;
+mod trust_ratio;
+pub mod validation;
+mod weight_monitor;
 
 // GPU training module (when GPU features are enabled)
 #[cfg(feature = "gpu")]
@@ -300,10 +575,61 @@ mod gpu_batch_training;
 mod gpu_training;
 
 // Re-export main types
+pub use crate::memory_manager::NumaPolicy;
 pub use adam::{Adam, AdamW};
+pub use adversarial::{
+    evaluate_robustness, AdversarialAttack, AdversarialGenerator, AdversarialTraining,
+    RobustnessReport,
+};
 pub use backprop::{BatchBackprop, IncrementalBackprop};
+pub use batch_size::{AdaptiveBatchOptions, AdaptiveBatchSize, BatchSizeStep};
+pub use batching::BatchIterator;
+pub use best_model::{BestModelKeeper, KeeperDecision};
+pub use bptt::Bptt;
+pub use cancellation::CancellationToken;
+pub use centralization::centralize_gradients;
+pub use checkpoint::{Checkpoint, CheckpointManager, DEFAULT_KEEP_LAST};
+pub use curriculum::{Curriculum, DifficultyCurriculum, DifficultyFn};
+pub use data_parallel::DataParallel;
+pub use early_stopping::EarlyStoppingTrainer;
+pub use events::{
+    EpochMetrics, EventDispatcher, ProgressCallback, ProgressReport, SubscriptionId,
+    TrainingCallback, TrainingEvent,
+};
+pub use hard_examples::{HardExampleSampler, SampleLosses};
+pub use lion::Lion;
+#[cfg(feature = "logging")]
+pub use metrics::LogRecorder;
+pub use metrics::{InMemoryRecorder, MetricsRecorder, ScalarRecord};
+pub use multi_task::{MultiTaskLoss, TaskHead, TaskWeighting};
+pub use nadam::Nadam;
+pub use noise_scale::{GradientNoiseEstimate, NoiseScaleEstimator};
+pub use options::{TrainingOptions, WithTrainingOptions};
+pub use ordering::{epoch_permutation, OrderingLog};
+pub use pipeline::PipelineParallel;
+pub use privacy::{DifferentialPrivacy, PrivacyAccountant};
+#[cfg(feature = "progress")]
+pub use progress::{ProgressSink, ProgressTracker, ProgressUpdate, StderrProgress};
 pub use quickprop::Quickprop;
+pub use radam::RAdam;
+pub use realtime::{LatencyStats, RealtimeStep, RealtimeTrainer};
+pub use regularization::{DecayMode, Regularization};
 pub use rprop::Rprop;
+#[cfg(feature = "parallel")]
+pub use scheduler::{SchedulerStats, WorkStealingScheduler};
+pub use split::{
+    check_contamination, find_contamination, ContaminationPolicy, ContaminationReport, DataSplit,
+    DataSplitter,
+};
+pub use statistics::{AdvancedTrainingAlgorithm, TrainingStatistics};
+pub use streaming::{DataSource, Sample, StreamingTrainingData};
+pub use supervisor::{SupervisorHandle, SupervisorState, TrainingHealth, TrainingSupervisor};
+pub use trainer::{Trainer, TrainingReport, ValidationMetrics, ValidationSchedule};
+pub use trimming::{TrimmedLoss, MAX_TRIM_FRACTION};
+pub use trust_ratio::{Lamb, Lars, LayerwiseTrustRatio, TrustRatio, TrustRatioUpdates};
+pub use weight_monitor::{
+    GradientDistributionReport, LayerDistributionReport, LayerHealth, WeightDistributionMonitor,
+};
 
 // Re-export GPU training types when available
 #[cfg(feature = "gpu")]
@@ -317,15 +643,74 @@ pub mod helpers {
     use super::*;
 
     /// Simple network representation for training algorithms
+    ///
+    /// `weights[l]` holds the incoming weights of the neurons of layer
+    /// `l + 1` row by row, with their biases in `biases[l]`. Fully connected
+    /// layers store dense rows of `layer_sizes[l]` weights in source order.
+    /// Sparse layers store only the connections that exist and describe them
+    /// in `connections[l]`. Either way the bias is the weight of the
+    /// connection from the previous layer's bias neuron.
     #[derive(Debug, Clone)]
     pub struct SimpleNetwork<T: Float> {
         pub layer_sizes: Vec<usize>,
         pub weights: Vec<Vec<T>>,
         pub biases: Vec<Vec<T>>,
+        /// Dropout rate of each layer
+        pub dropout: Vec<T>,
+        /// Row structure of each sparse weight layer; `None` when the layer
+        /// is fully connected
+        pub connections: Vec<Option<SparseConnections>>,
+    }
+
+    /// Compressed sparse row structure of one weight layer
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct SparseConnections {
+        /// Weights `row_starts[n]..row_starts[n + 1]` belong to neuron `n`
+        pub row_starts: Vec<usize>,
+        /// Source neuron in the previous layer of each weight
+        pub columns: Vec<usize>,
+    }
+
+    impl<T: Float> SimpleNetwork<T> {
+        /// `(weight index, source neuron)` of each incoming connection of
+        /// `neuron` in weight layer `layer`, bias excluded
+        pub fn inputs(
+            &self,
+            layer: usize,
+            neuron: usize,
+        ) -> impl Iterator<Item = (usize, usize)> + '_ {
+            let sparse = self.connections.get(layer).and_then(Option::as_ref);
+            let (start, end) = match sparse {
+                Some(sparse) => (sparse.row_starts[neuron], sparse.row_starts[neuron + 1]),
+                None => {
+                    let inputs = self.layer_sizes[layer];
+                    (neuron * inputs, (neuron + 1) * inputs)
+                }
+            };
+            let len = self.weights[layer].len();
+            (start.min(len)..end.min(len))
+                .map(move |k| (k, sparse.map_or(k - start, |sparse| sparse.columns[k])))
+        }
+    }
+
+    /// Whether every neuron of `layer` is connected to all neurons of
+    /// `prev`, bias included
+    fn fully_connected<T: Float>(prev: &crate::Layer<T>, layer: &crate::Layer<T>) -> bool {
+        layer
+            .neurons
+            .iter()
+            .filter(|n| !n.is_bias)
+            .all(|n| n.connections.len() == prev.size())
+    }
+
+    /// Index of the bias neuron of `layer` as seen by the next layer's
+    /// connections
+    fn bias_source<T: Float>(layer: &crate::Layer<T>) -> Option<usize> {
+        layer.has_bias().then(|| layer.num_regular_neurons())
     }
 
     /// Convert a real Network to a simplified representation for training
-    pub fn network_to_simple<T: Float + Default>(network: &Network<T>) -> SimpleNetwork<T> {
+    pub fn network_to_simple<T: Float>(network: &Network<T>) -> SimpleNetwork<T> {
         let layer_sizes: Vec<usize> = network
             .layers
             .iter()
@@ -335,39 +720,48 @@ pub mod helpers {
         // Extract weights and biases from the complex structure
         let mut weights = Vec::new();
         let mut biases = Vec::new();
+        let mut connections = Vec::new();
 
         for layer_idx in 1..network.layers.len() {
+            let prev_layer = &network.layers[layer_idx - 1];
             let current_layer = &network.layers[layer_idx];
-            let _prev_layer_size = network.layers[layer_idx - 1].size(); // Include bias neurons
+            let dense = fully_connected(prev_layer, current_layer);
+            let bias_neuron = bias_source(prev_layer);
 
             let mut layer_weights = Vec::new();
             let mut layer_biases = Vec::new();
-
-            for neuron in &current_layer.neurons {
-                if !neuron.is_bias {
-                    // Extract bias (connection index 0 should be bias)
-                    let bias = if !neuron.connections.is_empty() {
-                        neuron.connections[0].weight
+            // Only sparse layers keep the source of every weight
+            let mut row_starts = vec![0];
+            let mut columns = Vec::new();
+
+            for neuron in current_layer.neurons.iter().filter(|n| !n.is_bias) {
+                let mut bias = T::zero();
+                for connection in &neuron.connections {
+                    if Some(connection.from_neuron) == bias_neuron {
+                        bias = connection.weight;
                     } else {
-                        T::zero()
-                    };
-                    layer_biases.push(bias);
-
-                    // Extract weights (skip bias connection)
-                    for connection in neuron.connections.iter().skip(1) {
                         layer_weights.push(connection.weight);
+                        columns.push(connection.from_neuron);
                     }
                 }
+                layer_biases.push(bias);
+                row_starts.push(columns.len());
             }
 
             weights.push(layer_weights);
             biases.push(layer_biases);
+            connections.push((!dense).then_some(SparseConnections {
+                row_starts,
+                columns,
+            }));
         }
 
         SimpleNetwork {
             layer_sizes,
             weights,
             biases,
+            dropout: network.layers.iter().map(|layer| layer.dropout).collect(),
+            connections,
         }
     }
 
@@ -378,7 +772,9 @@ pub mod helpers {
         bias_updates: &[Vec<T>],
     ) {
         for layer_idx in 1..network.layers.len() {
-            let current_layer = &mut network.layers[layer_idx];
+            let (before, after) = network.layers.split_at_mut(layer_idx);
+            let bias_neuron = bias_source(&before[layer_idx - 1]);
+            let current_layer = &mut after[0];
             let weight_layer_idx = layer_idx - 1;
 
             let mut neuron_idx = 0;
@@ -386,17 +782,15 @@ pub mod helpers {
 
             for neuron in &mut current_layer.neurons {
                 if !neuron.is_bias {
-                    // Update bias (connection index 0)
-                    if !neuron.connections.is_empty() {
-                        neuron.connections[0].weight = neuron.connections[0].weight
-                            + bias_updates[weight_layer_idx][neuron_idx];
-                    }
-
-                    // Update weights (skip bias connection)
-                    for connection in neuron.connections.iter_mut().skip(1) {
-                        connection.weight =
-                            connection.weight + weight_updates[weight_layer_idx][weight_idx];
-                        weight_idx += 1;
+                    for connection in &mut neuron.connections {
+                        if Some(connection.from_neuron) == bias_neuron {
+                            connection.weight =
+                                connection.weight + bias_updates[weight_layer_idx][neuron_idx];
+                        } else {
+                            connection.weight =
+                                connection.weight + weight_updates[weight_layer_idx][weight_idx];
+                            weight_idx += 1;
+                        }
                     }
 
                     neuron_idx += 1;
@@ -405,6 +799,21 @@ pub mod helpers {
         }
     }
 
+    /// Per-layer optimizer buffers filled with `value`, one entry for every
+    /// connection into the layer, so they cover its weight gradients whether
+    /// it is dense or sparse
+    pub fn weight_buffers<T: Float>(network: &Network<T>, value: T) -> Vec<Vec<T>> {
+        network
+            .layers
+            .iter()
+            .skip(1) // Skip input layer
+            .map(|layer| {
+                let connections = layer.neurons.iter().map(|n| n.connections.len()).sum();
+                vec![value; connections]
+            })
+            .collect()
+    }
+
     /// Activation function that works with our simplified representation
     pub fn sigmoid<T: Float>(x: T) -> T {
         T::one() / (T::one() + (-x).exp())
@@ -415,30 +824,92 @@ pub mod helpers {
         output * (T::one() - output)
     }
 
+    /// Inverted-dropout masks for one training sample
+    ///
+    /// Each neuron of a layer with dropout gets the scale 0 (dropped) or
+    /// `1 / (1 - rate)` (kept); layers without dropout, and the output layer,
+    /// get an empty mask. Returns an empty list when no layer uses dropout,
+    /// so the masked helpers cost nothing then.
+    pub fn dropout_masks<T: Float, R: rand::Rng + ?Sized>(
+        network: &SimpleNetwork<T>,
+        rng: &mut R,
+    ) -> Vec<Vec<T>> {
+        let hidden = network.layer_sizes.len().saturating_sub(1);
+        if network
+            .dropout
+            .iter()
+            .take(hidden)
+            .all(|&rate| rate <= T::zero())
+        {
+            return Vec::new();
+        }
+        network
+            .layer_sizes
+            .iter()
+            .zip(&network.dropout)
+            .enumerate()
+            .map(|(layer_idx, (&size, &rate))| {
+                if layer_idx >= hidden || rate <= T::zero() {
+                    return Vec::new();
+                }
+                let keep = T::one() / (T::one() - rate);
+                let rate = rate.to_f64().unwrap_or(0.0);
+                (0..size)
+                    .map(|_| {
+                        if rng.gen::<f64>() < rate {
+                            T::zero()
+                        } else {
+                            keep
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Multiply `activations` by `mask`, if the layer has one
+    fn apply_mask<T: Float>(activations: &mut [T], mask: Option<&Vec<T>>) {
+        if let Some(mask) = mask.filter(|mask| !mask.is_empty()) {
+            for (activation, &scale) in activations.iter_mut().zip(mask) {
+                *activation = *activation * scale;
+            }
+        }
+    }
+
     /// Forward propagation through the simplified network
     pub fn forward_propagate<T: Float>(network: &SimpleNetwork<T>, input: &[T]) -> Vec<Vec<T>> {
+        forward_propagate_masked(network, input, &[])
+    }
+
+    /// Forward propagation with the [`dropout_masks`] of one sample applied
+    /// to the activations
+    pub fn forward_propagate_masked<T: Float>(
+        network: &SimpleNetwork<T>,
+        input: &[T],
+        masks: &[Vec<T>],
+    ) -> Vec<Vec<T>> {
         let mut activations = vec![input.to_vec()];
+        apply_mask(&mut activations[0], masks.first());
 
         for layer_idx in 1..network.layer_sizes.len() {
             let prev_activations = &activations[layer_idx - 1];
             let weights = &network.weights[layer_idx - 1];
             let biases = &network.biases[layer_idx - 1];
 
-            let mut layer_activations = Vec::with_capacity(network.layer_sizes[layer_idx]);
-
-            for neuron_idx in 0..network.layer_sizes[layer_idx] {
-                let mut sum = biases[neuron_idx];
-                let weight_start = neuron_idx * prev_activations.len();
-
-                for (input_idx, &input_val) in prev_activations.iter().enumerate() {
-                    if weight_start + input_idx < weights.len() {
-                        sum = sum + input_val * weights[weight_start + input_idx];
-                    }
-                }
-
-                layer_activations.push(sigmoid(sum));
-            }
-
+            let mut layer_activations: Vec<T> = (0..network.layer_sizes[layer_idx])
+                .map(|neuron_idx| {
+                    let sum = network.inputs(layer_idx - 1, neuron_idx).fold(
+                        biases[neuron_idx],
+                        |sum, (weight_idx, input_idx)| match prev_activations.get(input_idx) {
+                            Some(&input_val) => sum + input_val * weights[weight_idx],
+                            None => sum,
+                        },
+                    );
+                    sigmoid(sum)
+                })
+                .collect();
+
+            apply_mask(&mut layer_activations, masks.get(layer_idx));
             activations.push(layer_activations);
         }
 
@@ -451,6 +922,35 @@ pub mod helpers {
         activations: &[Vec<T>],
         desired_output: &[T],
         error_function: &dyn ErrorFunction<T>,
+    ) -> (Vec<Vec<T>>, Vec<Vec<T>>) {
+        calculate_gradients_masked(network, activations, &[], desired_output, error_function)
+    }
+
+    /// Error reaching each input of `network`, before any activation
+    /// derivative, from the bias gradients returned by [`calculate_gradients`]
+    /// (the bias gradient of a first-layer neuron is its error term)
+    pub fn input_errors<T: Float>(network: &SimpleNetwork<T>, bias_gradients: &[Vec<T>]) -> Vec<T> {
+        let mut errors = vec![T::zero(); network.layer_sizes.first().copied().unwrap_or(0)];
+        for (neuron, &error) in bias_gradients.first().into_iter().flatten().enumerate() {
+            for (weight, source) in network.inputs(0, neuron) {
+                if let Some(sum) = errors.get_mut(source) {
+                    *sum = *sum + error * network.weights[0][weight];
+                }
+            }
+        }
+        errors
+    }
+
+    /// Gradients for activations from [`forward_propagate_masked`]
+    ///
+    /// Dropped neurons pass no error back, and kept ones scale it by their
+    /// mask, matching the scaled forward pass.
+    pub fn calculate_gradients_masked<T: Float>(
+        network: &SimpleNetwork<T>,
+        activations: &[Vec<T>],
+        masks: &[Vec<T>],
+        desired_output: &[T],
+        error_function: &dyn ErrorFunction<T>,
     ) -> (Vec<Vec<T>>, Vec<Vec<T>>) {
         let mut weight_gradients = network
             .weights
@@ -470,36 +970,37 @@ pub mod helpers {
         let output_idx = activations.len() - 1;
         layer_errors[output_idx] = activations[output_idx]
             .iter()
-            .zip(desired_output.iter())
-            .map(|(&actual, &desired)| {
-                error_function.derivative(actual, desired) * sigmoid_derivative(actual)
-            })
+            .zip(error_function.output_derivatives(&activations[output_idx], desired_output))
+            .map(|(&actual, derivative)| derivative * sigmoid_derivative(actual))
             .collect();
 
         // Backpropagate errors to hidden layers
         for layer_idx in (1..network.layer_sizes.len() - 1).rev() {
-            layer_errors[layer_idx] = vec![T::zero(); network.layer_sizes[layer_idx]];
-
-            for neuron_idx in 0..network.layer_sizes[layer_idx] {
-                let mut error_sum = T::zero();
-
-                // Sum weighted errors from next layer
-                let next_layer_idx = layer_idx + 1;
-                let next_layer_weights_idx = layer_idx; // weights[i] connects layer i to layer i+1
-
-                for next_neuron_idx in 0..network.layer_sizes[next_layer_idx] {
-                    // Weight from current neuron to next layer neuron
-                    let weight_idx = next_neuron_idx * network.layer_sizes[layer_idx] + neuron_idx;
-                    if weight_idx < network.weights[next_layer_weights_idx].len() {
-                        error_sum = error_sum
-                            + layer_errors[next_layer_idx][next_neuron_idx]
-                                * network.weights[next_layer_weights_idx][weight_idx];
+            // weights[i] connects layer i to layer i+1, so each connection of
+            // the next layer passes its neuron's error back to its source
+            let mut error_sums = vec![T::zero(); network.layer_sizes[layer_idx]];
+            for (next_neuron_idx, &next_error) in layer_errors[layer_idx + 1].iter().enumerate() {
+                for (weight_idx, neuron_idx) in network.inputs(layer_idx, next_neuron_idx) {
+                    if let Some(sum) = error_sums.get_mut(neuron_idx) {
+                        *sum = *sum + next_error * network.weights[layer_idx][weight_idx];
                     }
                 }
-
-                layer_errors[layer_idx][neuron_idx] =
-                    error_sum * sigmoid_derivative(activations[layer_idx][neuron_idx]);
             }
+
+            layer_errors[layer_idx] = error_sums
+                .into_iter()
+                .enumerate()
+                .map(|(neuron_idx, error_sum)| {
+                    let activation = activations[layer_idx][neuron_idx];
+                    let derivative = match masks.get(layer_idx).and_then(|m| m.get(neuron_idx)) {
+                        Some(&scale) if scale == T::zero() => T::zero(),
+                        // The stored activation is already scaled
+                        Some(&scale) => scale * sigmoid_derivative(activation / scale),
+                        None => sigmoid_derivative(activation),
+                    };
+                    error_sum * derivative
+                })
+                .collect();
         }
 
         // Calculate gradients for each layer
@@ -508,16 +1009,14 @@ pub mod helpers {
             let prev_activations = &activations[layer_idx];
             let current_errors = &layer_errors[current_layer_idx];
 
-            for neuron_idx in 0..current_errors.len() {
+            for (neuron_idx, &error) in current_errors.iter().enumerate() {
                 // Bias gradient
-                bias_gradients[layer_idx][neuron_idx] = current_errors[neuron_idx];
+                bias_gradients[layer_idx][neuron_idx] = error;
 
                 // Weight gradients
-                let weight_start = neuron_idx * prev_activations.len();
-                for (input_idx, &activation) in prev_activations.iter().enumerate() {
-                    if weight_start + input_idx < weight_gradients[layer_idx].len() {
-                        weight_gradients[layer_idx][weight_start + input_idx] =
-                            current_errors[neuron_idx] * activation;
+                for (weight_idx, input_idx) in network.inputs(layer_idx, neuron_idx) {
+                    if let Some(&activation) = prev_activations.get(input_idx) {
+                        weight_gradients[layer_idx][weight_idx] = error * activation;
                     }
                 }
             }
@@ -539,6 +1038,150 @@ mod tests {
         assert!(sigmoid(10.0) > 0.99);
         assert!(sigmoid(-10.0) < 0.01);
     }
+
+    #[test]
+    fn test_simple_network_matches_run() {
+        use helpers::*;
+
+        let mut network = crate::NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let simple = network_to_simple(&network);
+        let activations = forward_propagate(&simple, &[0.3, -0.8]);
+        let output = network.run(&[0.3, -0.8]);
+        assert!((activations[2][0] - output[0]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_dropout_masks_and_gradients() {
+        use helpers::*;
+        use rand::SeedableRng;
+
+        let mut network = crate::NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer_with_dropout(6, 0.5)
+            .output_layer(1)
+            .build();
+        assert_eq!(network.layers[1].dropout, 0.5);
+        // Inference never drops neurons
+        assert_eq!(network.run(&[0.3, 0.7]), network.run(&[0.3, 0.7]));
+
+        let simple = network_to_simple(&network);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let masks = dropout_masks(&simple, &mut rng);
+        assert!(masks[0].is_empty() && masks[2].is_empty());
+        assert!(masks[1].iter().all(|&m| m == 0.0 || m == 2.0));
+        let dropped = masks[1].iter().position(|&m| m == 0.0).unwrap();
+        let kept = masks[1].iter().position(|&m| m == 2.0).unwrap();
+
+        let (input, desired) = ([0.3, 0.7], [0.9]);
+        let activations = forward_propagate_masked(&simple, &input, &masks);
+        assert_eq!(activations[1][dropped], 0.0);
+        let (weights, biases) =
+            calculate_gradients_masked(&simple, &activations, &masks, &desired, &MseError);
+        assert_eq!(biases[0][dropped], 0.0);
+        assert_eq!(weights[1][dropped], 0.0);
+
+        // Finite differences of the masked network agree with backprop
+        let loss = |simple: &SimpleNetwork<f64>| {
+            let out = forward_propagate_masked(simple, &input, &masks);
+            MseError.calculate(&out[2], &desired)
+        };
+        let h = 1e-6;
+        let mut shifted = simple.clone();
+        shifted.biases[0][kept] += h;
+        let numeric = (loss(&shifted) - loss(&simple)) / h;
+        assert!((numeric - biases[0][kept]).abs() < 1e-4);
+
+        let mut trainer = Adam::new(0.05);
+        let data = TrainingData {
+            inputs: vec![vec![0.0, 1.0], vec![1.0, 0.0]],
+            outputs: vec![vec![1.0], vec![0.0]],
+        };
+        for _ in 0..5 {
+            assert!(trainer
+                .train_epoch(&mut network, &data)
+                .unwrap()
+                .is_finite());
+        }
+    }
+
+    #[test]
+    fn test_sparse_helpers() {
+        use helpers::*;
+
+        let mut network = crate::NetworkBuilder::<f64>::new()
+            .input_layer(6)
+            .hidden_layer(5)
+            .output_layer(3)
+            .connection_rate(0.4)
+            .build();
+        network.randomize_weights(-1.0, 1.0);
+        let simple = network_to_simple(&network);
+        assert!(simple.connections.iter().all(Option::is_some));
+        let sparse = simple.connections[0].as_ref().unwrap();
+        assert_eq!(sparse.row_starts.len(), 6);
+        assert_eq!(sparse.columns.len(), simple.weights[0].len());
+        assert_eq!(
+            simple.weights.iter().map(Vec::len).sum::<usize>() + 8,
+            network.total_connections()
+        );
+
+        // The CSR forward pass is the real network's forward pass
+        let input = [0.1, 0.9, 0.4, 0.3, 0.7, 0.2];
+        let activations = forward_propagate(&simple, &input);
+        for (a, b) in activations[2].iter().zip(network.run(&input)) {
+            assert!((a - b).abs() < 1e-12);
+        }
+
+        // Numerical check of one weight gradient per layer
+        let desired = [1.0, 0.0, 0.5];
+        let (weights, _) = calculate_gradients(&simple, &activations, &desired, &MseError);
+        for layer in 0..2 {
+            let loss = |simple: &SimpleNetwork<f64>| {
+                let out = forward_propagate(simple, &input);
+                MseError.calculate(&out[2], &desired) * 3.0
+            };
+            let mut shifted = simple.clone();
+            shifted.weights[layer][0] += 1e-6;
+            let numeric = (loss(&shifted) - loss(&simple)) / 1e-6;
+            assert!((numeric - weights[layer][0]).abs() < 1e-4);
+        }
+
+        let data = TrainingData {
+            inputs: vec![input.to_vec()],
+            outputs: vec![desired.to_vec()],
+        };
+        let mut trainer = IncrementalBackprop::new(0.5);
+        let first = trainer.train_epoch(&mut network, &data).unwrap();
+        for _ in 0..20 {
+            trainer.train_epoch(&mut network, &data).unwrap();
+        }
+        assert!(trainer.calculate_error(&network, &data) < first);
+        assert_eq!(network_to_simple(&network).connections, simple.connections);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_numa_thread_pool() {
+        let options = ParallelTrainingOptions::default()
+            .with_threads(3)
+            .with_numa_policy(NumaPolicy::Spread);
+        let pool = options.build_thread_pool().unwrap();
+        assert_eq!(pool.current_num_threads(), 3);
+
+        let network = crate::NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let inputs: Vec<Vec<f32>> = (0..300).map(|i| vec![i as f32 / 300.0, 0.5]).collect();
+        let expected = evaluation::predict_matrix(&network, &inputs);
+        let predictions = pool.install(|| evaluation::predict_matrix(&network, &inputs));
+        assert_eq!(predictions, expected);
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,451 @@
+//! Multi-task losses over slices of the output layer
+//!
+//! A network with several heads is a network whose output layer is split into
+//! ranges, one per task. [`MultiTaskLoss`] gives each [`TaskHead`] its own
+//! error function and combines them with per-task weights, which are either
+//! fixed or balanced automatically:
+//!
+//! - [`TaskWeighting::Uncertainty`] learns a homoscedastic uncertainty per
+//!   task (Kendall, Gal & Cipolla, 2018): every task gets a log-variance `s`,
+//!   the objective is `sum(exp(-s) * L + s)`, and `s` follows the gradient of
+//!   that objective. At equilibrium `exp(-s) = 1 / L`, so tasks with large or
+//!   noisy losses are down-weighted without hand tuning.
+//! - [`TaskWeighting::GradNorm`] (Chen et al., 2018) moves the weights so that
+//!   every task's weighted gradient norm on the last shared layer approaches
+//!   the mean norm, scaled by `r^alpha` where `r` is the task's loss relative
+//!   to its first update. Tasks that train slower get larger gradients.
+//!
+//! The loss plugs into any optimizer through `with_error_function` and only
+//! reads the weights there. Balancing is an explicit step: share the loss
+//! through an [`Arc`](std::sync::Arc) and call
+//! [`MultiTaskLoss::update_weights`] between epochs.
+
+use super::helpers::{calculate_gradients, forward_propagate, network_to_simple};
+use super::{ErrorFunction, TrainingData, TrainingError};
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+use std::ops::Range;
+use std::sync::{Mutex, PoisonError};
+
+/// Log-variances are kept in this range so a task with near-zero loss cannot
+/// take an unbounded weight
+const LOG_VARIANCE_LIMIT: f64 = 10.0;
+
+/// One task: a contiguous range of network outputs and its error function
+pub struct TaskHead<T: Float> {
+    pub name: String,
+    pub outputs: Range<usize>,
+    pub loss: Box<dyn ErrorFunction<T>>,
+}
+
+/// How task losses are combined
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskWeighting<T: Float> {
+    /// Constant weight per task, in head order
+    Fixed(Vec<T>),
+    /// Learned log-variance per task, updated with this learning rate
+    Uncertainty { learning_rate: T },
+    /// Gradient normalization with restoring force `alpha`
+    GradNorm { learning_rate: T, alpha: T },
+}
+
+/// Learned balancing state, updated by [`MultiTaskLoss::update_weights`]
+#[derive(Debug, Clone)]
+struct Balance<T> {
+    /// Log-variances for `Uncertainty`, task weights for `GradNorm`
+    values: Vec<T>,
+    /// Task losses at the first GradNorm update
+    initial_losses: Vec<T>,
+}
+
+/// Weighted sum of per-head losses
+pub struct MultiTaskLoss<T: Float> {
+    heads: Vec<TaskHead<T>>,
+    weighting: TaskWeighting<T>,
+    balance: Mutex<Balance<T>>,
+}
+
+impl<T: Float> MultiTaskLoss<T> {
+    pub fn new(weighting: TaskWeighting<T>) -> Self {
+        Self {
+            heads: Vec::new(),
+            weighting,
+            balance: Mutex::new(Balance {
+                values: Vec::new(),
+                initial_losses: Vec::new(),
+            }),
+        }
+    }
+
+    /// Uncertainty-balanced loss with a log-variance learning rate of 0.01
+    pub fn uncertainty() -> Self {
+        Self::new(TaskWeighting::Uncertainty {
+            learning_rate: cast(0.01),
+        })
+    }
+
+    /// GradNorm-balanced loss with a learning rate of 0.025 and the paper's
+    /// default `alpha` of 1.5
+    pub fn grad_norm() -> Self {
+        Self::new(TaskWeighting::GradNorm {
+            learning_rate: cast(0.025),
+            alpha: cast(1.5),
+        })
+    }
+
+    /// Add a head covering `outputs`
+    pub fn with_head(
+        mut self,
+        name: impl Into<String>,
+        outputs: Range<usize>,
+        loss: Box<dyn ErrorFunction<T>>,
+    ) -> Self {
+        self.heads.push(TaskHead {
+            name: name.into(),
+            outputs,
+            loss,
+        });
+        // Log-variances start at zero, GradNorm weights at one
+        let initial = match self.weighting {
+            TaskWeighting::GradNorm { .. } => T::one(),
+            _ => T::zero(),
+        };
+        self.balance
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values
+            .push(initial);
+        self
+    }
+
+    pub fn heads(&self) -> &[TaskHead<T>] {
+        &self.heads
+    }
+
+    fn balance(&self) -> Vec<T> {
+        self.balance
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values
+            .clone()
+    }
+
+    /// Current learned log-variances, one per head (zero unless the
+    /// weighting is [`TaskWeighting::Uncertainty`])
+    pub fn log_variances(&self) -> Vec<T> {
+        match self.weighting {
+            TaskWeighting::Uncertainty { .. } => self.balance(),
+            _ => vec![T::zero(); self.heads.len()],
+        }
+    }
+
+    /// Current weight of every head
+    pub fn task_weights(&self) -> Vec<T> {
+        match &self.weighting {
+            TaskWeighting::Fixed(weights) => (0..self.heads.len())
+                .map(|i| weights.get(i).copied().unwrap_or(T::one()))
+                .collect(),
+            TaskWeighting::Uncertainty { .. } => {
+                self.balance().iter().map(|&s| (-s).exp()).collect()
+            }
+            TaskWeighting::GradNorm { .. } => self.balance(),
+        }
+    }
+
+    /// Unweighted loss of every head
+    pub fn task_losses(&self, actual: &[T], desired: &[T]) -> Vec<T> {
+        self.heads
+            .iter()
+            .map(|head| {
+                let range = clamp(&head.outputs, actual.len().min(desired.len()));
+                if range.is_empty() {
+                    T::zero()
+                } else {
+                    head.loss.calculate(&actual[range.clone()], &desired[range])
+                }
+            })
+            .collect()
+    }
+
+    /// Take one balancing step on the mean task losses of `network` over
+    /// `data`; fixed weights are left alone
+    pub fn update_weights(
+        &self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<(), TrainingError>
+    where
+        T: Default + Send + Sync,
+    {
+        data.check_shapes(network)?;
+        if matches!(self.weighting, TaskWeighting::Fixed(_)) || data.inputs.is_empty() {
+            return Ok(());
+        }
+
+        let simple = network_to_simple(network);
+        // GradNorm measures gradients on the weights into the last hidden
+        // layer, or the output layer when there is none
+        let shared = simple.weights.len().saturating_sub(2);
+        let grad_norm = matches!(self.weighting, TaskWeighting::GradNorm { .. });
+        let samples = cast::<T>(data.inputs.len());
+        let mut losses = vec![T::zero(); self.heads.len()];
+        let mut gradients = vec![Vec::new(); self.heads.len()];
+        for (input, desired) in data.inputs.iter().zip(&data.outputs) {
+            let activations = forward_propagate(&simple, input);
+            let output = &activations[activations.len() - 1];
+            for (sum, loss) in losses.iter_mut().zip(self.task_losses(output, desired)) {
+                *sum = *sum + loss / samples;
+            }
+            if !grad_norm {
+                continue;
+            }
+            for (head, sum) in self.heads.iter().zip(&mut gradients) {
+                let (weight_gradients, _) =
+                    calculate_gradients(&simple, &activations, desired, &HeadLoss { head });
+                let Some(layer) = weight_gradients.get(shared) else {
+                    continue;
+                };
+                sum.resize(layer.len(), T::zero());
+                for (s, &g) in sum.iter_mut().zip(layer) {
+                    *s = *s + g / samples;
+                }
+            }
+        }
+
+        let weights = self.task_weights();
+        let mut balance = self.balance.lock().unwrap_or_else(PoisonError::into_inner);
+        match self.weighting {
+            TaskWeighting::Fixed(_) => {}
+            TaskWeighting::Uncertainty { learning_rate } => {
+                // d/ds (exp(-s) * L + s) = 1 - exp(-s) * L
+                let limit = cast::<T>(LOG_VARIANCE_LIMIT);
+                for ((s, weight), loss) in balance.values.iter_mut().zip(weights).zip(losses) {
+                    let gradient = T::one() - weight * loss;
+                    *s = (*s - learning_rate * gradient).max(-limit).min(limit);
+                }
+            }
+            TaskWeighting::GradNorm {
+                learning_rate,
+                alpha,
+            } => {
+                if balance.initial_losses.is_empty() {
+                    balance.initial_losses = losses.clone();
+                }
+                let tasks = cast::<T>(self.heads.len());
+                let norms: Vec<T> = gradients
+                    .iter()
+                    .map(|g| g.iter().fold(T::zero(), |acc, &x| acc + x * x).sqrt())
+                    .collect();
+                let weighted: Vec<T> = weights.iter().zip(&norms).map(|(&w, &n)| w * n).collect();
+                let mean_norm = weighted.iter().fold(T::zero(), |acc, &x| acc + x) / tasks;
+                let ratios: Vec<T> = losses
+                    .iter()
+                    .zip(&balance.initial_losses)
+                    .map(|(&loss, &initial)| {
+                        if initial > T::zero() {
+                            loss / initial
+                        } else {
+                            T::one()
+                        }
+                    })
+                    .collect();
+                let mean_ratio = ratios.iter().fold(T::zero(), |acc, &x| acc + x) / tasks;
+
+                // The target norm is a constant, so the gradient of
+                // |w * |g| - target| with respect to w is sign(...) * |g|
+                for (i, w) in balance.values.iter_mut().enumerate() {
+                    let relative = if mean_ratio > T::zero() {
+                        ratios[i] / mean_ratio
+                    } else {
+                        T::one()
+                    };
+                    let target = mean_norm * relative.powf(alpha);
+                    let difference = weighted[i] - target;
+                    let sign = if difference > T::zero() {
+                        T::one()
+                    } else if difference < T::zero() {
+                        -T::one()
+                    } else {
+                        T::zero()
+                    };
+                    *w = (*w - learning_rate * sign * norms[i]).max(T::zero());
+                }
+
+                // Renormalize so the weights sum to the number of tasks
+                let total = balance.values.iter().fold(T::zero(), |acc, &w| acc + w);
+                if total > T::zero() {
+                    for w in balance.values.iter_mut() {
+                        *w = *w * tasks / total;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn clamp(range: &Range<usize>, len: usize) -> Range<usize> {
+    range.start.min(len)..range.end.min(len)
+}
+
+/// Derivatives of one head's output range, zero elsewhere
+fn head_derivatives<T: Float>(head: &TaskHead<T>, actual: &[T], desired: &[T]) -> Vec<T> {
+    let range = clamp(&head.outputs, actual.len().min(desired.len()));
+    let mut derivatives = vec![T::zero(); actual.len()];
+    let head_derivatives = head
+        .loss
+        .output_derivatives(&actual[range.clone()], &desired[range.clone()]);
+    for (d, h) in derivatives[range].iter_mut().zip(head_derivatives) {
+        *d = h;
+    }
+    derivatives
+}
+
+/// A single head's loss over the whole output layer, for per-task gradients
+struct HeadLoss<'a, T: Float> {
+    head: &'a TaskHead<T>,
+}
+
+impl<T: Float> ErrorFunction<T> for HeadLoss<'_, T> {
+    fn calculate(&self, actual: &[T], desired: &[T]) -> T {
+        let range = clamp(&self.head.outputs, actual.len().min(desired.len()));
+        if range.is_empty() {
+            T::zero()
+        } else {
+            self.head
+                .loss
+                .calculate(&actual[range.clone()], &desired[range])
+        }
+    }
+
+    fn derivative(&self, actual: T, desired: T) -> T {
+        self.head.loss.derivative(actual, desired)
+    }
+
+    fn output_derivatives(&self, actual: &[T], desired: &[T]) -> Vec<T> {
+        head_derivatives(self.head, actual, desired)
+    }
+}
+
+impl<T: Float + Send + Sync> ErrorFunction<T> for MultiTaskLoss<T> {
+    /// Weighted sum of the head losses with the current weights
+    fn calculate(&self, actual: &[T], desired: &[T]) -> T {
+        self.task_losses(actual, desired)
+            .into_iter()
+            .zip(self.task_weights())
+            .fold(T::zero(), |acc, (loss, weight)| acc + weight * loss)
+    }
+
+    /// Element-wise derivative without head information: the first head's
+    /// derivative, unweighted. Backpropagation uses
+    /// [`ErrorFunction::output_derivatives`] instead.
+    fn derivative(&self, actual: T, desired: T) -> T {
+        match self.heads.first() {
+            Some(head) => head.loss.derivative(actual, desired),
+            None => T::zero(),
+        }
+    }
+
+    fn output_derivatives(&self, actual: &[T], desired: &[T]) -> Vec<T> {
+        let mut derivatives = vec![T::zero(); actual.len()];
+        for (head, weight) in self.heads.iter().zip(self.task_weights()) {
+            for (d, h) in derivatives
+                .iter_mut()
+                .zip(head_derivatives(head, actual, desired))
+            {
+                *d = *d + weight * h;
+            }
+        }
+        derivatives
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::*;
+    use crate::NetworkBuilder;
+    use std::sync::Arc;
+
+    fn heads(weighting: TaskWeighting<f64>) -> MultiTaskLoss<f64> {
+        MultiTaskLoss::new(weighting)
+            .with_head("regression", 0..2, Box::new(MseError))
+            .with_head("classification", 2..3, Box::new(MaeError))
+    }
+
+    fn network_and_data() -> (Network<f64>, TrainingData<f64>) {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .output_layer(3)
+            .build();
+        let data = TrainingData {
+            inputs: vec![vec![0.0, 1.0], vec![1.0, 0.0]],
+            outputs: vec![vec![0.0, 1.0, 1.0], vec![1.0, 0.0, 0.0]],
+        };
+        (network, data)
+    }
+
+    #[test]
+    fn test_fixed_weights_scale_head_derivatives() {
+        let loss = heads(TaskWeighting::Fixed(vec![0.5, 3.0]));
+        let (actual, desired) = ([1.0, 0.0, 0.2], [0.0, 0.0, 1.0]);
+        assert_eq!(loss.task_losses(&actual, &desired), vec![0.5, 0.8]);
+        assert!((loss.calculate(&actual, &desired) - (0.25 + 2.4)).abs() < 1e-12);
+        assert_eq!(
+            loss.output_derivatives(&actual, &desired),
+            vec![0.5 * 2.0, 0.0, -3.0]
+        );
+    }
+
+    #[test]
+    fn test_uncertainty_balances_losses() {
+        let loss = heads(TaskWeighting::Uncertainty {
+            learning_rate: 0.05,
+        });
+        let (network, data) = network_and_data();
+
+        // Computing derivatives leaves the weights alone
+        loss.output_derivatives(&[1.0, 1.0, 0.9], &[0.0, 0.0, 1.0]);
+        assert_eq!(loss.log_variances(), vec![0.0, 0.0]);
+
+        for _ in 0..2000 {
+            loss.update_weights(&network, &data).unwrap();
+        }
+        // exp(-s) converges to 1 / L, so every weighted mean loss approaches 1
+        let simple = helpers::network_to_simple(&network);
+        let mut losses = [0.0; 2];
+        for (input, desired) in data.inputs.iter().zip(&data.outputs) {
+            let activations = helpers::forward_propagate(&simple, input);
+            let output = &activations[activations.len() - 1];
+            for (sum, task_loss) in losses.iter_mut().zip(loss.task_losses(output, desired)) {
+                *sum += task_loss / 2.0;
+            }
+        }
+        for (weight, task_loss) in loss.task_weights().iter().zip(&losses) {
+            assert!((weight * task_loss - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_grad_norm_balances_gradient_norms() {
+        let (mut network, data) = network_and_data();
+        let loss = Arc::new(heads(TaskWeighting::GradNorm {
+            learning_rate: 0.05,
+            alpha: 0.0,
+        }));
+        let mut adam = Adam::new(0.01).with_error_function(Box::new(Arc::clone(&loss)));
+        for _ in 0..20 {
+            let error = adam.train_epoch(&mut network, &data).unwrap();
+            assert!(error.is_finite());
+            loss.update_weights(&network, &data).unwrap();
+        }
+
+        // Weights move away from uniform but stay normalized to the number
+        // of tasks
+        let weights = loss.task_weights();
+        assert!((weights.iter().sum::<f64>() - 2.0).abs() < 1e-9);
+        assert_ne!(weights, vec![1.0, 1.0]);
+    }
+}
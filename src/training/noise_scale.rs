@@ -0,0 +1,219 @@
+//! Gradient noise scale
+//!
+//! The gradient noise scale `B = tr(Σ) / |G|²` compares the variance of
+//! per-sample gradients with the squared norm of the true gradient. Batches
+//! much smaller than `B` waste steps on noise, while batches much larger than
+//! `B` waste samples, so it serves as an estimate of the critical batch size.
+//!
+//! Following McCandlish et al. (2018), both quantities are estimated from the
+//! squared norms of a small-batch and a big-batch gradient:
+//!
+//! ```text
+//! |G|² ≈ (B_big |G_big|² - B_small |G_small|²) / (B_big - B_small)
+//! tr(Σ) ≈ (|G_small|² - |G_big|²) / (1 / B_small - 1 / B_big)
+//! ```
+//!
+//! Here the big batch is the whole (possibly subsampled) data set and the
+//! small-batch norm is averaged over disjoint small batches.
+
+use super::helpers::{calculate_gradients, forward_propagate, network_to_simple, SimpleNetwork};
+use super::{MseError, TrainingData};
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+
+/// Result of a gradient noise scale estimate
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientNoiseEstimate<T: Float> {
+    pub small_batch: usize,
+    pub big_batch: usize,
+    /// Mean squared norm of the small-batch gradients
+    pub small_batch_norm_sq: T,
+    /// Squared norm of the big-batch gradient
+    pub big_batch_norm_sq: T,
+    /// Unbiased estimate of the squared norm of the true gradient
+    pub gradient_norm_sq: T,
+    /// Unbiased estimate of the trace of the per-sample gradient covariance
+    pub trace: T,
+    /// `trace / gradient_norm_sq`, or `None` when the gradient estimate is
+    /// not positive (the gradient is lost in the noise)
+    pub noise_scale: Option<T>,
+}
+
+impl<T: Float> GradientNoiseEstimate<T> {
+    /// Fraction of the ideal per-step progress achieved with `batch_size`,
+    /// `1 / (1 + B_noise / batch_size)`
+    pub fn batch_efficiency(&self, batch_size: usize) -> Option<T> {
+        let noise = self.noise_scale?;
+        Some(T::one() / (T::one() + noise / cast(batch_size.max(1))))
+    }
+}
+
+/// Estimates the gradient noise scale of a network on a data set
+#[derive(Debug, Clone)]
+pub struct NoiseScaleEstimator {
+    small_batch: usize,
+    max_samples: usize,
+}
+
+impl NoiseScaleEstimator {
+    /// Estimator comparing batches of `small_batch` samples with the whole set
+    pub fn new(small_batch: usize) -> Self {
+        Self {
+            small_batch: small_batch.max(1),
+            max_samples: 1024,
+        }
+    }
+
+    /// Evenly subsample larger data sets down to `max_samples` (default 1024)
+    pub fn with_max_samples(mut self, max_samples: usize) -> Self {
+        self.max_samples = max_samples;
+        self
+    }
+
+    /// Estimate the noise scale of the squared error
+    ///
+    /// Returns `None` when there are not enough samples for at least two
+    /// small batches.
+    pub fn estimate<T: Float>(
+        &self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> Option<GradientNoiseEstimate<T>> {
+        let stride = data.inputs.len().div_ceil(self.max_samples.max(1)).max(1);
+        let samples: Vec<usize> = (0..data.inputs.len()).step_by(stride).collect();
+        let batches = samples.len() / self.small_batch;
+        if batches < 2 {
+            return None;
+        }
+        let big_batch = batches * self.small_batch;
+
+        let network = network_to_simple(network);
+        let num_weights = network
+            .weights
+            .iter()
+            .chain(&network.biases)
+            .map(Vec::len)
+            .sum();
+        let mut small_sum = vec![T::zero(); num_weights];
+        let mut big_sum = vec![T::zero(); num_weights];
+        let mut small_norm_sq = T::zero();
+
+        for batch in samples[..big_batch].chunks(self.small_batch) {
+            small_sum.iter_mut().for_each(|g| *g = T::zero());
+            for &i in batch {
+                let (weight_gradients, bias_gradients) =
+                    squared_error_gradient(&network, &data.inputs[i], &data.outputs[i]);
+                let sample_gradient = weight_gradients.iter().chain(&bias_gradients).flatten();
+                for ((small, big), &g) in
+                    small_sum.iter_mut().zip(&mut big_sum).zip(sample_gradient)
+                {
+                    *small = *small + g;
+                    *big = *big + g;
+                }
+            }
+            small_norm_sq = small_norm_sq + mean_norm_sq(&small_sum, self.small_batch);
+        }
+
+        let small = cast(self.small_batch);
+        let big = cast(big_batch);
+        let small_batch_norm_sq = small_norm_sq / cast(batches);
+        let big_batch_norm_sq = mean_norm_sq(&big_sum, big_batch);
+        let gradient_norm_sq =
+            (big * big_batch_norm_sq - small * small_batch_norm_sq) / (big - small);
+        let trace = (small_batch_norm_sq - big_batch_norm_sq) / (T::one() / small - T::one() / big);
+        let noise_scale =
+            (gradient_norm_sq > T::zero()).then(|| trace.max(T::zero()) / gradient_norm_sq);
+
+        Some(GradientNoiseEstimate {
+            small_batch: self.small_batch,
+            big_batch,
+            small_batch_norm_sq,
+            big_batch_norm_sq,
+            gradient_norm_sq,
+            trace,
+            noise_scale,
+        })
+    }
+}
+
+/// Squared norm of `sum / count`
+fn mean_norm_sq<T: Float>(sum: &[T], count: usize) -> T {
+    let count = cast(count);
+    sum.iter()
+        .fold(T::zero(), |acc, &g| acc + (g / count) * (g / count))
+}
+
+/// Per-layer weight and bias gradients of the squared error of one sample
+fn squared_error_gradient<T: Float>(
+    network: &SimpleNetwork<T>,
+    input: &[T],
+    target: &[T],
+) -> (Vec<Vec<T>>, Vec<Vec<T>>) {
+    let activations = forward_propagate(network, input);
+    calculate_gradients(network, &activations, target, &MseError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::xor_network;
+    use crate::training::helpers::apply_updates_to_network;
+
+    #[test]
+    fn test_gradient_matches_finite_differences() {
+        let mut network = xor_network::<f64>();
+        let input = [0.3, -0.8];
+        let target = [0.9];
+        let (weight_gradients, bias_gradients) =
+            squared_error_gradient(&network_to_simple(&network), &input, &target);
+
+        // Lay the gradients out in `get_weights` order
+        let weights = network.get_weights();
+        let mut mapped = network.clone();
+        mapped.set_weights(&vec![0.0; weights.len()]).unwrap();
+        apply_updates_to_network(&mut mapped, &weight_gradients, &bias_gradients);
+        let gradient = mapped.get_weights();
+
+        let loss = |network: &mut Network<f64>| (network.run(&input)[0] - target[0]).powi(2);
+        for (i, &analytic) in gradient.iter().enumerate() {
+            let mut shifted = weights.clone();
+            shifted[i] += 1e-6;
+            network.set_weights(&shifted).unwrap();
+            let up = loss(&mut network);
+            shifted[i] -= 2e-6;
+            network.set_weights(&shifted).unwrap();
+            let down = loss(&mut network);
+            assert!((analytic - (up - down) / 2e-6).abs() < 1e-6, "weight {i}");
+        }
+    }
+
+    #[test]
+    fn test_noise_scale_grows_with_label_noise() {
+        // Identical inputs, so all gradient variance comes from the targets;
+        // targets alternate in pairs so that small batches of two differ
+        let data = |spread: f64| TrainingData {
+            inputs: vec![vec![0.5, 0.5]; 64],
+            outputs: (0..64)
+                .map(|i| vec![0.2 + if i % 4 < 2 { spread } else { -spread }])
+                .collect(),
+        };
+        let estimator = NoiseScaleEstimator::new(2);
+        let network = xor_network::<f64>();
+
+        let clean = estimator.estimate(&network, &data(0.0)).unwrap();
+        assert!(clean.trace.abs() < 1e-12);
+        assert!(clean.noise_scale.unwrap() < 1e-6);
+
+        // Doubling the target spread quadruples the gradient variance
+        let noisy = estimator.estimate(&network, &data(0.1)).unwrap();
+        let noisier = estimator.estimate(&network, &data(0.2)).unwrap();
+        assert!(noisy.noise_scale.unwrap() > 0.01);
+        assert!(noisier.noise_scale.unwrap() > 3.0 * noisy.noise_scale.unwrap());
+        assert!(noisy.batch_efficiency(1024).unwrap() > noisy.batch_efficiency(1).unwrap());
+
+        assert!(NoiseScaleEstimator::new(64)
+            .estimate(&network, &data(0.1))
+            .is_none());
+    }
+}
@@ -0,0 +1,102 @@
+//! Options shared by the batch-gradient optimizers
+//!
+//! Trimmed loss, gradient centralization, mini-batches and cancellation mean
+//! the same thing for every optimizer that computes a batch gradient, so
+//! each of them keeps these settings in one [`TrainingOptions`] and gets the
+//! builder methods from [`WithTrainingOptions`]:
+//!
+//! ```
+//! use do_fann::training::{Adam, BatchIterator, WithTrainingOptions};
+//!
+//! let adam = Adam::<f32>::new(0.01)
+//!     .with_trimmed_loss(0.1)
+//!     .with_gradient_centralization(true)
+//!     .with_mini_batches(BatchIterator::new(32));
+//! assert!(adam.training_options().gradient_centralization);
+//! ```
+
+use super::{BatchIterator, CancellationToken, TrimmedLoss};
+
+/// Settings every batch-gradient optimizer supports
+#[derive(Debug, Clone, Default)]
+pub struct TrainingOptions {
+    /// Highest-loss fraction of each batch left out of the gradient
+    pub trimmed_loss: TrimmedLoss,
+    /// Subtract the mean of each neuron's incoming weight gradients before
+    /// the update
+    pub gradient_centralization: bool,
+    /// Update once per mini-batch drawn from here instead of once per epoch
+    pub mini_batches: Option<BatchIterator>,
+    /// Token polled between mini-batches
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl TrainingOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the cancellation token, if any, is cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+}
+
+/// Builder methods for optimizers that embed [`TrainingOptions`]
+pub trait WithTrainingOptions: Sized {
+    fn training_options(&self) -> &TrainingOptions;
+
+    fn training_options_mut(&mut self) -> &mut TrainingOptions;
+
+    /// Leave the highest-loss `fraction` of each batch out of the gradient
+    fn with_trimmed_loss(mut self, fraction: f64) -> Self {
+        self.training_options_mut().trimmed_loss = TrimmedLoss::new(fraction);
+        self
+    }
+
+    /// Subtract the mean of each neuron's incoming weight gradients before
+    /// the update (gradient centralization)
+    fn with_gradient_centralization(mut self, enabled: bool) -> Self {
+        self.training_options_mut().gradient_centralization = enabled;
+        self
+    }
+
+    /// Update once per mini-batch drawn by `batches` instead of once per
+    /// epoch
+    fn with_mini_batches(mut self, batches: BatchIterator) -> Self {
+        self.training_options_mut().mini_batches = Some(batches);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::{xor, xor_network};
+    use crate::training::{Quickprop, Rprop, TrainingAlgorithm};
+
+    #[test]
+    fn test_full_batch_optimizers_honor_mini_batches() {
+        let data = xor::<f64>();
+        type Make = fn() -> Box<dyn TrainingAlgorithm<f64>>;
+        let pairs: [(Make, Make); 2] = [
+            (
+                || Box::new(Rprop::new()),
+                || Box::new(Rprop::new().with_mini_batches(BatchIterator::new(1))),
+            ),
+            (
+                || Box::new(Quickprop::new()),
+                || Box::new(Quickprop::new().with_mini_batches(BatchIterator::new(1))),
+            ),
+        ];
+        for (full, mini) in pairs {
+            let mut a = xor_network::<f64>();
+            let mut b = a.clone();
+            full().train_epoch(&mut a, &data).unwrap();
+            mini().train_epoch(&mut b, &data).unwrap();
+            assert_ne!(a.get_weights(), b.get_weights());
+        }
+    }
+}
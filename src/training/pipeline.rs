@@ -0,0 +1,478 @@
+//! Two-stage pipeline-parallel training on CPU threads
+//!
+//! [`PipelineParallel`] splits the network's layers into two stages, each
+//! driven by its own host thread, and streams every mini-batch through them
+//! in micro-batches with a GPipe schedule: while the second stage runs forward and backward on micro-batch
+//! `m`, the first stage is already running forward on micro-batch `m + 1`.
+//! Only the boundary activations travel forward and the boundary error terms
+//! travel back, so neither stage needs the other's weights.
+//!
+//! Gradients of all micro-batches are accumulated and applied once per
+//! mini-batch, so the update is the same as batch backpropagation over that
+//! mini-batch; the pipeline only changes where and when the work happens.
+//! With two stages and `m` micro-batches each stage idles for a fraction
+//! `1 / (m + 1)` of the schedule (the pipeline bubble).
+//!
+//! Both stages compute on the CPU; there is no GPU placement of a stage.
+//! The split therefore pays off on multi-core hosts when both halves of the
+//! network are large enough to outweigh the hand-off between the threads.
+
+use super::helpers::{
+    calculate_gradients, forward_propagate, input_errors, network_to_simple, SimpleNetwork,
+};
+use super::statistics::{buffer_bytes, gradient_norm};
+use super::*;
+use crate::numeric::cast;
+use std::ops::Range;
+use std::sync::mpsc;
+use std::time::Instant;
+
+/// Weight and bias gradients of a range of layers, indexed like
+/// [`SimpleNetwork::weights`] and [`SimpleNetwork::biases`]
+struct Gradients<T> {
+    weights: Vec<Vec<T>>,
+    biases: Vec<Vec<T>>,
+}
+
+impl<T: Float> Gradients<T> {
+    fn zeros(network: &SimpleNetwork<T>) -> Self {
+        Self {
+            weights: network
+                .weights
+                .iter()
+                .map(|w| vec![T::zero(); w.len()])
+                .collect(),
+            biases: network
+                .biases
+                .iter()
+                .map(|b| vec![T::zero(); b.len()])
+                .collect(),
+        }
+    }
+
+    fn scale(&mut self, factor: T) {
+        for values in self.weights.iter_mut().chain(self.biases.iter_mut()) {
+            values.iter_mut().for_each(|g| *g = *g * factor);
+        }
+    }
+
+    /// Add gradients of the weight layers starting at `offset`
+    fn add(&mut self, offset: usize, weights: &[Vec<T>], biases: &[Vec<T>]) {
+        for (mine, theirs) in self.weights[offset..]
+            .iter_mut()
+            .zip(weights)
+            .chain(self.biases[offset..].iter_mut().zip(biases))
+        {
+            for (a, &b) in mine.iter_mut().zip(theirs) {
+                *a = *a + b;
+            }
+        }
+    }
+}
+
+/// Hands the error terms the second stage sends back to
+/// [`calculate_gradients`] as if they were output derivatives; they travel
+/// in the slot of the desired output
+struct UpstreamError;
+
+impl<T: Float> ErrorFunction<T> for UpstreamError {
+    fn calculate(&self, _actual: &[T], _desired: &[T]) -> T {
+        T::zero()
+    }
+
+    fn derivative(&self, _actual: T, upstream: T) -> T {
+        upstream
+    }
+}
+
+/// One stage: the weight layers `layers` of the network (weight layer `l`
+/// connects neuron layer `l` to `l + 1`) as a network of their own
+struct Stage<T: Float> {
+    network: SimpleNetwork<T>,
+    offset: usize,
+}
+
+impl<T: Float> Stage<T> {
+    fn new(network: &SimpleNetwork<T>, layers: Range<usize>) -> Self {
+        let neurons = layers.start..layers.end + 1;
+        Self {
+            network: SimpleNetwork {
+                layer_sizes: network.layer_sizes[neurons.clone()].to_vec(),
+                weights: network.weights[layers.clone()].to_vec(),
+                biases: network.biases[layers.clone()].to_vec(),
+                dropout: network.dropout[neurons].to_vec(),
+                connections: network.connections[layers.clone()].to_vec(),
+            },
+            offset: layers.start,
+        }
+    }
+
+    /// Activations of the stage's neuron layers, starting from `input`
+    fn forward(&self, input: &[T]) -> Vec<Vec<T>> {
+        forward_propagate(&self.network, input)
+    }
+
+    /// Accumulate the stage's gradients for the derivatives of the error
+    /// with respect to its outputs (`desired` under `error_function`) and
+    /// return the error reaching its inputs
+    fn backward(
+        &self,
+        activations: &[Vec<T>],
+        desired: &[T],
+        error_function: &dyn ErrorFunction<T>,
+        gradients: &mut Gradients<T>,
+    ) -> Vec<T> {
+        let (weights, biases) =
+            calculate_gradients(&self.network, activations, desired, error_function);
+        gradients.add(self.offset, &weights, &biases);
+        input_errors(&self.network, &biases)
+    }
+}
+
+/// Gradient descent with the layers split across two pipelined CPU stages
+pub struct PipelineParallel<T: Float + Send + Sync + Default> {
+    learning_rate: T,
+    /// Neuron layer at the stage boundary; `None` splits the weight layers
+    /// in half
+    split: Option<usize>,
+    micro_batches: usize,
+    batch_size: usize,
+    error_function: Box<dyn ErrorFunction<T>>,
+    callbacks: EventDispatcher<T>,
+    cancellation: Option<CancellationToken>,
+    statistics: TrainingStatistics<T>,
+}
+
+impl<T: Float + Send + Sync + Default> PipelineParallel<T> {
+    pub fn new(learning_rate: T) -> Self {
+        Self {
+            learning_rate,
+            split: None,
+            micro_batches: 4,
+            batch_size: 32,
+            error_function: Box::new(MseError),
+            callbacks: EventDispatcher::new(),
+            cancellation: None,
+            statistics: TrainingStatistics::new(),
+        }
+    }
+
+    /// Put layers `1..=split` on the first stage and the rest on the second
+    pub fn with_split(mut self, split: usize) -> Self {
+        self.split = Some(split);
+        self
+    }
+
+    /// Number of micro-batches each mini-batch is cut into (at least 1)
+    pub fn with_micro_batches(mut self, micro_batches: usize) -> Self {
+        self.micro_batches = micro_batches.max(1);
+        self
+    }
+
+    /// Samples per weight update (at least 1)
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_error_function(mut self, error_function: Box<dyn ErrorFunction<T>>) -> Self {
+        self.error_function = error_function;
+        self
+    }
+
+    /// Fraction of the schedule each stage spends idle
+    pub fn bubble_fraction(&self) -> f64 {
+        1.0 / (self.micro_batches as f64 + 1.0)
+    }
+
+    /// Boundary neuron layer for a network with `num_layers` layers
+    fn boundary(&self, num_layers: usize) -> Result<usize, TrainingError> {
+        if num_layers < 3 {
+            return Err(TrainingError::NetworkError(
+                "pipeline training needs at least one hidden layer to split at".to_string(),
+            ));
+        }
+        let split = self.split.unwrap_or(num_layers / 2);
+        if split == 0 || split >= num_layers - 1 {
+            return Err(TrainingError::NetworkError(format!(
+                "split layer {split} must be a hidden layer (1..{})",
+                num_layers - 1
+            )));
+        }
+        Ok(split)
+    }
+
+    /// Run one mini-batch through both stages and return its summed error
+    /// and gradients
+    ///
+    /// A panic in the second stage, e.g. from a custom error function, is
+    /// reported as [`TrainingError::TrainingFailed`].
+    fn run_batch(
+        &self,
+        network: &SimpleNetwork<T>,
+        split: usize,
+        inputs: &[Vec<T>],
+        outputs: &[Vec<T>],
+    ) -> Result<(T, Gradients<T>), TrainingError> {
+        let first = Stage::new(network, 0..split);
+        let second = Stage::new(network, split..network.weights.len());
+        let micro_size = inputs.len().div_ceil(self.micro_batches).max(1);
+        let error_function = self.error_function.as_ref();
+
+        let (forward_tx, forward_rx) = mpsc::channel::<Vec<Vec<T>>>();
+        let (backward_tx, backward_rx) = mpsc::channel::<Vec<Vec<T>>>();
+
+        std::thread::scope(|scope| {
+            let second_stage = scope.spawn(move || {
+                let mut gradients = Gradients::zeros(network);
+                let mut error = T::zero();
+                for (boundary, desired) in forward_rx.iter().zip(outputs.chunks(micro_size)) {
+                    let mut errors_back = Vec::with_capacity(boundary.len());
+                    for (input, desired) in boundary.iter().zip(desired) {
+                        let activations = second.forward(input);
+                        let output = &activations[activations.len() - 1];
+                        error = error + error_function.calculate(output, desired);
+                        errors_back.push(second.backward(
+                            &activations,
+                            desired,
+                            error_function,
+                            &mut gradients,
+                        ));
+                    }
+                    if backward_tx.send(errors_back).is_err() {
+                        break;
+                    }
+                }
+                (error, gradients)
+            });
+
+            // First stage: forward every micro-batch, then drain the error
+            // terms as the second stage returns them
+            let mut gradients = Gradients::zeros(network);
+            let mut stash = Vec::with_capacity(self.micro_batches);
+            for micro_batch in inputs.chunks(micro_size) {
+                let activations: Vec<Vec<Vec<T>>> = micro_batch
+                    .iter()
+                    .map(|input| first.forward(input))
+                    .collect();
+                let boundary = activations.iter().map(|a| a[a.len() - 1].clone()).collect();
+                stash.push(activations);
+                if forward_tx.send(boundary).is_err() {
+                    break;
+                }
+            }
+            drop(forward_tx);
+            for activations in stash {
+                let Ok(errors) = backward_rx.recv() else {
+                    break;
+                };
+                for (activations, errors) in activations.iter().zip(errors) {
+                    first.backward(activations, &errors, &UpstreamError, &mut gradients);
+                }
+            }
+
+            let (error, second_gradients) = second_stage.join().map_err(|_| {
+                TrainingError::TrainingFailed("pipeline stage worker panicked".to_string())
+            })?;
+            gradients.add(0, &second_gradients.weights, &second_gradients.biases);
+            Ok((error, gradients))
+        })
+    }
+}
+
+impl<T: Float + Send + Sync + Default> TrainingAlgorithm<T> for PipelineParallel<T> {
+    fn train_epoch(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<T, TrainingError> {
+        data.check_shapes(network)?;
+        let epoch_start = Instant::now();
+        if data.inputs.is_empty() {
+            return Err(TrainingError::InvalidData(
+                "no training samples".to_string(),
+            ));
+        }
+        let split = self.boundary(network.layers.len())?;
+
+        let mut total_error = T::zero();
+        let mut gradient_norm_sum = T::zero();
+        let mut batches = 0;
+        let mut seen = 0;
+        for (inputs, outputs) in data
+            .inputs
+            .chunks(self.batch_size)
+            .zip(data.outputs.chunks(self.batch_size))
+        {
+            let simple = network_to_simple(network);
+            let (error, mut gradients) = self.run_batch(&simple, split, inputs, outputs)?;
+            total_error = total_error + error;
+
+            let count = cast(inputs.len());
+            gradients.scale(T::one() / count);
+            gradient_norm_sum =
+                gradient_norm_sum + gradient_norm(&gradients.weights, &gradients.biases);
+            batches += 1;
+            gradients.scale(-self.learning_rate);
+            helpers::apply_updates_to_network(network, &gradients.weights, &gradients.biases);
+            seen += inputs.len();
+            if self.is_cancelled() {
+                break;
+            }
+        }
+
+        let simple = network_to_simple(network);
+        self.statistics.record_epoch(
+            epoch_start.elapsed(),
+            seen,
+            gradient_norm_sum / cast(batches),
+            buffer_bytes(&[&simple.weights, &simple.biases]),
+        );
+        Ok(total_error / cast(seen))
+    }
+
+    fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
+        super::evaluation::batch_error(network, data, self.error_function.as_ref())
+    }
+
+    fn count_bit_fails(
+        &self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+        bit_fail_limit: T,
+    ) -> usize {
+        super::evaluation::batch_bit_fails(network, data, bit_fail_limit)
+    }
+
+    fn save_state(&self) -> TrainingState<T> {
+        let mut state = HashMap::new();
+        state.insert("learning_rate".to_string(), vec![self.learning_rate]);
+        TrainingState {
+            epoch: 0,
+            best_error: cast(f32::MAX),
+            algorithm_specific: state,
+        }
+    }
+
+    fn restore_state(&mut self, state: TrainingState<T>) {
+        if let Some(&lr) = state
+            .algorithm_specific
+            .get("learning_rate")
+            .and_then(|v| v.first())
+        {
+            self.learning_rate = lr;
+        }
+    }
+
+    fn learning_rate(&self) -> Option<T> {
+        Some(self.learning_rate)
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.learning_rate = rate;
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.statistics.last_gradient_norm()
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    fn set_callback(&mut self, callback: TrainingCallback<T>) {
+        self.callbacks.subscribe(callback);
+    }
+
+    fn call_callback(
+        &mut self,
+        epoch: usize,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> bool {
+        if self.callbacks.is_empty() {
+            return true;
+        }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
+    }
+}
+
+impl<T: Float + Send + Sync + Default> AdvancedTrainingAlgorithm<T> for PipelineParallel<T> {
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        &self.statistics
+    }
+
+    fn reset_statistics(&mut self) {
+        self.statistics.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_pipeline_matches_batch_backprop() {
+        let build = || {
+            NetworkBuilder::<f64>::new()
+                .input_layer(2)
+                .hidden_layer(5)
+                .hidden_layer(4)
+                .output_layer(1)
+                .build()
+        };
+        let data = TrainingData {
+            inputs: vec![
+                vec![0.0, 0.0],
+                vec![0.0, 1.0],
+                vec![1.0, 0.0],
+                vec![1.0, 1.0],
+                vec![0.5, 0.2],
+            ],
+            outputs: vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0], vec![0.4]],
+        };
+        let mut reference = build();
+        let mut pipelined = reference.clone();
+
+        let mut batch = BatchBackprop::new(0.5);
+        let mut pipeline = PipelineParallel::new(0.5)
+            .with_split(1)
+            .with_micro_batches(3)
+            .with_batch_size(data.inputs.len());
+        for _ in 0..5 {
+            let expected = batch.train_epoch(&mut reference, &data).unwrap();
+            let error = pipeline.train_epoch(&mut pipelined, &data).unwrap();
+            assert!((expected - error).abs() < 1e-12);
+        }
+        let expected = reference.get_weights();
+        for (a, b) in pipelined.get_weights().iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        assert_eq!(pipeline.bubble_fraction(), 0.25);
+        assert_eq!(pipeline.statistics().epochs, 5);
+
+        // The boundary must be a hidden layer
+        let mut shallow = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        assert!(pipeline.train_epoch(&mut shallow, &data).is_err());
+        let mut network = build();
+        let mut bad_split = PipelineParallel::new(0.5).with_split(3);
+        assert!(bad_split.train_epoch(&mut network, &data).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+//! Differentially private training (DP-SGD)
+//!
+//! [`DifferentialPrivacy`] replaces an optimizer's batch gradient with the
+//! DP-SGD estimate of Abadi et al. (2016): every sample's gradient is clipped
+//! to an L2 norm of at most `clip_norm`, the clipped gradients are summed,
+//! Gaussian noise with standard deviation `noise_multiplier * clip_norm` is
+//! added to each coordinate and the result is divided by the batch size.
+//! [`BatchBackprop`](super::BatchBackprop) (SGD) and [`Adam`](super::Adam)
+//! take one through `with_differential_privacy`; the optimizer then updates
+//! from the private gradient as usual.
+//!
+//! Every private step is recorded in a [`PrivacyAccountant`], which tracks
+//! the Rényi differential privacy of the sampled Gaussian mechanism
+//! (Mironov et al., 2019) and converts it to an `(epsilon, delta)` budget.
+//! With mini-batches the sampling rate of each step is `batch / samples`;
+//! the bound assumes Poisson sampling, which shuffled fixed-size batches
+//! only approximate. Selecting samples by their loss, as a
+//! [`TrimmedLoss`](super::TrimmedLoss) does, is not covered by the bound.
+
+use super::{ErrorFunction, TrainingData};
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::StandardNormal;
+
+/// Rényi orders the accountant evaluates
+const RDP_ORDERS: [u32; 14] = [2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 32, 64, 128, 256];
+
+/// Per-sample clipping and Gaussian noise for batch gradients
+pub struct DifferentialPrivacy<T: Float> {
+    clip_norm: T,
+    noise_multiplier: T,
+    rng: StdRng,
+    population: Option<usize>,
+    accountant: PrivacyAccountant,
+}
+
+impl<T: Float> DifferentialPrivacy<T> {
+    /// Clip each sample's gradient to `clip_norm` and add noise with standard
+    /// deviation `noise_multiplier * clip_norm`; negative values are treated
+    /// as 0
+    pub fn new(clip_norm: T, noise_multiplier: T) -> Self {
+        let clip_norm = clip_norm.max(T::zero());
+        let noise_multiplier = noise_multiplier.max(T::zero());
+        Self {
+            clip_norm,
+            noise_multiplier,
+            rng: StdRng::from_entropy(),
+            population: None,
+            accountant: PrivacyAccountant::new(noise_multiplier.to_f64().unwrap_or(0.0)),
+        }
+    }
+
+    /// Draw the noise from a seeded generator
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn clip_norm(&self) -> T {
+        self.clip_norm
+    }
+
+    pub fn noise_multiplier(&self) -> T {
+        self.noise_multiplier
+    }
+
+    /// Privacy spent so far
+    pub fn accountant(&self) -> &PrivacyAccountant {
+        &self.accountant
+    }
+
+    /// Epsilon spent so far at the given `delta`
+    pub fn epsilon(&self, delta: f64) -> f64 {
+        self.accountant.epsilon(delta)
+    }
+
+    /// Size of the data set the following batches are drawn from; `None`
+    /// means every batch is the whole data set
+    pub(super) fn set_population(&mut self, samples: Option<usize>) {
+        self.population = samples;
+    }
+
+    /// Mean error and private mean gradients of `data`, in the layered
+    /// layout of [`helpers::SimpleNetwork`](super::helpers::SimpleNetwork)
+    pub fn gradients(
+        &mut self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+        error_function: &dyn ErrorFunction<T>,
+    ) -> (T, Vec<Vec<T>>, Vec<Vec<T>>)
+    where
+        T: Default,
+    {
+        use super::helpers::*;
+
+        let simple_network = network_to_simple(network);
+        let mut weight_sums: Vec<Vec<T>> = simple_network
+            .weights
+            .iter()
+            .map(|w| vec![T::zero(); w.len()])
+            .collect();
+        let mut bias_sums: Vec<Vec<T>> = simple_network
+            .biases
+            .iter()
+            .map(|b| vec![T::zero(); b.len()])
+            .collect();
+        let mut total_error = T::zero();
+
+        for (input, desired_output) in data.inputs.iter().zip(&data.outputs) {
+            let masks = dropout_masks(&simple_network, &mut self.rng);
+            let activations = forward_propagate_masked(&simple_network, input, &masks);
+            let output = &activations[activations.len() - 1];
+            total_error = total_error + error_function.calculate(output, desired_output);
+
+            let (weight_gradients, bias_gradients) = calculate_gradients_masked(
+                &simple_network,
+                &activations,
+                &masks,
+                desired_output,
+                error_function,
+            );
+            let norm = weight_gradients
+                .iter()
+                .chain(&bias_gradients)
+                .flatten()
+                .fold(T::zero(), |sum, &g| sum + g * g)
+                .sqrt();
+            let scale = if norm > self.clip_norm {
+                self.clip_norm / norm
+            } else {
+                T::one()
+            };
+            for (sums, gradients) in weight_sums
+                .iter_mut()
+                .chain(bias_sums.iter_mut())
+                .zip(weight_gradients.iter().chain(&bias_gradients))
+            {
+                for (sum, &gradient) in sums.iter_mut().zip(gradients) {
+                    *sum = *sum + gradient * scale;
+                }
+            }
+        }
+
+        let samples = data.inputs.len().max(1);
+        let std_dev = self.noise_multiplier * self.clip_norm;
+        let batch_size: T = cast(samples);
+        for value in weight_sums.iter_mut().chain(bias_sums.iter_mut()).flatten() {
+            let noise: f64 = self.rng.sample(StandardNormal);
+            *value = (*value + std_dev * cast(noise)) / batch_size;
+        }
+
+        let population = self.population.unwrap_or(samples).max(samples);
+        self.accountant.record(samples as f64 / population as f64);
+        (total_error / batch_size, weight_sums, bias_sums)
+    }
+}
+
+/// Rényi-DP accountant for the sampled Gaussian mechanism
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivacyAccountant {
+    noise_multiplier: f64,
+    /// Distinct sampling rates with the number of steps taken at each
+    steps: Vec<(f64, usize)>,
+}
+
+impl PrivacyAccountant {
+    pub fn new(noise_multiplier: f64) -> Self {
+        Self {
+            noise_multiplier,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Account for one step that used a `sampling_rate` fraction of the data
+    pub fn record(&mut self, sampling_rate: f64) {
+        let rate = sampling_rate.clamp(0.0, 1.0);
+        match self.steps.iter_mut().find(|(q, _)| *q == rate) {
+            Some((_, count)) => *count += 1,
+            None => self.steps.push((rate, 1)),
+        }
+    }
+
+    /// Private steps recorded so far
+    pub fn steps(&self) -> usize {
+        self.steps.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Total Rényi divergence of order `alpha` over all recorded steps
+    pub fn rdp(&self, alpha: u32) -> f64 {
+        self.steps
+            .iter()
+            .map(|&(q, count)| count as f64 * sampled_gaussian_rdp(q, self.noise_multiplier, alpha))
+            .sum()
+    }
+
+    /// Smallest epsilon such that training so far is `(epsilon, delta)`-DP;
+    /// infinite without noise
+    pub fn epsilon(&self, delta: f64) -> f64 {
+        if self.steps.is_empty() {
+            return 0.0;
+        }
+        let log_delta = delta.clamp(f64::MIN_POSITIVE, 1.0).ln();
+        RDP_ORDERS
+            .iter()
+            .map(|&alpha| self.rdp(alpha) - log_delta / f64::from(alpha - 1))
+            .fold(f64::INFINITY, f64::min)
+    }
+}
+
+/// RDP of one step of the Gaussian mechanism with Poisson sampling rate `q`
+/// at integer order `alpha` (Mironov, Talwar & Zhang, 2019)
+fn sampled_gaussian_rdp(q: f64, sigma: f64, alpha: u32) -> f64 {
+    if q <= 0.0 {
+        return 0.0;
+    }
+    if sigma <= 0.0 {
+        return f64::INFINITY;
+    }
+    let alpha_f = f64::from(alpha);
+    let variance = sigma * sigma;
+    if q >= 1.0 {
+        return alpha_f / (2.0 * variance);
+    }
+    // log sum_k C(alpha, k) (1-q)^(alpha-k) q^k exp((k^2 - k) / (2 sigma^2))
+    let (log_q, log_1mq) = (q.ln(), (-q).ln_1p());
+    let mut log_binomial = 0.0;
+    let mut terms = Vec::with_capacity(alpha as usize + 1);
+    for k in 0..=alpha {
+        let k_f = f64::from(k);
+        terms.push(
+            log_binomial
+                + (alpha_f - k_f) * log_1mq
+                + k_f * log_q
+                + (k_f * k_f - k_f) / (2.0 * variance),
+        );
+        log_binomial += (alpha_f - k_f).ln() - (k_f + 1.0).ln();
+    }
+    let max = terms.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let log_sum = max + terms.iter().map(|t| (t - max).exp()).sum::<f64>().ln();
+    log_sum / (alpha_f - 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::{Adam, BatchIterator, MseError, TrainingAlgorithm, WithTrainingOptions};
+    use crate::NetworkBuilder;
+
+    fn setup() -> (Network<f64>, TrainingData<f64>) {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let data = TrainingData {
+            inputs: (0..8)
+                .map(|i| vec![i as f64 / 8.0, 1.0 - i as f64 / 8.0])
+                .collect(),
+            outputs: (0..8)
+                .map(|i| vec![if i == 2 { 40.0 } else { 0.5 }])
+                .collect(),
+        };
+        (network, data)
+    }
+
+    #[test]
+    fn test_gradients_are_clipped_and_noised() {
+        let (network, data) = setup();
+        let norm = |w: &[Vec<f64>], b: &[Vec<f64>]| {
+            w.iter()
+                .chain(b)
+                .flatten()
+                .map(|g| g * g)
+                .sum::<f64>()
+                .sqrt()
+        };
+
+        let mut clipped = DifferentialPrivacy::new(0.01, 0.0);
+        let (_, weights, biases) = clipped.gradients(&network, &data, &MseError);
+        assert!(norm(&weights, &biases) <= 0.01 + 1e-12);
+        assert_eq!(clipped.accountant().steps(), 1);
+        assert_eq!(clipped.epsilon(1e-5), f64::INFINITY);
+
+        let mut first = DifferentialPrivacy::new(0.01, 1.0).with_seed(4);
+        let mut second = DifferentialPrivacy::new(0.01, 1.0).with_seed(4);
+        let noisy = first.gradients(&network, &data, &MseError);
+        assert_eq!(noisy, second.gradients(&network, &data, &MseError));
+        assert_ne!(noisy.1, weights);
+    }
+
+    #[test]
+    fn test_accountant_budget() {
+        // Without subsampling the Gaussian mechanism has RDP alpha / (2 sigma^2)
+        assert!((sampled_gaussian_rdp(1.0, 2.0, 4) - 0.5).abs() < 1e-12);
+        assert!(sampled_gaussian_rdp(0.999_999, 2.0, 4) <= 0.5 + 1e-6);
+        assert!(sampled_gaussian_rdp(0.01, 2.0, 4) < sampled_gaussian_rdp(0.1, 2.0, 4));
+
+        let mut accountant = PrivacyAccountant::new(1.1);
+        accountant.record(0.01);
+        let one_step = accountant.epsilon(1e-5);
+        for _ in 0..99 {
+            accountant.record(0.01);
+        }
+        assert_eq!(accountant.steps(), 100);
+        assert!(accountant.epsilon(1e-5) > one_step);
+        assert!(accountant.epsilon(1e-5) < accountant.epsilon(1e-7));
+
+        // Each mini-batch is one step at rate batch / samples
+        let (mut network, data) = setup();
+        let mut adam = Adam::new(0.01)
+            .with_mini_batches(BatchIterator::new(2))
+            .with_differential_privacy(DifferentialPrivacy::new(1.0, 1.1).with_seed(1));
+        adam.train_epoch(&mut network, &data).unwrap();
+        let privacy = adam.privacy().unwrap();
+        assert_eq!(privacy.accountant().steps, [(0.25, 4)]);
+        assert!(privacy.epsilon(1e-5).is_finite());
+    }
+}
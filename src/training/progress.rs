@@ -0,0 +1,341 @@
+//! Progress display for command-line training
+//!
+//! [`ProgressTracker`] turns [`TrainingEvent`]s into [`ProgressUpdate`]s with an
+//! ETA and forwards them to a [`ProgressSink`]. The sink methods mirror those of
+//! `indicatif::ProgressBar`, so a bar can be plugged in with a thin wrapper:
+//!
+//! ```ignore
+//! struct Bar(indicatif::ProgressBar);
+//!
+//! impl ProgressSink for Bar {
+//!     fn set_length(&mut self, len: u64) { self.0.set_length(len) }
+//!     fn set_position(&mut self, pos: u64) { self.0.set_position(pos) }
+//!     fn set_message(&mut self, message: String) { self.0.set_message(message) }
+//!     fn finish(&mut self) { self.0.finish() }
+//! }
+//! ```
+//!
+//! [`StderrProgress`] is a dependency-free sink that redraws one line on stderr.
+
+use super::{TrainingCallback, TrainingEvent};
+use num_traits::Float;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Snapshot of training progress
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressUpdate {
+    /// Completed epochs
+    pub epoch: usize,
+    pub total_epochs: usize,
+    /// Batch within the current epoch, for batch-level updates
+    pub batch: Option<usize>,
+    /// Most recent training error (of the batch or the epoch)
+    pub train_error: f64,
+    pub validation_error: Option<f64>,
+    pub elapsed: Duration,
+    /// Estimated time until `total_epochs` are completed
+    pub eta: Option<Duration>,
+}
+
+impl ProgressUpdate {
+    /// Short status line, e.g. `error 0.0125 | val 0.0200 | eta 12s`
+    pub fn message(&self) -> String {
+        let mut message = match self.batch {
+            Some(batch) => format!("batch {batch} | error {:.4}", self.train_error),
+            None => format!("error {:.4}", self.train_error),
+        };
+        if let Some(validation_error) = self.validation_error {
+            message.push_str(&format!(" | val {validation_error:.4}"));
+        }
+        if let Some(eta) = self.eta {
+            message.push_str(&format!(" | eta {}s", eta.as_secs()));
+        }
+        message
+    }
+}
+
+/// Receiver of progress updates, shaped after `indicatif::ProgressBar`
+pub trait ProgressSink: Send {
+    fn set_length(&mut self, len: u64);
+    fn set_position(&mut self, pos: u64);
+    fn set_message(&mut self, message: String);
+    fn finish(&mut self);
+
+    /// Receive a full update; the default forwards the position and message
+    fn update(&mut self, update: &ProgressUpdate) {
+        self.set_position(update.epoch as u64);
+        self.set_message(update.message());
+    }
+}
+
+/// Converts training events into progress updates
+///
+/// The sink is finished when the last epoch completes, when training ends
+/// early or fails, and at the latest when the tracker is dropped.
+pub struct ProgressTracker {
+    total_epochs: usize,
+    /// Set by the first event of a run
+    started: Option<Instant>,
+    last_epoch: usize,
+    finished: bool,
+    sink: Box<dyn ProgressSink>,
+}
+
+impl ProgressTracker {
+    /// Create a tracker for a run of `total_epochs`; the clock starts with
+    /// [`TrainingEvent::TrainingStart`] or the first progress event
+    pub fn new(total_epochs: usize, mut sink: Box<dyn ProgressSink>) -> Self {
+        sink.set_length(total_epochs as u64);
+        Self {
+            total_epochs,
+            started: None,
+            last_epoch: 0,
+            finished: false,
+            sink,
+        }
+    }
+
+    fn start(&mut self) {
+        self.started = Some(Instant::now());
+        self.last_epoch = 0;
+        self.finished = false;
+    }
+
+    fn finish(&mut self) {
+        if self.started.is_some() && !self.finished {
+            self.finished = true;
+            self.sink.finish();
+        }
+    }
+
+    /// Process one event, returning the update sent to the sink, if any
+    ///
+    /// An event for an earlier epoch than the previous one starts a new run and
+    /// restarts the clock.
+    pub fn handle<T: Float>(&mut self, event: &TrainingEvent<T>) -> Option<ProgressUpdate> {
+        let (epoch, batch, train_error, validation_error) = match event {
+            TrainingEvent::TrainingStart { total_epochs } => {
+                self.total_epochs = *total_epochs;
+                self.sink.set_length(*total_epochs as u64);
+                self.start();
+                return None;
+            }
+            TrainingEvent::TrainingEnd => {
+                self.finish();
+                return None;
+            }
+            TrainingEvent::EpochEnd { epoch, metrics } => (
+                epoch + 1,
+                None,
+                metrics.train_error,
+                metrics.validation_error,
+            ),
+            TrainingEvent::BatchEnd {
+                epoch,
+                batch,
+                error,
+            } => (*epoch, Some(*batch), *error, None),
+            _ => return None,
+        };
+
+        if self.started.is_none() || epoch < self.last_epoch {
+            self.start();
+        }
+        self.last_epoch = epoch;
+
+        let elapsed = self
+            .started
+            .map_or(Duration::ZERO, |started| started.elapsed());
+        let remaining = self.total_epochs.saturating_sub(epoch);
+        let eta = u32::try_from(epoch)
+            .ok()
+            .filter(|&done| done > 0)
+            .and_then(|done| u32::try_from(remaining).ok().map(|r| elapsed / done * r));
+
+        let update = ProgressUpdate {
+            epoch,
+            total_epochs: self.total_epochs,
+            batch,
+            train_error: train_error.to_f64().unwrap_or(f64::NAN),
+            validation_error: validation_error.and_then(|e| e.to_f64()),
+            elapsed,
+            eta,
+        };
+        self.sink.update(&update);
+        if batch.is_none() && epoch >= self.total_epochs {
+            self.finish();
+        }
+        Some(update)
+    }
+
+    /// Wrap the tracker in a subscriber for an [`EventDispatcher`](super::EventDispatcher)
+    pub fn into_callback<T: Float>(mut self) -> TrainingCallback<T> {
+        Box::new(move |event| {
+            self.handle(event);
+            true
+        })
+    }
+}
+
+impl Drop for ProgressTracker {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Progress bar drawn on a single stderr line
+#[derive(Debug, Default)]
+pub struct StderrProgress {
+    len: u64,
+    pos: u64,
+    message: String,
+}
+
+impl StderrProgress {
+    /// Width of the bar in characters
+    const WIDTH: usize = 30;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn render(&self) -> String {
+        let filled = if self.len == 0 {
+            0
+        } else {
+            (self.pos.min(self.len) as usize * Self::WIDTH) / self.len as usize
+        };
+        format!(
+            "[{}{}] {}/{} {}",
+            "=".repeat(filled),
+            " ".repeat(Self::WIDTH - filled),
+            self.pos,
+            self.len,
+            self.message
+        )
+    }
+
+    fn draw(&self) {
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r{}\x1b[K", self.render());
+        let _ = stderr.flush();
+    }
+}
+
+impl ProgressSink for StderrProgress {
+    fn set_length(&mut self, len: u64) {
+        self.len = len;
+    }
+
+    fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    fn set_message(&mut self, message: String) {
+        self.message = message;
+        self.draw();
+    }
+
+    fn finish(&mut self) {
+        self.draw();
+        let _ = writeln!(std::io::stderr());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::{IncrementalBackprop, Trainer, TrainingData};
+    use crate::NetworkBuilder;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Recorded {
+        len: u64,
+        positions: Vec<u64>,
+        finished: bool,
+    }
+
+    struct RecordingSink(Arc<Mutex<Recorded>>);
+
+    impl ProgressSink for RecordingSink {
+        fn set_length(&mut self, len: u64) {
+            self.0.lock().unwrap().len = len;
+        }
+
+        fn set_position(&mut self, pos: u64) {
+            self.0.lock().unwrap().positions.push(pos);
+        }
+
+        fn set_message(&mut self, _message: String) {}
+
+        fn finish(&mut self) {
+            self.0.lock().unwrap().finished = true;
+        }
+    }
+
+    #[test]
+    fn test_trainer_reports_progress() {
+        let recorded = Arc::new(Mutex::new(Recorded::default()));
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 3)
+            .with_progress(Box::new(RecordingSink(Arc::clone(&recorded))));
+        let data = TrainingData {
+            inputs: vec![vec![0.0f32, 1.0], vec![1.0, 0.0]],
+            outputs: vec![vec![1.0], vec![0.0]],
+        };
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        trainer.train(&mut network, &data).unwrap();
+
+        {
+            let recorded = recorded.lock().unwrap();
+            assert_eq!(recorded.len, 3);
+            assert_eq!(recorded.positions, vec![1, 2, 3]);
+            assert!(recorded.finished);
+        }
+
+        // A run that stops early still finishes the bar
+        let recorded = Arc::new(Mutex::new(Recorded::default()));
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 3)
+            .with_progress(Box::new(RecordingSink(Arc::clone(&recorded))));
+        trainer.subscribe(Box::new(|_| false));
+        let report = trainer.train(&mut network, &data).unwrap();
+        assert!(report.stopped_early);
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.positions, vec![1]);
+        assert!(recorded.finished);
+    }
+
+    #[test]
+    fn test_update_message_and_bar() {
+        let recorded = Arc::new(Mutex::new(Recorded::default()));
+        let mut tracker = ProgressTracker::new(4, Box::new(RecordingSink(Arc::clone(&recorded))));
+        let update = tracker
+            .handle(&TrainingEvent::BatchEnd {
+                epoch: 0,
+                batch: 2,
+                error: 0.5f64,
+            })
+            .unwrap();
+        assert_eq!(update.eta, None);
+        assert_eq!(update.message(), "batch 2 | error 0.5000");
+
+        // Dropping an unfinished tracker finishes its sink
+        assert!(!recorded.lock().unwrap().finished);
+        drop(tracker);
+        assert!(recorded.lock().unwrap().finished);
+
+        let bar = StderrProgress {
+            len: 4,
+            pos: 2,
+            message: "x".to_string(),
+        };
+        assert_eq!(
+            bar.render(),
+            format!("[{}{}] 2/4 x", "=".repeat(15), " ".repeat(15))
+        );
+    }
+}
@@ -2,16 +2,21 @@
 
 #![allow(clippy::needless_range_loop)]
 
+use super::centralization::centralize_gradients;
+use super::statistics::{buffer_bytes, gradient_norm};
 use super::*;
+use crate::numeric::cast;
 use num_traits::Float;
 use std::collections::HashMap;
+use std::time::Instant;
 
 /// Quickprop trainer
 /// An advanced batch training algorithm that uses second-order information
 pub struct Quickprop<T: Float + Send + Default> {
     learning_rate: T,
     mu: T,
-    decay: T,
+    regularization: Regularization<T>,
+    options: TrainingOptions,
     error_function: Box<dyn ErrorFunction<T>>,
 
     // State variables
@@ -20,28 +25,40 @@ pub struct Quickprop<T: Float + Send + Default> {
     previous_weight_deltas: Vec<Vec<T>>,
     previous_bias_deltas: Vec<Vec<T>>,
 
-    callback: Option<TrainingCallback<T>>,
+    callbacks: EventDispatcher<T>,
+    statistics: TrainingStatistics<T>,
 }
 
 impl<T: Float + Send + Default> Quickprop<T> {
     pub fn new() -> Self {
         Self {
-            learning_rate: T::from(0.7).unwrap(),
-            mu: T::from(1.75).unwrap(),
-            decay: T::from(-0.0001).unwrap(),
+            learning_rate: cast(0.7),
+            mu: cast(1.75),
+            regularization: Regularization::coupled(cast(0.0001)),
+            options: TrainingOptions::new(),
             error_function: Box::new(MseError),
             previous_weight_gradients: Vec::new(),
             previous_bias_gradients: Vec::new(),
             previous_weight_deltas: Vec::new(),
             previous_bias_deltas: Vec::new(),
-            callback: None,
+            callbacks: EventDispatcher::new(),
+            statistics: TrainingStatistics::new(),
         }
     }
 
+    /// `decay` follows FANN's sign convention (negative values shrink the
+    /// weights) and sets a coupled [`Regularization`] of `-decay`
     pub fn with_parameters(mut self, learning_rate: T, mu: T, decay: T) -> Self {
         self.learning_rate = learning_rate;
         self.mu = mu;
-        self.decay = decay;
+        self.regularization = Regularization::coupled(-decay);
+        self
+    }
+
+    /// Set the weight decay configuration; defaults to FANN's coupled decay
+    /// of 0.0001
+    pub fn with_regularization(mut self, regularization: Regularization<T>) -> Self {
+        self.regularization = regularization;
         self
     }
 
@@ -53,20 +70,7 @@ impl<T: Float + Send + Default> Quickprop<T> {
     fn initialize_state(&mut self, network: &Network<T>) {
         if self.previous_weight_gradients.is_empty() {
             // Initialize state for each layer
-            self.previous_weight_gradients = network
-                .layers
-                .iter()
-                .skip(1) // Skip input layer
-                .map(|layer| {
-                    let num_neurons = layer.neurons.len();
-                    let num_connections = if layer.neurons.is_empty() {
-                        0
-                    } else {
-                        layer.neurons[0].connections.len()
-                    };
-                    vec![T::zero(); num_neurons * num_connections]
-                })
-                .collect();
+            self.previous_weight_gradients = super::helpers::weight_buffers(network, T::zero());
 
             self.previous_bias_gradients = network
                 .layers
@@ -75,20 +79,7 @@ impl<T: Float + Send + Default> Quickprop<T> {
                 .map(|layer| vec![T::zero(); layer.neurons.len()])
                 .collect();
 
-            self.previous_weight_deltas = network
-                .layers
-                .iter()
-                .skip(1) // Skip input layer
-                .map(|layer| {
-                    let num_neurons = layer.neurons.len();
-                    let num_connections = if layer.neurons.is_empty() {
-                        0
-                    } else {
-                        layer.neurons[0].connections.len()
-                    };
-                    vec![T::zero(); num_neurons * num_connections]
-                })
-                .collect();
+            self.previous_weight_deltas = super::helpers::weight_buffers(network, T::zero());
 
             self.previous_bias_deltas = network
                 .layers
@@ -99,23 +90,17 @@ impl<T: Float + Send + Default> Quickprop<T> {
         }
     }
 
-    fn calculate_quickprop_delta(
-        &self,
-        gradient: T,
-        previous_gradient: T,
-        previous_delta: T,
-        weight: T,
-    ) -> T {
+    fn calculate_quickprop_delta(&self, gradient: T, previous_gradient: T, previous_delta: T) -> T {
         if previous_gradient == T::zero() {
             // First epoch or no previous gradient: use standard gradient descent
-            return -self.learning_rate * gradient + self.decay * weight;
+            return -self.learning_rate * gradient;
         }
 
         let gradient_diff = gradient - previous_gradient;
 
         if gradient_diff == T::zero() {
             // No change in gradient: use momentum-like update
-            return -self.learning_rate * gradient + self.decay * weight;
+            return -self.learning_rate * gradient;
         }
 
         // Quickprop formula: delta = (gradient / (previous_gradient - gradient)) * previous_delta
@@ -132,8 +117,7 @@ impl<T: Float + Send + Default> Quickprop<T> {
             };
         }
 
-        // Add decay term
-        delta + self.decay * weight
+        delta
     }
 }
 
@@ -143,7 +127,7 @@ impl<T: Float + Send + Default> Default for Quickprop<T> {
     }
 }
 
-impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
+impl<T: Float + Send + Sync + Default> TrainingAlgorithm<T> for Quickprop<T> {
     fn train_epoch(
         &mut self,
         network: &mut Network<T>,
@@ -151,6 +135,19 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
     ) -> Result<T, TrainingError> {
         use super::helpers::*;
 
+        if let Some(mut batches) = self.options.mini_batches.take() {
+            let error = batches.train_epoch(self, network, data);
+            self.options.mini_batches = Some(batches);
+            return error;
+        }
+        data.check_shapes(network)?;
+        let trimmed = self
+            .options
+            .trimmed_loss
+            .trim(network, data, self.error_function.as_ref());
+        let data = trimmed.as_ref();
+        let epoch_start = Instant::now();
+
         self.initialize_state(network);
 
         let mut total_error = T::zero();
@@ -171,9 +168,15 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
             .collect::<Vec<_>>();
 
         // Calculate gradients over entire dataset
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
+        let mut rng = rand::thread_rng();
+        for (seen, (input, desired_output)) in data.inputs.iter().zip(&data.outputs).enumerate() {
+            // A cancelled epoch ends without an update
+            if self.is_cancelled() {
+                return Ok(total_error / cast(seen.max(1)));
+            }
             // Forward propagation to get all layer activations
-            let activations = forward_propagate(&simple_network, input);
+            let masks = dropout_masks(&simple_network, &mut rng);
+            let activations = forward_propagate_masked(&simple_network, input, &masks);
 
             // Get output from last layer
             let output = &activations[activations.len() - 1];
@@ -182,9 +185,10 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
             total_error = total_error + self.error_function.calculate(output, desired_output);
 
             // Calculate gradients using backpropagation
-            let (weight_gradients, bias_gradients) = calculate_gradients(
+            let (weight_gradients, bias_gradients) = calculate_gradients_masked(
                 &simple_network,
                 &activations,
+                &masks,
                 desired_output,
                 self.error_function.as_ref(),
             );
@@ -203,7 +207,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
         }
 
         // Average gradients by batch size
-        let batch_size = T::from(data.inputs.len()).unwrap();
+        let batch_size = cast(data.inputs.len());
         for layer_idx in 0..accumulated_weight_gradients.len() {
             for i in 0..accumulated_weight_gradients[layer_idx].len() {
                 accumulated_weight_gradients[layer_idx][i] =
@@ -215,6 +219,17 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
             }
         }
 
+        if self.options.gradient_centralization {
+            centralize_gradients(network, &mut accumulated_weight_gradients);
+        }
+        self.regularization.add_to_gradients(
+            network,
+            &mut accumulated_weight_gradients,
+            &mut accumulated_bias_gradients,
+        );
+        self.regularization
+            .decay_weights(network, self.learning_rate);
+
         // Apply Quickprop updates
         let mut weight_updates = Vec::new();
         let mut bias_updates = Vec::new();
@@ -228,23 +243,15 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
                 let previous_gradient = self.previous_weight_gradients[layer_idx][i];
                 let previous_delta = self.previous_weight_deltas[layer_idx][i];
 
-                // Get current weight for decay term
-                let weight_idx = i;
-                let weight = if weight_idx < simple_network.weights[layer_idx].len() {
-                    simple_network.weights[layer_idx][weight_idx]
-                } else {
-                    T::zero()
-                };
-
                 let delta = if previous_gradient == T::zero() {
-                    // First epoch or no previous gradient: use standard gradient descent with decay
-                    -self.learning_rate * current_gradient + self.decay * weight
+                    // First epoch or no previous gradient: use standard gradient descent
+                    -self.learning_rate * current_gradient
                 } else {
                     let gradient_diff = previous_gradient - current_gradient;
 
-                    if gradient_diff.abs() < T::from(1e-15).unwrap() {
-                        // Gradient difference too small: use momentum-like update with decay
-                        -self.learning_rate * current_gradient + self.decay * weight
+                    if gradient_diff.abs() < cast(1e-15) {
+                        // Gradient difference too small: use momentum-like update
+                        -self.learning_rate * current_gradient
                     } else {
                         // Quickprop formula: delta = (gradient / (previous_gradient - gradient)) * previous_delta
                         let mut quickprop_delta =
@@ -266,8 +273,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
                                 quickprop_delta - self.learning_rate * current_gradient;
                         }
 
-                        // Add decay term
-                        quickprop_delta + self.decay * weight
+                        quickprop_delta
                     }
                 };
 
@@ -281,7 +287,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
             weight_updates.push(layer_weight_updates);
         }
 
-        // Update biases using Quickprop algorithm (no decay for biases)
+        // Update biases using Quickprop algorithm
         for layer_idx in 0..accumulated_bias_gradients.len() {
             let mut layer_bias_updates = Vec::new();
 
@@ -296,7 +302,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
                 } else {
                     let gradient_diff = previous_gradient - current_gradient;
 
-                    if gradient_diff.abs() < T::from(1e-15).unwrap() {
+                    if gradient_diff.abs() < cast(1e-15) {
                         // Gradient difference too small: use momentum-like update
                         -self.learning_rate * current_gradient
                     } else {
@@ -337,19 +343,29 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
         // Apply the updates to the actual network
         apply_updates_to_network(network, &weight_updates, &bias_updates);
 
+        self.statistics.record_epoch(
+            epoch_start.elapsed(),
+            data.inputs.len(),
+            gradient_norm(&accumulated_weight_gradients, &accumulated_bias_gradients),
+            buffer_bytes(&[
+                &simple_network.weights,
+                &simple_network.biases,
+                &accumulated_weight_gradients,
+                &accumulated_bias_gradients,
+                &self.previous_weight_gradients,
+                &self.previous_bias_gradients,
+                &self.previous_weight_deltas,
+                &self.previous_bias_deltas,
+            ]),
+        );
+        self.statistics
+            .record_gradients(accumulated_weight_gradients);
+
         Ok(total_error / batch_size)
     }
 
     fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
-        let mut total_error = T::zero();
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-            total_error = total_error + self.error_function.calculate(&output, desired_output);
-        }
-
-        total_error / T::from(data.inputs.len()).unwrap()
+        super::evaluation::batch_error(network, data, self.error_function.as_ref())
     }
 
     fn count_bit_fails(
@@ -358,20 +374,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
         data: &TrainingData<T>,
         bit_fail_limit: T,
     ) -> usize {
-        let mut bit_fails = 0;
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-
-            for (&actual, &desired) in output.iter().zip(desired_output.iter()) {
-                if (actual - desired).abs() > bit_fail_limit {
-                    bit_fails += 1;
-                }
-            }
-        }
-
-        bit_fails
+        super::evaluation::batch_bit_fails(network, data, bit_fail_limit)
     }
 
     fn save_state(&self) -> TrainingState<T> {
@@ -380,7 +383,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
         // Save Quickprop parameters
         state.insert("learning_rate".to_string(), vec![self.learning_rate]);
         state.insert("mu".to_string(), vec![self.mu]);
-        state.insert("decay".to_string(), vec![self.decay]);
+        self.regularization.save(&mut state);
 
         // Save previous gradients and deltas (flattened)
         let mut all_weight_gradients = Vec::new();
@@ -412,7 +415,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
 
         TrainingState {
             epoch: 0,
-            best_error: T::from(f32::MAX).unwrap(),
+            best_error: cast(f32::MAX),
             algorithm_specific: state,
         }
     }
@@ -429,18 +432,44 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
                 self.mu = val[0];
             }
         }
+        // States saved before Regularization carry FANN's signed decay
         if let Some(val) = state.algorithm_specific.get("decay") {
             if !val.is_empty() {
-                self.decay = val[0];
+                self.regularization = Regularization::coupled(-val[0]);
             }
         }
+        self.regularization.restore(&state.algorithm_specific);
 
         // Note: Previous gradients and deltas would need network structure info to properly restore
         // This is a simplified version - in production, you'd need to store layer sizes too
     }
 
+    fn learning_rate(&self) -> Option<T> {
+        Some(self.learning_rate)
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.learning_rate = rate;
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.statistics.last_gradient_norm()
+    }
+
+    fn last_weight_gradients(&self) -> Option<&[Vec<T>]> {
+        self.statistics.last_weight_gradients()
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.options.cancellation = token;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.options.is_cancelled()
+    }
+
     fn set_callback(&mut self, callback: TrainingCallback<T>) {
-        self.callback = Some(callback);
+        self.callbacks.subscribe(callback);
     }
 
     fn call_callback(
@@ -449,11 +478,36 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Quickprop<T> {
         network: &Network<T>,
         data: &TrainingData<T>,
     ) -> bool {
-        let error = self.calculate_error(network, data);
-        if let Some(ref mut callback) = self.callback {
-            callback(epoch, error)
-        } else {
-            true
+        if self.callbacks.is_empty() {
+            return true;
         }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
+    }
+}
+
+impl<T: Float + Send + Default> WithTrainingOptions for Quickprop<T> {
+    fn training_options(&self) -> &TrainingOptions {
+        &self.options
+    }
+
+    fn training_options_mut(&mut self) -> &mut TrainingOptions {
+        &mut self.options
+    }
+}
+
+impl<T: Float + Send + Sync + Default> AdvancedTrainingAlgorithm<T> for Quickprop<T> {
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        &self.statistics
+    }
+
+    fn reset_statistics(&mut self) {
+        self.statistics.reset();
     }
 }
@@ -0,0 +1,368 @@
+//! Rectified Adam (RAdam)
+//!
+//! In its first steps Adam divides by a second-moment estimate built from
+//! only a handful of gradients, and its adaptive step size has a very high
+//! variance. RAdam (Liu et al., 2020) measures that variance through the
+//! length of the approximated simple moving average, `rho_t`, and falls back
+//! to plain momentum SGD until the estimate is reliable (`rho_t > 5`). After
+//! that it scales Adam's step by a rectification term, removing the need for
+//! a learning-rate warmup.
+
+#![allow(clippy::needless_range_loop)]
+
+use super::adam::{batch_gradients, restore_buffers, save_buffers};
+use super::centralization::centralize_gradients;
+use super::statistics::{buffer_bytes, gradient_norm};
+use super::*;
+use crate::numeric::cast;
+use num_traits::Float;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Adam with variance rectification of the adaptive learning rate
+pub struct RAdam<T: Float + Send + Default> {
+    learning_rate: T,
+    beta1: T,
+    beta2: T,
+    epsilon: T,
+    regularization: Regularization<T>,
+    options: TrainingOptions,
+    error_function: Box<dyn ErrorFunction<T>>,
+
+    m_weights: Vec<Vec<T>>,
+    v_weights: Vec<Vec<T>>,
+    m_biases: Vec<Vec<T>>,
+    v_biases: Vec<Vec<T>>,
+    step: usize,
+
+    callbacks: EventDispatcher<T>,
+    statistics: TrainingStatistics<T>,
+}
+
+impl<T: Float + Send + Default> RAdam<T> {
+    pub fn new(learning_rate: T) -> Self {
+        Self {
+            learning_rate,
+            beta1: cast(0.9),
+            beta2: cast(0.999),
+            epsilon: cast(1e-8),
+            regularization: Regularization::none(),
+            options: TrainingOptions::new(),
+            error_function: Box::new(MseError),
+            m_weights: Vec::new(),
+            v_weights: Vec::new(),
+            m_biases: Vec::new(),
+            v_biases: Vec::new(),
+            step: 0,
+            callbacks: EventDispatcher::new(),
+            statistics: TrainingStatistics::new(),
+        }
+    }
+
+    /// Set beta1 parameter (momentum coefficient)
+    pub fn with_beta1(mut self, beta1: T) -> Self {
+        self.beta1 = beta1;
+        self
+    }
+
+    /// Set beta2 parameter (variance coefficient)
+    pub fn with_beta2(mut self, beta2: T) -> Self {
+        self.beta2 = beta2;
+        self
+    }
+
+    /// Set epsilon for numerical stability
+    pub fn with_epsilon(mut self, epsilon: T) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Set the weight decay configuration
+    pub fn with_regularization(mut self, regularization: Regularization<T>) -> Self {
+        self.regularization = regularization;
+        self
+    }
+
+    /// Set error function
+    pub fn with_error_function(mut self, error_function: Box<dyn ErrorFunction<T>>) -> Self {
+        self.error_function = error_function;
+        self
+    }
+
+    /// Rectification term for the current step, or `None` while the variance
+    /// of the adaptive learning rate is intractable
+    fn rectification(&self) -> Option<T> {
+        let one = T::one();
+        let two: T = cast(2.0);
+        let four: T = cast(4.0);
+        let t = self.step as i32;
+        let beta2_t = self.beta2.powi(t);
+        let rho_inf = two / (one - self.beta2) - one;
+        let rho_t = rho_inf - two * cast(self.step) * beta2_t / (one - beta2_t);
+        (rho_t > cast(5.0)).then(|| {
+            ((rho_t - four) * (rho_t - two) * rho_inf
+                / ((rho_inf - four) * (rho_inf - two) * rho_t))
+                .sqrt()
+        })
+    }
+
+    /// Apply one RAdam step for the given mean gradients
+    fn update_parameters(
+        &mut self,
+        network: &mut Network<T>,
+        weight_gradients: &[Vec<T>],
+        bias_gradients: &[Vec<T>],
+    ) {
+        let zeros = |gradients: &[Vec<T>]| -> Vec<Vec<T>> {
+            gradients.iter().map(|g| vec![T::zero(); g.len()]).collect()
+        };
+        if self.m_weights.is_empty() {
+            self.m_weights = zeros(weight_gradients);
+            self.v_weights = zeros(weight_gradients);
+            self.m_biases = zeros(bias_gradients);
+            self.v_biases = zeros(bias_gradients);
+        }
+        self.step += 1;
+
+        let one = T::one();
+        let t = self.step as i32;
+        let momentum_correction = one - self.beta1.powi(t);
+        let variance_correction = one - self.beta2.powi(t);
+        let rectification = self.rectification();
+
+        let (beta1, beta2, epsilon, learning_rate) =
+            (self.beta1, self.beta2, self.epsilon, self.learning_rate);
+        let step = |m: &mut Vec<Vec<T>>, v: &mut Vec<Vec<T>>, gradients: &[Vec<T>]| {
+            let mut updates = zeros(gradients);
+            for layer_idx in 0..gradients.len() {
+                for i in 0..gradients[layer_idx].len() {
+                    let grad = gradients[layer_idx][i];
+                    let m = &mut m[layer_idx][i];
+                    let v = &mut v[layer_idx][i];
+                    *m = beta1 * *m + (one - beta1) * grad;
+                    *v = beta2 * *v + (one - beta2) * grad * grad;
+
+                    let m_hat = *m / momentum_correction;
+                    updates[layer_idx][i] = match rectification {
+                        Some(r) => {
+                            let adaptive = variance_correction.sqrt() / (v.sqrt() + epsilon);
+                            -learning_rate * r * m_hat * adaptive
+                        }
+                        None => -learning_rate * m_hat,
+                    };
+                }
+            }
+            updates
+        };
+
+        let weight_updates = step(&mut self.m_weights, &mut self.v_weights, weight_gradients);
+        let bias_updates = step(&mut self.m_biases, &mut self.v_biases, bias_gradients);
+        super::helpers::apply_updates_to_network(network, &weight_updates, &bias_updates);
+    }
+}
+
+impl<T: Float + Send + Sync + Default> TrainingAlgorithm<T> for RAdam<T> {
+    fn train_epoch(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<T, TrainingError> {
+        if let Some(mut batches) = self.options.mini_batches.take() {
+            let error = batches.train_epoch(self, network, data);
+            self.options.mini_batches = Some(batches);
+            return error;
+        }
+        data.check_shapes(network)?;
+        let trimmed = self
+            .options
+            .trimmed_loss
+            .trim(network, data, self.error_function.as_ref());
+        let data = trimmed.as_ref();
+        let epoch_start = Instant::now();
+
+        let (error, mut weight_gradients, mut bias_gradients) =
+            batch_gradients(network, data, self.error_function.as_ref());
+        if self.options.gradient_centralization {
+            centralize_gradients(network, &mut weight_gradients);
+        }
+        self.regularization
+            .add_to_gradients(network, &mut weight_gradients, &mut bias_gradients);
+        self.regularization
+            .decay_weights(network, self.learning_rate);
+        self.update_parameters(network, &weight_gradients, &bias_gradients);
+
+        self.statistics.record_epoch(
+            epoch_start.elapsed(),
+            data.inputs.len(),
+            gradient_norm(&weight_gradients, &bias_gradients),
+            buffer_bytes(&[
+                &weight_gradients,
+                &bias_gradients,
+                &self.m_weights,
+                &self.v_weights,
+                &self.m_biases,
+                &self.v_biases,
+            ]),
+        );
+        self.statistics.record_gradients(weight_gradients);
+
+        Ok(error)
+    }
+
+    fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
+        super::evaluation::batch_error(network, data, self.error_function.as_ref())
+    }
+
+    fn count_bit_fails(
+        &self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+        bit_fail_limit: T,
+    ) -> usize {
+        super::evaluation::batch_bit_fails(network, data, bit_fail_limit)
+    }
+
+    fn save_state(&self) -> TrainingState<T> {
+        let mut state = HashMap::new();
+        state.insert("learning_rate".to_string(), vec![self.learning_rate]);
+        state.insert("beta1".to_string(), vec![self.beta1]);
+        state.insert("beta2".to_string(), vec![self.beta2]);
+        state.insert("epsilon".to_string(), vec![self.epsilon]);
+        state.insert("step".to_string(), vec![cast(self.step)]);
+        self.regularization.save(&mut state);
+        save_buffers(&mut state, "m_weights", &self.m_weights);
+        save_buffers(&mut state, "v_weights", &self.v_weights);
+        save_buffers(&mut state, "m_biases", &self.m_biases);
+        save_buffers(&mut state, "v_biases", &self.v_biases);
+
+        TrainingState {
+            epoch: 0,
+            best_error: cast(f32::MAX),
+            algorithm_specific: state,
+        }
+    }
+
+    fn restore_state(&mut self, state: TrainingState<T>) {
+        let state = state.algorithm_specific;
+        let scalar = |name: &str| state.get(name).and_then(|v| v.first()).copied();
+        if let Some(learning_rate) = scalar("learning_rate") {
+            self.learning_rate = learning_rate;
+        }
+        if let Some(beta1) = scalar("beta1") {
+            self.beta1 = beta1;
+        }
+        if let Some(beta2) = scalar("beta2") {
+            self.beta2 = beta2;
+        }
+        if let Some(epsilon) = scalar("epsilon") {
+            self.epsilon = epsilon;
+        }
+        if let Some(step) = scalar("step") {
+            self.step = step.to_usize().unwrap_or(0);
+        }
+        self.regularization.restore(&state);
+        self.m_weights = restore_buffers(&state, "m_weights");
+        self.v_weights = restore_buffers(&state, "v_weights");
+        self.m_biases = restore_buffers(&state, "m_biases");
+        self.v_biases = restore_buffers(&state, "v_biases");
+    }
+
+    fn learning_rate(&self) -> Option<T> {
+        Some(self.learning_rate)
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.learning_rate = rate;
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.statistics.last_gradient_norm()
+    }
+
+    fn last_weight_gradients(&self) -> Option<&[Vec<T>]> {
+        self.statistics.last_weight_gradients()
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.options.cancellation = token;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.options.is_cancelled()
+    }
+
+    fn set_callback(&mut self, callback: TrainingCallback<T>) {
+        self.callbacks.subscribe(callback);
+    }
+
+    fn call_callback(
+        &mut self,
+        epoch: usize,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> bool {
+        if self.callbacks.is_empty() {
+            return true;
+        }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
+    }
+}
+
+impl<T: Float + Send + Default> WithTrainingOptions for RAdam<T> {
+    fn training_options(&self) -> &TrainingOptions {
+        &self.options
+    }
+
+    fn training_options_mut(&mut self) -> &mut TrainingOptions {
+        &mut self.options
+    }
+}
+
+impl<T: Float + Send + Sync + Default> AdvancedTrainingAlgorithm<T> for RAdam<T> {
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        &self.statistics
+    }
+
+    fn reset_statistics(&mut self) {
+        self.statistics.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_warmup_then_rectified_steps() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .output_layer(1)
+            .build();
+        let mut radam = RAdam::new(0.1);
+
+        // With beta2 = 0.999, rho_t first exceeds 5 at step 6; until then the
+        // update is bias-corrected momentum, which equals the gradient here
+        for step in 1..=6 {
+            let before = network.get_weights()[1];
+            radam.update_parameters(&mut network, &[vec![0.0]], &[vec![0.5]]);
+            let delta = network.get_weights()[1] - before;
+            if step <= 5 {
+                assert!(radam.rectification().is_none());
+                assert!((delta + 0.05).abs() < 1e-12, "step {step}");
+            } else {
+                let r = radam.rectification().unwrap();
+                assert!(r > 0.0 && r < 1.0);
+                // Constant gradients make m_hat / sqrt(v_hat) equal to 1
+                assert!((delta + 0.1 * r).abs() < 1e-9, "step {step}");
+            }
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! Real-time training on streaming input
+//!
+//! [`RealtimeTrainer`] keeps the most recent samples of a sensor stream in a
+//! fixed-size ring buffer and trains on them in time-budgeted steps. Each
+//! [`step`](RealtimeTrainer::step) runs chunks of the buffer through the
+//! wrapped algorithm, round-robin, until the next chunk is predicted to
+//! overrun the budget. One step covers at most one pass over the buffer, so
+//! a control loop can call it once per cycle with a bounded cost.
+//!
+//! The prediction uses a running average of the chunk durations seen so far.
+//! A step always trains at least one chunk, so the worst-case latency is the
+//! larger of the budget and one chunk; keep chunks small (the default is a
+//! single sample, which suits [`IncrementalBackprop`](super::IncrementalBackprop)).
+//! [`LatencyStats`] records how well the budget held.
+
+use super::{TrainingAlgorithm, TrainingData, TrainingError};
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Outcome of one [`RealtimeTrainer::step`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RealtimeStep<T: Float> {
+    /// Samples trained in this step
+    pub samples: usize,
+    /// Mean training error over those samples
+    pub error: T,
+    pub elapsed: Duration,
+    /// Whether the step took longer than the budget
+    pub overrun: bool,
+}
+
+/// Step latency statistics of a [`RealtimeTrainer`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyStats {
+    pub steps: usize,
+    pub overruns: usize,
+    pub min: Duration,
+    pub max: Duration,
+    mean_secs: f64,
+    m2: f64,
+}
+
+impl LatencyStats {
+    fn record(&mut self, elapsed: Duration, overrun: bool) {
+        self.steps += 1;
+        self.overruns += usize::from(overrun);
+        if self.steps == 1 {
+            self.min = elapsed;
+            self.max = elapsed;
+        } else {
+            self.min = self.min.min(elapsed);
+            self.max = self.max.max(elapsed);
+        }
+        // Welford's online variance
+        let secs = elapsed.as_secs_f64();
+        let delta = secs - self.mean_secs;
+        self.mean_secs += delta / self.steps as f64;
+        self.m2 += delta * (secs - self.mean_secs);
+    }
+
+    pub fn mean(&self) -> Duration {
+        Duration::from_secs_f64(self.mean_secs)
+    }
+
+    /// Standard deviation of the step latency
+    pub fn jitter(&self) -> Duration {
+        if self.steps < 2 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((self.m2 / (self.steps - 1) as f64).sqrt())
+    }
+
+    /// Fraction of steps that overran the budget
+    pub fn overrun_rate(&self) -> f64 {
+        if self.steps == 0 {
+            return 0.0;
+        }
+        self.overruns as f64 / self.steps as f64
+    }
+}
+
+/// Trains a network on a bounded window of streaming samples under a
+/// per-step time budget
+pub struct RealtimeTrainer<T: Float> {
+    algorithm: Box<dyn TrainingAlgorithm<T>>,
+    samples: VecDeque<(Vec<T>, Vec<T>)>,
+    capacity: usize,
+    num_inputs: usize,
+    num_outputs: usize,
+    budget: Duration,
+    chunk_size: usize,
+    /// Next sample to train on, as an index into `samples`
+    cursor: usize,
+    /// Running average of the chunk duration, `None` before the first chunk
+    chunk_estimate: Option<Duration>,
+    chunk: TrainingData<T>,
+    latency: LatencyStats,
+}
+
+impl<T: Float> RealtimeTrainer<T> {
+    /// Trainer keeping the last `capacity` samples for `network` and
+    /// spending at most `budget` per step
+    pub fn new(
+        algorithm: Box<dyn TrainingAlgorithm<T>>,
+        network: &Network<T>,
+        capacity: usize,
+        budget: Duration,
+    ) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            algorithm,
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            num_inputs: network.num_inputs(),
+            num_outputs: network.num_outputs(),
+            budget,
+            chunk_size: 1,
+            cursor: 0,
+            chunk_estimate: None,
+            chunk: TrainingData {
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+            },
+            latency: LatencyStats::default(),
+        }
+    }
+
+    /// Samples passed to the algorithm per update (default 1)
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Add a sample, evicting the oldest one when the buffer is full
+    ///
+    /// Samples of the wrong width are rejected here, so a bad reading
+    /// cannot stall every later step.
+    pub fn push(&mut self, input: Vec<T>, output: Vec<T>) -> Result<(), TrainingError> {
+        if input.len() != self.num_inputs || output.len() != self.num_outputs {
+            return Err(TrainingError::InvalidData(format!(
+                "sample has {} inputs and {} outputs, the network expects {} and {}",
+                input.len(),
+                output.len(),
+                self.num_inputs,
+                self.num_outputs
+            )));
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+            self.cursor = self.cursor.saturating_sub(1);
+        }
+        self.samples.push_back((input, output));
+        Ok(())
+    }
+
+    /// Train on the buffered samples until the budget is spent or every
+    /// sample has been visited once
+    ///
+    /// Returns `None` while the buffer is empty.
+    pub fn step(
+        &mut self,
+        network: &mut Network<T>,
+    ) -> Result<Option<RealtimeStep<T>>, TrainingError> {
+        if self.samples.is_empty() {
+            return Ok(None);
+        }
+        let start = Instant::now();
+        let mut trained = 0;
+        let mut error_sum = T::zero();
+        while trained < self.samples.len() {
+            let elapsed = start.elapsed();
+            if trained > 0 {
+                let predicted = self.chunk_estimate.unwrap_or_default();
+                if elapsed + predicted > self.budget {
+                    break;
+                }
+            }
+            let len = self.chunk_size.min(self.samples.len() - trained);
+            self.fill_chunk(len);
+            let chunk_start = Instant::now();
+            let error = self.algorithm.train_epoch(network, &self.chunk)?;
+            let chunk_time = chunk_start.elapsed();
+            self.chunk_estimate = Some(match self.chunk_estimate {
+                Some(estimate) => (estimate * 7 + chunk_time) / 8,
+                None => chunk_time,
+            });
+            error_sum = error_sum + error * cast(len);
+            trained += len;
+            self.cursor = (self.cursor + len) % self.samples.len();
+        }
+        let elapsed = start.elapsed();
+        let overrun = elapsed > self.budget;
+        self.latency.record(elapsed, overrun);
+        Ok(Some(RealtimeStep {
+            samples: trained,
+            error: error_sum / cast(trained),
+            elapsed,
+            overrun,
+        }))
+    }
+
+    /// Copy `len` samples starting at the cursor into the reused chunk
+    fn fill_chunk(&mut self, len: usize) {
+        self.chunk.inputs.truncate(len);
+        self.chunk.outputs.truncate(len);
+        for i in 0..len {
+            let (input, output) = &self.samples[(self.cursor + i) % self.samples.len()];
+            if i < self.chunk.inputs.len() {
+                self.chunk.inputs[i].clone_from(input);
+                self.chunk.outputs[i].clone_from(output);
+            } else {
+                self.chunk.inputs.push(input.clone());
+                self.chunk.outputs.push(output.clone());
+            }
+        }
+    }
+
+    /// Number of buffered samples
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    pub fn latency(&self) -> &LatencyStats {
+        &self.latency
+    }
+
+    /// The buffered samples, oldest first
+    pub fn window(&self) -> TrainingData<T> {
+        let (inputs, outputs) = self.samples.iter().cloned().unzip();
+        TrainingData { inputs, outputs }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::IncrementalBackprop;
+    use crate::NetworkBuilder;
+
+    fn network() -> Network<f32> {
+        NetworkBuilder::<f32>::new()
+            .input_layer(1)
+            .hidden_layer(4)
+            .output_layer(1)
+            .build()
+    }
+
+    #[test]
+    fn test_ring_buffer_and_step_bounds() {
+        let mut network = network();
+        let mut trainer = RealtimeTrainer::new(
+            Box::new(IncrementalBackprop::new(0.1)),
+            &network,
+            4,
+            Duration::from_secs(10),
+        );
+        assert_eq!(trainer.step(&mut network).unwrap(), None);
+        assert!(trainer.push(vec![0.0, 1.0], vec![0.0]).is_err());
+
+        for i in 0..6 {
+            trainer.push(vec![i as f32], vec![i as f32 * 0.1]).unwrap();
+        }
+        assert_eq!(trainer.len(), 4);
+        assert_eq!(trainer.window().inputs[0], vec![2.0]);
+
+        // A generous budget covers the whole window exactly once
+        let step = trainer.step(&mut network).unwrap().unwrap();
+        assert_eq!(step.samples, 4);
+        assert!(!step.overrun);
+        assert!(step.error.is_finite());
+
+        // A zero budget still makes progress, one chunk at a time
+        let mut tight = RealtimeTrainer::new(
+            Box::new(IncrementalBackprop::new(0.1)),
+            &network,
+            8,
+            Duration::ZERO,
+        )
+        .with_chunk_size(2);
+        for i in 0..8 {
+            tight.push(vec![i as f32], vec![0.5]).unwrap();
+        }
+        for _ in 0..3 {
+            assert_eq!(tight.step(&mut network).unwrap().unwrap().samples, 2);
+        }
+        let latency = tight.latency();
+        assert_eq!(latency.steps, 3);
+        assert!(latency.min <= latency.mean() && latency.mean() <= latency.max);
+        assert!(latency.jitter() <= latency.max);
+    }
+
+    #[test]
+    fn test_streaming_training_reduces_error() {
+        let mut network = network();
+        let mut trainer = RealtimeTrainer::new(
+            Box::new(IncrementalBackprop::new(0.5)),
+            &network,
+            16,
+            Duration::from_secs(1),
+        );
+        for i in 0..16 {
+            let x = i as f32 / 16.0;
+            trainer.push(vec![x], vec![0.2 + 0.6 * x]).unwrap();
+        }
+        let first = trainer.step(&mut network).unwrap().unwrap().error;
+        let mut last = first;
+        for _ in 0..200 {
+            last = trainer.step(&mut network).unwrap().unwrap().error;
+        }
+        assert!(last < first);
+    }
+}
@@ -0,0 +1,351 @@
+//! Weight decay shared by the gradient-based optimizers
+//!
+//! Every optimizer that supports weight decay takes a [`Regularization`] and
+//! applies it the same way, so the only difference between optimizers is the
+//! [`DecayMode`] they default to:
+//!
+//! - [`DecayMode::Coupled`] is the classic L2 penalty. `weight_decay * w` is
+//!   added to the gradient before the optimizer sees it, so adaptive
+//!   optimizers rescale the decay together with the gradient. This is what
+//!   [`Adam::with_weight_decay`](super::Adam::with_weight_decay) means.
+//! - [`DecayMode::Decoupled`] shrinks the weights by
+//!   `1 - learning_rate * weight_decay` before the optimizer step
+//!   (Loshchilov & Hutter, 2019), independent of the gradient. This is what
+//!   [`AdamW`](super::AdamW) and [`Lion`](super::Lion) use.
+//!
+//! [`Quickprop`](super::Quickprop) defaults to FANN's coupled decay of
+//! 0.0001; [`Rprop`](super::Rprop) has no learning rate, so its decoupled
+//! decay shrinks by `1 - weight_decay` per epoch.
+//!
+//! For plain SGD both modes produce the same step; for adaptive optimizers
+//! they differ. Biases (the connections from each layer's bias neuron) are
+//! not decayed unless requested.
+
+use crate::{Layer, Network};
+use num_traits::Float;
+use std::collections::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How weight decay enters the update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DecayMode {
+    /// L2 penalty added to the gradient
+    Coupled,
+    /// Multiplicative shrink applied directly to the weights
+    Decoupled,
+}
+
+/// Weight decay configuration consumed by the optimizers
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Regularization<T: Float> {
+    pub weight_decay: T,
+    pub mode: DecayMode,
+    /// Also decay bias connections
+    pub decay_biases: bool,
+}
+
+impl<T: Float> Regularization<T> {
+    /// No weight decay
+    pub fn none() -> Self {
+        Self::coupled(T::zero())
+    }
+
+    /// L2 penalty `weight_decay / 2 * |w|²` added to the loss
+    pub fn coupled(weight_decay: T) -> Self {
+        Self {
+            weight_decay,
+            mode: DecayMode::Coupled,
+            decay_biases: false,
+        }
+    }
+
+    /// Decoupled decay `w <- w * (1 - learning_rate * weight_decay)`
+    pub fn decoupled(weight_decay: T) -> Self {
+        Self {
+            weight_decay,
+            mode: DecayMode::Decoupled,
+            decay_biases: false,
+        }
+    }
+
+    /// Decay bias connections as well as weights
+    pub fn with_bias_decay(mut self, decay_biases: bool) -> Self {
+        self.decay_biases = decay_biases;
+        self
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.weight_decay > T::zero()
+    }
+
+    /// Add the L2 penalty gradient to layered weight and bias gradients
+    ///
+    /// Does nothing unless the mode is [`DecayMode::Coupled`].
+    pub fn add_to_gradients(
+        &self,
+        network: &Network<T>,
+        weight_gradients: &mut [Vec<T>],
+        bias_gradients: &mut [Vec<T>],
+    ) {
+        if self.mode != DecayMode::Coupled || !self.is_active() {
+            return;
+        }
+        let decay = self.weight_decay;
+        let layers = network.layers.windows(2);
+        for ((pair, weight_gradients), bias_gradients) in
+            layers.zip(weight_gradients).zip(bias_gradients)
+        {
+            let bias_neuron = bias_source(&pair[0]);
+            let mut weight_idx = 0;
+            let neurons = pair[1].neurons.iter().filter(|n| !n.is_bias);
+            for (neuron_idx, neuron) in neurons.enumerate() {
+                for connection in &neuron.connections {
+                    if Some(connection.from_neuron) == bias_neuron {
+                        if self.decay_biases {
+                            if let Some(gradient) = bias_gradients.get_mut(neuron_idx) {
+                                *gradient = *gradient + decay * connection.weight;
+                            }
+                        }
+                        continue;
+                    }
+                    if let Some(gradient) = weight_gradients.get_mut(weight_idx) {
+                        *gradient = *gradient + decay * connection.weight;
+                    }
+                    weight_idx += 1;
+                }
+            }
+        }
+    }
+
+    /// Shrink the network's weights ahead of an optimizer step
+    ///
+    /// Does nothing unless the mode is [`DecayMode::Decoupled`].
+    pub fn decay_weights(&self, network: &mut Network<T>, learning_rate: T) {
+        if self.mode != DecayMode::Decoupled || !self.is_active() {
+            return;
+        }
+        let factor = T::one() - learning_rate * self.weight_decay;
+        let bias_sources: Vec<Option<usize>> = network.layers.iter().map(bias_source).collect();
+        for (layer, &bias_neuron) in network.layers.iter_mut().skip(1).zip(&bias_sources) {
+            for neuron in layer.neurons.iter_mut().filter(|n| !n.is_bias) {
+                for connection in &mut neuron.connections {
+                    if self.decay_biases || Some(connection.from_neuron) != bias_neuron {
+                        connection.weight = connection.weight * factor;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Store the configuration in an optimizer's saved state
+    pub(super) fn save(&self, state: &mut HashMap<String, Vec<T>>) {
+        let flag = |value: bool| vec![if value { T::one() } else { T::zero() }];
+        state.insert("weight_decay".to_string(), vec![self.weight_decay]);
+        state.insert(
+            "decoupled_weight_decay".to_string(),
+            flag(self.mode == DecayMode::Decoupled),
+        );
+        state.insert("decay_biases".to_string(), flag(self.decay_biases));
+    }
+
+    /// Read back a configuration written by [`Self::save`]; missing entries
+    /// keep their current value
+    pub(super) fn restore(&mut self, state: &HashMap<String, Vec<T>>) {
+        let scalar = |name: &str| state.get(name).and_then(|v| v.first()).copied();
+        if let Some(weight_decay) = scalar("weight_decay") {
+            self.weight_decay = weight_decay;
+        }
+        if let Some(decoupled) = scalar("decoupled_weight_decay") {
+            self.mode = if decoupled > T::zero() {
+                DecayMode::Decoupled
+            } else {
+                DecayMode::Coupled
+            };
+        }
+        if let Some(decay_biases) = scalar("decay_biases") {
+            self.decay_biases = decay_biases > T::zero();
+        }
+    }
+}
+
+/// Index of the bias neuron of `layer` as seen by the next layer's connections
+fn bias_source<T: Float>(layer: &Layer<T>) -> Option<usize> {
+    layer.has_bias().then(|| layer.num_regular_neurons())
+}
+
+impl<T: Float> Default for Regularization<T> {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::adam::batch_gradients;
+    use super::super::*;
+    use super::*;
+    use crate::NetworkBuilder;
+
+    const DECAY: f64 = 0.1;
+
+    /// One input, one output: connection 0 is the weight, 1 the bias
+    fn single_neuron() -> (Network<f64>, TrainingData<f64>) {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .output_layer(1)
+            .build();
+        network.set_weights(&[-0.7, 0.4]).unwrap();
+        let data = TrainingData {
+            inputs: vec![vec![0.2], vec![0.9]],
+            outputs: vec![vec![1.0], vec![0.0]],
+        };
+        (network, data)
+    }
+
+    /// Mean weight and bias gradient of the single neuron network
+    fn gradients(network: &Network<f64>, data: &TrainingData<f64>) -> (f64, f64) {
+        let (_, weights, biases) = batch_gradients(network, data, &MseError);
+        (weights[0][0], biases[0][0])
+    }
+
+    /// First Adam step for gradient `g`, with the default betas and epsilon
+    fn adam_step(learning_rate: f64, g: f64) -> f64 {
+        let (beta1, beta2, epsilon) = (0.9f64, 0.999f64, 1e-8);
+        let lr_t = learning_rate * (1.0 - beta2).sqrt() / (1.0 - beta1);
+        let (m, v) = ((1.0 - beta1) * g, (1.0 - beta2) * g * g);
+        -lr_t * m / (v.sqrt() + epsilon)
+    }
+
+    #[test]
+    fn test_coupled_gradient_layout() {
+        let (network, _) = single_neuron();
+        let mut weight_gradients = vec![vec![1.0]];
+        let mut bias_gradients = vec![vec![1.0]];
+        Regularization::coupled(DECAY).add_to_gradients(
+            &network,
+            &mut weight_gradients,
+            &mut bias_gradients,
+        );
+        assert_eq!(weight_gradients, vec![vec![1.0 - 0.7 * DECAY]]);
+        assert_eq!(bias_gradients, vec![vec![1.0]]);
+
+        Regularization::coupled(DECAY)
+            .with_bias_decay(true)
+            .add_to_gradients(&network, &mut weight_gradients, &mut bias_gradients);
+        assert_eq!(bias_gradients, vec![vec![1.0 + 0.4 * DECAY]]);
+
+        // Decoupled decay never touches the gradients
+        Regularization::decoupled(DECAY).add_to_gradients(
+            &network,
+            &mut weight_gradients,
+            &mut bias_gradients,
+        );
+        assert_eq!(bias_gradients, vec![vec![1.0 + 0.4 * DECAY]]);
+    }
+
+    #[test]
+    fn test_sgd_coupled_equals_decoupled() {
+        let (network, data) = single_neuron();
+        let (g_w, g_b) = gradients(&network, &data);
+        let train = |regularization: Regularization<f64>| {
+            let mut network = network.clone();
+            BatchBackprop::new(0.5)
+                .with_regularization(regularization)
+                .train_epoch(&mut network, &data)
+                .unwrap();
+            network.get_weights()
+        };
+
+        // Reference: w <- w - lr * (g + decay * w)
+        let coupled = train(Regularization::coupled(DECAY));
+        let decoupled = train(Regularization::decoupled(DECAY));
+        assert!((coupled[0] - (-0.7 - 0.5 * (g_w + DECAY * -0.7))).abs() < 1e-12);
+        assert!((coupled[1] - (0.4 - 0.5 * g_b)).abs() < 1e-12);
+        for (a, b) in coupled.iter().zip(&decoupled) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        assert_ne!(coupled, train(Regularization::none()));
+    }
+
+    #[test]
+    fn test_quickprop_and_rprop_match_reference() {
+        let (network, data) = single_neuron();
+        let (g_w, g_b) = gradients(&network, &data);
+
+        // The first Quickprop epoch is a gradient step on the penalized gradient;
+        // FANN's negative decay maps onto a coupled Regularization
+        let mut quickprop = network.clone();
+        Quickprop::new()
+            .with_parameters(0.5, 1.75, -DECAY)
+            .train_epoch(&mut quickprop, &data)
+            .unwrap();
+        let weights = quickprop.get_weights();
+        assert!((weights[0] - (-0.7 - 0.5 * (g_w + DECAY * -0.7))).abs() < 1e-12);
+        assert!((weights[1] - (0.4 - 0.5 * g_b)).abs() < 1e-12);
+
+        // Reference: w <- w * (1 - decay) - sign(g) * delta_zero
+        let mut rprop = network.clone();
+        Rprop::new()
+            .with_regularization(Regularization::decoupled(DECAY))
+            .train_epoch(&mut rprop, &data)
+            .unwrap();
+        let expected = -0.7 * (1.0 - DECAY) - g_w.signum() * 0.1;
+        assert!((rprop.get_weights()[0] - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_adam_l2_matches_reference() {
+        let (mut network, data) = single_neuron();
+        let (g_w, g_b) = gradients(&network, &data);
+        let mut adam = Adam::new(0.01).with_weight_decay(DECAY);
+        adam.train_epoch(&mut network, &data).unwrap();
+
+        // The penalty gradient is normalized together with the data gradient
+        let weights = network.get_weights();
+        let expected = -0.7 + adam_step(0.01, g_w + DECAY * -0.7);
+        assert!((weights[0] - expected).abs() < 1e-12);
+        assert!((weights[1] - (0.4 + adam_step(0.01, g_b))).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_adamw_decoupled_matches_reference() {
+        let (mut network, data) = single_neuron();
+        let (g_w, g_b) = gradients(&network, &data);
+        let mut adamw = AdamW::new(0.01).with_weight_decay(DECAY);
+        adamw.train_epoch(&mut network, &data).unwrap();
+
+        // Reference: w <- w * (1 - lr * decay) + adam_step(g)
+        let weights = network.get_weights();
+        let expected = -0.7 * (1.0 - 0.01 * DECAY) + adam_step(0.01, g_w);
+        assert!((weights[0] - expected).abs() < 1e-12);
+        assert!((weights[1] - (0.4 + adam_step(0.01, g_b))).abs() < 1e-12);
+
+        // Adam configured for decoupled decay is AdamW
+        let (mut network, _) = single_neuron();
+        Adam::new(0.01)
+            .with_regularization(Regularization::decoupled(DECAY))
+            .train_epoch(&mut network, &data)
+            .unwrap();
+        assert!((network.get_weights()[0] - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let regularization = Regularization::decoupled(0.25).with_bias_decay(true);
+        let mut state = HashMap::new();
+        regularization.save(&mut state);
+
+        let mut restored = Regularization::none();
+        restored.restore(&state);
+        assert_eq!(restored, regularization);
+
+        // States saved before the mode was recorded keep the current mode
+        let mut restored = Regularization::decoupled(0.0);
+        restored.restore(&HashMap::from([("weight_decay".to_string(), vec![0.5])]));
+        assert_eq!(restored, Regularization::decoupled(0.5));
+    }
+}
@@ -2,9 +2,13 @@
 
 #![allow(clippy::needless_range_loop)]
 
+use super::centralization::centralize_gradients;
+use super::statistics::{buffer_bytes, gradient_norm};
 use super::*;
+use crate::numeric::cast;
 use num_traits::Float;
 use std::collections::HashMap;
+use std::time::Instant;
 
 /// RPROP (Resilient Propagation) trainer
 /// An adaptive learning algorithm that only uses the sign of the gradient
@@ -14,6 +18,8 @@ pub struct Rprop<T: Float + Send + Default> {
     delta_min: T,
     delta_max: T,
     delta_zero: T,
+    regularization: Regularization<T>,
+    options: TrainingOptions,
     error_function: Box<dyn ErrorFunction<T>>,
 
     // State variables
@@ -22,23 +28,27 @@ pub struct Rprop<T: Float + Send + Default> {
     previous_weight_gradients: Vec<Vec<T>>,
     previous_bias_gradients: Vec<Vec<T>>,
 
-    callback: Option<TrainingCallback<T>>,
+    callbacks: EventDispatcher<T>,
+    statistics: TrainingStatistics<T>,
 }
 
 impl<T: Float + Send + Default> Rprop<T> {
     pub fn new() -> Self {
         Self {
-            increase_factor: T::from(1.2).unwrap(),
-            decrease_factor: T::from(0.5).unwrap(),
+            increase_factor: cast(1.2),
+            decrease_factor: cast(0.5),
             delta_min: T::zero(),
-            delta_max: T::from(50.0).unwrap(),
-            delta_zero: T::from(0.1).unwrap(),
+            delta_max: cast(50.0),
+            delta_zero: cast(0.1),
+            regularization: Regularization::none(),
+            options: TrainingOptions::new(),
             error_function: Box::new(MseError),
             weight_step_sizes: Vec::new(),
             bias_step_sizes: Vec::new(),
             previous_weight_gradients: Vec::new(),
             previous_bias_gradients: Vec::new(),
-            callback: None,
+            callbacks: EventDispatcher::new(),
+            statistics: TrainingStatistics::new(),
         }
     }
 
@@ -58,6 +68,15 @@ impl<T: Float + Send + Default> Rprop<T> {
         self
     }
 
+    /// Set the weight decay configuration
+    ///
+    /// Coupled decay enters the gradient signs; RPROP has no learning rate,
+    /// so decoupled decay shrinks the weights by `1 - weight_decay` per epoch.
+    pub fn with_regularization(mut self, regularization: Regularization<T>) -> Self {
+        self.regularization = regularization;
+        self
+    }
+
     pub fn with_error_function(mut self, error_function: Box<dyn ErrorFunction<T>>) -> Self {
         self.error_function = error_function;
         self
@@ -66,20 +85,7 @@ impl<T: Float + Send + Default> Rprop<T> {
     fn initialize_state(&mut self, network: &Network<T>) {
         if self.weight_step_sizes.is_empty() {
             // Initialize step sizes and gradients for each layer
-            self.weight_step_sizes = network
-                .layers
-                .iter()
-                .skip(1) // Skip input layer
-                .map(|layer| {
-                    let num_neurons = layer.neurons.len();
-                    let num_connections = if layer.neurons.is_empty() {
-                        0
-                    } else {
-                        layer.neurons[0].connections.len()
-                    };
-                    vec![self.delta_zero; num_neurons * num_connections]
-                })
-                .collect();
+            self.weight_step_sizes = super::helpers::weight_buffers(network, self.delta_zero);
 
             self.bias_step_sizes = network
                 .layers
@@ -89,20 +95,7 @@ impl<T: Float + Send + Default> Rprop<T> {
                 .collect();
 
             // Initialize previous gradients to zero
-            self.previous_weight_gradients = network
-                .layers
-                .iter()
-                .skip(1) // Skip input layer
-                .map(|layer| {
-                    let num_neurons = layer.neurons.len();
-                    let num_connections = if layer.neurons.is_empty() {
-                        0
-                    } else {
-                        layer.neurons[0].connections.len()
-                    };
-                    vec![T::zero(); num_neurons * num_connections]
-                })
-                .collect();
+            self.previous_weight_gradients = super::helpers::weight_buffers(network, T::zero());
 
             self.previous_bias_gradients = network
                 .layers
@@ -135,7 +128,7 @@ impl<T: Float + Send + Default> Default for Rprop<T> {
     }
 }
 
-impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
+impl<T: Float + Send + Sync + Default> TrainingAlgorithm<T> for Rprop<T> {
     fn train_epoch(
         &mut self,
         network: &mut Network<T>,
@@ -143,6 +136,19 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
     ) -> Result<T, TrainingError> {
         use super::helpers::*;
 
+        if let Some(mut batches) = self.options.mini_batches.take() {
+            let error = batches.train_epoch(self, network, data);
+            self.options.mini_batches = Some(batches);
+            return error;
+        }
+        data.check_shapes(network)?;
+        let trimmed = self
+            .options
+            .trimmed_loss
+            .trim(network, data, self.error_function.as_ref());
+        let data = trimmed.as_ref();
+        let epoch_start = Instant::now();
+
         self.initialize_state(network);
 
         let mut total_error = T::zero();
@@ -163,9 +169,15 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
             .collect::<Vec<_>>();
 
         // Calculate gradients over entire dataset
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
+        let mut rng = rand::thread_rng();
+        for (seen, (input, desired_output)) in data.inputs.iter().zip(&data.outputs).enumerate() {
+            // A cancelled epoch ends without an update
+            if self.is_cancelled() {
+                return Ok(total_error / cast(seen.max(1)));
+            }
             // Forward propagation to get all layer activations
-            let activations = forward_propagate(&simple_network, input);
+            let masks = dropout_masks(&simple_network, &mut rng);
+            let activations = forward_propagate_masked(&simple_network, input, &masks);
 
             // Get output from last layer
             let output = &activations[activations.len() - 1];
@@ -174,9 +186,10 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
             total_error = total_error + self.error_function.calculate(output, desired_output);
 
             // Calculate gradients using backpropagation
-            let (weight_gradients, bias_gradients) = calculate_gradients(
+            let (weight_gradients, bias_gradients) = calculate_gradients_masked(
                 &simple_network,
                 &activations,
+                &masks,
                 desired_output,
                 self.error_function.as_ref(),
             );
@@ -195,7 +208,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
         }
 
         // Average gradients by batch size
-        let batch_size = T::from(data.inputs.len()).unwrap();
+        let batch_size = cast(data.inputs.len());
         for layer_idx in 0..accumulated_weight_gradients.len() {
             for i in 0..accumulated_weight_gradients[layer_idx].len() {
                 accumulated_weight_gradients[layer_idx][i] =
@@ -207,6 +220,16 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
             }
         }
 
+        if self.options.gradient_centralization {
+            centralize_gradients(network, &mut accumulated_weight_gradients);
+        }
+        self.regularization.add_to_gradients(
+            network,
+            &mut accumulated_weight_gradients,
+            &mut accumulated_bias_gradients,
+        );
+        self.regularization.decay_weights(network, T::one());
+
         // Apply RPROP updates
         let mut weight_updates = Vec::new();
         let mut bias_updates = Vec::new();
@@ -330,19 +353,29 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
         // Apply the updates to the actual network
         apply_updates_to_network(network, &weight_updates, &bias_updates);
 
+        self.statistics.record_epoch(
+            epoch_start.elapsed(),
+            data.inputs.len(),
+            gradient_norm(&accumulated_weight_gradients, &accumulated_bias_gradients),
+            buffer_bytes(&[
+                &simple_network.weights,
+                &simple_network.biases,
+                &accumulated_weight_gradients,
+                &accumulated_bias_gradients,
+                &self.weight_step_sizes,
+                &self.bias_step_sizes,
+                &self.previous_weight_gradients,
+                &self.previous_bias_gradients,
+            ]),
+        );
+        self.statistics
+            .record_gradients(accumulated_weight_gradients);
+
         Ok(total_error / batch_size)
     }
 
     fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
-        let mut total_error = T::zero();
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-            total_error = total_error + self.error_function.calculate(&output, desired_output);
-        }
-
-        total_error / T::from(data.inputs.len()).unwrap()
+        super::evaluation::batch_error(network, data, self.error_function.as_ref())
     }
 
     fn count_bit_fails(
@@ -351,20 +384,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
         data: &TrainingData<T>,
         bit_fail_limit: T,
     ) -> usize {
-        let mut bit_fails = 0;
-        let mut network_clone = network.clone();
-
-        for (input, desired_output) in data.inputs.iter().zip(data.outputs.iter()) {
-            let output = network_clone.run(input);
-
-            for (&actual, &desired) in output.iter().zip(desired_output.iter()) {
-                if (actual - desired).abs() > bit_fail_limit {
-                    bit_fails += 1;
-                }
-            }
-        }
-
-        bit_fails
+        super::evaluation::batch_bit_fails(network, data, bit_fail_limit)
     }
 
     fn save_state(&self) -> TrainingState<T> {
@@ -376,6 +396,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
         state.insert("delta_min".to_string(), vec![self.delta_min]);
         state.insert("delta_max".to_string(), vec![self.delta_max]);
         state.insert("delta_zero".to_string(), vec![self.delta_zero]);
+        self.regularization.save(&mut state);
 
         // Save step sizes (flattened)
         let mut all_weight_steps = Vec::new();
@@ -392,7 +413,7 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
 
         TrainingState {
             epoch: 0,
-            best_error: T::from(f32::MAX).unwrap(),
+            best_error: cast(f32::MAX),
             algorithm_specific: state,
         }
     }
@@ -424,13 +445,30 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
                 self.delta_zero = val[0];
             }
         }
+        self.regularization.restore(&state.algorithm_specific);
 
         // Note: Step sizes would need network structure info to properly restore
         // This is a simplified version - in production, you'd need to store layer sizes too
     }
 
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.statistics.last_gradient_norm()
+    }
+
+    fn last_weight_gradients(&self) -> Option<&[Vec<T>]> {
+        self.statistics.last_weight_gradients()
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.options.cancellation = token;
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.options.is_cancelled()
+    }
+
     fn set_callback(&mut self, callback: TrainingCallback<T>) {
-        self.callback = Some(callback);
+        self.callbacks.subscribe(callback);
     }
 
     fn call_callback(
@@ -439,11 +477,36 @@ impl<T: Float + Send + Default> TrainingAlgorithm<T> for Rprop<T> {
         network: &Network<T>,
         data: &TrainingData<T>,
     ) -> bool {
-        let error = self.calculate_error(network, data);
-        if let Some(ref mut callback) = self.callback {
-            callback(epoch, error)
-        } else {
-            true
+        if self.callbacks.is_empty() {
+            return true;
         }
+        let event = TrainingEvent::EpochEnd {
+            epoch,
+            metrics: EpochMetrics {
+                train_error: self.calculate_error(network, data),
+                validation_error: None,
+            },
+        };
+        self.callbacks.dispatch(&event)
+    }
+}
+
+impl<T: Float + Send + Default> WithTrainingOptions for Rprop<T> {
+    fn training_options(&self) -> &TrainingOptions {
+        &self.options
+    }
+
+    fn training_options_mut(&mut self) -> &mut TrainingOptions {
+        &mut self.options
+    }
+}
+
+impl<T: Float + Send + Sync + Default> AdvancedTrainingAlgorithm<T> for Rprop<T> {
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        &self.statistics
+    }
+
+    fn reset_statistics(&mut self) {
+        self.statistics.reset();
     }
 }
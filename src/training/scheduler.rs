@@ -0,0 +1,346 @@
+//! Work-stealing job scheduler
+//!
+//! [`WorkStealingScheduler`] runs a list of independent jobs (per-batch
+//! gradient computations, for example) on a fixed set of worker threads.
+//! Every worker owns a crossbeam deque; jobs with an affinity start on the
+//! deque of their worker and jobs without one go to a shared injector. A
+//! worker pops its own deque first, then drains the injector, and only then
+//! steals from its peers, so jobs stay where they were placed unless that
+//! worker falls behind.
+//!
+//! The scheduler counts jobs, peer steals and jobs that ran on their
+//! preferred worker, and the time workers spent idle looking for work. The
+//! counters accumulate over runs until [`WorkStealingScheduler::reset_stats`].
+
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Counters of a [`WorkStealingScheduler`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SchedulerStats {
+    /// Jobs completed
+    pub jobs: usize,
+    /// Jobs taken from another worker's deque
+    pub steals: usize,
+    /// Jobs that ran on the worker named by their affinity
+    pub affinity_hits: usize,
+    /// Total time workers spent without a job, summed over workers
+    pub idle: Duration,
+}
+
+impl SchedulerStats {
+    /// Fraction of jobs that were stolen
+    pub fn steal_ratio(&self) -> f64 {
+        if self.jobs == 0 {
+            0.0
+        } else {
+            self.steals as f64 / self.jobs as f64
+        }
+    }
+}
+
+/// Job queued on a deque: position in the input, affinity and payload
+type Job<I> = (usize, Option<usize>, I);
+
+/// Runs independent jobs on worker threads with per-worker deques and
+/// stealing
+#[derive(Debug)]
+pub struct WorkStealingScheduler {
+    workers: usize,
+    jobs: AtomicUsize,
+    steals: AtomicUsize,
+    affinity_hits: AtomicUsize,
+    idle_nanos: AtomicU64,
+}
+
+impl Default for WorkStealingScheduler {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Clone for WorkStealingScheduler {
+    /// Same worker count, fresh counters
+    fn clone(&self) -> Self {
+        Self::new(self.workers)
+    }
+}
+
+/// Flags the run as aborted if a job panics, so the other workers stop
+/// waiting for a job count that will never reach zero
+struct AbortOnPanic<'a>(&'a AtomicBool);
+
+impl Drop for AbortOnPanic<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.store(true, Ordering::Release);
+        }
+    }
+}
+
+impl WorkStealingScheduler {
+    /// Scheduler with `workers` threads; 0 uses all available cores
+    pub fn new(workers: usize) -> Self {
+        let workers = match workers {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        Self {
+            workers,
+            jobs: AtomicUsize::new(0),
+            steals: AtomicUsize::new(0),
+            affinity_hits: AtomicUsize::new(0),
+            idle_nanos: AtomicU64::new(0),
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    pub fn stats(&self) -> SchedulerStats {
+        SchedulerStats {
+            jobs: self.jobs.load(Ordering::Relaxed),
+            steals: self.steals.load(Ordering::Relaxed),
+            affinity_hits: self.affinity_hits.load(Ordering::Relaxed),
+            idle: Duration::from_nanos(self.idle_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub fn reset_stats(&self) {
+        self.jobs.store(0, Ordering::Relaxed);
+        self.steals.store(0, Ordering::Relaxed);
+        self.affinity_hits.store(0, Ordering::Relaxed);
+        self.idle_nanos.store(0, Ordering::Relaxed);
+    }
+
+    /// Worker that owns job `index` of `len` when jobs are handed out in
+    /// contiguous blocks
+    pub fn block_affinity(&self, index: usize, len: usize) -> usize {
+        let block = len.max(1).div_ceil(self.workers);
+        (index / block).min(self.workers - 1)
+    }
+
+    /// Run `work(worker, job)` for every job and return the results in job
+    /// order
+    ///
+    /// Jobs are placed on the workers in contiguous blocks (see
+    /// [`Self::block_affinity`]), so neighbouring jobs share a worker until
+    /// stealing rebalances them.
+    pub fn run<I, R, F>(&self, jobs: Vec<I>, work: F) -> Vec<R>
+    where
+        I: Send,
+        R: Send,
+        F: Fn(usize, I) -> R + Sync,
+    {
+        let len = jobs.len();
+        let jobs = jobs
+            .into_iter()
+            .enumerate()
+            .map(|(index, job)| (Some(self.block_affinity(index, len)), job))
+            .collect();
+        self.run_with_affinity(jobs, work)
+    }
+
+    /// Like [`Self::run`] with an explicit preferred worker per job; `None`
+    /// puts the job on the shared injector and affinities wrap around the
+    /// worker count
+    pub fn run_with_affinity<I, R, F>(&self, jobs: Vec<(Option<usize>, I)>, work: F) -> Vec<R>
+    where
+        I: Send,
+        R: Send,
+        F: Fn(usize, I) -> R + Sync,
+    {
+        let len = jobs.len();
+        if len == 0 {
+            return Vec::new();
+        }
+        let locals: Vec<Worker<Job<I>>> = (0..self.workers).map(|_| Worker::new_fifo()).collect();
+        let stealers: Vec<Stealer<Job<I>>> = locals.iter().map(Worker::stealer).collect();
+        let injector = Injector::new();
+        for (index, (affinity, job)) in jobs.into_iter().enumerate() {
+            let affinity = affinity.map(|worker| worker % self.workers);
+            match affinity {
+                Some(worker) => locals[worker].push((index, affinity, job)),
+                None => injector.push((index, affinity, job)),
+            }
+        }
+
+        let remaining = AtomicUsize::new(len);
+        let aborted = AtomicBool::new(false);
+        let mut results: Vec<Option<R>> = std::iter::repeat_with(|| None).take(len).collect();
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = locals
+                .into_iter()
+                .enumerate()
+                .map(|(worker, local)| {
+                    let (stealers, injector) = (&stealers, &injector);
+                    let (remaining, aborted, work) = (&remaining, &aborted, &work);
+                    scope.spawn(move || {
+                        let _guard = AbortOnPanic(aborted);
+                        let mut done = Vec::new();
+                        let mut idle_since: Option<Instant> = None;
+                        loop {
+                            let found = local
+                                .pop()
+                                .map(|job| (job, false))
+                                .or_else(|| self.find_job(worker, &local, injector, stealers));
+                            let Some(((index, affinity, job), stolen)) = found else {
+                                if remaining.load(Ordering::Acquire) == 0
+                                    || aborted.load(Ordering::Acquire)
+                                {
+                                    break;
+                                }
+                                idle_since.get_or_insert_with(Instant::now);
+                                std::thread::yield_now();
+                                continue;
+                            };
+                            if let Some(start) = idle_since.take() {
+                                self.add_idle(start);
+                            }
+                            if stolen {
+                                self.steals.fetch_add(1, Ordering::Relaxed);
+                            }
+                            if affinity == Some(worker) {
+                                self.affinity_hits.fetch_add(1, Ordering::Relaxed);
+                            }
+                            done.push((index, work(worker, job)));
+                            self.jobs.fetch_add(1, Ordering::Relaxed);
+                            remaining.fetch_sub(1, Ordering::AcqRel);
+                        }
+                        if let Some(start) = idle_since {
+                            self.add_idle(start);
+                        }
+                        done
+                    })
+                })
+                .collect();
+            for handle in handles {
+                let done = handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                for (index, result) in done {
+                    results[index] = Some(result);
+                }
+            }
+        });
+
+        // Every slot is filled: a worker only stops once no job remains, and
+        // a panicking job has already been resumed above
+        results.into_iter().flatten().collect()
+    }
+
+    /// Next job for `worker` once its own deque is empty: a batch from the
+    /// injector, otherwise one job stolen from a peer. The flag marks steals.
+    fn find_job<I>(
+        &self,
+        worker: usize,
+        local: &Worker<Job<I>>,
+        injector: &Injector<Job<I>>,
+        stealers: &[Stealer<Job<I>>],
+    ) -> Option<(Job<I>, bool)> {
+        loop {
+            let mut retry = false;
+            match injector.steal_batch_and_pop(local) {
+                Steal::Success(job) => return Some((job, false)),
+                Steal::Retry => retry = true,
+                Steal::Empty => {}
+            }
+            // Start with the next worker so victims are spread out
+            for offset in 1..stealers.len() {
+                match stealers[(worker + offset) % stealers.len()].steal() {
+                    Steal::Success(job) => return Some((job, true)),
+                    Steal::Retry => retry = true,
+                    Steal::Empty => {}
+                }
+            }
+            if !retry {
+                return None;
+            }
+        }
+    }
+
+    fn add_idle(&self, since: Instant) {
+        let nanos = since.elapsed().as_nanos().min(u64::MAX as u128) as u64;
+        self.idle_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_results_in_order_with_stealing() {
+        let scheduler = WorkStealingScheduler::new(4);
+        // All jobs start on worker 0; the slow ones force the others to steal
+        let jobs: Vec<_> = (0..64u64).map(|i| (Some(0), i)).collect();
+        let results = scheduler.run_with_affinity(jobs, |_, i| {
+            if i % 8 == 0 {
+                std::thread::sleep(Duration::from_millis(2));
+            }
+            i * i
+        });
+        assert_eq!(results, (0..64u64).map(|i| i * i).collect::<Vec<_>>());
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.jobs, 64);
+        assert!(stats.steals > 0);
+        assert_eq!(stats.affinity_hits + stats.steals, 64);
+        assert!(stats.steal_ratio() > 0.0);
+
+        scheduler.reset_stats();
+        let workers = scheduler.run(vec![(); 8], |worker, ()| worker);
+        assert_eq!(workers.len(), 8);
+        assert_eq!(scheduler.stats().jobs, 8);
+        assert_eq!(scheduler.block_affinity(7, 8), 3);
+        let empty: Vec<()> = scheduler.run(Vec::<()>::new(), |_, ()| ());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_scheduled_batch_backprop_matches_sequential() {
+        use crate::training::{BatchBackprop, TrainingAlgorithm, TrainingData};
+        use crate::NetworkBuilder;
+
+        let data = TrainingData {
+            inputs: (0..23)
+                .map(|i| vec![(i as f64 * 0.37).sin(), (i as f64 * 0.11).cos()])
+                .collect(),
+            outputs: (0..23).map(|i| vec![(i % 2) as f64]).collect(),
+        };
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(5)
+            .output_layer(1)
+            .build();
+        let (mut sequential, mut scheduled) = (network.clone(), network);
+        let mut plain = BatchBackprop::new(0.5).with_momentum(0.3);
+        let mut parallel = BatchBackprop::new(0.5)
+            .with_momentum(0.3)
+            .with_scheduler(WorkStealingScheduler::new(3), 4);
+        for _ in 0..3 {
+            let a = plain.train_epoch(&mut sequential, &data).unwrap();
+            let b = parallel.train_epoch(&mut scheduled, &data).unwrap();
+            assert!((a - b).abs() < 1e-12);
+        }
+        for (a, b) in sequential.get_weights().iter().zip(scheduled.get_weights()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        // 23 samples in jobs of 4, three epochs
+        assert_eq!(parallel.scheduler().unwrap().stats().jobs, 18);
+    }
+
+    #[test]
+    #[should_panic(expected = "job failed")]
+    fn test_panicking_job_propagates() {
+        let scheduler = WorkStealingScheduler::new(3);
+        scheduler.run((0..12).collect(), |_, i| {
+            if i == 5 {
+                panic!("job failed");
+            }
+        });
+    }
+}
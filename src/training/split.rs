@@ -0,0 +1,318 @@
+//! Data set splitting with a train/test contamination guard
+//!
+//! [`DataSplitter`] partitions a data set into training, validation and test
+//! subsets. Because duplicated rows in the source data end up on both sides of
+//! a random split, every split is checked with [`find_contamination`], which
+//! hashes the held-out inputs and looks them up in the training set. The
+//! [`ContaminationPolicy`] decides whether overlaps are ignored, logged or
+//! rejected.
+
+use super::{TrainingData, TrainingError};
+use num_traits::Float;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// What to do when held-out samples also appear in the training set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContaminationPolicy {
+    /// Skip the check
+    Ignore,
+    /// Log a warning (with the `logging` feature) and continue
+    #[default]
+    Warn,
+    /// Fail with [`TrainingError::InvalidData`]
+    Error,
+}
+
+/// Result of a contamination check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContaminationReport {
+    /// Indices of held-out samples whose input also occurs in the training set
+    pub contaminated: Vec<usize>,
+    /// Number of held-out samples checked
+    pub held_out_samples: usize,
+}
+
+impl ContaminationReport {
+    pub fn is_clean(&self) -> bool {
+        self.contaminated.is_empty()
+    }
+
+    /// Fraction of held-out samples that also occur in the training set
+    pub fn fraction(&self) -> f64 {
+        if self.held_out_samples == 0 {
+            0.0
+        } else {
+            self.contaminated.len() as f64 / self.held_out_samples as f64
+        }
+    }
+}
+
+/// Find held-out samples whose input vector also occurs in `train`
+///
+/// Inputs are compared bit for bit (with `-0.0` equal to `0.0`); hashes only
+/// narrow down the candidates, so collisions never cause false positives.
+pub fn find_contamination<T: Float>(
+    train: &TrainingData<T>,
+    held_out: &TrainingData<T>,
+) -> ContaminationReport {
+    let mut index: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, input) in train.inputs.iter().enumerate() {
+        index.entry(hash_sample(input)).or_default().push(i);
+    }
+
+    let contaminated = held_out
+        .inputs
+        .iter()
+        .enumerate()
+        .filter(|(_, input)| {
+            index.get(&hash_sample(input)).is_some_and(|candidates| {
+                candidates
+                    .iter()
+                    .any(|&i| same_sample(&train.inputs[i], input))
+            })
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    ContaminationReport {
+        contaminated,
+        held_out_samples: held_out.inputs.len(),
+    }
+}
+
+/// Run [`find_contamination`] and apply `policy`
+///
+/// `name` identifies the held-out set in messages (e.g. `"validation"`).
+/// Returns `None` when the policy is [`ContaminationPolicy::Ignore`].
+pub fn check_contamination<T: Float>(
+    train: &TrainingData<T>,
+    held_out: &TrainingData<T>,
+    name: &str,
+    policy: ContaminationPolicy,
+) -> Result<Option<ContaminationReport>, TrainingError> {
+    if policy == ContaminationPolicy::Ignore {
+        return Ok(None);
+    }
+
+    let report = find_contamination(train, held_out);
+    if !report.is_clean() {
+        let message = format!(
+            "{} of {} {name} samples also appear in the training set",
+            report.contaminated.len(),
+            report.held_out_samples
+        );
+        if policy == ContaminationPolicy::Error {
+            return Err(TrainingError::InvalidData(message));
+        }
+        #[cfg(feature = "logging")]
+        log::warn!("{message}");
+    }
+    Ok(Some(report))
+}
+
+fn canonical_bits<T: Float>(value: T) -> u64 {
+    let value = value.to_f64().unwrap_or(f64::NAN);
+    if value == 0.0 {
+        0
+    } else {
+        value.to_bits()
+    }
+}
+
+fn hash_sample<T: Float>(input: &[T]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    input.len().hash(&mut hasher);
+    for &value in input {
+        canonical_bits(value).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn same_sample<T: Float>(a: &[T], b: &[T]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(&x, &y)| canonical_bits(x) == canonical_bits(y))
+}
+
+/// Training, validation and test subsets produced by [`DataSplitter`]
+#[derive(Debug, Clone)]
+pub struct DataSplit<T: Float> {
+    pub train: TrainingData<T>,
+    pub validation: TrainingData<T>,
+    pub test: TrainingData<T>,
+    /// Overlap between the validation and training sets
+    pub validation_contamination: Option<ContaminationReport>,
+    /// Overlap between the test and training sets
+    pub test_contamination: Option<ContaminationReport>,
+}
+
+/// Splits a data set into training, validation and test subsets
+#[derive(Debug, Clone)]
+pub struct DataSplitter {
+    validation_fraction: f64,
+    test_fraction: f64,
+    shuffle: bool,
+    seed: Option<u64>,
+    contamination_policy: ContaminationPolicy,
+}
+
+impl Default for DataSplitter {
+    fn default() -> Self {
+        Self {
+            validation_fraction: 0.0,
+            test_fraction: 0.2,
+            shuffle: true,
+            seed: None,
+            contamination_policy: ContaminationPolicy::default(),
+        }
+    }
+}
+
+impl DataSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fraction of samples placed in the validation set
+    pub fn with_validation_fraction(mut self, fraction: f64) -> Self {
+        self.validation_fraction = fraction;
+        self
+    }
+
+    /// Fraction of samples placed in the test set
+    pub fn with_test_fraction(mut self, fraction: f64) -> Self {
+        self.test_fraction = fraction;
+        self
+    }
+
+    /// Keep the original sample order instead of shuffling
+    pub fn with_shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Seed the shuffle for reproducible splits
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_contamination_policy(mut self, policy: ContaminationPolicy) -> Self {
+        self.contamination_policy = policy;
+        self
+    }
+
+    /// Split `data`; held-out sets are taken from the end of the (shuffled) order
+    pub fn split<T: Float>(&self, data: &TrainingData<T>) -> Result<DataSplit<T>, TrainingError> {
+        let fractions = [self.validation_fraction, self.test_fraction];
+        if fractions.iter().any(|f| !(0.0..1.0).contains(f))
+            || self.validation_fraction + self.test_fraction >= 1.0
+        {
+            return Err(TrainingError::InvalidData(format!(
+                "invalid split fractions: validation {}, test {}",
+                self.validation_fraction, self.test_fraction
+            )));
+        }
+        if data.inputs.len() != data.outputs.len() {
+            return Err(TrainingError::InvalidData(format!(
+                "{} inputs but {} outputs",
+                data.inputs.len(),
+                data.outputs.len()
+            )));
+        }
+
+        let mut order: Vec<usize> = (0..data.inputs.len()).collect();
+        if self.shuffle {
+            let mut rng = match self.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            order.shuffle(&mut rng);
+        }
+
+        let len = order.len();
+        let test_len = (len as f64 * self.test_fraction).round() as usize;
+        let validation_len = (len as f64 * self.validation_fraction).round() as usize;
+        let train_len = len.saturating_sub(test_len + validation_len);
+
+        let subset = |indices: &[usize]| TrainingData {
+            inputs: indices.iter().map(|&i| data.inputs[i].clone()).collect(),
+            outputs: indices.iter().map(|&i| data.outputs[i].clone()).collect(),
+        };
+        let train = subset(&order[..train_len]);
+        let validation = subset(&order[train_len..train_len + validation_len]);
+        let test = subset(&order[train_len + validation_len..]);
+
+        let policy = self.contamination_policy;
+        let validation_contamination =
+            check_contamination(&train, &validation, "validation", policy)?;
+        let test_contamination = check_contamination(&train, &test, "test", policy)?;
+
+        Ok(DataSplit {
+            train,
+            validation,
+            test,
+            validation_contamination,
+            test_contamination,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(n: usize) -> TrainingData<f32> {
+        TrainingData {
+            inputs: (0..n).map(|i| vec![i as f32, 1.0]).collect(),
+            outputs: (0..n).map(|i| vec![(i % 2) as f32]).collect(),
+        }
+    }
+
+    #[test]
+    fn test_split_sizes_and_reproducibility() {
+        let splitter = DataSplitter::new()
+            .with_validation_fraction(0.2)
+            .with_test_fraction(0.1)
+            .with_seed(7);
+        let a = splitter.split(&data(50)).unwrap();
+        let b = splitter.split(&data(50)).unwrap();
+
+        assert_eq!(a.train.inputs.len(), 35);
+        assert_eq!(a.validation.inputs.len(), 10);
+        assert_eq!(a.test.inputs.len(), 5);
+        assert_eq!(a.test.inputs, b.test.inputs);
+        assert!(a.validation_contamination.unwrap().is_clean());
+        assert!(DataSplitter::new()
+            .with_test_fraction(1.0)
+            .split(&data(4))
+            .is_err());
+    }
+
+    #[test]
+    fn test_duplicates_are_detected() {
+        let mut duplicated = data(10);
+        duplicated.inputs.push(vec![-0.0, 1.0]);
+        duplicated.outputs.push(vec![1.0]);
+
+        let splitter = DataSplitter::new()
+            .with_test_fraction(0.1)
+            .with_shuffle(false)
+            .with_contamination_policy(ContaminationPolicy::Error);
+        assert!(splitter.split(&duplicated).is_err());
+
+        let split = splitter
+            .with_contamination_policy(ContaminationPolicy::Warn)
+            .split(&duplicated)
+            .unwrap();
+        let report = split.test_contamination.unwrap();
+        assert_eq!(report.contaminated, vec![0]);
+        assert_eq!(report.fraction(), 1.0);
+    }
+}
@@ -0,0 +1,195 @@
+//! Per-epoch training instrumentation
+
+use super::TrainingAlgorithm;
+use num_traits::Float;
+use std::time::Duration;
+
+/// Statistics collected by an optimizer while training
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingStatistics<T: Float> {
+    /// Number of epochs trained
+    pub epochs: usize,
+    /// Wall-clock duration of each epoch
+    pub epoch_times: Vec<Duration>,
+    /// Total number of training samples processed
+    pub samples_processed: u64,
+    /// Largest estimated working set of the optimizer (network copy,
+    /// gradient buffers and optimizer state) in bytes
+    pub peak_memory_bytes: usize,
+    /// L2 norm of the gradient applied in each epoch (for incremental training,
+    /// the mean per-sample gradient norm)
+    pub gradient_norm_history: Vec<T>,
+    /// Time each worker thread spent computing gradients, summed over the
+    /// recorded epochs; empty for single-threaded optimizers
+    pub worker_times: Vec<Duration>,
+    /// Per-layer weight gradients of the most recent epoch, for full-batch
+    /// optimizers; empty for the others
+    pub last_weight_gradients: Vec<Vec<T>>,
+}
+
+impl<T: Float> Default for TrainingStatistics<T> {
+    fn default() -> Self {
+        Self {
+            epochs: 0,
+            epoch_times: Vec::new(),
+            samples_processed: 0,
+            peak_memory_bytes: 0,
+            gradient_norm_history: Vec::new(),
+            worker_times: Vec::new(),
+            last_weight_gradients: Vec::new(),
+        }
+    }
+}
+
+impl<T: Float> TrainingStatistics<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one completed epoch
+    pub fn record_epoch(
+        &mut self,
+        elapsed: Duration,
+        samples: usize,
+        gradient_norm: T,
+        memory_bytes: usize,
+    ) {
+        self.epochs += 1;
+        self.epoch_times.push(elapsed);
+        self.samples_processed += samples as u64;
+        self.peak_memory_bytes = self.peak_memory_bytes.max(memory_bytes);
+        self.gradient_norm_history.push(gradient_norm);
+    }
+
+    /// Keep the weight gradients applied in the most recent epoch
+    pub fn record_gradients(&mut self, weight_gradients: Vec<Vec<T>>) {
+        self.last_weight_gradients = weight_gradients;
+    }
+
+    /// Add the busy time of each worker thread in one epoch
+    pub fn record_worker_times(&mut self, times: &[Duration]) {
+        if self.worker_times.len() < times.len() {
+            self.worker_times.resize(times.len(), Duration::ZERO);
+        }
+        for (total, &time) in self.worker_times.iter_mut().zip(times) {
+            *total += time;
+        }
+    }
+
+    /// Total time spent training
+    pub fn total_time(&self) -> Duration {
+        self.epoch_times.iter().sum()
+    }
+
+    /// Mean epoch duration, if any epoch was recorded
+    pub fn mean_epoch_time(&self) -> Option<Duration> {
+        let epochs = u32::try_from(self.epoch_times.len()).ok()?;
+        (epochs > 0).then(|| self.total_time() / epochs)
+    }
+
+    /// Samples processed per second over all recorded epochs
+    pub fn samples_per_second(&self) -> f64 {
+        let seconds = self.total_time().as_secs_f64();
+        if seconds > 0.0 {
+            self.samples_processed as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Gradient norm of the most recent epoch
+    pub fn last_gradient_norm(&self) -> Option<T> {
+        self.gradient_norm_history.last().copied()
+    }
+
+    /// Weight gradients of the most recent epoch, if recorded
+    pub fn last_weight_gradients(&self) -> Option<&[Vec<T>]> {
+        (!self.last_weight_gradients.is_empty()).then_some(self.last_weight_gradients.as_slice())
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Training algorithm that exposes instrumentation
+pub trait AdvancedTrainingAlgorithm<T: Float>: TrainingAlgorithm<T> {
+    /// Statistics collected since construction or the last reset
+    fn statistics(&self) -> &TrainingStatistics<T>;
+
+    /// Clear the collected statistics
+    fn reset_statistics(&mut self);
+}
+
+/// L2 norm over layered weight and bias gradients
+pub(crate) fn gradient_norm<T: Float>(weights: &[Vec<T>], biases: &[Vec<T>]) -> T {
+    weights
+        .iter()
+        .chain(biases)
+        .flatten()
+        .fold(T::zero(), |acc, &g| acc + g * g)
+        .sqrt()
+}
+
+/// Size in bytes of a set of layered buffers
+pub(crate) fn buffer_bytes<T>(buffers: &[&[Vec<T>]]) -> usize {
+    buffers
+        .iter()
+        .flat_map(|layers| layers.iter())
+        .map(|layer| layer.len() * std::mem::size_of::<T>())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::xor;
+    use crate::training::{
+        Adam, AdamW, BatchBackprop, DataParallel, IncrementalBackprop, Lamb, Lars, Lion, Nadam,
+        Quickprop, RAdam, Rprop, TrainingData,
+    };
+    use crate::NetworkBuilder;
+
+    fn check<A: AdvancedTrainingAlgorithm<f32>>(mut algorithm: A) {
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let data = xor();
+
+        for _ in 0..3 {
+            algorithm.train_epoch(&mut network, &data).unwrap();
+        }
+
+        let stats = algorithm.statistics();
+        assert_eq!(stats.epochs, 3);
+        assert_eq!(stats.epoch_times.len(), 3);
+        assert_eq!(stats.samples_processed, 12);
+        assert_eq!(stats.gradient_norm_history.len(), 3);
+        assert!(stats
+            .gradient_norm_history
+            .iter()
+            .all(|n| n.is_finite() && *n > 0.0));
+        assert!(stats.peak_memory_bytes > 0);
+
+        algorithm.reset_statistics();
+        assert_eq!(algorithm.statistics().epochs, 0);
+    }
+
+    #[test]
+    fn test_all_optimizers_collect_statistics() {
+        check(IncrementalBackprop::new(0.1));
+        check(BatchBackprop::new(0.1));
+        check(Rprop::new());
+        check(Quickprop::new());
+        check(Adam::new(0.01));
+        check(AdamW::new(0.01));
+        check(Nadam::new(0.01));
+        check(Lion::new(0.001));
+        check(RAdam::new(0.01));
+        check(Lars::lars(0.01));
+        check(Lamb::lamb(0.01));
+        check(DataParallel::new(0.1).with_replicas(2));
+    }
+}
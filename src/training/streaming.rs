@@ -0,0 +1,459 @@
+//! Training on datasets larger than memory
+//!
+//! [`TrainingData`] holds every sample in memory. A [`DataSource`] instead
+//! hands out the data in chunks, one pass at a time, and
+//! [`TrainingAlgorithm::train_epoch_from`](super::TrainingAlgorithm::train_epoch_from)
+//! trains on each chunk as it arrives, so only a chunk, the shuffle buffer
+//! and the prefetch queue are resident at once.
+//!
+//! [`StreamingTrainingData`] is the provided source. It reads samples from a
+//! CSV file, reopened for every pass, or from any iterator factory. A
+//! shuffle buffer of `n` samples hands them out in random order: the
+//! shuffle is exact for datasets up to `n` samples and local beyond that, so
+//! pre-shuffle files that are sorted. With prefetching enabled a background
+//! thread reads and parses ahead while the network trains; a file is a
+//! sequential stream, so one thread reads it.
+//!
+//! ```
+//! use do_fann::training::{IncrementalBackprop, StreamingTrainingData, TrainingAlgorithm};
+//! use do_fann::NetworkBuilder;
+//!
+//! let xor = [([0.0, 0.0], 0.0), ([0.0, 1.0], 1.0), ([1.0, 0.0], 1.0), ([1.0, 1.0], 0.0)];
+//! let mut source = StreamingTrainingData::from_fn(move || {
+//!     Ok(xor.into_iter().map(|(input, output)| Ok((input.to_vec(), vec![output]))))
+//! })
+//! .with_chunk_size(2)
+//! .with_shuffle_buffer(4, 7)
+//! .with_prefetch(16);
+//!
+//! let mut network = NetworkBuilder::<f32>::new()
+//!     .input_layer(2)
+//!     .hidden_layer(3)
+//!     .output_layer(1)
+//!     .build();
+//! let mut trainer = IncrementalBackprop::new(0.5);
+//! let error = trainer.train_epoch_from(&mut network, &mut source)?;
+//! assert!(error.is_finite());
+//! # Ok::<(), do_fann::TrainingError>(())
+//! ```
+
+use super::{TrainingData, TrainingError};
+use num_traits::Float;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, Receiver};
+
+/// One `(input, output)` training pair
+pub type Sample<T> = (Vec<T>, Vec<T>);
+
+type SampleIter<T> = Box<dyn Iterator<Item = Result<Sample<T>, TrainingError>> + Send>;
+type OpenFn<T> = Box<dyn FnMut() -> Result<SampleIter<T>, TrainingError> + Send>;
+
+/// Training data delivered in chunks, one pass at a time
+pub trait DataSource<T: Float> {
+    /// Start a new pass over the data, abandoning any pass in progress
+    fn reset(&mut self) -> Result<(), TrainingError>;
+
+    /// Next chunk of the current pass, or `None` once it is exhausted
+    fn next_chunk(&mut self) -> Result<Option<TrainingData<T>>, TrainingError>;
+}
+
+/// Where a [`StreamingTrainingData`] reads its samples from
+enum Origin<T> {
+    Fn(OpenFn<T>),
+    Csv {
+        path: PathBuf,
+        num_inputs: usize,
+        num_outputs: usize,
+    },
+}
+
+/// Samples of the current pass, read inline or from the prefetch thread
+enum Feed<T> {
+    Inline(SampleIter<T>),
+    Prefetched(Receiver<Result<Sample<T>, TrainingError>>),
+}
+
+impl<T> Feed<T> {
+    fn next(&mut self) -> Option<Result<Sample<T>, TrainingError>> {
+        match self {
+            Self::Inline(samples) => samples.next(),
+            // A closed channel means the reader thread finished the pass
+            Self::Prefetched(receiver) => receiver.recv().ok(),
+        }
+    }
+}
+
+struct Pass<T> {
+    feed: Feed<T>,
+    buffer: Vec<Sample<T>>,
+    rng: StdRng,
+    exhausted: bool,
+}
+
+/// [`DataSource`] streaming samples from a file or iterator
+///
+/// Every pass reopens the origin, so each epoch sees the whole dataset.
+/// Shuffling is seeded per pass, like
+/// [`epoch_permutation`](super::epoch_permutation), which keeps runs
+/// reproducible as long as prefetching does not reorder samples (it does
+/// not: the queue is first in, first out).
+pub struct StreamingTrainingData<T: Float> {
+    origin: Origin<T>,
+    delimiter: char,
+    chunk_size: usize,
+    shuffle_buffer: usize,
+    seed: u64,
+    prefetch: usize,
+    passes: u64,
+    pass: Option<Pass<T>>,
+}
+
+impl<T: Float + Send + 'static> StreamingTrainingData<T> {
+    /// Stream the samples yielded by `open`, which is called once per pass
+    pub fn from_fn<F, I>(mut open: F) -> Self
+    where
+        F: FnMut() -> Result<I, TrainingError> + Send + 'static,
+        I: IntoIterator<Item = Result<Sample<T>, TrainingError>>,
+        I::IntoIter: Send + 'static,
+    {
+        Self::with_origin(Origin::Fn(Box::new(move || {
+            Ok(Box::new(open()?.into_iter()) as SampleIter<T>)
+        })))
+    }
+
+    /// Stream a CSV file whose rows hold `num_inputs` input fields followed
+    /// by `num_outputs` output fields
+    ///
+    /// Blank lines and lines starting with `#` are ignored, fields may be
+    /// quoted, and a first row that does not parse as numbers is treated as
+    /// a header.
+    pub fn from_csv(path: impl Into<PathBuf>, num_inputs: usize, num_outputs: usize) -> Self {
+        Self::with_origin(Origin::Csv {
+            path: path.into(),
+            num_inputs,
+            num_outputs,
+        })
+    }
+
+    fn with_origin(origin: Origin<T>) -> Self {
+        Self {
+            origin,
+            delimiter: ',',
+            chunk_size: 1,
+            shuffle_buffer: 0,
+            seed: 0,
+            prefetch: 0,
+            passes: 0,
+            pass: None,
+        }
+    }
+
+    /// Field separator of CSV files, `,` by default
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Samples per chunk handed to the training algorithm (default 1)
+    ///
+    /// Batch algorithms update once per chunk, so this is their mini-batch
+    /// size.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Shuffle through a buffer of `capacity` samples, seeded with `seed`;
+    /// 0 or 1 keeps the file order (the default)
+    pub fn with_shuffle_buffer(mut self, capacity: usize, seed: u64) -> Self {
+        self.shuffle_buffer = capacity;
+        self.seed = seed;
+        self
+    }
+
+    /// Read up to `samples` ahead on a background thread; 0 reads inline
+    /// (the default)
+    pub fn with_prefetch(mut self, samples: usize) -> Self {
+        self.prefetch = samples;
+        self
+    }
+
+    fn open(&mut self) -> Result<SampleIter<T>, TrainingError> {
+        match &mut self.origin {
+            Origin::Fn(open) => open(),
+            Origin::Csv {
+                path,
+                num_inputs,
+                num_outputs,
+            } => {
+                let file = File::open(&*path).map_err(|e| {
+                    TrainingError::TrainingFailed(format!("{}: {e}", path.display()))
+                })?;
+                Ok(Box::new(CsvSamples {
+                    lines: BufReader::new(file).lines().enumerate(),
+                    delimiter: self.delimiter,
+                    num_inputs: *num_inputs,
+                    num_outputs: *num_outputs,
+                    first_row: true,
+                    failed: false,
+                    _marker: PhantomData,
+                }))
+            }
+        }
+    }
+
+    fn next_sample(&mut self) -> Result<Option<Sample<T>>, TrainingError> {
+        if self.pass.is_none() {
+            self.reset()?;
+        }
+        let capacity = self.shuffle_buffer.max(1);
+        let Some(pass) = self.pass.as_mut() else {
+            return Ok(None);
+        };
+        while !pass.exhausted && pass.buffer.len() < capacity {
+            match pass.feed.next() {
+                Some(sample) => pass.buffer.push(sample?),
+                None => pass.exhausted = true,
+            }
+        }
+        if pass.buffer.is_empty() {
+            return Ok(None);
+        }
+        let index = if capacity > 1 {
+            pass.rng.gen_range(0..pass.buffer.len())
+        } else {
+            0
+        };
+        Ok(Some(pass.buffer.swap_remove(index)))
+    }
+}
+
+impl<T: Float + Send + 'static> DataSource<T> for StreamingTrainingData<T> {
+    fn reset(&mut self) -> Result<(), TrainingError> {
+        // Dropping the old pass closes its channel, which stops its reader
+        self.pass = None;
+        let samples = self.open()?;
+        let feed = if self.prefetch == 0 {
+            Feed::Inline(samples)
+        } else {
+            let (sender, receiver) = sync_channel(self.prefetch);
+            std::thread::Builder::new()
+                .name("do-fann-prefetch".into())
+                .spawn(move || {
+                    for sample in samples {
+                        let failed = sample.is_err();
+                        if sender.send(sample).is_err() || failed {
+                            break;
+                        }
+                    }
+                })
+                .map_err(|e| TrainingError::TrainingFailed(e.to_string()))?;
+            Feed::Prefetched(receiver)
+        };
+        let stream = self
+            .seed
+            .wrapping_add(self.passes.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        self.passes += 1;
+        self.pass = Some(Pass {
+            feed,
+            buffer: Vec::with_capacity(self.shuffle_buffer.max(1)),
+            rng: StdRng::seed_from_u64(stream),
+            exhausted: false,
+        });
+        Ok(())
+    }
+
+    fn next_chunk(&mut self) -> Result<Option<TrainingData<T>>, TrainingError> {
+        let mut chunk = TrainingData {
+            inputs: Vec::with_capacity(self.chunk_size),
+            outputs: Vec::with_capacity(self.chunk_size),
+        };
+        while chunk.inputs.len() < self.chunk_size {
+            let Some((input, output)) = self.next_sample()? else {
+                break;
+            };
+            chunk.inputs.push(input);
+            chunk.outputs.push(output);
+        }
+        if chunk.inputs.is_empty() {
+            // Ending the pass here makes the next call start a new one
+            self.pass = None;
+            return Ok(None);
+        }
+        Ok(Some(chunk))
+    }
+}
+
+/// Rows of a delimited text file parsed into samples
+struct CsvSamples<T> {
+    lines: std::iter::Enumerate<std::io::Lines<BufReader<File>>>,
+    delimiter: char,
+    num_inputs: usize,
+    num_outputs: usize,
+    first_row: bool,
+    /// Set after the first error, which ends the stream
+    failed: bool,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Float> CsvSamples<T> {
+    fn parse_row(&self, line: &str) -> Result<Sample<T>, String> {
+        let mut row = line
+            .split(self.delimiter)
+            .map(|field| {
+                let field = field.trim().trim_matches('"').trim();
+                field
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(T::from)
+                    .ok_or_else(|| format!("invalid number '{field}'"))
+            })
+            .collect::<Result<Vec<T>, String>>()?;
+        let expected = self.num_inputs + self.num_outputs;
+        if row.len() != expected {
+            return Err(format!("expected {expected} fields, got {}", row.len()));
+        }
+        let output = row.split_off(self.num_inputs);
+        Ok((row, output))
+    }
+}
+
+impl<T: Float> Iterator for CsvSamples<T> {
+    type Item = Result<Sample<T>, TrainingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        while let Some((line_idx, line)) = self.lines.next() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(TrainingError::TrainingFailed(e.to_string())));
+                }
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let first_row = std::mem::replace(&mut self.first_row, false);
+            match self.parse_row(line) {
+                Ok(sample) => return Some(Ok(sample)),
+                Err(_) if first_row => continue,
+                Err(message) => {
+                    self.failed = true;
+                    return Some(Err(TrainingError::InvalidData(format!(
+                        "line {}: {message}",
+                        line_idx + 1
+                    ))));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::{BatchBackprop, TrainingAlgorithm};
+    use crate::NetworkBuilder;
+
+    /// Every chunk of one pass, flattened to the first input of each sample
+    fn drain(source: &mut StreamingTrainingData<f32>) -> (Vec<usize>, Vec<f32>) {
+        source.reset().unwrap();
+        let (mut sizes, mut firsts) = (Vec::new(), Vec::new());
+        while let Some(chunk) = source.next_chunk().unwrap() {
+            sizes.push(chunk.inputs.len());
+            firsts.extend(chunk.inputs.iter().map(|input| input[0]));
+        }
+        (sizes, firsts)
+    }
+
+    #[test]
+    fn test_csv_stream_chunks_shuffles_and_trains() {
+        let path = std::env::temp_dir().join(format!("stream_{}.csv", std::process::id()));
+        let mut csv = String::from("x0,x1,y\n# comment\n\n");
+        for i in 0..50 {
+            let x = i as f32 / 50.0;
+            csv.push_str(&format!("{x},\"{}\",{}\n", 1.0 - x, x * 0.5));
+        }
+        std::fs::write(&path, csv).unwrap();
+
+        let mut source = StreamingTrainingData::<f32>::from_csv(&path, 2, 1).with_chunk_size(8);
+        let (sizes, ordered) = drain(&mut source);
+        assert_eq!(sizes, [8, 8, 8, 8, 8, 8, 2]);
+        assert_eq!(
+            ordered,
+            (0..50).map(|i| i as f32 / 50.0).collect::<Vec<_>>()
+        );
+
+        let shuffled = |prefetch| {
+            StreamingTrainingData::<f32>::from_csv(&path, 2, 1)
+                .with_chunk_size(8)
+                .with_shuffle_buffer(16, 3)
+                .with_prefetch(prefetch)
+        };
+        let mut source = shuffled(0);
+        let (_, first_pass) = drain(&mut source);
+        let (_, second_pass) = drain(&mut source);
+        assert_ne!(first_pass, ordered);
+        assert_ne!(first_pass, second_pass);
+        let mut sorted = first_pass.clone();
+        sorted.sort_by(f32::total_cmp);
+        assert_eq!(sorted, ordered);
+        // Prefetching keeps the order, so runs stay reproducible
+        assert_eq!(drain(&mut shuffled(4)).1, first_pass);
+
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .output_layer(1)
+            .build();
+        let mut trainer = BatchBackprop::new(0.5);
+        let mut source = shuffled(4);
+        let first = trainer.train_epoch_from(&mut network, &mut source).unwrap();
+        let mut last = first;
+        for _ in 0..30 {
+            last = trainer.train_epoch_from(&mut network, &mut source).unwrap();
+        }
+        assert!(last < first, "error rose from {first} to {last}");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let path = std::env::temp_dir().join(format!("stream_bad_{}.csv", std::process::id()));
+        std::fs::write(&path, "0.1,0.2,0.3\n0.4,oops,0.6\n").unwrap();
+        let mut source = StreamingTrainingData::<f32>::from_csv(&path, 2, 1).with_prefetch(2);
+        source.reset().unwrap();
+        assert!(source.next_chunk().unwrap().is_some());
+        match source.next_chunk() {
+            Err(TrainingError::InvalidData(message)) => assert!(message.starts_with("line 2")),
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let mut missing = StreamingTrainingData::<f32>::from_csv(&path, 2, 1);
+        assert!(matches!(
+            missing.reset(),
+            Err(TrainingError::TrainingFailed(_))
+        ));
+
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        let mut empty = StreamingTrainingData::<f32>::from_fn(|| Ok(Vec::new()));
+        assert!(matches!(
+            BatchBackprop::new(0.1).train_epoch_from(&mut network, &mut empty),
+            Err(TrainingError::InvalidData(_))
+        ));
+    }
+}
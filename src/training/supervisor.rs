@@ -0,0 +1,349 @@
+//! Background training with health checks
+//!
+//! [`TrainingSupervisor`] moves a network, an algorithm and a data set onto a
+//! background thread and returns a [`SupervisorHandle`]. A daemon polls
+//! [`health`](SupervisorHandle::health) for its status endpoint, uses
+//! [`is_stalled`](SupervisorHandle::is_stalled) as a watchdog, and pauses,
+//! resumes or stops the run. Commands take effect between epochs; an epoch
+//! in progress always completes.
+
+use super::{TrainingAlgorithm, TrainingData, TrainingError};
+use crate::Network;
+use num_traits::Float;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Lifecycle of a supervised run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupervisorState {
+    Running,
+    /// Parked between epochs until resumed or stopped
+    Paused,
+    /// Stopped on request
+    Stopped,
+    /// Reached the epoch limit or the target error
+    Finished,
+    /// An epoch returned an error; [`SupervisorHandle::join`] returns it
+    Failed,
+}
+
+impl SupervisorState {
+    /// Whether the training thread has exited or is about to
+    pub fn is_terminal(self) -> bool {
+        matches!(self, Self::Stopped | Self::Finished | Self::Failed)
+    }
+}
+
+/// Snapshot returned by [`SupervisorHandle::health`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainingHealth<T: Float> {
+    pub state: SupervisorState,
+    /// Completed epochs
+    pub epoch: usize,
+    /// Training error of the last completed epoch
+    pub loss: Option<T>,
+    pub last_epoch_time: Option<Duration>,
+    /// Time since the last epoch completed, or since the start before the
+    /// first one
+    pub since_progress: Duration,
+    /// Size of the network's weights and biases
+    pub model_bytes: usize,
+    /// Resident memory of the process; only available on Linux
+    pub resident_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Run,
+    Pause,
+    Stop,
+}
+
+struct Progress<T: Float> {
+    state: SupervisorState,
+    epoch: usize,
+    loss: Option<T>,
+    last_epoch_time: Option<Duration>,
+    last_progress: Instant,
+}
+
+struct Shared<T: Float> {
+    command: Mutex<Command>,
+    wake: Condvar,
+    progress: Mutex<Progress<T>>,
+    model_bytes: usize,
+}
+
+fn lock<V>(mutex: &Mutex<V>) -> MutexGuard<'_, V> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<T: Float> Shared<T> {
+    fn set_state(&self, state: SupervisorState) {
+        lock(&self.progress).state = state;
+    }
+
+    /// Block while paused; `false` once a stop was requested
+    fn wait_for_run(&self) -> bool {
+        let mut command = lock(&self.command);
+        if *command == Command::Pause {
+            self.set_state(SupervisorState::Paused);
+            while *command == Command::Pause {
+                command = self
+                    .wake
+                    .wait(command)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+            if *command == Command::Run {
+                self.set_state(SupervisorState::Running);
+            }
+        }
+        *command == Command::Run
+    }
+}
+
+/// Runs training on a background thread
+pub struct TrainingSupervisor<T: Float> {
+    algorithm: Box<dyn TrainingAlgorithm<T>>,
+    data: TrainingData<T>,
+    max_epochs: usize,
+    target_error: Option<T>,
+}
+
+impl<T: Float + Send + 'static> TrainingSupervisor<T> {
+    pub fn new(
+        algorithm: Box<dyn TrainingAlgorithm<T>>,
+        data: TrainingData<T>,
+        max_epochs: usize,
+    ) -> Self {
+        Self {
+            algorithm,
+            data,
+            max_epochs,
+            target_error: None,
+        }
+    }
+
+    /// Finish once an epoch's error is at or below `target_error`
+    pub fn with_target_error(mut self, target_error: T) -> Self {
+        self.target_error = Some(target_error);
+        self
+    }
+
+    /// Start training `network` on a new thread
+    pub fn spawn(self, network: Network<T>) -> Result<SupervisorHandle<T>, TrainingError> {
+        self.data.check_shapes(&network)?;
+        let shared = Arc::new(Shared {
+            command: Mutex::new(Command::Run),
+            wake: Condvar::new(),
+            progress: Mutex::new(Progress {
+                state: SupervisorState::Running,
+                epoch: 0,
+                loss: None,
+                last_epoch_time: None,
+                last_progress: Instant::now(),
+            }),
+            model_bytes: network.total_connections() * std::mem::size_of::<T>(),
+        });
+        let worker = Arc::clone(&shared);
+        let thread = thread::Builder::new()
+            .name("do-fann-training".to_string())
+            .spawn(move || self.run(network, &worker))
+            .map_err(|e| TrainingError::TrainingFailed(format!("cannot spawn thread: {e}")))?;
+        Ok(SupervisorHandle {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    fn run(
+        mut self,
+        mut network: Network<T>,
+        shared: &Shared<T>,
+    ) -> Result<Network<T>, TrainingError> {
+        for _ in 0..self.max_epochs {
+            if !shared.wait_for_run() {
+                shared.set_state(SupervisorState::Stopped);
+                return Ok(network);
+            }
+            let start = Instant::now();
+            let loss = match self.algorithm.train_epoch(&mut network, &self.data) {
+                Ok(loss) => loss,
+                Err(e) => {
+                    shared.set_state(SupervisorState::Failed);
+                    return Err(e);
+                }
+            };
+            let mut progress = lock(&shared.progress);
+            progress.epoch += 1;
+            progress.loss = Some(loss);
+            progress.last_epoch_time = Some(start.elapsed());
+            progress.last_progress = Instant::now();
+            if self.target_error.is_some_and(|target| loss <= target) {
+                break;
+            }
+        }
+        shared.set_state(SupervisorState::Finished);
+        Ok(network)
+    }
+}
+
+/// Controls a run started by [`TrainingSupervisor::spawn`]
+///
+/// Dropping the handle without [`join`](Self::join) asks the thread to stop
+/// and detaches it.
+pub struct SupervisorHandle<T: Float> {
+    shared: Arc<Shared<T>>,
+    thread: Option<JoinHandle<Result<Network<T>, TrainingError>>>,
+}
+
+impl<T: Float> SupervisorHandle<T> {
+    pub fn health(&self) -> TrainingHealth<T> {
+        let progress = lock(&self.shared.progress);
+        TrainingHealth {
+            state: progress.state,
+            epoch: progress.epoch,
+            loss: progress.loss,
+            last_epoch_time: progress.last_epoch_time,
+            since_progress: progress.last_progress.elapsed(),
+            model_bytes: self.shared.model_bytes,
+            resident_bytes: resident_bytes(),
+        }
+    }
+
+    /// Whether the run is supposed to be making progress but has not
+    /// completed an epoch within `timeout`
+    pub fn is_stalled(&self, timeout: Duration) -> bool {
+        let progress = lock(&self.shared.progress);
+        progress.state == SupervisorState::Running && progress.last_progress.elapsed() > timeout
+    }
+
+    /// Park the thread after the current epoch
+    pub fn pause(&self) {
+        self.send(Command::Pause);
+    }
+
+    pub fn resume(&self) {
+        self.send(Command::Run);
+        // The stall clock starts over rather than counting the pause
+        lock(&self.shared.progress).last_progress = Instant::now();
+    }
+
+    /// Stop after the current epoch; [`join`](Self::join) returns the
+    /// network trained so far
+    pub fn stop(&self) {
+        self.send(Command::Stop);
+    }
+
+    fn send(&self, command: Command) {
+        let mut current = lock(&self.shared.command);
+        if *current != Command::Stop {
+            *current = command;
+        }
+        self.shared.wake.notify_all();
+    }
+
+    /// Wait for the thread to exit and take back the network
+    pub fn join(mut self) -> Result<Network<T>, TrainingError> {
+        let thread = self.thread.take().ok_or_else(|| {
+            TrainingError::TrainingFailed("training thread already joined".to_string())
+        })?;
+        thread
+            .join()
+            .map_err(|_| TrainingError::TrainingFailed("training thread panicked".to_string()))?
+    }
+}
+
+impl<T: Float> Drop for SupervisorHandle<T> {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.stop();
+        }
+    }
+}
+
+/// Resident set size of this process from `/proc/self/status`
+fn resident_bytes() -> Option<usize> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::{xor, xor_network};
+    use crate::training::IncrementalBackprop;
+    use crate::NetworkBuilder;
+
+    fn wait_until(handle: &SupervisorHandle<f32>, done: impl Fn(&TrainingHealth<f32>) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done(&handle.health()) {
+            assert!(
+                Instant::now() < deadline,
+                "timed out: {:?}",
+                handle.health()
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_pause_resume_stop() {
+        let supervisor =
+            TrainingSupervisor::new(Box::new(IncrementalBackprop::new(0.5)), xor(), usize::MAX);
+        let handle = supervisor.spawn(xor_network()).unwrap();
+        wait_until(&handle, |health| health.epoch > 0);
+        let health = handle.health();
+        assert!(health.loss.is_some() && health.last_epoch_time.is_some());
+        assert!(health.model_bytes > 0);
+        if cfg!(target_os = "linux") {
+            assert!(health.resident_bytes.is_some());
+        }
+
+        handle.pause();
+        wait_until(&handle, |health| health.state == SupervisorState::Paused);
+        let paused_at = handle.health().epoch;
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(handle.health().epoch, paused_at);
+        assert!(!handle.is_stalled(Duration::ZERO));
+
+        handle.resume();
+        wait_until(&handle, |health| health.epoch > paused_at);
+        handle.stop();
+        wait_until(&handle, |health| health.state.is_terminal());
+        assert_eq!(handle.health().state, SupervisorState::Stopped);
+        let trained = handle.join().unwrap();
+        assert_eq!(trained.num_inputs(), 2);
+    }
+
+    #[test]
+    fn test_finish_and_failure() {
+        let handle = TrainingSupervisor::new(Box::new(IncrementalBackprop::new(0.5)), xor(), 3)
+            .spawn(xor_network())
+            .unwrap();
+        let mut trained = handle.join().unwrap();
+        assert_eq!(trained.run(&[0.0, 1.0]).len(), 1);
+
+        let bad = TrainingData {
+            inputs: vec![vec![0.0]],
+            outputs: vec![vec![0.0]],
+        };
+        let result =
+            TrainingSupervisor::new(Box::new(IncrementalBackprop::new(0.5)), bad, 3).spawn(trained);
+        assert!(matches!(result, Err(TrainingError::InvalidData(_))));
+
+        let handle = TrainingSupervisor::new(Box::new(IncrementalBackprop::new(0.5)), xor(), 100)
+            .with_target_error(f32::INFINITY)
+            .spawn(xor_network())
+            .unwrap();
+        wait_until(&handle, |health| health.state.is_terminal());
+        let health = handle.health();
+        assert_eq!((health.state, health.epoch), (SupervisorState::Finished, 1));
+    }
+}
@@ -2,21 +2,10 @@
 
 #[cfg(test)]
 mod tests {
+    use crate::tests::fixtures::xor;
     use crate::training::*;
     use crate::{ActivationFunction, Network};
 
-    fn create_xor_data() -> TrainingData<f32> {
-        TrainingData {
-            inputs: vec![
-                vec![0.0, 0.0],
-                vec![0.0, 1.0],
-                vec![1.0, 0.0],
-                vec![1.0, 1.0],
-            ],
-            outputs: vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]],
-        }
-    }
-
     fn create_simple_network() -> Network<f32> {
         let mut network = Network::new(&[2, 3, 1]);
         network.set_activation_function_hidden(ActivationFunction::Sigmoid);
@@ -28,7 +17,7 @@ mod tests {
     #[test]
     fn test_adam_training() {
         let mut network = create_simple_network();
-        let data = create_xor_data();
+        let data = xor();
 
         let mut trainer = Adam::new(0.01);
 
@@ -43,7 +32,7 @@ mod tests {
     #[test]
     fn test_adamw_training() {
         let mut network = create_simple_network();
-        let data = create_xor_data();
+        let data = xor();
 
         let mut trainer = AdamW::new(0.01).with_weight_decay(0.001);
 
@@ -58,7 +47,7 @@ mod tests {
     #[test]
     fn test_incremental_backprop_training() {
         let mut network = create_simple_network();
-        let data = create_xor_data();
+        let data = xor();
 
         let mut trainer = IncrementalBackprop::new(0.1).with_momentum(0.9);
 
@@ -73,7 +62,7 @@ mod tests {
     #[test]
     fn test_batch_backprop_training() {
         let mut network = create_simple_network();
-        let data = create_xor_data();
+        let data = xor();
 
         let mut trainer = BatchBackprop::new(0.1).with_momentum(0.9);
 
@@ -88,7 +77,7 @@ mod tests {
     #[test]
     fn test_rprop_training() {
         let mut network = create_simple_network();
-        let data = create_xor_data();
+        let data = xor();
 
         let mut trainer = Rprop::new();
 
@@ -103,7 +92,7 @@ mod tests {
     #[test]
     fn test_quickprop_training() {
         let mut network = create_simple_network();
-        let data = create_xor_data();
+        let data = xor();
 
         let mut trainer = Quickprop::new();
 
@@ -117,12 +106,18 @@ mod tests {
 
     #[test]
     fn test_all_algorithms_improve_error() {
-        let data = create_xor_data();
+        let data = xor();
 
         // Test each algorithm for multiple epochs
         let algorithms: Vec<(&str, Box<dyn TrainingAlgorithm<f32>>)> = vec![
             ("Adam", Box::new(Adam::new(0.1))), // Higher learning rate for Adam
             ("AdamW", Box::new(AdamW::new(0.1))),
+            ("AMSGrad", Box::new(Adam::new(0.1).with_amsgrad(true))),
+            ("Nadam", Box::new(Nadam::new(0.1))),
+            ("Lion", Box::new(Lion::new(0.01))),
+            ("RAdam", Box::new(RAdam::new(0.1))),
+            ("LARS", Box::new(Lars::lars(0.05))),
+            ("LAMB", Box::new(Lamb::lamb(0.05))),
             (
                 "IncrementalBackprop",
                 Box::new(IncrementalBackprop::new(0.1)),
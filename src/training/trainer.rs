@@ -0,0 +1,1347 @@
+//! Unified training loop
+//!
+//! [`Trainer`] drives any [`TrainingAlgorithm`] for a number of epochs and
+//! evaluates an optional validation set on a configurable schedule. Validation
+//! can run every N epochs or every M training samples; in the latter case each
+//! epoch is fed to the algorithm in chunks of M samples, so batch algorithms
+//! update once per chunk.
+//!
+//! An optional [`BestModelKeeper`] snapshots the weights whenever the monitored
+//! error improves and rolls the network back at the end of training.
+//!
+//! With an [`AdaptiveBatchSize`] controller each epoch is fed in mini-batches
+//! whose size the controller adjusts from one epoch to the next.
+//!
+//! A [`NoiseScaleEstimator`] measures the gradient noise scale after every
+//! epoch; the estimate is published, recorded as `gradient_noise_scale` and
+//! passed on to the adaptive batch size controller.
+//!
+//! A [`Curriculum`] picks and orders the samples of each epoch before they
+//! are split into batches, so easy samples can be trained on first.
+//!
+//! Alternatively each epoch can be shuffled with a seeded permutation. The
+//! order actually used in every epoch can be kept in an [`OrderingLog`] and
+//! fed back with [`Trainer::with_replay`] to reproduce a run exactly.
+//!
+//! A [`CancellationToken`] can abort training between batches; the report
+//! then describes the work completed so far.
+//!
+//! A [`CheckpointManager`] saves the weights, optimizer state and epoch
+//! counter every N epochs. With [`RecoveryStrategy::CheckpointAndContinue`] a
+//! failed epoch or a non-finite training error rolls back to the latest valid
+//! checkpoint, and [`Trainer::resume`] continues an interrupted run.
+//!
+//! A [`LearningRateSchedule`] sets the algorithm's learning rate before every
+//! epoch; each change is published as [`TrainingEvent::LrChanged`].
+//!
+//! Progress is published as [`TrainingEvent`]s to any number of subscribers.
+//! A [`ProgressCallback`] additionally receives a [`ProgressReport`] per epoch
+//! with the learning rate, gradient norm, elapsed time and bit-fail count.
+//! Epoch and validation errors are also sent to an optional [`MetricsRecorder`],
+//! together with per-layer diagnostics from a [`WeightDistributionMonitor`].
+
+use super::{
+    check_contamination, AdaptiveBatchSize, BestModelKeeper, CancellationToken, Checkpoint,
+    CheckpointManager, ContaminationPolicy, ContaminationReport, Curriculum, EpochMetrics,
+    EventDispatcher, KeeperDecision, LearningRateSchedule, MetricsRecorder, NoiseScaleEstimator,
+    OrderingLog, ProgressCallback, ProgressReport, SampleLosses, SubscriptionId, TrainingAlgorithm,
+    TrainingCallback, TrainingData, TrainingError, TrainingEvent, WeightDistributionMonitor,
+};
+use crate::errors::{RecoveryContext, RecoveryStrategy};
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+use std::time::Instant;
+
+/// When the validation set is evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationSchedule {
+    /// After every N completed epochs
+    EveryEpochs(usize),
+    /// After every M training samples, possibly several times per epoch
+    EverySamples(usize),
+}
+
+impl Default for ValidationSchedule {
+    fn default() -> Self {
+        Self::EveryEpochs(1)
+    }
+}
+
+/// Metrics reported at each validation point
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationMetrics<T: Float> {
+    /// Epoch in progress (0-based)
+    pub epoch: usize,
+    /// Training samples processed since training started
+    pub samples_seen: u64,
+    /// Mean training error since the previous validation point
+    pub train_error: T,
+    /// Error on the validation set
+    pub validation_error: T,
+}
+
+/// Outcome of [`Trainer::train`]
+#[derive(Debug, Clone)]
+pub struct TrainingReport<T: Float> {
+    /// Number of completed epochs, including those restored from a checkpoint
+    pub epochs: usize,
+    /// Training error of the last epoch
+    pub final_error: T,
+    /// Every validation point, in order
+    pub validation_history: Vec<ValidationMetrics<T>>,
+    /// Whether training ended before `max_epochs`
+    pub stopped_early: bool,
+    /// Whether training stopped because the best-model keeper detected divergence
+    pub diverged: bool,
+    /// Lowest error seen by the best-model keeper
+    pub best_error: Option<T>,
+    /// Epoch of the best snapshot
+    pub best_epoch: Option<usize>,
+    /// Whether the network was rolled back to the best snapshot
+    pub restored_best: bool,
+    /// Whether training stopped because its cancellation token was triggered
+    pub cancelled: bool,
+    /// Overlap between the validation and training sets, when checked
+    pub contamination: Option<ContaminationReport>,
+}
+
+/// Epoch-based trainer with validation scheduling
+pub struct Trainer<T: Float> {
+    algorithm: Box<dyn TrainingAlgorithm<T>>,
+    max_epochs: usize,
+    target_error: Option<T>,
+    validation_data: Option<TrainingData<T>>,
+    validation_schedule: ValidationSchedule,
+    events: EventDispatcher<T>,
+    best_model_keeper: Option<BestModelKeeper<T>>,
+    restore_best: bool,
+    metrics_recorder: Option<Box<dyn MetricsRecorder>>,
+    weight_monitor: Option<WeightDistributionMonitor>,
+    cancellation: Option<CancellationToken>,
+    contamination_policy: ContaminationPolicy,
+    batch_size: Option<AdaptiveBatchSize>,
+    noise_scale: Option<NoiseScaleEstimator>,
+    curriculum: Option<Box<dyn Curriculum<T>>>,
+    lr_schedule: Option<Box<dyn LearningRateSchedule<T> + Send>>,
+    shuffle_seed: Option<u64>,
+    replay: Option<OrderingLog>,
+    ordering_log: Option<OrderingLog>,
+    sample_losses: Option<SampleLosses<T>>,
+    checkpoints: Option<CheckpointManager>,
+    recovery: Option<RecoveryContext>,
+    resume_epoch: usize,
+    progress_callback: Option<ProgressCallback<T>>,
+    bit_fail_limit: T,
+}
+
+impl<T: Float> Trainer<T> {
+    pub fn new(algorithm: Box<dyn TrainingAlgorithm<T>>, max_epochs: usize) -> Self {
+        Self {
+            algorithm,
+            max_epochs,
+            target_error: None,
+            validation_data: None,
+            validation_schedule: ValidationSchedule::default(),
+            events: EventDispatcher::new(),
+            best_model_keeper: None,
+            restore_best: true,
+            metrics_recorder: None,
+            weight_monitor: None,
+            cancellation: None,
+            contamination_policy: ContaminationPolicy::Ignore,
+            batch_size: None,
+            noise_scale: None,
+            curriculum: None,
+            lr_schedule: None,
+            shuffle_seed: None,
+            replay: None,
+            ordering_log: None,
+            sample_losses: None,
+            checkpoints: None,
+            recovery: None,
+            resume_epoch: 0,
+            progress_callback: None,
+            bit_fail_limit: cast(0.35),
+        }
+    }
+
+    /// Stop once the epoch training error reaches `target_error`
+    pub fn with_target_error(mut self, target_error: T) -> Self {
+        self.target_error = Some(target_error);
+        self
+    }
+
+    /// Evaluate `data` according to `schedule` during training
+    pub fn with_validation(mut self, data: TrainingData<T>, schedule: ValidationSchedule) -> Self {
+        self.validation_data = Some(data);
+        self.validation_schedule = schedule;
+        self
+    }
+
+    /// Track the best weights, judged by validation error when a validation set
+    /// is configured and by epoch training error otherwise
+    ///
+    /// The best snapshot is restored when training ends (unless disabled with
+    /// [`Trainer::restore_best`]) and always when the keeper detects divergence.
+    pub fn with_best_model_keeper(mut self, keeper: BestModelKeeper<T>) -> Self {
+        self.best_model_keeper = Some(keeper);
+        self
+    }
+
+    /// Whether to restore the best snapshot at the end of training
+    pub fn restore_best(mut self, restore: bool) -> Self {
+        self.restore_best = restore;
+        self
+    }
+
+    pub fn best_model_keeper(&self) -> Option<&BestModelKeeper<T>> {
+        self.best_model_keeper.as_ref()
+    }
+
+    /// Send training metrics to `recorder`
+    pub fn with_metrics_recorder(mut self, recorder: Box<dyn MetricsRecorder>) -> Self {
+        self.metrics_recorder = Some(recorder);
+        self
+    }
+
+    /// Compare layer weight distributions after every epoch
+    ///
+    /// Per-layer metrics and stall/explosion warnings go to the metrics
+    /// recorder.
+    pub fn with_weight_monitor(mut self, monitor: WeightDistributionMonitor) -> Self {
+        self.weight_monitor = Some(monitor);
+        self
+    }
+
+    /// Check that no validation sample also appears in the training data
+    ///
+    /// The check runs at the start of [`Trainer::train`]; with
+    /// [`ContaminationPolicy::Error`] a contaminated validation set is rejected.
+    pub fn with_contamination_check(mut self, policy: ContaminationPolicy) -> Self {
+        self.contamination_policy = policy;
+        self
+    }
+
+    /// Stop training once `token` is cancelled
+    ///
+    /// The token is checked before every epoch and, with
+    /// [`ValidationSchedule::EverySamples`], after every chunk of samples.
+    /// It is also passed to the algorithm through
+    /// [`TrainingAlgorithm::set_cancellation`], so optimizers that poll it
+    /// stop in the middle of an epoch.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.algorithm.set_cancellation(Some(token.clone()));
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Feed each epoch in mini-batches sized by `controller`
+    ///
+    /// Cannot be combined with [`ValidationSchedule::EverySamples`], which
+    /// already fixes the chunk size.
+    pub fn with_adaptive_batch_size(mut self, controller: AdaptiveBatchSize) -> Self {
+        self.batch_size = Some(controller);
+        self
+    }
+
+    pub fn adaptive_batch_size(&self) -> Option<&AdaptiveBatchSize> {
+        self.batch_size.as_ref()
+    }
+
+    /// Estimate the gradient noise scale after every epoch
+    pub fn with_noise_scale(mut self, estimator: NoiseScaleEstimator) -> Self {
+        self.noise_scale = Some(estimator);
+        self
+    }
+
+    /// Display epoch progress, batch errors and an ETA on `sink`
+    #[cfg(feature = "progress")]
+    pub fn with_progress(mut self, sink: Box<dyn super::ProgressSink>) -> Self {
+        let tracker = super::ProgressTracker::new(self.max_epochs, sink);
+        self.events.subscribe(tracker.into_callback());
+        self
+    }
+
+    /// Subscribe to the trainer's events
+    pub fn subscribe(&mut self, callback: TrainingCallback<T>) -> SubscriptionId {
+        self.events.subscribe(callback)
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        self.events.unsubscribe(id)
+    }
+
+    /// Receive a [`ProgressReport`] after every epoch, replacing any earlier
+    /// progress callback
+    pub fn set_progress_callback(&mut self, callback: ProgressCallback<T>) {
+        self.progress_callback = Some(callback);
+    }
+
+    /// Largest output error not counted as a bit fail in progress reports
+    /// (default 0.35, as in FANN)
+    pub fn with_bit_fail_limit(mut self, limit: T) -> Self {
+        self.bit_fail_limit = limit;
+        self
+    }
+
+    pub fn algorithm(&self) -> &dyn TrainingAlgorithm<T> {
+        self.algorithm.as_ref()
+    }
+
+    pub fn algorithm_mut(&mut self) -> &mut dyn TrainingAlgorithm<T> {
+        self.algorithm.as_mut()
+    }
+
+    /// Select and order the samples of every epoch with a curriculum; the
+    /// number of samples used is recorded as `curriculum_samples`
+    pub fn with_curriculum(mut self, curriculum: Box<dyn Curriculum<T>>) -> Self {
+        self.curriculum = Some(curriculum);
+        self
+    }
+
+    /// Set the algorithm's learning rate from `schedule` before every epoch;
+    /// algorithms without a single learning rate are left unchanged
+    pub fn with_learning_rate_schedule(
+        mut self,
+        schedule: Box<dyn LearningRateSchedule<T> + Send>,
+    ) -> Self {
+        self.lr_schedule = Some(schedule);
+        self
+    }
+
+    /// Shuffle every epoch with a permutation derived from `seed` and the
+    /// epoch number
+    pub fn with_shuffle(mut self, seed: u64) -> Self {
+        self.shuffle_seed = Some(seed);
+        self
+    }
+
+    /// Train on the sample orders of a previous run, epoch by epoch
+    pub fn with_replay(mut self, log: OrderingLog) -> Self {
+        self.replay = Some(log);
+        self
+    }
+
+    /// Keep the sample order of every epoch; the log is reset by each call
+    /// to [`Trainer::train`]
+    pub fn record_ordering(mut self, record: bool) -> Self {
+        self.ordering_log = record.then(|| OrderingLog::new(None));
+        self
+    }
+
+    /// Sample orders of the last run, when recording is enabled
+    pub fn ordering_log(&self) -> Option<&OrderingLog> {
+        self.ordering_log.as_ref()
+    }
+
+    /// Measure the loss of every training sample after each epoch; the
+    /// hardest sample's loss is recorded as `hardest_sample_loss`
+    pub fn with_sample_losses(mut self, tracker: SampleLosses<T>) -> Self {
+        self.sample_losses = Some(tracker);
+        self
+    }
+
+    /// Per-sample losses as of the last epoch, when tracking is enabled
+    pub fn sample_losses(&self) -> Option<&SampleLosses<T>> {
+        self.sample_losses.as_ref()
+    }
+
+    /// Save a checkpoint whenever `manager` says one is due; each save is
+    /// published as [`TrainingEvent::CheckpointSaved`]
+    pub fn with_checkpoints(mut self, manager: CheckpointManager) -> Self {
+        self.checkpoints = Some(manager);
+        self
+    }
+
+    pub fn checkpoint_manager(&self) -> Option<&CheckpointManager> {
+        self.checkpoints.as_ref()
+    }
+
+    /// Recover from failed epochs according to `context`
+    ///
+    /// Only [`RecoveryStrategy::CheckpointAndContinue`] is acted on: when an
+    /// epoch fails or its training error is not finite, the network and
+    /// optimizer are restored from the latest valid checkpoint and training
+    /// continues after it, up to `max_retries` times per call to
+    /// [`Trainer::train`]. The restored files are listed in the context's
+    /// `checkpoints`.
+    pub fn with_recovery(mut self, context: RecoveryContext) -> Self {
+        self.recovery = Some(context);
+        self
+    }
+
+    pub fn recovery(&self) -> Option<&RecoveryContext> {
+        self.recovery.as_ref()
+    }
+
+    /// Restore `network` and the optimizer from the latest valid checkpoint
+    /// so the next call to [`Trainer::train`] continues after it; returns the
+    /// restored epoch, or `None` if there is no checkpoint
+    pub fn resume(&mut self, network: &mut Network<T>) -> Result<Option<usize>, TrainingError> {
+        let Some(manager) = &self.checkpoints else {
+            return Ok(None);
+        };
+        let epoch = manager.restore_latest(network, self.algorithm.as_mut())?;
+        self.resume_epoch = epoch.unwrap_or(0);
+        Ok(epoch)
+    }
+
+    /// Train `network` on `data`
+    pub fn train(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<TrainingReport<T>, TrainingError> {
+        validate_data(network, data)?;
+        let mut contamination = None;
+        if let Some(validation) = &self.validation_data {
+            validate_data(network, validation)?;
+            contamination =
+                check_contamination(data, validation, "validation", self.contamination_policy)?;
+        }
+        if let ValidationSchedule::EveryEpochs(0) | ValidationSchedule::EverySamples(0) =
+            self.validation_schedule
+        {
+            return Err(TrainingError::InvalidData(
+                "validation interval must be positive".to_string(),
+            ));
+        }
+        let order_sources = [
+            self.curriculum.is_some(),
+            self.shuffle_seed.is_some(),
+            self.replay.is_some(),
+        ];
+        if order_sources.iter().filter(|&&set| set).count() > 1 {
+            return Err(TrainingError::InvalidData(
+                "curriculum, shuffling and replay are mutually exclusive".to_string(),
+            ));
+        }
+        if let Some(log) = self.ordering_log.as_mut() {
+            *log = OrderingLog::new(self.shuffle_seed);
+        }
+        if self.batch_size.is_some() && self.validates_per_sample() {
+            return Err(TrainingError::InvalidData(
+                "adaptive batch size cannot be combined with sample-based validation".to_string(),
+            ));
+        }
+
+        self.events.dispatch(&TrainingEvent::TrainingStart {
+            total_epochs: self.max_epochs,
+        });
+        let result = self.train_loop(network, data, contamination);
+        self.events.dispatch(&TrainingEvent::TrainingEnd);
+        result
+    }
+
+    /// Body of [`Self::train`] after the configuration has been checked
+    fn train_loop(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+        contamination: Option<ContaminationReport>,
+    ) -> Result<TrainingReport<T>, TrainingError> {
+        let mut state = LoopState {
+            samples_seen: 0,
+            pending_error: T::zero(),
+            pending_samples: 0,
+            history: Vec::new(),
+            diverged: false,
+            cancelled: false,
+            started: Instant::now(),
+        };
+        if let Some(keeper) = self.best_model_keeper.as_mut() {
+            keeper.reset();
+        }
+        if let Some(monitor) = self.weight_monitor.as_mut() {
+            monitor.reset();
+            monitor.observe(network, 0, None);
+        }
+
+        if let Some(recovery) = self.recovery.as_mut() {
+            recovery.reset_retry_count();
+        }
+        let mut start_epoch = std::mem::take(&mut self.resume_epoch);
+        let (epochs, final_error, stopped_early) = loop {
+            match self.run(network, data, &mut state, start_epoch) {
+                Ok(outcome) => break outcome,
+                Err(error) => match self.recover(network)? {
+                    Some(epoch) => start_epoch = epoch,
+                    None => return Err(error),
+                },
+            }
+        };
+
+        let restored_best = match &self.best_model_keeper {
+            Some(keeper) if self.restore_best || state.diverged => keeper.restore(network),
+            _ => false,
+        };
+        let keeper = self.best_model_keeper.as_ref();
+        Ok(TrainingReport {
+            epochs,
+            final_error,
+            validation_history: state.history,
+            stopped_early,
+            diverged: state.diverged,
+            best_error: keeper.and_then(|k| k.best_error()),
+            best_epoch: keeper.and_then(|k| k.best_epoch()),
+            restored_best,
+            cancelled: state.cancelled,
+            contamination,
+        })
+    }
+
+    /// Run the epoch loop; returns completed epochs, last error and whether it stopped early
+    fn run(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+        state: &mut LoopState<T>,
+        start_epoch: usize,
+    ) -> Result<(usize, T, bool), TrainingError> {
+        let mut final_error = T::zero();
+
+        for epoch in start_epoch..self.max_epochs {
+            if self.check_cancelled(state) {
+                return Ok((epoch, final_error, true));
+            }
+            if !self.apply_lr_schedule(epoch) {
+                return Ok((epoch, final_error, true));
+            }
+            let scheduled = self.schedule_epoch(network, data, epoch)?;
+            let epoch_data = scheduled.as_ref().unwrap_or(data);
+            let samples = epoch_data.inputs.len();
+            let per_sample = self.validates_per_sample();
+            let chunk_size = match self.validation_schedule {
+                ValidationSchedule::EverySamples(interval) if per_sample => Some(interval),
+                _ => self.batch_size.as_ref().map(AdaptiveBatchSize::batch_size),
+            };
+
+            let epoch_error = match chunk_size {
+                Some(size) => {
+                    let started = Instant::now();
+                    let mut epoch_error = T::zero();
+                    for (batch, start) in (0..samples).step_by(size).enumerate() {
+                        let end = (start + size).min(samples);
+                        let chunk = TrainingData {
+                            inputs: epoch_data.inputs[start..end].to_vec(),
+                            outputs: epoch_data.outputs[start..end].to_vec(),
+                        };
+                        let error = self.algorithm.train_epoch(network, &chunk)?;
+                        let weight = cast(end - start);
+                        epoch_error = epoch_error + error * weight;
+                        state.record(error, end - start);
+
+                        let event = TrainingEvent::BatchEnd {
+                            epoch,
+                            batch,
+                            error,
+                        };
+                        if !self.events.dispatch(&event)
+                            || (per_sample && !self.validate(network, epoch, state))
+                            || self.check_cancelled(state)
+                        {
+                            return Ok((epoch + 1, error, true));
+                        }
+                    }
+                    if let Some(controller) = self.batch_size.as_mut() {
+                        controller.observe_epoch(samples, started.elapsed());
+                        if let Some(recorder) = self.metrics_recorder.as_mut() {
+                            recorder.record_scalar("batch_size", epoch, size as f64);
+                        }
+                    }
+                    epoch_error / cast(samples)
+                }
+                None => {
+                    let error = self.algorithm.train_epoch(network, epoch_data)?;
+                    state.record(error, samples);
+                    if self.check_cancelled(state) {
+                        return Ok((epoch + 1, error, true));
+                    }
+                    error
+                }
+            };
+            if !epoch_error.is_finite() && self.recovers_from_checkpoint() {
+                return Err(TrainingError::TrainingFailed(format!(
+                    "non-finite training error in epoch {epoch}"
+                )));
+            }
+
+            let due = match self.validation_schedule {
+                ValidationSchedule::EveryEpochs(n) => (epoch + 1) % n == 0,
+                ValidationSchedule::EverySamples(_) => false,
+            };
+            if due && !self.validate(network, epoch, state) {
+                return Ok((epoch + 1, epoch_error, true));
+            }
+
+            final_error = epoch_error;
+            self.record_epoch(network, data, epoch, epoch_error);
+            if let Some(path) = self.save_checkpoint(network, epoch + 1)? {
+                let event = TrainingEvent::CheckpointSaved {
+                    epoch,
+                    path: Some(path),
+                };
+                if !self.events.dispatch(&event) {
+                    return Ok((epoch + 1, final_error, true));
+                }
+            }
+            if !self.estimate_noise_scale(network, data, epoch) {
+                return Ok((epoch + 1, final_error, true));
+            }
+            let validation_error = state
+                .history
+                .last()
+                .filter(|m| m.epoch == epoch)
+                .map(|m| m.validation_error);
+            let event = TrainingEvent::EpochEnd {
+                epoch,
+                metrics: EpochMetrics {
+                    train_error: epoch_error,
+                    validation_error,
+                },
+            };
+            if !self.events.dispatch(&event) {
+                return Ok((epoch + 1, final_error, true));
+            }
+            if let Some(callback) = self.progress_callback.as_mut() {
+                let report = ProgressReport {
+                    epoch,
+                    train_error: epoch_error,
+                    validation_error,
+                    learning_rate: self.algorithm.learning_rate(),
+                    gradient_norm: self.algorithm.last_gradient_norm(),
+                    elapsed: state.started.elapsed(),
+                    bit_fails: self
+                        .algorithm
+                        .count_bit_fails(network, data, self.bit_fail_limit),
+                };
+                if !callback(&report) {
+                    return Ok((epoch + 1, final_error, true));
+                }
+            }
+            if self.validation_data.is_none()
+                && !self.observe_best(network, epoch_error, epoch, state)
+            {
+                return Ok((epoch + 1, final_error, true));
+            }
+            if !self.algorithm.call_callback(epoch, network, data) {
+                return Ok((epoch + 1, final_error, true));
+            }
+            if self
+                .target_error
+                .is_some_and(|target| epoch_error <= target)
+            {
+                return Ok((epoch + 1, final_error, epoch + 1 < self.max_epochs));
+            }
+        }
+
+        Ok((self.max_epochs, final_error, false))
+    }
+
+    /// The samples of `epoch` in curriculum, shuffled or replayed order, or
+    /// `None` to train on `data` as is
+    fn schedule_epoch(
+        &mut self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+        epoch: usize,
+    ) -> Result<Option<TrainingData<T>>, TrainingError> {
+        let samples = data.inputs.len();
+        let order = if let Some(curriculum) = self.curriculum.as_mut() {
+            let order = curriculum.schedule(epoch, network, data);
+            if let Some(recorder) = self.metrics_recorder.as_mut() {
+                recorder.record_scalar("curriculum_samples", epoch, order.len() as f64);
+            }
+            Some(order)
+        } else if let Some(seed) = self.shuffle_seed {
+            Some(super::epoch_permutation(seed, epoch, samples))
+        } else if let Some(replay) = &self.replay {
+            let order = replay.order(epoch).ok_or_else(|| {
+                TrainingError::InvalidData(format!("no replayed sample order for epoch {epoch}"))
+            })?;
+            Some(order.to_vec())
+        } else {
+            None
+        };
+
+        if let Some(log) = self.ordering_log.as_mut() {
+            log.push(order.clone().unwrap_or_else(|| (0..samples).collect()));
+        }
+        let Some(order) = order else {
+            return Ok(None);
+        };
+        if order.is_empty() {
+            return Err(TrainingError::InvalidData(format!(
+                "no samples scheduled for epoch {epoch}"
+            )));
+        }
+        if let Some(&index) = order.iter().find(|&&i| i >= samples) {
+            return Err(TrainingError::InvalidData(format!(
+                "scheduled sample {index} of {samples}"
+            )));
+        }
+        Ok(Some(TrainingData {
+            inputs: order.iter().map(|&i| data.inputs[i].clone()).collect(),
+            outputs: order.iter().map(|&i| data.outputs[i].clone()).collect(),
+        }))
+    }
+
+    /// Evaluate the validation set; returns `false` if a subscriber asks to stop
+    fn validate(&mut self, network: &Network<T>, epoch: usize, state: &mut LoopState<T>) -> bool {
+        let Some(validation) = &self.validation_data else {
+            return true;
+        };
+
+        let metrics = ValidationMetrics {
+            epoch,
+            samples_seen: state.samples_seen,
+            train_error: state.take_pending_error(),
+            validation_error: self.algorithm.calculate_error(network, validation),
+        };
+        let keep_going = self
+            .events
+            .dispatch(&TrainingEvent::ValidationEnd(metrics.clone()));
+        let error = metrics.validation_error;
+        if let Some(recorder) = self.metrics_recorder.as_mut() {
+            recorder.record_scalar(
+                "validation_error",
+                epoch,
+                error.to_f64().unwrap_or(f64::NAN),
+            );
+        }
+        state.history.push(metrics);
+        self.observe_best(network, error, epoch, state) && keep_going
+    }
+
+    /// Whether validation runs after every chunk of samples
+    fn validates_per_sample(&self) -> bool {
+        self.validation_data.is_some()
+            && matches!(
+                self.validation_schedule,
+                ValidationSchedule::EverySamples(_)
+            )
+    }
+
+    /// Apply the learning rate schedule for `epoch`; returns `false` if a
+    /// subscriber to the resulting `LrChanged` event asks to stop
+    fn apply_lr_schedule(&mut self, epoch: usize) -> bool {
+        let Some(schedule) = self.lr_schedule.as_mut() else {
+            return true;
+        };
+        let Some(old) = self.algorithm.learning_rate() else {
+            return true;
+        };
+        let new = schedule.get_rate(epoch);
+        if new == old {
+            return true;
+        }
+        self.algorithm.set_learning_rate(new);
+        self.events
+            .dispatch(&TrainingEvent::LrChanged { epoch, old, new })
+    }
+
+    fn check_cancelled(&self, state: &mut LoopState<T>) -> bool {
+        state.cancelled = self
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled);
+        state.cancelled
+    }
+
+    fn record_epoch(
+        &mut self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+        epoch: usize,
+        error: T,
+    ) {
+        if let Some(recorder) = self.metrics_recorder.as_mut() {
+            recorder.record_scalar("train_error", epoch, error.to_f64().unwrap_or(f64::NAN));
+        }
+        if let Some(tracker) = self.sample_losses.as_mut() {
+            tracker.record(network, data);
+            if let (Some(recorder), Some(&(_, loss))) =
+                (self.metrics_recorder.as_mut(), tracker.hardest(1).first())
+            {
+                recorder.record_scalar("hardest_sample_loss", epoch, loss);
+            }
+        }
+        if let Some(monitor) = self.weight_monitor.as_mut() {
+            let recorder = self
+                .metrics_recorder
+                .as_mut()
+                .map(|r| r.as_mut() as &mut dyn MetricsRecorder);
+            monitor.observe(network, epoch, recorder);
+            if let Some(gradients) = self.algorithm.last_weight_gradients() {
+                let recorder = self
+                    .metrics_recorder
+                    .as_mut()
+                    .map(|r| r.as_mut() as &mut dyn MetricsRecorder);
+                monitor.observe_gradients(gradients, epoch, recorder);
+            }
+        }
+    }
+
+    /// Estimate and publish the gradient noise scale; returns `false` if a
+    /// subscriber asks to stop
+    fn estimate_noise_scale(
+        &mut self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+        epoch: usize,
+    ) -> bool {
+        let Some(estimate) = self
+            .noise_scale
+            .as_ref()
+            .and_then(|estimator| estimator.estimate(network, data))
+        else {
+            return true;
+        };
+        if let Some(noise_scale) = estimate.noise_scale.and_then(|b| b.to_f64()) {
+            if let Some(recorder) = self.metrics_recorder.as_mut() {
+                recorder.record_scalar("gradient_noise_scale", epoch, noise_scale);
+            }
+            if let Some(controller) = self.batch_size.as_mut() {
+                controller.observe_noise_scale(noise_scale);
+            }
+        }
+        self.events
+            .dispatch(&TrainingEvent::GradientNoise { epoch, estimate })
+    }
+
+    /// Write a checkpoint after `epochs` completed epochs if one is due
+    fn save_checkpoint(
+        &self,
+        network: &Network<T>,
+        epochs: usize,
+    ) -> Result<Option<std::path::PathBuf>, TrainingError> {
+        match &self.checkpoints {
+            Some(manager) if manager.is_due(epochs) => {
+                let checkpoint = Checkpoint::capture(network, self.algorithm.as_ref(), epochs);
+                manager.save(&checkpoint).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn recovers_from_checkpoint(&self) -> bool {
+        self.checkpoints.is_some()
+            && self.recovery.as_ref().is_some_and(|recovery| {
+                matches!(recovery.strategy, RecoveryStrategy::CheckpointAndContinue)
+            })
+    }
+
+    /// Roll back to the latest valid checkpoint after a failed epoch; returns
+    /// the epoch to continue from, or `None` if recovery is not possible
+    fn recover(&mut self, network: &mut Network<T>) -> Result<Option<usize>, TrainingError> {
+        if !self.recovers_from_checkpoint() {
+            return Ok(None);
+        }
+        let (Some(manager), Some(recovery)) = (&self.checkpoints, self.recovery.as_mut()) else {
+            return Ok(None);
+        };
+        if !recovery.should_retry() {
+            return Ok(None);
+        }
+        let Some((path, checkpoint)) = manager.latest() else {
+            return Ok(None);
+        };
+        checkpoint.apply(network, self.algorithm.as_mut())?;
+        recovery.increment_retry();
+        recovery.checkpoints.push(path.display().to_string());
+        Ok(Some(checkpoint.epoch))
+    }
+
+    /// Report an error to the best-model keeper; returns `false` on divergence
+    fn observe_best(
+        &mut self,
+        network: &Network<T>,
+        error: T,
+        epoch: usize,
+        state: &mut LoopState<T>,
+    ) -> bool {
+        let Some(keeper) = self.best_model_keeper.as_mut() else {
+            return true;
+        };
+        if keeper.observe(network, error, epoch) == KeeperDecision::Diverged {
+            state.diverged = true;
+            return false;
+        }
+        true
+    }
+}
+
+struct LoopState<T: Float> {
+    samples_seen: u64,
+    pending_error: T,
+    pending_samples: usize,
+    history: Vec<ValidationMetrics<T>>,
+    diverged: bool,
+    cancelled: bool,
+    started: Instant,
+}
+
+impl<T: Float> LoopState<T> {
+    fn record(&mut self, mean_error: T, samples: usize) {
+        self.samples_seen += samples as u64;
+        self.pending_error = self.pending_error + mean_error * cast(samples);
+        self.pending_samples += samples;
+    }
+
+    fn take_pending_error(&mut self) -> T {
+        let error = if self.pending_samples == 0 {
+            T::zero()
+        } else {
+            self.pending_error / cast(self.pending_samples)
+        };
+        self.pending_error = T::zero();
+        self.pending_samples = 0;
+        error
+    }
+}
+
+pub(super) fn validate_data<T: Float>(
+    network: &Network<T>,
+    data: &TrainingData<T>,
+) -> Result<(), TrainingError> {
+    if data.inputs.is_empty() {
+        return Err(TrainingError::InvalidData(
+            "no samples provided".to_string(),
+        ));
+    }
+    // The algorithm decides whether it can train a recurrent network
+    data.check_widths(network)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fixtures::{xor, xor_network};
+    use crate::training::{
+        epoch_permutation, Adam, AdaptiveBatchOptions, DifficultyCurriculum, InMemoryRecorder,
+        IncrementalBackprop, StepDecay,
+    };
+    use crate::NetworkBuilder;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_validation_every_epochs() {
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 10)
+            .with_validation(xor(), ValidationSchedule::EveryEpochs(3));
+        let report = trainer.train(&mut xor_network(), &xor()).unwrap();
+
+        assert_eq!(report.epochs, 10);
+        assert!(!report.stopped_early);
+        let epochs: Vec<usize> = report.validation_history.iter().map(|m| m.epoch).collect();
+        assert_eq!(epochs, vec![2, 5, 8]);
+        assert_eq!(report.validation_history[0].samples_seen, 12);
+    }
+
+    #[test]
+    fn test_cancellation_returns_partial_report() {
+        let token = CancellationToken::new();
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 100)
+            .with_validation(xor(), ValidationSchedule::EverySamples(1))
+            .with_cancellation(token.clone());
+        let handle = token.clone();
+        trainer.subscribe(Box::new(move |event| {
+            if let TrainingEvent::BatchEnd {
+                epoch: 1, batch: 2, ..
+            } = event
+            {
+                handle.cancel();
+            }
+            true
+        }));
+
+        let report = trainer.train(&mut xor_network(), &xor()).unwrap();
+        assert!(report.cancelled);
+        assert!(report.stopped_early);
+        assert_eq!(report.epochs, 2);
+        assert_eq!(report.validation_history.len(), 7);
+
+        let report = trainer.train(&mut xor_network(), &xor()).unwrap();
+        assert_eq!(report.epochs, 0);
+        assert!(report.cancelled);
+    }
+
+    #[test]
+    fn test_contamination_check() {
+        let trainer = || {
+            Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 1)
+                .with_validation(xor(), ValidationSchedule::EveryEpochs(1))
+        };
+
+        let report = trainer().train(&mut xor_network(), &xor()).unwrap();
+        assert!(report.contamination.is_none());
+
+        let report = trainer()
+            .with_contamination_check(ContaminationPolicy::Warn)
+            .train(&mut xor_network(), &xor())
+            .unwrap();
+        assert_eq!(report.contamination.unwrap().contaminated.len(), 4);
+
+        assert!(trainer()
+            .with_contamination_check(ContaminationPolicy::Error)
+            .train(&mut xor_network(), &xor())
+            .is_err());
+    }
+
+    #[test]
+    fn test_adaptive_batch_size() {
+        let mut data = xor();
+        data.inputs.extend(data.inputs.clone());
+        data.outputs.extend(data.outputs.clone());
+
+        // A tolerance of 1 ignores timing noise, so the batch only grows
+        let controller = AdaptiveBatchSize::new(AdaptiveBatchOptions {
+            initial: 2,
+            max: 8,
+            throughput_tolerance: 1.0,
+            ..Default::default()
+        });
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 4)
+            .with_adaptive_batch_size(controller);
+        let batches = Arc::new(Mutex::new(vec![0; 4]));
+        let sink = Arc::clone(&batches);
+        trainer.subscribe(Box::new(move |event: &TrainingEvent<f32>| {
+            if let TrainingEvent::BatchEnd { epoch, .. } = event {
+                sink.lock().unwrap()[*epoch] += 1;
+            }
+            true
+        }));
+        trainer.train(&mut xor_network(), &data).unwrap();
+
+        assert_eq!(*batches.lock().unwrap(), vec![4, 2, 1, 1]);
+        let sizes: Vec<usize> = trainer
+            .adaptive_batch_size()
+            .unwrap()
+            .history()
+            .iter()
+            .map(|step| step.batch_size)
+            .collect();
+        assert_eq!(sizes, vec![2, 4, 8, 8]);
+
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 1)
+            .with_validation(xor(), ValidationSchedule::EverySamples(3))
+            .with_adaptive_batch_size(AdaptiveBatchSize::default());
+        assert!(trainer.train(&mut xor_network(), &data).is_err());
+    }
+
+    #[test]
+    fn test_noise_scale_reported() {
+        let mut data = xor();
+        data.inputs.extend(data.inputs.clone());
+        data.outputs.extend(data.outputs.clone());
+
+        let estimates = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&estimates);
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 3)
+            .with_noise_scale(NoiseScaleEstimator::new(2))
+            .with_adaptive_batch_size(AdaptiveBatchSize::default());
+        trainer.subscribe(Box::new(move |event: &TrainingEvent<f32>| {
+            if let TrainingEvent::GradientNoise { epoch, estimate } = event {
+                sink.lock().unwrap().push((*epoch, estimate.clone()));
+            }
+            true
+        }));
+        trainer.train(&mut xor_network(), &data).unwrap();
+
+        let estimates = estimates.lock().unwrap();
+        let epochs: Vec<usize> = estimates.iter().map(|(epoch, _)| *epoch).collect();
+        assert_eq!(epochs, vec![0, 1, 2]);
+        assert!(estimates
+            .iter()
+            .all(|(_, estimate)| estimate.big_batch == 8));
+
+        // The controller sees the latest usable estimate
+        let latest = estimates
+            .iter()
+            .rev()
+            .filter_map(|(_, estimate)| estimate.noise_scale)
+            .map(f64::from)
+            .find(|&noise_scale| noise_scale > 0.0);
+        assert_eq!(trainer.adaptive_batch_size().unwrap().noise_scale(), latest);
+    }
+
+    #[test]
+    fn test_validation_every_samples() {
+        let mut data = xor();
+        data.inputs.extend(data.inputs.clone());
+        data.outputs.extend(data.outputs.clone());
+
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 2)
+            .with_validation(xor(), ValidationSchedule::EverySamples(3));
+        let report = trainer.train(&mut xor_network(), &data).unwrap();
+
+        // 8 samples per epoch in chunks of 3, 3 and 2
+        let seen: Vec<u64> = report
+            .validation_history
+            .iter()
+            .map(|m| m.samples_seen)
+            .collect();
+        assert_eq!(seen, vec![3, 6, 8, 11, 14, 16]);
+        assert_eq!(report.validation_history[3].epoch, 1);
+    }
+
+    #[test]
+    fn test_callback_stops_training() {
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 100)
+            .with_validation(xor(), ValidationSchedule::EverySamples(2));
+        let mut validations = 0;
+        trainer.subscribe(Box::new(move |event| {
+            if let TrainingEvent::ValidationEnd(_) = event {
+                validations += 1;
+            }
+            validations < 3
+        }));
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&batches);
+        trainer.subscribe(Box::new(move |event| {
+            if let TrainingEvent::BatchEnd { epoch, batch, .. } = event {
+                sink.lock().unwrap().push((*epoch, *batch));
+            }
+            true
+        }));
+
+        let report = trainer.train(&mut xor_network(), &xor()).unwrap();
+        assert!(report.stopped_early);
+        assert_eq!(report.epochs, 2);
+        assert_eq!(report.validation_history.len(), 3);
+        assert_eq!(*batches.lock().unwrap(), vec![(0, 0), (0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn test_progress_reports() {
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 10)
+            .with_validation(xor(), ValidationSchedule::EveryEpochs(2))
+            .with_bit_fail_limit(0.0);
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reports);
+        trainer.set_progress_callback(Box::new(move |report| {
+            sink.lock().unwrap().push(report.clone());
+            report.epoch < 3
+        }));
+
+        let report = trainer.train(&mut xor_network(), &xor()).unwrap();
+        assert!(report.stopped_early);
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 4);
+        assert!(reports[0].validation_error.is_none());
+        assert!(reports[1].validation_error.is_some());
+        for (epoch, progress) in reports.iter().enumerate() {
+            assert_eq!(progress.epoch, epoch);
+            assert_eq!(progress.learning_rate, Some(0.1));
+            assert!(progress.gradient_norm.is_some_and(|norm| norm > 0.0));
+            assert_eq!(progress.bit_fails, 4);
+        }
+        assert!(reports[3].elapsed >= reports[0].elapsed);
+    }
+
+    #[test]
+    fn test_learning_rate_schedule() {
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 5)
+            .with_learning_rate_schedule(Box::new(StepDecay::new(0.4, 0.5, 2)));
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&changes);
+        trainer.subscribe(Box::new(move |event| {
+            if let TrainingEvent::LrChanged { epoch, old, new } = event {
+                sink.lock().unwrap().push((*epoch, *old, *new));
+            }
+            true
+        }));
+
+        trainer.train(&mut xor_network(), &xor()).unwrap();
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![(0, 0.1, 0.4), (2, 0.4, 0.2), (4, 0.2, 0.1)]
+        );
+        assert_eq!(trainer.algorithm().learning_rate(), Some(0.1));
+    }
+
+    #[test]
+    fn test_best_model_restored() {
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.5)), 20)
+            .with_validation(xor(), ValidationSchedule::EveryEpochs(1))
+            .with_best_model_keeper(BestModelKeeper::new());
+        let mut network = xor_network();
+        let report = trainer.train(&mut network, &xor()).unwrap();
+
+        let best_error = report.best_error.unwrap();
+        let best_epoch = report.best_epoch.unwrap();
+        assert!(report.restored_best);
+        assert_eq!(
+            best_error,
+            report.validation_history[best_epoch].validation_error
+        );
+        assert!(report
+            .validation_history
+            .iter()
+            .all(|m| m.validation_error >= best_error));
+        let restored_error = trainer.algorithm().calculate_error(&network, &xor());
+        assert!((restored_error - best_error).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_divergence_stops_training() {
+        // A negative learning rate climbs the error surface
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(-0.1)), 50)
+            .with_best_model_keeper(BestModelKeeper::new().with_divergence_factor(0.0))
+            .restore_best(false);
+        let report = trainer.train(&mut xor_network(), &xor()).unwrap();
+
+        assert!(report.diverged);
+        assert!(report.restored_best);
+        assert_eq!(report.epochs, 2);
+    }
+
+    #[test]
+    fn test_metrics_recorded() {
+        let recorder = Arc::new(Mutex::new(InMemoryRecorder::new()));
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.0)), 3)
+            .with_validation(xor(), ValidationSchedule::EveryEpochs(1))
+            .with_metrics_recorder(Box::new(Arc::clone(&recorder)))
+            .with_weight_monitor(WeightDistributionMonitor::new());
+        trainer.train(&mut xor_network(), &xor()).unwrap();
+
+        let recorder = recorder.lock().unwrap();
+        assert_eq!(recorder.series("train_error").len(), 3);
+        assert_eq!(recorder.series("validation_error").len(), 3);
+        assert_eq!(recorder.series("layer2/update_ratio").len(), 3);
+        // A zero learning rate leaves every layer stalled
+        assert_eq!(recorder.warnings.len(), 6);
+    }
+
+    #[test]
+    fn test_curriculum_orders_batches() {
+        let recorder = Arc::new(Mutex::new(InMemoryRecorder::new()));
+        // Samples with a larger first input count as easier
+        let curriculum =
+            DifficultyCurriculum::from_fn(|input: &[f32], _| -input[0] as f64).with_pacing(0.5, 2);
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.0)), 3)
+            .with_curriculum(Box::new(curriculum))
+            .with_validation(xor(), ValidationSchedule::EverySamples(1))
+            .with_metrics_recorder(Box::new(Arc::clone(&recorder)));
+        let mut trained = xor_network();
+        let initial = trained.clone();
+        let report = trainer.train(&mut trained, &xor()).unwrap();
+
+        let samples: Vec<u64> = report
+            .validation_history
+            .iter()
+            .filter(|m| m.epoch == 0)
+            .map(|m| m.samples_seen)
+            .collect();
+        assert_eq!(samples, vec![1, 2]);
+        // With a zero learning rate, the first chunk is the easiest sample alone
+        let easiest = TrainingData {
+            inputs: vec![vec![1.0, 0.0]],
+            outputs: vec![vec![1.0]],
+        };
+        let expected = IncrementalBackprop::new(0.0)
+            .train_epoch(&mut initial.clone(), &easiest)
+            .unwrap();
+        assert_eq!(report.validation_history[0].train_error, expected);
+
+        let recorder = recorder.lock().unwrap();
+        let used: Vec<f64> = recorder
+            .series("curriculum_samples")
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect();
+        assert_eq!(used, vec![2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_shuffled_run_replays_exactly() {
+        let data = TrainingData {
+            inputs: (0..12).map(|i| vec![i as f32 / 12.0, 1.0]).collect(),
+            outputs: (0..12).map(|i| vec![(i % 2) as f32]).collect(),
+        };
+        let initial = xor_network();
+        let run = |trainer: Trainer<f32>| {
+            let mut trainer = trainer
+                .with_validation(data.clone(), ValidationSchedule::EverySamples(5))
+                .record_ordering(true);
+            let mut network = initial.clone();
+            let report = trainer.train(&mut network, &data).unwrap();
+            (network, report, trainer.ordering_log().unwrap().clone())
+        };
+
+        let (shuffled, report, log) =
+            run(Trainer::new(Box::new(IncrementalBackprop::new(0.2)), 4).with_shuffle(3));
+        assert_eq!(log.seed, Some(3));
+        assert_eq!(log.len(), 4);
+        assert_ne!(log.order(0), log.order(1));
+        assert_eq!(log.order(2).unwrap(), epoch_permutation(3, 2, 12));
+
+        let (replayed, replay_report, replay_log) =
+            run(Trainer::new(Box::new(IncrementalBackprop::new(0.2)), 4).with_replay(log.clone()));
+        assert_eq!(replay_log.epochs, log.epochs);
+        assert_eq!(replayed.get_weights(), shuffled.get_weights());
+        let errors = |r: &TrainingReport<f32>| {
+            r.validation_history
+                .iter()
+                .map(|m| m.train_error)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(errors(&replay_report), errors(&report));
+
+        // Replaying past the end of the log is an error
+        let mut short = Trainer::new(Box::new(IncrementalBackprop::new(0.2)), 5).with_replay(log);
+        assert!(short.train(&mut initial.clone(), &data).is_err());
+    }
+
+    #[test]
+    fn test_recovers_from_checkpoint() {
+        let directory =
+            std::env::temp_dir().join(format!("trainer_checkpoints_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let manager = CheckpointManager::new(&directory).with_interval(2);
+
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 4)
+            .with_checkpoints(manager.clone());
+        let saved = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&saved);
+        trainer.subscribe(Box::new(move |event| {
+            if let TrainingEvent::CheckpointSaved { epoch, .. } = event {
+                sink.lock().unwrap().push(*epoch);
+            }
+            true
+        }));
+        let mut trained = xor_network();
+        trainer.train(&mut trained, &xor()).unwrap();
+        assert_eq!(*saved.lock().unwrap(), vec![1, 3]);
+
+        // A NaN learning rate breaks the first epoch; the checkpoint restores
+        // the weights, moments and learning rate it was taken with
+        let mut trainer = Trainer::new(Box::new(Adam::new(f32::NAN)), 6)
+            .with_checkpoints(manager.clone())
+            .with_recovery(RecoveryContext::new(
+                RecoveryStrategy::CheckpointAndContinue,
+            ));
+        let mut network = xor_network();
+        let report = trainer.train(&mut network, &xor()).unwrap();
+        assert_eq!(report.epochs, 6);
+        assert!(report.final_error.is_finite());
+        let recovery = trainer.recovery().unwrap();
+        assert_eq!(recovery.current_retry, 1);
+        assert!(recovery.checkpoints[0].ends_with("checkpoint-00000004.ckpt"));
+
+        // Resuming continues after the checkpoint the recovered run wrote
+        let mut trainer =
+            Trainer::new(Box::new(Adam::new(0.05)), 8).with_checkpoints(manager.with_interval(100));
+        assert_eq!(trainer.resume(&mut network).unwrap(), Some(6));
+        let report = trainer.train(&mut network, &xor()).unwrap();
+        assert_eq!(report.epochs, 8);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_schedule() {
+        let mut trainer = Trainer::new(Box::new(IncrementalBackprop::new(0.1)), 1)
+            .with_validation(xor(), ValidationSchedule::EverySamples(0));
+        assert!(trainer.train(&mut xor_network(), &xor()).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! Trimmed loss for outlier-robust training
+//!
+//! With a [`TrimmedLoss`] an optimizer ranks the samples of each batch by
+//! their loss and leaves the highest `fraction` of them out of the gradient.
+//! Mislabelled samples tend to carry the largest losses, so trimming them
+//! keeps a few bad labels from pulling a small regression network off the
+//! rest of the data. The ranking uses a plain forward pass without dropout.
+//!
+//! The batch-gradient optimizers ([`Adam`](super::Adam),
+//! [`AdamW`](super::AdamW), [`Nadam`](super::Nadam), [`RAdam`](super::RAdam),
+//! [`Lion`](super::Lion), [`BatchBackprop`](super::BatchBackprop),
+//! [`Rprop`](super::Rprop) and [`Quickprop`](super::Quickprop)) take one
+//! through
+//! [`with_trimmed_loss`](super::WithTrainingOptions::with_trimmed_loss). With mini-batches every batch is trimmed on
+//! its own. The error they return then covers only the kept samples.
+
+use super::{ErrorFunction, TrainingData};
+use crate::Network;
+use num_traits::Float;
+use std::borrow::Cow;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Largest fraction of a batch that may be trimmed
+pub const MAX_TRIM_FRACTION: f64 = 0.5;
+
+/// Fraction of highest-loss samples left out of each batch's gradient
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TrimmedLoss {
+    fraction: f64,
+}
+
+impl Default for TrimmedLoss {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl TrimmedLoss {
+    /// Keep every sample
+    pub fn none() -> Self {
+        Self { fraction: 0.0 }
+    }
+
+    /// Drop the top `fraction` of losses, clamped to
+    /// `[0, MAX_TRIM_FRACTION]`; NaN disables trimming
+    pub fn new(fraction: f64) -> Self {
+        let fraction = if fraction.is_nan() {
+            0.0
+        } else {
+            fraction.clamp(0.0, MAX_TRIM_FRACTION)
+        };
+        Self { fraction }
+    }
+
+    pub fn fraction(&self) -> f64 {
+        self.fraction
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.fraction > 0.0
+    }
+
+    /// Number of samples trimmed from a batch of `samples`; at least one
+    /// sample is always kept
+    pub fn trimmed_count(&self, samples: usize) -> usize {
+        let count = (samples as f64 * self.fraction).floor() as usize;
+        count.min(samples.saturating_sub(1))
+    }
+
+    /// Indices of the samples to keep, in data order; NaN losses are
+    /// trimmed first
+    pub fn kept<T: Float>(&self, losses: &[T]) -> Vec<usize> {
+        let trimmed = self.trimmed_count(losses.len());
+        let mut ranked: Vec<usize> = (0..losses.len()).collect();
+        ranked.sort_by(|&a, &b| {
+            let key = |i: usize| losses[i].to_f64().filter(|l| !l.is_nan());
+            key(b)
+                .unwrap_or(f64::INFINITY)
+                .total_cmp(&key(a).unwrap_or(f64::INFINITY))
+        });
+        let mut kept = ranked.split_off(trimmed);
+        kept.sort_unstable();
+        kept
+    }
+
+    /// `data` without its highest-loss samples under `network`; borrows
+    /// `data` when nothing is trimmed
+    pub fn trim<'a, T: Float + Default>(
+        &self,
+        network: &Network<T>,
+        data: &'a TrainingData<T>,
+        error_function: &dyn ErrorFunction<T>,
+    ) -> Cow<'a, TrainingData<T>> {
+        if self.trimmed_count(data.inputs.len()) == 0 {
+            return Cow::Borrowed(data);
+        }
+        use super::helpers::*;
+
+        let simple_network = network_to_simple(network);
+        let losses: Vec<T> = data
+            .inputs
+            .iter()
+            .zip(&data.outputs)
+            .map(|(input, desired)| {
+                let activations = forward_propagate(&simple_network, input);
+                error_function.calculate(&activations[activations.len() - 1], desired)
+            })
+            .collect();
+        let kept = self.kept(&losses);
+        Cow::Owned(TrainingData {
+            inputs: kept.iter().map(|&i| data.inputs[i].clone()).collect(),
+            outputs: kept.iter().map(|&i| data.outputs[i].clone()).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::{BatchBackprop, MseError, TrainingAlgorithm, WithTrainingOptions};
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_kept_drops_highest_losses() {
+        let trim = TrimmedLoss::new(0.25);
+        assert_eq!(
+            trim.kept(&[0.1, 5.0, 0.2, 0.3, f64::NAN, 0.0, 0.4, 0.5]),
+            [0, 2, 3, 5, 6, 7]
+        );
+        assert_eq!(trim.kept(&[1.0, 2.0]), [0, 1]);
+        assert_eq!(TrimmedLoss::new(0.9).fraction(), MAX_TRIM_FRACTION);
+        assert_eq!(TrimmedLoss::new(0.9).kept(&[3.0]), [0]);
+        assert!(!TrimmedLoss::new(f64::NAN).is_enabled());
+    }
+
+    #[test]
+    fn test_trimming_ignores_label_noise() {
+        let clean: Vec<f64> = (0..10).map(|i| 0.3 + i as f64 / 30.0).collect();
+        let mut noisy = clean.clone();
+        noisy[4] = 50.0;
+        let data = TrainingData {
+            inputs: (0..10).map(|i| vec![i as f64 / 10.0]).collect(),
+            outputs: noisy.iter().map(|&y| vec![y]).collect(),
+        };
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(1)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+
+        let trimmed = TrimmedLoss::new(0.1).trim(&network, &data, &MseError);
+        assert_eq!(trimmed.inputs.len(), 9);
+        assert!(!trimmed.outputs.contains(&vec![50.0]));
+
+        // The outlier has no effect on the trimmed gradient
+        let without = trimmed.into_owned();
+        let mut robust = network.clone();
+        BatchBackprop::new(0.5)
+            .with_trimmed_loss(0.1)
+            .train_epoch(&mut robust, &data)
+            .unwrap();
+        let mut reference = network.clone();
+        BatchBackprop::new(0.5)
+            .train_epoch(&mut reference, &without)
+            .unwrap();
+        assert_eq!(robust.get_weights(), reference.get_weights());
+    }
+}
@@ -0,0 +1,439 @@
+//! Layer-wise trust-ratio scaling (LARS / LAMB)
+//!
+//! With very large batches a single global learning rate is either too
+//! small for some layers or unstable for others, because the ratio between
+//! a layer's weight norm and its update norm varies widely across layers.
+//! LARS (You et al., 2017) and LAMB (You et al., 2019) fix this by rescaling
+//! every layer's update so that its norm is a fixed fraction, the trust
+//! coefficient, of that layer's weight norm.
+//!
+//! [`LayerwiseTrustRatio`] wraps an optimizer that implements
+//! [`TrustRatioUpdates`]: the wrapped optimizer chooses the update
+//! direction `d`, and at every step, before the weights are written, each
+//! layer's update `u = lr * d` is replaced by
+//! `lr * trust_coefficient * |w| / |d| * d`. Wrapping momentum SGD
+//! ([`BatchBackprop`]) gives LARS and wrapping [`Adam`] gives LAMB. Each
+//! layer thus moves by `lr * trust_coefficient * |w|`, so learning-rate
+//! schedules and warmup scale the step as they do for the plain optimizer.
+
+use super::statistics::AdvancedTrainingAlgorithm;
+use super::*;
+use crate::numeric::cast;
+use num_traits::Float;
+
+/// LARS: layer-wise trust ratio around momentum SGD
+pub type Lars<T> = LayerwiseTrustRatio<T, BatchBackprop<T>>;
+
+/// LAMB: layer-wise trust ratio around Adam
+pub type Lamb<T> = LayerwiseTrustRatio<T, Adam<T>>;
+
+/// Per-layer update scaling an optimizer applies inside its update step
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrustRatio<T: Float> {
+    pub trust_coefficient: T,
+    pub max_ratio: Option<T>,
+    last_ratios: Vec<T>,
+}
+
+impl<T: Float> TrustRatio<T> {
+    pub fn new(trust_coefficient: T) -> Self {
+        Self {
+            trust_coefficient,
+            max_ratio: None,
+            last_ratios: Vec::new(),
+        }
+    }
+
+    /// Never scale an update by more than `max_ratio`
+    pub fn with_max_ratio(mut self, max_ratio: T) -> Self {
+        self.max_ratio = Some(max_ratio);
+        self
+    }
+
+    /// Scale factor applied to each layer (excluding the input layer) in the
+    /// last step; `1` for layers left unscaled
+    pub fn last_ratios(&self) -> &[T] {
+        &self.last_ratios
+    }
+
+    /// Scale each layer's weight and bias updates, laid out as
+    /// [`helpers::apply_updates_to_network`] expects, by the trust ratio of
+    /// the weights they are about to be added to
+    ///
+    /// The updates already include `learning_rate`; the ratio is taken
+    /// against the direction before it, so the learning rate still sets the
+    /// step length.
+    pub(crate) fn scale(
+        &mut self,
+        network: &Network<T>,
+        learning_rate: T,
+        weight_updates: &mut [Vec<T>],
+        bias_updates: &mut [Vec<T>],
+    ) {
+        self.last_ratios.clear();
+        let layers = network.layers.iter().skip(1);
+        for ((layer, weight_updates), bias_updates) in layers.zip(weight_updates).zip(bias_updates)
+        {
+            let weight_norm_sq = layer
+                .neurons
+                .iter()
+                .flat_map(|n| &n.connections)
+                .fold(T::zero(), |acc, c| acc + c.weight * c.weight);
+            let update_norm_sq = weight_updates
+                .iter()
+                .chain(bias_updates.iter())
+                .fold(T::zero(), |acc, &u| acc + u * u);
+
+            let (weight_norm, update_norm) = (weight_norm_sq.sqrt(), update_norm_sq.sqrt());
+            // |u| / lr is the norm of the direction
+            let mut ratio = if weight_norm > T::zero() && update_norm > T::zero() {
+                self.trust_coefficient * weight_norm * learning_rate.abs() / update_norm
+            } else {
+                T::one()
+            };
+            if let Some(max_ratio) = self.max_ratio {
+                ratio = ratio.min(max_ratio);
+            }
+            if !ratio.is_finite() {
+                ratio = T::one();
+            }
+            self.last_ratios.push(ratio);
+
+            for update in weight_updates.iter_mut().chain(bias_updates.iter_mut()) {
+                *update = *update * ratio;
+            }
+        }
+    }
+}
+
+/// Optimizers that apply a [`TrustRatio`] to every update before writing it
+pub trait TrustRatioUpdates<T: Float> {
+    fn trust_ratio(&self) -> Option<&TrustRatio<T>>;
+
+    fn set_trust_ratio(&mut self, trust_ratio: Option<TrustRatio<T>>);
+}
+
+/// Rescales the per-layer updates of a wrapped optimizer by the trust ratio
+pub struct LayerwiseTrustRatio<T: Float, A> {
+    inner: A,
+    mini_batches: Option<BatchIterator>,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Float, A: TrainingAlgorithm<T> + TrustRatioUpdates<T>> LayerwiseTrustRatio<T, A> {
+    /// Wrap `inner` so each layer moves by its learning rate times
+    /// `trust_coefficient` times its weight norm per step
+    pub fn new(mut inner: A, trust_coefficient: T) -> Self {
+        inner.set_trust_ratio(Some(TrustRatio::new(trust_coefficient)));
+        Self {
+            inner,
+            mini_batches: None,
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Never scale an update by more than `max_ratio`, so layers with a
+    /// large weight norm and a tiny update are not blown up
+    pub fn with_max_ratio(mut self, max_ratio: T) -> Self {
+        let trust_ratio = self.config().with_max_ratio(max_ratio);
+        self.inner.set_trust_ratio(Some(trust_ratio));
+        self
+    }
+
+    /// Update once per mini-batch drawn by `batches`; the same as setting
+    /// mini-batches on the inner optimizer
+    pub fn with_mini_batches(mut self, batches: BatchIterator) -> Self {
+        self.mini_batches = Some(batches);
+        self
+    }
+
+    /// The wrapped optimizer
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    /// Mutable access to the wrapped optimizer
+    pub fn inner_mut(&mut self) -> &mut A {
+        &mut self.inner
+    }
+
+    /// The inner optimizer's trust ratio, restored if it was cleared
+    fn config(&self) -> TrustRatio<T> {
+        self.inner
+            .trust_ratio()
+            .cloned()
+            .unwrap_or_else(|| TrustRatio::new(T::one()))
+    }
+
+    pub fn trust_coefficient(&self) -> T {
+        self.config().trust_coefficient
+    }
+
+    pub fn max_ratio(&self) -> Option<T> {
+        self.config().max_ratio
+    }
+
+    /// Scale factor applied to each layer (excluding the input layer) in the
+    /// last step; `1` for layers left unscaled
+    pub fn last_ratios(&self) -> &[T] {
+        self.inner
+            .trust_ratio()
+            .map_or(&[], |trust_ratio| trust_ratio.last_ratios())
+    }
+}
+
+impl<T: Float + Send + Sync + Default> Lars<T> {
+    /// LARS with learning rate 0.1 and momentum 0.9
+    pub fn lars(trust_coefficient: T) -> Self {
+        Self::new(
+            BatchBackprop::new(cast(0.1)).with_momentum(cast(0.9)),
+            trust_coefficient,
+        )
+    }
+}
+
+impl<T: Float + Send + Sync + Default> Lamb<T> {
+    /// LAMB with Adam's default betas
+    pub fn lamb(trust_coefficient: T) -> Self {
+        Self::new(Adam::new(cast(0.001)), trust_coefficient)
+    }
+}
+
+impl<T: Float + Send + Sync, A: TrainingAlgorithm<T> + TrustRatioUpdates<T>> TrainingAlgorithm<T>
+    for LayerwiseTrustRatio<T, A>
+{
+    fn train_epoch(
+        &mut self,
+        network: &mut Network<T>,
+        data: &TrainingData<T>,
+    ) -> Result<T, TrainingError> {
+        if let Some(mut batches) = self.mini_batches.take() {
+            let error = batches.train_epoch(self, network, data);
+            self.mini_batches = Some(batches);
+            return error;
+        }
+        self.inner.train_epoch(network, data)
+    }
+
+    fn calculate_error(&self, network: &Network<T>, data: &TrainingData<T>) -> T {
+        self.inner.calculate_error(network, data)
+    }
+
+    fn count_bit_fails(
+        &self,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+        bit_fail_limit: T,
+    ) -> usize {
+        self.inner.count_bit_fails(network, data, bit_fail_limit)
+    }
+
+    fn save_state(&self) -> TrainingState<T> {
+        let mut state = self.inner.save_state();
+        let config = self.config();
+        state.algorithm_specific.insert(
+            "trust_coefficient".to_string(),
+            vec![config.trust_coefficient],
+        );
+        if let Some(max_ratio) = config.max_ratio {
+            state
+                .algorithm_specific
+                .insert("max_trust_ratio".to_string(), vec![max_ratio]);
+        }
+        state
+    }
+
+    fn restore_state(&mut self, mut state: TrainingState<T>) {
+        let specific = &mut state.algorithm_specific;
+        let mut config = self.config();
+        if let Some(&coefficient) = specific
+            .remove("trust_coefficient")
+            .as_ref()
+            .and_then(|v| v.first())
+        {
+            config.trust_coefficient = coefficient;
+        }
+        config.max_ratio = specific
+            .remove("max_trust_ratio")
+            .and_then(|v| v.first().copied());
+        self.inner.restore_state(state);
+        self.inner.set_trust_ratio(Some(config));
+    }
+
+    fn learning_rate(&self) -> Option<T> {
+        self.inner.learning_rate()
+    }
+
+    fn set_learning_rate(&mut self, rate: T) {
+        self.inner.set_learning_rate(rate);
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.inner.set_cancellation(token);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+
+    fn last_gradient_norm(&self) -> Option<T> {
+        self.inner.last_gradient_norm()
+    }
+
+    fn last_weight_gradients(&self) -> Option<&[Vec<T>]> {
+        self.inner.last_weight_gradients()
+    }
+
+    fn set_callback(&mut self, callback: TrainingCallback<T>) {
+        self.inner.set_callback(callback);
+    }
+
+    fn call_callback(
+        &mut self,
+        epoch: usize,
+        network: &Network<T>,
+        data: &TrainingData<T>,
+    ) -> bool {
+        self.inner.call_callback(epoch, network, data)
+    }
+}
+
+impl<T: Float + Send + Sync, A: AdvancedTrainingAlgorithm<T> + TrustRatioUpdates<T>>
+    AdvancedTrainingAlgorithm<T> for LayerwiseTrustRatio<T, A>
+{
+    fn statistics(&self) -> &TrainingStatistics<T> {
+        self.inner.statistics()
+    }
+
+    fn reset_statistics(&mut self) {
+        self.inner.reset_statistics();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    /// Weights of every layer after the input layer, in connection order
+    fn layer_weights(network: &Network<f64>) -> Vec<Vec<f64>> {
+        network
+            .layers
+            .iter()
+            .skip(1)
+            .map(|layer| {
+                layer
+                    .neurons
+                    .iter()
+                    .flat_map(|n| n.connections.iter().map(|c| c.weight))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn data() -> TrainingData<f64> {
+        TrainingData {
+            inputs: vec![vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]],
+            outputs: vec![vec![1.0], vec![1.0], vec![0.0]],
+        }
+    }
+
+    fn norm(values: &[f64]) -> f64 {
+        values.iter().map(|v| v * v).sum::<f64>().sqrt()
+    }
+
+    #[test]
+    fn test_layer_updates_follow_weight_norm() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .output_layer(1)
+            .build();
+        let before = layer_weights(&network);
+
+        let mut lars = Lars::lars(0.01);
+        lars.train_epoch(&mut network, &data()).unwrap();
+        let after = layer_weights(&network);
+
+        assert_eq!(lars.last_ratios().len(), 2);
+        for (before, after) in before.iter().zip(&after) {
+            let update: Vec<f64> = after.iter().zip(before).map(|(a, b)| a - b).collect();
+            assert!((norm(&update) - 0.1 * 0.01 * norm(before)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_learning_rate_sets_step_length() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let step = |learning_rate: f64, lamb: bool| {
+            let mut trained = network.clone();
+            let mut optimizer: Box<dyn TrainingAlgorithm<f64>> = if lamb {
+                Box::new(Lamb::lamb(0.01))
+            } else {
+                Box::new(Lars::lars(0.01))
+            };
+            optimizer.set_learning_rate(learning_rate);
+            optimizer.train_epoch(&mut trained, &data()).unwrap();
+            let before = network.get_weights();
+            let update: Vec<f64> = trained
+                .get_weights()
+                .iter()
+                .zip(&before)
+                .map(|(a, b)| a - b)
+                .collect();
+            norm(&update)
+        };
+        for lamb in [false, true] {
+            assert!((step(0.2, lamb) - 2.0 * step(0.1, lamb)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_ratio_applies_to_every_step() {
+        let network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let train = |mut lars: Lars<f64>| {
+            let mut network = network.clone();
+            lars.train_epoch(&mut network, &data()).unwrap();
+            layer_weights(&network)
+        };
+
+        // Mini-batches on the inner optimizer are scaled step by step, the
+        // same as mini-batches driven by the wrapper
+        let outer = train(Lars::lars(0.01).with_mini_batches(BatchIterator::new(1)));
+        let inner = BatchBackprop::new(0.1)
+            .with_momentum(0.9)
+            .with_mini_batches(BatchIterator::new(1));
+        assert_eq!(train(Lars::new(inner, 0.01)), outer);
+        assert_ne!(train(Lars::lars(0.01)), outer);
+    }
+
+    #[test]
+    fn test_max_ratio_and_state() {
+        let mut network = NetworkBuilder::<f64>::new()
+            .input_layer(2)
+            .output_layer(1)
+            .build();
+        let mut lamb = Lamb::lamb(10.0).with_max_ratio(2.0);
+        lamb.train_epoch(&mut network, &data()).unwrap();
+        assert!(lamb.last_ratios().iter().all(|&r| r <= 2.0));
+        assert_eq!(lamb.statistics().epochs, 1);
+
+        let mut restored = Lamb::lamb(0.5);
+        restored.restore_state(lamb.save_state());
+        assert_eq!(restored.trust_coefficient(), 10.0);
+        assert_eq!(restored.max_ratio(), Some(2.0));
+        // Adam's moments are carried along with the wrapper's settings
+        assert!(restored
+            .save_state()
+            .algorithm_specific
+            .contains_key("m_weights.0"));
+    }
+}
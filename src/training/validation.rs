@@ -0,0 +1,436 @@
+//! K-fold cross-validation
+//!
+//! [`KFold`] partitions the samples into `k` folds of nearly equal size;
+//! [`StratifiedKFold`] does the same per class so every fold keeps the class
+//! proportions of the whole data set. Both implement [`FoldSplitter`], whose
+//! [`split`](FoldSplitter::split) returns one train/test pair per fold.
+//!
+//! [`cross_validate`] trains a fresh network on each fold's training part and
+//! aggregates the test error and bit-fail rate over the folds.
+//!
+//! ```
+//! use do_fann::training::validation::{cross_validate, FoldSplitter, KFold};
+//! use do_fann::training::{Rprop, Trainer, TrainingData};
+//! use do_fann::NetworkBuilder;
+//!
+//! let data = TrainingData {
+//!     inputs: (0..20).map(|i| vec![i as f64 / 20.0]).collect(),
+//!     outputs: (0..20).map(|i| vec![0.2 + i as f64 / 40.0]).collect(),
+//! };
+//! assert_eq!(KFold::new().split(&data, 4)?.len(), 4);
+//!
+//! let report = cross_validate(
+//!     || NetworkBuilder::new().input_layer(1).hidden_layer(3).output_layer(1).build(),
+//!     &data,
+//!     5,
+//!     || Trainer::new(Box::new(Rprop::new()), 50),
+//! )?;
+//! assert_eq!(report.folds.len(), 5);
+//! assert!(report.mean_error.is_finite());
+//! # Ok::<(), do_fann::TrainingError>(())
+//! ```
+
+use super::{Trainer, TrainingData, TrainingError};
+use crate::numeric::cast;
+use crate::Network;
+use num_traits::Float;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::BTreeMap;
+
+/// Bit-fail limit [`cross_validate`] uses, FANN's default
+pub const DEFAULT_BIT_FAIL_LIMIT: f64 = 0.35;
+
+/// Training and held-out parts of one fold
+#[derive(Debug, Clone)]
+pub struct Fold<T: Float> {
+    pub train: TrainingData<T>,
+    pub test: TrainingData<T>,
+    /// Indices of the held-out samples in the original data
+    pub test_indices: Vec<usize>,
+}
+
+/// Strategy that assigns samples to `k` folds
+pub trait FoldSplitter {
+    /// Held-out sample indices of each fold; every sample is held out
+    /// exactly once
+    fn fold_indices<T: Float>(
+        &self,
+        data: &TrainingData<T>,
+        k: usize,
+    ) -> Result<Vec<Vec<usize>>, TrainingError>;
+
+    /// Train/test pairs of all `k` folds
+    fn split<T: Float>(
+        &self,
+        data: &TrainingData<T>,
+        k: usize,
+    ) -> Result<Vec<Fold<T>>, TrainingError> {
+        let folds = self.fold_indices(data, k)?;
+        let mut held_out = vec![usize::MAX; data.inputs.len()];
+        for (fold, indices) in folds.iter().enumerate() {
+            for &i in indices {
+                held_out[i] = fold;
+            }
+        }
+        let subset = |keep: &dyn Fn(usize) -> bool| {
+            let indices: Vec<usize> = (0..data.inputs.len()).filter(|&i| keep(i)).collect();
+            TrainingData {
+                inputs: indices.iter().map(|&i| data.inputs[i].clone()).collect(),
+                outputs: indices.iter().map(|&i| data.outputs[i].clone()).collect(),
+            }
+        };
+        Ok(folds
+            .into_iter()
+            .enumerate()
+            .map(|(fold, test_indices)| Fold {
+                train: subset(&|i| held_out[i] != fold),
+                test: subset(&|i| held_out[i] == fold),
+                test_indices,
+            })
+            .collect())
+    }
+}
+
+fn check_folds<T: Float>(data: &TrainingData<T>, k: usize) -> Result<(), TrainingError> {
+    if data.inputs.len() != data.outputs.len() {
+        return Err(TrainingError::InvalidData(format!(
+            "{} inputs but {} outputs",
+            data.inputs.len(),
+            data.outputs.len()
+        )));
+    }
+    if k < 2 || k > data.inputs.len() {
+        return Err(TrainingError::InvalidData(format!(
+            "cannot split {} samples into {k} folds",
+            data.inputs.len()
+        )));
+    }
+    Ok(())
+}
+
+fn sample_order(samples: usize, seed: Option<u64>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..samples).collect();
+    if let Some(seed) = seed {
+        order.shuffle(&mut StdRng::seed_from_u64(seed));
+    }
+    order
+}
+
+/// Contiguous (or shuffled) folds of nearly equal size
+#[derive(Debug, Clone, Default)]
+pub struct KFold {
+    seed: Option<u64>,
+}
+
+impl KFold {
+    /// Folds of consecutive samples, in data order
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shuffle the samples with `seed` before assigning folds
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+impl FoldSplitter for KFold {
+    fn fold_indices<T: Float>(
+        &self,
+        data: &TrainingData<T>,
+        k: usize,
+    ) -> Result<Vec<Vec<usize>>, TrainingError> {
+        check_folds(data, k)?;
+        let order = sample_order(data.inputs.len(), self.seed);
+        let (base, extra) = (order.len() / k, order.len() % k);
+        let mut start = 0;
+        Ok((0..k)
+            .map(|fold| {
+                let len = base + usize::from(fold < extra);
+                let indices = order[start..start + len].to_vec();
+                start += len;
+                indices
+            })
+            .collect())
+    }
+}
+
+/// Folds that preserve the class proportions of classification targets
+///
+/// The class of a sample is the index of its largest output, or for a single
+/// output whether it reaches `threshold` (0.5 by default).
+#[derive(Debug, Clone)]
+pub struct StratifiedKFold {
+    seed: Option<u64>,
+    threshold: f64,
+}
+
+impl Default for StratifiedKFold {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            threshold: 0.5,
+        }
+    }
+}
+
+impl StratifiedKFold {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shuffle each class with `seed` before assigning folds
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Decision threshold for single-output targets
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Class label of one target vector
+    pub fn class_of<T: Float>(&self, output: &[T]) -> usize {
+        match output {
+            [single] => usize::from(single.to_f64().is_some_and(|v| v >= self.threshold)),
+            _ => {
+                output
+                    .iter()
+                    .enumerate()
+                    .fold((0, T::neg_infinity()), |best, (i, &v)| {
+                        if v > best.1 {
+                            (i, v)
+                        } else {
+                            best
+                        }
+                    })
+                    .0
+            }
+        }
+    }
+}
+
+impl FoldSplitter for StratifiedKFold {
+    fn fold_indices<T: Float>(
+        &self,
+        data: &TrainingData<T>,
+        k: usize,
+    ) -> Result<Vec<Vec<usize>>, TrainingError> {
+        check_folds(data, k)?;
+        let mut classes: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for i in sample_order(data.inputs.len(), self.seed) {
+            classes
+                .entry(self.class_of(&data.outputs[i]))
+                .or_default()
+                .push(i);
+        }
+        // Deal each class round-robin, continuing where the previous class
+        // stopped so fold sizes stay within one sample of each other
+        let mut folds = vec![Vec::new(); k];
+        let mut next = 0;
+        for members in classes.values() {
+            for &i in members {
+                folds[next % k].push(i);
+                next += 1;
+            }
+        }
+        for fold in &mut folds {
+            fold.sort_unstable();
+        }
+        Ok(folds)
+    }
+}
+
+/// Test metrics of one fold
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoldResult<T: Float> {
+    pub fold: usize,
+    pub train_samples: usize,
+    pub test_samples: usize,
+    pub epochs: usize,
+    /// Training error of the last epoch
+    pub train_error: T,
+    pub test_error: T,
+    pub test_bit_fails: usize,
+}
+
+impl<T: Float> FoldResult<T> {
+    pub fn bit_fail_rate(&self) -> f64 {
+        self.test_bit_fails as f64 / self.test_samples.max(1) as f64
+    }
+}
+
+/// Per-fold results with their mean and sample standard deviation
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossValidationReport<T: Float> {
+    pub folds: Vec<FoldResult<T>>,
+    pub mean_error: T,
+    pub error_std_dev: T,
+    pub mean_bit_fail_rate: f64,
+    pub bit_fail_rate_std_dev: f64,
+}
+
+impl<T: Float> CrossValidationReport<T> {
+    fn from_folds(folds: Vec<FoldResult<T>>) -> Self {
+        let errors: Vec<f64> = folds
+            .iter()
+            .map(|f| f.test_error.to_f64().unwrap_or(f64::NAN))
+            .collect();
+        let rates: Vec<f64> = folds.iter().map(FoldResult::bit_fail_rate).collect();
+        let (mean_error, error_std_dev) = mean_std_dev(&errors);
+        let (mean_bit_fail_rate, bit_fail_rate_std_dev) = mean_std_dev(&rates);
+        Self {
+            folds,
+            mean_error: cast(mean_error),
+            error_std_dev: cast(error_std_dev),
+            mean_bit_fail_rate,
+            bit_fail_rate_std_dev,
+        }
+    }
+}
+
+fn mean_std_dev(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+    (mean, variance.sqrt())
+}
+
+/// Cross-validate with [`KFold`] in data order and
+/// [`DEFAULT_BIT_FAIL_LIMIT`]
+///
+/// `network_builder` creates the untrained network and `trainer` the
+/// [`Trainer`] (algorithm, epochs, stopping rules) of each fold.
+pub fn cross_validate<T, N, F>(
+    network_builder: N,
+    data: &TrainingData<T>,
+    k: usize,
+    trainer: F,
+) -> Result<CrossValidationReport<T>, TrainingError>
+where
+    T: Float,
+    N: FnMut() -> Network<T>,
+    F: FnMut() -> Trainer<T>,
+{
+    cross_validate_with(
+        &KFold::new(),
+        network_builder,
+        data,
+        k,
+        trainer,
+        cast(DEFAULT_BIT_FAIL_LIMIT),
+    )
+}
+
+/// Cross-validate with any [`FoldSplitter`] and bit-fail limit
+pub fn cross_validate_with<T, S, N, F>(
+    splitter: &S,
+    mut network_builder: N,
+    data: &TrainingData<T>,
+    k: usize,
+    mut trainer: F,
+    bit_fail_limit: T,
+) -> Result<CrossValidationReport<T>, TrainingError>
+where
+    T: Float,
+    S: FoldSplitter,
+    N: FnMut() -> Network<T>,
+    F: FnMut() -> Trainer<T>,
+{
+    let mut results = Vec::with_capacity(k);
+    for (fold, Fold { train, test, .. }) in splitter.split(data, k)?.into_iter().enumerate() {
+        let mut network = network_builder();
+        test.check_shapes(&network)?;
+        let mut trainer = trainer();
+        let report = trainer.train(&mut network, &train)?;
+        let algorithm = trainer.algorithm();
+        results.push(FoldResult {
+            fold,
+            train_samples: train.inputs.len(),
+            test_samples: test.inputs.len(),
+            epochs: report.epochs,
+            train_error: report.final_error,
+            test_error: algorithm.calculate_error(&network, &test),
+            test_bit_fails: algorithm.count_bit_fails(&network, &test, bit_fail_limit),
+        });
+    }
+    Ok(CrossValidationReport::from_folds(results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::{IncrementalBackprop, Trainer};
+    use crate::NetworkBuilder;
+
+    fn data(n: usize) -> TrainingData<f64> {
+        TrainingData {
+            inputs: (0..n).map(|i| vec![i as f64 / n as f64]).collect(),
+            // One positive in four
+            outputs: (0..n)
+                .map(|i| vec![f64::from(u8::from(i % 4 == 0))])
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_folds_partition_the_data() {
+        let folds = KFold::new().fold_indices(&data(10), 3).unwrap();
+        assert_eq!(folds, [vec![0, 1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]);
+
+        let shuffled = KFold::new()
+            .with_seed(1)
+            .fold_indices(&data(10), 3)
+            .unwrap();
+        let mut all: Vec<usize> = shuffled.concat();
+        all.sort_unstable();
+        assert_eq!(all, (0..10).collect::<Vec<_>>());
+        assert_ne!(shuffled, folds);
+
+        let stratified = StratifiedKFold::new().with_seed(2);
+        let data = data(20);
+        for fold in stratified.split(&data, 5).unwrap() {
+            assert_eq!(fold.test.inputs.len(), 4);
+            assert_eq!(fold.train.inputs.len(), 16);
+            let positives = fold.test.outputs.iter().filter(|o| o[0] == 1.0).count();
+            assert_eq!(positives, 1);
+        }
+        assert_eq!(stratified.class_of(&[0.1, 0.7, 0.2]), 1);
+        assert!(KFold::new().split(&data, 1).is_err());
+        assert!(KFold::new().split(&data, 21).is_err());
+    }
+
+    #[test]
+    fn test_cross_validate_aggregates_folds() {
+        let data = data(12);
+        let report = cross_validate_with(
+            &StratifiedKFold::new(),
+            || {
+                NetworkBuilder::<f64>::new()
+                    .input_layer(1)
+                    .hidden_layer(2)
+                    .output_layer(1)
+                    .build()
+            },
+            &data,
+            3,
+            || Trainer::new(Box::new(IncrementalBackprop::new(0.5)), 5),
+            0.35,
+        )
+        .unwrap();
+        assert_eq!(report.folds.len(), 3);
+        assert!(report
+            .folds
+            .iter()
+            .all(|f| f.test_samples == 4 && f.epochs == 5));
+        let mean = report.folds.iter().map(|f| f.test_error).sum::<f64>() / 3.0;
+        assert!((report.mean_error - mean).abs() < 1e-12);
+        assert!(report.error_std_dev >= 0.0);
+        assert!((0.0..=1.0).contains(&report.mean_bit_fail_rate));
+    }
+}
@@ -0,0 +1,362 @@
+//! Per-layer weight distribution monitoring
+//!
+//! [`WeightDistributionMonitor`] compares each layer's weights with the
+//! previous observation and flags layers that have stopped learning or whose
+//! weights are exploding. It also summarizes the weight gradients an
+//! optimizer applied in the last epoch.
+
+use super::MetricsRecorder;
+use crate::Network;
+use num_traits::Float;
+
+/// Health classification of a layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerHealth {
+    Healthy,
+    /// The weights barely changed since the previous observation
+    Stalled,
+    /// The weights grew beyond the configured limits
+    Exploding,
+}
+
+/// Distribution change of one layer between two observations
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerDistributionReport {
+    /// Network layer index (the input layer has no weights and is skipped)
+    pub layer: usize,
+    /// Wasserstein-1 distance between the previous and current weight distributions
+    pub wasserstein: f64,
+    /// KL divergence of the current histogram from the previous one
+    pub kl_divergence: f64,
+    /// Norm of the weight change relative to the norm of the previous weights
+    pub update_ratio: f64,
+    /// Root mean square of the current weights
+    pub rms: f64,
+    pub health: LayerHealth,
+}
+
+/// Gradient distribution of one layer in one epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientDistributionReport {
+    /// Network layer index the gradients flow into
+    pub layer: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// Root mean square of the gradients
+    pub rms: f64,
+    /// Largest absolute gradient
+    pub max_abs: f64,
+}
+
+/// Tracks weight distributions across epochs
+#[derive(Debug, Clone)]
+pub struct WeightDistributionMonitor {
+    /// Number of histogram bins used for the KL divergence
+    pub bins: usize,
+    /// Update ratio below which a layer is reported as stalled
+    pub stall_ratio: f64,
+    /// Update ratio above which a layer is reported as exploding
+    pub explode_ratio: f64,
+    /// RMS weight magnitude above which a layer is reported as exploding
+    pub max_rms: f64,
+    previous: Vec<Vec<f64>>,
+}
+
+impl Default for WeightDistributionMonitor {
+    fn default() -> Self {
+        Self {
+            bins: 32,
+            stall_ratio: 1e-6,
+            explode_ratio: 1.0,
+            max_rms: 1e3,
+            previous: Vec::new(),
+        }
+    }
+}
+
+impl WeightDistributionMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compare the network with the previous observation
+    ///
+    /// Returns an empty report on the first call or after the topology
+    /// changed. Per-layer metrics and warnings for unhealthy layers are sent
+    /// to `recorder` when one is given.
+    pub fn observe<T: Float>(
+        &mut self,
+        network: &Network<T>,
+        step: usize,
+        mut recorder: Option<&mut dyn MetricsRecorder>,
+    ) -> Vec<LayerDistributionReport> {
+        let current: Vec<Vec<f64>> = network
+            .layers
+            .iter()
+            .skip(1)
+            .map(|layer| {
+                layer
+                    .neurons
+                    .iter()
+                    .flat_map(|n| n.connections.iter())
+                    .map(|c| c.weight.to_f64().unwrap_or(f64::NAN))
+                    .collect()
+            })
+            .collect();
+
+        let comparable = self.previous.len() == current.len()
+            && self
+                .previous
+                .iter()
+                .zip(&current)
+                .all(|(a, b)| a.len() == b.len());
+
+        let mut reports = Vec::new();
+        if comparable {
+            for (index, (before, after)) in self.previous.iter().zip(&current).enumerate() {
+                if after.is_empty() {
+                    continue;
+                }
+                let report = self.compare(index + 1, before, after);
+                if let Some(recorder) = recorder.as_deref_mut() {
+                    record(recorder, step, &report);
+                }
+                reports.push(report);
+            }
+        }
+
+        self.previous = current;
+        reports
+    }
+
+    /// Summarize per-layer weight gradients, as returned by
+    /// [`TrainingAlgorithm::last_weight_gradients`]
+    ///
+    /// Per-layer metrics are sent to `recorder` when one is given, with a
+    /// warning for layers whose gradients are not finite.
+    ///
+    /// [`TrainingAlgorithm::last_weight_gradients`]: super::TrainingAlgorithm::last_weight_gradients
+    pub fn observe_gradients<T: Float>(
+        &self,
+        gradients: &[Vec<T>],
+        step: usize,
+        mut recorder: Option<&mut dyn MetricsRecorder>,
+    ) -> Vec<GradientDistributionReport> {
+        let mut reports = Vec::new();
+        for (index, layer) in gradients.iter().enumerate() {
+            if layer.is_empty() {
+                continue;
+            }
+            let values: Vec<f64> = layer
+                .iter()
+                .map(|g| g.to_f64().unwrap_or(f64::NAN))
+                .collect();
+            let count = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / count;
+            let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / count;
+            let report = GradientDistributionReport {
+                layer: index + 1,
+                mean,
+                std_dev: variance.sqrt(),
+                rms: (values.iter().map(|v| v * v).sum::<f64>() / count).sqrt(),
+                max_abs: values.iter().fold(0.0, |max, v| max.max(v.abs())),
+            };
+            if let Some(recorder) = recorder.as_deref_mut() {
+                record_gradients(recorder, step, &report);
+            }
+            reports.push(report);
+        }
+        reports
+    }
+
+    /// Forget the previous observation
+    pub fn reset(&mut self) {
+        self.previous.clear();
+    }
+
+    fn compare(&self, layer: usize, before: &[f64], after: &[f64]) -> LayerDistributionReport {
+        let norm = |values: &[f64]| values.iter().map(|v| v * v).sum::<f64>().sqrt();
+        let change: f64 = before
+            .iter()
+            .zip(after)
+            .map(|(a, b)| (b - a) * (b - a))
+            .sum::<f64>()
+            .sqrt();
+        let update_ratio = change / norm(before).max(f64::EPSILON);
+        let rms = norm(after) / (after.len() as f64).sqrt();
+
+        let health = if !rms.is_finite() || rms > self.max_rms || update_ratio > self.explode_ratio
+        {
+            LayerHealth::Exploding
+        } else if update_ratio < self.stall_ratio {
+            LayerHealth::Stalled
+        } else {
+            LayerHealth::Healthy
+        };
+
+        LayerDistributionReport {
+            layer,
+            wasserstein: wasserstein_distance(before, after),
+            kl_divergence: histogram_kl(before, after, self.bins.max(1)),
+            update_ratio,
+            rms,
+            health,
+        }
+    }
+}
+
+fn record(recorder: &mut dyn MetricsRecorder, step: usize, report: &LayerDistributionReport) {
+    let layer = report.layer;
+    recorder.record_scalar(
+        &format!("layer{layer}/wasserstein"),
+        step,
+        report.wasserstein,
+    );
+    recorder.record_scalar(
+        &format!("layer{layer}/kl_divergence"),
+        step,
+        report.kl_divergence,
+    );
+    recorder.record_scalar(
+        &format!("layer{layer}/update_ratio"),
+        step,
+        report.update_ratio,
+    );
+    recorder.record_scalar(&format!("layer{layer}/rms"), step, report.rms);
+
+    match report.health {
+        LayerHealth::Healthy => {}
+        LayerHealth::Stalled => recorder.record_warning(
+            step,
+            &format!(
+                "layer {layer} stopped learning (update ratio {:.3e})",
+                report.update_ratio
+            ),
+        ),
+        LayerHealth::Exploding => recorder.record_warning(
+            step,
+            &format!(
+                "layer {layer} weights are exploding (rms {:.3e}, update ratio {:.3e})",
+                report.rms, report.update_ratio
+            ),
+        ),
+    }
+}
+
+fn record_gradients(
+    recorder: &mut dyn MetricsRecorder,
+    step: usize,
+    report: &GradientDistributionReport,
+) {
+    let layer = report.layer;
+    recorder.record_scalar(&format!("layer{layer}/grad_mean"), step, report.mean);
+    recorder.record_scalar(&format!("layer{layer}/grad_std"), step, report.std_dev);
+    recorder.record_scalar(&format!("layer{layer}/grad_rms"), step, report.rms);
+    recorder.record_scalar(&format!("layer{layer}/grad_max_abs"), step, report.max_abs);
+    if !report.rms.is_finite() {
+        recorder.record_warning(step, &format!("layer {layer} gradients are not finite"));
+    }
+}
+
+/// Wasserstein-1 distance between two equally sized empirical distributions
+fn wasserstein_distance(a: &[f64], b: &[f64]) -> f64 {
+    let mut a = a.to_vec();
+    let mut b = b.to_vec();
+    a.sort_by(f64::total_cmp);
+    b.sort_by(f64::total_cmp);
+    a.iter().zip(&b).map(|(x, y)| (x - y).abs()).sum::<f64>() / a.len().max(1) as f64
+}
+
+/// KL(after || before) over histograms with shared, additively smoothed bins
+fn histogram_kl(before: &[f64], after: &[f64], bins: usize) -> f64 {
+    let (min, max) = before
+        .iter()
+        .chain(after)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    if !min.is_finite() || !max.is_finite() || max <= min {
+        return 0.0;
+    }
+
+    let histogram = |values: &[f64]| {
+        let mut counts = vec![1.0; bins];
+        for &v in values {
+            let bin = (((v - min) / (max - min)) * bins as f64) as usize;
+            counts[bin.min(bins - 1)] += 1.0;
+        }
+        let total: f64 = counts.iter().sum();
+        counts.into_iter().map(|c| c / total).collect::<Vec<_>>()
+    };
+
+    let p = histogram(after);
+    let q = histogram(before);
+    p.iter().zip(&q).map(|(p, q)| p * (p / q).ln()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training::InMemoryRecorder;
+    use crate::NetworkBuilder;
+
+    #[test]
+    fn test_detects_stalled_and_exploding_layers() {
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(4)
+            .output_layer(1)
+            .build();
+        let mut monitor = WeightDistributionMonitor::new();
+        let mut recorder = InMemoryRecorder::new();
+
+        assert!(monitor.observe(&network, 0, Some(&mut recorder)).is_empty());
+
+        let reports = monitor.observe(&network, 1, Some(&mut recorder));
+        assert_eq!(reports.len(), 2);
+        assert!(reports.iter().all(|r| r.health == LayerHealth::Stalled));
+        assert!(reports.iter().all(|r| r.wasserstein == 0.0));
+        assert_eq!(recorder.warnings.len(), 2);
+
+        let weights: Vec<f32> = network.get_weights().iter().map(|w| w * 100.0).collect();
+        network.set_weights(&weights).unwrap();
+        let reports = monitor.observe(&network, 2, Some(&mut recorder));
+        assert!(reports.iter().all(|r| r.health == LayerHealth::Exploding));
+        assert!(reports.iter().all(|r| r.wasserstein > 0.0));
+        assert_eq!(recorder.series("layer1/rms").len(), 2);
+    }
+
+    #[test]
+    fn test_records_optimizer_gradients() {
+        use crate::training::{Adam, TrainingAlgorithm, TrainingData};
+
+        let mut network = NetworkBuilder::<f32>::new()
+            .input_layer(2)
+            .hidden_layer(3)
+            .output_layer(1)
+            .build();
+        let data = TrainingData {
+            inputs: vec![vec![0.0, 1.0], vec![1.0, 0.0]],
+            outputs: vec![vec![1.0], vec![1.0]],
+        };
+        let mut adam = Adam::new(0.01);
+        assert!(adam.last_weight_gradients().is_none());
+        adam.train_epoch(&mut network, &data).unwrap();
+
+        let gradients = adam.last_weight_gradients().unwrap();
+        assert_eq!(gradients.len(), 2);
+        let mut recorder = InMemoryRecorder::new();
+        let reports =
+            WeightDistributionMonitor::new().observe_gradients(gradients, 1, Some(&mut recorder));
+        assert_eq!(reports.len(), 2);
+        assert!(reports.iter().all(|r| r.rms > 0.0 && r.max_abs >= r.rms));
+        assert_eq!(recorder.series("layer2/grad_rms").len(), 1);
+    }
+
+    #[test]
+    fn test_distribution_distances() {
+        assert!((wasserstein_distance(&[0.0, 1.0], &[1.0, 2.0]) - 1.0).abs() < 1e-12);
+        assert_eq!(histogram_kl(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0], 4), 0.0);
+        assert!(histogram_kl(&[0.0, 0.0, 0.0], &[1.0, 1.0, 1.0], 4) > 0.0);
+    }
+}
@@ -0,0 +1,403 @@
+//! Hyperparameter tuning
+//!
+//! [`TpeOptimizer`] searches continuous hyperparameters (learning rate,
+//! momentum, weight decay, dropout, ...) with the Tree-structured Parzen
+//! Estimator of Bergstra et al. (2011). After a few random trials it splits
+//! the observations into the best `gamma` fraction and the rest, fits a
+//! Parzen density to each, and proposes the candidate that maximises the
+//! ratio of the two. Compared with a grid, trials concentrate where good
+//! configurations were found, so far fewer trainings reach a good
+//! configuration, and the cost does not grow exponentially with the number
+//! of hyperparameters.
+//!
+//! ```
+//! use do_fann::training::{IncrementalBackprop, TrainingAlgorithm, TrainingData};
+//! use do_fann::tuning::{Hyperparameter, TpeOptimizer};
+//! use do_fann::NetworkBuilder;
+//!
+//! let data = TrainingData {
+//!     inputs: vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]],
+//!     outputs: vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]],
+//! };
+//! let mut tuner = TpeOptimizer::new(
+//!     vec![
+//!         Hyperparameter::log("learning_rate", 0.01, 2.0),
+//!         Hyperparameter::new("momentum", 0.0, 0.9),
+//!     ],
+//!     42,
+//! )?;
+//! let best = tuner.optimize(12, |params| {
+//!     let mut network = NetworkBuilder::<f32>::new()
+//!         .input_layer(2)
+//!         .hidden_layer(3)
+//!         .output_layer(1)
+//!         .build();
+//!     let mut trainer = IncrementalBackprop::new(params["learning_rate"] as f32)
+//!         .with_momentum(params["momentum"] as f32);
+//!     let mut error = f32::NAN;
+//!     for _ in 0..20 {
+//!         error = trainer.train_epoch(&mut network, &data).unwrap_or(f32::NAN);
+//!     }
+//!     f64::from(error)
+//! });
+//! assert!(best.is_some());
+//! # Ok::<(), do_fann::tuning::TuningError>(())
+//! ```
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Values of one trial, keyed by hyperparameter name
+pub type Params = BTreeMap<String, f64>;
+
+/// A continuous hyperparameter and its search range
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hyperparameter {
+    pub name: String,
+    pub low: f64,
+    pub high: f64,
+    /// Search the logarithm of the value, for scale parameters such as the
+    /// learning rate or weight decay
+    pub log_scale: bool,
+}
+
+impl Hyperparameter {
+    /// Uniform over `[low, high]`
+    pub fn new(name: impl Into<String>, low: f64, high: f64) -> Self {
+        Self {
+            name: name.into(),
+            low,
+            high,
+            log_scale: false,
+        }
+    }
+
+    /// Log-uniform over `[low, high]`; `low` must be positive
+    pub fn log(name: impl Into<String>, low: f64, high: f64) -> Self {
+        Self {
+            log_scale: true,
+            ..Self::new(name, low, high)
+        }
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        if self.log_scale {
+            (self.low.ln(), self.high.ln())
+        } else {
+            (self.low, self.high)
+        }
+    }
+
+    /// Value at position `unit` in `[0, 1]` of the (log) range
+    fn value_at(&self, unit: f64) -> f64 {
+        let (low, high) = self.bounds();
+        let value = low + unit.clamp(0.0, 1.0) * (high - low);
+        if self.log_scale {
+            value.exp().clamp(self.low, self.high)
+        } else {
+            value
+        }
+    }
+
+    fn unit_of(&self, value: f64) -> f64 {
+        let (low, high) = self.bounds();
+        let value = if self.log_scale { value.ln() } else { value };
+        ((value - low) / (high - low)).clamp(0.0, 1.0)
+    }
+}
+
+/// Invalid search space passed to [`TpeOptimizer::new`]
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum TuningError {
+    #[error("no hyperparameters to tune")]
+    EmptySpace,
+    #[error("hyperparameter `{0}` is listed twice")]
+    DuplicateName(String),
+    #[error("hyperparameter `{name}` has invalid range [{low}, {high}]")]
+    InvalidRange { name: String, low: f64, high: f64 },
+}
+
+/// One evaluated configuration; lower scores are better
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trial {
+    pub params: Params,
+    pub score: f64,
+}
+
+/// Tree-structured Parzen Estimator over continuous hyperparameters
+#[derive(Debug, Clone)]
+pub struct TpeOptimizer {
+    space: Vec<Hyperparameter>,
+    trials: Vec<Trial>,
+    startup_trials: usize,
+    candidates: usize,
+    gamma: f64,
+    rng: StdRng,
+}
+
+impl TpeOptimizer {
+    pub fn new(space: Vec<Hyperparameter>, seed: u64) -> Result<Self, TuningError> {
+        if space.is_empty() {
+            return Err(TuningError::EmptySpace);
+        }
+        for (i, parameter) in space.iter().enumerate() {
+            if space[..i].iter().any(|other| other.name == parameter.name) {
+                return Err(TuningError::DuplicateName(parameter.name.clone()));
+            }
+            let (low, high) = parameter.bounds();
+            if !(low.is_finite() && high.is_finite() && low < high) {
+                return Err(TuningError::InvalidRange {
+                    name: parameter.name.clone(),
+                    low: parameter.low,
+                    high: parameter.high,
+                });
+            }
+        }
+        Ok(Self {
+            space,
+            trials: Vec::new(),
+            startup_trials: 10,
+            candidates: 24,
+            gamma: 0.25,
+            rng: StdRng::seed_from_u64(seed),
+        })
+    }
+
+    /// Random trials before the model takes over (default 10)
+    pub fn with_startup_trials(mut self, trials: usize) -> Self {
+        self.startup_trials = trials;
+        self
+    }
+
+    /// Candidates drawn from the good density per suggestion (default 24)
+    pub fn with_candidates(mut self, candidates: usize) -> Self {
+        self.candidates = candidates.max(1);
+        self
+    }
+
+    /// Fraction of trials counted as good (default 0.25)
+    pub fn with_gamma(mut self, gamma: f64) -> Self {
+        self.gamma = gamma.clamp(0.01, 0.99);
+        self
+    }
+
+    /// Next configuration to evaluate
+    pub fn suggest(&mut self) -> Params {
+        let unit = self.suggest_unit();
+        self.space
+            .iter()
+            .zip(unit)
+            .map(|(parameter, u)| (parameter.name.clone(), parameter.value_at(u)))
+            .collect()
+    }
+
+    /// Record the score of a configuration; NaN counts as worse than any
+    /// number
+    pub fn observe(&mut self, params: Params, score: f64) {
+        let score = if score.is_nan() { f64::INFINITY } else { score };
+        self.trials.push(Trial { params, score });
+    }
+
+    /// Run `trials` suggest/evaluate/observe rounds and return the best trial
+    pub fn optimize(
+        &mut self,
+        trials: usize,
+        mut objective: impl FnMut(&Params) -> f64,
+    ) -> Option<&Trial> {
+        for _ in 0..trials {
+            let params = self.suggest();
+            let score = objective(&params);
+            self.observe(params, score);
+        }
+        self.best()
+    }
+
+    pub fn best(&self) -> Option<&Trial> {
+        self.trials
+            .iter()
+            .min_by(|a, b| a.score.total_cmp(&b.score))
+    }
+
+    pub fn trials(&self) -> &[Trial] {
+        &self.trials
+    }
+
+    /// Observations in unit coordinates, best first; parameters missing
+    /// from a trial are ignored by skipping that trial
+    fn sorted_unit_trials(&self) -> Vec<Vec<f64>> {
+        let mut trials: Vec<&Trial> = self.trials.iter().collect();
+        trials.sort_by(|a, b| a.score.total_cmp(&b.score));
+        trials
+            .into_iter()
+            .filter_map(|trial| {
+                self.space
+                    .iter()
+                    .map(|parameter| Some(parameter.unit_of(*trial.params.get(&parameter.name)?)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn suggest_unit(&mut self) -> Vec<f64> {
+        let dims = self.space.len();
+        let observed = self.sorted_unit_trials();
+        let good_count = ((self.gamma * observed.len() as f64).ceil() as usize).max(1);
+        if observed.len() < self.startup_trials.max(2) || good_count >= observed.len() {
+            return (0..dims).map(|_| self.rng.gen::<f64>()).collect();
+        }
+        let (good, bad) = observed.split_at(good_count);
+        let good = Parzen::fit(good, dims);
+        let bad = Parzen::fit(bad, dims);
+
+        let mut best = (f64::NEG_INFINITY, Vec::new());
+        for _ in 0..self.candidates {
+            let candidate = good.sample(&mut self.rng);
+            let ratio = good.log_density(&candidate) - bad.log_density(&candidate);
+            if ratio > best.0 || best.1.is_empty() {
+                best = (ratio, candidate);
+            }
+        }
+        best.1
+    }
+}
+
+/// Product of per-dimension Gaussian mixtures on `[0, 1]`, one component
+/// per observation plus a uniform prior that keeps every point reachable
+struct Parzen<'a> {
+    points: &'a [Vec<f64>],
+    bandwidth: Vec<f64>,
+}
+
+impl<'a> Parzen<'a> {
+    fn fit(points: &'a [Vec<f64>], dims: usize) -> Self {
+        let n = points.len() as f64;
+        // Scott's rule, floored so a few coincident points still explore
+        let bandwidth = (0..dims)
+            .map(|d| {
+                let mean = points.iter().map(|p| p[d]).sum::<f64>() / n;
+                let variance = points.iter().map(|p| (p[d] - mean).powi(2)).sum::<f64>() / n;
+                (1.06 * variance.sqrt() * n.powf(-0.2)).clamp(0.5 / (n + 1.0), 1.0)
+            })
+            .collect();
+        Self { points, bandwidth }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> Vec<f64> {
+        let center = &self.points[rng.gen_range(0..self.points.len())];
+        center
+            .iter()
+            .zip(&self.bandwidth)
+            .map(|(&mean, &sigma)| match Normal::new(mean, sigma) {
+                Ok(normal) => normal.sample(rng).clamp(0.0, 1.0),
+                Err(_) => mean,
+            })
+            .collect()
+    }
+
+    fn log_density(&self, x: &[f64]) -> f64 {
+        let weight = 1.0 / (self.points.len() as f64 + 1.0);
+        x.iter()
+            .enumerate()
+            .map(|(d, &value)| {
+                let sigma = self.bandwidth[d];
+                let mixture: f64 = self
+                    .points
+                    .iter()
+                    .map(|p| {
+                        let z = (value - p[d]) / sigma;
+                        (-0.5 * z * z).exp() / (sigma * (2.0 * std::f64::consts::PI).sqrt())
+                    })
+                    .sum();
+                // The prior is the uniform density 1 on [0, 1]
+                (weight * (mixture + 1.0)).ln()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn space() -> Vec<Hyperparameter> {
+        vec![
+            Hyperparameter::log("learning_rate", 1e-4, 1.0),
+            Hyperparameter::new("momentum", 0.0, 0.99),
+        ]
+    }
+
+    /// Smooth bowl with its minimum at lr = 0.03, momentum = 0.8
+    fn bowl(params: &Params) -> f64 {
+        let lr = params["learning_rate"].log10() - 0.03f64.log10();
+        let momentum = params["momentum"] - 0.8;
+        lr * lr + 4.0 * momentum * momentum
+    }
+
+    #[test]
+    fn test_tpe_beats_grid_with_same_budget() {
+        let mut tuner = TpeOptimizer::new(space(), 7).unwrap();
+        let best = tuner.optimize(40, bowl).unwrap().clone();
+        assert_eq!(tuner.trials().len(), 40);
+        for (parameter, value) in space().iter().zip(best.params.values()) {
+            assert!((parameter.low..=parameter.high).contains(value));
+        }
+
+        // A 6x6 grid with a few more trials than the tuner
+        let grid_best = (0..6)
+            .flat_map(|i| (0..6).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                let params: Params = space()
+                    .iter()
+                    .zip([i, j])
+                    .map(|(p, k)| (p.name.clone(), p.value_at(k as f64 / 5.0)))
+                    .collect();
+                bowl(&params)
+            })
+            .fold(f64::INFINITY, f64::min);
+        assert!(
+            best.score < grid_best,
+            "tpe {} vs grid {grid_best}",
+            best.score
+        );
+
+        // The same seed reproduces the same trials
+        let mut again = TpeOptimizer::new(space(), 7).unwrap();
+        again.optimize(40, bowl);
+        assert_eq!(again.trials(), tuner.trials());
+    }
+
+    #[test]
+    fn test_invalid_spaces_and_nan_scores() {
+        assert_eq!(
+            TpeOptimizer::new(vec![], 0).unwrap_err(),
+            TuningError::EmptySpace
+        );
+        assert!(matches!(
+            TpeOptimizer::new(vec![Hyperparameter::log("decay", 0.0, 1.0)], 0),
+            Err(TuningError::InvalidRange { .. })
+        ));
+        assert!(matches!(
+            TpeOptimizer::new(vec![Hyperparameter::new("x", 0.0, 1.0); 2], 0),
+            Err(TuningError::DuplicateName(_))
+        ));
+
+        let mut tuner = TpeOptimizer::new(vec![Hyperparameter::new("dropout", 0.0, 0.5)], 1)
+            .unwrap()
+            .with_startup_trials(3);
+        let best = tuner
+            .optimize(15, |params| {
+                let dropout = params["dropout"];
+                if dropout > 0.4 {
+                    f64::NAN
+                } else {
+                    (dropout - 0.1).abs()
+                }
+            })
+            .unwrap();
+        assert!(best.score.is_finite());
+        assert!(best.params["dropout"] <= 0.4);
+    }
+}
@@ -249,6 +249,11 @@ impl<T: Float + std::fmt::Debug + Send + Sync + 'static> BackendSelector<T> {
         }
     }
 
+    /// The backend of `backend_type`, if it was initialized
+    pub fn backend(&self, backend_type: BackendType) -> Option<&dyn ComputeBackend<T>> {
+        self.find_backend(backend_type)
+    }
+
     fn find_backend(&self, backend_type: BackendType) -> Option<&dyn ComputeBackend<T>> {
         self.backends
             .iter()
@@ -18,7 +18,7 @@ use crate::webgpu::error::{ComputeError, ComputeResult};
 use std::collections::{HashMap, VecDeque};
 use std::sync::{
     atomic::{AtomicU64, AtomicUsize, Ordering},
-    Arc, Mutex,
+    Arc, Mutex, PoisonError,
 };
 use std::time::{Duration, Instant};
 
@@ -422,11 +422,15 @@ impl PressureCircuitBreaker {
         F: FnOnce() -> ComputeResult<R>,
     {
         let state = {
-            let mut state = self.state.lock().unwrap();
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
             match state.clone() {
                 CircuitBreakerState::Open => {
                     // Check if recovery timeout has passed
-                    if let Some(last_failure) = *self.last_failure.lock().unwrap() {
+                    if let Some(last_failure) = *self
+                        .last_failure
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                    {
                         if last_failure.elapsed() >= self.recovery_timeout {
                             *state = CircuitBreakerState::HalfOpen;
                             CircuitBreakerState::HalfOpen
@@ -450,7 +454,8 @@ impl PressureCircuitBreaker {
                         // Reset failure count on success
                         self.failure_count.store(0, Ordering::Relaxed);
                         if state == CircuitBreakerState::HalfOpen {
-                            *self.state.lock().unwrap() = CircuitBreakerState::Closed;
+                            *self.state.lock().unwrap_or_else(PoisonError::into_inner) =
+                                CircuitBreakerState::Closed;
                         }
                         Ok(result)
                     }
@@ -468,10 +473,13 @@ impl PressureCircuitBreaker {
 
     fn record_failure(&self) {
         let failure_count = self.failure_count.fetch_add(1, Ordering::Relaxed) + 1;
-        *self.last_failure.lock().unwrap() = Some(Instant::now());
+        *self
+            .last_failure
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
 
         if failure_count >= self.failure_threshold {
-            *self.state.lock().unwrap() = CircuitBreakerState::Open;
+            *self.state.lock().unwrap_or_else(PoisonError::into_inner) = CircuitBreakerState::Open;
         }
     }
 }
@@ -577,7 +585,7 @@ impl AdvancedBufferPool {
         category: BufferCategory,
         start_time: Instant,
     ) -> ComputeResult<GpuBuffer> {
-        let mut pools = self.pools.lock().unwrap();
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
 
         if let Some(tier_pool) = pools.get_mut(&category) {
             // Try to find suitable buffer in pool (sub-millisecond path)
@@ -689,7 +697,7 @@ impl AdvancedBufferPool {
 
     /// Return buffer to pool with DAA optimization
     pub fn return_buffer(&self, buffer: GpuBuffer) {
-        let mut pools = self.pools.lock().unwrap();
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
 
         if let Some(tier_pool) = pools.get_mut(&buffer.category) {
             // Apply DAA-based retention decision
@@ -803,17 +811,17 @@ impl AdvancedBufferPool {
             }
 
             match recommendation.recommendation_type {
-                DaaRecommendationType::TriggerCoalescing => {
-                    if tier_pool.coalescing_candidates.len() >= 2 {
-                        // Trigger coalescing operation
-                        tier_pool
-                            .tier_stats
-                            .daa_optimizations
-                            .fetch_add(1, Ordering::Relaxed);
-                        self.global_stats
-                            .daa_optimizations_applied
-                            .fetch_add(1, Ordering::Relaxed);
-                    }
+                DaaRecommendationType::TriggerCoalescing
+                    if tier_pool.coalescing_candidates.len() >= 2 =>
+                {
+                    // Trigger coalescing operation
+                    tier_pool
+                        .tier_stats
+                        .daa_optimizations
+                        .fetch_add(1, Ordering::Relaxed);
+                    self.global_stats
+                        .daa_optimizations_applied
+                        .fetch_add(1, Ordering::Relaxed);
                 }
                 DaaRecommendationType::AdjustCleanupThreshold { new_threshold } => {
                     tier_pool.config.cleanup_threshold = new_threshold;
@@ -895,7 +903,7 @@ impl AdvancedBufferPool {
 
     /// Get comprehensive pool statistics
     pub fn get_statistics(&self) -> PoolStatisticsSnapshot {
-        let pools = self.pools.lock().unwrap();
+        let pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
         let mut tier_stats = HashMap::new();
 
         for (&category, tier_pool) in pools.iter() {
@@ -966,7 +974,7 @@ impl AdvancedBufferPool {
 
     /// Cleanup old buffers based on memory pressure
     pub fn cleanup_with_pressure_response(&self, pressure: MemoryPressure) {
-        let mut pools = self.pools.lock().unwrap();
+        let mut pools = self.pools.lock().unwrap_or_else(PoisonError::into_inner);
         let aggressiveness = pressure.cleanup_aggressiveness();
 
         for tier_pool in pools.values_mut() {
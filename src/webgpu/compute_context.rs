@@ -556,6 +556,12 @@ pub struct GpuMemoryManager<T: Float> {
     _phantom: std::marker::PhantomData<T>,
 }
 
+impl<T: Float> Default for GpuMemoryManager<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T: Float> GpuMemoryManager<T> {
     /// Create a new GPU memory manager
     pub fn new() -> Self {
@@ -159,9 +159,10 @@ where
 
         FallbackHealthStatus {
             backend_health,
-            primary_available: self.primary_backend.is_some()
-                && !self
-                    .is_circuit_breaker_open(self.primary_backend.as_ref().unwrap().backend_type()),
+            primary_available: self
+                .primary_backend
+                .as_ref()
+                .is_some_and(|primary| !self.is_circuit_breaker_open(primary.backend_type())),
             fallback_count: self.fallback_backends.len(),
         }
     }
@@ -2,7 +2,7 @@
 
 use super::error::{ComputeError, ComputeResult};
 use std::collections::{HashMap, VecDeque};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -94,10 +94,13 @@ impl CpuMemoryManager {
     }
 
     pub fn allocate(&self, size: usize) -> Result<BufferHandle, ComputeError> {
-        let mut buffers = self.buffers.lock().unwrap();
-        let mut buffer_info = self.buffer_info.lock().unwrap();
-        let mut next_id = self.next_id.lock().unwrap();
-        let mut stats = self.stats.lock().unwrap();
+        let mut buffers = self.buffers.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut buffer_info = self
+            .buffer_info
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut next_id = self.next_id.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
 
         let id = *next_id;
         *next_id += 1;
@@ -120,9 +123,12 @@ impl CpuMemoryManager {
     }
 
     pub fn deallocate(&self, handle: BufferHandle) -> Result<(), ComputeError> {
-        let mut buffers = self.buffers.lock().unwrap();
-        let mut buffer_info = self.buffer_info.lock().unwrap();
-        let mut stats = self.stats.lock().unwrap();
+        let mut buffers = self.buffers.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut buffer_info = self
+            .buffer_info
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
 
         if let Some(info) = buffer_info.get(&handle) {
             stats.total_allocated -= info.size;
@@ -136,7 +142,7 @@ impl CpuMemoryManager {
     }
 
     pub fn read(&self, handle: BufferHandle) -> Result<Vec<u8>, ComputeError> {
-        let buffers = self.buffers.lock().unwrap();
+        let buffers = self.buffers.lock().unwrap_or_else(PoisonError::into_inner);
         buffers
             .get(&handle)
             .cloned()
@@ -144,7 +150,7 @@ impl CpuMemoryManager {
     }
 
     pub fn write(&self, handle: BufferHandle, data: &[u8]) -> Result<(), ComputeError> {
-        let mut buffers = self.buffers.lock().unwrap();
+        let mut buffers = self.buffers.lock().unwrap_or_else(PoisonError::into_inner);
         let buffer = buffers
             .get_mut(&handle)
             .ok_or_else(|| ComputeError::General("Buffer not found".to_string()))?;
@@ -158,7 +164,10 @@ impl CpuMemoryManager {
     }
 
     pub fn get_stats(&self) -> MemoryStats {
-        self.stats.lock().unwrap().clone()
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
     }
 }
 
@@ -246,30 +255,36 @@ pub mod webgpu_memory {
         }
 
         pub fn allocate_buffer(&self, size: usize) -> Result<super::BufferHandle, ComputeError> {
-            let mut pool = self.pool.lock().unwrap();
-            let mut stats = self.stats.lock().unwrap();
+            let mut pool = self.pool.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
 
             let id = pool.allocate(size as u64, wgpu::BufferUsages::STORAGE);
             stats.total_allocated += size;
             stats.buffer_count += 1;
+            crate::memory_manager::device_memory_tracker().record_allocation(size);
 
             Ok(super::BufferHandle::new(id))
         }
 
         pub fn deallocate_buffer(&self, handle: super::BufferHandle) -> Result<(), ComputeError> {
-            let mut pool = self.pool.lock().unwrap();
-            let mut stats = self.stats.lock().unwrap();
+            let mut pool = self.pool.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
 
             if let Some(buffer) = pool.deallocate(handle.id()) {
                 stats.total_allocated = stats.total_allocated.saturating_sub(buffer.size as usize);
                 stats.buffer_count = stats.buffer_count.saturating_sub(1);
+                crate::memory_manager::device_memory_tracker()
+                    .record_deallocation(buffer.size as usize);
             }
 
             Ok(())
         }
 
         pub fn get_stats(&self) -> super::MemoryStats {
-            self.stats.lock().unwrap().clone()
+            self.stats
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
         }
     }
 }
@@ -318,9 +333,15 @@ impl GpuMemoryManager {
 
         // Try to get from pool first
         {
-            let mut pools = self.buffer_pools.lock().unwrap();
-            let mut allocated = self.allocated_buffers.lock().unwrap();
-            let mut stats = self.stats.lock().unwrap();
+            let mut pools = self
+                .buffer_pools
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let mut allocated = self
+                .allocated_buffers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
 
             if let Some(pool) = pools.get_mut(&size_category) {
                 while let Some(handle) = pool.pop_front() {
@@ -359,9 +380,15 @@ impl GpuMemoryManager {
     }
 
     pub fn deallocate(&self, handle: BufferHandle) -> Result<(), ComputeError> {
-        let mut allocated = self.allocated_buffers.lock().unwrap();
-        let mut pools = self.buffer_pools.lock().unwrap();
-        let mut stats = self.stats.lock().unwrap();
+        let mut allocated = self
+            .allocated_buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut pools = self
+            .buffer_pools
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
 
         if let Some(mut info) = allocated.remove(&handle) {
             info.in_use = false;
@@ -419,12 +446,21 @@ impl GpuMemoryManager {
     }
 
     pub fn get_detailed_stats(&self) -> MemoryManagerStats {
-        self.stats.lock().unwrap().clone()
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
     }
 
     pub fn cleanup_unused_buffers(&self, age_threshold: std::time::Duration) {
-        let mut allocated = self.allocated_buffers.lock().unwrap();
-        let mut pools = self.buffer_pools.lock().unwrap();
+        let mut allocated = self
+            .allocated_buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut pools = self
+            .buffer_pools
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
         let now = std::time::Instant::now();
 
         // Find old unused buffers
@@ -457,8 +493,11 @@ impl GpuMemoryManager {
     }
 
     fn track_allocation(&self, handle: BufferHandle, size: usize) {
-        let mut allocated = self.allocated_buffers.lock().unwrap();
-        let mut stats = self.stats.lock().unwrap();
+        let mut allocated = self
+            .allocated_buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
 
         let mut info = BufferInfo::new(size);
         info.in_use = true;
@@ -17,6 +17,7 @@ pub mod compute_context;
 pub mod error;
 pub mod fallback;
 pub mod memory;
+pub mod placement;
 pub mod shaders;
 
 // Enhanced memory management components
@@ -47,6 +48,7 @@ pub use compute_context::{ComputeContext, ComputePerformanceStats, DaaCoordinati
 pub use error::ComputeError;
 pub use fallback::FallbackManager;
 pub use memory::{BufferHandle, MemoryStats};
+pub use placement::{ExecutionPlan, LayerPlacement, PlacedExecutor, Placement, PlanStep};
 
 // Re-export enhanced memory management
 pub use buffer_pool::{
@@ -381,8 +381,8 @@ impl PerformanceMonitor {
             .collect();
 
         let avg_execution_nanos = execution_times.iter().sum::<u64>() / count as u64;
-        let min_execution_nanos = *execution_times.iter().min().unwrap();
-        let max_execution_nanos = *execution_times.iter().max().unwrap();
+        let min_execution_nanos = execution_times.iter().min().copied().unwrap_or(0);
+        let max_execution_nanos = execution_times.iter().max().copied().unwrap_or(0);
 
         // Calculate standard deviation
         let variance = execution_times
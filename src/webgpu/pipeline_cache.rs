@@ -3,7 +3,7 @@
 
 use crate::webgpu::error::ComputeError;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, PoisonError, RwLock};
 
 #[cfg(feature = "gpu")]
 use crate::webgpu::shaders::webgpu_shaders::ShaderType;
@@ -97,15 +97,24 @@ impl PipelineCache {
     ) -> Result<Arc<ComputePipeline>, ComputeError> {
         // Update cache statistics
         {
-            let mut stats = self.cache_stats.write().unwrap();
+            let mut stats = self
+                .cache_stats
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
             stats.pipeline_requests += 1;
         }
 
         // Try to get from cache first
         {
-            let pipelines = self.pipelines.read().unwrap();
+            let pipelines = self
+                .pipelines
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
             if let Some(pipeline) = pipelines.get(shader_type) {
-                let mut stats = self.cache_stats.write().unwrap();
+                let mut stats = self
+                    .cache_stats
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner);
                 stats.pipeline_hits += 1;
                 return Ok(Arc::clone(pipeline));
             }
@@ -118,7 +127,10 @@ impl PipelineCache {
 
         // Update compilation statistics
         {
-            let mut stats = self.compilation_stats.write().unwrap();
+            let mut stats = self
+                .compilation_stats
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
             stats.total_compilations += 1;
             stats.compilation_time_ns += compilation_time.as_nanos() as u64;
             stats.cache_misses += 1;
@@ -129,7 +141,10 @@ impl PipelineCache {
         // Store in cache
         let pipeline_arc = Arc::new(pipeline);
         {
-            let mut pipelines = self.pipelines.write().unwrap();
+            let mut pipelines = self
+                .pipelines
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
             pipelines.insert(shader_type.clone(), Arc::clone(&pipeline_arc));
         }
 
@@ -143,15 +158,24 @@ impl PipelineCache {
     ) -> Result<Arc<BindGroupLayout>, ComputeError> {
         // Update cache statistics
         {
-            let mut stats = self.cache_stats.write().unwrap();
+            let mut stats = self
+                .cache_stats
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
             stats.layout_requests += 1;
         }
 
         // Try to get from cache first
         {
-            let layouts = self.bind_group_layouts.read().unwrap();
+            let layouts = self
+                .bind_group_layouts
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
             if let Some(layout) = layouts.get(shader_type) {
-                let mut stats = self.cache_stats.write().unwrap();
+                let mut stats = self
+                    .cache_stats
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner);
                 stats.layout_hits += 1;
                 return Ok(Arc::clone(layout));
             }
@@ -163,7 +187,10 @@ impl PipelineCache {
 
         // Store in cache
         {
-            let mut layouts = self.bind_group_layouts.write().unwrap();
+            let mut layouts = self
+                .bind_group_layouts
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
             layouts.insert(shader_type.clone(), Arc::clone(&layout_arc));
         }
 
@@ -191,11 +218,17 @@ impl PipelineCache {
     /// Get comprehensive cache performance statistics
     pub fn get_performance_stats(&self) -> (CompilationStats, CacheStats) {
         let compilation_stats = {
-            let stats = self.compilation_stats.read().unwrap();
+            let stats = self
+                .compilation_stats
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
             stats.clone()
         };
         let cache_stats = {
-            let stats = self.cache_stats.read().unwrap();
+            let stats = self
+                .cache_stats
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
             stats.clone()
         };
         (compilation_stats, cache_stats)
@@ -204,26 +237,41 @@ impl PipelineCache {
     /// Clear cache and reset statistics (useful for testing)
     pub fn clear_cache(&self) {
         {
-            let mut pipelines = self.pipelines.write().unwrap();
+            let mut pipelines = self
+                .pipelines
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
             pipelines.clear();
         }
         {
-            let mut layouts = self.bind_group_layouts.write().unwrap();
+            let mut layouts = self
+                .bind_group_layouts
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
             layouts.clear();
         }
         {
-            let mut stats = self.compilation_stats.write().unwrap();
+            let mut stats = self
+                .compilation_stats
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
             *stats = CompilationStats::default();
         }
         {
-            let mut stats = self.cache_stats.write().unwrap();
+            let mut stats = self
+                .cache_stats
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
             *stats = CacheStats::default();
         }
     }
 
     /// Get cache hit ratio for performance monitoring
     pub fn get_cache_hit_ratio(&self) -> f64 {
-        let stats = self.cache_stats.read().unwrap();
+        let stats = self
+            .cache_stats
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
         if stats.pipeline_requests == 0 {
             return 0.0;
         }
@@ -0,0 +1,371 @@
+//! Per-layer compute placement
+//!
+//! Small layers gain nothing from a GPU: the transfer and dispatch overhead
+//! dwarfs the arithmetic. [`LayerPlacement`] pins each layer to the host
+//! (SIMD/CPU backends) or the GPU, and [`PlacedExecutor`] turns it into an
+//! [`ExecutionPlan`] with a transfer step wherever consecutive layers live on
+//! different devices, then runs the forward pass layer by layer on the
+//! chosen backends.
+//!
+//! Placements are requests: a layer pinned to a device whose backend is not
+//! available in the [`BackendSelector`] falls back along the usual
+//! WebGPU -> SIMD -> CPU chain, and the plan records where it actually runs.
+
+use super::backend::{BackendSelector, BackendType, ComputeBackend};
+use super::error::ComputeError;
+use crate::{Layer, Network};
+use num_traits::Float;
+
+/// Requested device of a layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Placement {
+    /// Let the backend selector decide from the layer size
+    #[default]
+    Auto,
+    /// SIMD or scalar CPU backend
+    Host,
+    /// WebGPU backend
+    Gpu,
+}
+
+/// Placement of every layer after the input layer
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LayerPlacement {
+    default: Placement,
+    /// Overrides by layer index (1 is the first hidden layer)
+    overrides: Vec<(usize, Placement)>,
+}
+
+impl LayerPlacement {
+    pub fn new(default: Placement) -> Self {
+        Self {
+            default,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Pin `layer` (an index into `network.layers`) to `placement`
+    pub fn with_layer(mut self, layer: usize, placement: Placement) -> Self {
+        self.overrides.retain(|(l, _)| *l != layer);
+        self.overrides.push((layer, placement));
+        self
+    }
+
+    /// Everything on the GPU except the first and last layer, which are
+    /// usually too narrow to benefit
+    pub fn gpu_interior(num_layers: usize) -> Self {
+        let mut placement = Self::new(Placement::Gpu).with_layer(1, Placement::Host);
+        if num_layers > 2 {
+            placement = placement.with_layer(num_layers - 1, Placement::Host);
+        }
+        placement
+    }
+
+    pub fn for_layer(&self, layer: usize) -> Placement {
+        self.overrides
+            .iter()
+            .find(|(l, _)| *l == layer)
+            .map_or(self.default, |(_, p)| *p)
+    }
+}
+
+/// Whether a backend computes in host memory
+fn on_host(backend: BackendType) -> bool {
+    backend != BackendType::WebGPU
+}
+
+/// One step of a placed forward pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanStep {
+    /// Move `values` activations between host and GPU memory
+    Transfer {
+        from: BackendType,
+        to: BackendType,
+        values: usize,
+    },
+    /// Compute `layer` on `backend`
+    Compute { layer: usize, backend: BackendType },
+}
+
+/// Resolved placement of a network
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionPlan {
+    pub steps: Vec<PlanStep>,
+}
+
+impl ExecutionPlan {
+    /// Backend of every computed layer, in order
+    pub fn layer_backends(&self) -> Vec<BackendType> {
+        self.steps
+            .iter()
+            .filter_map(|step| match step {
+                PlanStep::Compute { backend, .. } => Some(*backend),
+                PlanStep::Transfer { .. } => None,
+            })
+            .collect()
+    }
+
+    pub fn transfer_count(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|s| matches!(s, PlanStep::Transfer { .. }))
+            .count()
+    }
+
+    /// Activations copied between devices per sample
+    pub fn transferred_values(&self) -> usize {
+        self.steps
+            .iter()
+            .map(|step| match step {
+                PlanStep::Transfer { values, .. } => *values,
+                PlanStep::Compute { .. } => 0,
+            })
+            .sum()
+    }
+}
+
+/// Runs a network forward pass with each layer on its placed backend
+#[derive(Debug)]
+pub struct PlacedExecutor<T: Float + Send + Sync> {
+    selector: BackendSelector<T>,
+    placement: LayerPlacement,
+}
+
+impl<T: Float + std::fmt::Debug + Send + Sync + 'static> PlacedExecutor<T> {
+    pub fn new(placement: LayerPlacement) -> Self {
+        Self::with_selector(BackendSelector::new(), placement)
+    }
+
+    pub fn with_selector(selector: BackendSelector<T>, placement: LayerPlacement) -> Self {
+        Self {
+            selector,
+            placement,
+        }
+    }
+
+    pub fn placement(&self) -> &LayerPlacement {
+        &self.placement
+    }
+
+    fn resolve(&mut self, placement: Placement, rows: usize, cols: usize) -> BackendType {
+        let chain: &[BackendType] = match placement {
+            Placement::Auto => return self.selector.select_optimal_backend(rows, cols),
+            Placement::Host => &[BackendType::Simd, BackendType::Cpu],
+            Placement::Gpu => &[BackendType::WebGPU, BackendType::Simd, BackendType::Cpu],
+        };
+        let available = self.selector.get_available_backends();
+        chain
+            .iter()
+            .copied()
+            .find(|b| available.contains(b))
+            .unwrap_or_else(|| self.selector.get_current_backend())
+    }
+
+    /// Resolve placements for `network` and insert transfers between devices
+    ///
+    /// Inputs start and outputs end in host memory.
+    pub fn plan(&mut self, network: &Network<T>) -> ExecutionPlan {
+        let mut steps = Vec::new();
+        let mut location = BackendType::Cpu;
+        for (index, layer) in network.layers.iter().enumerate().skip(1) {
+            let rows = layer.num_regular_neurons();
+            let cols = network.layers[index - 1].size();
+            let backend = self.resolve(self.placement.for_layer(index), rows, cols);
+            if on_host(location) != on_host(backend) {
+                steps.push(PlanStep::Transfer {
+                    from: location,
+                    to: backend,
+                    values: cols,
+                });
+            }
+            steps.push(PlanStep::Compute {
+                layer: index,
+                backend,
+            });
+            location = backend;
+        }
+        if !on_host(location) {
+            let values = network.num_outputs();
+            steps.push(PlanStep::Transfer {
+                from: location,
+                to: BackendType::Cpu,
+                values,
+            });
+        }
+        ExecutionPlan { steps }
+    }
+
+    /// Forward pass of `input` following `plan`
+    pub fn run(
+        &self,
+        network: &Network<T>,
+        plan: &ExecutionPlan,
+        input: &[T],
+    ) -> Result<Vec<T>, ComputeError> {
+        if input.len() != network.num_inputs() {
+            return Err(ComputeError::InvalidDimensions(format!(
+                "expected {} inputs, got {}",
+                network.num_inputs(),
+                input.len()
+            )));
+        }
+        let Some(first) = network.layers.first() else {
+            return Ok(Vec::new());
+        };
+        let mut values = with_bias(first, input);
+        let mut outputs = input.to_vec();
+        for step in &plan.steps {
+            // Transfers are implicit: backends exchange host slices
+            let PlanStep::Compute { layer, backend } = *step else {
+                continue;
+            };
+            let backend = self.backend(backend)?;
+            let layer = &network.layers[layer];
+            let (matrix, rows, cols) = weight_matrix(layer, values.len());
+            let sums = backend.matrix_vector_multiply(&matrix, &values, rows, cols)?;
+            let neurons: Vec<_> = layer.neurons.iter().filter(|n| !n.is_bias).collect();
+            let uniform = neurons.windows(2).all(|w| {
+                w[0].activation_function == w[1].activation_function
+                    && w[0].activation_steepness == w[1].activation_steepness
+            });
+            let activated = match neurons.first() {
+                Some(n) if uniform => backend.apply_activation_function(
+                    &sums,
+                    n.activation_function,
+                    n.activation_steepness,
+                )?,
+                _ => neurons
+                    .iter()
+                    .zip(&sums)
+                    .map(|(n, &sum)| {
+                        backend
+                            .apply_activation_function(
+                                &[sum],
+                                n.activation_function,
+                                n.activation_steepness,
+                            )
+                            .map(|v| v[0])
+                    })
+                    .collect::<Result<_, _>>()?,
+            };
+            values = with_bias(layer, &activated);
+            outputs = activated;
+        }
+        Ok(outputs)
+    }
+
+    fn backend(&self, backend: BackendType) -> Result<&dyn ComputeBackend<T>, ComputeError> {
+        self.selector.backend(backend).ok_or_else(|| {
+            ComputeError::BackendError(format!("backend {backend:?} is not available"))
+        })
+    }
+}
+
+/// Layer values in neuron order, with 1 in the bias neuron's slot
+fn with_bias<T: Float>(layer: &Layer<T>, values: &[T]) -> Vec<T> {
+    let mut values = values.iter().copied();
+    layer
+        .neurons
+        .iter()
+        .map(|n| {
+            if n.is_bias {
+                T::one()
+            } else {
+                values.next().unwrap_or(T::zero())
+            }
+        })
+        .collect()
+}
+
+/// Dense row-major weights of `layer`: one row per non-bias neuron, one
+/// column per neuron of the previous layer
+fn weight_matrix<T: Float>(layer: &Layer<T>, cols: usize) -> (Vec<T>, usize, usize) {
+    let neurons: Vec<_> = layer.neurons.iter().filter(|n| !n.is_bias).collect();
+    let mut matrix = vec![T::zero(); neurons.len() * cols];
+    for (row, neuron) in neurons.iter().enumerate() {
+        for connection in &neuron.connections {
+            if connection.from_neuron < cols {
+                matrix[row * cols + connection.from_neuron] = connection.weight;
+            }
+        }
+    }
+    (matrix, neurons.len(), cols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkBuilder;
+
+    fn network() -> Network<f64> {
+        NetworkBuilder::<f64>::new()
+            .input_layer(3)
+            .hidden_layer(8)
+            .hidden_layer(8)
+            .output_layer(2)
+            .build()
+    }
+
+    #[test]
+    fn test_plan_inserts_transfers_and_falls_back() {
+        let network = network();
+        let mut executor = PlacedExecutor::new(LayerPlacement::gpu_interior(4));
+        let plan = executor.plan(&network);
+        assert_eq!(plan.layer_backends().len(), 3);
+
+        if executor.selector.backend(BackendType::WebGPU).is_some() {
+            // Host -> GPU before layer 2, GPU -> host before layer 3
+            assert_eq!(plan.transfer_count(), 2);
+        } else {
+            // Without a GPU everything stays on the host
+            assert_eq!(plan.transfer_count(), 0);
+            assert!(plan.layer_backends().iter().all(|&b| on_host(b)));
+        }
+
+        let host = LayerPlacement::new(Placement::Host).with_layer(2, Placement::Host);
+        assert_eq!(host.for_layer(2), Placement::Host);
+        assert_eq!(LayerPlacement::gpu_interior(4).for_layer(2), Placement::Gpu);
+        assert_eq!(
+            LayerPlacement::gpu_interior(4).for_layer(3),
+            Placement::Host
+        );
+    }
+
+    #[test]
+    fn test_placed_run_matches_network() {
+        let mut network = network();
+        let input = [0.3, -0.2, 0.9];
+        let expected = network.run(&input);
+
+        for placement in [
+            LayerPlacement::default(),
+            LayerPlacement::new(Placement::Host).with_layer(2, Placement::Gpu),
+        ] {
+            let mut executor = PlacedExecutor::new(placement);
+            let plan = executor.plan(&network);
+            let outputs = executor.run(&network, &plan, &input).unwrap();
+            for (a, b) in outputs.iter().zip(&expected) {
+                assert!((a - b).abs() < 1e-12);
+            }
+        }
+        let mut executor = PlacedExecutor::new(LayerPlacement::default());
+        let plan = executor.plan(&network);
+        assert!(executor.run(&network, &plan, &[1.0]).is_err());
+
+        // Transfers are counted for a plan that mixes devices
+        let mixed = ExecutionPlan {
+            steps: vec![
+                PlanStep::Compute {
+                    layer: 1,
+                    backend: BackendType::Cpu,
+                },
+                PlanStep::Transfer {
+                    from: BackendType::Cpu,
+                    to: BackendType::WebGPU,
+                    values: 9,
+                },
+            ],
+        };
+        assert_eq!(mixed.transferred_values(), 9);
+    }
+}
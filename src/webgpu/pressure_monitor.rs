@@ -14,7 +14,7 @@
 use std::collections::{HashMap, VecDeque};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    Arc, Mutex,
+    Arc, Mutex, PoisonError,
 };
 use std::thread;
 use std::time::{Duration, Instant, SystemTime};
@@ -583,11 +583,15 @@ impl MemoryPressureMonitor {
                 // Collect pressure reading
                 if let Ok(reading) = Self::collect_pressure_reading(&buffer_pool, &config) {
                     // Update current pressure
-                    *current_pressure.lock().unwrap() = reading.pressure;
+                    *current_pressure
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner) = reading.pressure;
 
                     // Store in history
                     {
-                        let mut history = pressure_history.lock().unwrap();
+                        let mut history = pressure_history
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner);
                         history.push_back(reading.clone());
 
                         // Maintain history size
@@ -605,7 +609,10 @@ impl MemoryPressureMonitor {
                     // Detect anomalies
                     if config.anomaly_detection_enabled {
                         if let Some(anomaly) = Self::detect_anomalies(&anomaly_detector, &reading) {
-                            monitoring_stats.lock().unwrap().anomalies_detected += 1;
+                            monitoring_stats
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .anomalies_detected += 1;
                             #[cfg(feature = "logging")]
                             log::warn!("Memory anomaly detected: {anomaly:?}");
                             #[cfg(not(feature = "logging"))]
@@ -616,7 +623,10 @@ impl MemoryPressureMonitor {
                     // Generate predictions
                     if let Ok(prediction) = Self::generate_prediction(&predictor, &reading, &config)
                     {
-                        monitoring_stats.lock().unwrap().predictions_made += 1;
+                        monitoring_stats
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .predictions_made += 1;
 
                         // Check if autonomous response is needed
                         if config.daa_enabled && config.autonomous_response_enabled {
@@ -632,14 +642,19 @@ impl MemoryPressureMonitor {
                                     response,
                                     &autonomous_responses,
                                 );
-                                monitoring_stats.lock().unwrap().daa_interventions += 1;
+                                monitoring_stats
+                                    .lock()
+                                    .unwrap_or_else(PoisonError::into_inner)
+                                    .daa_interventions += 1;
                             }
                         }
                     }
 
                     // Update monitoring statistics
                     {
-                        let mut stats = monitoring_stats.lock().unwrap();
+                        let mut stats = monitoring_stats
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner);
                         stats.readings_collected += 1;
                         stats.average_sampling_latency = sampling_start.elapsed();
                         stats.system_uptime = start_time.elapsed();
@@ -743,7 +758,9 @@ impl MemoryPressureMonitor {
         anomaly_detector: &Arc<Mutex<AnomalyDetector>>,
         reading: &PressureReading,
     ) {
-        let mut detector = anomaly_detector.lock().unwrap();
+        let mut detector = anomaly_detector
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
         let stats = &mut detector.baseline_stats;
 
         // Exponential moving average updates
@@ -765,7 +782,9 @@ impl MemoryPressureMonitor {
         anomaly_detector: &Arc<Mutex<AnomalyDetector>>,
         reading: &PressureReading,
     ) -> Option<AnomalyEvent> {
-        let detector = anomaly_detector.lock().unwrap();
+        let detector = anomaly_detector
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
         let stats = &detector.baseline_stats;
 
         // Check for pressure spike anomaly
@@ -799,7 +818,7 @@ impl MemoryPressureMonitor {
         current_reading: &PressureReading,
         config: &MonitorConfig,
     ) -> ComputeResult<PressurePrediction> {
-        let mut predictor = predictor.lock().unwrap();
+        let mut predictor = predictor.lock().unwrap_or_else(PoisonError::into_inner);
 
         // Add current reading to historical data
         predictor.historical_data.push_back(current_reading.clone());
@@ -858,8 +877,12 @@ impl MemoryPressureMonitor {
         prediction: &PressurePrediction,
         alert_thresholds: &Arc<Mutex<AlertThresholds>>,
     ) -> Option<AutonomousResponse> {
-        let coordinator = daa_coordinator.lock().unwrap();
-        let thresholds = alert_thresholds.lock().unwrap();
+        let coordinator = daa_coordinator
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let thresholds = alert_thresholds
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
 
         // Check if current pressure warrants response
         let needs_response = match reading.pressure {
@@ -934,22 +957,35 @@ impl MemoryPressureMonitor {
         response.execution_time = start_time.elapsed();
 
         // Store response for analysis
-        responses.lock().unwrap().push(response);
+        responses
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(response);
     }
 
     /// Get current pressure reading
     pub fn current_pressure(&self) -> MemoryPressure {
-        *self.current_pressure.lock().unwrap()
+        *self
+            .current_pressure
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
     }
 
     /// Get latest pressure prediction
     pub fn latest_prediction(&self) -> Option<PressurePrediction> {
-        self.predictor.lock().unwrap().last_prediction.clone()
+        self.predictor
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .last_prediction
+            .clone()
     }
 
     /// Get recent anomalies
     pub fn recent_anomalies(&self, limit: usize) -> Vec<AnomalyEvent> {
-        let detector = self.anomaly_detector.lock().unwrap();
+        let detector = self
+            .anomaly_detector
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
         detector
             .recent_anomalies
             .iter()
@@ -961,21 +997,27 @@ impl MemoryPressureMonitor {
 
     /// Get monitoring statistics
     pub fn get_statistics(&self) -> MonitoringStatistics {
-        self.monitoring_stats.lock().unwrap().clone()
+        self.monitoring_stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
     }
 
     /// Get DAA performance metrics
     pub fn get_daa_metrics(&self) -> DaaPerformanceMetrics {
         self.daa_coordinator
             .lock()
-            .unwrap()
+            .unwrap_or_else(PoisonError::into_inner)
             .performance_metrics
             .clone()
     }
 
     /// Update alert thresholds
     pub fn update_thresholds(&self, new_thresholds: AlertThresholds) {
-        *self.alert_thresholds.lock().unwrap() = new_thresholds;
+        *self
+            .alert_thresholds
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = new_thresholds;
     }
 
     /// Generate monitoring report
@@ -84,7 +84,7 @@ pub mod webgpu_shaders {
 
     use crate::webgpu::kernel_optimizer::KernelOptimizer;
     use crate::webgpu::pipeline_cache::PipelineCache;
-    use std::sync::Arc;
+    use std::sync::{Arc, PoisonError};
 
     #[derive(Debug)]
     pub struct ShaderManager {
@@ -159,7 +159,10 @@ pub mod webgpu_shaders {
             shader_type: &ShaderType,
             data_size: usize,
         ) -> Result<crate::webgpu::kernel_optimizer::KernelConfig, ComputeError> {
-            let mut optimizer = self.kernel_optimizer.lock().unwrap();
+            let mut optimizer = self
+                .kernel_optimizer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
 
             match shader_type {
                 ShaderType::MatrixVectorMultiply => {
@@ -212,6 +215,8 @@ pub mod webgpu_shaders {
                 ActivationFunction::ThresholdSymmetric => {
                     Some(ShaderType::ActivationThresholdSymmetric)
                 }
+                // No shaders yet; these run on the CPU
+                ActivationFunction::Gelu | ActivationFunction::Swish => None,
             }
         }
 
@@ -298,7 +303,10 @@ pub mod webgpu_shaders {
             data_size: usize,
             metrics: crate::webgpu::kernel_optimizer::OptimizationMetrics,
         ) {
-            let mut optimizer = self.kernel_optimizer.lock().unwrap();
+            let mut optimizer = self
+                .kernel_optimizer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
             optimizer.record_performance(shader_type, data_size, metrics);
         }
 
@@ -308,14 +316,20 @@ pub mod webgpu_shaders {
             shader_type: &ShaderType,
             data_size: usize,
         ) -> Option<crate::webgpu::kernel_optimizer::OptimizationMetrics> {
-            let optimizer = self.kernel_optimizer.lock().unwrap();
+            let optimizer = self
+                .kernel_optimizer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
             optimizer.predict_performance(shader_type, data_size)
         }
 
         /// Clear all caches (useful for testing)
         pub fn clear_caches(&self) {
             self.pipeline_cache.clear_cache();
-            let mut optimizer = self.kernel_optimizer.lock().unwrap();
+            let mut optimizer = self
+                .kernel_optimizer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
             optimizer.clear_caches();
         }
 
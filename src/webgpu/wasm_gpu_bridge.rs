@@ -1117,13 +1117,15 @@ impl WasmGpuBridge {
         ));
 
         // Write data to buffer
-        let data = js_array.to_vec();
-        let data_u8: &[u8] =
-            unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * 4) };
+        let data_u8: Vec<u8> = js_array
+            .to_vec()
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
 
         device
             .queue()
-            .write_buffer_with_u8_array(&buffer, 0, data_u8);
+            .write_buffer_with_u8_array(&buffer, 0, &data_u8);
         Ok(buffer)
     }
 
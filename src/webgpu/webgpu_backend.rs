@@ -57,6 +57,7 @@
 pub mod webgpu_impl {
     use num_traits::Float;
 
+    use crate::numeric::cast;
     use crate::webgpu::backend::{
         BackendCapabilities, BackendType, ComputeBackend, MemoryManager, VectorOps,
     };
@@ -65,6 +66,7 @@ pub mod webgpu_impl {
     use crate::webgpu::shaders::webgpu_shaders::{ShaderManager, ShaderType};
     use crate::ActivationFunction;
     use std::collections::HashMap;
+    use std::sync::PoisonError;
 
     /// WebGPU compute backend
     ///
@@ -113,11 +115,18 @@ pub mod webgpu_impl {
         buffer_pool: HashMap<u64, wgpu::Buffer>,
         /// Bind group layouts cache
         bind_group_layouts: HashMap<ShaderType, wgpu::BindGroupLayout>,
+        /// Size in bytes of every live buffer handle
+        buffer_sizes: HashMap<u64, usize>,
+        /// Id of the next allocated buffer; 0 is never handed out
+        next_buffer_id: u64,
     }
 
     impl<T: Float + std::fmt::Debug + Send + Sync + 'static> std::fmt::Debug for WebGPUBackend<T> {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            let gpu_state = self.gpu_state.read().unwrap();
+            let gpu_state = self
+                .gpu_state
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
             f.debug_struct("WebGPUBackend")
                 .field("capabilities", &self.capabilities)
                 .field("shader_manager", &self.shader_manager)
@@ -137,6 +146,21 @@ pub mod webgpu_impl {
             pollster::block_on(Self::initialize())
         }
 
+        /// Size in bytes of a live buffer
+        fn buffer_size(&self, handle: BufferHandle, action: &str) -> Result<usize, ComputeError> {
+            self.gpu_state
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .buffer_sizes
+                .get(&handle.id())
+                .copied()
+                .ok_or_else(|| {
+                    ComputeError::InvalidDimensions(format!(
+                        "Cannot {action} invalid buffer handle"
+                    ))
+                })
+        }
+
         /// Initialize WebGPU backend asynchronously
         ///
         /// This method performs the following initialization steps:
@@ -229,6 +253,8 @@ pub mod webgpu_impl {
                     pipelines: HashMap::new(),
                     buffer_pool: HashMap::new(),
                     bind_group_layouts: HashMap::new(),
+                    buffer_sizes: HashMap::new(),
+                    next_buffer_id: 1,
                 }),
                 _phantom: std::marker::PhantomData,
             })
@@ -522,7 +548,10 @@ pub mod webgpu_impl {
     impl<T: Float + std::fmt::Debug + Send + Sync + 'static> WebGPUBackend<T> {
         /// Get or compile a shader module
         fn get_or_create_shader_module(&self, shader_type: ShaderType) -> Result<(), ComputeError> {
-            let mut gpu_state = self.gpu_state.write().unwrap();
+            let mut gpu_state = self
+                .gpu_state
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
 
             if gpu_state.shader_modules.contains_key(&shader_type) {
                 return Ok(());
@@ -558,7 +587,10 @@ pub mod webgpu_impl {
             entry_point: &str,
         ) -> Result<(), ComputeError> {
             {
-                let gpu_state = self.gpu_state.read().unwrap();
+                let gpu_state = self
+                    .gpu_state
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner);
                 if gpu_state.pipelines.contains_key(&shader_type) {
                     return Ok(());
                 }
@@ -567,8 +599,13 @@ pub mod webgpu_impl {
             // Ensure shader module exists
             self.get_or_create_shader_module(shader_type.clone())?;
 
-            let mut gpu_state = self.gpu_state.write().unwrap();
-            let module = gpu_state.shader_modules.get(&shader_type).unwrap();
+            let mut gpu_state = self
+                .gpu_state
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            let module = gpu_state.shader_modules.get(&shader_type).ok_or_else(|| {
+                ComputeError::ShaderError(format!("{:?} shader module missing", shader_type))
+            })?;
 
             // Create bind group layout based on shader type
             let entries = match shader_type {
@@ -892,19 +929,31 @@ pub mod webgpu_impl {
             // Ensure the compute pipeline exists
             self.get_or_create_pipeline(ShaderType::MatrixVectorMultiply, "main")?;
 
-            let gpu_state = self.gpu_state.read().unwrap();
+            let gpu_state = self
+                .gpu_state
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
             let pipeline = gpu_state
                 .pipelines
                 .get(&ShaderType::MatrixVectorMultiply)
-                .unwrap();
+                .ok_or_else(|| {
+                    ComputeError::ShaderError(format!(
+                        "{:?} pipeline missing",
+                        ShaderType::MatrixVectorMultiply
+                    ))
+                })?;
 
             // Create GPU buffers
             use wgpu::util::DeviceExt;
 
             // Convert to f32 for GPU operations (WebGPU doesn't support f64)
-            let matrix_f32: Vec<f32> = matrix.iter().map(|&x| x.to_f32().unwrap()).collect();
-            let vector_f32: Vec<f32> = vector.iter().map(|&x| x.to_f32().unwrap()).collect();
+            let matrix_f32: Vec<f32> = matrix.iter().map(|&x| cast::<f32>(x)).collect();
+            let vector_f32: Vec<f32> = vector.iter().map(|&x| cast::<f32>(x)).collect();
 
+            crate::memory_manager::device_memory_tracker().record_upload(
+                std::mem::size_of_val(matrix_f32.as_slice())
+                    + std::mem::size_of_val(vector_f32.as_slice()),
+            );
             let matrix_buffer = self
                 .device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -1032,24 +1081,25 @@ pub mod webgpu_impl {
             let buffer_slice = staging_buffer.slice(..);
             let (sender, receiver) = std::sync::mpsc::channel();
             buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-                sender.send(result).unwrap();
+                let _ = sender.send(result);
             });
 
             // CRITICAL FIX: Use Poll instead of Wait to avoid blocking
             self.device.poll(wgpu::Maintain::Wait);
             receiver
                 .recv()
-                .unwrap()
+                .map_err(|_| ComputeError::AsyncError("buffer mapping was cancelled".to_string()))?
                 .map_err(|_| ComputeError::ComputeError("Failed to map buffer".to_string()))?;
 
             let data = buffer_slice.get_mapped_range();
             let result_f32: Vec<f32> = bytemuck::cast_slice(&data).to_vec();
+            crate::memory_manager::device_memory_tracker().record_download(data.len());
 
             drop(data);
             staging_buffer.unmap();
 
             // Convert back to T
-            let result: Vec<T> = result_f32.iter().map(|&x| T::from(x).unwrap()).collect();
+            let result: Vec<T> = result_f32.iter().map(|&x| cast(x)).collect();
 
             Ok(result)
         }
@@ -1074,20 +1124,28 @@ pub mod webgpu_impl {
             // Ensure shader and pipeline exist
             self.get_or_create_pipeline(ShaderType::BatchMatrixVectorMultiply, "main")?;
 
-            let gpu_state = self.gpu_state.read().unwrap();
+            let gpu_state = self
+                .gpu_state
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
             let pipeline = gpu_state
                 .pipelines
                 .get(&ShaderType::BatchMatrixVectorMultiply)
-                .unwrap();
+                .ok_or_else(|| {
+                    ComputeError::ShaderError(format!(
+                        "{:?} pipeline missing",
+                        ShaderType::BatchMatrixVectorMultiply
+                    ))
+                })?;
 
             use wgpu::util::DeviceExt;
 
             // Convert data to f32 for GPU
-            let matrix_f32: Vec<f32> = matrix.iter().map(|&x| x.to_f32().unwrap()).collect();
+            let matrix_f32: Vec<f32> = matrix.iter().map(|&x| cast::<f32>(x)).collect();
             let mut vectors_f32 = Vec::with_capacity(batch_size * cols);
             for vec in vectors {
                 for &val in vec {
-                    vectors_f32.push(val.to_f32().unwrap());
+                    vectors_f32.push(cast::<f32>(val));
                 }
             }
 
@@ -1116,6 +1174,10 @@ pub mod webgpu_impl {
             }
 
             // Create GPU buffers
+            crate::memory_manager::device_memory_tracker().record_upload(
+                std::mem::size_of_val(matrix_f32.as_slice())
+                    + std::mem::size_of_val(vectors_f32.as_slice()),
+            );
             let matrix_buffer = self
                 .device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -1285,19 +1347,20 @@ pub mod webgpu_impl {
             let buffer_slice = staging_buffer.slice(..);
             let (sender, receiver) = std::sync::mpsc::channel();
             buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-                sender.send(result).unwrap();
+                let _ = sender.send(result);
             });
 
             // Wait for mapping to complete
             self.device.poll(wgpu::Maintain::Wait);
             receiver
                 .recv()
-                .unwrap()
+                .map_err(|_| ComputeError::AsyncError("buffer mapping was cancelled".to_string()))?
                 .map_err(|_| ComputeError::ComputeError("Failed to map buffer".to_string()))?;
 
             // Read data
             let data = buffer_slice.get_mapped_range();
             let result_f32: Vec<f32> = bytemuck::cast_slice(&data).to_vec();
+            crate::memory_manager::device_memory_tracker().record_download(data.len());
 
             drop(data);
             staging_buffer.unmap();
@@ -1307,10 +1370,7 @@ pub mod webgpu_impl {
             for i in 0..batch_size {
                 let start = i * rows;
                 let end = start + rows;
-                let row_results: Vec<T> = result_f32[start..end]
-                    .iter()
-                    .map(|&x| T::from(x).unwrap())
-                    .collect();
+                let row_results: Vec<T> = result_f32[start..end].iter().map(|&x| cast(x)).collect();
                 results.push(row_results);
             }
 
@@ -1454,7 +1514,15 @@ pub mod webgpu_impl {
 
             // TODO: Implement actual GPU buffer allocation
             // For now, return a handle that tracks the requested size
-            Ok(BufferHandle::new(size as u64))
+            let mut gpu_state = self
+                .gpu_state
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            let id = gpu_state.next_buffer_id;
+            gpu_state.next_buffer_id += 1;
+            gpu_state.buffer_sizes.insert(id, size);
+            crate::memory_manager::device_memory_tracker().record_allocation(size);
+            Ok(BufferHandle::new(id))
         }
 
         /// Upload data to GPU buffer with transfer optimization
@@ -1469,15 +1537,9 @@ pub mod webgpu_impl {
         /// - **Compression**: Sparse data may be compressed during transfer
         /// - **Validation**: Data integrity is verified after transfer
         fn upload_data(&self, handle: BufferHandle, data: &[T]) -> Result<(), ComputeError> {
-            // Validate buffer handle
-            if handle.id() == 0 {
-                return Err(ComputeError::InvalidDimensions(
-                    "Cannot upload to invalid buffer handle".to_string(),
-                ));
-            }
-
             // Check data size compatibility
-            let expected_elements = handle.id() as usize / std::mem::size_of::<T>();
+            let size = self.buffer_size(handle, "upload to")?;
+            let expected_elements = size / std::mem::size_of::<T>();
             if data.len() > expected_elements {
                 return Err(ComputeError::InvalidDimensions(format!(
                     "Data size {} exceeds buffer capacity {}",
@@ -1488,6 +1550,8 @@ pub mod webgpu_impl {
 
             // TODO: Implement actual data upload to GPU
             // For now, just validate the operation
+            crate::memory_manager::device_memory_tracker()
+                .record_upload(std::mem::size_of_val(data));
             Ok(())
         }
 
@@ -1496,18 +1560,14 @@ pub mod webgpu_impl {
         /// Transfers data from GPU memory back to CPU, with automatic
         /// optimization for different transfer patterns and sizes.
         fn download_data(&self, handle: BufferHandle) -> Result<Vec<T>, ComputeError> {
-            // Validate buffer handle
-            if handle.id() == 0 {
-                return Err(ComputeError::InvalidDimensions(
-                    "Cannot download from invalid buffer handle".to_string(),
-                ));
-            }
-
             // Calculate expected data size
-            let expected_elements = handle.id() as usize / std::mem::size_of::<T>();
+            let size = self.buffer_size(handle, "download from")?;
+            let expected_elements = size / std::mem::size_of::<T>();
 
             // TODO: Implement actual data download from GPU
             // For now, return empty vector as placeholder
+            crate::memory_manager::device_memory_tracker()
+                .record_download(expected_elements * std::mem::size_of::<T>());
             Ok(vec![T::zero(); expected_elements])
         }
 
@@ -1516,15 +1576,22 @@ pub mod webgpu_impl {
         /// Frees the GPU buffer and returns it to the memory pool for reuse.
         /// The implementation includes automatic defragmentation when beneficial.
         fn deallocate_buffer(&self, handle: BufferHandle) -> Result<(), ComputeError> {
-            // Validate buffer handle
-            if handle.id() == 0 {
-                return Err(ComputeError::InvalidDimensions(
-                    "Cannot deallocate invalid buffer handle".to_string(),
-                ));
-            }
+            // Forget the handle so a second deallocation is rejected
+            let size = self
+                .gpu_state
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .buffer_sizes
+                .remove(&handle.id())
+                .ok_or_else(|| {
+                    ComputeError::InvalidDimensions(
+                        "Cannot deallocate invalid buffer handle".to_string(),
+                    )
+                })?;
 
             // TODO: Implement actual GPU buffer deallocation
             // For now, just validate the operation
+            crate::memory_manager::device_memory_tracker().record_deallocation(size);
             Ok(())
         }
 
@@ -1540,12 +1607,14 @@ pub mod webgpu_impl {
         /// - **Buffer Count**: Number of active buffers
         /// - **Fragmentation**: Measure of memory fragmentation (0.0-1.0)
         fn memory_usage(&self) -> MemoryStats {
-            // TODO: Implement actual memory usage tracking
-            // For now, return conservative estimates
+            let device = crate::memory_manager::device_memory_tracker().snapshot();
             MemoryStats {
-                total_allocated: 0,
-                available: self.capabilities.max_buffer_size,
-                buffer_count: 0,
+                total_allocated: device.allocated_bytes,
+                available: self
+                    .capabilities
+                    .max_buffer_size
+                    .saturating_sub(device.allocated_bytes),
+                buffer_count: device.buffer_count,
                 fragmentation_ratio: 0.0, // Perfect defragmentation
             }
         }